@@ -96,6 +96,25 @@ fn main() {
     
     #[cfg(windows)]
     println!("cargo:rustc-cfg=feature=\"windows_sockets\"");
+
+    // Raw scans on Windows send and capture through Npcap; pnet's datalink backend links
+    // against Packet.lib from the Npcap SDK
+    println!("cargo:rerun-if-env-changed=NPCAP_SDK");
+    if target.contains("windows") {
+        match env::var("NPCAP_SDK") {
+            Ok(sdk) => {
+                let lib_dir = if target.starts_with("x86_64") {
+                    Path::new(&sdk).join("Lib").join("x64")
+                } else if target.starts_with("aarch64") {
+                    Path::new(&sdk).join("Lib").join("ARM64")
+                } else {
+                    Path::new(&sdk).join("Lib")
+                };
+                println!("cargo:rustc-link-search=native={}", lib_dir.display());
+            }
+            Err(_) => println!("cargo:warning=NPCAP_SDK is not set; linking Packet.lib may fail. Point it at the unpacked Npcap SDK."),
+        }
+    }
 }
 
 // Removed compile_wrapper function as it's not needed
@@ -0,0 +1,180 @@
+//! UDP amplification exposure.
+//!
+//! Services that answer a small UDP query with a much larger reply can be abused as DDoS
+//! reflectors: an attacker spoofs the victim's address and the service multiplies the
+//! traffic. For the classic reflectors the standard query is sent once and every reply
+//! datagram is counted:
+//! - NTP on 123: `monlist` (mode 7) and `readvar` (mode 6)
+//! - SSDP on 1900: `M-SEARCH` for `ssdp:all`
+//! - memcached on 11211: `stats` over UDP
+//!
+//! The amplification factor is reply bytes over query bytes (UDP payloads only). Replies
+//! stop being counted after `MAX_REPLY_BYTES`, so very large factors are lower bounds.
+
+use std::net::{IpAddr, SocketAddr};
+use std::time::Duration;
+
+use anyhow::Result;
+use log::{debug, warn};
+use tokio::time::{timeout, Instant};
+
+use crate::models::{AmplificationMeasurement, Confidence, Finding};
+
+/// Reply bytes counted per query before stopping
+const MAX_REPLY_BYTES: usize = 512 * 1024;
+
+/// Quiet gap after a reply datagram that ends a multi-datagram answer
+const REPLY_GAP: Duration = Duration::from_millis(750);
+
+/// Factor from which a reflector is worth an attacker's while
+const HIGH_FACTOR: f64 = 10.0;
+
+/// Factor from which a reply is reported at all
+const MEDIUM_FACTOR: f64 = 2.0;
+
+/// A standard query of a reflector protocol
+struct Query {
+    port: u16,
+    service: &'static str,
+    name: &'static str,
+    payload: fn() -> Vec<u8>,
+}
+
+/// NTP mode 7 MON_GETLIST_1: the last clients the server saw
+fn ntp_monlist() -> Vec<u8> {
+    let mut packet = vec![0x17, 0x00, 0x03, 0x2a];
+    packet.resize(48, 0);
+    packet
+}
+
+/// NTP mode 6 READVAR: the system variables
+fn ntp_readvar() -> Vec<u8> {
+    vec![0x16, 0x02, 0x00, 0x01, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00]
+}
+
+fn ssdp_msearch() -> Vec<u8> {
+    b"M-SEARCH * HTTP/1.1\r\nHOST: 239.255.255.250:1900\r\nMAN: \"ssdp:discover\"\r\nMX: 1\r\nST: ssdp:all\r\n\r\n".to_vec()
+}
+
+/// memcached UDP frame header (request 0, sequence 0, one datagram) and `stats`
+fn memcached_stats() -> Vec<u8> {
+    let mut packet = vec![0x00, 0x01, 0x00, 0x00, 0x00, 0x01, 0x00, 0x00];
+    packet.extend_from_slice(b"stats\r\n");
+    packet
+}
+
+const QUERIES: [Query; 4] = [
+    Query { port: 123, service: "ntp", name: "monlist", payload: ntp_monlist },
+    Query { port: 123, service: "ntp", name: "readvar", payload: ntp_readvar },
+    Query { port: 1900, service: "ssdp", name: "M-SEARCH ssdp:all", payload: ssdp_msearch },
+    Query { port: 11211, service: "memcached", name: "stats", payload: memcached_stats },
+];
+
+/// Send one query and count every reply datagram
+async fn measure(target: IpAddr, query: &Query, wait: Duration) -> Result<Option<AmplificationMeasurement>> {
+    let payload = (query.payload)();
+    let socket = crate::netbind::bind_udp(target).await?;
+    socket.connect(SocketAddr::new(target, query.port)).await?;
+    crate::scheduler::spend(payload.len() + crate::scheduler::UDP_HEADER_BYTES).await;
+    crate::audit::record(|| crate::audit::Probe::new("amplification", "udp", target, Some(query.port), payload.len() + crate::scheduler::UDP_HEADER_BYTES)
+        .detail(format!("{} {}", query.service, query.name)));
+    socket.send(&payload).await?;
+
+    let mut buf = vec![0u8; 65536];
+    let mut response_bytes = 0;
+    let mut datagrams = 0;
+    let deadline = Instant::now() + wait;
+    while response_bytes < MAX_REPLY_BYTES {
+        // Wait the full timeout for the first datagram, then only for the rest of a burst
+        let remaining = if datagrams == 0 {
+            deadline.saturating_duration_since(Instant::now())
+        } else {
+            REPLY_GAP
+        };
+        match timeout(remaining, socket.recv(&mut buf)).await {
+            Ok(Ok(len)) => {
+                response_bytes += len;
+                datagrams += 1;
+            }
+            Ok(Err(e)) if datagrams == 0 => {
+                debug!("[Amplification] {}:{} {}: {}", target, query.port, query.name, e);
+                return Ok(None);
+            }
+            _ => break,
+        }
+    }
+    if datagrams == 0 {
+        return Ok(None);
+    }
+    Ok(Some(AmplificationMeasurement {
+        query: format!("{} {}", query.service, query.name),
+        request_bytes: payload.len(),
+        response_bytes,
+        datagrams,
+        factor: response_bytes as f64 / payload.len() as f64,
+        capped: response_bytes >= MAX_REPLY_BYTES,
+    }))
+}
+
+/// Vulnerability for a measurement large enough to matter
+fn finding(service: &str, measurement: &AmplificationMeasurement) -> Option<Finding> {
+    let severity = if measurement.factor >= HIGH_FACTOR {
+        "High"
+    } else if measurement.factor >= MEDIUM_FACTOR {
+        "Medium"
+    } else {
+        return None;
+    };
+    Some(Finding {
+        id: format!("UDP-AMPLIFICATION-{}", service.to_ascii_uppercase()),
+        title: format!("{} UDP amplification", service.to_ascii_uppercase()),
+        description: format!("{} answers a {}-byte {} query with {} bytes in {} datagrams ({}{:.1}x amplification); usable as a DDoS reflector",
+                             service, measurement.request_bytes, measurement.query, measurement.response_bytes, measurement.datagrams,
+                             if measurement.capped { "at least " } else { "" }, measurement.factor),
+        severity: severity.to_string(),
+        confidence: Confidence::Confirmed,
+        evidence: vec![format!("{} request bytes, {} response bytes", measurement.request_bytes, measurement.response_bytes)],
+        references: vec!["https://www.cisa.gov/news-events/alerts/2014/01/17/udp-based-amplification-attacks".to_string()],
+        ..Default::default()
+    })
+}
+
+/// Measure the amplification of every reflector service in scope
+///
+/// # Arguments
+/// * `target` - Host to check
+/// * `udp_ports` - UDP ports in scope; only reflector ports among them are queried
+/// * `wait` - Time to wait for the first reply datagram
+///
+/// # Returns
+/// * `Vec<(u16, &str, Vec<AmplificationMeasurement>, Vec<Finding>)>` - Per answering port:
+///   service name, measurements and findings
+pub async fn check_amplification(target: IpAddr, udp_ports: &[u16], wait: Duration) -> Vec<(u16, &'static str, Vec<AmplificationMeasurement>, Vec<Finding>)> {
+    let mut checked: Vec<(u16, &'static str, Vec<AmplificationMeasurement>, Vec<Finding>)> = Vec::new();
+    for query in QUERIES.iter().filter(|query| udp_ports.contains(&query.port)) {
+        let measurement = match measure(target, query, wait).await {
+            Ok(Some(measurement)) => measurement,
+            Ok(None) => continue,
+            Err(e) => {
+                debug!("[Amplification] {} query to {}:{} failed: {}", query.name, target, query.port, e);
+                continue;
+            }
+        };
+        let vuln = finding(query.service, &measurement);
+        if let Some(vuln) = &vuln {
+            warn!("[Amplification] {}:{} {}", target, query.port, vuln.description);
+        }
+        match checked.iter_mut().find(|(port, ..)| *port == query.port) {
+            Some((_, _, measurements, vulns)) => {
+                let largest = measurements.iter().all(|m| m.factor <= measurement.factor);
+                measurements.push(measurement);
+                // One finding per service, for its largest factor
+                if let (Some(vuln), true) = (vuln, largest) {
+                    *vulns = vec![vuln];
+                }
+            }
+            None => checked.push((query.port, query.service, vec![measurement], vuln.into_iter().collect())),
+        }
+    }
+    checked
+}
@@ -0,0 +1,263 @@
+//! API specification discovery.
+//!
+//! An OpenAPI or Swagger document left on a web port lists every route, parameter and
+//! authentication scheme of the API behind it, and a GraphQL endpoint with introspection
+//! enabled hands out its whole schema on request. Each web port is asked for the usual
+//! spec locations until one answers, then sent one introspection query per GraphQL path.
+//! What is found is summarised under `api_surface` in the port's service details: the
+//! spec version, title, operation count with a sample of routes and the security schemes,
+//! or the GraphQL queries, mutations and type count.
+
+use std::collections::HashMap;
+use std::net::{IpAddr, SocketAddr};
+use std::time::Duration;
+
+use log::{debug, info};
+use serde_json::{json, Value};
+
+use crate::models::{Confidence, Finding, PortResult};
+
+/// Where API specifications are usually published, tried in order until one is found
+const SPEC_PATHS: [&str; 6] = ["/swagger.json", "/openapi.json", "/openapi.yaml", "/v3/api-docs", "/v2/api-docs", "/swagger/v1/swagger.json"];
+
+/// Where GraphQL endpoints usually live
+const GRAPHQL_PATHS: [&str; 2] = ["/graphql", "/api/graphql"];
+
+/// Introspection query asking only for what the summary needs
+const INTROSPECTION_QUERY: &str = r#"{"query":"query{__schema{queryType{fields{name}}mutationType{fields{name}}subscriptionType{name}types{name}}}"}"#;
+
+/// Most web ports checked per host
+const MAX_PORTS: usize = 8;
+
+/// Most route or field names kept in a summary
+const MAX_SAMPLE: usize = 25;
+
+/// HTTP methods an OpenAPI path item can hold operations for
+const OPERATION_METHODS: [&str; 8] = ["get", "put", "post", "delete", "patch", "head", "options", "trace"];
+
+/// API surface found on one web port
+#[derive(Debug)]
+pub struct ApiCheck {
+    pub port: u16,
+    pub details: Value,
+    pub vulns: Vec<Finding>,
+}
+
+/// Parse a specification body, JSON or YAML
+fn parse_spec(body: &str) -> Option<Value> {
+    let spec: Value = serde_json::from_str(body).ok()
+        .or_else(|| serde_yaml::from_str(body).ok())?;
+    spec.get("openapi").or_else(|| spec.get("swagger"))?.as_str()?;
+    Some(spec)
+}
+
+/// Version of a specification too large to read whole, from its opening lines
+fn truncated_spec_version(body: &str) -> Option<String> {
+    let head: String = body.chars().take(512).collect();
+    ["\"openapi\"", "\"swagger\"", "openapi:", "swagger:"].iter().find_map(|key| {
+        let after = &head[head.find(key)? + key.len()..];
+        let version: String = after.trim_start_matches([':', ' ', '"', '\''])
+            .chars()
+            .take_while(|c| c.is_ascii_digit() || *c == '.')
+            .collect();
+        (!version.is_empty()).then_some(version)
+    })
+}
+
+/// Summary of an OpenAPI or Swagger document
+fn summarize_spec(path: &str, spec: &Value) -> Value {
+    let version = spec.get("openapi").or_else(|| spec.get("swagger")).and_then(Value::as_str).unwrap_or("");
+    let mut operations = Vec::new();
+    if let Some(paths) = spec.get("paths").and_then(Value::as_object) {
+        for (route, item) in paths {
+            for method in OPERATION_METHODS {
+                if item.get(method).is_some() {
+                    operations.push(format!("{} {}", method.to_ascii_uppercase(), route));
+                }
+            }
+        }
+    }
+    // OpenAPI 3 keeps schemes under components, Swagger 2 at the top level
+    let security_schemes: Vec<&String> = spec.pointer("/components/securitySchemes")
+        .or_else(|| spec.get("securityDefinitions"))
+        .and_then(Value::as_object)
+        .map(|schemes| schemes.keys().collect())
+        .unwrap_or_default();
+    let mut servers: Vec<String> = spec.get("servers").and_then(Value::as_array).into_iter().flatten()
+        .filter_map(|server| server.get("url").and_then(Value::as_str).map(str::to_string))
+        .collect();
+    if let Some(host) = spec.get("host").and_then(Value::as_str) {
+        servers.push(format!("{}{}", host, spec.get("basePath").and_then(Value::as_str).unwrap_or("")));
+    }
+
+    json!({
+        "kind": if version.starts_with('2') { "swagger" } else { "openapi" },
+        "path": path,
+        "spec_version": version,
+        "title": spec.pointer("/info/title").and_then(Value::as_str),
+        "api_version": spec.pointer("/info/version").and_then(Value::as_str),
+        "paths": spec.get("paths").and_then(Value::as_object).map_or(0, |paths| paths.len()),
+        "operations": operations.len(),
+        "sample_operations": operations.iter().take(MAX_SAMPLE).collect::<Vec<_>>(),
+        "security_schemes": security_schemes,
+        "servers": servers,
+    })
+}
+
+/// Field names of a GraphQL root type from an introspection result
+fn root_fields(schema: &Value, root: &str) -> Vec<String> {
+    schema.get(root)
+        .and_then(|root| root.get("fields"))
+        .and_then(Value::as_array)
+        .into_iter()
+        .flatten()
+        .filter_map(|field| field.get("name").and_then(Value::as_str).map(str::to_string))
+        .collect()
+}
+
+/// Summary of a GraphQL endpoint, `None` when the reply is not GraphQL
+fn summarize_graphql(path: &str, body: &str) -> Option<Value> {
+    let reply: Value = serde_json::from_str(body.trim()).ok()?;
+    let Some(schema) = reply.pointer("/data/__schema").filter(|schema| !schema.is_null()) else {
+        // An endpoint that refuses introspection still answers with GraphQL errors
+        let errors = reply.get("errors")?.as_array()?;
+        let message = errors.first().and_then(|e| e.get("message")).and_then(Value::as_str).unwrap_or("");
+        return Some(json!({ "kind": "graphql", "path": path, "introspection": false, "error": message }));
+    };
+    let queries = root_fields(schema, "queryType");
+    let mutations = root_fields(schema, "mutationType");
+    let types = schema.get("types").and_then(Value::as_array).into_iter().flatten()
+        .filter_map(|t| t.get("name").and_then(Value::as_str))
+        .filter(|name| !name.starts_with("__"))
+        .count();
+    Some(json!({
+        "kind": "graphql",
+        "path": path,
+        "introspection": true,
+        "queries": queries.len(),
+        "mutations": mutations.len(),
+        "subscriptions": schema.pointer("/subscriptionType/name").is_some_and(|name| !name.is_null()),
+        "types": types,
+        "sample_queries": queries.iter().take(MAX_SAMPLE).collect::<Vec<_>>(),
+        "sample_mutations": mutations.iter().take(MAX_SAMPLE).collect::<Vec<_>>(),
+    }))
+}
+
+fn spec_finding(port: u16, summary: &Value) -> Finding {
+    let path = summary["path"].as_str().unwrap_or("");
+    let unauthenticated = summary["security_schemes"].as_array().is_some_and(|schemes| schemes.is_empty());
+    Finding {
+        id: "API-SPEC-EXPOSED".to_string(),
+        title: "API specification exposed".to_string(),
+        description: format!("{} on port {} documents {} operations{}", path, port, summary["operations"],
+                             if unauthenticated { " and declares no authentication scheme" } else { "" }),
+        severity: "Low".to_string(),
+        confidence: Confidence::Confirmed,
+        evidence: summary["sample_operations"].as_array().into_iter().flatten()
+            .filter_map(|op| op.as_str().map(str::to_string))
+            .take(10)
+            .collect(),
+        references: vec!["https://owasp.org/API-Security/editions/2023/en/0xa9-improper-inventory-management/".to_string()],
+        remediation: Some("Publish API documentation only where intended, and check every listed operation enforces authentication and authorization.".to_string()),
+        ..Default::default()
+    }
+}
+
+fn introspection_finding(port: u16, summary: &Value) -> Finding {
+    let path = summary["path"].as_str().unwrap_or("");
+    Finding {
+        id: "GRAPHQL-INTROSPECTION".to_string(),
+        title: "GraphQL introspection enabled".to_string(),
+        description: format!("{} on port {} returns its full schema to anyone: {} queries, {} mutations and {} types",
+                             path, port, summary["queries"], summary["mutations"], summary["types"]),
+        severity: "Medium".to_string(),
+        confidence: Confidence::Confirmed,
+        evidence: summary["sample_mutations"].as_array().into_iter()
+            .chain(summary["sample_queries"].as_array())
+            .flatten()
+            .filter_map(|field| field.as_str().map(str::to_string))
+            .take(10)
+            .collect(),
+        references: vec!["https://cheatsheetseries.owasp.org/cheatsheets/GraphQL_Cheat_Sheet.html".to_string()],
+        remediation: Some("Disable introspection in production or restrict it to authenticated developers.".to_string()),
+        ..Default::default()
+    }
+}
+
+/// Look for API specifications and GraphQL introspection on each web port of a host
+///
+/// # Arguments
+/// * `target` - Host to check
+/// * `hostname` - Name to send in `Host` and SNI; the address is used when not given
+/// * `results` - Port results after detection
+/// * `wait` - Connect and reply timeout
+///
+/// # Returns
+/// * `Vec<ApiCheck>` - Entries for ports where an API surface was found
+pub async fn discover_apis(target: IpAddr, hostname: Option<&str>, results: &HashMap<u16, PortResult>, wait: Duration) -> Vec<ApiCheck> {
+    let host = hostname.map(str::to_string).unwrap_or_else(|| target.to_string());
+
+    let mut checks = Vec::new();
+    for (port, tls) in crate::web_hygiene::web_ports(results).into_iter().take(MAX_PORTS) {
+        let addr = SocketAddr::new(target, port);
+        let mut surface = Vec::new();
+        let mut vulns = Vec::new();
+
+        for path in SPEC_PATHS {
+            if crate::scheduler::winding_down() {
+                break;
+            }
+            let response = match crate::web_hygiene::get(addr, tls, &host, path, "api-discovery", wait).await {
+                Ok(response) => response,
+                Err(e) => {
+                    debug!("[API] GET {} on {} failed: {}", path, addr, e);
+                    continue;
+                }
+            };
+            let Some((200, body)) = crate::web_hygiene::split_response(&response) else { continue };
+            let summary = match parse_spec(&body) {
+                Some(spec) => summarize_spec(path, &spec),
+                // Cut off at the response limit: note the spec without its contents
+                None => match truncated_spec_version(&body) {
+                    Some(version) => json!({ "kind": "openapi", "path": path, "spec_version": version, "truncated": true }),
+                    None => continue,
+                },
+            };
+            info!("[API] {} serves an API specification at {}", addr, path);
+            if summary.get("truncated").is_none() {
+                vulns.push(spec_finding(port, &summary));
+            }
+            surface.push(summary);
+            break;
+        }
+
+        for path in GRAPHQL_PATHS {
+            if crate::scheduler::winding_down() {
+                break;
+            }
+            let body = Some(("application/json", INTROSPECTION_QUERY.as_bytes()));
+            let response = match crate::web_hygiene::request(addr, tls, &host, path, body, "api-discovery", wait).await {
+                Ok(response) => response,
+                Err(e) => {
+                    debug!("[API] Introspection on {}{} failed: {}", addr, path, e);
+                    continue;
+                }
+            };
+            let Some((_, body)) = crate::web_hygiene::split_response(&response) else { continue };
+            if let Some(summary) = summarize_graphql(path, &body) {
+                info!("[API] {} has a GraphQL endpoint at {} (introspection {})", addr, path,
+                      if summary["introspection"] == true { "enabled" } else { "disabled" });
+                if summary["introspection"] == true {
+                    vulns.push(introspection_finding(port, &summary));
+                }
+                surface.push(summary);
+                break;
+            }
+        }
+
+        if !surface.is_empty() {
+            checks.push(ApiCheck { port, details: json!({ "api_surface": surface }), vulns });
+        }
+    }
+    checks
+}
@@ -0,0 +1,178 @@
+//! Asset metadata from `--asset-file`.
+//!
+//! The asset file maps IPs and CIDRs to what the operator knows about them: owner,
+//! environment, criticality and free-form tags. Every host result gets the tags of the
+//! entries covering its address, so findings arrive already in context. Entries are
+//! applied from the widest network to the narrowest, so a host entry overrides the
+//! fields of its subnet and adds to its tags.
+//!
+//! With an asset file loaded, each host also gets a risk assessment: its worst finding
+//! and exposure, weighted by criticality. Hosts no entry covers count as medium.
+//!
+//! ```yaml
+//! assets:
+//!   - match: 10.0.0.0/24
+//!     owner: infrastructure
+//!     environment: production
+//!     criticality: high
+//!     tags: [dmz]
+//!   - match: 10.0.0.5
+//!     owner: payments
+//!     criticality: critical
+//!     tags: [pci]
+//! ```
+
+use std::net::IpAddr;
+use std::path::Path;
+use std::sync::Arc;
+
+use anyhow::{anyhow, Context, Result};
+use ipnet::IpNet;
+use lazy_static::lazy_static;
+use parking_lot::RwLock;
+use serde::Deserialize;
+
+use crate::models::{AssetInfo, Criticality, ScanResults};
+
+/// One entry as written in the asset file
+#[derive(Debug, Deserialize)]
+struct AssetSpec {
+    /// IP address or CIDR the entry covers
+    #[serde(rename = "match", alias = "cidr", alias = "ip")]
+    network: String,
+    owner: Option<String>,
+    environment: Option<String>,
+    criticality: Option<Criticality>,
+    #[serde(default)]
+    tags: Vec<String>,
+}
+
+/// Top level of an asset file
+#[derive(Debug, Deserialize)]
+struct AssetFile {
+    #[serde(default)]
+    assets: Vec<AssetSpec>,
+}
+
+/// Asset entries, narrowest network last
+#[derive(Debug, Default)]
+pub struct AssetMap {
+    entries: Vec<(IpNet, AssetInfo)>,
+}
+
+impl AssetMap {
+    /// Load an asset file, YAML (`.yaml`/`.yml`) or TOML (`.toml`) by extension
+    pub fn load_file(path: &Path) -> Result<Self> {
+        let text = std::fs::read_to_string(path)
+            .with_context(|| format!("Failed to read asset file {}", path.display()))?;
+        let file: AssetFile = match path.extension().and_then(|e| e.to_str()).map(str::to_ascii_lowercase).as_deref() {
+            Some("toml") => toml::from_str(&text).with_context(|| format!("Invalid asset file {}", path.display()))?,
+            Some("yaml") | Some("yml") => serde_yaml::from_str(&text).with_context(|| format!("Invalid asset file {}", path.display()))?,
+            _ => return Err(anyhow!("Asset file {} must end in .yaml, .yml or .toml", path.display())),
+        };
+
+        let mut entries = file.assets.into_iter()
+            .map(|spec| {
+                let network = spec.network.trim();
+                let net = network.parse::<IpNet>()
+                    .or_else(|_| network.parse::<IpAddr>().map(IpNet::from))
+                    .map_err(|_| anyhow!("'{}' is not an IP address or CIDR", network))
+                    .with_context(|| format!("Invalid asset file {}", path.display()))?;
+                let info = AssetInfo {
+                    owner: spec.owner,
+                    environment: spec.environment,
+                    criticality: spec.criticality,
+                    tags: spec.tags,
+                };
+                Ok((net.trunc(), info))
+            })
+            .collect::<Result<Vec<_>>>()?;
+        // Stable, so entries for the same network keep their file order
+        entries.sort_by_key(|(net, _)| net.prefix_len());
+        Ok(AssetMap { entries })
+    }
+
+    /// Number of entries
+    pub fn entry_count(&self) -> usize {
+        self.entries.len()
+    }
+
+    /// Merged metadata of every entry covering `ip`, if any does
+    pub fn lookup(&self, ip: IpAddr) -> Option<AssetInfo> {
+        let mut merged: Option<AssetInfo> = None;
+        for (_, info) in self.entries.iter().filter(|(net, _)| net.contains(&ip)) {
+            let asset = merged.get_or_insert_with(AssetInfo::default);
+            if info.owner.is_some() {
+                asset.owner = info.owner.clone();
+            }
+            if info.environment.is_some() {
+                asset.environment = info.environment.clone();
+            }
+            if info.criticality.is_some() {
+                asset.criticality = info.criticality;
+            }
+            for tag in &info.tags {
+                if !asset.tags.contains(tag) {
+                    asset.tags.push(tag.clone());
+                }
+            }
+        }
+        merged
+    }
+}
+
+lazy_static! {
+    /// Active asset map; `None` unless `--asset-file` was given
+    static ref ASSETS: RwLock<Option<Arc<AssetMap>>> = RwLock::new(None);
+}
+
+/// Use (or stop using, with `None`) an asset map
+pub fn set_assets(assets: Option<AssetMap>) {
+    *ASSETS.write() = assets.map(Arc::new);
+}
+
+/// Attach asset metadata and a risk assessment to a finished host result
+///
+/// Does nothing unless an asset file is loaded.
+pub fn annotate(results: &mut ScanResults) {
+    let Some(assets) = ASSETS.read().clone() else { return };
+    let Ok(ip) = results.target_ip.parse::<IpAddr>() else { return };
+    results.asset = assets.lookup(ip);
+    let criticality = results.asset.as_ref().and_then(|a| a.criticality).unwrap_or(Criticality::Medium);
+    results.risk_assessment = Some(risk_assessment(results, criticality));
+}
+
+/// Weight of the worst finding, on a 0-10 scale
+fn severity_score(severity: &str) -> f64 {
+    match severity.to_ascii_lowercase().as_str() {
+        "critical" => 10.0,
+        "high" => 7.5,
+        "medium" | "moderate" => 5.0,
+        "low" => 2.5,
+        _ => 0.0,
+    }
+}
+
+/// Score a host from its worst finding and its number of open ports, weighted by how
+/// much the asset matters
+///
+/// # Returns
+/// * `String` - Level and score with what it was based on, e.g.
+///   `High (7.0): worst finding Medium, 3 open ports, criticality high`
+fn risk_assessment(results: &ScanResults, criticality: Criticality) -> String {
+    let worst = results.results.values()
+        .flat_map(|r| r.vulns.iter())
+        .max_by(|a, b| severity_score(&a.severity).total_cmp(&severity_score(&b.severity)));
+    // Each open port adds a little exposure, up to two points
+    let exposure = (results.open_ports.len() as f64 * 0.2).min(2.0);
+    let score = ((worst.map_or(0.0, |v| severity_score(&v.severity)) + exposure) * criticality.weight()).min(10.0);
+    let level = match score {
+        s if s >= 9.0 => "Critical",
+        s if s >= 7.0 => "High",
+        s if s >= 4.0 => "Medium",
+        s if s > 0.0 => "Low",
+        _ => "None",
+    };
+    format!("{} ({:.1}): worst finding {}, {} open ports, criticality {}",
+            level, score, worst.map_or("none", |v| v.severity.as_str()), results.open_ports.len(), criticality)
+}
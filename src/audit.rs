@@ -0,0 +1,278 @@
+//! Probe audit trail.
+//!
+//! With `--audit-log <path>` every probe the scanner puts on the wire is recorded as one
+//! JSON object per line: raw packets and fragments, connection attempts, payloads written
+//! on open connections, UDP and ICMP datagrams, tunnel traffic and cover traffic. Each
+//! record carries the time, source and destination, the technique and scan type it was
+//! sent for, its estimated size and, for crafted packets, the evasion parameters it was
+//! built with. The result is an attestation of exactly what was sent to a client's
+//! network, to hand over after the engagement.
+//!
+//! The trail follows the log settings: with `--encrypt-logs` it is an encrypted stream
+//! that `quantum_scanner logs decrypt` reads back. In memory-only mode nothing is written
+//! during the scan; records are kept in memory and written once, encrypted, when the run
+//! ends. Records are never redacted, since an attestation with masked targets proves
+//! nothing.
+
+use std::fs::File;
+use std::future::Future;
+use std::io::Write;
+use std::net::IpAddr;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicBool, Ordering};
+
+use anyhow::{Context, Result};
+use chrono::{SecondsFormat, Utc};
+use lazy_static::lazy_static;
+use log::{info, warn};
+use parking_lot::Mutex;
+use serde::Serialize;
+
+use crate::encryption::EncryptedLogWriter;
+use crate::evasion::ProbeShape;
+use crate::models::ScanType;
+use crate::utils::SecureString;
+
+/// Where audit records go
+enum AuditSink {
+    Plain(File),
+    Encrypted(EncryptedLogWriter<File>),
+    /// Memory-only mode: records wait here and are written encrypted by `finish`
+    Memory { path: PathBuf, passphrase: SecureString, records: Vec<SecureString> },
+}
+
+lazy_static! {
+    /// Audit trail for this run, if `--audit-log` was given
+    static ref SINK: Mutex<Option<AuditSink>> = Mutex::new(None);
+}
+
+/// Fast check on every send path, so a run without an audit log pays nothing
+static ENABLED: AtomicBool = AtomicBool::new(false);
+
+tokio::task_local! {
+    /// Scan type of the per-port task a probe is sent from
+    static SCAN_TYPE: ScanType;
+}
+
+/// One probe about to be sent
+pub struct Probe<'a> {
+    technique: &'a str,
+    proto: &'static str,
+    dst: String,
+    dport: Option<u16>,
+    src: Option<IpAddr>,
+    sport: Option<u16>,
+    bytes: usize,
+    detail: Option<String>,
+    shape: Option<&'a ProbeShape>,
+}
+
+impl<'a> Probe<'a> {
+    /// Probe of `bytes` (headers included) sent with `technique` to `dst`
+    pub fn new(technique: &'a str, proto: &'static str, dst: impl ToString, dport: Option<u16>, bytes: usize) -> Self {
+        Probe { technique, proto, dst: dst.to_string(), dport, src: None, sport: None, bytes, detail: None, shape: None }
+    }
+
+    /// Source address and port, where the sender chooses them
+    pub fn source(mut self, src: IpAddr, sport: Option<u16>) -> Self {
+        self.src = Some(src);
+        self.sport = sport;
+        self
+    }
+
+    /// Free-form detail such as TCP flags or an ICMP type
+    pub fn detail(mut self, detail: impl Into<String>) -> Self {
+        self.detail = Some(detail.into());
+        self
+    }
+
+    /// Header fields the evasion pipeline gave a crafted packet
+    pub fn shape(mut self, shape: &'a ProbeShape) -> Self {
+        self.shape = Some(shape);
+        self
+    }
+}
+
+/// Evasion parameters of a crafted packet
+#[derive(Serialize)]
+struct EvasionRecord {
+    pipeline: String,
+    ttl: u8,
+    window: u16,
+    tcp_options: usize,
+    dont_fragment: bool,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    fragment_size: Option<usize>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pad_to: Option<usize>,
+    bad_checksum: bool,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    extra_flags: Option<String>,
+    delay_ms: u64,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    spoofed_mac: Option<String>,
+}
+
+/// One line of the audit trail
+#[derive(Serialize)]
+struct AuditRecord<'a> {
+    ts: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    run_id: Option<String>,
+    technique: &'a str,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    scan_type: Option<ScanType>,
+    proto: &'static str,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    src: Option<IpAddr>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    sport: Option<u16>,
+    dst: &'a str,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    dport: Option<u16>,
+    bytes: usize,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    detail: Option<&'a str>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    evasion: Option<EvasionRecord>,
+}
+
+/// Start recording probes to `path`
+///
+/// # Arguments
+/// * `path` - Audit trail file, created or truncated (in memory-only mode, when the run ends)
+/// * `encrypt` - Whether to write an encrypted stream
+/// * `passphrase` - Passphrase spec for encryption; one is generated and printed when omitted
+/// * `memory_only` - Keep records in memory until `finish`, always encrypted
+///
+/// # Opsec Considerations
+/// The trail names every target and port probed. Unencrypted it is as sensitive as the
+/// results themselves, and it is not removed by `--secure-delete` or `--burn-after-run`.
+pub fn start(path: &Path, encrypt: bool, passphrase: Option<&str>, memory_only: bool) -> Result<()> {
+    let sink = if memory_only {
+        if !encrypt {
+            anyhow::bail!("--audit-log in memory-only mode is written encrypted at the end of the run; enable --encrypt-logs");
+        }
+        AuditSink::Memory {
+            path: path.to_path_buf(),
+            passphrase: crate::logging::log_passphrase(path, passphrase)?,
+            records: Vec::new(),
+        }
+    } else {
+        let file = File::create(path).with_context(|| format!("Failed to create audit log {}", path.display()))?;
+        if encrypt {
+            let passphrase = crate::logging::log_passphrase(path, passphrase)?;
+            AuditSink::Encrypted(EncryptedLogWriter::new(file, &passphrase)?)
+        } else {
+            AuditSink::Plain(file)
+        }
+    };
+    *SINK.lock() = Some(sink);
+    ENABLED.store(true, Ordering::Relaxed);
+    info!("Recording every probe to {}{}{}", path.display(),
+          if encrypt { " (encrypted)" } else { "" },
+          if memory_only { ", written when the run ends" } else { "" });
+    Ok(())
+}
+
+/// Run `task` with probes it sends attributed to `scan_type`
+pub async fn with_scan_type<F: Future>(scan_type: ScanType, task: F) -> F::Output {
+    SCAN_TYPE.scope(scan_type, task).await
+}
+
+/// Record a probe; `probe` is only built when an audit trail is being written
+pub fn record<'a>(probe: impl FnOnce() -> Probe<'a>) {
+    if !ENABLED.load(Ordering::Relaxed) {
+        return;
+    }
+    let probe = probe();
+    let evasion = probe.shape.map(|shape| EvasionRecord {
+        pipeline: crate::evasion::pipeline().to_string(),
+        ttl: shape.ttl,
+        window: shape.window,
+        tcp_options: shape.tcp_options.len(),
+        dont_fragment: shape.dont_fragment,
+        fragment_size: shape.fragment_size,
+        pad_to: shape.pad_to,
+        bad_checksum: shape.bad_checksum,
+        extra_flags: (shape.extra_flags != 0).then(|| tcp_flags(shape.extra_flags)),
+        delay_ms: shape.delay.as_millis() as u64,
+        spoofed_mac: crate::mac_vendor::spoofed_mac().map(|mac| mac.to_string()),
+    });
+    let record = AuditRecord {
+        ts: Utc::now().to_rfc3339_opts(SecondsFormat::Micros, true),
+        run_id: crate::metadata::current().map(|meta| meta.run_id),
+        technique: probe.technique,
+        scan_type: SCAN_TYPE.try_with(|scan_type| *scan_type).ok(),
+        proto: probe.proto,
+        src: probe.src,
+        sport: probe.sport,
+        dst: &probe.dst,
+        dport: probe.dport,
+        bytes: probe.bytes,
+        detail: probe.detail.as_deref(),
+        evasion,
+    };
+    let mut line = match serde_json::to_string(&record) {
+        Ok(line) => line,
+        Err(e) => {
+            warn!("[Audit] Failed to serialise probe record: {}", e);
+            return;
+        }
+    };
+    line.push('\n');
+
+    // A failing audit file must not take the scan down with it, but must not go unnoticed
+    let written = match SINK.lock().as_mut() {
+        Some(AuditSink::Plain(file)) => file.write_all(line.as_bytes()),
+        Some(AuditSink::Encrypted(writer)) => writer.write_all(line.as_bytes()),
+        Some(AuditSink::Memory { records, .. }) => {
+            records.push(SecureString::new(line));
+            Ok(())
+        }
+        None => Ok(()),
+    };
+    if let Err(e) = written {
+        warn!("[Audit] Failed to record probe to {}: {}", record.dst, e);
+    }
+}
+
+/// Close the audit trail, writing it out in memory-only mode
+///
+/// Runs once at the end of the run; probes sent afterwards are not recorded.
+pub fn finish() {
+    ENABLED.store(false, Ordering::Relaxed);
+    let sink = SINK.lock().take();
+    let outcome = match sink {
+        Some(AuditSink::Plain(mut file)) => file.flush().map_err(Into::into),
+        Some(AuditSink::Encrypted(mut writer)) => writer.finish().map_err(Into::into),
+        Some(AuditSink::Memory { path, passphrase, records }) => write_encrypted(&path, &passphrase, &records),
+        None => return,
+    };
+    match outcome {
+        Ok(()) => info!("Audit trail complete"),
+        Err(e) => warn!("Failed to finalise audit trail: {}", e),
+    }
+}
+
+/// Write buffered records as an encrypted stream; the records are wiped as they are dropped
+fn write_encrypted(path: &Path, passphrase: &str, records: &[SecureString]) -> Result<()> {
+    let file = File::create(path).with_context(|| format!("Failed to create audit log {}", path.display()))?;
+    let mut writer = EncryptedLogWriter::new(file, passphrase)?;
+    for record in records {
+        writer.write_all(record.as_bytes())?;
+    }
+    writer.finish()?;
+    info!("Wrote {} probe records to {}", records.len(), path.display());
+    Ok(())
+}
+
+/// TCP flags as `SYN|ACK`
+pub fn tcp_flags(flags: u8) -> String {
+    const NAMES: [(u8, &str); 8] = [
+        (0x01, "FIN"), (0x02, "SYN"), (0x04, "RST"), (0x08, "PSH"),
+        (0x10, "ACK"), (0x20, "URG"), (0x40, "ECE"), (0x80, "CWR"),
+    ];
+    let names: Vec<&str> = NAMES.iter().filter(|(bit, _)| flags & bit != 0).map(|(_, name)| *name).collect();
+    if names.is_empty() { "NONE".to_string() } else { names.join("|") }
+}
@@ -1,9 +1,176 @@
 use log::{debug, trace};
+use std::collections::{HashMap, HashSet};
 use std::net::{IpAddr, SocketAddr};
-use tokio::io::{AsyncReadExt, AsyncWriteExt};
-use tokio::net::TcpStream;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::io::AsyncReadExt;
+use tokio::sync::{mpsc, Semaphore};
+use tokio::task::{JoinHandle, JoinSet};
 use tokio::time::timeout;
-use anyhow::Result;
+use anyhow::{anyhow, Result};
+
+use crate::service_fingerprints::ProbeProtocol;
+
+/// Ports whose services send a greeting before the client speaks (FTP, SSH, Telnet,
+/// SMTP, POP3, IMAP, MySQL, VNC)
+const SERVER_FIRST_PORTS: [u16; 10] = [21, 22, 23, 25, 110, 143, 587, 2222, 3306, 5900];
+
+/// Ports that get the HTTP request first instead of waiting for a greeting
+const WEB_PORTS: [u16; 16] = [80, 81, 443, 591, 3000, 5000, 8000, 8008, 8080, 8081, 8088, 8443, 8888, 9000, 9090, 9443];
+
+/// Default for the most banner grabs in flight per host
+pub const DEFAULT_BANNER_CONCURRENCY: usize = 32;
+
+/// Longest reply a banner grab reads, and so the most `--capture-responses` can keep
+pub const MAX_CAPTURE_BYTES: usize = 4096;
+
+/// Only read what services send on their own, never send a request (`--safe`)
+static READ_ONLY: AtomicBool = AtomicBool::new(false);
+
+/// Make banner grabs read-only: no HTTP requests, plugin or custom probes
+pub fn set_read_only(enabled: bool) {
+    READ_ONLY.store(enabled, Ordering::Relaxed);
+}
+
+/// How long an unknown service gets to send a greeting before it is sent an HTTP request
+const GREETING_WAIT: Duration = Duration::from_secs(2);
+
+/// Path a banner grab takes to the target
+#[derive(Clone)]
+pub enum BannerRoute {
+    Direct,
+    Icmp(IpAddr, crate::tunnel::CovertChannelConfig),
+    Http(crate::tunnel::HttpTunnelConfig),
+    Quic(crate::tunnel::QuicTunnelConfig),
+}
+
+/// Grab a banner from `target_ip:port` over `route`
+pub async fn grab(route: &BannerRoute, target_ip: IpAddr, port: u16, timeout_duration: Duration) -> Result<Vec<u8>> {
+    match route {
+        BannerRoute::Direct => {
+            // A port with only a custom UDP probe is a UDP service; ask it over UDP when
+            // nothing listens on TCP
+            let banner = grab_banner_raw(target_ip, port, timeout_duration).await;
+            let udp_probe = crate::service_fingerprints::database()
+                .custom_probe(port, ProbeProtocol::Udp)
+                .map(|probe| probe.payload.clone());
+            match (banner, udp_probe) {
+                (Err(_), Some(payload)) => grab_udp_banner(target_ip, port, &payload, timeout_duration).await,
+                (banner, _) => banner,
+            }
+        }
+        BannerRoute::Icmp(relay, config) => grab_banner_via_icmp(*relay, config.clone(), target_ip, port, timeout_duration).await,
+        BannerRoute::Http(config) => grab_banner_via_http(config, target_ip, port, timeout_duration).await,
+        BannerRoute::Quic(config) => grab_banner_via_quic(config, target_ip, port, timeout_duration).await,
+    }
+}
+
+/// Send a custom UDP probe and return the reply
+pub async fn grab_udp_banner(target_ip: IpAddr, port: u16, payload: &[u8], timeout_duration: Duration) -> Result<Vec<u8>> {
+    let socket = crate::netbind::bind_udp(target_ip).await?;
+    socket.connect(SocketAddr::new(target_ip, port)).await?;
+    crate::scheduler::spend(payload.len() + crate::scheduler::UDP_HEADER_BYTES).await;
+    crate::audit::record(|| crate::audit::Probe::new("udp-banner", "udp", target_ip, Some(port), payload.len() + crate::scheduler::UDP_HEADER_BYTES));
+    socket.send(payload).await?;
+    let mut buffer = vec![0; 4096];
+    match timeout(timeout_duration, socket.recv(&mut buffer)).await {
+        Ok(Ok(n)) => {
+            buffer.truncate(n);
+            trace!("UDP reply from {}:{} ({} bytes)", target_ip, port, n);
+            Ok(buffer)
+        }
+        Ok(Err(e)) => Err(anyhow!("UDP probe to {}:{} failed: {}", target_ip, port, e)),
+        Err(_) => Err(anyhow!("No UDP reply from {}:{}", target_ip, port)),
+    }
+}
+
+/// Banner grabs pipelined with port discovery
+///
+/// Ports are submitted as the port scan finds them open and grabbed right away, at most
+/// `concurrency` at a time, so service detection overlaps the rest of the scan instead of
+/// starting once it is done. Each port is grabbed once however often it is submitted.
+pub struct BannerStage {
+    tx: parking_lot::Mutex<Option<mpsc::UnboundedSender<u16>>>,
+    worker: parking_lot::Mutex<Option<JoinHandle<HashMap<u16, Result<Vec<u8>>>>>>,
+}
+
+impl BannerStage {
+    /// Start the stage for one host
+    ///
+    /// # Arguments
+    /// * `target_ip` - Host whose ports are grabbed
+    /// * `route` - Direct connection or relay
+    /// * `timeout_duration` - Banner timeout per port
+    /// * `concurrency` - Most grabs in flight at once
+    pub fn spawn(target_ip: IpAddr, route: BannerRoute, timeout_duration: Duration, concurrency: usize) -> Self {
+        let (tx, mut rx) = mpsc::unbounded_channel::<u16>();
+        let worker = tokio::spawn(async move {
+            let semaphore = Arc::new(Semaphore::new(concurrency.max(1)));
+            let mut seen = HashSet::new();
+            let mut grabs = JoinSet::new();
+            while let Some(port) = rx.recv().await {
+                if !seen.insert(port) {
+                    continue;
+                }
+                let Ok(permit) = semaphore.clone().acquire_owned().await else { break };
+                let route = route.clone();
+                grabs.spawn(async move {
+                    let banner = grab(&route, target_ip, port, timeout_duration).await;
+                    drop(permit);
+                    (port, banner)
+                });
+            }
+            let mut banners = HashMap::new();
+            while let Some(joined) = grabs.join_next().await {
+                if let Ok((port, banner)) = joined {
+                    banners.insert(port, banner);
+                }
+            }
+            banners
+        });
+        BannerStage {
+            tx: parking_lot::Mutex::new(Some(tx)),
+            worker: parking_lot::Mutex::new(Some(worker)),
+        }
+    }
+
+    /// Queue `port` for a banner grab; ignored once the stage is finished
+    pub fn submit(&self, port: u16) {
+        if let Some(tx) = self.tx.lock().as_ref() {
+            let _ = tx.send(port);
+        }
+    }
+
+    /// Stop taking ports and wait for the grabs in flight
+    ///
+    /// # Returns
+    /// * `HashMap<u16, Result<Vec<u8>>>` - Banner or grab error per submitted port
+    pub async fn finish(&self) -> HashMap<u16, Result<Vec<u8>>> {
+        self.tx.lock().take();
+        let worker = self.worker.lock().take();
+        match worker {
+            Some(worker) => worker.await.unwrap_or_else(|e| {
+                debug!("Banner stage ended abnormally: {}", e);
+                HashMap::new()
+            }),
+            None => HashMap::new(),
+        }
+    }
+
+    /// Drop queued ports and cancel grabs in flight
+    pub fn abort(&self) {
+        self.tx.lock().take();
+        if let Some(worker) = self.worker.lock().take() {
+            worker.abort();
+        }
+    }
+}
+
+/// Error for a port the banner stage has no result for
+pub fn not_grabbed(port: u16) -> anyhow::Error {
+    anyhow!("No banner grab was run for port {}", port)
+}
 
 /// Grab a service banner from the specified IP address and port
 /// 
@@ -17,7 +184,7 @@ pub async fn grab_banner(target_ip: IpAddr, port: u16) -> Result<String> {
     // Use a shorter initial connection timeout (1.5 seconds instead of 3)
     let conn_result = timeout(
         std::time::Duration::from_millis(1500),
-        TcpStream::connect(&addr)
+        crate::netbind::connect_tcp(addr)
     ).await;
     
     match conn_result {
@@ -53,7 +220,7 @@ pub async fn grab_banner(target_ip: IpAddr, port: u16) -> Result<String> {
                     // Send the request with timeout
                     match timeout(
                         std::time::Duration::from_millis(1000),
-                        stream.write_all(request.as_bytes())
+                        crate::evasion::write_payload(&mut stream, request.as_bytes())
                     ).await {
                         Ok(Ok(_)) => {
                             // Read the response
@@ -138,69 +305,43 @@ pub async fn grab_banner_raw(target_ip: IpAddr, port: u16, timeout_duration: std
     // Connect with timeout
     let conn_result = timeout(
         timeout_duration,
-        TcpStream::connect(&addr)
+        crate::netbind::connect_tcp(addr)
     ).await;
     
     match conn_result {
         Ok(Ok(mut stream)) => {
             trace!("Connection established to {}:{} for raw banner grab", target_ip, port);
             
-            // Try different grabbing strategies based on the port
-            let banner = match port {
-                21 | 25 | 110 | 143 => {
-                    // These protocols typically send a banner immediately
-                    // Just read initial data without sending anything
-                    let mut buffer = vec![0; 2048];
-                    
-                    match timeout(timeout_duration, stream.read(&mut buffer)).await {
-                        Ok(Ok(n)) if n > 0 => buffer[0..n].to_vec(),
-                        _ => Vec::new()
-                    }
-                },
-                80 | 443 | 8080 | 8443 => {
-                    // HTTP/HTTPS - send a minimal HTTP request
-                    let request = format!(
-                        "GET / HTTP/1.0\r\n\
-                         Host: {}\r\n\
-                         User-Agent: Mozilla/5.0\r\n\
-                         Connection: close\r\n\r\n",
-                        if target_ip.is_ipv4() { target_ip.to_string() } else { format!("[{}]", target_ip) }
-                    );
-                    
-                    // Send the request with timeout
-                    match timeout(
-                        timeout_duration,
-                        stream.write_all(request.as_bytes())
-                    ).await {
-                        Ok(Ok(_)) => {
-                            // Read the response
-                            let mut buffer = vec![0; 4096];
-                            
-                            match timeout(timeout_duration, stream.read(&mut buffer)).await {
-                                Ok(Ok(n)) if n > 0 => buffer[0..n].to_vec(),
-                                _ => Vec::new()
-                            }
-                        },
-                        _ => Vec::new()
-                    }
-                },
-                22 => {
-                    // SSH - just read the banner, no need to send anything
-                    let mut buffer = vec![0; 256];
-                    
-                    match timeout(timeout_duration, stream.read(&mut buffer)).await {
-                        Ok(Ok(n)) if n > 0 => buffer[0..n].to_vec(),
-                        _ => Vec::new()
-                    }
-                },
-                _ => {
-                    // Generic approach - try passive grabbing 
-                    let mut buffer = vec![0; 1024];
-                    
-                    match timeout(timeout_duration, stream.read(&mut buffer)).await {
-                        Ok(Ok(n)) if n > 0 => buffer[0..n].to_vec(),
-                        _ => Vec::new()
-                    }
+            // Probe order depends on the service: a plugin or custom probe comes first,
+            // services that speak first are only read, web ports get the HTTP request
+            // straight away, and anything else is read before an HTTP request is tried on
+            // the same connection
+            let plugin_probe = crate::plugins::probe_payload(target_ip, port);
+            let custom_probe = crate::service_fingerprints::database()
+                .custom_probe(port, ProbeProtocol::Tcp)
+                .map(|probe| probe.payload.clone());
+            let banner = if READ_ONLY.load(Ordering::Relaxed) || crate::service_policy::protects_port(port) {
+                read_some(&mut stream, 2048, timeout_duration.min(GREETING_WAIT)).await
+            } else if let Some(request) = plugin_probe {
+                send_and_read(&mut stream, &request, 4096, timeout_duration).await
+            } else if let Some(request) = custom_probe {
+                // An empty custom payload means the service speaks first
+                if request.is_empty() {
+                    read_some(&mut stream, 4096, timeout_duration).await
+                } else {
+                    send_and_read(&mut stream, &request, 4096, timeout_duration).await
+                }
+            } else if SERVER_FIRST_PORTS.contains(&port) {
+                read_some(&mut stream, 2048, timeout_duration).await
+            } else if is_web_port(port) {
+                send_and_read(&mut stream, &banner_probe(target_ip, port), 4096, timeout_duration).await
+            } else {
+                let greeting = read_some(&mut stream, 1024, timeout_duration.min(GREETING_WAIT)).await;
+                if greeting.is_empty() {
+                    trace!("No greeting from {}:{}, trying an HTTP request", target_ip, port);
+                    send_and_read(&mut stream, &http_request(target_ip), 4096, timeout_duration).await
+                } else {
+                    greeting
                 }
             };
             
@@ -219,6 +360,149 @@ pub async fn grab_banner_raw(target_ip: IpAddr, port: u16, timeout_duration: std
     }
 }
 
+/// Probe sent to elicit a banner from a port (empty for services that speak first)
+///
+/// # Arguments
+/// * `target_ip` - The IP address used for the HTTP Host header
+/// * `port` - The port being probed
+///
+/// # Returns
+/// * `Vec<u8>` - The probe bytes, empty if the banner should be read passively
+pub fn banner_probe(target_ip: IpAddr, port: u16) -> Vec<u8> {
+    if let Some(probe) = crate::plugins::probe_payload(target_ip, port) {
+        return probe;
+    }
+    if let Some(probe) = crate::service_fingerprints::database().custom_probe(port, ProbeProtocol::Tcp) {
+        return probe.payload.clone();
+    }
+    if is_web_port(port) { http_request(target_ip) } else { Vec::new() }
+}
+
+/// Minimal HTTP/1.0 request for `target_ip`
+fn http_request(target_ip: IpAddr) -> Vec<u8> {
+    format!(
+        "GET / HTTP/1.0\r\n\
+         Host: {}\r\n\
+         User-Agent: Mozilla/5.0\r\n\
+         Connection: close\r\n\r\n",
+        if target_ip.is_ipv4() { target_ip.to_string() } else { format!("[{}]", target_ip) }
+    ).into_bytes()
+}
+
+/// Whether `port` is one web servers commonly listen on
+fn is_web_port(port: u16) -> bool {
+    WEB_PORTS.contains(&port)
+}
+
+/// Read whatever the service sends within `timeout_duration`, up to `max` bytes
+async fn read_some(stream: &mut tokio::net::TcpStream, max: usize, timeout_duration: Duration) -> Vec<u8> {
+    let mut buffer = vec![0; max];
+    match timeout(timeout_duration, stream.read(&mut buffer)).await {
+        Ok(Ok(n)) if n > 0 => {
+            buffer.truncate(n);
+            buffer
+        }
+        _ => Vec::new(),
+    }
+}
+
+/// Send `request` and read the reply
+async fn send_and_read(stream: &mut tokio::net::TcpStream, request: &[u8], max: usize, timeout_duration: Duration) -> Vec<u8> {
+    match timeout(timeout_duration, crate::evasion::write_payload(stream, request)).await {
+        Ok(Ok(_)) => read_some(stream, max, timeout_duration).await,
+        _ => Vec::new(),
+    }
+}
+
+/// Grab a banner through an ICMP covert channel relay
+///
+/// The relay opens the TCP connection on our behalf, so the target only ever sees
+/// the relay's address and no TCP traffic leaves the scanner.
+///
+/// # Arguments
+/// * `relay` - Address of the ICMP relay
+/// * `config` - Covert channel settings (obfuscation must match the relay)
+/// * `target_ip` - The IP address to connect to
+/// * `port` - The port to connect to
+/// * `timeout_duration` - How long to wait for the banner
+///
+/// # Returns
+/// * `Result<Vec<u8>>` - The raw banner bytes or an error
+pub async fn grab_banner_via_icmp(
+    relay: IpAddr,
+    config: crate::tunnel::CovertChannelConfig,
+    target_ip: IpAddr,
+    port: u16,
+    timeout_duration: std::time::Duration,
+) -> Result<Vec<u8>> {
+    trace!("Relaying banner grab for {}:{} through ICMP relay {}", target_ip, port, relay);
+    let banner = crate::tunnel::relay_request(
+        relay,
+        SocketAddr::new(target_ip, port),
+        banner_probe(target_ip, port),
+        config,
+        timeout_duration,
+    ).await?;
+    if banner.is_empty() {
+        debug!("No banner relayed from {}:{}", target_ip, port);
+    }
+    Ok(banner)
+}
+
+/// Grab a banner through an HTTP(S) relay
+///
+/// The relay opens the TCP connection on our behalf and returns what it read inside
+/// an ordinary web response, so only HTTP(S) traffic to the relay leaves the scanner.
+///
+/// # Arguments
+/// * `config` - Relay endpoint and Host/SNI settings
+/// * `target_ip` - The IP address to connect to
+/// * `port` - The port to connect to
+/// * `timeout_duration` - How long the relay waits for the banner
+///
+/// # Returns
+/// * `Result<Vec<u8>>` - The raw banner bytes or an error
+pub async fn grab_banner_via_http(
+    config: &crate::tunnel::HttpTunnelConfig,
+    target_ip: IpAddr,
+    port: u16,
+    timeout_duration: std::time::Duration,
+) -> Result<Vec<u8>> {
+    trace!("Relaying banner grab for {}:{} through HTTP relay {}", target_ip, port, config.connect_host);
+    let banner = crate::tunnel::http_relay_request(
+        config,
+        SocketAddr::new(target_ip, port),
+        banner_probe(target_ip, port),
+        timeout_duration,
+    ).await?;
+    if banner.is_empty() {
+        debug!("No banner relayed from {}:{}", target_ip, port);
+    }
+    Ok(banner)
+}
+
+/// Grab a banner through a QUIC relay
+///
+/// Same as `grab_banner_via_http`, but the request travels on a QUIC stream.
+pub async fn grab_banner_via_quic(
+    config: &crate::tunnel::QuicTunnelConfig,
+    target_ip: IpAddr,
+    port: u16,
+    timeout_duration: std::time::Duration,
+) -> Result<Vec<u8>> {
+    trace!("Relaying banner grab for {}:{} through QUIC relay {}", target_ip, port, config.relay);
+    let banner = crate::tunnel::quic_relay_request(
+        config,
+        SocketAddr::new(target_ip, port),
+        banner_probe(target_ip, port),
+        timeout_duration,
+    ).await?;
+    if banner.is_empty() {
+        debug!("No banner relayed from {}:{}", target_ip, port);
+    }
+    Ok(banner)
+}
+
 /// Identify service from a banner string
 ///
 /// This function analyzes a service banner to determine the service type.
@@ -0,0 +1,111 @@
+//! Cross-host baselines.
+//!
+//! Hosts on one subnet are usually built alike: the same ports open, the same services
+//! and the same versions. Once every host is scanned, each one is compared with the rest
+//! of the population and what sets it apart is recorded on the host as an outlier:
+//! - a port no other host has open
+//! - a service other than the one nearly every other host runs on that port
+//! - a version other than the one nearly every other host runs for that service
+//!
+//! That is where shadow IT, forgotten test boxes and compromised machines tend to show.
+//! Baselines only mean something for a population, so runs with fewer than
+//! `MIN_POPULATION` responding hosts are left alone.
+
+use std::collections::{BTreeMap, HashMap};
+
+use log::info;
+
+use crate::models::{HostOutlier, OutlierKind, ScanResults};
+
+/// Fewest hosts with open ports before a baseline is drawn
+const MIN_POPULATION: usize = 5;
+
+/// Share of hosts that must agree on a service or version for it to count as the norm
+const UNIFORM_SHARE: f64 = 0.75;
+
+/// The value most hosts report, if at least `UNIFORM_SHARE` of `total` agree on it
+fn norm<'a>(values: &[&'a str], total: usize) -> Option<(&'a str, usize)> {
+    let mut counts: HashMap<&str, usize> = HashMap::new();
+    for value in values {
+        *counts.entry(*value).or_insert(0) += 1;
+    }
+    counts.into_iter()
+        .max_by(|a, b| a.1.cmp(&b.1).then_with(|| b.0.cmp(a.0)))
+        .filter(|(_, count)| *count as f64 >= total as f64 * UNIFORM_SHARE)
+}
+
+/// Compare every host with the others and record what sets it apart
+///
+/// # Arguments
+/// * `results` - Results of every host of the run; outliers are added to `outliers`
+///
+/// # Returns
+/// * `usize` - Number of outliers found
+pub fn find_outliers(results: &mut [ScanResults]) -> usize {
+    let population = results.iter().filter(|host| !host.open_ports.is_empty()).count();
+    if population < MIN_POPULATION {
+        return 0;
+    }
+
+    // Hosts with each port open, by port so outliers come out in port order
+    let mut open_on: BTreeMap<u16, Vec<usize>> = BTreeMap::new();
+    for (index, host) in results.iter().enumerate() {
+        for &port in &host.open_ports {
+            open_on.entry(port).or_default().push(index);
+        }
+    }
+
+    let mut found: Vec<(usize, HostOutlier)> = Vec::new();
+    for (&port, hosts) in &open_on {
+        if hosts.len() == 1 {
+            found.push((hosts[0], HostOutlier {
+                port,
+                kind: OutlierKind::UniquePort,
+                detail: format!("port {} is open on no other of the {} responding hosts", port, population),
+            }));
+            continue;
+        }
+
+        let services: Vec<(usize, &str)> = hosts.iter()
+            .filter_map(|&i| results[i].results.get(&port)?.service.as_deref().map(|s| (i, s)))
+            .collect();
+        if services.len() < MIN_POPULATION {
+            continue;
+        }
+        let names: Vec<&str> = services.iter().map(|(_, s)| *s).collect();
+        let Some((service, agreeing)) = norm(&names, names.len()) else { continue };
+        for &(i, other) in services.iter().filter(|(_, s)| *s != service) {
+            found.push((i, HostOutlier {
+                port,
+                kind: OutlierKind::OddService,
+                detail: format!("runs {} on port {} where {} of {} hosts run {}", other, port, agreeing, names.len(), service),
+            }));
+        }
+
+        let versions: Vec<(usize, &str)> = services.iter()
+            .filter(|(_, s)| *s == service)
+            .filter_map(|&(i, _)| results[i].results.get(&port)?.version.as_deref().map(|v| (i, v)))
+            .collect();
+        if versions.len() < MIN_POPULATION {
+            continue;
+        }
+        let numbers: Vec<&str> = versions.iter().map(|(_, v)| *v).collect();
+        let Some((version, agreeing)) = norm(&numbers, numbers.len()) else { continue };
+        for &(i, other) in versions.iter().filter(|(_, v)| *v != version) {
+            found.push((i, HostOutlier {
+                port,
+                kind: OutlierKind::OddVersion,
+                detail: format!("{} {} on port {} where {} of {} hosts run {}", service, other, port, agreeing, numbers.len(), version),
+            }));
+        }
+    }
+
+    let count = found.len();
+    for (index, outlier) in found {
+        results[index].outliers.push(outlier);
+    }
+    if count > 0 {
+        info!("[Baseline] {} outliers across {} responding hosts", count, population);
+    }
+    count
+}
@@ -0,0 +1,147 @@
+//! Per-host timeout calibration.
+//!
+//! A single `--timeout` is either too short for a host behind a slow VPN link, so late
+//! replies turn into false "filtered" results, or far too long on a LAN, where every
+//! silent port wastes seconds. With `--adaptive-timeout` each host gets a few TCP
+//! connect probes before the main scan. Both a completed handshake and a RST give one
+//! round-trip sample; the samples are smoothed as in RFC 6298 and the probe timeout
+//! becomes `srtt + 4 * rttvar`, kept between `MIN_PROBE_TIMEOUT` and the global timeout.
+//!
+//! A shorter timeout makes a late reply more likely, so unanswered probes get one
+//! retransmission, and a second one on hosts that dropped calibration probes.
+
+use std::io;
+use std::net::{IpAddr, SocketAddr};
+use std::time::{Duration, Instant};
+
+use log::{debug, info, warn};
+
+use crate::models::HostTiming;
+
+/// Connect probes sent per host
+const CALIBRATION_PROBES: usize = 5;
+
+/// Ports tried first, since they are the most likely to answer with a SYN-ACK
+const PREFERRED_PORTS: [u16; 8] = [80, 443, 22, 445, 3389, 8080, 25, 53];
+
+/// Lower bound on the derived timeout; below this scheduling jitter dominates the RTT
+const MIN_PROBE_TIMEOUT: Duration = Duration::from_millis(100);
+
+/// Most retransmissions an unanswered probe gets
+const MAX_RETRIES: u8 = 2;
+
+/// Estimate the target's round-trip time and derive its probe timeout
+///
+/// # Arguments
+/// * `target` - Host to calibrate
+/// * `ports` - Ports of the scan; probes go to well-known ones among them first
+/// * `max_timeout` - The global `--timeout`, used for each calibration probe and as the
+///   upper bound of the result
+///
+/// # Returns
+/// * `HostTiming` - Derived timeout and retry count; the global timeout with no retries
+///   if the host answered none of the probes
+///
+/// # Opsec Considerations
+/// Calibration uses full connects, so a port that is open logs a connection from the
+/// scanner before the actual scan begins.
+pub async fn calibrate(target: IpAddr, ports: &[u16], max_timeout: Duration) -> HostTiming {
+    let mut probe_ports: Vec<u16> = PREFERRED_PORTS.iter().copied().filter(|p| ports.contains(p)).collect();
+    probe_ports.extend(ports.iter().copied().filter(|p| !PREFERRED_PORTS.contains(p)));
+    probe_ports.truncate(CALIBRATION_PROBES);
+
+    let mut timing = HostTiming {
+        samples: 0,
+        lost: 0,
+        srtt_ms: None,
+        rttvar_ms: None,
+        probe_timeout_ms: max_timeout.as_millis() as u64,
+        retries: 0,
+    };
+    if probe_ports.is_empty() {
+        return timing;
+    }
+
+    let mut srtt = 0.0f64;
+    let mut rttvar = 0.0f64;
+    for i in 0..CALIBRATION_PROBES {
+        let port = probe_ports[i % probe_ports.len()];
+        crate::scheduler::wait_turn().await;
+        let start = Instant::now();
+        let outcome = tokio::time::timeout(max_timeout, crate::netbind::connect_tcp(SocketAddr::new(target, port))).await;
+        let rtt = start.elapsed().as_secs_f64() * 1000.0;
+        match outcome {
+            Ok(Ok(_)) => {}
+            Ok(Err(e)) if e.kind() == io::ErrorKind::ConnectionRefused => {}
+            Ok(Err(e)) => {
+                debug!("[Calibrate] {}:{} failed without a reply: {}", target, port, e);
+                timing.lost += 1;
+                continue;
+            }
+            Err(_) => {
+                timing.lost += 1;
+                continue;
+            }
+        }
+
+        debug!("[Calibrate] {}:{} answered in {:.1} ms", target, port, rtt);
+        if timing.samples == 0 {
+            srtt = rtt;
+            rttvar = rtt / 2.0;
+        } else {
+            rttvar = 0.75 * rttvar + 0.25 * (srtt - rtt).abs();
+            srtt = 0.875 * srtt + 0.125 * rtt;
+        }
+        timing.samples += 1;
+    }
+
+    if timing.samples == 0 {
+        warn!("[Calibrate] {} answered none of {} calibration probes; keeping the global timeout of {:?}",
+              target, CALIBRATION_PROBES, max_timeout);
+        return timing;
+    }
+
+    let timeout = derived_timeout(srtt, rttvar, max_timeout);
+    timing.srtt_ms = Some(srtt);
+    timing.rttvar_ms = Some(rttvar);
+    timing.probe_timeout_ms = timeout.as_millis() as u64;
+    if timeout < max_timeout {
+        timing.retries += 1;
+    }
+    if timing.lost > 0 {
+        timing.retries += 1;
+    }
+    timing.retries = timing.retries.min(MAX_RETRIES);
+
+    info!("[Calibrate] {}: srtt {:.1} ms, rttvar {:.1} ms over {} samples ({} lost) -> timeout {:?}, {} retries",
+          target, srtt, rttvar, timing.samples, timing.lost, timeout, timing.retries);
+    timing
+}
+
+/// Probe timeout of `srtt + 4 * rttvar` (in ms), at least `MIN_PROBE_TIMEOUT` but never
+/// above `max_timeout`, even when the global timeout itself is below the minimum
+fn derived_timeout(srtt: f64, rttvar: f64, max_timeout: Duration) -> Duration {
+    Duration::from_secs_f64((srtt + 4.0 * rttvar) / 1000.0)
+        .max(MIN_PROBE_TIMEOUT)
+        .min(max_timeout)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn timeout_follows_rtt_within_bounds() {
+        let max = Duration::from_secs(3);
+        assert_eq!(derived_timeout(125.0, 31.25, max), Duration::from_millis(250));
+        assert_eq!(derived_timeout(0.2, 0.1, max), MIN_PROBE_TIMEOUT);
+        assert_eq!(derived_timeout(2000.0, 1000.0, max), max);
+    }
+
+    #[test]
+    fn global_timeout_below_minimum_wins() {
+        let max = Duration::from_millis(50);
+        assert_eq!(derived_timeout(0.2, 0.1, max), max);
+        assert_eq!(derived_timeout(500.0, 100.0, max), max);
+    }
+}
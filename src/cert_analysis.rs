@@ -0,0 +1,187 @@
+//! Certificate chain analysis.
+//!
+//! The SSL scan collects the full chain a server presents, even when it does not
+//! validate, and this module turns it into structured `CertificateIssue`s: self-signed
+//! leaves, untrusted or broken chains, expired, not-yet-valid and soon-to-expire
+//! certificates, weak signature algorithms, short RSA/DSA keys and hostname mismatches.
+//!
+//! Trust is judged by rustls against the system store (see
+//! `ssl_config::recording_tls_config`); everything else is read from the certificates
+//! themselves, so analysis sends no extra traffic.
+
+use chrono::Utc;
+use lazy_static::lazy_static;
+use parking_lot::RwLock;
+
+use crate::models::{CertIssueKind, CertificateInfo, CertificateIssue};
+
+/// Default window, in days, for the expires-soon warning
+pub const DEFAULT_EXPIRY_WARNING_DAYS: u32 = 30;
+
+lazy_static! {
+    /// Days before expiry at which a certificate is reported as expiring soon
+    static ref EXPIRY_WARNING_DAYS: RwLock<u32> = RwLock::new(DEFAULT_EXPIRY_WARNING_DAYS);
+}
+
+/// Set the expires-soon window (`--cert-expiry-warn-days`)
+pub fn set_expiry_warning_days(days: u32) {
+    *EXPIRY_WARNING_DAYS.write() = days;
+}
+
+/// Current expires-soon window in days
+pub fn expiry_warning_days() -> u32 {
+    *EXPIRY_WARNING_DAYS.read()
+}
+
+/// Signature algorithm OIDs considered weak, with a readable name
+const WEAK_SIGNATURES: &[(&str, &str)] = &[
+    ("1.2.840.113549.1.1.2", "md2WithRSAEncryption"),
+    ("1.2.840.113549.1.1.4", "md5WithRSAEncryption"),
+    ("1.2.840.113549.1.1.5", "sha1WithRSAEncryption"),
+    ("1.3.14.3.2.29", "sha1WithRSASignature"),
+    ("1.2.840.10040.4.3", "dsaWithSHA1"),
+    ("1.2.840.10045.4.1", "ecdsaWithSHA1"),
+];
+
+/// RSA and DSA keys shorter than this are reported
+const MIN_KEY_BITS: u32 = 2048;
+
+fn issue(kind: CertIssueKind, severity: &str, detail: String) -> CertificateIssue {
+    CertificateIssue { kind, severity: severity.to_string(), detail }
+}
+
+/// Analyze a certificate chain as presented by the server
+///
+/// # Arguments
+/// * `ders` - DER certificates, leaf first
+/// * `trust` - Verdict from the trust store check, if one was made
+///
+/// # Returns
+/// * `Vec<CertificateIssue>` - Issues found, leaf problems first
+#[cfg(not(feature = "minimal-static"))]
+pub fn analyze_chain(ders: &[&[u8]], trust: Option<&Result<(), String>>) -> Vec<CertificateIssue> {
+    use x509_parser::public_key::PublicKey;
+
+    let certs: Vec<_> = ders.iter()
+        .filter_map(|der| x509_parser::parse_x509_certificate(der).ok().map(|(_, cert)| cert))
+        .collect();
+    let Some(leaf) = certs.first() else { return Vec::new() };
+
+    let mut issues = Vec::new();
+    let now = Utc::now().timestamp();
+    let warn_secs = i64::from(expiry_warning_days()) * 86_400;
+
+    let is_self_signed = |cert: &x509_parser::certificate::X509Certificate<'_>| {
+        cert.subject().as_raw() == cert.issuer().as_raw() && cert.verify_signature(None).is_ok()
+    };
+    let leaf_self_signed = is_self_signed(leaf);
+    if leaf_self_signed {
+        issues.push(issue(CertIssueKind::SelfSigned, "Medium",
+            format!("Certificate for {} is self-signed", leaf.subject())));
+    }
+
+    for (position, cert) in certs.iter().enumerate() {
+        let label = if position == 0 { "Leaf certificate".to_string() } else { format!("Chain certificate {} ({})", position, cert.subject()) };
+        let validity = cert.validity();
+        let not_before = validity.not_before.timestamp();
+        let not_after = validity.not_after.timestamp();
+
+        if now > not_after {
+            issues.push(issue(CertIssueKind::Expired, "High",
+                format!("{} expired on {}", label, validity.not_after.to_datetime())));
+        } else if now < not_before {
+            issues.push(issue(CertIssueKind::NotYetValid, "Medium",
+                format!("{} is not valid until {}", label, validity.not_before.to_datetime())));
+        } else if not_after - now <= warn_secs {
+            issues.push(issue(CertIssueKind::ExpiresSoon, "Low",
+                format!("{} expires in {} days ({})", label, (not_after - now) / 86_400, validity.not_after.to_datetime())));
+        }
+
+        // A root's own signature is never checked by clients, so only flag the rest
+        let oid = cert.signature_algorithm.algorithm.to_id_string();
+        if let Some((_, name)) = WEAK_SIGNATURES.iter().find(|(weak, _)| *weak == oid) {
+            if position == 0 || !is_self_signed(cert) {
+                let severity = if name.starts_with("md") { "High" } else { "Medium" };
+                issues.push(issue(CertIssueKind::WeakSignature, severity,
+                    format!("{} is signed with {}", label, name)));
+            }
+        }
+
+        let key = match cert.public_key().parsed() {
+            Ok(PublicKey::RSA(rsa)) => Some(("RSA", rsa.key_size() as u32)),
+            Ok(PublicKey::DSA(dsa)) => Some(("DSA", dsa.len() as u32 * 8)),
+            _ => None,
+        };
+        if let Some((algorithm, bits)) = key {
+            if bits > 0 && bits < MIN_KEY_BITS {
+                let severity = if bits < 1024 { "High" } else { "Medium" };
+                issues.push(issue(CertIssueKind::WeakKey, severity,
+                    format!("{} has a {}-bit {} key", label, bits, algorithm)));
+            }
+        }
+    }
+
+    // Each certificate should be issued by the one after it
+    let mut broken = false;
+    for pair in certs.windows(2) {
+        let (child, parent) = (&pair[0], &pair[1]);
+        let linked = child.issuer().as_raw() == parent.subject().as_raw()
+            && child.verify_signature(Some(parent.public_key())).is_ok();
+        if !linked {
+            broken = true;
+            issues.push(issue(CertIssueKind::BrokenChain, "Medium",
+                format!("{} is not issued by the next certificate sent ({})", child.subject(), parent.subject())));
+        }
+    }
+
+    if let Some(Err(reason)) = trust {
+        let explained = leaf_self_signed || broken || issues.iter()
+            .any(|i| matches!(i.kind, CertIssueKind::Expired | CertIssueKind::NotYetValid));
+        if !explained {
+            issues.push(issue(CertIssueKind::Untrusted, "Medium",
+                format!("Chain does not validate against the system trust store: {}", reason)));
+        }
+    }
+
+    issues
+}
+
+#[cfg(feature = "minimal-static")]
+pub fn analyze_chain(_ders: &[&[u8]], _trust: Option<&Result<(), String>>) -> Vec<CertificateIssue> {
+    // Certificates are not parsed in minimal builds
+    Vec::new()
+}
+
+/// Check that a certificate covers `hostname`
+///
+/// SANs are used when present, otherwise the subject CN. A leading `*.` matches exactly
+/// one label.
+///
+/// # Returns
+/// * `Option<CertificateIssue>` - A hostname-mismatch issue, or `None` if the name matches
+///   or the certificate names nothing to compare against
+pub fn check_hostname(cert: &CertificateInfo, hostname: &str) -> Option<CertificateIssue> {
+    let hostname = hostname.trim_end_matches('.').to_ascii_lowercase();
+    let mut names: Vec<String> = cert.alt_names.iter()
+        .map(|n| n.trim_end_matches('.').to_ascii_lowercase())
+        .collect();
+    if names.is_empty() {
+        names.extend(cert.subject.split(',')
+            .map(str::trim)
+            .find_map(|part| part.strip_prefix("CN="))
+            .map(|cn| cn.trim_end_matches('.').to_ascii_lowercase()));
+    }
+    if names.is_empty() {
+        return None;
+    }
+
+    let matches = |pattern: &str| match pattern.strip_prefix("*.") {
+        Some(suffix) => hostname.split_once('.').map_or(false, |(label, rest)| !label.is_empty() && rest == suffix),
+        None => pattern == hostname,
+    };
+    if names.iter().any(|name| matches(name)) {
+        return None;
+    }
+    Some(issue(CertIssueKind::HostnameMismatch, "Medium",
+        format!("Certificate does not cover {} (names: {})", hostname, names.join(", "))))
+}
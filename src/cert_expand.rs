@@ -0,0 +1,123 @@
+//! Target expansion from certificate names.
+//!
+//! TLS certificates routinely list the other hostnames served by the same
+//! infrastructure. With `--expand-from-certs`, the SAN entries (and subject CN) of every
+//! certificate collected during a scan are resolved, and those landing inside the
+//! `--scope` ranges are queued as further targets. Expansion is recursive (a new target's
+//! certificates can add more) and bounded by `--expand-max`.
+//!
+//! Names are resolved through the system resolver, so expansion sends DNS queries for
+//! every name found. Wildcard names cannot be resolved to a host and are skipped.
+
+use std::collections::HashSet;
+use std::net::IpAddr;
+
+use anyhow::{anyhow, Result};
+use ipnet::IpNet;
+use log::{debug, info};
+
+use crate::models::ScanResults;
+
+/// A hostname found in a certificate that resolves into scope
+#[derive(Debug, Clone)]
+pub struct ExpandedTarget {
+    pub hostname: String,
+    pub ip: IpAddr,
+    /// `host:port` whose certificate named it
+    pub source: String,
+}
+
+/// Parse `--scope` entries: CIDR ranges or single addresses
+pub fn parse_scope(entries: &[String]) -> Result<Vec<IpNet>> {
+    entries.iter()
+        .flat_map(|entry| entry.split(','))
+        .map(str::trim)
+        .filter(|entry| !entry.is_empty())
+        .map(|entry| {
+            entry.parse::<IpNet>()
+                .or_else(|_| entry.parse::<IpAddr>().map(IpNet::from))
+                .map_err(|_| anyhow!("Invalid scope '{}': expected an IP address or CIDR range", entry))
+        })
+        .collect()
+}
+
+/// Hostnames and IPs named by the certificates in a scan's results
+///
+/// Returns `(name, source)` pairs, deduplicated by name.
+fn certificate_names(results: &ScanResults) -> Vec<(String, String)> {
+    let mut seen = HashSet::new();
+    let mut names = Vec::new();
+    let mut ports: Vec<_> = results.results.iter().collect();
+    ports.sort_unstable_by_key(|(port, _)| **port);
+
+    // Certificates harvested with --sni-list name tenants the default one does not
+    let certs = ports.into_iter().flat_map(|(port, result)| result.cert_info.iter()
+        .chain(result.sni_certificates.iter().map(|sni| &sni.certificate))
+        .map(move |cert| (port, cert)));
+    for (port, cert) in certs {
+        let common_name = cert.subject.split(',')
+            .map(str::trim)
+            .find_map(|part| part.strip_prefix("CN="))
+            .map(str::to_string);
+        for name in cert.alt_names.iter().cloned().chain(common_name) {
+            let name = name.trim().trim_end_matches('.').to_ascii_lowercase();
+            if name.is_empty() || !seen.insert(name.clone()) {
+                continue;
+            }
+            names.push((name, format!("{}:{}", results.target_ip, port)));
+        }
+    }
+    names
+}
+
+/// New in-scope targets named by the certificates in `results`
+///
+/// # Arguments
+/// * `results` - A finished scan
+/// * `scope` - Ranges new targets must resolve into
+/// * `known` - Addresses already scanned or queued; skipped
+/// * `allow_ipv6` - Whether IPv6 addresses may be returned
+///
+/// # Returns
+/// * `Vec<ExpandedTarget>` - One entry per new address, first hostname wins
+///
+/// # Opsec Considerations
+/// Each certificate name is looked up with the system resolver, which the target's DNS
+/// operators may see.
+pub async fn expansion_targets(results: &ScanResults, scope: &[IpNet], known: &HashSet<IpAddr>, allow_ipv6: bool) -> Vec<ExpandedTarget> {
+    let mut found: Vec<ExpandedTarget> = Vec::new();
+
+    for (name, source) in certificate_names(results) {
+        if name.starts_with("*.") {
+            debug!("[Expand] Skipping wildcard name {} from {}", name, source);
+            continue;
+        }
+        let addresses: Vec<IpAddr> = match name.parse::<IpAddr>() {
+            Ok(ip) => vec![ip],
+            Err(_) => match tokio::net::lookup_host(format!("{}:0", name)).await {
+                Ok(addrs) => addrs.map(|a| a.ip()).collect(),
+                Err(e) => {
+                    debug!("[Expand] {} (from {}) does not resolve: {}", name, source, e);
+                    continue;
+                }
+            },
+        };
+
+        for ip in addresses {
+            if (ip.is_ipv6() && !allow_ipv6) || known.contains(&ip) || found.iter().any(|t| t.ip == ip) {
+                continue;
+            }
+            if crate::dns_sanity::is_nxdomain_placeholder(&ip) {
+                debug!("[Expand] {} -> {} is the resolver's NXDOMAIN placeholder", name, ip);
+                continue;
+            }
+            if !scope.iter().any(|range| range.contains(&ip)) {
+                debug!("[Expand] {} -> {} is out of scope", name, ip);
+                continue;
+            }
+            info!("[Expand] Certificate on {} names {} ({}), adding as a target", source, name, ip);
+            found.push(ExpandedTarget { hostname: name.clone(), ip, source: source.clone() });
+        }
+    }
+    found
+}
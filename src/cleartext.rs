@@ -0,0 +1,228 @@
+//! Cleartext administrative protocol findings.
+//!
+//! Services that carry logins or administration in the clear are turned into
+//! findings with remediation text, so the report says what the port list only
+//! implies:
+//! - Telnet, rlogin, rsh and rexec: always cleartext, flagged from detection alone
+//! - FTP: flagged unless the server lists `AUTH TLS` (or `AUTH SSL`) in its `FEAT`
+//!   reply. The `FEAT` exchange is the only traffic this module sends and is left
+//!   out under --safe, where FTP is flagged with the TLS support unknown.
+//! - HTTP admin panels: plain HTTP ports whose title, technologies or basic-auth
+//!   challenge show a login or management interface
+
+use std::collections::HashMap;
+use std::net::{IpAddr, SocketAddr};
+use std::time::Duration;
+
+use anyhow::{Context, Result};
+use log::{debug, info};
+use serde_json::json;
+use tokio::io::AsyncReadExt;
+use tokio::net::TcpStream;
+use tokio::time::timeout;
+
+use crate::models::{Confidence, Finding, PortResult, PortStatus};
+
+/// Remote shell services: (service names, usual port, finding id, label)
+const R_SERVICES: [(&[&str], u16, &str, &str); 3] = [
+    (&["rlogin", "login"], 513, "CLEARTEXT-RLOGIN", "rlogin"),
+    (&["rsh", "shell"], 514, "CLEARTEXT-RSH", "rsh"),
+    (&["rexec", "exec"], 512, "CLEARTEXT-REXEC", "rexec"),
+];
+
+/// Words in a page title that mark a login or management interface
+const ADMIN_TITLE_WORDS: [&str; 12] = [
+    "admin", "login", "log in", "sign in", "management", "manager", "console",
+    "dashboard", "configuration", "control panel", "router", "setup",
+];
+
+/// Technologies that are administration interfaces in their own right
+const ADMIN_TECHNOLOGIES: [&str; 8] = [
+    "phpmyadmin", "webmin", "jenkins", "tomcat manager", "cpanel", "plesk", "grafana", "kibana",
+];
+
+/// Largest FTP reply read
+const MAX_REPLY: usize = 4096;
+
+/// Findings for one port
+#[derive(Debug)]
+pub struct CleartextCheck {
+    pub port: u16,
+    pub details: serde_json::Value,
+    pub vulns: Vec<Finding>,
+}
+
+fn finding(id: &str, title: &str, severity: &str, confidence: Confidence, description: String, remediation: &str) -> Finding {
+    Finding {
+        id: id.to_string(),
+        title: title.to_string(),
+        description,
+        severity: severity.to_string(),
+        confidence,
+        remediation: Some(remediation.to_string()),
+        ..Default::default()
+    }
+}
+
+/// Whether detection saw TLS on the port
+fn uses_tls(result: &PortResult) -> bool {
+    let service = result.service.as_deref().unwrap_or("").to_ascii_lowercase();
+    result.cert_info.is_some() || result.tls_protocol_version.is_some()
+        || service.starts_with("https") || service.contains("ssl") || service.contains("tls")
+}
+
+/// Why a plain HTTP port looks like an admin panel, if it does
+fn admin_panel_evidence(result: &PortResult) -> Option<String> {
+    let http = result.http_info.as_ref()?;
+    let basic_auth = http.headers.iter()
+        .find(|(name, _)| name.eq_ignore_ascii_case("www-authenticate"))
+        .map(|(_, value)| value.to_ascii_lowercase())
+        .filter(|value| value.starts_with("basic") || value.starts_with("digest"));
+    if let Some(scheme) = basic_auth {
+        return Some(format!("asks for HTTP {} authentication", scheme.split_whitespace().next().unwrap_or("basic")));
+    }
+    if let Some(tech) = http.technologies.iter().find(|t| ADMIN_TECHNOLOGIES.iter().any(|a| t.to_ascii_lowercase().contains(a))) {
+        return Some(format!("serves {}", tech));
+    }
+    let title = http.title.as_deref()?;
+    let lower = title.to_ascii_lowercase();
+    ADMIN_TITLE_WORDS.iter().any(|word| lower.contains(word))
+        .then(|| format!("page title \"{}\"", title.trim()))
+}
+
+/// Ask an FTP server for its features and report whether it offers `AUTH TLS`
+async fn ftp_offers_tls(addr: SocketAddr, wait: Duration) -> Result<bool> {
+    let mut stream: TcpStream = timeout(wait, crate::netbind::connect_tcp(addr)).await.context("Connect timed out")??;
+    let greeting = read_reply(&mut stream, wait).await?;
+    if !greeting.starts_with("220") {
+        anyhow::bail!("Unexpected FTP greeting: {}", greeting.lines().next().unwrap_or(""));
+    }
+    crate::scheduler::metered_write(&mut stream, b"FEAT\r\n").await?;
+    let features = read_reply(&mut stream, wait).await?;
+    let _ = crate::scheduler::metered_write(&mut stream, b"QUIT\r\n").await;
+    crate::audit::record(|| crate::audit::Probe::new("cleartext", "tcp", addr.ip(), Some(addr.port()), 12).detail("FTP FEAT"));
+    let upper = features.to_ascii_uppercase();
+    Ok(upper.contains("AUTH TLS") || upper.contains("AUTH SSL"))
+}
+
+/// Read a complete FTP reply, including multi-line `211-` blocks
+async fn read_reply(stream: &mut TcpStream, wait: Duration) -> Result<String> {
+    let mut reply = Vec::new();
+    let mut buffer = [0u8; 1024];
+    loop {
+        let n = timeout(wait, stream.read(&mut buffer)).await.context("Reply timed out")??;
+        if n == 0 {
+            break;
+        }
+        reply.extend_from_slice(&buffer[..n]);
+        let text = String::from_utf8_lossy(&reply);
+        // A reply ends with a line of three digits followed by a space
+        let complete = text.ends_with('\n') && text.lines().last()
+            .is_some_and(|line| line.len() >= 4 && line.as_bytes()[..3].iter().all(u8::is_ascii_digit) && line.as_bytes()[3] == b' ');
+        if complete || reply.len() >= MAX_REPLY {
+            break;
+        }
+    }
+    Ok(String::from_utf8_lossy(&reply).into_owned())
+}
+
+/// Flag every cleartext administrative protocol among a host's open ports
+///
+/// # Arguments
+/// * `target` - Host the results belong to
+/// * `results` - Port results after detection; only open TCP ports are considered
+/// * `probe_ftp` - Ask FTP servers for `FEAT` to see whether they offer TLS
+/// * `wait` - Connect and reply timeout for the FTP exchange
+///
+/// # Returns
+/// * `Vec<CleartextCheck>` - Findings, one entry per flagged port
+pub async fn check_cleartext(target: IpAddr, results: &HashMap<u16, PortResult>, probe_ftp: bool, wait: Duration) -> Vec<CleartextCheck> {
+    let mut ports: Vec<(&u16, &PortResult)> = results.iter()
+        .filter(|(_, r)| r.tcp_states.values().any(|&s| s == PortStatus::Open))
+        .collect();
+    ports.sort_by_key(|(port, _)| **port);
+
+    let mut checks = Vec::new();
+    for (&port, result) in ports {
+        let service = result.service.as_deref().unwrap_or("").to_ascii_lowercase();
+        let known = !service.is_empty();
+        // Services matched by port number alone were never identified
+        let confidence = if known { Confidence::Firm } else { Confidence::Tentative };
+
+        if service == "telnet" || (!known && (port == 23 || port == 2323)) {
+            checks.push(CleartextCheck {
+                port,
+                details: json!({ "cleartext_protocol": "telnet" }),
+                vulns: vec![finding("CLEARTEXT-TELNET", "Telnet enabled", "High", confidence,
+                    "Telnet sends logins and the whole session unencrypted; anyone on the path can read credentials and commands".to_string(),
+                    "Disable Telnet and administer the device over SSH. If the device cannot run SSH, restrict the port to a management network.")],
+            });
+            continue;
+        }
+
+        if let Some((_, _, id, label)) = R_SERVICES.iter()
+            .find(|(names, usual, _, _)| names.contains(&service.as_str()) || (!known && port == *usual)) {
+            checks.push(CleartextCheck {
+                port,
+                details: json!({ "cleartext_protocol": label }),
+                vulns: vec![finding(id, &format!("{} enabled", label), "High", confidence,
+                    format!("{} is a cleartext remote shell that trusts source addresses or sends passwords unencrypted", label),
+                    "Disable the r-services (rlogin, rsh, rexec) and remove .rhosts and hosts.equiv trust; use SSH instead.")],
+            });
+            continue;
+        }
+
+        if service == "ftp" || (!known && port == 21) {
+            let tls = if probe_ftp {
+                let addr = SocketAddr::new(target, port);
+                info!("[Cleartext] Asking FTP server {} for its features", addr);
+                match ftp_offers_tls(addr, wait).await {
+                    Ok(tls) => Some(tls),
+                    Err(e) => {
+                        debug!("[Cleartext] FEAT on {} failed: {}", addr, e);
+                        None
+                    }
+                }
+            } else {
+                None
+            };
+            if tls == Some(true) {
+                continue;
+            }
+            let description = match tls {
+                Some(_) => "FTP server does not offer AUTH TLS, so logins and file transfers cross the network unencrypted".to_string(),
+                None => "FTP control channel is cleartext unless clients negotiate AUTH TLS, which was not verified".to_string(),
+            };
+            checks.push(CleartextCheck {
+                port,
+                details: json!({ "cleartext_protocol": "ftp", "ftp_auth_tls": tls }),
+                vulns: vec![finding("CLEARTEXT-FTP", "FTP without TLS", "Medium",
+                    if tls.is_some() { Confidence::Firm } else { Confidence::Tentative }, description,
+                    "Require FTPS (AUTH TLS) for every login or replace FTP with SFTP; disable anonymous access that is not needed.")],
+            });
+            continue;
+        }
+
+        if service.starts_with("http") && !uses_tls(result) {
+            if let Some(evidence) = admin_panel_evidence(result) {
+                // An auth challenge is certain; a title or technology match is a guess
+                let (severity, confidence) = if evidence.starts_with("asks for") {
+                    ("High", Confidence::Firm)
+                } else {
+                    ("Medium", Confidence::Tentative)
+                };
+                checks.push(CleartextCheck {
+                    port,
+                    details: json!({ "cleartext_protocol": "http", "admin_panel": evidence }),
+                    vulns: vec![Finding {
+                        evidence: vec![evidence.clone()],
+                        ..finding("CLEARTEXT-HTTP-ADMIN", "Admin interface over HTTP", severity, confidence,
+                            format!("Administrative web interface served over plain HTTP ({}); credentials and session cookies are sent unencrypted", evidence),
+                            "Serve the interface over HTTPS only and redirect HTTP to it, or bind it to a management network.")
+                    }],
+                });
+            }
+        }
+    }
+    checks
+}
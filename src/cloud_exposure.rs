@@ -0,0 +1,372 @@
+//! Cloud metadata exposure.
+//!
+//! Instances in AWS, Azure, GCP, Oracle Cloud and Alibaba Cloud read their credentials
+//! from a metadata service on a link-local address. A reverse proxy that forwards by
+//! `Host` header or accepts absolute URLs hands that service to anyone, and an error page
+//! naming 169.254.169.254 shows an application that already talks to it. Web ports are
+//! checked for both:
+//!
+//! - **References**: banners, headers and redirects that name a metadata address, and
+//!   redirects carrying a URL in a parameter, which are the usual SSRF entry points
+//! - **Canaries** (not with `--safe`): the index of each metadata service, asked for
+//!   through the port with a `Host` header and as an absolute URL. Only the index is read,
+//!   never a credential path
+//!
+//! Hosts are then tagged with the cloud their headers, cookies, names and load balancer
+//! point to, along with where that cloud's metadata service lives, to guide follow-up
+//! testing of the SSRF candidates.
+
+use std::collections::{BTreeSet, HashMap};
+use std::net::{IpAddr, SocketAddr};
+use std::time::Duration;
+
+use anyhow::{anyhow, Context, Result};
+use log::{debug, info, warn};
+use serde_json::json;
+use tokio::io::{AsyncRead, AsyncReadExt, AsyncWrite, AsyncWriteExt};
+use tokio::time::timeout;
+
+use crate::models::{CloudInfo, Confidence, EdgeInfo, Finding, PortResult, PortStatus};
+
+/// Web ports checked when detection did not see HTTP on them, with whether they use TLS
+const WEB_PORTS: [(u16, bool); 4] = [(80, false), (443, true), (8080, false), (8443, true)];
+
+/// Estimated size of a TLS handshake, counted against the scan budget
+const TLS_HANDSHAKE_BYTES: usize = 700;
+
+/// Largest response read
+const MAX_RESPONSE: usize = 64 * 1024;
+
+/// Metadata addresses and paths whose mention in a response is worth following up
+const METADATA_REFERENCES: [&str; 7] = [
+    "169.254.169.254", "metadata.google.internal", "/latest/meta-data", "/computemetadata/",
+    "fd00:ec2::254", "100.100.100.200", "169.254.170.2",
+];
+
+/// Query parameters that usually carry a URL the server fetches or redirects to
+const URL_PARAMETERS: [&str; 12] = ["url", "uri", "u", "src", "dest", "target", "redirect", "next", "proxy", "feed", "image", "link"];
+
+/// Metadata service of a provider: address, index path, required header, markers of a real answer
+struct Canary {
+    provider: &'static str,
+    host: &'static str,
+    path: &'static str,
+    header: &'static str,
+    markers: &'static [&'static str],
+}
+
+const CANARIES: [Canary; 5] = [
+    Canary { provider: "AWS", host: "169.254.169.254", path: "/latest/meta-data/", header: "", markers: &["ami-id", "instance-id"] },
+    Canary { provider: "Azure", host: "169.254.169.254", path: "/metadata/instance?api-version=2021-02-01", header: "Metadata: true\r\n", markers: &["\"compute\"", "vmId"] },
+    Canary { provider: "GCP", host: "metadata.google.internal", path: "/computeMetadata/v1/", header: "Metadata-Flavor: Google\r\n", markers: &["instance/", "project/"] },
+    Canary { provider: "Oracle Cloud", host: "169.254.169.254", path: "/opc/v2/instance/", header: "Authorization: Bearer Oracle\r\n", markers: &["availabilityDomain", "compartmentId"] },
+    Canary { provider: "Alibaba Cloud", host: "100.100.100.200", path: "/latest/meta-data/", header: "", markers: &["instance-id", "region-id"] },
+];
+
+/// Response headers a provider's load balancers and platforms set (lowercase name, optional value substring)
+const PROVIDER_HEADERS: &[(&str, Option<&str>, &str)] = &[
+    ("server", Some("awselb"), "AWS"),
+    ("server", Some("amazons3"), "AWS"),
+    ("x-amzn-requestid", None, "AWS"),
+    ("x-amzn-trace-id", None, "AWS"),
+    ("x-amz-request-id", None, "AWS"),
+    ("x-amz-apigw-id", None, "AWS"),
+    ("server", Some("microsoft-azure-application-gateway"), "Azure"),
+    ("x-ms-request-id", None, "Azure"),
+    ("x-azure-ref", None, "Azure"),
+    ("x-ms-routing-request-id", None, "Azure"),
+    ("via", Some("google"), "GCP"),
+    ("server", Some("google frontend"), "GCP"),
+    ("x-cloud-trace-context", None, "GCP"),
+    ("x-goog-generation", None, "GCP"),
+    ("opc-request-id", None, "Oracle Cloud"),
+    ("eagleid", None, "Alibaba Cloud"),
+];
+
+/// Load balancer affinity cookies, by provider
+const PROVIDER_COOKIES: [(&str, &str); 5] = [
+    ("AWSALB", "AWS"), ("AWSELB", "AWS"), ("ARRAffinity", "Azure"), ("GCLB", "GCP"), ("SERVERID", "Alibaba Cloud"),
+];
+
+/// Hostname suffixes of provider-assigned names
+const PROVIDER_SUFFIXES: [(&str, &str); 9] = [
+    ("amazonaws.com", "AWS"), ("awsapprunner.com", "AWS"), ("cloudapp.azure.com", "Azure"), ("azurewebsites.net", "Azure"),
+    ("googleusercontent.com", "GCP"), ("appspot.com", "GCP"), ("run.app", "GCP"), ("oraclecloud.com", "Oracle Cloud"),
+    ("aliyuncs.com", "Alibaba Cloud"),
+];
+
+/// Where to find a provider's metadata service during follow-up testing
+fn metadata_endpoint(provider: &str) -> &'static str {
+    match provider {
+        "AWS" => "http://169.254.169.254/latest/meta-data/ (IMDSv2 wants a token from PUT /latest/api/token; ECS task credentials are at 169.254.170.2)",
+        "Azure" => "http://169.254.169.254/metadata/instance?api-version=2021-02-01 with header Metadata: true",
+        "GCP" => "http://metadata.google.internal/computeMetadata/v1/ with header Metadata-Flavor: Google",
+        "Oracle Cloud" => "http://169.254.169.254/opc/v2/instance/ with header Authorization: Bearer Oracle",
+        "Alibaba Cloud" => "http://100.100.100.200/latest/meta-data/",
+        _ => "unknown",
+    }
+}
+
+/// Findings of one web port
+#[derive(Debug)]
+pub struct MetadataCheck {
+    pub port: u16,
+    pub details: serde_json::Value,
+    pub vulns: Vec<Finding>,
+}
+
+/// Cloud a host's own responses point to, with the reasons
+fn provider_signals(target: &str, results: &HashMap<u16, PortResult>, edge: Option<&EdgeInfo>) -> Vec<(&'static str, String)> {
+    let mut signals = Vec::new();
+    let name = target.trim_end_matches('.').to_ascii_lowercase();
+    for (suffix, provider) in PROVIDER_SUFFIXES {
+        if name.ends_with(suffix) {
+            signals.push((provider, format!("{} is a {} name", name, provider)));
+        }
+    }
+
+    let mut ports: Vec<(&u16, &PortResult)> = results.iter().collect();
+    ports.sort_by_key(|(port, _)| **port);
+    for (port, result) in ports {
+        if let Some(http) = &result.http_info {
+            let headers: HashMap<String, String> = http.headers.iter()
+                .map(|(name, value)| (name.to_lowercase(), value.to_lowercase()))
+                .collect();
+            for (header, needle, provider) in PROVIDER_HEADERS {
+                let Some(value) = headers.get(*header) else { continue };
+                if needle.map_or(true, |needle| value.contains(needle)) {
+                    signals.push((*provider, format!("port {} answers with the {} {} header", port, provider, header)));
+                }
+            }
+            for (cookie, provider) in PROVIDER_COOKIES {
+                if http.cookies.iter().any(|set| set.starts_with(&format!("{}=", cookie))) {
+                    signals.push((provider, format!("port {} sets the {} {} cookie", port, provider, cookie)));
+                }
+            }
+        }
+        if let Some(found) = result.service_details.as_ref().and_then(|d| d.get("cloud_metadata_provider")).and_then(|p| p.as_str()) {
+            if let Some(canary) = CANARIES.iter().find(|canary| canary.provider == found) {
+                signals.push((canary.provider, format!("the {} metadata service answered through port {}", canary.provider, port)));
+            }
+        }
+    }
+
+    // Edge providers that belong to a cloud
+    if let Some(provider) = edge.and_then(|edge| edge.provider.as_deref()) {
+        let cloud = if provider.contains("AWS") || provider.contains("Amazon") {
+            Some("AWS")
+        } else if provider.contains("Azure") {
+            Some("Azure")
+        } else {
+            None
+        };
+        if let Some(cloud) = cloud {
+            signals.push((cloud, format!("fronted by {}", provider)));
+        }
+    }
+    signals
+}
+
+/// Tag a host with the cloud provider its responses point to
+///
+/// # Arguments
+/// * `target` - Hostname or address the host was scanned as
+/// * `results` - Port results of the host, after the metadata checks
+/// * `edge` - CDN or load balancer evidence for the host
+///
+/// # Returns
+/// * `Option<CloudInfo>` - The provider with the most signals, if any
+pub fn infer(target: &str, results: &HashMap<u16, PortResult>, edge: Option<&EdgeInfo>) -> Option<CloudInfo> {
+    let signals = provider_signals(target, results, edge);
+    let mut counts: HashMap<&str, usize> = HashMap::new();
+    for (provider, _) in &signals {
+        *counts.entry(*provider).or_default() += 1;
+    }
+    let provider = counts.into_iter().max_by_key(|(provider, count)| (*count, std::cmp::Reverse(*provider)))?.0;
+
+    let mut ssrf_candidates: Vec<u16> = results.iter()
+        .filter(|(_, r)| r.service_details.as_ref().and_then(|d| d.get("ssrf_candidates")).is_some()
+            || r.vulns.iter().any(|v| v.id.starts_with("CLOUD-METADATA")))
+        .map(|(&port, _)| port)
+        .collect();
+    ssrf_candidates.sort_unstable();
+    Some(CloudInfo {
+        provider: provider.to_string(),
+        evidence: signals.into_iter().filter(|(p, _)| *p == provider).map(|(_, reason)| reason).collect(),
+        metadata_endpoint: metadata_endpoint(provider).to_string(),
+        ssrf_candidates,
+    })
+}
+
+/// Metadata addresses a port's banner or headers name
+fn metadata_references(result: &PortResult) -> BTreeSet<&'static str> {
+    let mut text = result.banner.clone().unwrap_or_default();
+    if let Some(http) = &result.http_info {
+        for (name, value) in &http.headers {
+            text.push_str(&format!("\n{}: {}", name, value));
+        }
+        text.extend(http.redirects.iter().map(|r| format!("\n{}", r)));
+    }
+    let text = text.to_ascii_lowercase();
+    METADATA_REFERENCES.iter().filter(|reference| text.contains(*reference)).copied().collect()
+}
+
+/// Redirect targets that carry a URL in a query parameter
+fn ssrf_candidates(result: &PortResult) -> Vec<String> {
+    let Some(http) = &result.http_info else { return Vec::new() };
+    let mut candidates: Vec<String> = http.redirects.iter().cloned()
+        .filter(|url| {
+            let Some((_, query)) = url.split_once('?') else { return false };
+            query.split('&').any(|pair| {
+                let (key, value) = pair.split_once('=').unwrap_or((pair, ""));
+                let value = value.to_ascii_lowercase();
+                URL_PARAMETERS.contains(&key.to_ascii_lowercase().as_str())
+                    || value.starts_with("http") || value.starts_with("%2f%2f") || value.starts_with("//")
+            })
+        })
+        .collect();
+    candidates.sort();
+    candidates.dedup();
+    candidates
+}
+
+async fn exchange<S: AsyncRead + AsyncWrite + Unpin>(stream: &mut S, request: &[u8], wait: Duration) -> Result<Vec<u8>> {
+    timeout(wait, stream.write_all(request)).await.context("Write timed out")??;
+    let mut response = Vec::new();
+    let mut buf = vec![0u8; 8192];
+    while response.len() < MAX_RESPONSE {
+        match timeout(wait, stream.read(&mut buf)).await {
+            Ok(Ok(0)) | Err(_) => break,
+            Ok(Ok(n)) => response.extend_from_slice(&buf[..n]),
+            Ok(Err(e)) if response.is_empty() => return Err(e.into()),
+            Ok(Err(_)) => break,
+        }
+    }
+    Ok(response)
+}
+
+/// Ask the port for a metadata index, either by `Host` header or as an absolute URL
+async fn fetch_through(addr: SocketAddr, tls: bool, server_name: &str, canary: &Canary, absolute: bool, wait: Duration) -> Result<String> {
+    let target = if absolute { format!("http://{}{}", canary.host, canary.path) } else { canary.path.to_string() };
+    let request = format!("GET {} HTTP/1.1\r\nHost: {}\r\n{}User-Agent: {}\r\nConnection: close\r\n\r\n",
+                          target, canary.host, canary.header, crate::utils::get_random_user_agent(None));
+    let bytes = request.len() + crate::scheduler::TCP_HEADER_BYTES + if tls { TLS_HANDSHAKE_BYTES } else { 0 };
+    crate::scheduler::spend(bytes).await;
+    crate::audit::record(|| crate::audit::Probe::new("cloud-metadata", "tcp", addr.ip(), Some(addr.port()), bytes)
+        .detail(format!("GET {} with Host {}", target, canary.host)));
+
+    let mut stream = timeout(wait, crate::netbind::connect_tcp(addr)).await.context("Connect timed out")??;
+    let response = if tls {
+        let name = crate::ssl_config::convert_to_server_name(server_name)
+            .map_err(|e| anyhow!("Invalid server name '{}': {}", server_name, e))?
+            .to_owned();
+        let connector = tokio_rustls::TlsConnector::from(crate::ssl_config::create_tls_config(false));
+        let mut tls_stream = timeout(wait, connector.connect(name, stream)).await.context("TLS handshake timed out")??;
+        exchange(&mut tls_stream, request.as_bytes(), wait).await?
+    } else {
+        exchange(&mut stream, request.as_bytes(), wait).await?
+    };
+    Ok(String::from_utf8_lossy(&response).into_owned())
+}
+
+/// First canary the port relays, with how it was asked
+async fn probe_port(addr: SocketAddr, tls: bool, server_name: &str, canaries: &[&Canary], wait: Duration) -> Option<(&'static str, &'static str)> {
+    for canary in canaries {
+        for absolute in [false, true] {
+            match fetch_through(addr, tls, server_name, canary, absolute, wait).await {
+                Ok(response) if canary.markers.iter().all(|marker| response.contains(marker)) => {
+                    return Some((canary.provider, if absolute { "absolute URL" } else { "Host header" }));
+                }
+                Ok(_) => {}
+                Err(e) => {
+                    debug!("[Cloud] {} canary through {} failed: {}", canary.provider, addr, e);
+                    break;
+                }
+            }
+        }
+    }
+    None
+}
+
+/// Check the web ports of a host for metadata references and, when allowed, relayed metadata
+///
+/// # Arguments
+/// * `target` - Host to check
+/// * `hostname` - Name to send in SNI; the address is used when not given
+/// * `results` - Port results after detection
+/// * `send_canaries` - Whether metadata requests may be sent (not under `--safe`)
+/// * `wait` - Connect and reply timeout
+///
+/// # Returns
+/// * `Vec<MetadataCheck>` - Findings for every web port that had any
+pub async fn check_metadata(target: IpAddr, hostname: Option<&str>, results: &HashMap<u16, PortResult>, send_canaries: bool, wait: Duration) -> Vec<MetadataCheck> {
+    let mut ports: Vec<(u16, bool)> = results.iter()
+        .filter(|(_, r)| r.tcp_states.values().any(|&s| s == PortStatus::Open))
+        .filter_map(|(&port, r)| {
+            let known = WEB_PORTS.iter().find(|(web, _)| *web == port).map(|(_, tls)| *tls);
+            match (&r.http_info, known) {
+                (Some(_), _) => Some((port, r.cert_info.is_some() || known == Some(true))),
+                (None, known) => known.map(|tls| (port, tls)),
+            }
+        })
+        .collect();
+    ports.sort_unstable();
+
+    // Canaries of the providers the host already points to; every provider when it points nowhere
+    let inferred: BTreeSet<&str> = provider_signals(hostname.unwrap_or(""), results, None).into_iter().map(|(p, _)| p).collect();
+    let canaries: Vec<&Canary> = CANARIES.iter().filter(|c| inferred.is_empty() || inferred.contains(c.provider)).collect();
+    let server_name = hostname.map(str::to_string).unwrap_or_else(|| target.to_string());
+
+    let mut checks = Vec::new();
+    for (port, tls) in ports {
+        let result = &results[&port];
+        let references = metadata_references(result);
+        let candidates = ssrf_candidates(result);
+        let mut details = serde_json::Map::new();
+        let mut vulns = Vec::new();
+
+        if !references.is_empty() {
+            details.insert("cloud_metadata_references".to_string(), json!(references));
+            vulns.push(Finding {
+                id: "CLOUD-METADATA-REFERENCE".to_string(),
+                title: "Cloud metadata service referenced".to_string(),
+                description: format!("Responses on port {} name the cloud metadata service ({}); the application likely fetches from it, so test its URL inputs for SSRF",
+                                     port, references.iter().copied().collect::<Vec<_>>().join(", ")),
+                severity: "Medium".to_string(),
+                confidence: Confidence::Tentative,
+                evidence: references.iter().map(|r| r.to_string()).collect(),
+                references: vec!["https://owasp.org/www-community/attacks/Server_Side_Request_Forgery".to_string()],
+                ..Default::default()
+            });
+        }
+        if !candidates.is_empty() {
+            details.insert("ssrf_candidates".to_string(), json!(candidates));
+        }
+
+        if send_canaries {
+            let addr = SocketAddr::new(target, port);
+            info!("[Cloud] Asking {} for cloud metadata", addr);
+            if let Some((provider, how)) = probe_port(addr, tls, &server_name, &canaries, wait).await {
+                warn!("[Cloud] {} relays the {} metadata service by {}", addr, provider, how);
+                details.insert("cloud_metadata_provider".to_string(), json!(provider));
+                details.insert("cloud_metadata_relay".to_string(), json!(how));
+                vulns.push(Finding {
+                    id: "CLOUD-METADATA-PROXY".to_string(),
+                    title: "Cloud metadata service relayed".to_string(),
+                    description: format!("Port {} forwards requests to the {} instance metadata service when asked by {}; instance credentials are readable by anyone",
+                                         port, provider, how),
+                    severity: "Critical".to_string(),
+                    confidence: Confidence::Confirmed,
+                    evidence: vec![how.to_string()],
+                    references: vec!["https://owasp.org/www-community/attacks/Server_Side_Request_Forgery".to_string()],
+                    ..Default::default()
+                });
+            }
+        }
+
+        if !details.is_empty() {
+            checks.push(MetadataCheck { port, details: serde_json::Value::Object(details), vulns });
+        }
+    }
+    checks
+}
@@ -0,0 +1,474 @@
+//! Container platform detection.
+//!
+//! Docker Engine, etcd, the kubelet and the Kubernetes API server each answer a plain
+//! HTTP(S) `GET` that shows what they are, which version they run and whether they
+//! serve anonymous callers:
+//! - Docker API (2375, 2376 over TLS): `/version`; an answer means the socket is
+//!   reachable without a client certificate, which is root on the host
+//! - etcd (2379): `/version`, then a keys-only range read of one key through the v3
+//!   JSON gateway
+//! - kubelet (10250, 10255 read-only): `/pods`, plus the build info in `/metrics`
+//! - Kubernetes API (6443): `/version`, then a namespace listing
+//!
+//! Every request is a read; nothing is created or changed. Ports that answer are named
+//! after the platform and grouped under the "container-platform" service category.
+
+use std::collections::HashMap;
+use std::net::{IpAddr, SocketAddr};
+use std::time::Duration;
+
+use anyhow::{anyhow, Context, Result};
+use log::{debug, info, warn};
+use serde_json::json;
+use tokio::io::{AsyncRead, AsyncReadExt, AsyncWrite, AsyncWriteExt};
+use tokio::time::timeout;
+
+use crate::models::{Confidence, Finding, PortResult, PortStatus};
+
+/// Service category the platforms are grouped under
+pub const CATEGORY: &str = "container-platform";
+
+/// Estimated size of a TLS handshake, counted against the scan budget
+const TLS_HANDSHAKE_BYTES: usize = 700;
+
+/// Largest response read; kubelet metrics are cut off here
+const MAX_RESPONSE: usize = 256 * 1024;
+
+/// Words in identified service names that mark a container platform
+const SERVICE_KEYWORDS: [&str; 5] = ["docker", "etcd", "kubelet", "kubernetes", "k8s"];
+
+/// Platform components that can be probed
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Platform {
+    Docker,
+    Etcd,
+    Kubelet,
+    KubeletReadOnly,
+    KubernetesApi,
+}
+
+impl Platform {
+    /// Service name given to a port the component answered on
+    fn service(&self) -> &'static str {
+        match self {
+            Platform::Docker => "docker",
+            Platform::Etcd => "etcd",
+            Platform::Kubelet | Platform::KubeletReadOnly => "kubelet",
+            Platform::KubernetesApi => "kubernetes",
+        }
+    }
+
+    /// Component usually listening on `port`, and whether it speaks TLS there
+    fn for_port(port: u16) -> Option<(Self, bool)> {
+        match port {
+            2375 => Some((Platform::Docker, false)),
+            2376 => Some((Platform::Docker, true)),
+            2379 => Some((Platform::Etcd, false)),
+            6443 => Some((Platform::KubernetesApi, true)),
+            10250 => Some((Platform::Kubelet, true)),
+            10255 => Some((Platform::KubeletReadOnly, false)),
+            _ => None,
+        }
+    }
+
+    /// Component named by detection, for platforms found on unusual ports
+    fn for_service(service: &str) -> Option<Self> {
+        let service = service.to_ascii_lowercase();
+        if service.contains("docker") {
+            Some(Platform::Docker)
+        } else if service.contains("etcd") {
+            Some(Platform::Etcd)
+        } else if service.contains("kubelet") {
+            Some(Platform::Kubelet)
+        } else if service.contains("kubernetes") || service.contains("k8s") {
+            Some(Platform::KubernetesApi)
+        } else {
+            None
+        }
+    }
+}
+
+/// How a component treated an anonymous caller
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Access {
+    /// Served the protected resource
+    Anonymous,
+    /// Authenticated the caller as anonymous but denied the resource
+    Forbidden,
+    /// Asked for credentials or a client certificate
+    AuthRequired,
+}
+
+impl Access {
+    fn name(&self) -> &'static str {
+        match self {
+            Access::Anonymous => "anonymous",
+            Access::Forbidden => "anonymous_forbidden",
+            Access::AuthRequired => "auth_required",
+        }
+    }
+
+    fn from_status(status: u16) -> Option<Self> {
+        match status {
+            200..=299 => Some(Access::Anonymous),
+            403 => Some(Access::Forbidden),
+            401 => Some(Access::AuthRequired),
+            _ => None,
+        }
+    }
+}
+
+/// What one container platform port revealed
+#[derive(Debug)]
+pub struct PlatformCheck {
+    pub port: u16,
+    /// Service name for the port
+    pub service: &'static str,
+    pub version: Option<String>,
+    pub details: serde_json::Value,
+    pub vulns: Vec<Finding>,
+}
+
+/// Status code and body of a response
+struct Response {
+    status: u16,
+    body: Vec<u8>,
+}
+
+impl Response {
+    fn json(&self) -> Option<serde_json::Value> {
+        serde_json::from_slice(&self.body).ok()
+    }
+}
+
+/// Connection details shared by the requests to one port
+struct Endpoint<'a> {
+    addr: SocketAddr,
+    tls: bool,
+    host: &'a str,
+    wait: Duration,
+}
+
+impl Endpoint<'_> {
+    async fn get(&self, path: &str) -> Result<Response> {
+        self.request("GET", path, None).await
+    }
+
+    async fn request(&self, method: &str, path: &str, body: Option<&str>) -> Result<Response> {
+        let mut request = format!(
+            "{} {} HTTP/1.1\r\nHost: {}:{}\r\nUser-Agent: {}\r\nAccept: application/json, */*\r\nConnection: close\r\n",
+            method, path, self.host, self.addr.port(), crate::utils::get_random_user_agent(None)
+        );
+        if let Some(body) = body {
+            request.push_str(&format!("Content-Type: application/json\r\nContent-Length: {}\r\n\r\n{}", body.len(), body));
+        } else {
+            request.push_str("\r\n");
+        }
+
+        let bytes = request.len() + crate::scheduler::TCP_HEADER_BYTES + if self.tls { TLS_HANDSHAKE_BYTES } else { 0 };
+        crate::scheduler::spend(bytes).await;
+        crate::audit::record(|| crate::audit::Probe::new("container-platform", "tcp", self.addr.ip(), Some(self.addr.port()), bytes)
+            .detail(format!("{} {}", method, path)));
+
+        let mut stream = timeout(self.wait, crate::netbind::connect_tcp(self.addr)).await.context("Connect timed out")??;
+        let response = if self.tls {
+            let server_name = crate::ssl_config::convert_to_server_name(self.host)
+                .map_err(|e| anyhow!("Invalid server name '{}': {}", self.host, e))?
+                .to_owned();
+            // Cluster components use certificates from a private CA
+            let connector = tokio_rustls::TlsConnector::from(crate::ssl_config::create_tls_config(false));
+            let mut tls = timeout(self.wait, connector.connect(server_name, stream)).await.context("TLS handshake timed out")??;
+            exchange(&mut tls, request.as_bytes(), self.wait).await?
+        } else {
+            exchange(&mut stream, request.as_bytes(), self.wait).await?
+        };
+        parse_response(&response)
+    }
+}
+
+async fn exchange<S: AsyncRead + AsyncWrite + Unpin>(stream: &mut S, request: &[u8], wait: Duration) -> Result<Vec<u8>> {
+    timeout(wait, stream.write_all(request)).await.context("Write timed out")??;
+    let mut response = Vec::new();
+    let mut buf = vec![0u8; 8192];
+    while response.len() < MAX_RESPONSE {
+        match timeout(wait, stream.read(&mut buf)).await {
+            Ok(Ok(0)) | Err(_) => break,
+            Ok(Ok(n)) => response.extend_from_slice(&buf[..n]),
+            Ok(Err(e)) if response.is_empty() => return Err(e.into()),
+            Ok(Err(_)) => break,
+        }
+    }
+    Ok(response)
+}
+
+fn parse_response(response: &[u8]) -> Result<Response> {
+    let split = response.windows(4).position(|w| w == b"\r\n\r\n")
+        .ok_or_else(|| anyhow!("incomplete HTTP response"))?;
+    let head = String::from_utf8_lossy(&response[..split]);
+    if !head.starts_with("HTTP/") {
+        return Err(anyhow!("not an HTTP response"));
+    }
+    let status = head.split_whitespace().nth(1).and_then(|code| code.parse().ok())
+        .ok_or_else(|| anyhow!("malformed status line"))?;
+    let mut body = response[split + 4..].to_vec();
+    if head.lines().any(|line| line.to_ascii_lowercase().starts_with("transfer-encoding:") && line.to_ascii_lowercase().contains("chunked")) {
+        body = dechunk(&body);
+    }
+    Ok(Response { status, body })
+}
+
+/// Join the chunks of a chunked body, keeping whatever arrived before a cut-off
+fn dechunk(body: &[u8]) -> Vec<u8> {
+    let mut out = Vec::new();
+    let mut rest = body;
+    while let Some(end) = rest.windows(2).position(|w| w == b"\r\n") {
+        let size = String::from_utf8_lossy(&rest[..end]);
+        let Ok(size) = usize::from_str_radix(size.split(';').next().unwrap_or("").trim(), 16) else { break };
+        if size == 0 {
+            break;
+        }
+        let start = end + 2;
+        let stop = (start + size).min(rest.len());
+        out.extend_from_slice(&rest[start..stop]);
+        if stop + 2 > rest.len() {
+            break;
+        }
+        rest = &rest[stop + 2..];
+    }
+    out
+}
+
+fn string_field(value: &serde_json::Value, key: &str) -> Option<String> {
+    value.get(key).and_then(|v| v.as_str()).map(str::to_string)
+}
+
+/// Kubernetes build version from a Prometheus `kubernetes_build_info` sample
+fn build_info_version(metrics: &[u8]) -> Option<String> {
+    let text = String::from_utf8_lossy(metrics);
+    let line = text.lines().find(|line| line.starts_with("kubernetes_build_info{"))?;
+    let start = line.find("git_version=\"")? + "git_version=\"".len();
+    let end = line[start..].find('"')?;
+    Some(line[start..start + end].to_string())
+}
+
+/// Anonymous access is only reported once a request has been served, so findings are confirmed
+fn finding(id: &str, title: &str, severity: &str, description: String) -> Finding {
+    Finding {
+        id: id.to_string(),
+        title: title.to_string(),
+        description,
+        severity: severity.to_string(),
+        confidence: Confidence::Confirmed,
+        ..Default::default()
+    }
+}
+
+async fn check_docker(endpoint: &Endpoint<'_>) -> Result<Option<PlatformCheck>> {
+    let response = match endpoint.get("/version").await {
+        Ok(response) => response,
+        // Docker over TLS normally insists on a client certificate
+        Err(e) if endpoint.tls => {
+            debug!("[Container] {} refused the TLS client: {}", endpoint.addr, e);
+            return Ok(Some(PlatformCheck {
+                port: endpoint.addr.port(),
+                service: Platform::Docker.service(),
+                version: None,
+                details: json!({ "container_platform": "docker", "access": Access::AuthRequired.name() }),
+                vulns: Vec::new(),
+            }));
+        }
+        Err(e) => return Err(e),
+    };
+    let Some(info) = response.json().filter(|info| info.get("ApiVersion").is_some()) else {
+        return Ok(None);
+    };
+    let version = string_field(&info, "Version");
+    let details = json!({
+        "container_platform": "docker",
+        "access": Access::Anonymous.name(),
+        "api_version": string_field(&info, "ApiVersion"),
+        "os": string_field(&info, "Os"),
+        "arch": string_field(&info, "Arch"),
+        "kernel": string_field(&info, "KernelVersion"),
+    });
+    let vulns = vec![finding("DOCKER-API-UNAUTHENTICATED", "Unauthenticated Docker API", "Critical", format!(
+        "Docker Engine API {} answers without authentication; anyone reaching it can start privileged containers and take over the host",
+        version.as_deref().unwrap_or("(unknown version)")))];
+    Ok(Some(PlatformCheck { port: endpoint.addr.port(), service: Platform::Docker.service(), version, details, vulns }))
+}
+
+async fn check_etcd(endpoint: &Endpoint<'_>) -> Result<Option<PlatformCheck>> {
+    let Some(info) = endpoint.get("/version").await?.json().filter(|info| info.get("etcdserver").is_some()) else {
+        return Ok(None);
+    };
+    let version = string_field(&info, "etcdserver");
+
+    // Keys-only read of the first key; "AA==" is a zero byte, so the range covers every key
+    let range = r#"{"key":"AA==","range_end":"AA==","limit":1,"keys_only":true}"#;
+    let read = endpoint.request("POST", "/v3/kv/range", Some(range)).await?;
+    let body = read.json().unwrap_or_default();
+    let access = if read.status == 200 && body.get("header").is_some() {
+        Access::Anonymous
+    } else {
+        // "user name is empty" and "permission denied" both come back as errors here
+        debug!("[Container] etcd at {} refused the range read with HTTP {}", endpoint.addr, read.status);
+        Access::AuthRequired
+    };
+
+    let details = json!({
+        "container_platform": "etcd",
+        "access": access.name(),
+        "cluster_version": string_field(&info, "etcdcluster"),
+        "key_count": body.get("count").cloned(),
+    });
+    let vulns = if access == Access::Anonymous {
+        vec![finding("ETCD-UNAUTHENTICATED", "Unauthenticated etcd", "Critical", format!(
+            "etcd {} serves key reads without authentication; a Kubernetes etcd holds every Secret of the cluster",
+            version.as_deref().unwrap_or("(unknown version)")))]
+    } else {
+        Vec::new()
+    };
+    Ok(Some(PlatformCheck { port: endpoint.addr.port(), service: Platform::Etcd.service(), version, details, vulns }))
+}
+
+async fn check_kubelet(endpoint: &Endpoint<'_>, read_only: bool) -> Result<Option<PlatformCheck>> {
+    let pods = endpoint.get("/pods").await?;
+    let Some(access) = Access::from_status(pods.status) else {
+        return Ok(None);
+    };
+    let listing = pods.json();
+    if access == Access::Anonymous && listing.as_ref().and_then(|l| l.get("items")).is_none() {
+        return Ok(None);
+    }
+    let pod_count = listing.as_ref().and_then(|l| l.get("items")).and_then(|items| items.as_array()).map(|items| items.len());
+
+    let version = if access == Access::Anonymous {
+        endpoint.get("/metrics").await.ok().filter(|m| m.status == 200).and_then(|m| build_info_version(&m.body))
+    } else {
+        None
+    };
+    let details = json!({
+        "container_platform": "kubelet",
+        "access": access.name(),
+        "read_only_port": read_only,
+        "pods": pod_count,
+    });
+    let vulns = match (access, read_only) {
+        (Access::Anonymous, false) => vec![finding("KUBELET-ANONYMOUS", "Anonymous kubelet API", "Critical",
+            "Kubelet API serves anonymous requests; pods on the node can be listed and commands run in their containers".to_string())],
+        (Access::Anonymous, true) => vec![finding("KUBELET-READONLY-PORT", "Kubelet read-only port", "High",
+            "Kubelet read-only port is open; pod specs, including environment variables, are readable without authentication".to_string())],
+        _ => Vec::new(),
+    };
+    Ok(Some(PlatformCheck { port: endpoint.addr.port(), service: Platform::Kubelet.service(), version, details, vulns }))
+}
+
+async fn check_kubernetes_api(endpoint: &Endpoint<'_>) -> Result<Option<PlatformCheck>> {
+    let version_response = endpoint.get("/version").await?;
+    let info = version_response.json().filter(|info| info.get("gitVersion").is_some());
+    let namespaces = endpoint.get("/api/v1/namespaces").await?;
+    let listing = namespaces.json();
+    // Only a Kubernetes Status or List object shows this is an API server
+    let is_kubernetes = info.is_some()
+        || listing.as_ref().and_then(|l| l.get("kind")).and_then(|k| k.as_str()).is_some_and(|kind| kind == "Status" || kind == "NamespaceList");
+    if !is_kubernetes {
+        return Ok(None);
+    }
+    let Some(access) = Access::from_status(namespaces.status) else {
+        return Ok(None);
+    };
+    let version = info.as_ref().and_then(|info| string_field(info, "gitVersion"));
+    let namespace_names: Vec<String> = listing.as_ref()
+        .filter(|_| access == Access::Anonymous)
+        .and_then(|l| l.get("items")).and_then(|items| items.as_array())
+        .map(|items| items.iter().filter_map(|item| item.pointer("/metadata/name").and_then(|n| n.as_str()).map(str::to_string)).collect())
+        .unwrap_or_default();
+
+    let details = json!({
+        "container_platform": "kubernetes",
+        "access": access.name(),
+        "version_public": info.is_some(),
+        "platform": info.as_ref().and_then(|info| string_field(info, "platform")),
+        "namespaces": namespace_names,
+    });
+    let vulns = match access {
+        Access::Anonymous => vec![finding("K8S-API-ANONYMOUS", "Anonymous Kubernetes API", "Critical", format!(
+            "Kubernetes API server {} lets anonymous users list namespaces; RBAC grants cluster access to unauthenticated requests",
+            version.as_deref().unwrap_or("(unknown version)")))],
+        _ => Vec::new(),
+    };
+    Ok(Some(PlatformCheck { port: endpoint.addr.port(), service: Platform::KubernetesApi.service(), version, details, vulns }))
+}
+
+/// Ports of a host whose identified service is a container platform
+///
+/// # Arguments
+/// * `results` - Port results of one host
+///
+/// # Returns
+/// * `Vec<u16>` - Sorted ports for the "container-platform" category
+pub fn category_ports(results: &HashMap<u16, PortResult>) -> Vec<u16> {
+    let mut ports: Vec<u16> = results.iter()
+        .filter(|(_, r)| r.service.as_deref().is_some_and(|service| {
+            let service = service.to_ascii_lowercase();
+            SERVICE_KEYWORDS.iter().any(|keyword| service.contains(keyword))
+        }))
+        .map(|(&port, _)| port)
+        .collect();
+    ports.sort_unstable();
+    ports
+}
+
+/// Probe the container platform ports of a host
+///
+/// # Arguments
+/// * `target` - Host to check
+/// * `hostname` - Name to send in `Host` and SNI; the address is used when not given
+/// * `results` - Port results after detection; only open TCP ports are considered
+/// * `wait` - Connect and reply timeout
+///
+/// # Returns
+/// * `Vec<PlatformCheck>` - One entry per port a platform component answered on
+pub async fn check_platforms(target: IpAddr, hostname: Option<&str>, results: &HashMap<u16, PortResult>, wait: Duration) -> Vec<PlatformCheck> {
+    let mut ports: Vec<(u16, Platform, bool)> = results.iter()
+        .filter(|(_, r)| r.tcp_states.values().any(|&s| s == PortStatus::Open))
+        .filter_map(|(&port, r)| {
+            let by_port = Platform::for_port(port);
+            let by_service = r.service.as_deref().and_then(Platform::for_service);
+            match (by_service, by_port) {
+                // The port tells the kubelet's read-only port from its API
+                (Some(platform), Some((by_port, tls))) if by_port.service() == platform.service() => Some((port, by_port, tls)),
+                (Some(platform), Some((_, tls))) => Some((port, platform, tls)),
+                (Some(platform), None) => Some((port, platform, r.cert_info.is_some())),
+                (None, Some((platform, tls))) => Some((port, platform, tls)),
+                (None, None) => None,
+            }
+        })
+        .collect();
+    ports.sort_unstable_by_key(|(port, _, _)| *port);
+
+    let host = hostname.map(str::to_string).unwrap_or_else(|| target.to_string());
+    let mut checks = Vec::new();
+    for (port, platform, tls) in ports {
+        let endpoint = Endpoint { addr: SocketAddr::new(target, port), tls, host: &host, wait };
+        info!("[Container] Probing {} as {}", endpoint.addr, platform.service());
+        let outcome = match platform {
+            Platform::Docker => check_docker(&endpoint).await,
+            Platform::Etcd => check_etcd(&endpoint).await,
+            Platform::Kubelet => check_kubelet(&endpoint, false).await,
+            Platform::KubeletReadOnly => check_kubelet(&endpoint, true).await,
+            Platform::KubernetesApi => check_kubernetes_api(&endpoint).await,
+        };
+        match outcome {
+            Ok(Some(check)) => {
+                for vuln in &check.vulns {
+                    warn!("[Container] {}: {}", endpoint.addr, vuln.description);
+                }
+                checks.push(check);
+            }
+            Ok(None) => debug!("[Container] {} did not answer as {}", endpoint.addr, platform.service()),
+            Err(e) => debug!("[Container] Probe of {} failed: {}", endpoint.addr, e),
+        }
+    }
+    checks
+}
@@ -0,0 +1,447 @@
+//! Response correlation for asymmetric scans.
+//!
+//! Probes carry a keyed tag in their TCP sequence number: a truncated hash of a secret,
+//! the target address, target port and source port. A receiver that knows the secret can
+//! recognise replies to our probes among arbitrary traffic without any record of what
+//! was sent, so sending and receiving can live in different tasks, on different
+//! interfaces, or in different processes (`quantum_scanner collect`). Stateless sweeps
+//! use it today; decoy and idle modes need the same split.
+
+use std::collections::HashMap;
+use std::net::IpAddr;
+use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
+use std::sync::Arc;
+use std::time::Duration;
+
+use anyhow::{anyhow, Result};
+use clap::Parser;
+use lazy_static::lazy_static;
+use log::{debug, info};
+use parking_lot::{Mutex, RwLock};
+use pnet::datalink::{self, Channel};
+use pnet::packet::ethernet::{EtherTypes, EthernetPacket};
+use pnet::packet::ip::IpNextHeaderProtocols;
+use pnet::packet::ipv4::Ipv4Packet;
+use pnet::packet::ipv6::Ipv6Packet;
+use pnet::packet::tcp::{TcpFlags, TcpPacket};
+use pnet::packet::Packet;
+use pnet::transport::{self, transport_channel, TransportChannelType, TransportProtocol};
+use rand::{thread_rng, Rng};
+use serde::Serialize;
+use sha2::{Digest, Sha256};
+
+use crate::models::PortStatus;
+
+/// Keyed probe tag generator shared by senders and receivers
+///
+/// The secret is random per run unless `--correlation-key` supplies one, so tags
+/// cannot be predicted or replayed across scans.
+#[derive(Clone)]
+pub struct ProbeKey {
+    secret: [u8; 16],
+}
+
+impl ProbeKey {
+    /// Create a key with a fresh random secret
+    pub fn new() -> Self {
+        ProbeKey { secret: thread_rng().gen() }
+    }
+
+    /// Parse a 32-hex-digit key as printed by `to_hex`
+    pub fn from_hex(hex: &str) -> Result<Self> {
+        let hex = hex.trim();
+        if hex.len() != 32 || !hex.is_ascii() {
+            return Err(anyhow!("Correlation key must be 32 hex digits"));
+        }
+        let mut secret = [0u8; 16];
+        for (i, byte) in secret.iter_mut().enumerate() {
+            *byte = u8::from_str_radix(&hex[i * 2..i * 2 + 2], 16)
+                .map_err(|_| anyhow!("Correlation key must be 32 hex digits"))?;
+        }
+        Ok(ProbeKey { secret })
+    }
+
+    /// Key as hex, for handing to a separate collector process
+    pub fn to_hex(&self) -> String {
+        self.secret.iter().map(|b| format!("{:02x}", b)).collect()
+    }
+
+    /// Sequence number to put in a probe
+    pub fn tag(&self, target_ip: IpAddr, target_port: u16, source_port: u16) -> u32 {
+        let mut hasher = Sha256::new();
+        hasher.update(self.secret);
+        match target_ip {
+            IpAddr::V4(ip) => hasher.update(ip.octets()),
+            IpAddr::V6(ip) => hasher.update(ip.octets()),
+        }
+        hasher.update(target_port.to_be_bytes());
+        hasher.update(source_port.to_be_bytes());
+        let digest = hasher.finalize();
+        u32::from_be_bytes([digest[0], digest[1], digest[2], digest[3]])
+    }
+
+    /// Check that a reply answers a probe we tagged
+    ///
+    /// SYN-ACK and RST replies to a SYN acknowledge `tag + 1`; RSTs to segments without
+    /// SYN acknowledge `tag` (or `tag + 1` for FIN) or echo our acknowledgement number,
+    /// which taggers set to the tag as well.
+    pub fn validate(&self, target_ip: IpAddr, target_port: u16, source_port: u16, seq: u32, ack: u32) -> bool {
+        let tag = self.tag(target_ip, target_port, source_port);
+        ack == tag.wrapping_add(1) || ack == tag || seq == tag
+    }
+}
+
+impl Default for ProbeKey {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+lazy_static! {
+    /// Key used to tag probes this run
+    static ref PROBE_KEY: RwLock<ProbeKey> = RwLock::new(ProbeKey::new());
+    /// Interface replies are collected on when it differs from the sending path
+    static ref CAPTURE_SOURCE: RwLock<CaptureSource> = RwLock::new(CaptureSource::RawSocket);
+}
+
+/// Use a fixed key (shared with a separate collector) instead of a random one
+pub fn set_probe_key(key: ProbeKey) {
+    *PROBE_KEY.write() = key;
+}
+
+/// Key used to tag probes this run
+pub fn probe_key() -> ProbeKey {
+    PROBE_KEY.read().clone()
+}
+
+/// Collect replies from a different source than the sending socket
+pub fn set_capture_source(source: CaptureSource) {
+    *CAPTURE_SOURCE.write() = source;
+}
+
+/// Where replies are collected from
+pub fn capture_source() -> CaptureSource {
+    CAPTURE_SOURCE.read().clone()
+}
+
+/// Where the correlator listens
+#[derive(Debug, Clone, PartialEq)]
+pub enum CaptureSource {
+    /// Raw IPv4 TCP socket: sees replies routed to this host
+    RawSocket,
+    /// Promiscuous capture on an interface: sees replies addressed elsewhere too,
+    /// e.g. to a spoofed source or on a return path separate from the send path
+    Interface(String),
+}
+
+/// A validated reply to one of our probes
+#[derive(Debug, Clone, Serialize)]
+pub struct CorrelatedReply {
+    pub target: IpAddr,
+    pub port: u16,
+    /// Address the reply was sent to (ours, or a spoofed/decoy source)
+    pub destination: IpAddr,
+    pub source_port: u16,
+    pub flags: u8,
+    pub window: u16,
+    pub ttl: u8,
+    /// Open for SYN-ACK, closed for RST; `None` for other flag combinations
+    pub status: Option<PortStatus>,
+}
+
+impl CorrelatedReply {
+    /// Reason string in the style of the other scan engines
+    pub fn reason(&self) -> String {
+        match self.status {
+            Some(PortStatus::Open) => "syn-ack (correlated)".to_string(),
+            Some(PortStatus::Closed) => "rst (correlated)".to_string(),
+            _ => format!("flags {:#04x} (correlated)", self.flags),
+        }
+    }
+}
+
+/// Which replies a correlator keeps
+#[derive(Debug, Clone, Default)]
+pub struct ReplyFilter {
+    /// Only replies from these targets (empty = any)
+    pub targets: Vec<IpAddr>,
+    /// Only replies to this source port (None = any)
+    pub source_port: Option<u16>,
+}
+
+impl ReplyFilter {
+    fn accepts(&self, target: IpAddr, destination_port: u16) -> bool {
+        (self.targets.is_empty() || self.targets.contains(&target))
+            && self.source_port.map_or(true, |port| port == destination_port)
+    }
+}
+
+/// Everything a correlator saw by the time it was stopped
+#[derive(Debug, Default)]
+pub struct CorrelationResults {
+    /// First validated reply per (target, port)
+    pub replies: HashMap<(IpAddr, u16), CorrelatedReply>,
+    /// Replies that failed tag validation (stray traffic or spoofing)
+    pub invalid_replies: usize,
+}
+
+/// Background receiver validating replies against a probe key
+pub struct Correlator {
+    replies: Arc<Mutex<HashMap<(IpAddr, u16), CorrelatedReply>>>,
+    invalid: Arc<AtomicUsize>,
+    done: Arc<AtomicBool>,
+    handle: tokio::task::JoinHandle<()>,
+}
+
+impl Correlator {
+    /// Start receiving
+    ///
+    /// # Arguments
+    /// * `key` - Key the probes were tagged with
+    /// * `source` - Raw socket or interface to capture on
+    /// * `filter` - Which replies to keep
+    ///
+    /// # Returns
+    /// * `Result<Self>` - Running correlator, or an error if the capture could not be opened
+    ///
+    /// # Opsec Considerations
+    /// - Interface capture puts the NIC in promiscuous mode, which some EDR products flag
+    /// - Purely passive: nothing is sent
+    pub fn start(key: ProbeKey, source: CaptureSource, filter: ReplyFilter) -> Result<Self> {
+        let replies = Arc::new(Mutex::new(HashMap::new()));
+        let invalid = Arc::new(AtomicUsize::new(0));
+        let done = Arc::new(AtomicBool::new(false));
+
+        let mut next_packet = open_capture(&source)?;
+        info!("[Correlate] Collecting replies via {:?}", source);
+
+        let handle = {
+            let (replies, invalid, done) = (replies.clone(), invalid.clone(), done.clone());
+            tokio::task::spawn_blocking(move || {
+                while !done.load(Ordering::Relaxed) {
+                    let reply = match next_packet() {
+                        Some(reply) => reply,
+                        None => continue,
+                    };
+                    let RawTcpReply { source, destination, source_port, destination_port, seq, ack, flags, window, ttl } = reply;
+                    if !filter.accepts(source, destination_port) {
+                        continue;
+                    }
+                    if !key.validate(source, source_port, destination_port, seq, ack) {
+                        invalid.fetch_add(1, Ordering::Relaxed);
+                        continue;
+                    }
+
+                    let status = if flags & TcpFlags::SYN != 0 && flags & TcpFlags::ACK != 0 {
+                        Some(PortStatus::Open)
+                    } else if flags & TcpFlags::RST != 0 {
+                        Some(PortStatus::Closed)
+                    } else {
+                        None
+                    };
+                    debug!("[Correlate] {}:{} -> {} flags {:#04x} ({:?})", source, source_port, destination, flags, status);
+
+                    // First validated reply wins; duplicates are retransmissions
+                    replies.lock().entry((source, source_port)).or_insert(CorrelatedReply {
+                        target: source,
+                        port: source_port,
+                        destination,
+                        source_port: destination_port,
+                        flags,
+                        window,
+                        ttl,
+                        status,
+                    });
+                }
+            })
+        };
+
+        Ok(Correlator { replies, invalid, done, handle })
+    }
+
+    /// Replies validated so far
+    pub fn snapshot(&self) -> Vec<CorrelatedReply> {
+        self.replies.lock().values().cloned().collect()
+    }
+
+    /// Stop receiving and return everything collected
+    pub async fn stop(self) -> Result<CorrelationResults> {
+        self.done.store(true, Ordering::Relaxed);
+        self.handle.await.map_err(|e| anyhow!("Correlator task failed: {}", e))?;
+        let replies = std::mem::take(&mut *self.replies.lock());
+        Ok(CorrelationResults { replies, invalid_replies: self.invalid.load(Ordering::Relaxed) })
+    }
+}
+
+/// TCP header fields the correlator needs, from any capture source
+struct RawTcpReply {
+    source: IpAddr,
+    destination: IpAddr,
+    source_port: u16,
+    destination_port: u16,
+    seq: u32,
+    ack: u32,
+    flags: u8,
+    window: u16,
+    ttl: u8,
+}
+
+impl RawTcpReply {
+    fn from_tcp(source: IpAddr, destination: IpAddr, ttl: u8, tcp: &TcpPacket) -> Self {
+        RawTcpReply {
+            source,
+            destination,
+            source_port: tcp.get_source(),
+            destination_port: tcp.get_destination(),
+            seq: tcp.get_sequence(),
+            ack: tcp.get_acknowledgement(),
+            flags: tcp.get_flags(),
+            window: tcp.get_window(),
+            ttl,
+        }
+    }
+}
+
+/// Capture poll: returns the next TCP packet or `None` after a short idle timeout
+type NextPacket = Box<dyn FnMut() -> Option<RawTcpReply> + Send>;
+
+fn open_capture(source: &CaptureSource) -> Result<NextPacket> {
+    match source {
+        CaptureSource::RawSocket => {
+            let (_, mut rx) = transport_channel(
+                1 << 20,
+                TransportChannelType::Layer4(TransportProtocol::Ipv4(IpNextHeaderProtocols::Tcp)),
+            ).map_err(|e| anyhow!("Failed to open raw receive socket (requires root/admin privileges): {}", e))?;
+            // The Layer4 iterator does not expose the IP header, so TTL and the local
+            // destination address are unknown here
+            Ok(Box::new(move || {
+                let mut iter = transport::tcp_packet_iter(&mut rx);
+                match iter.next_with_timeout(Duration::from_millis(100)) {
+                    Ok(Some((packet, addr))) => {
+                        let unspecified = IpAddr::V4(std::net::Ipv4Addr::UNSPECIFIED);
+                        Some(RawTcpReply::from_tcp(addr, unspecified, 0, &packet))
+                    }
+                    Ok(None) => None,
+                    Err(e) => {
+                        debug!("[Correlate] Receive error: {}", e);
+                        None
+                    }
+                }
+            }))
+        }
+        CaptureSource::Interface(name) => {
+            let iface = datalink::interfaces()
+                .into_iter()
+                .find(|iface| &iface.name == name)
+                .ok_or_else(|| anyhow!("No such interface: {}", name))?;
+            let config = datalink::Config {
+                read_timeout: Some(Duration::from_millis(100)),
+                promiscuous: true,
+                ..Default::default()
+            };
+            let mut rx = match datalink::channel(&iface, config) {
+                Ok(Channel::Ethernet(_, rx)) => rx,
+                Ok(_) => return Err(anyhow!("Unsupported datalink channel type on {}", iface.name)),
+                Err(e) => return Err(anyhow!("Failed to open capture on {} (requires root/admin privileges): {}", iface.name, e)),
+            };
+            Ok(Box::new(move || {
+                let frame = rx.next().ok()?;
+                parse_frame(frame)
+            }))
+        }
+    }
+}
+
+/// Extract a TCP segment from an Ethernet frame
+fn parse_frame(frame: &[u8]) -> Option<RawTcpReply> {
+    let ethernet = EthernetPacket::new(frame)?;
+    match ethernet.get_ethertype() {
+        EtherTypes::Ipv4 => {
+            let ip = Ipv4Packet::new(ethernet.payload())?;
+            if ip.get_next_level_protocol() != IpNextHeaderProtocols::Tcp {
+                return None;
+            }
+            let tcp = TcpPacket::new(ip.payload())?;
+            Some(RawTcpReply::from_tcp(IpAddr::V4(ip.get_source()), IpAddr::V4(ip.get_destination()), ip.get_ttl(), &tcp))
+        }
+        EtherTypes::Ipv6 => {
+            let ip = Ipv6Packet::new(ethernet.payload())?;
+            if ip.get_next_header() != IpNextHeaderProtocols::Tcp {
+                return None;
+            }
+            let tcp = TcpPacket::new(ip.payload())?;
+            Some(RawTcpReply::from_tcp(IpAddr::V6(ip.get_source()), IpAddr::V6(ip.get_destination()), ip.get_hop_limit(), &tcp))
+        }
+        _ => None,
+    }
+}
+
+/// Command line for collector mode
+#[derive(Parser, Debug)]
+#[clap(
+    name = "quantum_collect",
+    about = "Collect and correlate replies to probes sent by another quantum scanner process",
+    after_help = "EXAMPLES:
+    # Sender on one host/interface, collector on the return path
+    quantum_scanner 10.0.0.0/24 --stateless --correlation-key 00112233445566778899aabbccddeeff
+    quantum_scanner collect --key 00112233445566778899aabbccddeeff --iface eth1 --duration 120"
+)]
+pub struct CollectArgs {
+    /// Correlation key the sender was started with (--correlation-key)
+    #[clap(long)]
+    pub key: String,
+
+    /// Interface to capture on (default: raw socket for replies routed to this host)
+    #[clap(long)]
+    pub iface: Option<String>,
+
+    /// Only keep replies from these targets (comma-separated)
+    #[clap(long, value_delimiter = ',')]
+    pub target: Vec<IpAddr>,
+
+    /// Stop after this many seconds (default: run until interrupted)
+    #[clap(long)]
+    pub duration: Option<u64>,
+}
+
+/// Run the collector, printing each validated reply as a JSON line
+///
+/// # Arguments
+/// * `args` - Parsed collector command line
+///
+/// # Returns
+/// * `Result<()>` - Error if the capture could not be opened
+///
+/// # Opsec Considerations
+/// - Sends nothing; only the sender's probes are visible to the target
+pub async fn run_collect(args: CollectArgs) -> Result<()> {
+    let key = ProbeKey::from_hex(&args.key)?;
+    let source = args.iface.clone().map_or(CaptureSource::RawSocket, CaptureSource::Interface);
+    let correlator = Correlator::start(key, source, ReplyFilter { targets: args.target.clone(), source_port: None })?;
+
+    let deadline = args.duration.map(|secs| tokio::time::Instant::now() + Duration::from_secs(secs));
+    let mut printed = std::collections::HashSet::new();
+    loop {
+        for reply in correlator.snapshot() {
+            if printed.insert((reply.target, reply.port)) {
+                println!("{}", serde_json::to_string(&reply)?);
+            }
+        }
+        if deadline.map_or(false, |d| tokio::time::Instant::now() >= d) {
+            break;
+        }
+        tokio::select! {
+            _ = tokio::time::sleep(Duration::from_millis(500)) => {}
+            _ = tokio::signal::ctrl_c() => break,
+        }
+    }
+
+    let results = correlator.stop().await?;
+    for reply in results.replies.values() {
+        if printed.insert((reply.target, reply.port)) {
+            println!("{}", serde_json::to_string(reply)?);
+        }
+    }
+    info!("[Correlate] Collected {} replies, {} failed validation", printed.len(), results.invalid_replies);
+    Ok(())
+}
@@ -0,0 +1,158 @@
+//! Cover traffic: benign-looking requests interleaved with scan probes.
+//!
+//! With `--cover-traffic <ratio>` every probe the scanner launches earns `ratio` credit;
+//! each whole unit of credit fires one background request to a popular public site,
+//! either a DNS lookup through the system resolver or an HTTPS GET of the front page.
+//! The requests are real, so a sensor watching our egress sees the scan mixed into
+//! ordinary browsing rather than as an isolated burst.
+
+use std::sync::Arc;
+use std::time::Duration;
+
+use anyhow::{anyhow, Context, Result};
+use lazy_static::lazy_static;
+use log::debug;
+use parking_lot::{Mutex, RwLock};
+use rand::seq::SliceRandom;
+use rand::Rng;
+use tokio::io::{AsyncReadExt, AsyncWriteExt};
+use tokio::time::timeout;
+
+/// Sites whose traffic is common enough to be unremarkable on most networks
+const COVER_DOMAINS: &[&str] = &[
+    "www.google.com", "www.youtube.com", "www.facebook.com", "www.wikipedia.org",
+    "www.amazon.com", "www.microsoft.com", "www.apple.com", "www.linkedin.com",
+    "www.bing.com", "www.office.com", "outlook.live.com", "www.github.com",
+    "www.reddit.com", "www.netflix.com", "www.cloudflare.com", "www.yahoo.com",
+];
+
+/// Time allowed for one cover request before it is abandoned
+const COVER_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// Most of a front page we bother reading before closing the connection
+const MAX_COVER_READ: usize = 64 * 1024;
+
+/// Our side of a TLS handshake (ClientHello, key share, Finished), charged to the bandwidth budget
+const TLS_HANDSHAKE_BYTES: usize = 700;
+/// One A or AAAA query for a cover domain, headers included
+const DNS_QUERY_BYTES: usize = crate::scheduler::UDP_HEADER_BYTES + 12 + 4 + 2;
+
+/// Kind of benign request to emit
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum CoverKind {
+    Dns,
+    Https,
+}
+
+/// Cover traffic settings
+#[derive(Debug, Clone, Copy)]
+pub struct CoverTrafficConfig {
+    /// Cover requests per scan probe (0.5 = one request every two probes)
+    pub ratio: f64,
+    /// Share of cover requests that are DNS lookups; the rest are HTTPS GETs
+    pub dns_share: f64,
+}
+
+struct CoverState {
+    config: CoverTrafficConfig,
+    credit: Mutex<f64>,
+}
+
+lazy_static! {
+    static ref COVER: RwLock<Option<Arc<CoverState>>> = RwLock::new(None);
+}
+
+/// Enable cover traffic for the rest of the scan
+pub fn set_cover_traffic(config: CoverTrafficConfig) {
+    *COVER.write() = Some(Arc::new(CoverState { config, credit: Mutex::new(0.0) }));
+}
+
+/// Account for one scan probe and launch any cover requests it has earned.
+///
+/// Requests run in the background; the probe is never delayed by them.
+pub fn on_probe() {
+    let state = match COVER.read().clone() {
+        Some(state) => state,
+        None => return,
+    };
+
+    let due = {
+        let mut credit = state.credit.lock();
+        *credit += state.config.ratio;
+        let due = credit.floor();
+        *credit -= due;
+        due as usize
+    };
+
+    for _ in 0..due {
+        let kind = if crate::utils::scan_rng().gen_bool(state.config.dns_share.clamp(0.0, 1.0)) {
+            CoverKind::Dns
+        } else {
+            CoverKind::Https
+        };
+        let domain = *COVER_DOMAINS.choose(&mut crate::utils::scan_rng()).unwrap();
+        tokio::spawn(async move {
+            // Small random offset so cover requests don't line up exactly with probes
+            let jitter = crate::utils::scan_rng().gen_range(0..1500);
+            tokio::time::sleep(Duration::from_millis(jitter)).await;
+            match cover_request(kind, domain).await {
+                Ok(()) => debug!("[Cover] {:?} request to {} done", kind, domain),
+                Err(e) => debug!("[Cover] {:?} request to {} failed: {}", kind, domain, e),
+            }
+        });
+    }
+}
+
+/// Issue one benign request
+async fn cover_request(kind: CoverKind, domain: &str) -> Result<()> {
+    match kind {
+        CoverKind::Dns => {
+            // The resolver asks for A and AAAA
+            crate::scheduler::spend(2 * (DNS_QUERY_BYTES + domain.len())).await;
+            crate::audit::record(|| crate::audit::Probe::new("cover", "dns", domain, None, 2 * (DNS_QUERY_BYTES + domain.len()))
+                .detail("A and AAAA lookup through the system resolver"));
+            timeout(COVER_TIMEOUT, tokio::net::lookup_host((domain, 443)))
+                .await
+                .context("DNS lookup timed out")??;
+            Ok(())
+        }
+        CoverKind::Https => https_get(domain).await,
+    }
+}
+
+/// Fetch a site's front page the way a browser would start to
+async fn https_get(domain: &str) -> Result<()> {
+    crate::scheduler::spend(TLS_HANDSHAKE_BYTES).await;
+    crate::audit::record(|| crate::audit::Probe::new("cover", "tcp", domain, Some(443), TLS_HANDSHAKE_BYTES)
+        .detail("HTTPS connection and TLS handshake"));
+    let stream = timeout(COVER_TIMEOUT, crate::netbind::connect_tcp_host(domain, 443))
+        .await
+        .context("Connect timed out")??;
+    let server_name = crate::ssl_config::convert_to_server_name(domain)
+        .map_err(|e| anyhow!("Invalid server name '{}': {}", domain, e))?
+        .to_owned();
+    let connector = tokio_rustls::TlsConnector::from(crate::ssl_config::create_tls_config(true));
+    let mut tls = timeout(COVER_TIMEOUT, connector.connect(server_name, stream))
+        .await
+        .context("TLS handshake timed out")??;
+
+    let request = format!(
+        "GET / HTTP/1.1\r\nHost: {}\r\nUser-Agent: {}\r\nAccept: text/html,application/xhtml+xml,application/xml;q=0.9,*/*;q=0.8\r\nAccept-Language: en-US,en;q=0.9\r\nConnection: close\r\n\r\n",
+        domain, crate::utils::get_random_user_agent(None)
+    );
+    crate::scheduler::spend(request.len() + crate::scheduler::TCP_HEADER_BYTES).await;
+    crate::audit::record(|| crate::audit::Probe::new("cover", "tcp", domain, Some(443), request.len() + crate::scheduler::TCP_HEADER_BYTES)
+        .detail("GET /"));
+    tls.write_all(request.as_bytes()).await?;
+
+    let mut buf = vec![0u8; 8192];
+    let mut total = 0;
+    while total < MAX_COVER_READ {
+        match timeout(COVER_TIMEOUT, tls.read(&mut buf)).await {
+            Ok(Ok(0)) | Err(_) => break,
+            Ok(Ok(n)) => total += n,
+            Ok(Err(e)) => return Err(e.into()),
+        }
+    }
+    Ok(())
+}
@@ -0,0 +1,499 @@
+//! Default credential checks (opt-in).
+//!
+//! With `--check-default-creds`, a small list of vendor default logins is tried against
+//! the admin interfaces detection found: FTP, Telnet, HTTP basic auth and SNMP
+//! communities. Every accepted login is recorded as a critical vulnerability.
+//!
+//! This is the loudest thing the scanner can do, so it is deliberately conservative:
+//! - ports are checked one at a time, with a fixed delay between attempts
+//! - each service gets at most `max_attempts` tries and stops at the first success
+//! - anything that looks like a lockout (FTP 421, HTTP 429, "too many attempts" text,
+//!   repeated connection failures) stops that service immediately
+//!
+//! The credential list comes from `--creds-file` when given, one `service user password`
+//! per line (`snmp community` for SNMP, `""` for an empty value).
+
+use std::collections::HashMap;
+use std::net::{IpAddr, SocketAddr};
+use std::path::Path;
+use std::sync::Arc;
+use std::time::Duration;
+
+use anyhow::{anyhow, Context, Result};
+use base64::Engine as _;
+use lazy_static::lazy_static;
+use log::{debug, info, warn};
+use parking_lot::RwLock;
+use rand::Rng;
+use tokio::io::AsyncReadExt;
+use tokio::net::TcpStream;
+use tokio::time::{sleep, timeout, Instant};
+
+use crate::models::{Confidence, Finding, PortResult, PortStatus};
+
+/// Consecutive connection failures treated as a lockout or ban
+const MAX_CONNECT_FAILURES: usize = 2;
+
+/// Built-in defaults, kept short on purpose: this is a misconfiguration check, not a
+/// password attack
+const BUILTIN_CREDENTIALS: &str = "\
+ftp anonymous anonymous@
+ftp ftp ftp
+ftp admin admin
+ftp root root
+telnet admin admin
+telnet root root
+telnet admin password
+telnet root \"\"
+telnet admin 1234
+http admin admin
+http admin password
+http admin \"\"
+http root root
+http tomcat tomcat
+snmp public
+snmp private
+";
+
+/// Services credentials are tried against
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum CredService {
+    Ftp,
+    Telnet,
+    HttpBasic,
+    Snmp,
+}
+
+impl std::fmt::Display for CredService {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(match self {
+            CredService::Ftp => "FTP",
+            CredService::Telnet => "Telnet",
+            CredService::HttpBasic => "HTTP basic auth",
+            CredService::Snmp => "SNMP",
+        })
+    }
+}
+
+/// One login to try; SNMP uses `username` for the community and no password
+#[derive(Debug, Clone)]
+pub struct Credential {
+    pub service: CredService,
+    pub username: String,
+    pub password: String,
+}
+
+/// Default credential check settings
+#[derive(Debug, Clone)]
+pub struct CredCheckConfig {
+    pub credentials: Vec<Credential>,
+    /// Pause between attempts against the same service
+    pub delay: Duration,
+    /// Attempts per service before giving up
+    pub max_attempts: usize,
+    /// Connect and response timeout per attempt
+    pub timeout: Duration,
+}
+
+impl CredCheckConfig {
+    /// Built-in credential list with the given limits
+    pub fn builtin(delay: Duration, max_attempts: usize, timeout: Duration) -> Self {
+        CredCheckConfig {
+            credentials: parse_credentials(BUILTIN_CREDENTIALS).expect("built-in credential list must parse"),
+            delay,
+            max_attempts,
+            timeout,
+        }
+    }
+
+    /// Replace the credential list with the contents of a file
+    pub fn load_credentials(&mut self, path: &Path) -> Result<()> {
+        let text = std::fs::read_to_string(path)
+            .with_context(|| format!("Failed to read credential file {}", path.display()))?;
+        self.credentials = parse_credentials(&text).with_context(|| path.display().to_string())?;
+        Ok(())
+    }
+
+    fn for_service(&self, service: CredService) -> impl Iterator<Item = &Credential> {
+        self.credentials.iter().filter(move |c| c.service == service).take(self.max_attempts)
+    }
+}
+
+/// Parse `service user password` lines
+fn parse_credentials(text: &str) -> Result<Vec<Credential>> {
+    let unquote = |value: &str| if value == "\"\"" { String::new() } else { value.to_string() };
+    let mut credentials = Vec::new();
+    for (index, line) in text.lines().enumerate() {
+        let line = line.trim();
+        if line.is_empty() || line.starts_with('#') {
+            continue;
+        }
+        let fields: Vec<&str> = line.split_whitespace().collect();
+        let service = match fields[0].to_ascii_lowercase().as_str() {
+            "ftp" => CredService::Ftp,
+            "telnet" => CredService::Telnet,
+            "http" => CredService::HttpBasic,
+            "snmp" => CredService::Snmp,
+            other => return Err(anyhow!("line {}: unknown service '{}'", index + 1, other)),
+        };
+        let (username, password) = match (service, &fields[1..]) {
+            (CredService::Snmp, [community]) => (unquote(community), String::new()),
+            (CredService::Snmp, _) => return Err(anyhow!("line {}: expected 'snmp <community>'", index + 1)),
+            (_, [username, password]) => (unquote(username), unquote(password)),
+            _ => return Err(anyhow!("line {}: expected '<service> <username> <password>'", index + 1)),
+        };
+        credentials.push(Credential { service, username, password });
+    }
+    Ok(credentials)
+}
+
+lazy_static! {
+    /// Active configuration; `None` unless `--check-default-creds` was given
+    static ref CONFIG: RwLock<Option<Arc<CredCheckConfig>>> = RwLock::new(None);
+}
+
+/// Enable (or disable with `None`) default credential checks
+pub fn set_config(config: Option<CredCheckConfig>) {
+    *CONFIG.write() = config.map(Arc::new);
+}
+
+/// Current configuration, if checks are enabled
+pub fn config() -> Option<Arc<CredCheckConfig>> {
+    CONFIG.read().clone()
+}
+
+/// Result of one login attempt
+#[derive(Debug)]
+enum Attempt {
+    Accepted,
+    Rejected,
+    /// The service signalled rate limiting or lockout; stop trying it
+    Lockout(String),
+}
+
+/// What the checks found on one host
+#[derive(Debug, Default)]
+pub struct CredCheckReport {
+    /// Accepted logins, as critical vulnerabilities per port
+    pub findings: Vec<(u16, Finding)>,
+    /// Services where checks stopped early, with the reason
+    pub notes: Vec<(u16, String)>,
+}
+
+/// Which service to check on an open port, judged by detection results and port number
+fn service_for(port: u16, result: &PortResult) -> Option<CredService> {
+    let service = result.service.as_deref().unwrap_or("").to_ascii_lowercase();
+    if service == "ftp" || (service.is_empty() && port == 21) {
+        Some(CredService::Ftp)
+    } else if service == "telnet" || (service.is_empty() && (port == 23 || port == 2323)) {
+        Some(CredService::Telnet)
+    } else if service.starts_with("http") && !service.starts_with("https") {
+        Some(CredService::HttpBasic)
+    } else {
+        None
+    }
+}
+
+/// Try default credentials against a host's admin interfaces
+///
+/// # Arguments
+/// * `target` - Host to check
+/// * `results` - Port results after detection; only open TCP ports are considered
+/// * `snmp_port` - UDP port to try SNMP communities on, when SNMP was in scope
+/// * `config` - Credential list and safety limits
+///
+/// # Returns
+/// * `CredCheckReport` - Accepted logins and any services cut short
+///
+/// # Opsec Considerations
+/// Failed logins are logged by nearly every service and often alerted on. Only run this
+/// where testing credentials is explicitly authorized.
+pub async fn check_default_creds(
+    target: IpAddr,
+    results: &HashMap<u16, PortResult>,
+    snmp_port: Option<u16>,
+    config: &CredCheckConfig,
+) -> CredCheckReport {
+    let mut report = CredCheckReport::default();
+
+    let mut targets: Vec<(u16, CredService)> = results.iter()
+        .filter(|(_, r)| r.tcp_states.values().any(|&s| s == PortStatus::Open))
+        .filter_map(|(&port, r)| service_for(port, r).map(|service| (port, service)))
+        .collect();
+    targets.sort_unstable_by_key(|(port, _)| *port);
+    if let Some(port) = snmp_port {
+        targets.push((port, CredService::Snmp));
+    }
+
+    for (port, service) in targets {
+        let addr = SocketAddr::new(target, port);
+        if service == CredService::HttpBasic && !http_wants_basic_auth(addr, config.timeout).await {
+            debug!("[Creds] {} does not ask for basic auth, skipping", addr);
+            continue;
+        }
+        info!("[Creds] Trying up to {} default {} credentials on {}", config.max_attempts, service, addr);
+
+        let mut connect_failures = 0;
+        for (attempt, credential) in config.for_service(service).enumerate() {
+            if attempt > 0 {
+                // Jitter keeps attempts from landing on an exact, recognisable interval
+                let jitter = crate::utils::scan_rng().gen_range(0..=config.delay.as_millis() as u64 / 4);
+                sleep(config.delay + Duration::from_millis(jitter)).await;
+            }
+
+            let outcome = match service {
+                CredService::Ftp => try_ftp(addr, credential, config.timeout).await,
+                CredService::Telnet => try_telnet(addr, credential, config.timeout).await,
+                CredService::HttpBasic => try_http_basic(addr, credential, config.timeout).await,
+                CredService::Snmp => try_snmp(addr, credential, config.timeout).await,
+            };
+
+            match outcome {
+                Ok(Attempt::Accepted) => {
+                    let secret = if service == CredService::Snmp { &credential.username } else { &credential.password };
+                    crate::redaction::register(crate::redaction::RedactCategory::Credentials, secret);
+                    let login = if service == CredService::Snmp {
+                        format!("community '{}'", credential.username)
+                    } else {
+                        format!("{} / {}", credential.username, if credential.password.is_empty() { "<empty>" } else { &credential.password })
+                    };
+                    warn!("[Creds] {} on {} accepts default credentials: {}", service, addr, login);
+                    report.findings.push((port, Finding {
+                        id: "DEFAULT-CREDENTIALS".to_string(),
+                        title: "Default credentials accepted".to_string(),
+                        description: format!("{} accepts default credentials {}", service, login),
+                        severity: "Critical".to_string(),
+                        confidence: Confidence::Confirmed,
+                        evidence: vec![login.clone()],
+                        ..Default::default()
+                    }));
+                    break;
+                }
+                Ok(Attempt::Rejected) => connect_failures = 0,
+                Ok(Attempt::Lockout(reason)) => {
+                    warn!("[Creds] Stopping {} checks on {}: {}", service, addr, reason);
+                    report.notes.push((port, format!("Default credential checks stopped: {}", reason)));
+                    break;
+                }
+                Err(e) => {
+                    debug!("[Creds] {} attempt on {} failed: {}", service, addr, e);
+                    connect_failures += 1;
+                    if connect_failures >= MAX_CONNECT_FAILURES {
+                        report.notes.push((port, format!("Default credential checks stopped after {} failed connections", connect_failures)));
+                        break;
+                    }
+                }
+            }
+        }
+    }
+    report
+}
+
+/// Read until one of `needles` appears (case-insensitive) or the timeout expires
+async fn read_until(stream: &mut TcpStream, needles: &[&str], wait: Duration, telnet: bool) -> Result<String> {
+    let deadline = Instant::now() + wait;
+    let mut text = String::new();
+    let mut buffer = [0u8; 2048];
+    loop {
+        let remaining = deadline.saturating_duration_since(Instant::now());
+        if remaining.is_zero() {
+            return Ok(text);
+        }
+        let n = match timeout(remaining, stream.read(&mut buffer)).await {
+            Ok(Ok(0)) | Err(_) => return Ok(text),
+            Ok(Ok(n)) => n,
+            Ok(Err(e)) => return Err(e.into()),
+        };
+        let data = if telnet { refuse_telnet_options(stream, &buffer[..n]).await? } else { buffer[..n].to_vec() };
+        text.push_str(&String::from_utf8_lossy(&data));
+        let lower = text.to_ascii_lowercase();
+        if needles.iter().any(|needle| lower.contains(needle)) {
+            return Ok(text);
+        }
+    }
+}
+
+/// Strip Telnet option negotiation, refusing every option the server asks about
+async fn refuse_telnet_options(stream: &mut TcpStream, data: &[u8]) -> Result<Vec<u8>> {
+    const IAC: u8 = 255;
+    const WILL: u8 = 251;
+    const WONT: u8 = 252;
+    const DO: u8 = 253;
+    const DONT: u8 = 254;
+
+    let mut text = Vec::with_capacity(data.len());
+    let mut replies = Vec::new();
+    let mut i = 0;
+    while i < data.len() {
+        if data[i] != IAC || i + 1 >= data.len() {
+            text.push(data[i]);
+            i += 1;
+            continue;
+        }
+        match data[i + 1] {
+            DO | DONT | WILL | WONT if i + 2 < data.len() => {
+                match data[i + 1] {
+                    DO => replies.extend_from_slice(&[IAC, WONT, data[i + 2]]),
+                    WILL => replies.extend_from_slice(&[IAC, DONT, data[i + 2]]),
+                    _ => {}
+                }
+                i += 3;
+            }
+            IAC => {
+                text.push(IAC);
+                i += 2;
+            }
+            _ => i += 2,
+        }
+    }
+    if !replies.is_empty() {
+        crate::scheduler::metered_write(stream, &replies).await?;
+    }
+    Ok(text)
+}
+
+/// Lockout wording shared by Telnet and FTP servers
+fn mentions_lockout(text: &str) -> bool {
+    let lower = text.to_ascii_lowercase();
+    ["locked", "too many", "try again later", "temporarily blocked"].iter().any(|w| lower.contains(w))
+}
+
+async fn connect(addr: SocketAddr, wait: Duration) -> Result<TcpStream> {
+    timeout(wait, crate::netbind::connect_tcp(addr)).await
+        .map_err(|_| anyhow!("connect timed out"))?
+        .map_err(Into::into)
+}
+
+async fn try_ftp(addr: SocketAddr, credential: &Credential, wait: Duration) -> Result<Attempt> {
+    let mut stream = connect(addr, wait).await?;
+    let greeting = read_until(&mut stream, &["\n"], wait, false).await?;
+    if greeting.starts_with("421") {
+        return Ok(Attempt::Lockout(format!("server refused connection: {}", greeting.trim())));
+    }
+
+    crate::scheduler::metered_write(&mut stream, format!("USER {}\r\n", credential.username).as_bytes()).await?;
+    let reply = read_until(&mut stream, &["\n"], wait, false).await?;
+    match reply.get(..3) {
+        Some("230") => return Ok(Attempt::Accepted),
+        Some("331") => {}
+        Some("421") => return Ok(Attempt::Lockout(reply.trim().to_string())),
+        _ => return Ok(Attempt::Rejected),
+    }
+
+    crate::scheduler::metered_write(&mut stream, format!("PASS {}\r\n", credential.password).as_bytes()).await?;
+    let reply = read_until(&mut stream, &["\n"], wait, false).await?;
+    let _ = crate::scheduler::metered_write(&mut stream, b"QUIT\r\n").await;
+    Ok(match reply.get(..3) {
+        Some("230") => Attempt::Accepted,
+        Some("421") => Attempt::Lockout(reply.trim().to_string()),
+        _ if mentions_lockout(&reply) => Attempt::Lockout(reply.trim().to_string()),
+        _ => Attempt::Rejected,
+    })
+}
+
+async fn try_telnet(addr: SocketAddr, credential: &Credential, wait: Duration) -> Result<Attempt> {
+    let mut stream = connect(addr, wait).await?;
+    let banner = read_until(&mut stream, &["login:", "username:", "user:"], wait, true).await?;
+    if mentions_lockout(&banner) {
+        return Ok(Attempt::Lockout("server reports lockout".to_string()));
+    }
+    if !["login:", "username:", "user:"].iter().any(|p| banner.to_ascii_lowercase().contains(p)) {
+        return Err(anyhow!("no login prompt"));
+    }
+
+    crate::scheduler::metered_write(&mut stream, format!("{}\r\n", credential.username).as_bytes()).await?;
+    read_until(&mut stream, &["password:"], wait, true).await?;
+    crate::scheduler::metered_write(&mut stream, format!("{}\r\n", credential.password).as_bytes()).await?;
+    let reply = read_until(&mut stream, &["$", "#", ">", "login:", "incorrect", "failed", "denied", "invalid"], wait, true).await?;
+
+    let lower = reply.to_ascii_lowercase();
+    if mentions_lockout(&reply) {
+        return Ok(Attempt::Lockout("server reports lockout".to_string()));
+    }
+    if ["login:", "incorrect", "failed", "denied", "invalid"].iter().any(|w| lower.contains(w)) {
+        return Ok(Attempt::Rejected);
+    }
+    let prompt = reply.trim_end();
+    Ok(if prompt.ends_with('$') || prompt.ends_with('#') || prompt.ends_with('>') {
+        Attempt::Accepted
+    } else {
+        Attempt::Rejected
+    })
+}
+
+/// Send a GET / and return the status code and response head
+async fn http_get(addr: SocketAddr, authorization: Option<&str>, wait: Duration) -> Result<(u16, String)> {
+    let mut stream = connect(addr, wait).await?;
+    let mut request = format!("GET / HTTP/1.0\r\nHost: {}\r\nUser-Agent: Mozilla/5.0\r\n", addr.ip());
+    if let Some(value) = authorization {
+        request.push_str(&format!("Authorization: Basic {}\r\n", value));
+    }
+    request.push_str("Connection: close\r\n\r\n");
+    crate::scheduler::metered_write(&mut stream, request.as_bytes()).await?;
+
+    let head = read_until(&mut stream, &["\r\n\r\n"], wait, false).await?;
+    let status = head.split_whitespace().nth(1)
+        .and_then(|code| code.parse().ok())
+        .ok_or_else(|| anyhow!("no HTTP status line"))?;
+    Ok((status, head))
+}
+
+/// Whether the root page asks for HTTP basic authentication
+async fn http_wants_basic_auth(addr: SocketAddr, wait: Duration) -> bool {
+    match http_get(addr, None, wait).await {
+        Ok((401, head)) => head.to_ascii_lowercase().contains("www-authenticate: basic"),
+        _ => false,
+    }
+}
+
+async fn try_http_basic(addr: SocketAddr, credential: &Credential, wait: Duration) -> Result<Attempt> {
+    let token = base64::engine::general_purpose::STANDARD.encode(format!("{}:{}", credential.username, credential.password));
+    let (status, _) = http_get(addr, Some(&token), wait).await?;
+    Ok(match status {
+        200..=399 => Attempt::Accepted,
+        429 => Attempt::Lockout("HTTP 429 Too Many Requests".to_string()),
+        _ => Attempt::Rejected,
+    })
+}
+
+/// BER type-length-value
+fn tlv(tag: u8, content: &[u8]) -> Vec<u8> {
+    let mut out = vec![tag];
+    if content.len() < 0x80 {
+        out.push(content.len() as u8);
+    } else {
+        out.extend_from_slice(&[0x82, (content.len() >> 8) as u8, content.len() as u8]);
+    }
+    out.extend_from_slice(content);
+    out
+}
+
+/// SNMPv1 GetRequest for sysDescr.0 with the given community
+fn snmp_get_sysdescr(community: &str, request_id: u32) -> Vec<u8> {
+    // 1.3.6.1.2.1.1.1.0
+    let varbind = tlv(0x30, &[tlv(0x06, &[0x2b, 6, 1, 2, 1, 1, 1, 0]), vec![0x05, 0x00]].concat());
+    let pdu = tlv(0xa0, &[
+        tlv(0x02, &request_id.to_be_bytes()),
+        tlv(0x02, &[0]),
+        tlv(0x02, &[0]),
+        tlv(0x30, &varbind),
+    ].concat());
+    tlv(0x30, &[tlv(0x02, &[0]), tlv(0x04, community.as_bytes()), pdu].concat())
+}
+
+async fn try_snmp(addr: SocketAddr, credential: &Credential, wait: Duration) -> Result<Attempt> {
+    let socket = crate::netbind::bind_udp(addr.ip()).await?;
+    let request_id: u32 = crate::utils::scan_rng().gen_range(1..0x7fff_ffff);
+    let request = snmp_get_sysdescr(&credential.username, request_id);
+    crate::scheduler::spend(request.len() + crate::scheduler::UDP_HEADER_BYTES).await;
+    crate::audit::record(|| crate::audit::Probe::new("default-creds", "udp", addr.ip(), Some(addr.port()), request.len() + crate::scheduler::UDP_HEADER_BYTES)
+        .detail("SNMP GET with a default community"));
+    socket.send_to(&request, addr).await?;
+
+    // Agents silently drop requests with a wrong community, so any GetResponse is a hit
+    let mut buffer = [0u8; 2048];
+    match timeout(wait, socket.recv_from(&mut buffer)).await {
+        Ok(Ok((n, from))) if from == addr && n > 2 && buffer[0] == 0x30 && buffer[..n].contains(&0xa2) => Ok(Attempt::Accepted),
+        _ => Ok(Attempt::Rejected),
+    }
+}
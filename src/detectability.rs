@@ -0,0 +1,198 @@
+//! Heuristic detectability estimate of a configured scan.
+//!
+//! Before anything is sent, the techniques, rate, port and host counts and evasion
+//! settings are weighed against what common network IDS rules and portscan detectors
+//! key on (Snort/Suricata flag signatures, sfPortscan-style thresholds, service logs).
+//! The result is a score from 0 to 100 with one line per contributing factor, so an
+//! operator sees why a configuration is loud before running it. The weights are rules
+//! of thumb, not measurements against a particular sensor.
+
+use std::fmt;
+
+use crate::models::ScanType;
+use crate::scheduler::TimingModel;
+
+/// Score every configuration starts from: any scan leaves some trace
+const BASE_SCORE: i32 = 10;
+
+/// What the assessment looks at
+#[derive(Clone)]
+pub struct ScanProfile<'a> {
+    pub scan_types: &'a [ScanType],
+    pub ports: usize,
+    pub hosts: usize,
+    /// `--rate`, 0 for unlimited
+    pub rate: usize,
+    pub concurrency: usize,
+    pub timing_model: Option<TimingModel>,
+    /// Stage names of the evasion pipeline, e.g. `ttl-jitter`, `frag`
+    pub evasion_stages: Vec<String>,
+    pub stateless: bool,
+    pub service_scan: bool,
+    pub default_creds: bool,
+    pub scripts: bool,
+    pub icmp_probes: bool,
+    pub auto_technique: bool,
+    pub cover_traffic: bool,
+    pub tcp_segment: bool,
+}
+
+/// One reason the score went up or down
+#[derive(Debug, Clone)]
+pub struct Factor {
+    pub points: i32,
+    pub reason: String,
+}
+
+/// Detectability score with its explanation
+#[derive(Debug, Clone)]
+pub struct Assessment {
+    /// 0 (quiet) to 100 (certain to be noticed)
+    pub score: u8,
+    pub factors: Vec<Factor>,
+}
+
+impl Assessment {
+    /// Label for the score band
+    pub fn level(&self) -> &'static str {
+        match self.score {
+            0..=29 => "low",
+            30..=59 => "moderate",
+            60..=79 => "high",
+            _ => "very high",
+        }
+    }
+}
+
+impl fmt::Display for Assessment {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(f, "Estimated detectability: {}/100 ({})", self.score, self.level())?;
+        for factor in &self.factors {
+            writeln!(f, "  {:+4}  {}", factor.points, factor.reason)?;
+        }
+        write!(f, "Heuristic estimate; actual detection depends on the sensors and how they are tuned.")
+    }
+}
+
+/// Mean gap between probes of a timing model, in milliseconds
+fn mean_gap_ms(model: &TimingModel) -> f64 {
+    match *model {
+        TimingModel::Uniform { min, max } => (min + max) as f64 / 2.0,
+        TimingModel::Poisson { rate } => 1000.0 / rate.max(f64::MIN_POSITIVE),
+        TimingModel::LogNormal { median, sigma } => median * (sigma * sigma / 2.0).exp(),
+        TimingModel::Diurnal { mean } => mean,
+    }
+}
+
+/// Score a scan configuration
+///
+/// # Arguments
+/// * `profile` - Techniques, volume and evasion settings of the scan
+///
+/// # Returns
+/// * `Assessment` - Score and the factors behind it, largest contributions first
+pub fn assess(profile: &ScanProfile) -> Assessment {
+    let mut factors = Vec::new();
+    let mut add = |points: i32, reason: String| factors.push(Factor { points, reason });
+
+    // Techniques: illegal flag combinations are what stealth-scan signatures match
+    for scan_type in profile.scan_types {
+        match scan_type {
+            ScanType::Xmas => add(25, "XMAS scans (FIN+PSH+URG) match stealth-scan signatures in nearly every NIDS ruleset".into()),
+            ScanType::Null => add(20, "NULL scans send TCP segments without flags, which no real stack does; most NIDS flag them".into()),
+            ScanType::Fin => add(15, "FIN scans send FINs outside any connection, a classic stealth-scan signature".into()),
+            ScanType::Maimon => add(15, "Maimon scans send FIN/ACKs outside any connection, which stateful sensors flag as out-of-state".into()),
+            ScanType::Ack | ScanType::Window => add(10, format!("{} scans send ACKs without a connection; stateful firewalls log them as out-of-state", scan_type)),
+            ScanType::BadSum => add(10, "Bad-checksum probes are discarded by hosts but stand out to sensors that verify checksums".into()),
+            ScanType::Frag => add(10, "Tiny IP fragments are flagged by fragment reassembly preprocessors (e.g. Snort frag3)".into()),
+            ScanType::IpProto => add(10, "IP protocol scans send rarely seen protocol numbers".into()),
+            ScanType::Ssl | ScanType::Mimic => add(10, format!("{} scans complete TCP connections that the services themselves log", scan_type)),
+            ScanType::DnsTunnel => add(5, "DNS tunnel probes use long high-entropy query names that DNS analytics flag".into()),
+            ScanType::Syn => add(5, "SYN scans are the common case; whether they are noticed depends on rate and port count".into()),
+            ScanType::Udp => add(5, "UDP probes to closed ports draw ICMP unreachables that some sensors count".into()),
+            ScanType::IcmpTunnel | ScanType::HttpTunnel | ScanType::QuicTunnel | ScanType::Passive => {}
+        }
+    }
+
+    // Rate: threshold-based portscan detection counts probes per source over a window
+    let rate_cap = (profile.rate > 0).then_some(profile.rate as f64);
+    let model_rate = profile.timing_model.as_ref().map(|model| 1000.0 / mean_gap_ms(model).max(0.001));
+    match rate_cap.into_iter().chain(model_rate).reduce(f64::min) {
+        None => add(25, format!("No rate limit: up to {} probes in flight per host, far above portscan thresholds", profile.concurrency)),
+        Some(pps) if pps > 1000.0 => add(25, format!("Rate {:.0} pps exceeds typical portscan thresholds many times over", pps)),
+        Some(pps) if pps > 100.0 => add(15, format!("Rate {:.0} pps exceeds stealth thresholds (tens of probes per minute)", pps)),
+        Some(pps) if pps > 10.0 => add(5, format!("Rate {:.0} pps trips portscan detectors with short windows", pps)),
+        Some(pps) if pps <= 1.0 => add(-10, format!("Rate {:.2} pps stays below most threshold-based portscan detection", pps)),
+        Some(_) => {}
+    }
+
+    // Volume: detectors count distinct ports per host and distinct hosts per source
+    match profile.ports {
+        ports if ports > 1000 => add(15, format!("{} ports per host; touching many ports is what portscan detectors count", ports)),
+        ports if ports > 100 => add(5, format!("{} ports per host", ports)),
+        ports if ports <= 10 => add(-5, format!("Only {} ports per host", ports)),
+        _ => {}
+    }
+    match profile.hosts {
+        hosts if hosts > 256 => add(10, format!("{} hosts: a horizontal sweep from one source is easy to correlate", hosts)),
+        hosts if hosts > 16 => add(5, format!("{} hosts scanned from one source", hosts)),
+        _ => {}
+    }
+
+    // Extra phases that talk to services
+    if profile.stateless {
+        add(10, "Stateless sweeps send SYNs at line rate from a single source port".into());
+    }
+    if profile.service_scan {
+        add(10, "Service detection (-sV) connects and sends protocol probes that services log".into());
+    }
+    if profile.default_creds {
+        add(20, "Default credential checks produce failed logins in authentication logs and SIEM alerts".into());
+    }
+    if profile.scripts {
+        add(5, "Scripts open connections and send their own payloads".into());
+    }
+    if profile.icmp_probes {
+        add(5, "ICMP timestamp and address mask requests are rare and flagged by some rulesets".into());
+    }
+    if profile.auto_technique {
+        add(5, "Technique selection sends FIN and ACK probes to every host before the scan".into());
+    }
+
+    // Evasion settings
+    let stage = |name: &str| profile.evasion_stages.iter().any(|s| s == name);
+    let raw_tcp = profile.scan_types.iter().any(|t| matches!(t, ScanType::Syn | ScanType::Ack | ScanType::Fin | ScanType::Xmas | ScanType::Null | ScanType::Window | ScanType::Maimon | ScanType::BadSum));
+    if stage("timing") || profile.timing_model.is_some() {
+        add(-10, "Randomised probe timing blurs rate thresholds and periodicity detection".into());
+    }
+    if stage("tcp-opts") {
+        add(-5, "OS TCP fingerprints make probes look like ordinary clients".into());
+    } else if raw_tcp {
+        add(5, "Raw probes use window 1024 with only an MSS option, a well-known scanner fingerprint".into());
+    }
+    if stage("ttl-jitter") {
+        add(-3, "TTL jitter avoids a constant scanner TTL".into());
+    }
+    if stage("pad") {
+        add(-3, "Padding gives probes realistic sizes".into());
+    }
+    if stage("frag") {
+        add(5, "Fragmented probes are flagged by fragment reassembly preprocessors".into());
+    }
+    if stage("badsum") {
+        add(10, "Corrupted checksums stand out to sensors that verify them".into());
+    }
+    if stage("flags") {
+        add(15, "Illegal flag combinations match anomaly signatures".into());
+    }
+    if profile.tcp_segment {
+        add(-5, "Payloads split into tiny segments slip past signatures that do not reassemble streams".into());
+    }
+    if profile.cover_traffic {
+        add(-5, "Cover traffic buries the scan among benign requests".into());
+    }
+
+    factors.sort_by_key(|factor| -factor.points.abs());
+    let score = (BASE_SCORE + factors.iter().map(|f| f.points).sum::<i32>()).clamp(0, 100) as u8;
+    Assessment { score, factors }
+}
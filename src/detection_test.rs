@@ -0,0 +1,190 @@
+//! Blue-team detection coverage test (`--detection-test`).
+//!
+//! Instead of one scan, the target is scanned once per technique, from the quietest to
+//! the loudest as `detectability` rates them, with a pause between stages. Every stage
+//! records when it started and ended, what it sent and what the heuristic expected a
+//! sensor to notice. The blue team lines those windows up with their IDS alerts to see
+//! which techniques their sensors catch and which slip through.
+//!
+//! For probe-level correlation, run with `--audit-log`: every record carries the
+//! technique and a timestamp inside one of the stage windows.
+
+use std::fmt;
+use std::net::IpAddr;
+use std::time::Duration;
+
+use chrono::{DateTime, Utc};
+use log::{info, warn};
+use serde::Serialize;
+
+use crate::detectability::{self, Assessment, ScanProfile};
+use crate::models::ScanType;
+use crate::scanner::QuantumScanner;
+
+/// Techniques a detection test runs through, before ordering by detectability
+pub const SPECTRUM: [ScanType; 12] = [
+    ScanType::Syn, ScanType::Ack, ScanType::Window, ScanType::Fin, ScanType::Maimon, ScanType::Null,
+    ScanType::Xmas, ScanType::Frag, ScanType::BadSum, ScanType::Udp, ScanType::Ssl, ScanType::Mimic,
+];
+
+/// Default pause between stages, so alerts fall into separate windows
+pub const DEFAULT_STAGE_GAP_SECS: u64 = 60;
+
+/// One technique of the test, with what it is expected to trigger
+#[derive(Debug, Clone)]
+pub struct PlannedStage {
+    pub technique: ScanType,
+    pub expected: Assessment,
+}
+
+/// Order the techniques of `profile` from stealthiest to noisiest
+///
+/// Each technique is scored alone, with the rest of the configuration (rate, ports,
+/// evasion, service detection) as it will run.
+pub fn plan(profile: &ScanProfile) -> Vec<PlannedStage> {
+    let mut stages: Vec<PlannedStage> = profile.scan_types.iter()
+        .map(|technique| PlannedStage {
+            technique: *technique,
+            expected: detectability::assess(&ScanProfile {
+                scan_types: std::slice::from_ref(technique),
+                ..profile.clone()
+            }),
+        })
+        .collect();
+    // Stable, so equally rated techniques keep the spectrum's order
+    stages.sort_by_key(|stage| stage.expected.score);
+    stages
+}
+
+/// What one stage sent and when
+#[derive(Debug, Clone, Serialize)]
+pub struct StageRecord {
+    pub stage: usize,
+    pub technique: ScanType,
+    pub started_at: DateTime<Utc>,
+    pub finished_at: DateTime<Utc>,
+    /// Heuristic detectability of the technique alone, 0-100
+    pub expected_score: u8,
+    pub expected_level: String,
+    /// Why the technique is expected to be noticed (or not)
+    pub expected_reasons: Vec<String>,
+    pub packets_sent: usize,
+    pub bytes_sent: u64,
+    pub open_ports: Vec<u16>,
+    /// Why the stage failed, if it did
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub error: Option<String>,
+}
+
+/// Per-technique record of a detection test, for correlation with IDS alerts
+#[derive(Debug, Clone, Serialize)]
+pub struct DetectionTestReport {
+    pub target: String,
+    pub target_ip: String,
+    /// Address the probes were sent from, as the sensor will see it
+    pub source_ip: Option<IpAddr>,
+    pub run_id: Option<String>,
+    pub stage_gap_secs: u64,
+    pub stages: Vec<StageRecord>,
+}
+
+impl fmt::Display for DetectionTestReport {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(f, "Detection test against {} ({})", self.target, self.target_ip)?;
+        if let Some(source) = self.source_ip {
+            writeln!(f, "Source address: {}", source)?;
+        }
+        if let Some(run_id) = &self.run_id {
+            writeln!(f, "Run ID: {}", run_id)?;
+        }
+        writeln!(f, "Stages ran {}s apart, quietest first. Times are UTC.", self.stage_gap_secs)?;
+        writeln!(f)?;
+        writeln!(f, "{:>2}  {:<8}  {:<12}  {:<12}  {:>8}  {:>7}  {:>5}  Expected", "#", "Technique", "Start", "End", "Packets", "Bytes", "Open")?;
+        for stage in &self.stages {
+            writeln!(f, "{:>2}  {:<8}  {:<12}  {:<12}  {:>8}  {:>7}  {:>5}  {}/100 ({})",
+                     stage.stage, stage.technique.to_string(), stage.started_at.format("%H:%M:%S%.3f"),
+                     stage.finished_at.format("%H:%M:%S%.3f"), stage.packets_sent, stage.bytes_sent,
+                     stage.open_ports.len(), stage.expected_score, stage.expected_level)?;
+        }
+        for stage in &self.stages {
+            writeln!(f)?;
+            writeln!(f, "Stage {} - {}: {} to {}", stage.stage, stage.technique,
+                     stage.started_at.format("%Y-%m-%d %H:%M:%S%.3f"), stage.finished_at.format("%Y-%m-%d %H:%M:%S%.3f"))?;
+            if let Some(error) = &stage.error {
+                writeln!(f, "  Failed: {}", error)?;
+            }
+            for reason in &stage.expected_reasons {
+                writeln!(f, "  - {}", reason)?;
+            }
+        }
+        write!(f, "\nMark each stage detected or missed from the IDS alerts raised in its window.")
+    }
+}
+
+/// Scan the target once per planned stage, quietest first
+///
+/// # Arguments
+/// * `scanner` - Scanner set up for the target; its scan types are replaced per stage
+/// * `stages` - Techniques in the order to run them, from `plan`
+/// * `source_ip` - Local address the probes leave from
+/// * `gap` - Pause between stages
+///
+/// # Returns
+/// * `DetectionTestReport` - One record per stage; a failed stage is recorded, not fatal
+pub async fn run(scanner: &mut QuantumScanner, stages: Vec<PlannedStage>, source_ip: Option<IpAddr>, gap: Duration) -> DetectionTestReport {
+    let target_ip = scanner.target_ip();
+    let mut report = DetectionTestReport {
+        target: String::new(),
+        target_ip: target_ip.to_string(),
+        source_ip,
+        run_id: crate::metadata::current().map(|meta| meta.run_id),
+        stage_gap_secs: gap.as_secs(),
+        stages: Vec::new(),
+    };
+
+    let count = stages.len();
+    for (index, planned) in stages.into_iter().enumerate() {
+        if index > 0 {
+            info!("[Detection test] Waiting {}s before the next stage", gap.as_secs());
+            tokio::time::sleep(gap).await;
+        }
+        info!("[Detection test] Stage {}/{}: {} (expected detectability {}/100)",
+              index + 1, count, planned.technique, planned.expected.score);
+        scanner.set_scan_types(vec![planned.technique]);
+        let started_at = Utc::now();
+        let outcome = scanner.run_scan().await;
+        let finished_at = Utc::now();
+
+        let mut record = StageRecord {
+            stage: index + 1,
+            technique: planned.technique,
+            started_at,
+            finished_at,
+            expected_score: planned.expected.score,
+            expected_level: planned.expected.level().to_string(),
+            expected_reasons: planned.expected.factors.iter().map(|factor| factor.reason.clone()).collect(),
+            packets_sent: 0,
+            bytes_sent: 0,
+            open_ports: Vec::new(),
+            error: None,
+        };
+        match outcome {
+            Ok(results) => {
+                report.target = results.target.clone();
+                record.packets_sent = results.packets_sent;
+                record.bytes_sent = results.bytes_sent;
+                record.open_ports = results.open_ports.iter().copied().collect();
+                record.open_ports.sort_unstable();
+            }
+            Err(e) => {
+                warn!("[Detection test] Stage {} ({}) failed: {}", index + 1, planned.technique, e);
+                record.error = Some(e.to_string());
+            }
+        }
+        report.stages.push(record);
+    }
+    if report.target.is_empty() {
+        report.target = report.target_ip.clone();
+    }
+    report
+}
@@ -0,0 +1,305 @@
+//! DNS server configuration audit.
+//!
+//! A DNS server found on port 53 is asked three things a misconfigured server gives away:
+//! - `version.bind` (CHAOS TXT), which names the software and often its exact version
+//! - a recursive query for a name outside its zones; an answer with recursion available
+//!   makes it an open resolver, usable for cache poisoning and DNS amplification
+//! - a zone transfer (AXFR, over TCP) of the target's domains: the domain of a hostname
+//!   target plus any given with `--dns-zones`
+//!
+//! What was learned goes to the port's `service_details`; problems are vulnerabilities.
+//! Transferred records are counted, not kept.
+
+use std::net::{IpAddr, SocketAddr};
+use std::sync::Arc;
+use std::time::Duration;
+
+use anyhow::{anyhow, Context, Result};
+use lazy_static::lazy_static;
+use log::{debug, info, warn};
+use parking_lot::RwLock;
+use rand::Rng;
+use serde_json::json;
+use tokio::io::{AsyncReadExt, AsyncWriteExt};
+use tokio::time::timeout;
+
+use crate::models::{Confidence, Finding};
+
+/// Name resolved to test recursion; outside any zone the target could be authoritative for
+const RECURSION_PROBE_NAME: &str = "www.example.com";
+
+/// Records counted before a transfer is cut short
+const MAX_AXFR_RECORDS: usize = 10_000;
+
+const TYPE_A: u16 = 1;
+const TYPE_SOA: u16 = 6;
+const TYPE_TXT: u16 = 16;
+const TYPE_AXFR: u16 = 252;
+const CLASS_IN: u16 = 1;
+const CLASS_CHAOS: u16 = 3;
+
+lazy_static! {
+    /// Extra zones to try transfers of, from `--dns-zones`
+    static ref ZONES: RwLock<Arc<Vec<String>>> = RwLock::new(Arc::new(Vec::new()));
+}
+
+/// Set the zones zone transfers are attempted for, besides the target's own domain
+pub fn set_zones(zones: Vec<String>) {
+    let zones = zones.into_iter()
+        .map(|zone| zone.trim().trim_end_matches('.').to_ascii_lowercase())
+        .filter(|zone| !zone.is_empty())
+        .collect();
+    *ZONES.write() = Arc::new(zones);
+}
+
+/// Build a query with one question
+fn build_query(id: u16, name: &str, qtype: u16, qclass: u16, recursion_desired: bool) -> Vec<u8> {
+    let mut packet = Vec::with_capacity(name.len() + 18);
+    packet.extend_from_slice(&id.to_be_bytes());
+    packet.extend_from_slice(&(if recursion_desired { 0x0100u16 } else { 0 }).to_be_bytes());
+    packet.extend_from_slice(&[0, 1, 0, 0, 0, 0, 0, 0]);
+    for label in name.split('.').filter(|label| !label.is_empty()) {
+        packet.push(label.len().min(63) as u8);
+        packet.extend_from_slice(&label.as_bytes()[..label.len().min(63)]);
+    }
+    packet.push(0);
+    packet.extend_from_slice(&qtype.to_be_bytes());
+    packet.extend_from_slice(&qclass.to_be_bytes());
+    packet
+}
+
+/// Position after the (possibly compressed) name starting at `pos`
+fn skip_name(data: &[u8], mut pos: usize) -> Option<usize> {
+    loop {
+        let len = *data.get(pos)? as usize;
+        if len == 0 {
+            return Some(pos + 1);
+        }
+        if len & 0xc0 == 0xc0 {
+            return Some(pos + 2);
+        }
+        pos += 1 + len;
+    }
+}
+
+/// A parsed reply: header fields and the answer records as (type, rdata)
+#[derive(Debug)]
+struct Response {
+    recursion_available: bool,
+    rcode: u8,
+    answers: Vec<(u16, Vec<u8>)>,
+}
+
+fn parse_response(data: &[u8], id: u16) -> Option<Response> {
+    if data.len() < 12 || u16::from_be_bytes([data[0], data[1]]) != id || data[2] & 0x80 == 0 {
+        return None;
+    }
+    let questions = u16::from_be_bytes([data[4], data[5]]);
+    let answer_count = u16::from_be_bytes([data[6], data[7]]);
+    let mut pos = 12;
+    for _ in 0..questions {
+        pos = skip_name(data, pos)? + 4;
+    }
+    let mut answers = Vec::new();
+    for _ in 0..answer_count {
+        pos = skip_name(data, pos)?;
+        let header = data.get(pos..pos + 10)?;
+        let rtype = u16::from_be_bytes([header[0], header[1]]);
+        let rdlength = u16::from_be_bytes([header[8], header[9]]) as usize;
+        pos += 10;
+        answers.push((rtype, data.get(pos..pos + rdlength)?.to_vec()));
+        pos += rdlength;
+    }
+    Some(Response {
+        recursion_available: data[3] & 0x80 != 0,
+        rcode: data[3] & 0x0f,
+        answers,
+    })
+}
+
+/// Text of a TXT record: its character strings, joined
+fn txt_text(rdata: &[u8]) -> String {
+    let mut text = String::new();
+    let mut pos = 0;
+    while let Some(&len) = rdata.get(pos) {
+        let end = (pos + 1 + len as usize).min(rdata.len());
+        text.push_str(&String::from_utf8_lossy(&rdata[pos + 1..end]));
+        pos = end;
+    }
+    text
+}
+
+/// Send one query over UDP and wait for its reply
+async fn query_udp(target: IpAddr, port: u16, packet: &[u8], id: u16, wait: Duration) -> Result<Option<Response>> {
+    let socket = crate::netbind::bind_udp(target).await?;
+    socket.connect(SocketAddr::new(target, port)).await?;
+    crate::scheduler::spend(packet.len() + crate::scheduler::UDP_HEADER_BYTES).await;
+    crate::audit::record(|| crate::audit::Probe::new("dns-audit", "udp", target, Some(port), packet.len() + crate::scheduler::UDP_HEADER_BYTES));
+    socket.send(packet).await?;
+
+    let mut buf = vec![0u8; 4096];
+    let deadline = tokio::time::Instant::now() + wait;
+    loop {
+        let remaining = deadline.saturating_duration_since(tokio::time::Instant::now());
+        match timeout(remaining, socket.recv(&mut buf)).await {
+            Ok(Ok(len)) => {
+                if let Some(response) = parse_response(&buf[..len], id) {
+                    return Ok(Some(response));
+                }
+            }
+            Ok(Err(e)) => return Err(anyhow!("DNS receive failed: {}", e)),
+            Err(_) => return Ok(None),
+        }
+    }
+}
+
+/// Try a zone transfer over TCP, returning the number of records received
+async fn try_axfr(target: IpAddr, port: u16, zone: &str, wait: Duration) -> Result<Option<usize>> {
+    let id: u16 = crate::utils::scan_rng().gen();
+    let query = build_query(id, zone, TYPE_AXFR, CLASS_IN, false);
+    let mut framed = (query.len() as u16).to_be_bytes().to_vec();
+    framed.extend_from_slice(&query);
+
+    let mut stream = timeout(wait, crate::netbind::connect_tcp(SocketAddr::new(target, port))).await
+        .context("AXFR connect timed out")??;
+    crate::audit::record(|| crate::audit::Probe::new("dns-audit", "tcp", target, Some(port), framed.len() + crate::scheduler::TCP_HEADER_BYTES)
+        .detail(format!("AXFR {}", zone)));
+    crate::scheduler::metered_write(&mut stream, &framed).await?;
+
+    // A transfer is a series of messages, opened and closed by the zone's SOA
+    let mut records = 0;
+    let mut soa_seen = 0;
+    while records < MAX_AXFR_RECORDS && soa_seen < 2 {
+        let mut length = [0u8; 2];
+        match timeout(wait, stream.read_exact(&mut length)).await {
+            Ok(Ok(_)) => {}
+            _ => break,
+        }
+        let mut message = vec![0u8; u16::from_be_bytes(length) as usize];
+        timeout(wait, stream.read_exact(&mut message)).await.context("AXFR read timed out")??;
+        let Some(response) = parse_response(&message, id) else { break };
+        if response.rcode != 0 || response.answers.is_empty() {
+            return Ok(None);
+        }
+        soa_seen += response.answers.iter().filter(|(rtype, _)| *rtype == TYPE_SOA).count();
+        records += response.answers.len();
+    }
+    let _ = stream.shutdown().await;
+    Ok((records > 0).then_some(records))
+}
+
+/// What the audit of one DNS port found
+#[derive(Debug)]
+pub struct DnsAudit {
+    pub details: serde_json::Value,
+    pub vulns: Vec<Finding>,
+    /// The server answered over UDP, not only over TCP
+    pub udp_answered: bool,
+}
+
+/// Audit a DNS server
+///
+/// # Arguments
+/// * `target` - Server address
+/// * `port` - DNS port, normally 53
+/// * `hostname` - Name the target was given as; its domain is tried for zone transfers
+/// * `tcp_open` - Whether the TCP port is open; zone transfers need it
+/// * `wait` - Per-query timeout
+///
+/// # Returns
+/// * `Option<DnsAudit>` - `None` if the server answered nothing
+pub async fn audit_dns(target: IpAddr, port: u16, hostname: Option<&str>, tcp_open: bool, wait: Duration) -> Option<DnsAudit> {
+    let mut details = serde_json::Map::new();
+    let mut vulns = Vec::new();
+    let mut udp_answered = false;
+    let mut tcp_answered = false;
+
+    // --- version.bind ---
+    let id: u16 = crate::utils::scan_rng().gen();
+    match query_udp(target, port, &build_query(id, "version.bind", TYPE_TXT, CLASS_CHAOS, false), id, wait).await {
+        Ok(Some(response)) => {
+            udp_answered = true;
+            let version = response.answers.iter()
+                .find(|(rtype, _)| *rtype == TYPE_TXT)
+                .map(|(_, rdata)| txt_text(rdata))
+                .filter(|text| !text.trim().is_empty());
+            if let Some(version) = version {
+                info!("[DNS] {}:{} discloses version.bind: {}", target, port, version);
+                vulns.push(Finding {
+                    id: "DNS-VERSION-DISCLOSURE".to_string(),
+                    title: "DNS server version disclosure".to_string(),
+                    description: format!("version.bind discloses the server software: {}", version),
+                    severity: "Low".to_string(),
+                    confidence: Confidence::Confirmed,
+                    evidence: vec![format!("version.bind: {}", version)],
+                    ..Default::default()
+                });
+                details.insert("version_bind".to_string(), json!(version));
+            }
+        }
+        Ok(None) => debug!("[DNS] No reply to version.bind from {}:{}", target, port),
+        Err(e) => debug!("[DNS] version.bind query to {}:{} failed: {}", target, port, e),
+    }
+
+    // --- Recursion ---
+    let id: u16 = crate::utils::scan_rng().gen();
+    match query_udp(target, port, &build_query(id, RECURSION_PROBE_NAME, TYPE_A, CLASS_IN, true), id, wait).await {
+        Ok(Some(response)) => {
+            udp_answered = true;
+            let open = response.recursion_available && response.rcode == 0
+                && response.answers.iter().any(|(rtype, _)| *rtype == TYPE_A);
+            details.insert("recursion".to_string(), json!(open));
+            if open {
+                warn!("[DNS] {}:{} is an open resolver", target, port);
+                vulns.push(Finding {
+                    id: "DNS-OPEN-RECURSION".to_string(),
+                    title: "Open DNS resolver".to_string(),
+                    description: format!("Resolves {} for any client; usable for cache poisoning and DNS amplification", RECURSION_PROBE_NAME),
+                    severity: "High".to_string(),
+                    confidence: Confidence::Confirmed,
+                    ..Default::default()
+                });
+            }
+        }
+        Ok(None) => debug!("[DNS] No reply to the recursive query from {}:{}", target, port),
+        Err(e) => debug!("[DNS] Recursive query to {}:{} failed: {}", target, port, e),
+    }
+
+    // --- Zone transfers ---
+    let mut zones: Vec<String> = hostname.map(crate::edge_detect::base_domain).into_iter().collect();
+    zones.extend(ZONES.read().iter().cloned());
+    zones.sort();
+    zones.dedup();
+    if tcp_open && !zones.is_empty() {
+        let mut transfers = serde_json::Map::new();
+        for zone in zones {
+            match try_axfr(target, port, &zone, wait).await {
+                Ok(Some(records)) => {
+                    tcp_answered = true;
+                    warn!("[DNS] {}:{} allows a zone transfer of {} ({} records)", target, port, zone, records);
+                    vulns.push(Finding {
+                        id: "DNS-AXFR".to_string(),
+                        title: "DNS zone transfer allowed".to_string(),
+                        description: format!("Allows a zone transfer of {} to anyone ({}{} records)", zone,
+                                             if records >= MAX_AXFR_RECORDS { "at least " } else { "" }, records),
+                        severity: "High".to_string(),
+                        confidence: Confidence::Confirmed,
+                        evidence: vec![format!("AXFR {}: {} records", zone, records)],
+                        ..Default::default()
+                    });
+                    transfers.insert(zone, json!(records));
+                }
+                Ok(None) => {
+                    tcp_answered = true;
+                    transfers.insert(zone, json!(false));
+                }
+                Err(e) => debug!("[DNS] AXFR of {} from {}:{} failed: {}", zone, target, port, e),
+            }
+        }
+        if !transfers.is_empty() {
+            details.insert("axfr".to_string(), serde_json::Value::Object(transfers));
+        }
+    }
+
+    (udp_answered || tcp_answered).then(|| DnsAudit { details: serde_json::Value::Object(details), vulns, udp_answered })
+}
@@ -0,0 +1,208 @@
+//! Resolver sanity checks.
+//!
+//! Before DNS tunnel scans or scans of a target given by name, the resolver path is
+//! checked for three kinds of misbehaviour that otherwise corrupt results silently:
+//!
+//! - **NXDOMAIN rewriting**: a name that cannot exist resolves to an address (ISP
+//!   search pages, captive portals). A hostname target may then "resolve" to the
+//!   resolver's landing page.
+//! - **Wildcard records**: a random subdomain of the target's or tunnel's domain
+//!   resolves, so a hostname's address may come from the wildcard rather than a real
+//!   record, and tunnel queries may be answered before reaching the tunnel server.
+//! - **Interception**: a query sent to an address that runs no DNS server is answered,
+//!   meaning a transparent proxy rewrites DNS traffic whatever resolver is configured.
+//!
+//! The checks cost a handful of queries for random names and one query to a
+//! documentation address (RFC 5737 / RFC 3849).
+
+use std::collections::HashMap;
+use std::net::{IpAddr, Ipv4Addr, Ipv6Addr, SocketAddr};
+use std::time::Duration;
+
+use anyhow::{anyhow, Result};
+use lazy_static::lazy_static;
+use log::{debug, info, warn};
+use parking_lot::RwLock;
+use rand::Rng;
+use tokio::time::timeout;
+
+use crate::models::DnsSanityReport;
+
+lazy_static! {
+    /// Outcome of the checks for this run, if they ran
+    static ref REPORT: RwLock<Option<DnsSanityReport>> = RwLock::new(None);
+}
+
+/// Record the checks' outcome for the scanner and DNS tunnel to consult
+pub fn set_report(report: Option<DnsSanityReport>) {
+    *REPORT.write() = report;
+}
+
+/// Outcome of the checks, if they ran
+pub fn report() -> Option<DnsSanityReport> {
+    REPORT.read().clone()
+}
+
+/// Whether answers on the resolver path can be made up by something other than the
+/// authoritative servers
+pub fn path_unreliable() -> bool {
+    REPORT.read().as_ref()
+        .map_or(false, |report| report.intercepted || !report.nxdomain_rewrite.is_empty())
+}
+
+/// Whether `ip` is what the resolver hands out for names that do not exist
+pub fn is_nxdomain_placeholder(ip: &IpAddr) -> bool {
+    REPORT.read().as_ref().map_or(false, |report| report.nxdomain_rewrite.contains(ip))
+}
+
+/// Random label that will not exist in any zone
+fn random_label() -> String {
+    let alphabet = b"abcdefghijklmnopqrstuvwxyz0123456789";
+    let mut rng = crate::utils::scan_rng();
+    (0..16).map(|_| alphabet[rng.gen_range(0..alphabet.len())] as char).collect()
+}
+
+/// Answer to an address lookup
+struct Lookup {
+    rcode: u8,
+    addresses: Vec<IpAddr>,
+}
+
+fn build_query(id: u16, name: &str, qtype: u16) -> Vec<u8> {
+    let mut query = Vec::with_capacity(name.len() + 18);
+    query.extend_from_slice(&id.to_be_bytes());
+    query.extend_from_slice(&0x0100u16.to_be_bytes()); // Standard query, recursion desired
+    query.extend_from_slice(&1u16.to_be_bytes()); // QDCOUNT
+    query.extend_from_slice(&[0, 0, 0, 0, 0, 0]); // ANCOUNT, NSCOUNT, ARCOUNT
+    for label in name.split('.').filter(|l| !l.is_empty()) {
+        query.push(label.len() as u8);
+        query.extend_from_slice(label.as_bytes());
+    }
+    query.push(0);
+    query.extend_from_slice(&qtype.to_be_bytes());
+    query.extend_from_slice(&1u16.to_be_bytes()); // Class IN
+    query
+}
+
+fn parse_response(response: &[u8]) -> Option<Lookup> {
+    if response.len() < 12 {
+        return None;
+    }
+    let rcode = response[3] & 0x0f;
+    let qdcount = u16::from_be_bytes([response[4], response[5]]);
+    let ancount = u16::from_be_bytes([response[6], response[7]]);
+
+    let mut offset = 12;
+    for _ in 0..qdcount {
+        let (_, next) = crate::tunnel::read_dns_name(response, offset)?;
+        offset = next + 4;
+    }
+
+    let mut addresses = Vec::new();
+    for _ in 0..ancount {
+        let Some((_, next)) = crate::tunnel::read_dns_name(response, offset) else { break };
+        if next + 10 > response.len() {
+            break;
+        }
+        let rtype = u16::from_be_bytes([response[next], response[next + 1]]);
+        let rdlen = u16::from_be_bytes([response[next + 8], response[next + 9]]) as usize;
+        let Some(rdata) = response.get(next + 10..next + 10 + rdlen) else { break };
+        match (rtype, rdlen) {
+            (1, 4) => addresses.push(IpAddr::V4(Ipv4Addr::new(rdata[0], rdata[1], rdata[2], rdata[3]))),
+            (28, 16) => {
+                let mut octets = [0u8; 16];
+                octets.copy_from_slice(rdata);
+                addresses.push(IpAddr::V6(Ipv6Addr::from(octets)));
+            }
+            _ => {}
+        }
+        offset = next + 10 + rdlen;
+    }
+    Some(Lookup { rcode, addresses })
+}
+
+/// Send one A query for `name` to `server` and wait for the matching answer
+async fn lookup(server: SocketAddr, name: &str, timeout_duration: Duration) -> Result<Lookup> {
+    let socket = crate::netbind::bind_udp(server.ip()).await?;
+    socket.connect(server).await?;
+    let id: u16 = crate::utils::scan_rng().gen();
+    let query = build_query(id, name, 1);
+    crate::scheduler::spend(query.len() + crate::scheduler::UDP_HEADER_BYTES).await;
+    crate::audit::record(|| crate::audit::Probe::new("dns-sanity", "udp", server.ip(), Some(server.port()), query.len() + crate::scheduler::UDP_HEADER_BYTES)
+        .detail(format!("A query for {}", name)));
+    socket.send(&query).await?;
+
+    let mut buf = [0u8; 1500];
+    loop {
+        let len = timeout(timeout_duration, socket.recv(&mut buf)).await
+            .map_err(|_| anyhow!("no answer from {} for {}", server, name))??;
+        if len >= 12 && buf[0..2] == id.to_be_bytes() {
+            return parse_response(&buf[..len]).ok_or_else(|| anyhow!("malformed answer from {}", server));
+        }
+    }
+}
+
+/// Check the resolver path before relying on it
+///
+/// # Arguments
+/// * `resolver` - Resolver the scan will use (`--dns-server`, else the system resolver)
+/// * `domains` - Domains to test for wildcard records (target's parent, tunnel domain)
+/// * `timeout_duration` - Per-query timeout
+///
+/// # Returns
+/// * `DnsSanityReport` - What was found; `warnings` is filled in by the caller, which
+///   knows what the scan depends on
+///
+/// # Opsec Considerations
+/// Queries for random names are a common resolver-probing pattern; the interception
+/// check sends one packet to a documentation address, which never leaves a
+/// well-behaved network.
+pub async fn check_resolver(resolver: Option<IpAddr>, domains: &[String], timeout_duration: Duration) -> DnsSanityReport {
+    let mut report = DnsSanityReport { resolver, ..Default::default() };
+
+    // A documentation address never runs a DNS server, so any answer was forged
+    let probe_ip: IpAddr = match resolver {
+        Some(IpAddr::V6(_)) => IpAddr::V6(Ipv6Addr::new(0x2001, 0xdb8, 0, 0, 0, 0, 0, 0x53)),
+        _ => IpAddr::V4(Ipv4Addr::new(192, 0, 2, 53)),
+    };
+    if lookup(SocketAddr::new(probe_ip, 53), "example.com", timeout_duration).await.is_ok() {
+        warn!("[DNS] Query to {} (no DNS server there) was answered: DNS traffic is being intercepted", probe_ip);
+        report.intercepted = true;
+    }
+
+    let Some(resolver_ip) = resolver else {
+        debug!("[DNS] No resolver address known, skipping NXDOMAIN and wildcard checks");
+        return report;
+    };
+    let server = SocketAddr::new(resolver_ip, 53);
+
+    // .com answers NXDOMAIN for anything unregistered
+    let bogus = format!("{}.com", random_label());
+    match lookup(server, &bogus, timeout_duration).await {
+        Ok(answer) if !answer.addresses.is_empty() => {
+            warn!("[DNS] Resolver {} answers non-existent names with {:?} (NXDOMAIN rewriting)", resolver_ip, answer.addresses);
+            report.nxdomain_rewrite = answer.addresses;
+        }
+        Ok(answer) => debug!("[DNS] Resolver {} returned rcode {} for {}", resolver_ip, answer.rcode, bogus),
+        Err(e) => debug!("[DNS] NXDOMAIN check against {} failed: {}", resolver_ip, e),
+    }
+
+    let mut wildcard_domains = HashMap::new();
+    for domain in domains {
+        let domain = domain.trim_matches('.').to_ascii_lowercase();
+        if domain.is_empty() || wildcard_domains.contains_key(&domain) {
+            continue;
+        }
+        let name = format!("{}.{}", random_label(), domain);
+        match lookup(server, &name, timeout_duration).await {
+            Ok(answer) if !answer.addresses.is_empty() => {
+                info!("[DNS] {} has a wildcard record: random subdomains resolve to {:?}", domain, answer.addresses);
+                wildcard_domains.insert(domain, answer.addresses);
+            }
+            Ok(_) => debug!("[DNS] No wildcard under {}", domain),
+            Err(e) => debug!("[DNS] Wildcard check for {} failed: {}", domain, e),
+        }
+    }
+    report.wildcard_domains = wildcard_domains;
+    report
+}
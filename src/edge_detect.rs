@@ -0,0 +1,238 @@
+//! CDN and load-balancer detection.
+//!
+//! A hostname behind a CDN or load balancer resolves to edge servers, so its open ports,
+//! banners and certificates describe the edge and say little about the origin behind it.
+//! Once a host is scanned, the evidence gathered is weighed for signs of edge
+//! infrastructure:
+//!
+//! - **Address ownership**: the address lies in a range a CDN publishes for its edge
+//! - **DNS**: the name is a CNAME to a CDN or load balancer domain, resolves to several
+//!   addresses, or is served with the short TTL used for traffic steering
+//! - **Response headers**: headers CDNs add to every response (`CF-Ray`, `X-Amz-Cf-Id`,
+//!   `X-Served-By`...) and generic proxy headers (`Via`, `X-Cache`)
+//! - **Certificates**: a certificate naming dozens of names or many unrelated domains is
+//!   a shared edge certificate, not the site's own
+//!
+//! Nothing is sent to the target for this; the DNS lookup is the only extra traffic and
+//! is skipped when the resolver path cannot be trusted. The range list is a snapshot of
+//! the providers' published ranges, so an unlisted edge address is still caught by the
+//! other signals.
+
+use std::collections::{BTreeSet, HashMap};
+use std::net::IpAddr;
+
+use hickory_resolver::proto::rr::RecordType;
+use hickory_resolver::TokioAsyncResolver;
+use ipnet::IpNet;
+use log::{debug, warn};
+
+use crate::models::{EdgeInfo, EdgeKind, PortResult};
+
+/// Published edge ranges, by provider
+const PROVIDER_RANGES: &[(&str, &[&str])] = &[
+    ("Cloudflare", &[
+        "173.245.48.0/20", "103.21.244.0/22", "103.22.200.0/22", "103.31.4.0/22", "141.101.64.0/18",
+        "108.162.192.0/18", "190.93.240.0/20", "188.114.96.0/20", "197.234.240.0/22", "198.41.128.0/17",
+        "162.158.0.0/15", "104.16.0.0/13", "104.24.0.0/14", "172.64.0.0/13", "131.0.72.0/22",
+        "2400:cb00::/32", "2606:4700::/32", "2803:f800::/32", "2405:b500::/32", "2405:8100::/32",
+        "2a06:98c0::/29", "2c0f:f248::/32",
+    ]),
+    ("Fastly", &[
+        "23.235.32.0/20", "43.249.72.0/22", "103.244.50.0/24", "103.245.222.0/23", "104.156.80.0/20",
+        "140.248.64.0/18", "140.248.128.0/17", "146.75.0.0/17", "151.101.0.0/16", "157.52.64.0/18",
+        "167.82.0.0/17", "172.111.64.0/18", "185.31.16.0/22", "199.27.72.0/21", "199.232.0.0/16",
+    ]),
+    ("Amazon CloudFront", &[
+        "13.32.0.0/15", "13.224.0.0/14", "18.64.0.0/14", "52.84.0.0/15", "54.182.0.0/16",
+        "54.192.0.0/16", "54.230.0.0/16", "54.239.128.0/18", "99.84.0.0/16", "143.204.0.0/16",
+    ]),
+    ("Akamai", &[
+        "2.16.0.0/13", "23.0.0.0/12", "23.32.0.0/11", "23.192.0.0/11", "72.246.0.0/15",
+        "96.16.0.0/15", "104.64.0.0/10", "184.24.0.0/13",
+    ]),
+    ("Imperva", &["199.83.128.0/21", "45.64.64.0/22", "198.143.32.0/19", "149.126.72.0/21"]),
+    ("Sucuri", &["192.88.134.0/23", "185.93.228.0/22", "66.248.200.0/22"]),
+];
+
+/// Suffixes of CNAME targets, with the provider and whether it is a CDN or a balancer
+const CNAME_SUFFIXES: &[(&str, &str, EdgeKind)] = &[
+    ("cdn.cloudflare.net", "Cloudflare", EdgeKind::Cdn),
+    ("cloudfront.net", "Amazon CloudFront", EdgeKind::Cdn),
+    ("akamaiedge.net", "Akamai", EdgeKind::Cdn),
+    ("edgekey.net", "Akamai", EdgeKind::Cdn),
+    ("edgesuite.net", "Akamai", EdgeKind::Cdn),
+    ("akamai.net", "Akamai", EdgeKind::Cdn),
+    ("fastly.net", "Fastly", EdgeKind::Cdn),
+    ("fastlylb.net", "Fastly", EdgeKind::Cdn),
+    ("azureedge.net", "Azure CDN", EdgeKind::Cdn),
+    ("azurefd.net", "Azure Front Door", EdgeKind::Cdn),
+    ("edgecastcdn.net", "Edgio", EdgeKind::Cdn),
+    ("incapdns.net", "Imperva", EdgeKind::Cdn),
+    ("cdn77.org", "CDN77", EdgeKind::Cdn),
+    ("b-cdn.net", "Bunny CDN", EdgeKind::Cdn),
+    ("vercel-dns.com", "Vercel", EdgeKind::Cdn),
+    ("elb.amazonaws.com", "AWS Elastic Load Balancing", EdgeKind::LoadBalancer),
+    ("trafficmanager.net", "Azure Traffic Manager", EdgeKind::LoadBalancer),
+    ("cloudapp.azure.com", "Azure Load Balancer", EdgeKind::LoadBalancer),
+];
+
+/// Response headers only a given CDN sets (lowercase name, optional value substring)
+const PROVIDER_HEADERS: &[(&str, Option<&str>, &str)] = &[
+    ("cf-ray", None, "Cloudflare"),
+    ("server", Some("cloudflare"), "Cloudflare"),
+    ("x-amz-cf-id", None, "Amazon CloudFront"),
+    ("x-amz-cf-pop", None, "Amazon CloudFront"),
+    ("via", Some("cloudfront"), "Amazon CloudFront"),
+    ("x-fastly-request-id", None, "Fastly"),
+    ("x-served-by", Some("cache-"), "Fastly"),
+    ("server", Some("akamaighost"), "Akamai"),
+    ("akamai-grn", None, "Akamai"),
+    ("x-akamai-transformed", None, "Akamai"),
+    ("x-azure-ref", None, "Azure Front Door"),
+    ("x-iinfo", None, "Imperva"),
+    ("x-cdn", Some("imperva"), "Imperva"),
+    ("x-sucuri-id", None, "Sucuri"),
+    ("x-vercel-id", None, "Vercel"),
+    ("server", Some("bunnycdn"), "Bunny CDN"),
+];
+
+/// Response headers any caching proxy or balancer may add
+const PROXY_HEADERS: [&str; 4] = ["via", "x-cache", "x-cache-hits", "age"];
+
+/// Most names a site's own certificate usually carries
+const BROAD_CERT_NAMES: usize = 25;
+
+/// Most unrelated domains a site's own certificate usually carries
+const BROAD_CERT_DOMAINS: usize = 5;
+
+/// TTL at or below which answers look steered, in seconds
+const STEERING_TTL: u32 = 300;
+
+/// Provider whose published ranges hold `ip`
+pub(crate) fn provider_for_address(ip: IpAddr) -> Option<&'static str> {
+    PROVIDER_RANGES.iter()
+        .find(|(_, ranges)| ranges.iter().filter_map(|r| r.parse::<IpNet>().ok()).any(|net| net.contains(&ip)))
+        .map(|(provider, _)| *provider)
+}
+
+/// Registrable part of a name, roughly: its last two labels
+pub(crate) fn base_domain(name: &str) -> String {
+    let labels: Vec<&str> = name.trim_start_matches("*.").trim_end_matches('.').rsplit('.').take(2).collect();
+    labels.into_iter().rev().collect::<Vec<_>>().join(".").to_lowercase()
+}
+
+/// What DNS says about a hostname
+struct DnsEvidence {
+    addresses: Vec<IpAddr>,
+    cnames: Vec<String>,
+    min_ttl: Option<u32>,
+}
+
+async fn resolve(hostname: &str) -> Option<DnsEvidence> {
+    let resolver = TokioAsyncResolver::tokio_from_system_conf().ok()?;
+    let lookup = match resolver.lookup_ip(hostname).await {
+        Ok(lookup) => lookup,
+        Err(e) => {
+            debug!("[Edge] Lookup of {} failed: {}", hostname, e);
+            return None;
+        }
+    };
+    let records = lookup.as_lookup().records();
+    Some(DnsEvidence {
+        addresses: lookup.iter().collect::<BTreeSet<_>>().into_iter().collect(),
+        cnames: records.iter()
+            .filter(|record| record.record_type() == RecordType::CNAME)
+            .filter_map(|record| record.data().map(|data| data.to_string().trim_end_matches('.').to_lowercase()))
+            .collect(),
+        min_ttl: records.iter().map(|record| record.ttl()).min(),
+    })
+}
+
+/// Look for signs that a host is a CDN or load-balancer edge
+///
+/// # Arguments
+/// * `target` - Target as given; DNS is only consulted for hostnames
+/// * `target_ip` - Address that was scanned
+/// * `ports` - Port results with their HTTP headers and certificates
+///
+/// # Returns
+/// * `Option<EdgeInfo>` - The evidence, if it points to edge infrastructure
+pub async fn detect(target: &str, target_ip: IpAddr, ports: &HashMap<u16, PortResult>) -> Option<EdgeInfo> {
+    let mut evidence = Vec::new();
+    let mut provider: Option<String> = None;
+    let mut kind = EdgeKind::Cdn;
+    // Signals that fit a CDN or balancer but also plenty of ordinary setups
+    let mut weak = 0;
+
+    if let Some(owner) = provider_for_address(target_ip) {
+        evidence.push(format!("{} is in a published {} edge range", target_ip, owner));
+        provider = Some(owner.to_string());
+    }
+
+    let mut addresses = Vec::new();
+    let mut dns_ttl = None;
+    if target.parse::<IpAddr>().is_err() && !crate::dns_sanity::path_unreliable() {
+        if let Some(dns) = resolve(target).await {
+            for cname in &dns.cnames {
+                if let Some((suffix, owner, edge)) = CNAME_SUFFIXES.iter().find(|(suffix, _, _)| cname.ends_with(suffix)) {
+                    evidence.push(format!("{} is a CNAME to {} ({})", target, cname, suffix));
+                    provider.get_or_insert_with(|| owner.to_string());
+                    kind = *edge;
+                }
+            }
+            if dns.addresses.len() > 1 {
+                evidence.push(format!("{} resolves to {} addresses", target, dns.addresses.len()));
+                weak += 1;
+            }
+            if let Some(ttl) = dns.min_ttl.filter(|ttl| *ttl <= STEERING_TTL) {
+                evidence.push(format!("answers carry a {}s TTL, short enough for traffic steering", ttl));
+                weak += 1;
+            }
+            addresses = dns.addresses;
+            dns_ttl = dns.min_ttl;
+        }
+    }
+
+    let mut proxy_headers = BTreeSet::new();
+    for (port, result) in ports {
+        if let Some(http) = &result.http_info {
+            let headers: HashMap<String, String> = http.headers.iter()
+                .map(|(name, value)| (name.to_lowercase(), value.to_lowercase()))
+                .collect();
+            for (name, needle, owner) in PROVIDER_HEADERS {
+                let Some(value) = headers.get(*name) else { continue };
+                if needle.map_or(true, |needle| value.contains(needle)) {
+                    evidence.push(format!("port {} answers with the {} {} header", port, owner, name));
+                    provider.get_or_insert_with(|| owner.to_string());
+                }
+            }
+            proxy_headers.extend(PROXY_HEADERS.iter().filter(|name| headers.contains_key(**name)));
+        }
+        if let Some(cert) = &result.cert_info {
+            let domains: BTreeSet<String> = cert.alt_names.iter().map(|name| base_domain(name)).collect();
+            if cert.alt_names.iter().any(|name| name.ends_with("cloudflaressl.com")) {
+                evidence.push(format!("port {} presents a Cloudflare universal SSL certificate", port));
+                provider.get_or_insert_with(|| "Cloudflare".to_string());
+            } else if cert.alt_names.len() >= BROAD_CERT_NAMES || domains.len() >= BROAD_CERT_DOMAINS {
+                evidence.push(format!("port {} presents a shared certificate naming {} names across {} domains",
+                                      port, cert.alt_names.len(), domains.len()));
+                weak += 1;
+            }
+        }
+    }
+    if !proxy_headers.is_empty() {
+        evidence.push(format!("responses carry proxy headers ({})", proxy_headers.into_iter().collect::<Vec<_>>().join(", ")));
+        weak += 1;
+    }
+
+    // A named provider settles it; otherwise it takes two independent hints
+    if provider.is_none() && weak < 2 {
+        return None;
+    }
+    if provider.is_none() {
+        kind = EdgeKind::LoadBalancer;
+    }
+    warn!("[Edge] {} looks like {} infrastructure{}: results describe the edge, not the origin",
+          target, kind, provider.as_deref().map(|p| format!(" ({})", p)).unwrap_or_default());
+    Some(EdgeInfo { kind, provider, evidence, addresses, dns_ttl, origin_candidates: Vec::new() })
+}
@@ -0,0 +1,347 @@
+//! Passphrase-based encryption for files written to disk.
+//!
+//! Reports written with `--encrypt-output` are sealed with AES-256-GCM under a key
+//! derived from the passphrase with Argon2id. The file layout is:
+//!
+//! ```text
+//! "QSENC" | version (1) | salt (16) | m_cost (4) | t_cost (4) | p_cost (4) | nonce (12) | ciphertext+tag
+//! ```
+//!
+//! Everything before the ciphertext is authenticated as associated data, so the KDF
+//! parameters cannot be downgraded without detection. `quantum_scanner decrypt` reads
+//! the files back.
+
+use std::path::{Path, PathBuf};
+
+use aes_gcm::aead::{Aead, AeadCore, KeyInit, OsRng, Payload};
+use aes_gcm::{Aes256Gcm, Nonce};
+use anyhow::{anyhow, Context, Result};
+use argon2::{Algorithm, Argon2, Params, Version};
+use clap::Parser;
+use lazy_static::lazy_static;
+use log::info;
+use parking_lot::RwLock;
+use rand::RngCore;
+use zeroize::Zeroizing;
+
+use crate::utils::SecureString;
+
+/// Magic bytes opening every encrypted file
+const MAGIC: &[u8; 5] = b"QSENC";
+/// Current file format version
+const FORMAT_VERSION: u8 = 1;
+const SALT_LEN: usize = 16;
+const NONCE_LEN: usize = 12;
+const HEADER_LEN: usize = MAGIC.len() + 1 + SALT_LEN + 12 + NONCE_LEN;
+
+/// Argon2id cost: 19 MiB, 2 passes, 1 lane (OWASP minimum recommendation)
+pub(crate) const KDF_M_COST: u32 = 19 * 1024;
+pub(crate) const KDF_T_COST: u32 = 2;
+pub(crate) const KDF_P_COST: u32 = 1;
+
+lazy_static! {
+    /// Passphrase for report files, set by `--encrypt-output`
+    static ref OUTPUT_PASSPHRASE: RwLock<Option<SecureString>> = RwLock::new(None);
+}
+
+/// Resolve a passphrase argument
+///
+/// `env:NAME` reads the environment variable NAME and `file:PATH` reads the first line
+/// of a file, which keeps the secret off the command line and out of shell history.
+/// Anything else is the passphrase itself.
+pub fn resolve_passphrase(spec: &str) -> Result<SecureString> {
+    let passphrase = if let Some(name) = spec.strip_prefix("env:") {
+        std::env::var(name).with_context(|| format!("Passphrase variable {} is not set", name))?
+    } else if let Some(path) = spec.strip_prefix("file:") {
+        std::fs::read_to_string(path)
+            .with_context(|| format!("Failed to read passphrase file {}", path))?
+            .lines().next().unwrap_or("").to_string()
+    } else {
+        spec.to_string()
+    };
+    if passphrase.is_empty() {
+        return Err(anyhow!("Empty passphrase"));
+    }
+    Ok(SecureString::new(passphrase))
+}
+
+/// Derive a 256-bit key from a passphrase with Argon2id (wiped from memory on drop)
+pub fn derive_key(passphrase: &str, salt: &[u8], m_cost: u32, t_cost: u32, p_cost: u32) -> Result<Zeroizing<[u8; 32]>> {
+    let params = Params::new(m_cost, t_cost, p_cost, Some(32))
+        .map_err(|e| anyhow!("Invalid key derivation parameters: {}", e))?;
+    let mut key = Zeroizing::new([0u8; 32]);
+    Argon2::new(Algorithm::Argon2id, Version::V0x13, params)
+        .hash_password_into(passphrase.as_bytes(), salt, key.as_mut())
+        .map_err(|e| anyhow!("Key derivation failed: {}", e))?;
+    Ok(key)
+}
+
+/// Encrypt `plaintext` under `passphrase`
+///
+/// # Returns
+/// * `Result<Vec<u8>>` - The complete encrypted file contents
+pub fn encrypt_bytes(passphrase: &str, plaintext: &[u8]) -> Result<Vec<u8>> {
+    let mut salt = [0u8; SALT_LEN];
+    OsRng.fill_bytes(&mut salt);
+    let key = derive_key(passphrase, &salt, KDF_M_COST, KDF_T_COST, KDF_P_COST)?;
+    let cipher = Aes256Gcm::new((&*key).into());
+    let nonce = Aes256Gcm::generate_nonce(&mut OsRng);
+
+    let mut sealed = Vec::with_capacity(HEADER_LEN + plaintext.len() + 16);
+    sealed.extend_from_slice(MAGIC);
+    sealed.push(FORMAT_VERSION);
+    sealed.extend_from_slice(&salt);
+    sealed.extend_from_slice(&KDF_M_COST.to_be_bytes());
+    sealed.extend_from_slice(&KDF_T_COST.to_be_bytes());
+    sealed.extend_from_slice(&KDF_P_COST.to_be_bytes());
+    sealed.extend_from_slice(&nonce);
+
+    let ciphertext = cipher.encrypt(&nonce, Payload { msg: plaintext, aad: &sealed })
+        .map_err(|_| anyhow!("Encryption failed"))?;
+    sealed.extend_from_slice(&ciphertext);
+    Ok(sealed)
+}
+
+/// Whether `data` starts like a file written by `encrypt_bytes`
+pub fn is_encrypted(data: &[u8]) -> bool {
+    data.len() >= HEADER_LEN && data.starts_with(MAGIC)
+}
+
+/// Decrypt a file produced by `encrypt_bytes`
+///
+/// Fails without output on a wrong passphrase or any modification of the file.
+pub fn decrypt_bytes(passphrase: &str, data: &[u8]) -> Result<Vec<u8>> {
+    if !is_encrypted(data) {
+        return Err(anyhow!("Not a quantum_scanner encrypted file"));
+    }
+    if data[MAGIC.len()] != FORMAT_VERSION {
+        return Err(anyhow!("Unsupported encrypted file version {}", data[MAGIC.len()]));
+    }
+    let (header, ciphertext) = data.split_at(HEADER_LEN);
+    let mut offset = MAGIC.len() + 1;
+    let salt = &header[offset..offset + SALT_LEN];
+    offset += SALT_LEN;
+    let mut cost = [0u32; 3];
+    for value in cost.iter_mut() {
+        *value = u32::from_be_bytes(header[offset..offset + 4].try_into().unwrap());
+        offset += 4;
+    }
+    let nonce = Nonce::from_slice(&header[offset..offset + NONCE_LEN]);
+
+    let key = derive_key(passphrase, salt, cost[0], cost[1], cost[2])?;
+    Aes256Gcm::new((&*key).into())
+        .decrypt(nonce, Payload { msg: ciphertext, aad: header })
+        .map_err(|_| anyhow!("Decryption failed: wrong passphrase or corrupted file"))
+}
+
+/// Encrypt report files with this passphrase from now on (`None` writes plaintext)
+pub fn set_output_passphrase(passphrase: Option<SecureString>) {
+    *OUTPUT_PASSPHRASE.write() = passphrase;
+}
+
+/// Write a report file, encrypting it when `--encrypt-output` is active
+pub fn write_output(path: &Path, contents: &[u8]) -> Result<()> {
+    let passphrase = OUTPUT_PASSPHRASE.read().clone();
+    let data = match passphrase {
+        Some(passphrase) => encrypt_bytes(&passphrase, contents)?,
+        None => contents.to_vec(),
+    };
+    std::fs::write(path, data).with_context(|| format!("Failed to write output file: {:?}", path))
+}
+
+// --- Streaming log encryption ---
+//
+// Log files are written record by record, so they cannot be sealed in one piece. Each
+// record becomes its own AES-256-GCM chunk (STREAM construction):
+//
+//   "QSLOG" | version (1) | salt (16) | m_cost (4) | t_cost (4) | p_cost (4) | nonce prefix (7)
+//   then per chunk: length (4) | ciphertext+tag
+//
+// The nonce of chunk n is prefix | n (4, big endian) | last flag (1), and the file header
+// is the associated data of every chunk. Reordering, dropping or splicing chunks fails
+// authentication; a file without a final chunk was cut short (crash, kill, or tampering).
+
+/// Magic bytes opening an encrypted log
+const LOG_MAGIC: &[u8; 5] = b"QSLOG";
+const LOG_NONCE_PREFIX_LEN: usize = 7;
+const LOG_HEADER_LEN: usize = LOG_MAGIC.len() + 1 + SALT_LEN + 12 + LOG_NONCE_PREFIX_LEN;
+
+fn log_nonce(prefix: &[u8], counter: u32, last: bool) -> [u8; NONCE_LEN] {
+    let mut nonce = [0u8; NONCE_LEN];
+    nonce[..LOG_NONCE_PREFIX_LEN].copy_from_slice(prefix);
+    nonce[LOG_NONCE_PREFIX_LEN..LOG_NONCE_PREFIX_LEN + 4].copy_from_slice(&counter.to_be_bytes());
+    nonce[NONCE_LEN - 1] = last as u8;
+    nonce
+}
+
+/// Writer that encrypts each `write` call as one log chunk
+///
+/// env_logger hands over one formatted record per write, so every record is
+/// individually authenticated and readable even if the process dies mid-scan.
+/// Call `finish` at the end to mark the log as complete.
+pub struct EncryptedLogWriter<W: std::io::Write> {
+    inner: W,
+    cipher: Aes256Gcm,
+    header: Vec<u8>,
+    counter: u32,
+    finished: bool,
+}
+
+impl<W: std::io::Write> EncryptedLogWriter<W> {
+    /// Start an encrypted log on `inner`, writing the header immediately
+    pub fn new(mut inner: W, passphrase: &str) -> Result<Self> {
+        let mut salt = [0u8; SALT_LEN];
+        OsRng.fill_bytes(&mut salt);
+        let mut prefix = [0u8; LOG_NONCE_PREFIX_LEN];
+        OsRng.fill_bytes(&mut prefix);
+        let key = derive_key(passphrase, &salt, KDF_M_COST, KDF_T_COST, KDF_P_COST)?;
+
+        let mut header = Vec::with_capacity(LOG_HEADER_LEN);
+        header.extend_from_slice(LOG_MAGIC);
+        header.push(FORMAT_VERSION);
+        header.extend_from_slice(&salt);
+        header.extend_from_slice(&KDF_M_COST.to_be_bytes());
+        header.extend_from_slice(&KDF_T_COST.to_be_bytes());
+        header.extend_from_slice(&KDF_P_COST.to_be_bytes());
+        header.extend_from_slice(&prefix);
+        inner.write_all(&header)?;
+        inner.flush()?;
+
+        Ok(EncryptedLogWriter { inner, cipher: Aes256Gcm::new((&*key).into()), header, counter: 0, finished: false })
+    }
+
+    fn write_chunk(&mut self, data: &[u8], last: bool) -> std::io::Result<()> {
+        if self.finished {
+            return Err(std::io::Error::new(std::io::ErrorKind::Other, "encrypted log already finished"));
+        }
+        let prefix = &self.header[LOG_HEADER_LEN - LOG_NONCE_PREFIX_LEN..];
+        let nonce = log_nonce(prefix, self.counter, last);
+        let sealed = self.cipher.encrypt(Nonce::from_slice(&nonce), Payload { msg: data, aad: &self.header })
+            .map_err(|_| std::io::Error::new(std::io::ErrorKind::Other, "log encryption failed"))?;
+        self.counter = self.counter.checked_add(1)
+            .ok_or_else(|| std::io::Error::new(std::io::ErrorKind::Other, "encrypted log chunk limit reached"))?;
+        self.inner.write_all(&(sealed.len() as u32).to_be_bytes())?;
+        self.inner.write_all(&sealed)?;
+        self.inner.flush()
+    }
+
+    /// Write the final (empty) chunk that marks the log as complete
+    pub fn finish(&mut self) -> std::io::Result<()> {
+        if self.finished {
+            return Ok(());
+        }
+        self.write_chunk(&[], true)?;
+        self.finished = true;
+        Ok(())
+    }
+}
+
+impl<W: std::io::Write> std::io::Write for EncryptedLogWriter<W> {
+    fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+        if !buf.is_empty() {
+            self.write_chunk(buf, false)?;
+        }
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> std::io::Result<()> {
+        self.inner.flush()
+    }
+}
+
+/// Whether `data` starts like a log written by `EncryptedLogWriter`
+pub fn is_encrypted_log(data: &[u8]) -> bool {
+    data.len() >= LOG_HEADER_LEN && data.starts_with(LOG_MAGIC)
+}
+
+/// Decrypt a log written by `EncryptedLogWriter`
+///
+/// # Returns
+/// * `Result<(Vec<u8>, bool)>` - The plaintext of every intact chunk, and whether the
+///   final chunk was present. An incomplete log still returns what could be read.
+pub fn decrypt_log(passphrase: &str, data: &[u8]) -> Result<(Vec<u8>, bool)> {
+    if !is_encrypted_log(data) {
+        return Err(anyhow!("Not a quantum_scanner encrypted log"));
+    }
+    if data[LOG_MAGIC.len()] != FORMAT_VERSION {
+        return Err(anyhow!("Unsupported encrypted log version {}", data[LOG_MAGIC.len()]));
+    }
+    let header = &data[..LOG_HEADER_LEN];
+    let mut offset = LOG_MAGIC.len() + 1;
+    let salt = &header[offset..offset + SALT_LEN];
+    offset += SALT_LEN;
+    let mut cost = [0u32; 3];
+    for value in cost.iter_mut() {
+        *value = u32::from_be_bytes(header[offset..offset + 4].try_into().unwrap());
+        offset += 4;
+    }
+    let prefix = &header[offset..offset + LOG_NONCE_PREFIX_LEN];
+    let cipher = Aes256Gcm::new((&*derive_key(passphrase, salt, cost[0], cost[1], cost[2])?).into());
+
+    let mut plaintext = Vec::new();
+    let mut position = LOG_HEADER_LEN;
+    let mut counter: u32 = 0;
+    while position + 4 <= data.len() {
+        let len = u32::from_be_bytes(data[position..position + 4].try_into().unwrap()) as usize;
+        let Some(sealed) = data.get(position + 4..position + 4 + len) else { break };
+        position += 4 + len;
+
+        // The final chunk is the only one sealed with the last flag set
+        for last in [false, true] {
+            let nonce = log_nonce(prefix, counter, last);
+            if let Ok(chunk) = cipher.decrypt(Nonce::from_slice(&nonce), Payload { msg: sealed, aad: header }) {
+                if last {
+                    return Ok((plaintext, position == data.len()));
+                }
+                plaintext.extend_from_slice(&chunk);
+                break;
+            } else if last {
+                if counter == 0 {
+                    return Err(anyhow!("Decryption failed: wrong passphrase or corrupted log"));
+                }
+                return Err(anyhow!("Log chunk {} failed authentication; the file was modified", counter));
+            }
+        }
+        counter += 1;
+    }
+    Ok((plaintext, false))
+}
+
+/// Arguments for `quantum_scanner decrypt`
+#[derive(Parser, Debug)]
+#[clap(name = "decrypt", about = "Decrypt a report written with --encrypt-output")]
+pub struct DecryptArgs {
+    /// Encrypted file to read
+    pub input: PathBuf,
+
+    /// Where to write the plaintext (default: stdout)
+    #[clap(short, long)]
+    pub output: Option<PathBuf>,
+
+    /// Passphrase, or env:NAME / file:PATH to read it from (prompted for when omitted)
+    #[clap(short, long)]
+    pub passphrase: Option<String>,
+}
+
+/// Run the `decrypt` subcommand
+pub fn run_decrypt(args: DecryptArgs) -> Result<()> {
+    let data = std::fs::read(&args.input)
+        .with_context(|| format!("Failed to read {}", args.input.display()))?;
+    let passphrase = match &args.passphrase {
+        Some(spec) => resolve_passphrase(spec)?,
+        None => SecureString::new(dialoguer::Password::new().with_prompt("Passphrase").interact()?),
+    };
+    let plaintext = decrypt_bytes(&passphrase, &data)?;
+
+    match &args.output {
+        Some(path) => {
+            std::fs::write(path, &plaintext).with_context(|| format!("Failed to write {}", path.display()))?;
+            info!("Decrypted {} to {}", args.input.display(), path.display());
+        }
+        None => {
+            use std::io::Write;
+            std::io::stdout().write_all(&plaintext)?;
+        }
+    }
+    Ok(())
+}
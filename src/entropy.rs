@@ -0,0 +1,151 @@
+//! Entropy and byte-distribution analysis of service responses.
+//!
+//! When no fingerprint matches, the shape of the bytes still says a lot: line-based
+//! protocols are printable text, ciphertext spreads evenly over all 256 byte values,
+//! and compressed data is nearly as dense but keeps a header and an uneven
+//! distribution. The class feeds the ML features and lets reports say "unknown
+//! encrypted service" instead of lumping it with ports that sent nothing.
+
+use crate::models::{PayloadClass, PayloadProfile};
+
+/// Share of printable bytes from which a response counts as text
+const TEXT_PRINTABLE: f64 = 0.85;
+
+/// Fewest bytes whose entropy says anything about randomness
+const MIN_SAMPLE: usize = 32;
+
+/// Share of the entropy expected from random bytes of the same length above which a
+/// response is dense enough to be compressed or encrypted
+const DENSE_RATIO: f64 = 0.9;
+
+/// Fewest bytes for which the chi-square uniformity test is meaningful (one expected
+/// occurrence per byte value)
+const CHI_SQUARE_SAMPLE: usize = 256;
+
+/// Chi-square statistic (255 degrees of freedom) above which bytes are not uniform;
+/// random data stays below it 99.9% of the time
+const CHI_SQUARE_LIMIT: f64 = 330.0;
+
+/// Leading bytes of common compression formats
+const COMPRESSION_MAGIC: [(&[u8], &str); 8] = [
+    (&[0x1f, 0x8b], "gzip"),
+    (&[0x78, 0x01], "zlib"),
+    (&[0x78, 0x5e], "zlib"),
+    (&[0x78, 0x9c], "zlib"),
+    (&[0x78, 0xda], "zlib"),
+    (&[0x28, 0xb5, 0x2f, 0xfd], "zstd"),
+    (b"BZh", "bzip2"),
+    (&[0xfd, b'7', b'z', b'X', b'Z', 0x00], "xz"),
+];
+
+/// Shannon entropy in bits per byte
+pub fn shannon_entropy(data: &[u8]) -> f64 {
+    if data.is_empty() {
+        return 0.0;
+    }
+    let mut counts = [0usize; 256];
+    for &byte in data {
+        counts[byte as usize] += 1;
+    }
+    let total = data.len() as f64;
+    counts.iter()
+        .filter(|&&count| count > 0)
+        .map(|&count| {
+            let p = count as f64 / total;
+            -p * p.log2()
+        })
+        .sum()
+}
+
+/// Chi-square statistic of the byte histogram against a uniform distribution
+fn chi_square(data: &[u8]) -> f64 {
+    let mut counts = [0usize; 256];
+    for &byte in data {
+        counts[byte as usize] += 1;
+    }
+    let expected = data.len() as f64 / 256.0;
+    counts.iter()
+        .map(|&count| (count as f64 - expected).powi(2) / expected)
+        .sum()
+}
+
+/// Entropy random bytes of this length would show on average
+///
+/// Short samples cannot reach 8 bits per byte, and the plug-in estimate undershoots by
+/// about (K - 1) / (2n ln 2) once every byte value is likely to appear.
+fn expected_random_entropy(length: usize) -> f64 {
+    if length < CHI_SQUARE_SAMPLE {
+        // Few repeats among fewer bytes than byte values
+        (length as f64).log2()
+    } else {
+        8.0 - 255.0 / (2.0 * length as f64 * std::f64::consts::LN_2)
+    }
+}
+
+/// Container or record format recognised from the leading bytes
+fn leading_format(data: &[u8]) -> Option<&'static str> {
+    if let Some((_, name)) = COMPRESSION_MAGIC.iter().find(|(magic, _)| data.starts_with(magic)) {
+        return Some(name);
+    }
+    // TLS record: content type 20-23, then a 3.x version
+    match data {
+        [0x14..=0x17, 0x03, 0x00..=0x04, ..] => Some("tls record"),
+        _ => None,
+    }
+}
+
+/// Classify a response by its byte statistics
+///
+/// # Arguments
+/// * `data` - Bytes the service sent (banner or probe reply)
+///
+/// # Returns
+/// * `PayloadProfile` - Class, entropy and the numbers behind the call
+pub fn profile(data: &[u8]) -> PayloadProfile {
+    let entropy = shannon_entropy(data);
+    let printable = if data.is_empty() {
+        0.0
+    } else {
+        data.iter().filter(|&&b| (32..=126).contains(&b) || matches!(b, b'\t' | b'\r' | b'\n')).count() as f64
+            / data.len() as f64
+    };
+    let format = leading_format(data);
+
+    let class = if data.is_empty() {
+        PayloadClass::NoData
+    } else if printable >= TEXT_PRINTABLE {
+        PayloadClass::Text
+    } else if format == Some("tls record") {
+        PayloadClass::Encrypted
+    } else if format.is_some() {
+        PayloadClass::Compressed
+    } else if data.len() < MIN_SAMPLE || entropy < DENSE_RATIO * expected_random_entropy(data.len()) {
+        PayloadClass::Binary
+    } else if data.len() >= CHI_SQUARE_SAMPLE && chi_square(data) > CHI_SQUARE_LIMIT {
+        // Dense but uneven: compressed streams without a recognised header
+        PayloadClass::Compressed
+    } else {
+        PayloadClass::Encrypted
+    };
+
+    PayloadProfile {
+        class,
+        entropy,
+        printable,
+        length: data.len(),
+        format: format.map(String::from),
+    }
+}
+
+/// Service name for a port nothing identified, from the class of what it sent
+///
+/// # Returns
+/// * `Option<&str>` - `unknown-encrypted` or `unknown-compressed`; `None` for text,
+///   binary or silent services, which stay plain unknown
+pub fn unknown_service(profile: &PayloadProfile) -> Option<&'static str> {
+    match profile.class {
+        PayloadClass::Encrypted => Some("unknown-encrypted"),
+        PayloadClass::Compressed => Some("unknown-compressed"),
+        _ => None,
+    }
+}
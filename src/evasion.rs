@@ -0,0 +1,748 @@
+//! Composable evasion pipeline.
+//!
+//! `--evasion ttl-jitter,tcp-opts:windows,timing:lognormal,frag` builds an ordered list
+//! of stages. Before a raw-socket technique builds a probe it takes a default
+//! `ProbeShape` (TTL, TCP window and options, padding, fragmentation, send delay) and lets every
+//! stage adjust it in turn; techniques.rs then builds and sends the packet exactly as
+//! shaped. Later stages override earlier ones where they touch the same field.
+//!
+//! The old `-e`/`-E` switches are presets over the same stages (`EvasionPipeline::basic`
+//! and `EvasionPipeline::enhanced`).
+
+use std::fmt;
+use std::str::FromStr;
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+
+use anyhow::{anyhow, Result};
+use lazy_static::lazy_static;
+use parking_lot::RwLock;
+use pnet::packet::tcp::{TcpFlags, TcpOption, TcpOptionPacket};
+use rand::Rng;
+use tokio::io::AsyncWriteExt;
+use tokio::net::TcpStream;
+
+use crate::scheduler::TimingModel;
+
+/// Header fields and send behaviour of one outgoing probe
+#[derive(Debug, Clone)]
+pub struct ProbeShape {
+    /// Probe goes out over IPv6
+    pub ipv6: bool,
+    /// IPv4 TTL / IPv6 hop limit
+    pub ttl: u8,
+    /// TCP window size
+    pub window: u16,
+    /// TCP options on SYN probes, in wire order
+    pub tcp_options: Vec<TcpOption>,
+    /// TCP options on non-SYN probes (FIN/ACK/NULL/XMAS); `None` reuses `tcp_options`
+    pub segment_options: Option<Vec<TcpOption>>,
+    /// Set the IPv4 Don't Fragment flag
+    pub dont_fragment: bool,
+    /// Split the IP payload into fragments of this many bytes (multiple of 8)
+    pub fragment_size: Option<usize>,
+    /// Pad the packet with random payload up to this IP total length
+    pub pad_to: Option<usize>,
+    /// Corrupt the TCP checksum; real hosts drop the probe, lax middleboxes still answer
+    pub bad_checksum: bool,
+    /// Flags ORed into every probe, for illegal combinations such as SYN+FIN
+    pub extra_flags: u8,
+    /// Wait this long before sending
+    pub delay: Duration,
+}
+
+impl ProbeShape {
+    /// Unmodified probe: TTL 64, window 1024, MSS only
+    pub fn new(ipv6: bool) -> Self {
+        ProbeShape {
+            ipv6,
+            ttl: 64,
+            window: 1024,
+            tcp_options: vec![TcpOption::mss(if ipv6 { 1440 } else { 1460 })],
+            segment_options: None,
+            dont_fragment: true,
+            fragment_size: None,
+            pad_to: None,
+            bad_checksum: false,
+            extra_flags: 0,
+            delay: Duration::ZERO,
+        }
+    }
+
+    /// TCP options for a probe with the given flags
+    pub fn options_for(&self, tcp_flags: u8) -> &[TcpOption] {
+        if tcp_flags & TcpFlags::SYN != 0 {
+            &self.tcp_options
+        } else {
+            self.segment_options.as_deref().unwrap_or(&self.tcp_options)
+        }
+    }
+
+    /// Bytes the TCP options for a probe with the given flags occupy, padded to a 32-bit
+    /// boundary (the padding is zero, i.e. End of Option List)
+    pub fn tcp_options_len(&self, tcp_flags: u8) -> usize {
+        let raw: usize = self.options_for(tcp_flags).iter().map(TcpOptionPacket::packet_size).sum();
+        (raw + 3) & !3
+    }
+
+    /// Payload bytes needed to bring a packet with `header_len` bytes of IP and TCP
+    /// headers up to `pad_to` (0 when unset or already larger)
+    pub fn padding_len(&self, header_len: usize) -> usize {
+        self.pad_to.map_or(0, |target| target.min(MAX_PADDED_LEN).saturating_sub(header_len))
+    }
+}
+
+/// Largest packet padding will produce; stays under a 1500-byte Ethernet MTU
+pub const MAX_PADDED_LEN: usize = 1500;
+
+/// One step of the evasion pipeline
+pub trait EvasionStage: Send + Sync {
+    /// Stage as written on the command line
+    fn describe(&self) -> String;
+
+    /// Adjust an outgoing probe
+    fn apply(&self, probe: &mut ProbeShape);
+
+    /// Whether the stage sends malformed traffic that fragile stacks may mishandle
+    fn malformed(&self) -> bool {
+        false
+    }
+}
+
+/// Randomise TTL around the initial value of an OS (`ttl-jitter[:os][:amount]`)
+pub struct TtlJitter {
+    os: String,
+    amount: u8,
+}
+
+impl EvasionStage for TtlJitter {
+    fn describe(&self) -> String {
+        format!("ttl-jitter:{}:{}", self.os, self.amount)
+    }
+
+    fn apply(&self, probe: &mut ProbeShape) {
+        probe.ttl = crate::utils::get_advanced_ttl(resolve_os(&self.os), self.amount);
+    }
+}
+
+/// SYN option layout of a real TCP stack
+#[derive(Debug, Clone, Copy)]
+enum OptionKind {
+    Nop,
+    Mss,
+    WScale(u8),
+    SackPerm,
+    Timestamp,
+}
+
+/// TCP fingerprint of an OS as seen by p0f-style passive sensors
+#[derive(Debug, Clone, Copy)]
+pub struct OsTcpProfile {
+    /// Initial window on SYN
+    window: u16,
+    /// Options in the order the stack emits them
+    syn_options: &'static [OptionKind],
+    /// Stack sends RFC 7323 timestamps, so non-SYN segments carry NOP,NOP,TS too
+    timestamps: bool,
+}
+
+/// Linux 5.x/6.x: MSS,SACK,TS,NOP,WS=7, window 64240
+const LINUX_PROFILE: OsTcpProfile = OsTcpProfile {
+    window: 64240,
+    syn_options: &[OptionKind::Mss, OptionKind::SackPerm, OptionKind::Timestamp, OptionKind::Nop, OptionKind::WScale(7)],
+    timestamps: true,
+};
+
+/// Windows 10/11: MSS,NOP,WS=8,NOP,NOP,SACK, no timestamps, window 64240
+const WINDOWS_PROFILE: OsTcpProfile = OsTcpProfile {
+    window: 64240,
+    syn_options: &[OptionKind::Mss, OptionKind::Nop, OptionKind::WScale(8), OptionKind::Nop, OptionKind::Nop, OptionKind::SackPerm],
+    timestamps: false,
+};
+
+/// macOS 11+: MSS,NOP,WS=6,NOP,NOP,TS,SACK,EOL, window 65535
+const MACOS_PROFILE: OsTcpProfile = OsTcpProfile {
+    window: 65535,
+    syn_options: &[
+        OptionKind::Mss, OptionKind::Nop, OptionKind::WScale(6), OptionKind::Nop, OptionKind::Nop,
+        OptionKind::Timestamp, OptionKind::SackPerm,
+    ],
+    timestamps: true,
+};
+
+impl OsTcpProfile {
+    /// Profile for a concrete OS name (windows, linux, macos)
+    pub fn for_os(os: &str) -> Self {
+        match os {
+            "windows" => WINDOWS_PROFILE,
+            "macos" => MACOS_PROFILE,
+            _ => LINUX_PROFILE,
+        }
+    }
+
+    /// Apply window and option fingerprint to a probe
+    pub fn apply(&self, probe: &mut ProbeShape) {
+        let mss = if probe.ipv6 { 1440 } else { 1460 };
+        let tsval = timestamp_value();
+        probe.window = self.window;
+        probe.tcp_options = self.syn_options.iter().map(|kind| match *kind {
+            OptionKind::Nop => TcpOption::nop(),
+            OptionKind::Mss => TcpOption::mss(mss),
+            OptionKind::WScale(shift) => TcpOption::wscale(shift),
+            OptionKind::SackPerm => TcpOption::sack_perm(),
+            OptionKind::Timestamp => TcpOption::timestamp(tsval, 0),
+        }).collect();
+        // Established-state segments from these stacks carry only NOP,NOP,TS (or nothing)
+        probe.segment_options = Some(if self.timestamps {
+            vec![TcpOption::nop(), TcpOption::nop(), TcpOption::timestamp(tsval, 0)]
+        } else {
+            Vec::new()
+        });
+    }
+}
+
+lazy_static! {
+    /// Per-run clock base so TSval looks like a millisecond uptime counter that advances
+    /// consistently across probes instead of jumping randomly
+    static ref TS_CLOCK: (Instant, u32) = (Instant::now(), crate::utils::scan_rng().gen_range(1_000_000..u32::MAX / 2));
+}
+
+fn timestamp_value() -> u32 {
+    let (start, base) = *TS_CLOCK;
+    base.wrapping_add(start.elapsed().as_millis() as u32)
+}
+
+/// Use an OS's full TCP fingerprint: window, MSS, window scale, SACK, timestamps and
+/// option ordering (`tcp-opts:<os>`)
+pub struct TcpOptionsProfile {
+    os: String,
+}
+
+impl EvasionStage for TcpOptionsProfile {
+    fn describe(&self) -> String {
+        format!("tcp-opts:{}", self.os)
+    }
+
+    fn apply(&self, probe: &mut ProbeShape) {
+        OsTcpProfile::for_os(resolve_os(&self.os)).apply(probe);
+    }
+}
+
+/// Delay each probe according to a timing model (`timing:<model>`)
+pub struct Timing {
+    name: String,
+    model: TimingModel,
+}
+
+impl EvasionStage for Timing {
+    fn describe(&self) -> String {
+        format!("timing:{}", self.name)
+    }
+
+    fn apply(&self, probe: &mut ProbeShape) {
+        probe.delay += self.model.sample();
+    }
+}
+
+/// Send probes as IP fragments (`frag[:bytes]`, default 8 bytes per fragment)
+pub struct Fragment {
+    size: usize,
+}
+
+impl EvasionStage for Fragment {
+    fn describe(&self) -> String {
+        format!("frag:{}", self.size)
+    }
+
+    fn malformed(&self) -> bool {
+        true
+    }
+
+    fn apply(&self, probe: &mut ProbeShape) {
+        probe.fragment_size = Some(self.size);
+        probe.dont_fragment = false;
+    }
+}
+
+/// Send probes with a corrupted TCP checksum (`badsum`)
+pub struct BadChecksum;
+
+impl EvasionStage for BadChecksum {
+    fn describe(&self) -> String {
+        "badsum".to_string()
+    }
+
+    fn malformed(&self) -> bool {
+        true
+    }
+
+    fn apply(&self, probe: &mut ProbeShape) {
+        probe.bad_checksum = true;
+    }
+}
+
+/// Add flags to every probe, e.g. `flags:syn-fin` (`flags:<name-name...|0xNN>`)
+pub struct ExtraFlags {
+    spec: String,
+    flags: u8,
+}
+
+impl EvasionStage for ExtraFlags {
+    fn describe(&self) -> String {
+        format!("flags:{}", self.spec)
+    }
+
+    fn malformed(&self) -> bool {
+        true
+    }
+
+    fn apply(&self, probe: &mut ProbeShape) {
+        probe.extra_flags |= self.flags;
+    }
+}
+
+/// Parse a flag combination such as `syn-fin`, `fin-urg-psh` or `0x03`
+pub fn parse_tcp_flags(spec: &str) -> Result<u8> {
+    if let Some(hex) = spec.strip_prefix("0x") {
+        return u8::from_str_radix(hex, 16).map_err(|_| anyhow!("Invalid TCP flag value '{}'", spec));
+    }
+    spec.split(['-', '+']).try_fold(0u8, |flags, name| {
+        let bit = match name.to_lowercase().as_str() {
+            "fin" => TcpFlags::FIN,
+            "syn" => TcpFlags::SYN,
+            "rst" => TcpFlags::RST,
+            "psh" => TcpFlags::PSH,
+            "ack" => TcpFlags::ACK,
+            "urg" => TcpFlags::URG,
+            "ece" => TcpFlags::ECE,
+            "cwr" => TcpFlags::CWR,
+            other => return Err(anyhow!("Unknown TCP flag '{}': expected fin, syn, rst, psh, ack, urg, ece, cwr or 0xNN", other)),
+        };
+        Ok(flags | bit)
+    })
+}
+
+/// Target size distribution for padded probes
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum PadProfile {
+    /// Uniform between `min` and `max` bytes
+    Range { min: usize, max: usize },
+    /// Mix of packet sizes seen on HTTPS flows: small ACK/control segments, mid-size
+    /// handshake and request records, and full-MSS data segments
+    Https,
+}
+
+impl PadProfile {
+    /// Draw one IP total length
+    pub fn sample(&self) -> usize {
+        let mut rng = crate::utils::scan_rng();
+        match *self {
+            PadProfile::Range { min, max } => rng.gen_range(min..=max.max(min)),
+            PadProfile::Https => match rng.gen_range(0..100) {
+                0..=39 => rng.gen_range(52..=100),
+                40..=59 => rng.gen_range(100..=600),
+                60..=69 => rng.gen_range(600..=1400),
+                _ => rng.gen_range(1400..=MAX_PADDED_LEN),
+            },
+        }
+    }
+}
+
+impl FromStr for PadProfile {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        let s = s.to_lowercase();
+        match s.as_str() {
+            "random" => return Ok(PadProfile::Range { min: 60, max: 300 }),
+            "https" | "tls" => return Ok(PadProfile::Https),
+            _ => {}
+        }
+        let (min, max) = match s.split_once('-') {
+            Some((min, max)) => (min.parse::<usize>(), max.parse::<usize>()),
+            None => (s.parse::<usize>(), s.parse::<usize>()),
+        };
+        match (min, max) {
+            (Ok(min), Ok(max)) if min <= max && max <= MAX_PADDED_LEN => Ok(PadProfile::Range { min, max }),
+            _ => Err(anyhow!("Invalid padding profile '{}': expected random, https, <bytes> or <min>-<max> (at most {})", s, MAX_PADDED_LEN)),
+        }
+    }
+}
+
+/// Pad probes with random payload to a size drawn from a profile (`pad[:random|https|<n>|<min>-<max>]`)
+pub struct Padding {
+    spec: String,
+    profile: PadProfile,
+}
+
+impl EvasionStage for Padding {
+    fn describe(&self) -> String {
+        format!("pad:{}", self.spec)
+    }
+
+    fn apply(&self, probe: &mut ProbeShape) {
+        probe.pad_to = Some(self.profile.sample());
+    }
+}
+
+/// Order in which the frag scan emits fragments
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+pub enum FragmentOrder {
+    /// Ascending offsets
+    #[default]
+    InOrder,
+    /// Descending offsets, so the reassembler sees the final fragment first
+    Reverse,
+    /// Shuffled
+    Random,
+    /// Final fragment first, then the rest in order; defeats sensors that wait for
+    /// offset 0 before they start tracking a datagram
+    LastFirst,
+}
+
+impl FromStr for FragmentOrder {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        match s.to_lowercase().as_str() {
+            "in-order" | "inorder" | "ordered" => Ok(FragmentOrder::InOrder),
+            "reverse" => Ok(FragmentOrder::Reverse),
+            "random" | "shuffle" => Ok(FragmentOrder::Random),
+            "last-first" => Ok(FragmentOrder::LastFirst),
+            other => Err(anyhow!("Unknown fragment order '{}': expected in-order, reverse, random or last-first", other)),
+        }
+    }
+}
+
+/// Overlapping fragments added by the frag scan
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+pub enum FragmentOverlap {
+    /// No overlap
+    #[default]
+    None,
+    /// Resend one fragment with identical data; checks that overlap alone is tolerated
+    Duplicate,
+    /// Send the real fragment, then a conflicting one at the same offset; stacks that keep
+    /// the first copy (Windows, BSD) reassemble the real probe
+    FirstWins,
+    /// Send a conflicting fragment, then the real one at the same offset; stacks that keep
+    /// the last copy (Linux for exact overlaps, many IDS defaults) reassemble the real probe
+    LastWins,
+}
+
+impl FromStr for FragmentOverlap {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        match s.to_lowercase().as_str() {
+            "none" => Ok(FragmentOverlap::None),
+            "duplicate" => Ok(FragmentOverlap::Duplicate),
+            "first-wins" | "favor-old" => Ok(FragmentOverlap::FirstWins),
+            "last-wins" | "favor-new" => Ok(FragmentOverlap::LastWins),
+            other => Err(anyhow!("Unknown fragment overlap '{}': expected none, duplicate, first-wins or last-wins", other)),
+        }
+    }
+}
+
+/// One IP fragment's worth of payload
+#[derive(Debug, Clone)]
+pub struct FragmentPiece {
+    /// Byte offset into the original payload (multiple of 8)
+    pub offset: usize,
+    pub data: Vec<u8>,
+    /// Final fragment (More Fragments clear)
+    pub last: bool,
+}
+
+/// How the frag scan splits, orders and overlaps its fragments
+#[derive(Debug, Clone, Copy, Default)]
+pub struct FragmentPolicy {
+    pub order: FragmentOrder,
+    pub overlap: FragmentOverlap,
+}
+
+/// Offset of the flags byte within a TCP header
+const TCP_FLAGS_OFFSET: usize = 13;
+
+impl FragmentPolicy {
+    /// Split `payload` into `fragment_size`-byte pieces (rounded down to a multiple of 8)
+    /// and apply ordering and overlap
+    pub fn plan(&self, payload: &[u8], fragment_size: usize) -> Vec<FragmentPiece> {
+        let fragment_size = (fragment_size / 8).max(1) * 8;
+        let mut pieces: Vec<FragmentPiece> = payload
+            .chunks(fragment_size)
+            .enumerate()
+            .map(|(index, chunk)| FragmentPiece {
+                offset: index * fragment_size,
+                data: chunk.to_vec(),
+                last: (index + 1) * fragment_size >= payload.len(),
+            })
+            .collect();
+
+        match self.order {
+            FragmentOrder::InOrder => {}
+            FragmentOrder::Reverse => pieces.reverse(),
+            FragmentOrder::Random => rand::seq::SliceRandom::shuffle(pieces.as_mut_slice(), &mut crate::utils::scan_rng()),
+            FragmentOrder::LastFirst => pieces.rotate_right(1),
+        }
+
+        if self.overlap == FragmentOverlap::None {
+            return pieces;
+        }
+
+        // Overlap the fragment carrying the TCP flags: the two reassemblies then disagree
+        // on what kind of segment this is
+        let target = match pieces.iter().position(|p| (p.offset..p.offset + p.data.len()).contains(&TCP_FLAGS_OFFSET)) {
+            Some(index) => index,
+            None => return pieces,
+        };
+        let mut decoy = pieces[target].clone();
+        if self.overlap != FragmentOverlap::Duplicate {
+            crate::utils::scan_rng().fill(decoy.data.as_mut_slice());
+        }
+        match self.overlap {
+            FragmentOverlap::LastWins => pieces.insert(target, decoy),
+            _ => pieces.insert(target + 1, decoy),
+        }
+        pieces
+    }
+}
+
+lazy_static! {
+    /// OS that "random" stands for during this run
+    static ref RANDOM_OS: &'static str = ["windows", "linux", "macos"][crate::utils::scan_rng().gen_range(0..3)];
+}
+
+/// Map "random" to one concrete OS for the whole run, so TTL, window and options of every
+/// probe agree with each other the way a single real host's would
+fn resolve_os(os: &str) -> &str {
+    match os {
+        "random" => *RANDOM_OS,
+        other => other,
+    }
+}
+
+fn parse_os(os: &str) -> Result<String> {
+    match os.to_lowercase().as_str() {
+        os @ ("windows" | "linux" | "macos" | "random") => Ok(os.to_string()),
+        other => Err(anyhow!("Unknown OS profile '{}': expected windows, linux, macos or random", other)),
+    }
+}
+
+/// Ordered set of evasion stages applied to every raw probe
+#[derive(Default)]
+pub struct EvasionPipeline {
+    stages: Vec<Box<dyn EvasionStage>>,
+}
+
+impl EvasionPipeline {
+    /// Preset behind `-e`: TTL jitter and uniform random delays
+    pub fn basic() -> Self {
+        EvasionPipeline {
+            stages: vec![
+                Box::new(TtlJitter { os: "random".to_string(), amount: 1 }),
+                Box::new(Timing { name: "uniform".to_string(), model: TimingModel::Uniform { min: 50, max: 500 } }),
+            ],
+        }
+    }
+
+    /// Preset behind `-E`: OS-consistent TTL and TCP options with log-normal delays
+    pub fn enhanced(os: &str, ttl_jitter: u8) -> Result<Self> {
+        let os = parse_os(os)?;
+        Ok(EvasionPipeline {
+            stages: vec![
+                Box::new(TtlJitter { os: os.clone(), amount: ttl_jitter }),
+                Box::new(TcpOptionsProfile { os }),
+                Box::new(Timing { name: "lognormal".to_string(), model: TimingModel::LogNormal { median: 200.0, sigma: 0.8 } }),
+            ],
+        })
+    }
+
+    /// Add a stage at the end of the pipeline
+    pub fn push(&mut self, stage: Box<dyn EvasionStage>) {
+        self.stages.push(stage);
+    }
+
+    /// Drop the stages that send malformed traffic, for `--safe`
+    ///
+    /// # Returns
+    /// * `Vec<String>` - The dropped stages as written on the command line
+    pub fn remove_malformed(&mut self) -> Vec<String> {
+        let removed = self.stages.iter().filter(|s| s.malformed()).map(|s| s.describe()).collect();
+        self.stages.retain(|s| !s.malformed());
+        removed
+    }
+
+    /// No stages configured
+    pub fn is_empty(&self) -> bool {
+        self.stages.is_empty()
+    }
+
+    /// Shape one outgoing probe
+    pub fn shape(&self, ipv6: bool) -> ProbeShape {
+        let mut probe = ProbeShape::new(ipv6);
+        for stage in &self.stages {
+            stage.apply(&mut probe);
+        }
+        probe
+    }
+}
+
+impl fmt::Display for EvasionPipeline {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if self.stages.is_empty() {
+            return write!(f, "none");
+        }
+        let stages: Vec<String> = self.stages.iter().map(|s| s.describe()).collect();
+        write!(f, "{}", stages.join(","))
+    }
+}
+
+impl FromStr for EvasionPipeline {
+    type Err = anyhow::Error;
+
+    /// Parse a comma-separated stage list, e.g. `ttl-jitter,tcp-opts:windows,timing:lognormal,frag`
+    fn from_str(spec: &str) -> Result<Self> {
+        let mut pipeline = EvasionPipeline::default();
+        for item in spec.split(',').map(str::trim).filter(|s| !s.is_empty()) {
+            let mut parts = item.split(':');
+            let name = parts.next().unwrap_or("").to_lowercase();
+            let args: Vec<&str> = parts.collect();
+            let stage: Box<dyn EvasionStage> = match name.as_str() {
+                "ttl-jitter" | "ttl" => Box::new(TtlJitter {
+                    os: parse_os(args.first().copied().unwrap_or("random"))?,
+                    amount: args.get(1).map(|a| a.parse()).transpose()
+                        .map_err(|_| anyhow!("Invalid TTL jitter amount in '{}'", item))?
+                        .unwrap_or(2),
+                }),
+                "tcp-opts" | "tcp-options" => Box::new(TcpOptionsProfile {
+                    os: parse_os(args.first().copied().unwrap_or("random"))?,
+                }),
+                "timing" => {
+                    let name = if args.is_empty() { "uniform".to_string() } else { args.join(":").to_lowercase() };
+                    Box::new(Timing { model: name.parse()?, name })
+                }
+                "frag" | "fragment" => {
+                    let size: usize = args.first().map(|a| a.parse()).transpose()
+                        .map_err(|_| anyhow!("Invalid fragment size in '{}'", item))?
+                        .unwrap_or(8);
+                    if size == 0 || size % 8 != 0 {
+                        return Err(anyhow!("Fragment size must be a positive multiple of 8 (got {})", size));
+                    }
+                    Box::new(Fragment { size })
+                }
+                "pad" | "padding" => {
+                    let spec = args.first().copied().unwrap_or("random").to_lowercase();
+                    Box::new(Padding { profile: spec.parse()?, spec })
+                }
+                "badsum" | "bad-checksum" => Box::new(BadChecksum),
+                "flags" => {
+                    let spec = args.first().copied()
+                        .ok_or_else(|| anyhow!("flags stage needs a combination, e.g. flags:syn-fin"))?
+                        .to_lowercase();
+                    Box::new(ExtraFlags { flags: parse_tcp_flags(&spec)?, spec })
+                }
+                other => return Err(anyhow!("Unknown evasion stage '{}': expected ttl-jitter, tcp-opts, timing, frag, pad, badsum or flags", other)),
+            };
+            pipeline.push(stage);
+        }
+        Ok(pipeline)
+    }
+}
+
+lazy_static! {
+    /// Process-wide pipeline applied by the raw-socket techniques
+    static ref EVASION_PIPELINE: RwLock<Arc<EvasionPipeline>> = RwLock::new(Arc::new(EvasionPipeline::default()));
+}
+
+/// Install the pipeline used for all subsequent probes
+pub fn set_pipeline(pipeline: EvasionPipeline) {
+    *EVASION_PIPELINE.write() = Arc::new(pipeline);
+}
+
+/// Current pipeline
+pub fn pipeline() -> Arc<EvasionPipeline> {
+    EVASION_PIPELINE.read().clone()
+}
+
+/// Shape a probe with the current pipeline
+pub fn shape_probe(ipv6: bool) -> ProbeShape {
+    pipeline().shape(ipv6)
+}
+
+lazy_static! {
+    /// Fragment ordering/overlap used by the frag scan
+    static ref FRAGMENT_POLICY: RwLock<FragmentPolicy> = RwLock::new(FragmentPolicy::default());
+}
+
+/// Set the frag scan's fragment ordering and overlap policy
+pub fn set_fragment_policy(policy: FragmentPolicy) {
+    *FRAGMENT_POLICY.write() = policy;
+}
+
+/// Current frag scan policy
+pub fn fragment_policy() -> FragmentPolicy {
+    *FRAGMENT_POLICY.read()
+}
+
+/// Splitting of application payloads (mimic, banner and TLS ClientHello) into tiny TCP
+/// segments, so DPI engines that only inspect the first segment of a flow miss the
+/// request. Works on kernel sockets with Nagle disabled: every write leaves as its own
+/// segment, and the delay keeps the stack from coalescing retransmissions.
+#[derive(Debug, Clone, Copy)]
+pub struct SegmentationPolicy {
+    /// Payload bytes per segment
+    pub segment_size: usize,
+    /// Pause between segments
+    pub min_delay: Duration,
+    pub max_delay: Duration,
+}
+
+impl SegmentationPolicy {
+    /// Write `data` in `segment_size` pieces with a random pause between them
+    pub async fn write(&self, stream: &mut TcpStream, data: &[u8]) -> std::io::Result<()> {
+        stream.set_nodelay(true)?;
+        let mut chunks = data.chunks(self.segment_size.max(1)).peekable();
+        while let Some(chunk) = chunks.next() {
+            crate::scheduler::spend(chunk.len() + crate::scheduler::TCP_HEADER_BYTES).await;
+            if let Ok(peer) = stream.peer_addr() {
+                crate::audit::record(|| crate::audit::Probe::new("tcp-payload", "tcp", peer.ip(), Some(peer.port()), chunk.len() + crate::scheduler::TCP_HEADER_BYTES)
+                    .detail(format!("segment of at most {} bytes", self.segment_size)));
+            }
+            stream.write_all(chunk).await?;
+            stream.flush().await?;
+            if chunks.peek().is_some() {
+                let delay = if self.max_delay > self.min_delay {
+                    crate::utils::scan_rng().gen_range(self.min_delay..=self.max_delay)
+                } else {
+                    self.min_delay
+                };
+                tokio::time::sleep(delay).await;
+            }
+        }
+        Ok(())
+    }
+}
+
+lazy_static! {
+    /// Payload segmentation for connect-based probes; `None` writes payloads in one go
+    static ref SEGMENTATION: RwLock<Option<SegmentationPolicy>> = RwLock::new(None);
+}
+
+/// Set payload segmentation for connect-based probes
+pub fn set_segmentation(policy: Option<SegmentationPolicy>) {
+    *SEGMENTATION.write() = policy;
+}
+
+/// Current payload segmentation
+pub fn segmentation() -> Option<SegmentationPolicy> {
+    *SEGMENTATION.read()
+}
+
+/// Write a probe payload, segmented if `--tcp-segment` is set
+pub async fn write_payload(stream: &mut TcpStream, data: &[u8]) -> std::io::Result<()> {
+    match segmentation() {
+        Some(policy) => policy.write(stream, data).await,
+        None => crate::scheduler::metered_write(stream, data).await,
+    }
+}
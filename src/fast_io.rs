@@ -0,0 +1,725 @@
+//! High-performance raw packet I/O for large sweeps (`--fast-io`).
+//!
+//! The default raw scan path opens a fresh raw socket per probe and polls it for the
+//! reply, which caps throughput at a few thousand packets per second. This module
+//! replaces that with a single shared engine per scan:
+//!
+//! - **Transmit**: an `AF_PACKET` socket with a memory-mapped `PACKET_TX_RING`. Probes
+//!   are copied into ring slots and flushed to the kernel in batches with a single
+//!   `send()` call, so the per-packet syscall cost disappears.
+//! - **Receive**: one `AF_PACKET` socket with a classic BPF filter attached so the kernel
+//!   only hands us IPv4 packets for the local address that are ICMP or TCP addressed to
+//!   the probe source port range. A dedicated thread parses replies and dispatches them
+//!   to the waiting probe by target address and source port.
+//!
+//! The engine is per interface rather than per target, so one instance serves a whole
+//! multi-host sweep, every hostgroup included.
+//!
+//! On Windows, which has no raw TCP sockets, the same engine runs on an Npcap datalink
+//! channel instead: frames are injected with `pcap_sendpacket` and a capture thread
+//! dispatches replies. It is the only raw scan path there.
+//!
+//! Linux and Windows; IPv4 only. Other platforms and IPv6 probes use the regular raw
+//! socket path.
+
+use std::collections::HashMap;
+use std::net::{IpAddr, Ipv4Addr};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+use std::time::Duration;
+
+use anyhow::{anyhow, Result};
+use lazy_static::lazy_static;
+use log::{debug, info, warn};
+use parking_lot::{Mutex, RwLock};
+use tokio::sync::oneshot;
+
+/// Reply to a probe sent through the fast I/O engine
+#[derive(Debug)]
+pub enum FastReply {
+    /// TCP segment from the target addressed to the probe's source port
+    Tcp {
+        flags: u16,
+        data: Vec<u8>,
+        source: u16,
+        destination: u16,
+        /// TTL of the IP packet that carried it
+        ttl: u8,
+    },
+    /// ICMP error quoting the probe (type, code)
+    Icmp(u8, u8),
+}
+
+lazy_static! {
+    /// Engine shared by all scan tasks when `--fast-io` is enabled
+    static ref FAST_IO: RwLock<Option<Arc<FastIo>>> = RwLock::new(None);
+}
+
+/// Install (or clear) the process-wide fast I/O engine
+pub fn set_engine(engine: Option<Arc<FastIo>>) {
+    *FAST_IO.write() = engine;
+}
+
+/// Currently installed fast I/O engine, if any
+pub fn engine() -> Option<Arc<FastIo>> {
+    FAST_IO.read().clone()
+}
+
+/// Key used to route replies back to probes: (target address, our source port)
+type ProbeKey = (Ipv4Addr, u16);
+
+/// Shared AF_PACKET transmit ring and filtered receiver
+///
+/// One engine serves every host of the run: it is bound to the interface that owns the
+/// local address, resolves the next hop of each target on first use and dispatches
+/// replies on (target address, source port).
+pub struct FastIo {
+    #[cfg(target_os = "linux")]
+    tx: Mutex<linux::TxRing>,
+    #[cfg(windows)]
+    tx: Mutex<Box<dyn pnet_datalink::DataLinkSender>>,
+    /// Interface frames leave through
+    iface: pnet_datalink::NetworkInterface,
+    /// Source address of the probes; also used to prime the neighbour cache
+    local_ip: Ipv4Addr,
+    /// Source MAC of every frame (the spoofed one with `--spoof-mac`)
+    source_mac: pnet_datalink::MacAddr,
+    /// Next hop MAC of each target seen so far
+    next_hops: Mutex<HashMap<Ipv4Addr, pnet_datalink::MacAddr>>,
+    /// Probes waiting for a reply
+    waiters: Arc<Mutex<HashMap<ProbeKey, oneshot::Sender<FastReply>>>>,
+    /// Signals the receive and flush threads to exit
+    shutdown: Arc<AtomicBool>,
+}
+
+impl FastIo {
+    /// Set up the engine on the interface that owns `local_ip`
+    ///
+    /// # Arguments
+    /// * `local_ip` - Local IPv4 address; selects the interface to bind
+    ///
+    /// # Returns
+    /// * `Result<Arc<FastIo>>` - Running engine, or an error if AF_PACKET is unavailable
+    ///
+    /// # Opsec Considerations
+    /// - Frames bypass the kernel routing decision, so next hop MACs are resolved by the
+    ///   engine: once per on-link target, and once per interface for the gateway
+    /// - Honours `--spoof-mac` for the source address of emitted frames
+    #[cfg(target_os = "linux")]
+    pub fn new(local_ip: Ipv4Addr) -> Result<Arc<Self>> {
+        let iface = crate::mac_vendor::interface_for_ip(IpAddr::V4(local_ip))
+            .ok_or_else(|| anyhow!("No interface owns local address {}", local_ip))?;
+
+        let source_mac = crate::mac_vendor::spoofed_mac()
+            .or(iface.mac)
+            .ok_or_else(|| anyhow!("Interface {} has no MAC address (fast I/O needs an Ethernet interface)", iface.name))?;
+
+        let tx = linux::TxRing::new(iface.index)?;
+        let rx_fd = linux::open_filtered_rx(iface.index, local_ip)?;
+
+        let engine = Arc::new(FastIo {
+            tx: Mutex::new(tx),
+            iface,
+            local_ip,
+            source_mac,
+            next_hops: Mutex::new(HashMap::new()),
+            waiters: Arc::new(Mutex::new(HashMap::new())),
+            shutdown: Arc::new(AtomicBool::new(false)),
+        });
+
+        // Receiver thread: parse filtered frames and wake the matching probe
+        {
+            let waiters = engine.waiters.clone();
+            let shutdown = engine.shutdown.clone();
+            std::thread::spawn(move || linux::receive_loop(rx_fd, waiters, shutdown));
+        }
+
+        // Flush thread: pushes out partially filled batches so low-rate scans don't stall
+        {
+            let weak = Arc::downgrade(&engine);
+            std::thread::spawn(move || loop {
+                std::thread::sleep(Duration::from_millis(1));
+                match weak.upgrade() {
+                    Some(engine) if !engine.shutdown.load(Ordering::Relaxed) => engine.tx.lock().flush(),
+                    _ => break,
+                }
+            });
+        }
+
+        info!("Fast I/O enabled on {} (source {}, MAC {})", engine.iface.name, local_ip, source_mac);
+        Ok(engine)
+    }
+
+    /// Set up the engine on an Npcap datalink channel (Windows)
+    ///
+    /// # Opsec Considerations
+    /// - Npcap injects frames below the Windows TCP/IP stack, which would otherwise answer
+    ///   SYN-ACKs for connections it does not know about with RSTs; it does not here, but
+    ///   the host firewall never sees the probes either
+    #[cfg(windows)]
+    pub fn new(local_ip: Ipv4Addr) -> Result<Arc<Self>> {
+        if !npcap_available() {
+            return Err(anyhow!("Npcap is not installed (raw scans on Windows need it: https://npcap.com)"));
+        }
+        let iface = crate::mac_vendor::interface_for_ip(IpAddr::V4(local_ip))
+            .ok_or_else(|| anyhow!("No Npcap adapter owns local address {}", local_ip))?;
+        let source_mac = crate::mac_vendor::spoofed_mac()
+            .or(iface.mac)
+            .ok_or_else(|| anyhow!("Adapter {} has no MAC address", iface.name))?;
+
+        // A read timeout lets the capture thread notice shutdown
+        let config = pnet_datalink::Config {
+            read_timeout: Some(Duration::from_millis(100)),
+            ..Default::default()
+        };
+        let (tx, mut rx) = match pnet_datalink::channel(&iface, config) {
+            Ok(pnet_datalink::Channel::Ethernet(tx, rx)) => (tx, rx),
+            Ok(_) => return Err(anyhow!("Unsupported datalink channel type on {}", iface.name)),
+            Err(e) => return Err(anyhow!("Failed to open Npcap adapter {}: {} (run as Administrator)", iface.name, e)),
+        };
+
+        let engine = Arc::new(FastIo {
+            tx: Mutex::new(tx),
+            iface,
+            local_ip,
+            source_mac,
+            next_hops: Mutex::new(HashMap::new()),
+            waiters: Arc::new(Mutex::new(HashMap::new())),
+            shutdown: Arc::new(AtomicBool::new(false)),
+        });
+
+        // Capture thread: other traffic on the adapter simply matches no waiter
+        {
+            let waiters = engine.waiters.clone();
+            let shutdown = engine.shutdown.clone();
+            std::thread::spawn(move || {
+                while !shutdown.load(Ordering::Relaxed) {
+                    let Ok(frame) = rx.next() else { continue };
+                    if let Some((key, reply)) = parse_reply(frame) {
+                        if let Some(waiter) = waiters.lock().remove(&key) {
+                            let _ = waiter.send(reply);
+                        }
+                    }
+                }
+            });
+        }
+
+        info!("Npcap raw I/O enabled on {} (source {}, MAC {})", engine.iface.name, local_ip, source_mac);
+        Ok(engine)
+    }
+
+    /// AF_PACKET rings are Linux-specific and Npcap is Windows-specific
+    #[cfg(not(any(target_os = "linux", windows)))]
+    pub fn new(_local_ip: Ipv4Addr) -> Result<Arc<Self>> {
+        Err(anyhow!("Fast I/O (AF_PACKET) is only supported on Linux"))
+    }
+
+    /// Ethernet header for a frame to `target_ip`
+    ///
+    /// The next hop is resolved on a blocking thread the first time a target is seen;
+    /// off-link targets share the gateway's cached MAC.
+    async fn eth_header(&self, target_ip: Ipv4Addr) -> Result<[u8; 14]> {
+        let cached = self.next_hops.lock().get(&target_ip).copied();
+        let dest_mac = match cached {
+            Some(mac) => mac,
+            None => {
+                let (iface, local_ip) = (self.iface.clone(), self.local_ip);
+                let mac = tokio::task::spawn_blocking(move || {
+                    crate::mac_vendor::resolve_next_hop_mac(&iface, IpAddr::V4(local_ip), IpAddr::V4(target_ip))
+                }).await??;
+                debug!("Fast I/O next hop for {} is {}", target_ip, mac);
+                self.next_hops.lock().insert(target_ip, mac);
+                mac
+            }
+        };
+
+        let mut header = [0u8; 14];
+        header[0..6].copy_from_slice(&dest_mac.octets());
+        header[6..12].copy_from_slice(&self.source_mac.octets());
+        header[12..14].copy_from_slice(&0x0800u16.to_be_bytes());
+        Ok(header)
+    }
+
+    /// Queue an IPv4 packet for transmission and wait for the reply addressed to `source_port`
+    ///
+    /// # Arguments
+    /// * `target_ip` - Destination of the probe
+    /// * `source_port` - Source port of the probe; replies are matched on it
+    /// * `ip_packet` - Complete IPv4 packet including headers
+    /// * `timeout_duration` - How long to wait for a reply
+    ///
+    /// # Returns
+    /// * `Result<Option<FastReply>>` - The reply, or None on timeout
+    pub async fn probe(
+        &self,
+        target_ip: Ipv4Addr,
+        source_port: u16,
+        ip_packet: &[u8],
+        timeout_duration: Duration,
+    ) -> Result<Option<FastReply>> {
+        let key = (target_ip, source_port);
+        let (reply_tx, reply_rx) = oneshot::channel();
+
+        // Register before sending so fast replies are not lost
+        self.waiters.lock().insert(key, reply_tx);
+
+        if let Err(e) = self.send(target_ip, ip_packet).await {
+            self.waiters.lock().remove(&key);
+            return Err(e);
+        }
+
+        let reply = match tokio::time::timeout(timeout_duration, reply_rx).await {
+            Ok(Ok(reply)) => Some(reply),
+            _ => None,
+        };
+        self.waiters.lock().remove(&key);
+        Ok(reply)
+    }
+
+    /// Queue an IPv4 packet without registering for a reply
+    ///
+    /// Used by the stateless sweep, which validates replies itself instead of tracking probes.
+    pub async fn send_only(&self, target_ip: Ipv4Addr, ip_packet: &[u8]) -> Result<()> {
+        self.send(target_ip, ip_packet).await
+    }
+
+    /// Copy a frame into the TX ring
+    #[cfg(target_os = "linux")]
+    async fn send(&self, target_ip: Ipv4Addr, ip_packet: &[u8]) -> Result<()> {
+        let eth_header = self.eth_header(target_ip).await?;
+        let mut frame = Vec::with_capacity(eth_header.len() + ip_packet.len());
+        frame.extend_from_slice(&eth_header);
+        frame.extend_from_slice(ip_packet);
+        self.tx.lock().queue(&frame)
+    }
+
+    /// Inject a frame through Npcap
+    #[cfg(windows)]
+    async fn send(&self, target_ip: Ipv4Addr, ip_packet: &[u8]) -> Result<()> {
+        let eth_header = self.eth_header(target_ip).await?;
+        let mut frame = Vec::with_capacity(eth_header.len() + ip_packet.len());
+        frame.extend_from_slice(&eth_header);
+        frame.extend_from_slice(ip_packet);
+        match self.tx.lock().send_to(&frame, None) {
+            Some(Ok(())) => Ok(()),
+            Some(Err(e)) => Err(anyhow!("Npcap failed to send frame: {}", e)),
+            None => Err(anyhow!("Npcap adapter refused the frame")),
+        }
+    }
+
+    #[cfg(not(any(target_os = "linux", windows)))]
+    async fn send(&self, _target_ip: Ipv4Addr, _ip_packet: &[u8]) -> Result<()> {
+        Err(anyhow!("Fast I/O (AF_PACKET) is only supported on Linux"))
+    }
+}
+
+impl Drop for FastIo {
+    fn drop(&mut self) {
+        self.shutdown.store(true, Ordering::Relaxed);
+        #[cfg(target_os = "linux")]
+        self.tx.lock().flush();
+        debug!("Fast I/O engine shut down");
+    }
+}
+
+/// Whether the Npcap driver is installed (always false off Windows)
+///
+/// Npcap installs its DLLs under `System32\Npcap`; WinPcap-compatible mode also puts
+/// `wpcap.dll` in System32 itself.
+pub fn npcap_available() -> bool {
+    #[cfg(windows)]
+    {
+        let system_root = std::env::var("SystemRoot").unwrap_or_else(|_| "C:\\Windows".to_string());
+        let system32 = std::path::Path::new(&system_root).join("System32");
+        system32.join("Npcap").join("Packet.dll").exists() || system32.join("wpcap.dll").exists()
+    }
+    #[cfg(not(windows))]
+    {
+        false
+    }
+}
+
+/// Parse an Ethernet frame into a probe key and reply
+///
+/// Handles direct TCP replies and ICMP errors that quote our original probe.
+fn parse_reply(frame: &[u8]) -> Option<(ProbeKey, FastReply)> {
+    use pnet::packet::ethernet::{EtherTypes, EthernetPacket};
+    use pnet::packet::ip::IpNextHeaderProtocols;
+    use pnet::packet::ipv4::Ipv4Packet;
+    use pnet::packet::tcp::TcpPacket;
+    use pnet::packet::Packet;
+
+    let eth = EthernetPacket::new(frame)?;
+    if eth.get_ethertype() != EtherTypes::Ipv4 {
+        return None;
+    }
+    let ip = Ipv4Packet::new(eth.payload())?;
+
+    match ip.get_next_level_protocol() {
+        IpNextHeaderProtocols::Tcp => {
+            let tcp = TcpPacket::new(ip.payload())?;
+            Some((
+                (ip.get_source(), tcp.get_destination()),
+                FastReply::Tcp {
+                    flags: tcp.get_flags().into(),
+                    data: tcp.packet().to_vec(),
+                    source: tcp.get_source(),
+                    destination: tcp.get_destination(),
+                    ttl: ip.get_ttl(),
+                },
+            ))
+        }
+        IpNextHeaderProtocols::Icmp => {
+            // ICMP error: 8 byte ICMP header followed by the original IP header + 8 bytes
+            let icmp = ip.payload();
+            if icmp.len() < 8 {
+                return None;
+            }
+            let (icmp_type, icmp_code) = (icmp[0], icmp[1]);
+            let quoted = Ipv4Packet::new(&icmp[8..])?;
+            let quoted_l4 = icmp.get(8 + quoted.get_header_length() as usize * 4..)?;
+            if quoted_l4.len() < 4 {
+                return None;
+            }
+            let source_port = u16::from_be_bytes([quoted_l4[0], quoted_l4[1]]);
+            Some(((quoted.get_destination(), source_port), FastReply::Icmp(icmp_type, icmp_code)))
+        }
+        _ => None,
+    }
+}
+
+#[cfg(target_os = "linux")]
+mod linux {
+    //! Raw AF_PACKET plumbing. pnet's datalink channel does not expose ring setup or
+    //! BPF attachment on Linux, so this talks to the kernel through libc directly.
+
+    use super::*;
+    use std::os::raw::c_void;
+
+    const ETH_P_IP: u16 = 0x0800;
+    const PACKET_VERSION: libc::c_int = 10;
+    const PACKET_TX_RING: libc::c_int = 13;
+    const TPACKET_V2: libc::c_int = 1;
+    const TP_STATUS_AVAILABLE: u32 = 0;
+    const TP_STATUS_SEND_REQUEST: u32 = 1;
+    const TP_STATUS_WRONG_FORMAT: u32 = 1 << 2;
+
+    /// Ring geometry: 64 blocks of 64KiB, 2KiB frames -> 2048 slots
+    const BLOCK_SIZE: u32 = 1 << 16;
+    const BLOCK_COUNT: u32 = 64;
+    const FRAME_SIZE: u32 = 1 << 11;
+    /// Frame data starts after the aligned tpacket2_hdr
+    const FRAME_DATA_OFFSET: usize = 32;
+    /// Flush to the kernel once this many frames are queued
+    const FLUSH_BATCH: usize = 64;
+
+    #[repr(C)]
+    struct TpacketReq {
+        tp_block_size: u32,
+        tp_block_nr: u32,
+        tp_frame_size: u32,
+        tp_frame_nr: u32,
+    }
+
+    #[repr(C)]
+    struct Tpacket2Hdr {
+        tp_status: u32,
+        tp_len: u32,
+        tp_snaplen: u32,
+        tp_mac: u16,
+        tp_net: u16,
+        tp_sec: u32,
+        tp_nsec: u32,
+        tp_vlan_tci: u16,
+        tp_vlan_tpid: u16,
+        tp_padding: [u8; 4],
+    }
+
+    /// Memory-mapped PACKET_TX_RING
+    pub(super) struct TxRing {
+        fd: libc::c_int,
+        ring: *mut u8,
+        ring_len: usize,
+        frame_count: usize,
+        next: usize,
+        pending: usize,
+    }
+
+    // The ring pointer is only touched while holding the engine's mutex
+    unsafe impl Send for TxRing {}
+
+    fn last_error(what: &str) -> anyhow::Error {
+        anyhow!("{} failed: {} (fast I/O requires root/CAP_NET_RAW)", what, std::io::Error::last_os_error())
+    }
+
+    fn bind_to_interface(fd: libc::c_int, ifindex: u32, protocol: u16) -> Result<()> {
+        let mut addr: libc::sockaddr_ll = unsafe { std::mem::zeroed() };
+        addr.sll_family = libc::AF_PACKET as u16;
+        addr.sll_protocol = protocol.to_be();
+        addr.sll_ifindex = ifindex as i32;
+        let rc = unsafe {
+            libc::bind(
+                fd,
+                &addr as *const libc::sockaddr_ll as *const libc::sockaddr,
+                std::mem::size_of::<libc::sockaddr_ll>() as libc::socklen_t,
+            )
+        };
+        if rc < 0 {
+            return Err(last_error("bind(AF_PACKET)"));
+        }
+        Ok(())
+    }
+
+    impl TxRing {
+        pub(super) fn new(ifindex: u32) -> Result<Self> {
+            unsafe {
+                // Protocol 0: transmit only, the kernel won't queue received frames here
+                let fd = libc::socket(libc::AF_PACKET, libc::SOCK_RAW, 0);
+                if fd < 0 {
+                    return Err(last_error("socket(AF_PACKET)"));
+                }
+
+                let version = TPACKET_V2;
+                if libc::setsockopt(fd, libc::SOL_PACKET, PACKET_VERSION,
+                    &version as *const _ as *const c_void, std::mem::size_of_val(&version) as libc::socklen_t) < 0 {
+                    libc::close(fd);
+                    return Err(last_error("setsockopt(PACKET_VERSION)"));
+                }
+
+                let req = TpacketReq {
+                    tp_block_size: BLOCK_SIZE,
+                    tp_block_nr: BLOCK_COUNT,
+                    tp_frame_size: FRAME_SIZE,
+                    tp_frame_nr: (BLOCK_SIZE / FRAME_SIZE) * BLOCK_COUNT,
+                };
+                if libc::setsockopt(fd, libc::SOL_PACKET, PACKET_TX_RING,
+                    &req as *const _ as *const c_void, std::mem::size_of::<TpacketReq>() as libc::socklen_t) < 0 {
+                    libc::close(fd);
+                    return Err(last_error("setsockopt(PACKET_TX_RING)"));
+                }
+
+                let ring_len = (BLOCK_SIZE * BLOCK_COUNT) as usize;
+                let ring = libc::mmap(std::ptr::null_mut(), ring_len, libc::PROT_READ | libc::PROT_WRITE,
+                    libc::MAP_SHARED, fd, 0);
+                if ring == libc::MAP_FAILED {
+                    libc::close(fd);
+                    return Err(last_error("mmap(PACKET_TX_RING)"));
+                }
+
+                // Protocol 0 again, so binding picks the interface without subscribing to its traffic
+                if let Err(e) = bind_to_interface(fd, ifindex, 0) {
+                    libc::munmap(ring, ring_len);
+                    libc::close(fd);
+                    return Err(e);
+                }
+
+                Ok(TxRing {
+                    fd,
+                    ring: ring as *mut u8,
+                    ring_len,
+                    frame_count: req.tp_frame_nr as usize,
+                    next: 0,
+                    pending: 0,
+                })
+            }
+        }
+
+        /// Copy a frame into the next free slot, flushing when a batch is full
+        pub(super) fn queue(&mut self, frame: &[u8]) -> Result<()> {
+            if frame.len() > FRAME_SIZE as usize - FRAME_DATA_OFFSET {
+                return Err(anyhow!("Frame of {} bytes exceeds TX ring slot size", frame.len()));
+            }
+
+            let slot = unsafe { self.ring.add(self.next * FRAME_SIZE as usize) };
+            let header = slot as *mut Tpacket2Hdr;
+
+            // Wait for the kernel to release the slot if the ring has wrapped around
+            let mut spins = 0;
+            loop {
+                let status = unsafe { std::ptr::read_volatile(&(*header).tp_status) };
+                if status == TP_STATUS_AVAILABLE || status & TP_STATUS_WRONG_FORMAT != 0 {
+                    break;
+                }
+                self.flush();
+                spins += 1;
+                if spins > 1000 {
+                    return Err(anyhow!("TX ring is full; the interface is not draining frames"));
+                }
+                std::thread::yield_now();
+            }
+
+            unsafe {
+                std::ptr::copy_nonoverlapping(frame.as_ptr(), slot.add(FRAME_DATA_OFFSET), frame.len());
+                (*header).tp_len = frame.len() as u32;
+                std::sync::atomic::fence(Ordering::Release);
+                std::ptr::write_volatile(&mut (*header).tp_status, TP_STATUS_SEND_REQUEST);
+            }
+
+            self.next = (self.next + 1) % self.frame_count;
+            self.pending += 1;
+            if self.pending >= FLUSH_BATCH {
+                self.flush();
+            }
+            Ok(())
+        }
+
+        /// Hand all queued frames to the kernel with a single syscall
+        pub(super) fn flush(&mut self) {
+            if self.pending == 0 {
+                return;
+            }
+            let rc = unsafe { libc::send(self.fd, std::ptr::null(), 0, libc::MSG_DONTWAIT) };
+            if rc < 0 {
+                let err = std::io::Error::last_os_error();
+                if err.kind() != std::io::ErrorKind::WouldBlock {
+                    warn!("Fast I/O TX ring flush failed: {}", err);
+                }
+            }
+            self.pending = 0;
+        }
+    }
+
+    impl Drop for TxRing {
+        fn drop(&mut self) {
+            unsafe {
+                libc::munmap(self.ring as *mut c_void, self.ring_len);
+                libc::close(self.fd);
+            }
+        }
+    }
+
+    /// Open a receive socket that only sees replies to fast I/O probes
+    ///
+    /// Probes from every target share the socket, so the filter keys on what the probes
+    /// themselves carry instead of a target address: IPv4 to the local address, and
+    /// either ICMP (errors quoting a probe) or unfragmented TCP addressed to the probe
+    /// source port range.
+    pub(super) fn open_filtered_rx(ifindex: u32, local_ip: Ipv4Addr) -> Result<libc::c_int> {
+        // Classic BPF, equivalent to:
+        //   ip dst host <local> and (icmp or (tcp and ip[6:2] & 0x1fff = 0
+        //                                     and tcp dst portrange <lo>-<hi>))
+        let local = u32::from(local_ip);
+        let ports = crate::utils::PROBE_PORT_RANGE;
+        let mut filter = [
+            libc::sock_filter { code: 0x28, jt: 0, jf: 0, k: 12 },                      // ldh [12]      (ethertype)
+            libc::sock_filter { code: 0x15, jt: 0, jf: 12, k: 0x0800 },                 // jeq #IPv4
+            libc::sock_filter { code: 0x20, jt: 0, jf: 0, k: 30 },                      // ld [30]       (ip dst)
+            libc::sock_filter { code: 0x15, jt: 0, jf: 10, k: local },                  // jeq #local
+            libc::sock_filter { code: 0x30, jt: 0, jf: 0, k: 23 },                      // ldb [23]      (ip proto)
+            libc::sock_filter { code: 0x15, jt: 7, jf: 0, k: 1 },                       // jeq #icmp     -> accept
+            libc::sock_filter { code: 0x15, jt: 0, jf: 7, k: 6 },                       // jeq #tcp
+            libc::sock_filter { code: 0x28, jt: 0, jf: 0, k: 20 },                      // ldh [20]      (flags/frag offset)
+            libc::sock_filter { code: 0x45, jt: 5, jf: 0, k: 0x1FFF },                  // jset #0x1fff  (non-first fragment)
+            libc::sock_filter { code: 0xB1, jt: 0, jf: 0, k: 14 },                      // ldxb 4*([14]&0xf)
+            libc::sock_filter { code: 0x48, jt: 0, jf: 0, k: 16 },                      // ldh [x+16]    (tcp dst port)
+            libc::sock_filter { code: 0x35, jt: 0, jf: 2, k: ports.start as u32 },      // jge #lo
+            libc::sock_filter { code: 0x25, jt: 1, jf: 0, k: ports.end as u32 - 1 },    // jgt #hi
+            libc::sock_filter { code: 0x06, jt: 0, jf: 0, k: 0xFFFF },                  // ret #65535
+            libc::sock_filter { code: 0x06, jt: 0, jf: 0, k: 0 },                       // ret #0
+        ];
+        let program = libc::sock_fprog {
+            len: filter.len() as u16,
+            filter: filter.as_mut_ptr(),
+        };
+
+        unsafe {
+            let fd = libc::socket(libc::AF_PACKET, libc::SOCK_RAW, (ETH_P_IP).to_be() as libc::c_int);
+            if fd < 0 {
+                return Err(last_error("socket(AF_PACKET)"));
+            }
+
+            if libc::setsockopt(fd, libc::SOL_SOCKET, libc::SO_ATTACH_FILTER,
+                &program as *const _ as *const c_void, std::mem::size_of::<libc::sock_fprog>() as libc::socklen_t) < 0 {
+                libc::close(fd);
+                return Err(last_error("setsockopt(SO_ATTACH_FILTER)"));
+            }
+
+            // Short receive timeout so the thread notices shutdown
+            let tv = libc::timeval { tv_sec: 0, tv_usec: 100_000 };
+            libc::setsockopt(fd, libc::SOL_SOCKET, libc::SO_RCVTIMEO,
+                &tv as *const _ as *const c_void, std::mem::size_of::<libc::timeval>() as libc::socklen_t);
+
+            // Large receive buffer so bursts of replies are not dropped
+            let rcvbuf: libc::c_int = 8 * 1024 * 1024;
+            libc::setsockopt(fd, libc::SOL_SOCKET, libc::SO_RCVBUF,
+                &rcvbuf as *const _ as *const c_void, std::mem::size_of_val(&rcvbuf) as libc::socklen_t);
+
+            if let Err(e) = bind_to_interface(fd, ifindex, ETH_P_IP) {
+                libc::close(fd);
+                return Err(e);
+            }
+            Ok(fd)
+        }
+    }
+
+    /// Receive filtered frames and dispatch them to waiting probes until shutdown
+    pub(super) fn receive_loop(
+        fd: libc::c_int,
+        waiters: Arc<Mutex<HashMap<ProbeKey, oneshot::Sender<FastReply>>>>,
+        shutdown: Arc<AtomicBool>,
+    ) {
+        let mut buf = vec![0u8; 65536];
+        while !shutdown.load(Ordering::Relaxed) {
+            let len = unsafe { libc::recv(fd, buf.as_mut_ptr() as *mut c_void, buf.len(), 0) };
+            if len <= 0 {
+                // Timeout or transient error; loop around to re-check shutdown
+                continue;
+            }
+
+            if let Some((key, reply)) = parse_reply(&buf[..len as usize]) {
+                if let Some(waiter) = waiters.lock().remove(&key) {
+                    let _ = waiter.send(reply);
+                }
+            }
+        }
+        unsafe { libc::close(fd) };
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const TARGET: [u8; 4] = [192, 0, 2, 2];
+
+    /// Ethernet frame carrying an ICMP port unreachable from the target that quotes
+    /// `quoted`, the start of our original probe
+    fn icmp_error_frame(quoted: &[u8]) -> Vec<u8> {
+        let mut frame = vec![0u8; 14];
+        frame[12..14].copy_from_slice(&[0x08, 0x00]);
+        let total_len = 20 + 8 + quoted.len();
+        let mut ip = vec![0x45, 0, (total_len >> 8) as u8, total_len as u8, 0, 0, 0, 0, 64, 1, 0, 0];
+        ip.extend_from_slice(&TARGET);
+        ip.extend_from_slice(&[192, 0, 2, 1]);
+        frame.extend_from_slice(&ip);
+        frame.extend_from_slice(&[3, 3, 0, 0, 0, 0, 0, 0]);
+        frame.extend_from_slice(quoted);
+        frame
+    }
+
+    /// Quoted IPv4 header from us to the target with the given header length in words
+    fn quoted_header(ihl: u8) -> Vec<u8> {
+        let mut header = vec![0x40 | ihl, 0, 0, 28, 0, 0, 0, 0, 64, 17, 0, 0, 192, 0, 2, 1];
+        header.extend_from_slice(&TARGET);
+        header
+    }
+
+    #[test]
+    fn icmp_error_is_routed_to_the_quoted_source_port() {
+        let mut quoted = quoted_header(5);
+        quoted.extend_from_slice(&[0xc0, 0x01, 0x00, 0x35, 0, 8, 0, 0]);
+        let (key, reply) = parse_reply(&icmp_error_frame(&quoted)).expect("valid ICMP error");
+        assert_eq!(key, (Ipv4Addr::from(TARGET), 0xc001));
+        assert!(matches!(reply, FastReply::Icmp(3, 3)));
+    }
+
+    #[test]
+    fn truncated_quoted_header_is_dropped() {
+        // IHL claims 60 bytes of header but only the fixed 20 were quoted
+        assert!(parse_reply(&icmp_error_frame(&quoted_header(15))).is_none());
+        // Header fits but the quoted ports were cut off
+        let mut quoted = quoted_header(5);
+        quoted.extend_from_slice(&[0xc0]);
+        assert!(parse_reply(&icmp_error_frame(&quoted)).is_none());
+    }
+}
@@ -0,0 +1,369 @@
+//! Hostile-network detection.
+//!
+//! Inline IPSs and some firewalls answer probes on the host's behalf: they reset
+//! connections to "closed" ports, complete handshakes on every port to waste a
+//! scanner's time, or reply from a fixed delay. Every raw TCP reply is fed to a
+//! per-target monitor that looks for the tells:
+//! - RSTs whose TTL differs from the host's SYN-ACKs (sent from a different hop), or
+//!   that answer SYNs with the same non-zero window (a host's own stack sends 0)
+//! - SYN-ACKs from nearly every probed port
+//! - Replies arriving with the same delay whatever the port, over a path long enough
+//!   that a real host would jitter
+//!
+//! Each sign is warned about once, as soon as it shows, and reported with the scan
+//! results so the ports it covers can be marked unreliable.
+//!
+//! The same monitor watches for signs that the scan itself has been noticed and is
+//! being countered mid-run:
+//! - A probe type the host used to answer suddenly goes unanswered everywhere
+//! - Replies slow down by an order of magnitude (a tarpit switching on)
+//! - A port that accepted connections starts resetting them
+//!
+//! These are warned about as well; with `--abort-on-countermeasure` the first one
+//! halts every send path and ends the run (see `arm_abort`).
+
+use std::collections::{BTreeSet, HashMap};
+use std::io;
+use std::net::{IpAddr, SocketAddr};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::time::Duration;
+
+use lazy_static::lazy_static;
+use log::{error, warn};
+use parking_lot::Mutex;
+use pnet::packet::tcp::TcpFlags;
+use tokio::sync::Notify;
+
+use crate::models::{InterferenceKind, InterferenceSign};
+use crate::transport::{RawResponse, TcpProbe};
+
+/// RSTs to SYN probes needed before their TTL or window is judged
+const MIN_RSTS: usize = 5;
+
+/// SYN-ACKs needed to know the host's own TTL
+const MIN_SYN_ACKS: usize = 3;
+
+/// Distinct ports SYN-probed before SYN-ACK coverage is judged
+const MIN_SYN_PORTS: usize = 50;
+
+/// Share of SYN-probed ports that must answer SYN-ACK to look forged
+const SYN_ACK_SHARE: f64 = 0.95;
+
+/// Replies timed before their spread is judged
+const MIN_TIMED_REPLIES: usize = 20;
+
+/// Mean round trip below which uniform timing is expected (same segment or host)
+const MIN_UNIFORM_RTT_MS: f64 = 2.0;
+
+/// Standard deviation, as a share of the mean, below which timing counts as identical
+const MAX_TIMING_SPREAD: f64 = 0.02;
+
+/// Probes of one kind whose reply share sets the baseline for sudden filtering
+const EARLY_PROBES: usize = 40;
+
+/// Baseline reply share above which a kind of probe counts as answered
+const MIN_EARLY_SHARE: f64 = 0.5;
+
+/// Unanswered probes in a row, after an answered baseline, that count as sudden filtering
+const SILENT_STREAK: usize = 50;
+
+/// Factor by which recent round trips must exceed the baseline to look like a tarpit
+const TARPIT_FACTOR: f64 = 5.0;
+
+/// Milliseconds recent round trips must exceed the baseline by, so fast links stay quiet
+const TARPIT_MIN_EXTRA_MS: f64 = 200.0;
+
+/// Weight of the newest reply in the recent round-trip average
+const RECENT_WEIGHT: f64 = 0.2;
+
+/// Replies to one kind of probe (by TCP flags) on one target
+#[derive(Default)]
+struct ProbeStats {
+    probes: usize,
+    replies: usize,
+    /// Reply share over the first `EARLY_PROBES` probes
+    early_share: Option<f64>,
+    /// Probes since the last reply
+    silent: usize,
+}
+
+/// Replies seen from one target during the current scan
+#[derive(Default)]
+struct HostMonitor {
+    syn_ports: BTreeSet<u16>,
+    syn_ack_ports: BTreeSet<u16>,
+    syn_ack_ttls: HashMap<u8, usize>,
+    rst_ports: BTreeSet<u16>,
+    rst_ttls: HashMap<u8, usize>,
+    rst_windows: HashMap<u16, usize>,
+    rst_count: usize,
+    reply_ports: BTreeSet<u16>,
+    timed: usize,
+    rtt_sum: f64,
+    rtt_sum_sq: f64,
+    raised: Vec<(InterferenceKind, String)>,
+    by_flags: HashMap<u8, ProbeStats>,
+    /// Mean round trip of the first `MIN_TIMED_REPLIES` replies
+    baseline_rtt: Option<f64>,
+    recent_rtt: f64,
+    accepted_ports: BTreeSet<u16>,
+    /// A countermeasure was already reported for this target
+    countered: bool,
+}
+
+lazy_static! {
+    /// Monitor per target, from its first raw TCP reply until `finish`
+    static ref MONITORS: Mutex<HashMap<IpAddr, HostMonitor>> = Mutex::new(HashMap::new());
+
+    /// Why the run was aborted, once it has been
+    static ref ABORT_REASON: Mutex<Option<String>> = Mutex::new(None);
+
+    /// Wakes `abort_signal` when a countermeasure trips an armed abort
+    static ref ABORT_SIGNAL: Notify = Notify::new();
+}
+
+/// Exit code of a run ended by `--abort-on-countermeasure`
+pub const ABORT_EXIT_CODE: i32 = 3;
+
+/// Whether a countermeasure ends the run (`--abort-on-countermeasure`)
+static ABORT_ARMED: AtomicBool = AtomicBool::new(false);
+
+/// Most frequent value and its count
+fn mode<K: Copy + Ord>(counts: &HashMap<K, usize>) -> Option<(K, usize)> {
+    counts.iter().map(|(&k, &n)| (k, n)).max_by(|a, b| a.1.cmp(&b.1).then(b.0.cmp(&a.0)))
+}
+
+impl HostMonitor {
+    fn has(&self, kind: InterferenceKind) -> bool {
+        self.raised.iter().any(|(k, _)| *k == kind)
+    }
+
+    /// Signs the replies so far show that were not raised before
+    fn new_signs(&self) -> Vec<(InterferenceKind, String)> {
+        let mut signs = Vec::new();
+
+        if !self.has(InterferenceKind::ForgedRst) {
+            let rst_ttls: usize = self.rst_ttls.values().sum();
+            let syn_ack_ttls: usize = self.syn_ack_ttls.values().sum();
+            if let (Some((rst_ttl, _)), Some((host_ttl, _))) = (mode(&self.rst_ttls), mode(&self.syn_ack_ttls)) {
+                if rst_ttls >= MIN_RSTS && syn_ack_ttls >= MIN_SYN_ACKS && rst_ttl.abs_diff(host_ttl) > 1 {
+                    signs.push((InterferenceKind::ForgedRst, format!(
+                        "RSTs arrive with TTL {} while the host's SYN-ACKs arrive with TTL {}; a device at another hop answers for closed ports",
+                        rst_ttl, host_ttl)));
+                }
+            }
+            if signs.is_empty() && self.rst_count >= MIN_RSTS {
+                if let Some((window, count)) = mode(&self.rst_windows) {
+                    if window != 0 && count as f64 >= self.rst_count as f64 * 0.8 {
+                        signs.push((InterferenceKind::ForgedRst, format!(
+                            "{} of {} RSTs to SYN probes carry window {}; a host's own stack answers a SYN with window 0",
+                            count, self.rst_count, window)));
+                    }
+                }
+            }
+        }
+
+        if !self.has(InterferenceKind::SynAckEverywhere) && self.syn_ports.len() >= MIN_SYN_PORTS
+            && self.syn_ack_ports.len() as f64 >= self.syn_ports.len() as f64 * SYN_ACK_SHARE {
+            signs.push((InterferenceKind::SynAckEverywhere, format!(
+                "{} of {} SYN-probed ports completed the handshake; an appliance may be answering for every port",
+                self.syn_ack_ports.len(), self.syn_ports.len())));
+        }
+
+        // Judged once per batch of replies so a quiet start cannot decide it alone
+        if !self.has(InterferenceKind::UniformTiming) && self.timed >= MIN_TIMED_REPLIES && self.timed % MIN_TIMED_REPLIES == 0 {
+            let n = self.timed as f64;
+            let mean = self.rtt_sum / n;
+            let deviation = (self.rtt_sum_sq / n - mean * mean).max(0.0).sqrt();
+            if mean >= MIN_UNIFORM_RTT_MS && deviation <= mean * MAX_TIMING_SPREAD {
+                signs.push((InterferenceKind::UniformTiming, format!(
+                    "{} replies took {:.2} ms ± {:.2} ms whatever the port; a responder with a fixed delay rather than the host may be answering",
+                    self.timed, mean, deviation)));
+            }
+        }
+
+        signs
+    }
+
+    /// Countermeasure shown by the latest reply to a probe with `flags`, if any
+    fn countermeasure(&self, flags: u8) -> Option<String> {
+        if let Some(stats) = self.by_flags.get(&flags) {
+            if stats.early_share.is_some_and(|share| share >= MIN_EARLY_SHARE) && stats.silent >= SILENT_STREAK {
+                return Some(format!(
+                    "the last {} probes with flags {} went unanswered after {:.0}% of the first {} were answered; the host or a filter started dropping the scan",
+                    stats.silent, crate::audit::tcp_flags(flags), stats.early_share.unwrap_or_default() * 100.0, EARLY_PROBES));
+            }
+        }
+        // Throttled sends wait inside the timed window, so slow replies mean nothing then
+        if let Some(baseline) = self.baseline_rtt {
+            if crate::scheduler::bandwidth_limit().is_none()
+                && self.recent_rtt >= baseline * TARPIT_FACTOR && self.recent_rtt - baseline >= TARPIT_MIN_EXTRA_MS {
+                return Some(format!(
+                    "replies now take {:.0} ms against {:.0} ms earlier in the scan; a tarpit appears to have switched on",
+                    self.recent_rtt, baseline));
+            }
+        }
+        None
+    }
+
+    /// Ports a sign casts doubt on
+    fn ports(&self, kind: InterferenceKind) -> Vec<u16> {
+        let ports = match kind {
+            InterferenceKind::ForgedRst => &self.rst_ports,
+            InterferenceKind::SynAckEverywhere => &self.syn_ack_ports,
+            InterferenceKind::UniformTiming => &self.reply_ports,
+        };
+        ports.iter().copied().collect()
+    }
+}
+
+/// Feed one raw TCP probe and its reply to the target's monitor
+///
+/// Warns the operator the first time a sign of interference shows.
+///
+/// # Arguments
+/// * `probe` - Probe that was sent
+/// * `response` - What came back
+/// * `elapsed` - Time from send to reply, without the evasion delay
+pub(crate) fn observe(probe: &TcpProbe, response: &Option<RawResponse>, elapsed: Duration) {
+    let syn_probe = probe.flags & TcpFlags::SYN != 0 && probe.flags & TcpFlags::ACK == 0;
+    let mut monitors = MONITORS.lock();
+    let monitor = monitors.entry(probe.target_ip).or_default();
+    if syn_probe {
+        monitor.syn_ports.insert(probe.port);
+    }
+
+    let answered = matches!(response, Some(RawResponse::TcpStatic { .. } | RawResponse::IcmpFiltered(..) | RawResponse::Icmpv6Filtered(..)));
+    let stats = monitor.by_flags.entry(probe.flags).or_default();
+    stats.probes += 1;
+    if answered {
+        stats.replies += 1;
+        stats.silent = 0;
+    } else {
+        stats.silent += 1;
+    }
+    if stats.probes == EARLY_PROBES {
+        stats.early_share = Some(stats.replies as f64 / EARLY_PROBES as f64);
+    }
+
+    let Some(RawResponse::TcpStatic { flags, data, ttl, .. }) = response else {
+        if let Some(reason) = monitor.countermeasure(probe.flags) {
+            countered(probe.target_ip, monitor, reason);
+        }
+        return;
+    };
+    let syn_ack = u16::from(TcpFlags::SYN | TcpFlags::ACK);
+    if syn_probe && flags & syn_ack == syn_ack {
+        monitor.syn_ack_ports.insert(probe.port);
+        if let Some(ttl) = ttl {
+            *monitor.syn_ack_ttls.entry(*ttl).or_default() += 1;
+        }
+    } else if syn_probe && flags & u16::from(TcpFlags::RST) != 0 {
+        if monitor.syn_ack_ports.contains(&probe.port) || monitor.accepted_ports.contains(&probe.port) {
+            let reason = format!("port {} now resets SYNs after completing the handshake earlier in the scan", probe.port);
+            countered(probe.target_ip, monitor, reason);
+        }
+        monitor.rst_ports.insert(probe.port);
+        monitor.rst_count += 1;
+        if let Some(ttl) = ttl {
+            *monitor.rst_ttls.entry(*ttl).or_default() += 1;
+        }
+        if let Some(window) = crate::techniques::rst_window(data) {
+            *monitor.rst_windows.entry(window).or_default() += 1;
+        }
+    }
+    monitor.reply_ports.insert(probe.port);
+    let rtt = elapsed.as_secs_f64() * 1000.0;
+    monitor.timed += 1;
+    monitor.rtt_sum += rtt;
+    monitor.rtt_sum_sq += rtt * rtt;
+    match monitor.baseline_rtt {
+        Some(_) => monitor.recent_rtt += (rtt - monitor.recent_rtt) * RECENT_WEIGHT,
+        None if monitor.timed == MIN_TIMED_REPLIES => {
+            monitor.baseline_rtt = Some(monitor.rtt_sum / monitor.timed as f64);
+            monitor.recent_rtt = monitor.rtt_sum / monitor.timed as f64;
+        }
+        None => {}
+    }
+
+    for (kind, detail) in monitor.new_signs() {
+        warn!("[Hostile] {} ({}): {}. Results for the affected ports may be forged.", probe.target_ip, kind, detail);
+        monitor.raised.push((kind, detail));
+    }
+    if let Some(reason) = monitor.countermeasure(probe.flags) {
+        countered(probe.target_ip, monitor, reason);
+    }
+}
+
+/// Feed the outcome of a full TCP connection attempt to the target's monitor
+///
+/// # Arguments
+/// * `addr` - Address connected to
+/// * `error` - Kind of the connect error, `None` when the connection was made
+pub(crate) fn observe_connect(addr: SocketAddr, error: Option<io::ErrorKind>) {
+    let mut monitors = MONITORS.lock();
+    let monitor = monitors.entry(addr.ip()).or_default();
+    match error {
+        None => {
+            monitor.accepted_ports.insert(addr.port());
+        }
+        Some(io::ErrorKind::ConnectionRefused)
+            if monitor.accepted_ports.contains(&addr.port()) || monitor.syn_ack_ports.contains(&addr.port()) => {
+            let reason = format!("port {} now refuses connections after accepting them earlier in the scan", addr.port());
+            countered(addr.ip(), monitor, reason);
+        }
+        Some(_) => {}
+    }
+}
+
+/// Report a countermeasure once per target, and abort the run if armed
+fn countered(target: IpAddr, monitor: &mut HostMonitor, reason: String) {
+    if monitor.countered {
+        return;
+    }
+    monitor.countered = true;
+    if !ABORT_ARMED.load(Ordering::Relaxed) {
+        warn!("[Hostile] {}: {}. The scan may have been noticed.", target, reason);
+        return;
+    }
+    error!("[Hostile] {}: {}. Aborting the scan (--abort-on-countermeasure).", target, reason);
+    crate::scheduler::halt();
+    ABORT_REASON.lock().get_or_insert_with(|| format!("{}: {}", target, reason));
+    ABORT_SIGNAL.notify_waiters();
+}
+
+/// End the run on the first countermeasure instead of only warning about it
+pub fn arm_abort() {
+    ABORT_ARMED.store(true, Ordering::Relaxed);
+}
+
+/// Wait until a countermeasure aborts the run
+///
+/// # Returns
+/// * `String` - The target and the countermeasure that tripped the abort
+pub async fn abort_signal() -> String {
+    loop {
+        // Registered before the check, so an abort in between still wakes it
+        let notified = ABORT_SIGNAL.notified();
+        if let Some(reason) = ABORT_REASON.lock().clone() {
+            return reason;
+        }
+        notified.await;
+    }
+}
+
+/// Stop monitoring a target and return the signs of interference it showed
+///
+/// # Arguments
+/// * `target` - Host whose scan ended
+///
+/// # Returns
+/// * `Vec<InterferenceSign>` - Signs in the order they were raised, with the ports each covers
+pub(crate) fn finish(target: IpAddr) -> Vec<InterferenceSign> {
+    let Some(monitor) = MONITORS.lock().remove(&target) else {
+        return Vec::new();
+    };
+    monitor.raised.iter()
+        .map(|(kind, detail)| InterferenceSign { kind: *kind, detail: detail.clone(), ports: monitor.ports(*kind) })
+        .collect()
+}
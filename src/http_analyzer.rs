@@ -1,8 +1,36 @@
 use std::collections::HashMap;
 use log::{debug, info};
+use serde::Serialize;
 use crate::models::HttpInfo;
 use crate::models::HttpSecurityHeader;
 
+/// Shortest HSTS max-age (180 days) that keeps browsers on HTTPS between visits
+const HSTS_MIN_AGE: u64 = 15_552_000;
+
+/// Most points cookie flags can cost, however many cookies a site sets
+const COOKIE_PENALTY_CAP: u32 = 25;
+
+/// Security hygiene of one site, scored out of 100
+#[derive(Debug, Clone, Serialize)]
+pub struct HygieneReport {
+    /// 100 less the deductions for every problem found
+    pub score: u8,
+    /// Letter grade for the score, A (90+) to F (under 60)
+    pub grade: char,
+    /// Problems found, each with the points it cost
+    pub issues: Vec<String>,
+}
+
+impl HygieneReport {
+    /// Summary for `security_posture`: the score, then each issue, separated by `; `
+    pub fn summary(&self) -> String {
+        std::iter::once(format!("Web hygiene {}/100 ({})", self.score, self.grade))
+            .chain(self.issues.iter().cloned())
+            .collect::<Vec<_>>()
+            .join("; ")
+    }
+}
+
 /// HTTP response analyzer for web services
 #[allow(dead_code)]
 pub struct HttpAnalyzer {
@@ -240,6 +268,119 @@ impl HttpAnalyzer {
     }
 }
 
+/// Value of a response header, whatever its case
+fn header<'a>(http_info: &'a HttpInfo, name: &str) -> Option<&'a str> {
+    http_info.headers.iter()
+        .find(|(key, _)| key.eq_ignore_ascii_case(name))
+        .map(|(_, value)| value.as_str())
+}
+
+/// `max-age` of a Strict-Transport-Security value
+fn hsts_max_age(value: &str) -> Option<u64> {
+    value.split(';')
+        .filter_map(|directive| directive.trim().split_once('='))
+        .find(|(name, _)| name.trim().eq_ignore_ascii_case("max-age"))
+        .and_then(|(_, age)| age.trim().trim_matches('"').parse().ok())
+}
+
+/// Score the security headers, cookies and OCSP stapling of a site
+///
+/// # Arguments
+/// * `http_info` - Response to `GET /` on the port
+/// * `tls` - Whether the response came over TLS
+/// * `ocsp_stapled` - Whether the TLS handshake carried a stapled OCSP response, `None`
+///   when unknown
+///
+/// # Returns
+/// * `HygieneReport` - Score, grade and the problems behind them. A plain HTTP port that
+///   redirects to HTTPS is judged only on what it discloses, since browsers never render it
+pub fn assess_hygiene(http_info: &HttpInfo, tls: bool, ocsp_stapled: Option<bool>) -> HygieneReport {
+    let mut issues = Vec::new();
+    let mut penalty = 0u32;
+    let mut deduct = |points: u32, issue: String| {
+        penalty += points;
+        issues.push(format!("{} (-{})", issue, points));
+    };
+
+    let redirects_to_https = !tls
+        && http_info.status_code.is_some_and(|code| (300..400).contains(&code))
+        && header(http_info, "Location").is_some_and(|location| location.to_ascii_lowercase().starts_with("https://"));
+
+    if !redirects_to_https {
+        if tls {
+            match header(http_info, "Strict-Transport-Security").map(hsts_max_age) {
+                None => deduct(20, "no Strict-Transport-Security".to_string()),
+                Some(None) | Some(Some(0)) => deduct(20, "HSTS without a usable max-age".to_string()),
+                Some(Some(age)) if age < HSTS_MIN_AGE => deduct(10, format!("HSTS max-age {} is under 180 days", age)),
+                Some(Some(_)) => {}
+            }
+            if ocsp_stapled == Some(false) {
+                deduct(5, "no OCSP stapling".to_string());
+            }
+        } else {
+            deduct(15, "served over plain HTTP without a redirect to HTTPS".to_string());
+        }
+
+        let csp = header(http_info, "Content-Security-Policy");
+        match csp {
+            None => deduct(15, "no Content-Security-Policy".to_string()),
+            Some(policy) if policy.contains("'unsafe-inline'") || policy.contains("'unsafe-eval'") => {
+                deduct(5, "Content-Security-Policy allows unsafe-inline or unsafe-eval".to_string())
+            }
+            Some(_) => {}
+        }
+        if header(http_info, "X-Frame-Options").is_none() && !csp.is_some_and(|policy| policy.contains("frame-ancestors")) {
+            deduct(10, "no X-Frame-Options or frame-ancestors".to_string());
+        }
+        if !header(http_info, "X-Content-Type-Options").is_some_and(|value| value.eq_ignore_ascii_case("nosniff")) {
+            deduct(5, "no X-Content-Type-Options: nosniff".to_string());
+        }
+        if header(http_info, "Referrer-Policy").is_none() {
+            deduct(5, "no Referrer-Policy".to_string());
+        }
+
+        let mut cookie_penalty = 0;
+        let mut cookie_issues = Vec::new();
+        for cookie in &http_info.cookies {
+            let name = cookie.split(['=', ';']).next().unwrap_or("").trim();
+            let attributes: Vec<String> = cookie.split(';').skip(1)
+                .map(|attribute| attribute.trim().split('=').next().unwrap_or("").to_ascii_lowercase())
+                .collect();
+            let missing: Vec<&str> = [("secure", tls), ("httponly", true), ("samesite", true)].iter()
+                .filter(|(flag, applies)| *applies && !attributes.iter().any(|a| a == flag))
+                .map(|(flag, _)| match *flag {
+                    "secure" => "Secure",
+                    "httponly" => "HttpOnly",
+                    _ => "SameSite",
+                })
+                .collect();
+            if !missing.is_empty() {
+                cookie_penalty += 5 * missing.len() as u32;
+                cookie_issues.push(format!("{} without {}", name, missing.join("/")));
+            }
+        }
+        if !cookie_issues.is_empty() {
+            deduct(cookie_penalty.min(COOKIE_PENALTY_CAP), format!("cookie {}", cookie_issues.join(", ")));
+        }
+    }
+
+    let versioned_server = header(http_info, "Server")
+        .is_some_and(|server| server.split('/').nth(1).is_some_and(|v| v.starts_with(|c: char| c.is_ascii_digit())));
+    if versioned_server || header(http_info, "X-Powered-By").is_some() {
+        deduct(5, "Server or X-Powered-By header discloses the software".to_string());
+    }
+
+    let score = 100u32.saturating_sub(penalty) as u8;
+    let grade = match score {
+        90..=100 => 'A',
+        80..=89 => 'B',
+        70..=79 => 'C',
+        60..=69 => 'D',
+        _ => 'F',
+    };
+    HygieneReport { score, grade, issues }
+}
+
 /// Extract HTML title from a response body
 #[allow(dead_code)]
 fn extract_html_title(html: &str) -> Option<String> {
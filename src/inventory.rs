@@ -0,0 +1,253 @@
+//! Known-good baseline comparison (`--baseline`).
+//!
+//! A baseline is the inventory a host is expected to expose: its open ports and,
+//! optionally, the service and version on each. After the scan every host is compared
+//! with it and each deviation is recorded on the host:
+//! - a port open that the baseline does not list
+//! - a listed port found closed or filtered
+//! - a service or version other than the listed one
+//!
+//! The run then exits with `DEVIATION_EXIT_CODE` instead of 0, so the scanner can gate
+//! a compliance check. Listed ports that were not scanned this time are not reported
+//! missing, and services and versions are only compared where the scan identified one.
+//!
+//! The baseline is either a hand-written YAML/TOML inventory or JSON results of an
+//! earlier scan, taken as known-good:
+//!
+//! ```yaml
+//! hosts:
+//!   - host: 10.0.0.0/24      # IP, CIDR or hostname as given on the command line
+//!     ports: [22, 443]
+//!   - host: 10.0.0.5
+//!     ports:
+//!       - 22
+//!       - port: 5432
+//!         service: postgresql
+//!         version: "14.9"
+//! ```
+
+use std::collections::BTreeMap;
+use std::net::IpAddr;
+use std::path::Path;
+
+use anyhow::{anyhow, Context, Result};
+use ipnet::IpNet;
+use log::warn;
+use serde::Deserialize;
+
+use crate::models::{BaselineDeviation, DeviationKind, PortStatus, ScanResults};
+
+/// Exit code of a run whose results deviate from the baseline
+pub const DEVIATION_EXIT_CODE: i32 = 2;
+
+/// A listed port, bare or with the service expected on it
+#[derive(Debug, Deserialize)]
+#[serde(untagged)]
+enum PortSpec {
+    Port(u16),
+    Detailed {
+        port: u16,
+        service: Option<String>,
+        version: Option<String>,
+    },
+}
+
+/// One host entry as written in the baseline file
+#[derive(Debug, Deserialize)]
+struct HostSpec {
+    host: String,
+    #[serde(default)]
+    ports: Vec<PortSpec>,
+}
+
+/// Top level of a baseline file
+#[derive(Debug, Deserialize)]
+struct InventoryFile {
+    #[serde(default)]
+    hosts: Vec<HostSpec>,
+}
+
+/// What is expected on one port
+#[derive(Debug, Clone, Default)]
+struct ExpectedPort {
+    service: Option<String>,
+    version: Option<String>,
+}
+
+/// Which hosts an entry covers
+#[derive(Debug)]
+enum HostMatch {
+    Network(IpNet),
+    Name(String),
+}
+
+/// Expected open ports per host
+#[derive(Debug, Default)]
+pub struct Inventory {
+    entries: Vec<(HostMatch, BTreeMap<u16, ExpectedPort>)>,
+}
+
+impl Inventory {
+    /// Load a baseline: YAML/TOML inventory, or JSON results of an earlier scan
+    pub fn load_file(path: &Path) -> Result<Self> {
+        let text = std::fs::read_to_string(path)
+            .with_context(|| format!("Failed to read baseline {}", path.display()))?;
+        let file: InventoryFile = match path.extension().and_then(|e| e.to_str()).map(str::to_ascii_lowercase).as_deref() {
+            Some("json") => {
+                let (results, _) = crate::schema::load_results(&text)
+                    .with_context(|| format!("Invalid baseline {}", path.display()))?;
+                return Ok(Self::from_results(&results));
+            }
+            Some("toml") => toml::from_str(&text).with_context(|| format!("Invalid baseline {}", path.display()))?,
+            Some("yaml") | Some("yml") => serde_yaml::from_str(&text).with_context(|| format!("Invalid baseline {}", path.display()))?,
+            _ => return Err(anyhow!("Baseline {} must end in .json, .yaml, .yml or .toml", path.display())),
+        };
+
+        let entries = file.hosts.into_iter()
+            .map(|spec| {
+                let host = spec.host.trim();
+                if host.is_empty() {
+                    return Err(anyhow!("Invalid baseline {}: host entry without a host", path.display()));
+                }
+                let matcher = match host.parse::<IpNet>().or_else(|_| host.parse::<IpAddr>().map(IpNet::from)) {
+                    Ok(net) => HostMatch::Network(net.trunc()),
+                    Err(_) => HostMatch::Name(host.to_lowercase()),
+                };
+                let ports = spec.ports.into_iter()
+                    .map(|port| match port {
+                        PortSpec::Port(port) => (port, ExpectedPort::default()),
+                        PortSpec::Detailed { port, service, version } => (port, ExpectedPort { service, version }),
+                    })
+                    .collect();
+                Ok((matcher, ports))
+            })
+            .collect::<Result<Vec<_>>>()?;
+        Ok(Inventory { entries })
+    }
+
+    /// Take the open ports, services and versions of earlier results as the baseline
+    fn from_results(results: &[ScanResults]) -> Self {
+        let entries = results.iter()
+            .map(|host| {
+                let matcher = match host.target_ip.parse::<IpAddr>() {
+                    Ok(ip) => HostMatch::Network(IpNet::from(ip)),
+                    Err(_) => HostMatch::Name(host.target.to_lowercase()),
+                };
+                let ports = host.open_ports.iter()
+                    .map(|&port| {
+                        let result = host.results.get(&port);
+                        (port, ExpectedPort {
+                            service: result.and_then(|r| r.service.clone()),
+                            version: result.and_then(|r| r.version.clone()),
+                        })
+                    })
+                    .collect();
+                (matcher, ports)
+            })
+            .collect();
+        Inventory { entries }
+    }
+
+    /// Number of host entries
+    pub fn host_count(&self) -> usize {
+        self.entries.len()
+    }
+
+    /// Expected ports of a host: its hostname entry, else the narrowest network holding it
+    fn expected(&self, host: &ScanResults) -> Option<&BTreeMap<u16, ExpectedPort>> {
+        let name = host.target.to_lowercase();
+        if let Some((_, ports)) = self.entries.iter().find(|(m, _)| matches!(m, HostMatch::Name(n) if *n == name)) {
+            return Some(ports);
+        }
+        let ip = host.target_ip.parse::<IpAddr>().ok()?;
+        self.entries.iter()
+            .filter_map(|(m, ports)| match m {
+                HostMatch::Network(net) if net.contains(&ip) => Some((net.prefix_len(), ports)),
+                _ => None,
+            })
+            .max_by_key(|(prefix, _)| *prefix)
+            .map(|(_, ports)| ports)
+    }
+
+    /// Compare each host with the baseline and record the deviations on it
+    ///
+    /// # Arguments
+    /// * `results` - Results of every host of the run; deviations go to `deviations`
+    ///
+    /// # Returns
+    /// * `usize` - Number of deviations across all hosts
+    pub fn compare(&self, results: &mut [ScanResults]) -> usize {
+        let mut total = 0;
+        for host in results.iter_mut() {
+            let deviations = match self.expected(host) {
+                Some(expected) => deviations(host, expected),
+                None => {
+                    // A host the baseline does not know should expose nothing
+                    let mut open: Vec<u16> = host.open_ports.iter().copied().collect();
+                    open.sort_unstable();
+                    open.into_iter()
+                        .map(|port| BaselineDeviation {
+                            port,
+                            kind: DeviationKind::UnexpectedPort,
+                            detail: format!("port {} is open on a host the baseline does not list", port),
+                        })
+                        .collect()
+                }
+            };
+            if !deviations.is_empty() {
+                warn!("[Baseline] {} has {} deviations from the baseline", host.target, deviations.len());
+            }
+            total += deviations.len();
+            host.deviations = deviations;
+        }
+        total
+    }
+}
+
+/// Deviations of one host from its expected ports, in port order
+fn deviations(host: &ScanResults, expected: &BTreeMap<u16, ExpectedPort>) -> Vec<BaselineDeviation> {
+    let mut found = Vec::new();
+    let mut open: Vec<u16> = host.open_ports.iter().copied().filter(|port| !expected.contains_key(port)).collect();
+    open.sort_unstable();
+    for port in open {
+        found.push(BaselineDeviation {
+            port,
+            kind: DeviationKind::UnexpectedPort,
+            detail: format!("port {} is open but not in the baseline", port),
+        });
+    }
+
+    for (&port, want) in expected {
+        // Ports outside this run's port list were not checked, so they are not missing
+        let Some(result) = host.results.get(&port) else { continue };
+        if !host.open_ports.contains(&port) && result.final_status != PortStatus::Open {
+            found.push(BaselineDeviation {
+                port,
+                kind: DeviationKind::MissingPort,
+                detail: format!("port {} is in the baseline but {}", port, result.final_status),
+            });
+            continue;
+        }
+        if let (Some(want), Some(have)) = (&want.service, &result.service) {
+            if !want.eq_ignore_ascii_case(have) {
+                found.push(BaselineDeviation {
+                    port,
+                    kind: DeviationKind::ServiceChanged,
+                    detail: format!("port {} runs {}, baseline has {}", port, have, want),
+                });
+                continue;
+            }
+        }
+        if let (Some(want), Some(have)) = (&want.version, &result.version) {
+            if want != have {
+                found.push(BaselineDeviation {
+                    port,
+                    kind: DeviationKind::VersionChanged,
+                    detail: format!("port {} runs version {}, baseline has {}", port, have, want),
+                });
+            }
+        }
+    }
+    found.sort_by_key(|deviation| deviation.port);
+    found
+}
@@ -0,0 +1,180 @@
+//! Log output routing.
+//!
+//! env_logger can only be initialised once, and that happens before the arguments are
+//! parsed, so it always writes to a `LogTee`. The tee passes every record through the
+//! redaction policy, then copies it to stderr and, once `set_file_sink` has been called,
+//! to the log file. With `--encrypt-logs` the file is an encrypted stream (see
+//! `encryption::EncryptedLogWriter`) that only `quantum_scanner logs decrypt` can read back.
+
+use std::fs::File;
+use std::io::Write;
+use std::path::{Path, PathBuf};
+
+use anyhow::{Context, Result};
+use clap::{Parser, Subcommand};
+use lazy_static::lazy_static;
+use log::{info, warn};
+use parking_lot::Mutex;
+use rand::{thread_rng, Rng};
+
+use crate::encryption::{self, EncryptedLogWriter};
+use crate::utils::SecureString;
+
+/// Where log records go besides stderr
+enum FileSink {
+    Plain(File),
+    Encrypted(EncryptedLogWriter<File>),
+}
+
+lazy_static! {
+    /// Log file for this run, if disk logging is on
+    static ref FILE_SINK: Mutex<Option<FileSink>> = Mutex::new(None);
+}
+
+/// env_logger target writing each record to stderr and the log file
+pub struct LogTee;
+
+impl Write for LogTee {
+    fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+        let redacted = match std::str::from_utf8(buf) {
+            Ok(text) => crate::redaction::apply(text),
+            Err(_) => String::from_utf8_lossy(buf),
+        };
+        let record = redacted.as_bytes();
+        let _ = std::io::stderr().write_all(record);
+        // A failing log file must not take the scan down with it
+        let _ = match FILE_SINK.lock().as_mut() {
+            Some(FileSink::Plain(file)) => file.write_all(record),
+            Some(FileSink::Encrypted(writer)) => writer.write_all(record),
+            None => Ok(()),
+        };
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> std::io::Result<()> {
+        std::io::stderr().flush()
+    }
+}
+
+/// Initialise the process-wide logger (default level "info", overridable with RUST_LOG)
+pub fn init() {
+    let _ = env_logger::Builder::from_env(env_logger::Env::default().default_filter_or("info"))
+        .format_timestamp_secs()
+        .target(env_logger::Target::Pipe(Box::new(LogTee)))
+        .try_init();
+}
+
+/// Random passphrase for a log when none was given
+fn generate_passphrase() -> SecureString {
+    let alphabet = b"ABCDEFGHJKLMNPQRSTUVWXYZabcdefghijkmnopqrstuvwxyz23456789";
+    let mut rng = thread_rng();
+    SecureString::new((0..24).map(|_| alphabet[rng.gen_range(0..alphabet.len())] as char).collect())
+}
+
+/// Start copying log records to `path`
+///
+/// # Arguments
+/// * `path` - Log file, created or truncated
+/// * `encrypt` - Whether to write an encrypted log
+/// * `passphrase` - Passphrase spec (literal, env:NAME or file:PATH); a random one is
+///   generated and printed once when encrypting without it
+///
+/// # Opsec Considerations
+/// A generated passphrase is printed to stderr only, never logged, so it does not end
+/// up in the log it protects. Anyone who can see the terminal can read it.
+pub fn set_file_sink(path: &Path, encrypt: bool, passphrase: Option<&str>) -> Result<()> {
+    let file = File::create(path).with_context(|| format!("Failed to create log file {}", path.display()))?;
+    let sink = if encrypt {
+        let passphrase = log_passphrase(path, passphrase)?;
+        FileSink::Encrypted(EncryptedLogWriter::new(file, &passphrase)?)
+    } else {
+        FileSink::Plain(file)
+    };
+    *FILE_SINK.lock() = Some(sink);
+    info!("Logging to file: {}{}", path.display(), if encrypt { " (encrypted)" } else { "" });
+    Ok(())
+}
+
+/// Passphrase for an encrypted log at `path`: the given spec, or a generated one printed
+/// once on stderr
+pub fn log_passphrase(path: &Path, passphrase: Option<&str>) -> Result<SecureString> {
+    Ok(match passphrase {
+        Some(spec) => encryption::resolve_passphrase(spec)?,
+        None => {
+            let generated = generate_passphrase();
+            eprintln!("[Log] No --log-password given; {} is encrypted with the generated passphrase: {}", path.display(), generated);
+            eprintln!("[Log] Keep it to read the log back with `quantum_scanner logs decrypt {}`", path.display());
+            generated
+        }
+    })
+}
+
+/// Stop writing to the log file, marking an encrypted log as complete
+///
+/// Must run before the log file is read, moved or securely deleted.
+pub fn close_file_sink() {
+    let sink = FILE_SINK.lock().take();
+    match sink {
+        Some(FileSink::Encrypted(mut writer)) => {
+            if let Err(e) = writer.finish() {
+                warn!("Failed to finalise encrypted log: {}", e);
+            }
+        }
+        Some(FileSink::Plain(mut file)) => {
+            let _ = file.flush();
+        }
+        None => {}
+    }
+}
+
+/// Arguments for `quantum_scanner logs`
+#[derive(Parser, Debug)]
+#[clap(name = "logs", about = "Work with scanner log files")]
+pub struct LogsArgs {
+    #[clap(subcommand)]
+    pub command: LogsCommand,
+}
+
+/// `quantum_scanner logs` subcommands
+#[derive(Subcommand, Debug)]
+pub enum LogsCommand {
+    /// Decrypt a log written with --encrypt-logs
+    Decrypt {
+        /// Encrypted log to read
+        input: PathBuf,
+
+        /// Where to write the plaintext (default: stdout)
+        #[clap(short, long)]
+        output: Option<PathBuf>,
+
+        /// Passphrase, or env:NAME / file:PATH to read it from (prompted for when omitted)
+        #[clap(short, long)]
+        passphrase: Option<String>,
+    },
+}
+
+/// Run the `logs` subcommand
+pub fn run_logs(args: LogsArgs) -> Result<()> {
+    match args.command {
+        LogsCommand::Decrypt { input, output, passphrase } => {
+            let data = std::fs::read(&input).with_context(|| format!("Failed to read {}", input.display()))?;
+            let passphrase = match &passphrase {
+                Some(spec) => encryption::resolve_passphrase(spec)?,
+                None => SecureString::new(dialoguer::Password::new().with_prompt("Log passphrase").interact()?),
+            };
+            let (plaintext, complete) = encryption::decrypt_log(&passphrase, &data)?;
+            if !complete {
+                warn!("{} ends without its final record: the scan was interrupted or the file was truncated", input.display());
+            }
+
+            match &output {
+                Some(path) => {
+                    std::fs::write(path, &plaintext).with_context(|| format!("Failed to write {}", path.display()))?;
+                    info!("Decrypted {} to {}", input.display(), path.display());
+                }
+                None => std::io::stdout().write_all(&plaintext)?,
+            }
+            Ok(())
+        }
+    }
+}
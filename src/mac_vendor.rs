@@ -0,0 +1,544 @@
+//! MAC address helpers: vendor (OUI) identification for on-link targets and
+//! source MAC spoofing for the raw frames emitted by the scanner.
+//!
+//! Target MACs are only visible when the target shares a layer 2 segment with the
+//! scanning host, so identification relies on the kernel neighbour (ARP/NDP) cache
+//! which is populated as a side effect of the scan traffic itself.
+
+use std::collections::HashMap;
+use std::fmt;
+use std::net::IpAddr;
+use std::str::FromStr;
+use std::time::Duration;
+
+use anyhow::{anyhow, Result};
+use lazy_static::lazy_static;
+use log::{debug, info};
+use parking_lot::{Mutex, RwLock};
+use pnet::datalink::{self, Channel, DataLinkSender, MacAddr, NetworkInterface};
+use pnet::packet::ethernet::{EtherTypes, MutableEthernetPacket};
+use rand::Rng;
+
+/// Known OUI prefixes and the vendors they are registered to.
+///
+/// Deliberately small: covers common network gear, virtualisation platforms and
+/// consumer devices, which are both the most useful vendors to identify and the
+/// most plausible ones to impersonate.
+const OUI_VENDORS: &[([u8; 3], &str)] = &[
+    ([0x00, 0x00, 0x0C], "Cisco"),
+    ([0x00, 0x1B, 0x54], "Cisco"),
+    ([0x00, 0x50, 0x56], "VMware"),
+    ([0x00, 0x0C, 0x29], "VMware"),
+    ([0x00, 0x05, 0x69], "VMware"),
+    ([0x08, 0x00, 0x27], "VirtualBox"),
+    ([0x52, 0x54, 0x00], "QEMU/KVM"),
+    ([0x00, 0x16, 0x3E], "Xen"),
+    ([0x00, 0x15, 0x5D], "Microsoft Hyper-V"),
+    ([0x00, 0x1C, 0x42], "Parallels"),
+    ([0x02, 0x42, 0xAC], "Docker"),
+    ([0x00, 0x03, 0x93], "Apple"),
+    ([0x3C, 0x22, 0xFB], "Apple"),
+    ([0xF0, 0x18, 0x98], "Apple"),
+    ([0x00, 0x1A, 0x11], "Google"),
+    ([0x3C, 0x5A, 0xB4], "Google"),
+    ([0x00, 0x14, 0x22], "Dell"),
+    ([0xF8, 0xBC, 0x12], "Dell"),
+    ([0x00, 0x1B, 0x78], "Hewlett Packard"),
+    ([0x3C, 0xD9, 0x2B], "Hewlett Packard"),
+    ([0x00, 0x1E, 0x0B], "Hewlett Packard"),
+    ([0x00, 0x1F, 0x29], "Hewlett Packard"),
+    ([0x00, 0x02, 0xB3], "Intel"),
+    ([0x3C, 0x97, 0x0E], "Intel"),
+    ([0x00, 0x1D, 0x09], "Dell"),
+    ([0x00, 0x09, 0x0F], "Fortinet"),
+    ([0x00, 0x1B, 0x17], "Palo Alto Networks"),
+    ([0x00, 0x05, 0x85], "Juniper Networks"),
+    ([0x00, 0x0D, 0xB9], "PC Engines"),
+    ([0x00, 0x0C, 0x42], "MikroTik"),
+    ([0x4C, 0x5E, 0x0C], "MikroTik"),
+    ([0x00, 0x18, 0x0A], "Cisco Meraki"),
+    ([0x00, 0x27, 0x22], "Ubiquiti"),
+    ([0x24, 0xA4, 0x3C], "Ubiquiti"),
+    ([0xB8, 0x27, 0xEB], "Raspberry Pi"),
+    ([0xDC, 0xA6, 0x32], "Raspberry Pi"),
+    ([0x00, 0x11, 0x32], "Synology"),
+    ([0x00, 0x08, 0x9B], "QNAP"),
+    ([0x00, 0x1E, 0x58], "D-Link"),
+    ([0x00, 0x14, 0x6C], "Netgear"),
+    ([0x50, 0xC7, 0xBF], "TP-Link"),
+    ([0x00, 0x00, 0xAA], "Xerox"),
+    ([0x00, 0x00, 0x48], "Epson"),
+    ([0x00, 0x80, 0x77], "Brother"),
+    ([0x00, 0x00, 0x85], "Canon"),
+    ([0x00, 0x04, 0x0D], "Avaya"),
+    ([0x00, 0x04, 0xF2], "Polycom"),
+    ([0x00, 0x0B, 0x82], "Grandstream"),
+    ([0x00, 0x80, 0xF4], "Schneider Electric"),
+    ([0x00, 0x1B, 0x1B], "Siemens"),
+    ([0x00, 0x1C, 0xB3], "Apple"),
+    ([0x00, 0x12, 0xFB], "Samsung"),
+];
+
+lazy_static! {
+    /// Source MAC address applied to raw frames, if spoofing is enabled.
+    /// Set once during scanner configuration and read by the raw packet senders.
+    static ref SPOOFED_MAC: RwLock<Option<MacAddr>> = RwLock::new(None);
+    /// Default gateway of each interface and address family, looked up once per run
+    static ref GATEWAYS: Mutex<HashMap<(String, bool), Option<IpAddr>>> = Mutex::new(HashMap::new());
+    /// MAC addresses of next hops resolved so far, keyed by next hop address
+    static ref NEXT_HOP_MACS: Mutex<HashMap<IpAddr, MacAddr>> = Mutex::new(HashMap::new());
+    /// Datalink channels spoofed frames are sent on, keyed by the local address they serve
+    static ref SPOOF_LINKS: Mutex<HashMap<IpAddr, SpoofLink>> = Mutex::new(HashMap::new());
+    /// Next hop MAC of every target a spoofed frame has been prepared for
+    static ref SPOOF_HOPS: Mutex<HashMap<IpAddr, MacAddr>> = Mutex::new(HashMap::new());
+}
+
+/// Interface and open datalink channel used for spoofed frames from one local address
+struct SpoofLink {
+    iface: NetworkInterface,
+    tx: Box<dyn DataLinkSender>,
+}
+
+/// Source MAC spoofing modes accepted by `--spoof-mac`
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum SpoofMac {
+    /// Random MAC with the OUI of the named vendor (e.g. "cisco", "apple")
+    Vendor(String),
+    /// Fully random, locally administered unicast MAC
+    Random,
+    /// Explicit MAC address supplied by the operator
+    Fixed(MacAddr),
+}
+
+impl FromStr for SpoofMac {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        let value = s.trim();
+        if value.eq_ignore_ascii_case("random") || value == "0" {
+            return Ok(SpoofMac::Random);
+        }
+
+        // Anything that looks like a MAC address is taken literally
+        if value.contains(':') || value.contains('-') {
+            let mac = parse_mac(value)?;
+            if mac.0 & 0x01 != 0 {
+                return Err(anyhow!("Spoofed MAC {} is a multicast address", value));
+            }
+            return Ok(SpoofMac::Fixed(mac));
+        }
+
+        // Otherwise treat the value as a vendor name
+        if vendor_oui(value).is_none() {
+            return Err(anyhow!("Unknown vendor '{}' for MAC spoofing (use a vendor name, 'random' or XX:XX:XX:XX:XX:XX)", value));
+        }
+        Ok(SpoofMac::Vendor(value.to_string()))
+    }
+}
+
+impl fmt::Display for SpoofMac {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            SpoofMac::Vendor(name) => write!(f, "vendor:{}", name),
+            SpoofMac::Random => write!(f, "random"),
+            SpoofMac::Fixed(mac) => write!(f, "{}", mac),
+        }
+    }
+}
+
+impl SpoofMac {
+    /// Produce the concrete MAC address for this spoofing mode
+    ///
+    /// Vendor and random modes generate a fresh address on each call, so this should
+    /// be resolved once per scan to keep the source address consistent.
+    pub fn resolve(&self) -> MacAddr {
+        let mut rng = crate::utils::scan_rng();
+        match self {
+            SpoofMac::Fixed(mac) => *mac,
+            SpoofMac::Random => {
+                // Locally administered (bit 1 set), unicast (bit 0 clear)
+                let first = (rng.gen::<u8>() | 0x02) & 0xFE;
+                MacAddr::new(first, rng.gen(), rng.gen(), rng.gen(), rng.gen(), rng.gen())
+            }
+            SpoofMac::Vendor(name) => {
+                // Validated in from_str, fall back to random just in case
+                let oui = vendor_oui(name).unwrap_or([0x02, rng.gen(), rng.gen()]);
+                MacAddr::new(oui[0], oui[1], oui[2], rng.gen(), rng.gen(), rng.gen())
+            }
+        }
+    }
+}
+
+/// Parse a MAC address in colon or dash separated notation
+pub fn parse_mac(value: &str) -> Result<MacAddr> {
+    let octets: Vec<&str> = value.split(|c| c == ':' || c == '-').collect();
+    if octets.len() != 6 {
+        return Err(anyhow!("Invalid MAC address format: {}", value));
+    }
+
+    let mut bytes = [0u8; 6];
+    for (i, octet) in octets.iter().enumerate() {
+        bytes[i] = u8::from_str_radix(octet, 16)
+            .map_err(|_| anyhow!("Invalid MAC address octet '{}' in {}", octet, value))?;
+    }
+    Ok(MacAddr::new(bytes[0], bytes[1], bytes[2], bytes[3], bytes[4], bytes[5]))
+}
+
+/// Find the OUI registered to a vendor name (case-insensitive, substring match)
+fn vendor_oui(name: &str) -> Option<[u8; 3]> {
+    let needle = name.to_lowercase();
+    OUI_VENDORS
+        .iter()
+        .find(|(_, vendor)| vendor.to_lowercase().contains(&needle))
+        .map(|(oui, _)| *oui)
+}
+
+/// Resolve the vendor for a MAC address from its OUI
+///
+/// # Returns
+/// * `Option<&'static str>` - Vendor name, "Locally administered" for randomised
+///   addresses, or None if the OUI is unknown
+pub fn lookup_vendor(mac: MacAddr) -> Option<&'static str> {
+    let oui = [mac.0, mac.1, mac.2];
+    if let Some((_, vendor)) = OUI_VENDORS.iter().find(|(prefix, _)| *prefix == oui) {
+        return Some(vendor);
+    }
+
+    // Randomised (privacy) MACs used by modern phones and VMs set this bit
+    if mac.0 & 0x02 != 0 {
+        return Some("Locally administered");
+    }
+    None
+}
+
+/// Look up the MAC address of an on-link host in the kernel neighbour cache
+///
+/// Only works for targets on the same layer 2 segment. The cache is filled by the
+/// scan traffic itself, so this should be called after probes have been sent.
+///
+/// # OPSEC Considerations
+/// - Reads local state only, no packets are sent
+pub fn lookup_neighbor_mac(target_ip: IpAddr) -> Option<MacAddr> {
+    if cfg!(windows) {
+        return windows_neighbor_mac(target_ip);
+    }
+
+    // IPv4: /proc/net/arp is the cheapest source on Linux
+    if let IpAddr::V4(_) = target_ip {
+        if let Ok(contents) = std::fs::read_to_string("/proc/net/arp") {
+            let target = target_ip.to_string();
+            for line in contents.lines().skip(1) {
+                let fields: Vec<&str> = line.split_whitespace().collect();
+                // IP address, HW type, Flags, HW address, Mask, Device
+                if fields.len() >= 4 && fields[0] == target && fields[3] != "00:00:00:00:00:00" {
+                    return parse_mac(fields[3]).ok();
+                }
+            }
+        }
+    }
+
+    // Fall back to `ip neigh` which covers IPv6 (NDP) as well
+    let output = std::process::Command::new("ip")
+        .args(["neigh", "show", &target_ip.to_string()])
+        .output()
+        .ok()?;
+    let text = String::from_utf8_lossy(&output.stdout);
+    let mut fields = text.split_whitespace();
+    while let Some(field) = fields.next() {
+        if field == "lladdr" {
+            return fields.next().and_then(|mac| parse_mac(mac).ok());
+        }
+    }
+    None
+}
+
+/// Neighbour cache lookup on Windows
+///
+/// `arp -a <ip>` prints "  192.168.1.1     aa-bb-cc-dd-ee-ff     dynamic".
+fn windows_neighbor_mac(target_ip: IpAddr) -> Option<MacAddr> {
+    let output = std::process::Command::new("arp")
+        .args(["-a", &target_ip.to_string()])
+        .output()
+        .ok()?;
+    let text = String::from_utf8_lossy(&output.stdout);
+    let target = target_ip.to_string();
+    text.lines()
+        .map(|line| line.split_whitespace().collect::<Vec<_>>())
+        .find(|fields| fields.len() >= 2 && fields[0] == target)
+        .and_then(|fields| parse_mac(fields[1]).ok())
+        .filter(|mac| *mac != MacAddr::zero() && *mac != MacAddr::broadcast())
+}
+
+/// Default IPv4 gateway of an interface on Windows
+///
+/// `route print -4 0.0.0.0` lists "0.0.0.0  0.0.0.0  <gateway>  <interface ip>  <metric>".
+fn windows_default_gateway(iface: &NetworkInterface) -> Option<IpAddr> {
+    let output = std::process::Command::new("route")
+        .args(["print", "-4", "0.0.0.0"])
+        .output()
+        .ok()?;
+    let text = String::from_utf8_lossy(&output.stdout);
+    let local_ips: Vec<String> = iface.ips.iter().map(|net| net.ip().to_string()).collect();
+    text.lines()
+        .map(|line| line.split_whitespace().collect::<Vec<_>>())
+        .find(|fields| fields.len() >= 4 && fields[0] == "0.0.0.0" && fields[1] == "0.0.0.0" && local_ips.iter().any(|ip| ip == fields[3]))
+        .and_then(|fields| fields[2].parse().ok())
+}
+
+/// Enable source MAC spoofing for all raw frames sent by this process
+pub fn set_spoofed_mac(mac: Option<MacAddr>) {
+    if let Some(mac) = mac {
+        info!("Spoofing source MAC address as {} ({})", mac, lookup_vendor(mac).unwrap_or("unknown vendor"));
+    }
+    *SPOOFED_MAC.write() = mac;
+}
+
+/// Currently configured spoofed source MAC, if any
+pub fn spoofed_mac() -> Option<MacAddr> {
+    *SPOOFED_MAC.read()
+}
+
+/// Find the interface that owns the given local address
+pub(crate) fn interface_for_ip(local_ip: IpAddr) -> Option<NetworkInterface> {
+    datalink::interfaces()
+        .into_iter()
+        .find(|iface| iface.ips.iter().any(|net| net.ip() == local_ip))
+}
+
+/// Default gateway of an interface for one address family
+fn default_gateway(iface: &NetworkInterface, ipv4: bool) -> Option<IpAddr> {
+    if cfg!(windows) {
+        return if ipv4 { windows_default_gateway(iface) } else { None };
+    }
+
+    // Find the default gateway for this interface from the routing table
+    let output = std::process::Command::new("ip")
+        .args([if ipv4 { "-4" } else { "-6" }, "route", "show", "default", "dev", &iface.name])
+        .output()
+        .ok()?;
+    let text = String::from_utf8_lossy(&output.stdout);
+    let mut fields = text.split_whitespace();
+    while let Some(field) = fields.next() {
+        if field == "via" {
+            return fields.next()?.parse().ok();
+        }
+    }
+    None
+}
+
+/// Address frames for `target_ip` are handed to: the target itself when it is on-link,
+/// otherwise the interface's default gateway (looked up once per interface)
+fn next_hop_ip(iface: &NetworkInterface, target_ip: IpAddr) -> Option<IpAddr> {
+    if iface.ips.iter().any(|net| net.contains(target_ip)) {
+        return Some(target_ip);
+    }
+    *GATEWAYS.lock()
+        .entry((iface.name.clone(), target_ip.is_ipv4()))
+        .or_insert_with(|| default_gateway(iface, target_ip.is_ipv4()))
+}
+
+/// Determine the next hop MAC address for a target
+///
+/// On-link targets are resolved directly; everything else goes to the default gateway.
+/// Resolved addresses are cached, so a sweep of off-link hosts reads the neighbour cache
+/// once for the gateway rather than once per host.
+pub(crate) fn next_hop_mac(iface: &NetworkInterface, target_ip: IpAddr) -> Option<MacAddr> {
+    let hop = next_hop_ip(iface, target_ip)?;
+    if let Some(mac) = NEXT_HOP_MACS.lock().get(&hop) {
+        return Some(*mac);
+    }
+    let mac = lookup_neighbor_mac(hop)?;
+    NEXT_HOP_MACS.lock().insert(hop, mac);
+    Some(mac)
+}
+
+/// Next hop MAC address for a target, priming the neighbour cache if it is cold
+///
+/// Blocks for up to 300ms when the next hop is not resolved yet, so async callers should
+/// run it on a blocking thread.
+///
+/// # OPSEC Considerations
+/// - On a miss an empty UDP datagram is sent to the target (port 9) so the kernel
+///   resolves the next hop; it is recorded in the audit log
+pub(crate) fn resolve_next_hop_mac(iface: &NetworkInterface, local_ip: IpAddr, target_ip: IpAddr) -> Result<MacAddr> {
+    if let Some(mac) = next_hop_mac(iface, target_ip) {
+        return Ok(mac);
+    }
+
+    if let Ok(sock) = std::net::UdpSocket::bind((local_ip, 0)) {
+        crate::audit::record(|| crate::audit::Probe::new("neighbor-prime", "udp", target_ip, Some(9), crate::scheduler::UDP_HEADER_BYTES)
+            .detail("Empty datagram so the kernel resolves the next hop MAC"));
+        let _ = sock.send_to(&[], (target_ip, 9));
+    }
+    std::thread::sleep(Duration::from_millis(300));
+    next_hop_mac(iface, target_ip)
+        .ok_or_else(|| anyhow!("Could not resolve next hop MAC address for {}", target_ip))
+}
+
+/// Open the datalink channel spoofed frames from `local_ip` leave through
+///
+/// Called when `--spoof-mac` is configured so a missing interface or a channel the
+/// platform refuses stops the scan up front; later calls reuse the open channel.
+///
+/// # Returns
+/// * `Result<()>` - Error if no interface owns `local_ip` or its channel cannot be opened
+pub fn open_spoof_link(local_ip: IpAddr) -> Result<()> {
+    let mut links = SPOOF_LINKS.lock();
+    if links.contains_key(&local_ip) {
+        return Ok(());
+    }
+
+    let iface = interface_for_ip(local_ip)
+        .ok_or_else(|| anyhow!("No interface owns {}; cannot send frames with a spoofed MAC", local_ip))?;
+    let tx = match datalink::channel(&iface, Default::default()) {
+        Ok(Channel::Ethernet(tx, _)) => tx,
+        Ok(_) => return Err(anyhow!("Unsupported datalink channel type on {}", iface.name)),
+        Err(e) => return Err(anyhow!("Failed to open datalink channel on {}: {}", iface.name, e)),
+    };
+    debug!("Opened datalink channel on {} for spoofed frames from {}", iface.name, local_ip);
+    links.insert(local_ip, SpoofLink { iface, tx });
+    Ok(())
+}
+
+/// Resolve everything a spoofed frame from `local_ip` to `target_ip` needs
+///
+/// Opens the interface's channel on first use and resolves the target's next hop,
+/// priming the neighbour cache when it is cold. Both are cached, so only the first
+/// probe to a next hop does any work.
+///
+/// # Returns
+/// * `Result<()>` - Error if the frame cannot be sent with the spoofed MAC
+pub async fn prepare_spoofed_frame(local_ip: IpAddr, target_ip: IpAddr) -> Result<()> {
+    if spoof_ready(local_ip, target_ip) {
+        return Ok(());
+    }
+    tokio::task::spawn_blocking(move || prepare_spoofed_frame_blocking(local_ip, target_ip)).await?
+}
+
+/// Blocking form of `prepare_spoofed_frame` for probes sent from `spawn_blocking`
+pub fn prepare_spoofed_frame_blocking(local_ip: IpAddr, target_ip: IpAddr) -> Result<()> {
+    if spoof_ready(local_ip, target_ip) {
+        return Ok(());
+    }
+    open_spoof_link(local_ip)?;
+    let iface = SPOOF_LINKS.lock().get(&local_ip).map(|link| link.iface.clone())
+        .ok_or_else(|| anyhow!("Datalink channel for {} was closed", local_ip))?;
+    let mac = resolve_next_hop_mac(&iface, local_ip, target_ip)?;
+    SPOOF_HOPS.lock().insert(target_ip, mac);
+    Ok(())
+}
+
+fn spoof_ready(local_ip: IpAddr, target_ip: IpAddr) -> bool {
+    SPOOF_HOPS.lock().contains_key(&target_ip) && SPOOF_LINKS.lock().contains_key(&local_ip)
+}
+
+/// Send a fully built IP packet with the spoofed source MAC when `--spoof-mac` is set
+///
+/// For raw senders that would otherwise hand the packet to a layer 3 socket, where the
+/// kernel fills in the interface's real MAC.
+///
+/// # Returns
+/// * `Result<bool>` - Ok(false) if spoofing is disabled and the caller should send the
+///   packet itself; with spoofing enabled the frame is sent or an error returned
+pub async fn send_spoofed(local_ip: IpAddr, target_ip: IpAddr, ip_packet: &[u8]) -> Result<bool> {
+    if spoofed_mac().is_none() {
+        return Ok(false);
+    }
+    prepare_spoofed_frame(local_ip, target_ip).await?;
+    send_spoofed_frame(local_ip, target_ip, ip_packet)
+}
+
+/// Blocking form of `send_spoofed` for probes sent from `spawn_blocking`
+pub fn send_spoofed_blocking(local_ip: IpAddr, target_ip: IpAddr, ip_packet: &[u8]) -> Result<bool> {
+    if spoofed_mac().is_none() {
+        return Ok(false);
+    }
+    prepare_spoofed_frame_blocking(local_ip, target_ip)?;
+    send_spoofed_frame(local_ip, target_ip, ip_packet)
+}
+
+/// Send a fully built IP packet inside an Ethernet frame carrying the spoofed source MAC
+///
+/// The channel and next hop must have been set up with `prepare_spoofed_frame`.
+///
+/// # Arguments
+/// * `local_ip` - Local address the packet is sourced from (selects the interface)
+/// * `target_ip` - Destination of the packet (selects the next hop)
+/// * `ip_packet` - Complete IPv4/IPv6 packet including headers
+///
+/// # Returns
+/// * `Result<bool>` - Ok(true) if the frame was sent, Ok(false) if spoofing is disabled
+///   and the caller should use the normal path. With spoofing enabled every failure is
+///   an error, so a probe never leaves with the real MAC
+///
+/// # OPSEC Considerations
+/// - Replies are addressed to the spoofed MAC; on switched networks they will only be
+///   seen if the interface is promiscuous or the switch floods the frame
+/// - Port security / 802.1X may shut the switch port on an unexpected source MAC
+pub fn send_spoofed_frame(local_ip: IpAddr, target_ip: IpAddr, ip_packet: &[u8]) -> Result<bool> {
+    let source_mac = match spoofed_mac() {
+        Some(mac) => mac,
+        None => return Ok(false),
+    };
+
+    let dest_mac = SPOOF_HOPS.lock().get(&target_ip).copied()
+        .ok_or_else(|| anyhow!("Next hop MAC for {} is not resolved", target_ip))?;
+
+    let mut frame = vec![0u8; 14 + ip_packet.len()];
+    let mut ethernet = MutableEthernetPacket::new(&mut frame)
+        .ok_or_else(|| anyhow!("Failed to build Ethernet frame"))?;
+    ethernet.set_source(source_mac);
+    ethernet.set_destination(dest_mac);
+    ethernet.set_ethertype(if target_ip.is_ipv4() { EtherTypes::Ipv4 } else { EtherTypes::Ipv6 });
+    ethernet.set_payload(ip_packet);
+
+    let mut links = SPOOF_LINKS.lock();
+    let link = links.get_mut(&local_ip)
+        .ok_or_else(|| anyhow!("No datalink channel open for {}", local_ip))?;
+    match link.tx.send_to(&frame, None) {
+        Some(Ok(())) => {
+            debug!("Sent {} byte frame {} -> {} via {}", frame.len(), source_mac, dest_mac, link.iface.name);
+            Ok(true)
+        }
+        Some(Err(e)) => Err(anyhow!("Failed to send spoofed frame on {}: {}", link.iface.name, e)),
+        None => Err(anyhow!("Datalink channel on {} refused the frame", link.iface.name)),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::net::Ipv4Addr;
+
+    /// TEST-NET-1 addresses: no interface owns the source, so no frame can be sent
+    const LOCAL: IpAddr = IpAddr::V4(Ipv4Addr::new(192, 0, 2, 1));
+    const TARGET: IpAddr = IpAddr::V4(Ipv4Addr::new(192, 0, 2, 2));
+
+    /// IPv4 header plus an ICMP timestamp request, as `icmp_host_probe` sends it
+    fn icmp_timestamp_request() -> Vec<u8> {
+        let mut packet = vec![0u8; 40];
+        packet[0] = 0x45;
+        packet[3] = 40;
+        packet[8] = 64;
+        packet[9] = 1;
+        packet[12..16].copy_from_slice(&[192, 0, 2, 1]);
+        packet[16..20].copy_from_slice(&[192, 0, 2, 2]);
+        packet[20] = 13;
+        packet
+    }
+
+    #[tokio::test]
+    async fn spoofed_icmp_never_falls_back_to_the_raw_socket() {
+        let packet = icmp_timestamp_request();
+        assert!(!send_spoofed(LOCAL, TARGET, &packet).await.unwrap());
+        assert!(!send_spoofed_blocking(LOCAL, TARGET, &packet).unwrap());
+
+        set_spoofed_mac(Some(MacAddr::new(0x02, 0, 0, 0, 0, 1)));
+        let spoofed = send_spoofed(LOCAL, TARGET, &packet).await;
+        let spoofed_blocking = send_spoofed_blocking(LOCAL, TARGET, &packet);
+        set_spoofed_mac(None);
+
+        assert!(spoofed.is_err(), "frame without a link must fail, not report Ok(false)");
+        assert!(spoofed_blocking.is_err(), "frame without a link must fail, not report Ok(false)");
+    }
+}
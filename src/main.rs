@@ -1,39 +1,99 @@
 use clap::{Parser, ValueEnum};
 use log::{debug, error, info, warn};
-use std::path::PathBuf;
+use std::path::{Path, PathBuf};
 use std::process;
 use std::time::Duration;
 use rand::{thread_rng, Rng};
 use std::sync::Arc;
 use std::fs;
 use std::net::IpAddr;
-use std::io::Write;
+use std::collections::{HashSet, VecDeque};
+use std::io::{IsTerminal, Write};
 use serde_json;
 use anyhow::{Result, anyhow};
 use tokio::time::sleep;
 use crate::utils::MemoryLogBuffer;
-use crate::models::{ScanType, PortRanges, TopPorts, requires_raw_sockets};
+use crate::models::{ScanResults, ScanType, PortRanges, ProtocolPorts, TopPorts, requires_raw_sockets, parse_ip_protocols};
 
 // Declare the ndpi_bindings module generated by build.rs
 #[allow(warnings)] // Suppress warnings from generated code
 pub mod ndpi_bindings;
 
+mod amplification;
+mod api_discovery;
+mod assets;
+mod audit;
 mod banner;
+mod baseline;
+mod calibration;
+mod cert_analysis;
+mod cert_expand;
+mod cleartext;
+mod cloud_exposure;
+mod container_platform;
+mod correlate;
+mod cover;
+mod default_creds;
+mod detectability;
+mod detection_test;
+mod dns_audit;
+mod dns_sanity;
+mod edge_detect;
+mod encryption;
+mod entropy;
+mod evasion;
+mod fast_io;
+mod hostile;
 mod http_analyzer;
+mod inventory;
+mod logging;
+mod mac_vendor;
+mod merge;
+mod metadata;
 mod ml_service_ident;
 mod models;
 mod ndpi_integration;
 mod ndpi_sys;
+mod netbind;
+mod open_proxy;
+mod origin_hunt;
 mod output;
+mod passive;
+mod plugins;
+mod printer_enum;
+mod progress;
+mod query;
+mod redaction;
+mod relay;
+mod remote_access;
+mod report;
 mod scanner;
+mod scheduler;
+mod schema;
+mod scripting;
 mod service_fingerprints;
+mod service_policy;
+mod simulate;
+mod smtp_checks;
+mod sni_probe;
+mod target_stream;
+mod technique_select;
 mod techniques;
+mod transport;
 mod tunnel;
 mod utils;
 mod ssl_config;
+mod stateless;
+mod voip;
+mod vpn_detect;
+mod web_hygiene;
+mod well_known;
 
 use scanner::QuantumScanner;
 
+/// Largest CIDR target expanded for an active scan (a /16)
+const MAX_NETWORK_HOSTS: usize = 65536;
+
 /// Advanced port scanner with evasion capabilities for authorized red team operations
 #[derive(Parser, Debug)]
 #[clap(
@@ -86,15 +146,27 @@ use scanner::QuantumScanner;
     # Scan a range with multiple techniques and evasion
     quantum_scanner 192.168.0.0/24 -p 22,80,443-8000 -s syn,fin,xmas -e
 
+    # Compose evasion stages explicitly
+    quantum_scanner 10.0.0.1 -s syn --evasion ttl-jitter,tcp-opts:windows,timing:lognormal,frag
+
     # Full stealth scan through Tor with enhanced evasion
     quantum_scanner example.com -E -m --mimic-os linux --use-tor
 
     # Using protocol tunneling to bypass firewalls
     quantum_scanner 10.0.0.1 --dns-tunnel --lookup-domain example.com
 
+    # Run the tunnel relay on a host you control (see 'quantum_scanner relay --help')
+    quantum_scanner relay --dns-domain t.example.com --http-listen 0.0.0.0:8080 --token s3cret --allow 10.0.0.0/8
+
+    # Relay probes through a fronted HTTPS relay
+    quantum_scanner 10.0.0.1 -s http-tunnel --http-relay https://cdn.example.net/sync --http-host-header relay.example.org
+
     # Enhanced service identification with ML
     quantum_scanner 192.168.1.100 --ml-ident -p 22,80,443
 
+    # Fast sweep of all ports, then deep enumeration of the ones that answered
+    quantum_scanner 10.0.0.1 -p 1-10000 --two-phase --stateless --fast-io
+
     # Save results to a file in JSON format
     quantum_scanner 10.0.0.1 -j -o scan_results.json
 
@@ -111,6 +183,10 @@ AVAILABLE SCAN TYPES:
     frag        - Fragments packets to bypass deep packet inspection
     dns-tunnel  - Tunnels scan traffic through DNS queries
     icmp-tunnel - Tunnels scan traffic through ICMP echo (ping) packets
+    http-tunnel - Relays scan probes through an HTTP(S) relay (--http-relay)
+    quic-tunnel - Relays scan probes over QUIC/HTTP3-like UDP (--quic-relay)
+    ipproto     - Enumerates supported IP protocols (ICMP, GRE, ESP, AH, SCTP...)
+    badsum      - Compares normal, bad-checksum and SYN+FIN probes to spot middlebox replies
 
 MIMICRY OPTIONS:
     PROTOCOLS (used with --mimic-protocol):
@@ -131,14 +207,18 @@ MIMICRY OPTIONS:
 "
 )]
 struct Args {
-    /// Target IP address, hostname, or CIDR notation for subnet
-    #[clap(value_parser)]
+    /// Target IP address, hostname, or CIDR notation for subnet; `-` reads targets from stdin
+    #[clap(value_parser, long_help = "Target IP address, hostname, or CIDR notation for subnet.\n\
+                                      With `-`, targets are read from stdin, one per line, and each \
+                                      is scanned as soon as its line arrives, e.g. \
+                                      `amass enum -d example.com | quantum_scanner - -T`. Up to \
+                                      --max-hostgroup stdin targets are scanned at once.")]
     target: String,
 
     // ========== TARGET AND PORT SELECTION ==========
     
-    /// Ports to scan (comma-separated, ranges like 1-1000)
-    #[clap(short, long, default_value = "1-1000", group = "port_selection", help_heading = "TARGET AND PORT SELECTION")]
+    /// Ports to scan (comma-separated, ranges like 1-1000, or service names like ssh,https,smb)
+    #[clap(short, long, default_value = "1-1000", group = "port_selection", help_heading = "TARGET AND PORT SELECTION", long_help = "Ports to scan. Accepts single ports, ranges and service names, comma-separated, e.g. 22,80,8000-8100 or ssh,http,https,rdp,smb. Use nmap-style T: and U: prefixes to give TCP and UDP scan types different port sets, e.g. T:22,80,443,U:53,161 (ports before any prefix apply to both). Service names expand to every port the service is commonly found on (http includes 8080/8000/8888, https includes 8443).")]
     ports: String,
 
     /// Scan the top 100 common ports
@@ -149,6 +229,18 @@ struct Args {
     #[clap(short = 't', long, group = "port_selection", help_heading = "TARGET AND PORT SELECTION")]
     top_10: bool,
 
+    /// Scan the N most common ports (like nmap's --top-ports)
+    #[clap(long, value_name = "N", group = "port_selection", help_heading = "TARGET AND PORT SELECTION", long_help = "Scan the N most common TCP ports, most frequent first. Uses the embedded port-frequency table (the top 1000 matches nmap's default port set) unless --port-frequency-file is given. Values above 1000 are padded with the remaining ports in ascending order.")]
+    top_ports: Option<usize>,
+
+    /// Port frequency file used by --top-ports (nmap-services format or "port frequency" per line)
+    #[clap(long, requires = "top_ports", help_heading = "TARGET AND PORT SELECTION")]
+    port_frequency_file: Option<PathBuf>,
+
+    /// Expand to related ports based on what is found open
+    #[clap(long, default_value_t = false, help_heading = "TARGET AND PORT SELECTION", long_help = "Adaptive port selection. After the selected ports are scanned, open ports trigger probes of related ports: an alternate web port (e.g. 8080) expands to the 8000-8999 web range and common admin ports, SMB/LDAP/Kerberos expand to the full Windows service set, and similar groups for databases, mail, remote administration and container APIs. Repeats for up to three rounds while new ports are discovered. Works best with -T/--top-100.")]
+    adaptive_ports: bool,
+
     /// Use IPv6
     #[clap(short = '6', long, help_heading = "TARGET AND PORT SELECTION")]
     ipv6: bool,
@@ -156,29 +248,181 @@ struct Args {
     // ========== SCAN METHODS ==========
 
     /// Scan techniques to use (comma-separated)
-    #[clap(short, long, default_value = "syn", group = "scan_execution", help_heading = "SCAN METHODS", long_help = "Available techniques: syn, ssl, udp, ack, fin, xmas, null, window, mimic, frag, dns-tunnel, icmp-tunnel\nExamples: -s syn,ssl,udp or -s syn -s ssl\nNote: Do not include spaces after commas\n\n⚠️ OPSEC WARNING: The ssl and mimic scan types use full TCP connections that are easily logged by target systems. For stealth-critical operations, prefer using only the raw socket scan types like syn, fin, xmas, null, etc.")]
+    #[clap(short, long, default_value = "syn", group = "scan_execution", help_heading = "SCAN METHODS", long_help = "Available techniques: syn, ssl, udp, ack, fin, xmas, null, window, maimon, mimic, frag, dns-tunnel, icmp-tunnel, http-tunnel, quic-tunnel, ipproto, badsum\nExamples: -s syn,ssl,udp or -s syn -s ssl\nNote: Do not include spaces after commas\n\n⚠️ OPSEC WARNING: The ssl and mimic scan types use full TCP connections that are easily logged by target systems. For stealth-critical operations, prefer using only the raw socket scan types like syn, fin, xmas, null, etc.")]
     scan_types_str: String,
 
+    /// Gather host information with ICMP timestamp, address mask and unreachable analysis
+    #[clap(long, default_value_t = false, help_heading = "SCAN METHODS", long_help = "Sends an ICMP timestamp request, an ICMP address mask request and a UDP datagram to a closed port, then analyzes the replies: remote clock offset, subnet mask, initial TTL and how ICMP errors quote the offending datagram. The results characterize the host and feed the OS summary. Requires root privileges; IPv4 only.\n\n⚠️ OPSEC WARNING: Timestamp and address mask requests are unusual and commonly flagged by IDS.")]
+    icmp_probes: bool,
+
+    /// Pick the TCP techniques per host from a quick pre-probe of its stack
+    #[clap(long, default_value_t = false, conflicts_with = "passive", help_heading = "SCAN METHODS", long_help = "Before scanning each host, read its initial TTL from ICMP replies, find an open and a closed port with SYNs, and check how both answer FIN, ACK and Window probes. The TCP techniques of the scan are then chosen from what the host actually distinguishes: SYN always; FIN only when open ports ignore it and closed ports reset (not on Windows and other stacks that reset everything, where FIN/NULL/XMAS report every port closed); ACK when a stateless filter drops some SYNs; Window when RST windows differ between open and closed ports. Other requested scan types (udp, ssl, tunnels...) are kept. The choice and the observations behind it are reported per host as technique_selection. Requires root privileges.\n\n⚠️ OPSEC WARNING: The pre-probe sends FINs and ACKs outside any connection, which stealth-scan signatures match.")]
+    auto_technique: bool,
+
+    /// Payload of UDP probes as hex (e.g. 0xDEADBEEF)
+    #[clap(long, value_name = "HEX", conflicts_with = "udp_payload_file", help_heading = "SCAN METHODS", long_help = "Send these bytes in UDP scan probes instead of the generic payload, given as hex digits with an optional 0x prefix (whitespace between bytes allowed). Applies to every UDP port, or only to --udp-payload-ports. Probes from --probe-file take precedence on their ports. Use it to test custom UDP services that ignore datagrams they cannot parse")]
+    udp_payload: Option<String>,
+
+    /// Read the payload of UDP probes from a file (raw bytes)
+    #[clap(long, value_name = "PATH", help_heading = "SCAN METHODS", long_help = "Send the raw contents of this file in UDP scan probes instead of the generic payload. Applies to every UDP port, or only to --udp-payload-ports. Probes from --probe-file take precedence on their ports")]
+    udp_payload_file: Option<PathBuf>,
+
+    /// Ports the UDP payload applies to (e.g. 5000,7000-7010)
+    #[clap(long, value_name = "PORTS", help_heading = "SCAN METHODS", long_help = "Restrict --udp-payload or --udp-payload-file to these ports; other UDP ports get the generic probe. Same range syntax as -p")]
+    udp_payload_ports: Option<String>,
+
+    /// IP protocol numbers probed by the ipproto scan type (e.g. 1,47,50-51)
+    #[clap(long, help_heading = "SCAN METHODS", long_help = "Comma-separated IP protocol numbers or ranges (0-255) probed by the ipproto scan type. Defaults to a curated list: ICMP, IGMP, IP-in-IP, TCP, UDP, IPv6-in-IPv4, GRE, ESP, AH, OSPF, PIM, VRRP, L2TP, SCTP, UDPLite and MPLS-in-IP. Use 0-255 for a full protocol sweep.")]
+    ip_protocols: Option<String>,
+
     /// Enable port scan only mode (no service identification)
     #[clap(short = 'P', long = "port-scan", group = "scan_mode", help_heading = "SCAN METHODS", long_help = "Enables port scan only mode. This mode focuses solely on discovering open ports with minimal footprint. Disables nDPI, banner grabbing, and service version detection for maximum OPSEC.")]
     port_scan_only: bool,
 
+    /// Stateless SYN sweep with SYN cookies before the stateful scan types
+    #[clap(long, default_value_t = false, group = "scan_execution", help_heading = "SCAN METHODS", long_help = "Discover ports with a masscan-style stateless SYN sweep. Probes are sent without waiting for replies and the sequence number of each SYN is a keyed SYN cookie, so replies are validated without per-probe state. Ports that answer are then handed to the stateful engine for the remaining scan types and service detection. IPv4 only, requires root. Combine with --rate to cap throughput and --fast-io for maximum speed.")]
+    stateless: bool,
+
+    /// Fast discovery sweep, then deep enumeration of responsive ports only
+    #[clap(long, default_value_t = false, group = "scan_execution", help_heading = "SCAN METHODS", long_help = "Two-phase scan. Phase 1 performs a rapid SYN sweep across all selected ports (the stateless sweep if --stateless is also given). Phase 2 re-scans only the ports that responded with the full technique set: the selected scan types plus ssl and mimic probes, followed by banner grabbing, nDPI and ML service identification. Implies service detection unless --port-scan is given.")]
+    two_phase: bool,
+
+    /// Fixed 32-hex-digit key for tagging probes, shared with a separate collector
+    #[clap(long, help_heading = "SCAN METHODS", long_help = "Tag probes with this 32-hex-digit key instead of a random per-run key. Stateless probes carry a keyed hash of the key, target and ports in their sequence number, so a collector started with the same key ('quantum_scanner collect --key <hex>') can validate replies on another host or interface without knowing what was sent. The key in use is logged at debug level when not given")]
+    correlation_key: Option<String>,
+
+    /// Collect stateless replies by capturing on this interface instead of the raw socket
+    #[clap(long, help_heading = "SCAN METHODS", long_help = "Collect replies to stateless probes by promiscuous capture on this interface instead of the raw receive socket. Use when replies return on a different interface than probes leave from, or are addressed to a spoofed source on the local segment. Captures IPv4 and IPv6")]
+    response_iface: Option<String>,
+
+    /// Send all probes through this network interface
+    #[clap(long, value_name = "IFACE", help_heading = "SCAN METHODS", long_help = "Send every probe through this interface, e.g. eth1 or tun0. Raw probes use its first IPv4/IPv6 address as source unless --source-ip says otherwise, and on Linux raw, TCP and UDP sockets are bound to it with SO_BINDTODEVICE so traffic leaves through it whatever the routing table says. Use on multihomed jump boxes, or to keep a scan inside a particular VPN tunnel. On other platforms only the source address is bound and the route to the target picks the NIC")]
+    interface: Option<String>,
+
+    /// Source address for raw probes and connect/UDP sockets
+    #[clap(long, value_name = "IP", help_heading = "SCAN METHODS", long_help = "Source address for raw probes and for the TCP and UDP sockets opened by connect-based scans, service detection and follow-up checks. Must be assigned to a local interface (to --interface when both are given). Overrides auto-detection of the local address for raw scans")]
+    source_ip: Option<IpAddr>,
+
+    /// Run inside this Linux network namespace
+    #[clap(long, value_name = "NAME|PATH", help_heading = "SCAN METHODS", long_help = "Run every socket operation inside this Linux network namespace: a name created with 'ip netns add' or a path such as /proc/<pid>/ns/net to scan from a container's network. Unlike 'ip netns exec', only the network namespace changes, so files are read and written on the host as usual. Hostnames are still resolved with the host's /etc/resolv.conf, which may not be reachable from the namespace; prefer IP targets. Requires CAP_SYS_ADMIN. For a VRF, use --interface with the VRF device instead")]
+    netns: Option<String>,
+
+    /// Passive mode: build results from traffic on an interface or in a pcap file, sending nothing
+    #[clap(long, value_name = "IFACE|PCAP", help_heading = "SCAN METHODS", long_help = "Passive scanning. Build results purely by observing traffic on a live interface (promiscuous capture) or in a classic pcap file, without sending a single packet: SYN-ACKs and RSTs answering other clients, server greetings and HTTP responses as banners, TLS versions and (up to TLS 1.2) server certificates, and hostnames clients request via SNI or Host headers. Reports every host in the target IP/CIDR with observed activity on the selected ports. Hostname targets are refused since resolving them would send DNS queries. A port absent from the results was simply not seen")]
+    passive: Option<passive::PassiveSource>,
+
+    /// Seconds to watch a live interface in passive mode (0 = until Ctrl-C)
+    #[clap(long, default_value_t = 60, help_heading = "SCAN METHODS")]
+    passive_duration: u64,
+
+    /// Safe mode for fragile networks: no intrusive probes, conservative rates
+    #[clap(long, help_heading = "SCAN METHODS", long_help = "For fragile OT, medical and embedded networks where a malformed packet or an unexpected payload can crash a device. Drops the mimic, frag, badsum, xmas, null, maimon and ipproto scan types and the frag, badsum and flags evasion stages, disables default credential checks, scripts, plugins, custom UDP payloads and probe files, VPN handshakes, amplification, SMTP, DNS, open proxy, remote desktop and container platform checks, printer and SIP enumeration, cloud metadata canaries, FTP feature queries, API discovery, --web-paths and the mimic probes of --two-phase, and makes banner grabs read-only (services that speak first are read, nothing is sent). Caps --concurrency at 8, --rate at 50 packets per second, --banner-concurrency at 2 and --max-hostgroup at 4, and protects ICS, database, medical and printer services unless --protect-services is given. Every change is logged")]
+    safe: bool,
+
+    /// Service classes, services or ports that never get payload probes after identification
+    #[clap(long, value_name = "LIST", help_heading = "SCAN METHODS", long_help = "Comma-separated OPSEC policy of what deep enumeration must leave alone. Entries are the classes ics (Modbus, S7, DNP3, BACnet, EtherNet/IP, IEC 104, OPC UA), database, medical (DICOM, HL7) and printer, single service names as detection reports them (e.g. ldap), or port numbers. Ports are still discovered and identified; once identified as a protected service they get no VPN handshakes, amplification, SMTP, DNS, open proxy, remote desktop, container platform, cloud metadata, printer, SIP or cleartext protocol checks, default credential checks, scripts or plugins, and are marked with protected_by in the results. Banner grabs on the well-known ports of a protected class only read what the service sends on its own. --safe protects all four classes unless this is given")]
+    protect_services: Option<String>,
+
+    /// Extra zones to try DNS zone transfers of (comma-separated)
+    #[clap(long = "dns-zones", value_name = "ZONES", value_delimiter = ',', help_heading = "SCAN METHODS", long_help = "Zones the DNS audit tries a zone transfer (AXFR) of on every DNS server found, besides the domain of a hostname target. The audit runs with service detection (-V) when port 53 is in scope: it also asks for version.bind and tests for open recursion.")]
+    dns_zones: Vec<String>,
+
+    /// Media port range to test for RTP relaying (e.g. 10000-20000)
+    #[clap(long = "rtp-range", value_name = "START-END", help_heading = "SCAN METHODS", long_help = "Media ports to test for open RTP relays once a SIP service answers. Each even port in the range gets one RTP packet; a relay that sends RTP back to an unsolicited sender leaks the audio of calls in progress (RTP Bleed). At most 2000 media ports. Needs service detection (-V).")]
+    rtp_range: Option<String>,
+
+    /// Server names to try on every TLS port, one per line, to harvest the certificates behind them
+    #[clap(long = "sni-list", value_name = "FILE", help_heading = "SCAN METHODS", long_help = "File of server names (one per line, # comments allowed) to send as SNI on every TLS port once detection has found it. The server picks its certificate, and often its backend, by this name, so shared hosting, CDN edges and SNI-routing proxies reveal the other tenants and internal services they front. Distinct certificates are listed per port with the names that returned them, marked when they differ from the certificate served without SNI, and their names feed --expand-from-certs. One handshake per name and port; at most 1000 names. Needs service detection (-V).")]
+    sni_list: Option<PathBuf>,
+
+    /// Request a few well-known sensitive paths on every web port
+    #[clap(long = "web-paths", help_heading = "SCAN METHODS", long_help = "Request /robots.txt, /.git/HEAD, /.env, /server-status and /actuator once each on every web port found by detection, at most 8 ports per host, giving up on a port after 2 failed requests. A path is reported only when its body is what the path should hold (a git ref, KEY=value lines, Apache's status page, actuator links), so catch-all pages are not flagged. Exposed paths become findings and robots.txt Disallow entries are listed in the port's service details. Needs service detection (-V); not under --safe.")]
+    web_paths: bool,
+
     /// Enable service and version detection (less stealthy)
     #[clap(short = 'V', long = "service-scan", group = "scan_mode", help_heading = "SCAN METHODS", long_help = "Enables service and version detection. This mode performs additional connections after discovering open ports to identify services using nDPI, banner grabbing, and protocol analysis. Note: This is less stealthy as it requires establishing full TCP connections.")]
     service_scan: bool,
 
+    /// Keep the first NUM bytes of each service response in the results
+    #[clap(long, value_name = "NUM", help_heading = "SCAN METHODS", long_help = "Store the first NUM bytes (at most 4096, the most a banner grab reads) the service sent on each open port in the results, hex-encoded, for offline analysis and re-fingerprinting. Needs service detection (-sV or --two-phase). With --redact banners they are redacted like the banners themselves, and in memory-only mode they are wiped with the rest of the results at the end of the run; they reach disk only through the output files you ask for")]
+    capture_responses: Option<usize>,
+
+    /// Custom ML service model produced by 'quantum_scanner train-model'
+    #[clap(long, value_name = "PATH", help_heading = "SCAN METHODS", long_help = "Use this ML service model instead of the stock one for service identification. Train one on labelled banners of in-house or proprietary services with 'quantum_scanner train-model --dataset <file.jsonl> --output <path>'. Only used with service detection (-sV or --two-phase)")]
+    ml_model: Option<PathBuf>,
+
+    /// Extra service fingerprint file (nmap-service-probes syntax), reloaded on change; repeatable
+    #[clap(long, value_name = "PATH", help_heading = "SCAN METHODS", long_help = "Load additional service fingerprints from this file, in nmap-service-probes syntax (Probe, ports, match and softmatch lines with p/ v/ i/ version templates). A stock nmap-service-probes file can be used directly; signatures the regex engine cannot handle are skipped (see the import-fingerprints subcommand). Files are consulted in the order given, ahead of the built-in fingerprints, and are reloaded automatically when they change during a scan. May be given several times")]
+    fingerprints: Vec<PathBuf>,
+
+    /// Custom probes to send and match (YAML or TOML)
+    #[clap(long, value_name = "PATH", help_heading = "SCAN METHODS", long_help = "Load custom probes from a YAML (.yaml/.yml) or TOML (.toml) file: for each, the port(s), tcp or udp, the bytes to send (send with \\r \\n \\xNN escapes, or send_hex) and an expect regex the reply must match, with the service name and optional product/version/info templates using $1-style capture groups. TCP probes are sent when grabbing the port's banner (-sV); UDP probes replace the generic UDP scan payload on their ports, and their replies are matched during service detection. Custom probes are consulted ahead of all fingerprints, so proprietary internal services get detected and versioned. May be given several times")]
+    probe_file: Vec<PathBuf>,
+
+    /// Report certificates expiring within this many days
+    #[clap(long, value_name = "DAYS", default_value_t = cert_analysis::DEFAULT_EXPIRY_WARNING_DAYS, help_heading = "SCAN METHODS", long_help = "Certificates collected by the ssl scan are checked for self-signed or untrusted chains, broken links, expiry, weak signatures and short keys. Those whose validity ends within this many days are reported as expires-soon. 0 disables the warning")]
+    cert_expiry_warn_days: u32,
+
+    /// Run the Rhai scripts (*.rhai) in this directory against open ports after detection
+    #[clap(long, value_name = "DIR", help_heading = "SCAN METHODS", long_help = "Run user checks written in Rhai against open TCP ports once service detection is done. Every *.rhai file in the directory must define fn portrule(port) and fn action(port); scripts can send/recv on the port and report results with add_finding and add_vuln. Scripts open their own connections to the target")]
+    scripts: Option<PathBuf>,
+
+    /// Load the WebAssembly detection plugins (*.wasm) in this directory
+    #[clap(long, value_name = "DIR", help_heading = "SCAN METHODS", long_help = "Load sandboxed WebAssembly plugins. Plugins can supply banner probes, add to port results after detection (service, version, findings, vulnerabilities) and render extra report text. They run in an interpreter with no network or filesystem access and a per-call instruction budget")]
+    plugins: Option<PathBuf>,
+
+    /// Try a short list of default credentials against detected FTP, Telnet, HTTP basic auth and SNMP
+    #[clap(long, default_value_t = false, help_heading = "SCAN METHODS", long_help = "Opt-in: after service detection, try vendor default logins against FTP, Telnet, HTTP basic auth (when / returns 401 Basic) and SNMP communities (when UDP 161 is in the port list). Ports are checked one at a time with --creds-delay between attempts, at most --creds-max-attempts per service, stopping at the first success or any sign of lockout. Accepted logins are reported as critical vulnerabilities.\n\n⚠️ OPSEC WARNING: Failed logins are logged and frequently alerted on. Only use where credential testing is authorized.")]
+    check_default_creds: bool,
+
+    /// Credential list for --check-default-creds: `service user password` per line
+    #[clap(long, value_name = "PATH", requires = "check_default_creds", help_heading = "SCAN METHODS")]
+    creds_file: Option<PathBuf>,
+
+    /// Milliseconds between default credential attempts on the same service
+    #[clap(long, value_name = "MS", default_value_t = 2000, requires = "check_default_creds", help_heading = "SCAN METHODS")]
+    creds_delay: u64,
+
+    /// Default credential attempts per service before giving up
+    #[clap(long, value_name = "N", default_value_t = 5, requires = "check_default_creds", help_heading = "SCAN METHODS")]
+    creds_max_attempts: usize,
+
+    /// Scan hostnames from collected certificates (SAN/CN) that resolve into --scope
+    #[clap(long, default_value_t = false, requires = "scope", help_heading = "TARGET AND PORT SELECTION", long_help = "After the scan, resolve every Subject Alternative Name and subject CN in the TLS certificates collected, and scan the addresses that fall inside the --scope ranges with the same settings. New targets' certificates are expanded in turn, up to --expand-max extra hosts. Certificates are only collected by TLS-capable scan types (e.g. -s ssl or service detection).\n\n⚠️ OPSEC WARNING: Every certificate name is looked up through the system resolver.")]
+    expand_from_certs: bool,
+
+    /// Address ranges expanded targets must fall in (CIDR or IP, comma-separated or repeated)
+    #[clap(long, value_name = "CIDR", help_heading = "TARGET AND PORT SELECTION")]
+    scope: Vec<String>,
+
+    /// Maximum number of extra hosts --expand-from-certs may add
+    #[clap(long, value_name = "N", default_value_t = 32, help_heading = "TARGET AND PORT SELECTION")]
+    expand_max: usize,
+
+    /// Look for the origin servers behind hostnames found to sit behind a CDN
+    #[clap(long = "find-origin", default_value_t = false, conflicts_with = "passive", help_heading = "TARGET AND PORT SELECTION", long_help = "After the scan, look for the origin server of every hostname target flagged as a CDN edge. Candidates come from the --dns-history dataset (past addresses of the name and its sibling names that are not CDN addresses), from directly scanned hosts whose certificates name the hostname, and from directly scanned web servers that serve the site's favicon when asked for the hostname. Scan the client's ranges in the same run (or give them as --scope) so there is something to match against. Candidates are listed on the edge host with the evidence for each.\n\n⚠️ OPSEC WARNING: Favicon requests go to the edge and to every directly scanned web server, and to DNS history candidates inside --scope.")]
+    find_origin: bool,
+
+    /// CSV of past DNS answers (hostname,ip[,last_seen]) for --find-origin
+    #[clap(long = "dns-history", value_name = "FILE", requires = "find_origin", help_heading = "TARGET AND PORT SELECTION")]
+    dns_history: Option<PathBuf>,
+
     // ========== EVASION OPTIONS ==========
 
-    /// Enable basic evasion techniques 
-    #[clap(short, long, group = "evasion_options", help_heading = "EVASION OPTIONS", long_help = "Enable basic evasion techniques (simple TTL manipulation, basic timing randomization, minimal TCP option adjustment, packet sequencing randomization)")]
-    evasion: bool,
+    /// Evasion pipeline applied to raw probes, e.g. ttl-jitter,tcp-opts:windows,timing:lognormal,frag
+    #[clap(long, group = "evasion_options", help_heading = "EVASION OPTIONS", long_help = "Comma-separated evasion stages applied in order to every raw-socket probe. Stages: ttl-jitter[:os[:amount]] (randomise TTL around an OS's initial value), tcp-opts:<os> (OS TCP fingerprint: window, MSS, window scale, SACK, timestamps and option order), timing:<model> (independent random delay before each probe; same models as --timing-model), frag[:bytes] (send IPv4 probes as fragments of this many bytes, multiple of 8, default 8), pad[:random|https|<bytes>|<min>-<max>] (pad probes with random payload to a size drawn from the profile; https follows a typical HTTPS packet size mix), badsum (corrupt the TCP checksum), flags:<combo> (OR illegal flags such as syn-fin or fin-urg-psh into every probe). OS is windows, linux, macos or random. Later stages override earlier ones. Example: --evasion ttl-jitter,tcp-opts:windows,timing:lognormal,frag")]
+    evasion: Option<String>,
 
-    /// Enable advanced evasion techniques
-    #[clap(short = 'E', long, default_value_t = false, group = "evasion_options", help_heading = "EVASION OPTIONS", long_help = "Enable advanced evasion techniques (OS fingerprint spoofing, TTL jittering, protocol-specific mimicry, banner grabbing suppression, sophisticated protocol variants)")]
+    /// Basic evasion preset (same as --evasion ttl-jitter:random:1,timing:uniform)
+    #[clap(short = 'e', long, group = "evasion_options", help_heading = "EVASION OPTIONS", long_help = "Basic evasion preset: small TTL jitter and uniform random delays between probes. Shorthand for --evasion ttl-jitter:random:1,timing:uniform; ignored when --evasion is given")]
+    basic_evasion: bool,
+
+    /// Enhanced evasion preset (OS-consistent TTL and TCP options, log-normal timing)
+    #[clap(short = 'E', long, default_value_t = false, group = "evasion_options", help_heading = "EVASION OPTIONS", long_help = "Enhanced evasion preset: TTL, window and MSS consistent with --mimic-os, TTL jitter of --ttl-jitter and log-normal delays between probes. Shorthand for --evasion ttl-jitter:<os>:<jitter>,tcp-opts:<os>,timing:lognormal; ignored for packet shaping when --evasion is given, but still enables OS and protocol mimicry elsewhere")]
     enhanced_evasion: bool,
 
     /// Operating system to mimic in enhanced evasion mode (windows, linux, macos, random)
-    #[clap(long, group = "evasion_options", help_heading = "EVASION OPTIONS")]
+    #[clap(long, group = "evasion_options", help_heading = "EVASION OPTIONS", long_help = "Operating system to mimic in enhanced evasion mode (windows, linux, macos, random). Sets the initial TTL and the full TCP option fingerprint of raw probes: Linux MSS,SACK,TS,NOP,WS=7; Windows MSS,NOP,WS=8,NOP,NOP,SACK without timestamps; macOS MSS,NOP,WS=6,NOP,NOP,TS,SACK,EOL. 'random' picks one OS for the whole run")]
     mimic_os: Option<String>,
 
     /// TTL jitter amount for enhanced evasion (1-5)
@@ -197,6 +441,26 @@ struct Args {
     #[clap(long, default_value_t = false, group = "evasion_options", help_heading = "EVASION OPTIONS")]
     use_tor: bool,
 
+    /// Spoof the source MAC address of raw frames (vendor name, 'random', or XX:XX:XX:XX:XX:XX)
+    #[clap(long, group = "evasion_options", help_heading = "EVASION OPTIONS", long_help = "Spoof the source MAC address of raw frames emitted by the scanner. Accepts a vendor name (e.g. cisco, apple, vmware) to generate an address with that vendor's OUI, 'random' for a random locally administered address, or an explicit address in XX:XX:XX:XX:XX:XX form. Only affects raw-socket scan types, stateless sweeps and ICMP probing; the icmptunnel scan type is refused because the kernel builds its frames. Replies to the spoofed address may not be received on switched networks.")]
+    spoof_mac: Option<mac_vendor::SpoofMac>,
+
+    /// Split application payloads of connect-based probes into TCP segments of this many bytes
+    #[clap(long, group = "evasion_options", help_heading = "EVASION OPTIONS", long_help = "Send the application payload of mimic, banner and SSL probes (HTTP request, protocol greeting, TLS ClientHello) as TCP segments of this many bytes with Nagle disabled, pausing --tcp-segment-delay between them. Defeats DPI engines that classify a flow from its first segment only. Raw-socket scan types carry no payload and are unaffected")]
+    tcp_segment: Option<usize>,
+
+    /// Delay between payload segments in ms, as <min>-<max> or a single value
+    #[clap(long, default_value = "10-50", group = "evasion_options", help_heading = "EVASION OPTIONS")]
+    tcp_segment_delay: String,
+
+    /// Benign cover requests per scan probe (e.g. 0.5 = one every two probes)
+    #[clap(long, group = "evasion_options", help_heading = "EVASION OPTIONS", long_help = "Interleave real, benign-looking requests with scan probes: DNS lookups and HTTPS GETs of popular public sites, at this many requests per probe (e.g. 0.5 = one request every two probes, 2 = two per probe). Requests run in the background from this host and never delay probes. Buries the scan in ordinary browsing noise for egress monitoring, at the cost of extra traffic")]
+    cover_traffic: Option<f64>,
+
+    /// Share of cover requests that are DNS lookups (rest are HTTPS GETs)
+    #[clap(long, default_value_t = 0.6, group = "evasion_options", help_heading = "EVASION OPTIONS")]
+    cover_dns_share: f64,
+
     // ========== TUNNELING OPTIONS ==========
 
     /// Use DNS tunneling to bypass restrictive firewalls
@@ -215,6 +479,70 @@ struct Args {
     #[clap(long = "lookup-domain", group = "tunneling_options", help_heading = "TUNNELING OPTIONS")]
     lookup_domain: Option<String>,
 
+    /// Record type carrying DNS tunnel answers (txt, null, cname)
+    #[clap(long = "dns-record-type", default_value = "txt", group = "tunneling_options", help_heading = "TUNNELING OPTIONS", long_help = "Record type the DNS tunnel server answers with: 'txt' (widely passed by resolvers), 'null' (raw binary, highest capacity, often filtered by public resolvers) or 'cname' (blends in with alias chains, lowest capacity).")]
+    dns_record_type: tunnel::DnsRecordType,
+
+    /// Encoding for DNS tunnel payloads (base32, base64url)
+    #[clap(long = "dns-encoding", default_value = "base32", group = "tunneling_options", help_heading = "TUNNELING OPTIONS", long_help = "Encoding for data carried in query names and TXT answers. 'base32' is case-insensitive and survives resolvers that randomise case (0x20 encoding); 'base64url' is 20% denser but needs a case-preserving resolver path.")]
+    dns_encoding: tunnel::DnsEncoding,
+
+    /// Do not add an EDNS0 OPT record to DNS tunnel queries
+    #[clap(long = "no-edns0", default_value_t = false, group = "tunneling_options", help_heading = "TUNNELING OPTIONS")]
+    no_edns0: bool,
+
+    /// Skip probing the resolver path for usable query/response sizes
+    #[clap(long = "no-dns-mtu-probe", default_value_t = false, group = "tunneling_options", help_heading = "TUNNELING OPTIONS")]
+    no_dns_mtu_probe: bool,
+
+    /// Skip resolver sanity checks (NXDOMAIN rewriting, wildcards, interception)
+    #[clap(long = "no-dns-sanity", default_value_t = false, help_heading = "TUNNELING OPTIONS", long_help = "Before DNS tunnel scans and scans of a hostname target, the resolver path is checked for NXDOMAIN rewriting, wildcard records on the target's and tunnel's domains, and transparent DNS interception, and results are flagged or adjusted accordingly. This skips those checks and the queries they send.")]
+    no_dns_sanity: bool,
+
+    /// Relay banner grabs through an ICMP covert channel to this relay host
+    #[clap(long = "icmp-relay", group = "tunneling_options", help_heading = "TUNNELING OPTIONS", long_help = "Route banner grabs and service probes through an ICMP echo covert channel. The relay host opens the TCP connections on the scanner's behalf and returns the responses inside echo replies, so no TCP traffic leaves the scanner for service detection. The relay must run the quantum scanner ICMP relay with kernel echo replies disabled. Requires root privileges; IPv4 relays only.")]
+    icmp_relay: Option<IpAddr>,
+
+    /// Obfuscation for the ICMP covert channel (none, xor, aes)
+    #[clap(long = "tunnel-obfuscation", default_value = "none", group = "tunneling_options", help_heading = "TUNNELING OPTIONS", long_help = "Payload obfuscation for the ICMP covert channel: 'none', 'xor' (keyed XOR keystream, hides content only) or 'aes' (AES-256-GCM, also authenticates frames). xor and aes require --tunnel-key and must match the relay.")]
+    tunnel_obfuscation: String,
+
+    /// Shared passphrase for ICMP covert channel obfuscation
+    #[clap(long = "tunnel-key", group = "tunneling_options", help_heading = "TUNNELING OPTIONS")]
+    tunnel_key: Option<String>,
+
+    /// HTTP(S) relay URL for HTTP tunnel scans and relayed banner grabs
+    #[clap(long = "http-relay", group = "tunneling_options", help_heading = "TUNNELING OPTIONS", long_help = "URL of an operator-controlled HTTP(S) relay (e.g. https://cdn.example.com/api/v1/sync). The 'http-tunnel' scan type asks the relay to test each port, and banner grabs are relayed through it when no ICMP relay is set, so only web requests to the relay leave the scanner.")]
+    http_relay: Option<String>,
+
+    /// Host header to send to the HTTP relay (fronting)
+    #[clap(long = "http-host-header", group = "tunneling_options", help_heading = "TUNNELING OPTIONS", long_help = "Host header sent inside requests to the HTTP relay, overriding the URL host. Combined with an https:// relay URL pointing at a shared edge, the connection and SNI name the edge while the encrypted Host header selects the actual relay (domain fronting). Only works where the edge routes on Host.")]
+    http_host_header: Option<String>,
+
+    /// TLS SNI to send to the HTTP relay (defaults to the URL host)
+    #[clap(long = "http-sni", group = "tunneling_options", help_heading = "TUNNELING OPTIONS")]
+    http_sni: Option<String>,
+
+    /// Auth token expected by the HTTP and QUIC relays
+    #[clap(long = "http-relay-token", group = "tunneling_options", help_heading = "TUNNELING OPTIONS")]
+    http_relay_token: Option<String>,
+
+    /// Do not verify the HTTP relay's TLS certificate
+    #[clap(long = "http-relay-insecure", default_value_t = false, group = "tunneling_options", help_heading = "TUNNELING OPTIONS")]
+    http_relay_insecure: bool,
+
+    /// QUIC relay (host[:port], default port 443) for QUIC tunnel scans and relayed banner grabs
+    #[clap(long = "quic-relay", group = "tunneling_options", help_heading = "TUNNELING OPTIONS", long_help = "Address of an operator-controlled QUIC relay (host[:port], default port 443). The 'quic-tunnel' scan type sends each probe as a stream on a QUIC connection offering ALPN h3, so traffic resembles HTTP/3. Banner grabs are relayed through it when no ICMP or HTTP relay is set. The relay needs a certificate trusted by the system roots. Shares --http-relay-token as its auth token.")]
+    quic_relay: Option<String>,
+
+    /// TLS SNI for the QUIC relay (defaults to the relay host)
+    #[clap(long = "quic-sni", group = "tunneling_options", help_heading = "TUNNELING OPTIONS")]
+    quic_sni: Option<String>,
+
+    /// Open a fresh 0-RTT resumed QUIC connection for every probe
+    #[clap(long = "quic-fresh-connections", default_value_t = false, group = "tunneling_options", help_heading = "TUNNELING OPTIONS", long_help = "Open a new QUIC connection per probe instead of multiplexing all probes on one connection. After the first handshake each connection resumes with 0-RTT, so the probe rides in the first packet; the many short connections from changing source ports look like ordinary browser sessions rather than one long-lived flow.")]
+    quic_fresh_connections: bool,
+
     // ========== SERVICE DETECTION ==========
 
     // ========== TIMING AND PERFORMANCE ==========
@@ -227,10 +555,26 @@ struct Args {
     #[clap(short = 'r', long, default_value_t = 0, group = "timing_control", help_heading = "TIMING AND PERFORMANCE")]
     rate: usize,
 
+    /// Use the high-performance AF_PACKET send/receive path for raw scans (Linux, IPv4)
+    #[clap(long, default_value_t = false, group = "timing_control", help_heading = "TIMING AND PERFORMANCE", long_help = "Send raw probes through a memory-mapped AF_PACKET TX ring and receive replies on a single BPF-filtered socket instead of opening a raw socket per probe. Raises raw scan throughput by orders of magnitude; combine with a high --concurrency for large sweeps. Linux and IPv4 only, requires root. Falls back to the regular raw socket path if the engine cannot be set up.")]
+    fast_io: bool,
+
     /// Scan timeout in seconds
     #[clap(short, long, default_value_t = 5.0, group = "timing_control", help_heading = "TIMING AND PERFORMANCE")]
     timeout: f64,
 
+    /// Hosts scanned in parallel at the start of a multi-host scan
+    #[clap(long, default_value_t = 1, value_name = "NUM", group = "timing_control", help_heading = "TIMING AND PERFORMANCE", long_help = "Number of hosts of a CIDR target scanned in parallel in the first group. Each following group is twice as large, up to --max-hostgroup. --concurrency stays a per-host limit, so up to max-hostgroup x concurrency probes are in flight; --rate remains a limit for the whole run. Use a large hostgroup with low concurrency to scan many hosts slowly each, or a hostgroup of 1 with high concurrency to scan one host very fast")]
+    min_hostgroup: usize,
+
+    /// Most hosts scanned in parallel
+    #[clap(long, default_value_t = 1, value_name = "NUM", group = "timing_control", help_heading = "TIMING AND PERFORMANCE", long_help = "Largest number of hosts of a CIDR target scanned in parallel. Groups start at --min-hostgroup and double after each group up to this size. The default of 1 scans hosts one after another")]
+    max_hostgroup: usize,
+
+    /// Derive each host's probe timeout from its measured round-trip time
+    #[clap(long, default_value_t = false, group = "timing_control", help_heading = "TIMING AND PERFORMANCE", long_help = "Before scanning each host, send five TCP connect probes to estimate its round-trip time, and derive that host's probe timeout as srtt + 4*rttvar (RFC 6298), between 100 ms and --timeout. Unanswered probes are then retransmitted once, or twice when calibration probes were lost, so a shorter timeout does not turn late replies into filtered ports. Fast LAN hosts stop waiting seconds per silent port; hosts on slow links keep the full --timeout. Hosts that answer no calibration probe use --timeout. Banner, nDPI and script timeouts are not affected. The measured RTT is reported per host as host_timing.\n\n⚠️ OPSEC WARNING: Calibration probes are full connects, logged by open services before the scan starts.")]
+    adaptive_timeout: bool,
+
     /// Connect timeout in seconds
     #[clap(long, default_value_t = 5.0, group = "timing_control", help_heading = "TIMING AND PERFORMANCE")]
     timeout_connect: f64,
@@ -239,6 +583,14 @@ struct Args {
     #[clap(long, default_value_t = 5.0, group = "timing_control", help_heading = "TIMING AND PERFORMANCE")]
     timeout_banner: f64,
 
+    /// Maximum banner grabs in flight per host
+    #[clap(long, value_name = "NUM", default_value_t = banner::DEFAULT_BANNER_CONCURRENCY, group = "timing_control", help_heading = "TIMING AND PERFORMANCE", long_help = "With service detection, banners are grabbed as soon as the port scan finds a port open, while the rest of the ports are still being scanned. This caps how many banner connections are open to one host at once, on top of the port scan's --concurrency. Web ports get an HTTP request first, services that greet the client are only read, and other ports are read for up to 2 seconds before an HTTP request is tried")]
+    banner_concurrency: usize,
+
+    /// Seed for all randomized scan behaviour, to make runs reproducible
+    #[clap(long, value_name = "U64", help_heading = "TIMING AND PERFORMANCE", long_help = "Draw every randomized choice of the scan from this seed instead of the system RNG: start and inter-probe delays, timing jitter, source ports, sequence numbers and IP IDs, TTLs, padding, fragment order, decoy data, spoofed MACs and OS profile selection. Each probe draws from its own generator derived from the seed, target, port, technique and retransmission attempt, so two runs with the same seed and settings send the same probes at any --concurrency, which helps when debugging evasion logic or writing regression tests for it. Probes may still go out in a different order. Keys, passphrases and run IDs stay random. A fixed seed makes the traffic pattern repeatable, so do not reuse one across engagements")]
+    seed: Option<u64>,
+
     /// Add randomized delay before scan start (0-5 seconds)
     #[clap(long, default_value_t = true, group = "timing_control", help_heading = "TIMING AND PERFORMANCE")]
     random_delay: bool,
@@ -247,6 +599,18 @@ struct Args {
     #[clap(long, default_value_t = 3, group = "timing_control", help_heading = "TIMING AND PERFORMANCE")]
     max_delay: u64,
 
+    /// Inter-probe timing distribution (uniform, poisson, lognormal, diurnal)
+    #[clap(long, group = "timing_control", help_heading = "TIMING AND PERFORMANCE", long_help = "Space probe launches across the whole scan according to a timing distribution, independent of concurrency. Models: uniform[:min-max] (ms, default 50-500), poisson[:rate] (probes/sec, default 4), lognormal[:median[:sigma]] (ms, default 200:0.8), diurnal[:mean] (Poisson gaps with mean in ms during local business hours, default 300; stretched 2.5x over lunch, 3x at the edges of the day, 10x at weekends and 20x at night). Applies on top of --rate")]
+    timing_model: Option<scheduler::TimingModel>,
+
+    /// Cap total scan traffic, e.g. 5mbps, 512kbps or 1MB/s
+    #[clap(long, value_name = "RATE", group = "timing_control", help_heading = "TIMING AND PERFORMANCE", long_help = "Hard cap on the scanner's outgoing traffic for engagements with bandwidth rules of engagement. Accepts bits per second (800bps, 512kbps, 5mbps, 1gbps) or bytes per second with an upper-case B (256KB/s, 1MB/s). Every send path is paced against one budget: raw probes and fragments, TCP handshakes, banner, TLS and mimic payloads, UDP probes, default credential checks, tunnel transports, scripts and cover traffic. Sizes include IP and transport headers. The estimated bytes sent are reported per target as bytes_sent. Applies on top of --rate and --timing-model")]
+    max_bandwidth: Option<scheduler::Bandwidth>,
+
+    /// Finish within this time, e.g. 90s, 30m or 1h30m
+    #[clap(long, value_name = "DURATION", value_parser = scheduler::parse_duration, group = "timing_control", help_heading = "TIMING AND PERFORMANCE", long_help = "Total time budget for the run, e.g. 90s, 30m or 1h30m (a bare number is seconds). In the last fifth of the budget the scanner winds down: probes of ports not yet found open, adaptive port expansion, IP protocol and ICMP probing, VPN detection, credential checks and scripts stop, so the remaining time goes to confirming open ports and identifying their services. When the budget is spent nothing new is sent and results are written as usual. Probes that were never sent are listed per port under skipped_probes and omitted phases under skipped_phases, so an unscanned port is not mistaken for a filtered one")]
+    max_scan_time: Option<Duration>,
+
     // ========== FRAGMENTATION OPTIONS ==========
 
     /// Minimum fragment size for fragmented scans
@@ -277,12 +641,24 @@ struct Args {
     #[clap(long, group = "fragmentation", help_heading = "FRAGMENTATION OPTIONS")]
     frag_two_frags: bool,
 
+    /// Fragment emission order for frag scans (in-order, reverse, random, last-first)
+    #[clap(long, default_value = "in-order", group = "fragmentation", help_heading = "FRAGMENTATION OPTIONS")]
+    frag_order: evasion::FragmentOrder,
+
+    /// Overlapping fragment policy for frag scans (none, duplicate, first-wins, last-wins)
+    #[clap(long, default_value = "none", group = "fragmentation", help_heading = "FRAGMENTATION OPTIONS", long_help = "Add an overlapping fragment over the part of the TCP header holding the flags. duplicate resends it unchanged; first-wins sends the real fragment then a conflicting copy (targets keeping the first copy, e.g. Windows/BSD, see the real probe); last-wins sends the conflicting copy first (targets keeping the last copy see the real probe). Comparing results across policies shows how the target and any middlebox reassemble ambiguous datagrams")]
+    frag_overlap: evasion::FragmentOverlap,
+
     // ========== OUTPUT OPTIONS ==========
 
     /// Enable verbose output
     #[clap(short, long, group = "output_options", help_heading = "OUTPUT OPTIONS", long_help = "When enabled, provides detailed information about the scanning process to stdout, including debug-level messages. In disk mode, verbose logs are also written to the log file.")]
     verbose: bool,
 
+    /// Only print results: no banner, colors, progress or log messages below errors
+    #[clap(short = 'q', long, conflicts_with_all = ["verbose", "debug", "progress"], group = "output_options", help_heading = "OUTPUT OPTIONS", long_help = "Suppress the banner, ANSI colors, informational and warning log messages and progress lines, so that stdout carries only the results. With --json stdout is a single parseable JSON document. Errors are still reported on stderr, as is a generated log passphrase. The evasion confirmation prompt is not shown and is answered no. --progress-json events still go to stderr when requested.")]
+    quiet: bool,
+
     /// Show detailed debug information during scan
     #[clap(short = 'd', long, group = "output_options", help_heading = "OUTPUT OPTIONS", long_help = "When enabled, shows detailed debug information during scan, including individual port scan results. Use for troubleshooting.")]
     debug: bool,
@@ -299,10 +675,46 @@ struct Args {
     #[clap(short, long, group = "output_options", help_heading = "OUTPUT OPTIONS")]
     output: Option<PathBuf>,
 
+    /// Report format: text, json, markdown, sarif, nessus, greenbone, csv or html
+    #[clap(long = "output-format", value_enum, value_name = "FORMAT", conflicts_with = "json", group = "output_options", help_heading = "OUTPUT OPTIONS", long_help = "Format of the report printed or written with --output. text is the default report, json the same as -j. markdown produces a report for engagement deliverables: a summary table per host, all vulnerabilities and anomalies ordered by severity with each issue listed once along with the endpoints it affects, and a section per host listing its open ports with service, version, TLS and certificate details. Tables are GitHub-flavoured Markdown and paste directly into most reporting platforms. sarif writes a SARIF 2.1.0 log for CI/CD security gates and code scanning dashboards: each vulnerability id is a rule, each occurrence a result located at proto://host:port, with GitHub security-severity scores; security posture assessments are reported at level note. nessus writes a Nessus v2 (.nessus) file and greenbone a Greenbone/OpenVAS report XML (import with GMP create_report), for merging into vulnerability management programs: open ports, identified services and vulnerabilities become findings with stable synthetic plugin IDs (990000-999999) and CVE references where the id is a CVE. csv writes one row per port (host, port, protocol, state, service, version, reason, first banner line, finding count, highest severity, run ID) and html a self-contained report page with the summary, the findings across hosts and each host's open ports, ready to print to PDF. Saved JSON results can be rendered in any of these later with `quantum_scanner report <FILE> -f <FORMAT>`")]
+    output_format: Option<output::OutputFormat>,
+
+    /// Render results through a Tera template instead of a built-in format
+    #[clap(long = "report-template", value_name = "FILE", conflicts_with_all = ["json", "output_format"], group = "output_options", help_heading = "OUTPUT OPTIONS", long_help = "Render the results through a Tera template (Jinja2-like syntax) for branded HTML, PDF-ready reports or bespoke text formats. The template gets hosts (every host's results with a ports array sorted by port, each entry carrying port and proto alongside the port's fields), findings (each issue once with its affected endpoints), metadata (run ID, tool version, command line) and generated_at. Templates named *.html, *.htm or *.xml, optionally with a .tera suffix, have values HTML-escaped. The template is checked before scanning starts")]
+    report_template: Option<PathBuf>,
+
+    /// Only report ports matching this expression, e.g. "port<1024 && service==http && vulns>0"
+    #[clap(long, value_name = "EXPR", group = "output_options", help_heading = "OUTPUT OPTIONS", long_help = "Only report ports matching a filter expression, evaluated per port with the host's fields available: numbers port, vulns, anomalies, scripts; text host, target, status, proto, service, version, banner, os, posture, tls, cert, ndpi, reason, unreliable, owner, environment, criticality; lists cve, severity, confidence, script, tag. Operators: == != < <= > >= and ~ (case-insensitive regex), combined with &&, || and ! and parentheses; a field alone tests that it is present. Text comparisons ignore case; quote values containing spaces or operators. Hosts without matching ports are left out. Applies to every output format; the same language filters saved JSON with `quantum_scanner query <FILE> <EXPR>`")]
+    filter: Option<query::Filter>,
+
+    /// Attach owner, environment, criticality and tags from this file to each host
+    #[clap(long, value_name = "FILE", group = "output_options", help_heading = "OUTPUT OPTIONS", long_help = "YAML (.yaml/.yml) or TOML (.toml) file mapping IPs and CIDRs to asset metadata: a list under assets: of entries with match (IP or CIDR), owner, environment, criticality (low, medium, high or critical) and tags. Each host result carries the merged metadata of every entry covering it, the narrowest network winning, and shows it in every report format. Hosts also get a risk assessment from their worst finding and open ports, weighted by criticality; hosts no entry covers count as medium. The filter fields owner, environment, criticality and tag select on it")]
+    asset_file: Option<PathBuf>,
+
+    /// Compare results with an expected inventory and exit with code 2 on any deviation
+    #[clap(long, value_name = "FILE", group = "output_options", help_heading = "OUTPUT OPTIONS", long_help = "Known-good baseline for compliance checks: a YAML (.yaml/.yml) or TOML (.toml) inventory with a list under hosts: of entries with host (IP, CIDR or hostname) and ports (port numbers, or port with optional service and version), or JSON results of an earlier scan taken as known-good. Each host is compared with its hostname entry, else the narrowest network holding it. Open ports the baseline does not list, listed ports found closed or filtered, and changed services or versions are recorded under deviations and highlighted in every report format. Hosts the baseline does not list should have no open ports. Listed ports outside this run's port list are not reported missing; services and versions are only compared where the scan identified one, so use -V to check them. The run exits with code 2 when any host deviates")]
+    baseline: Option<PathBuf>,
+
     /// Use ANSI colors in output
     #[clap(long, default_value_t = true, group = "output_options", help_heading = "OUTPUT OPTIONS")]
     color: bool,
 
+    /// Encrypt report files written with --output (passphrase, env:VAR or file:PATH)
+    #[clap(long = "encrypt-output", value_name = "PASSPHRASE", requires = "output", group = "output_options", help_heading = "OUTPUT OPTIONS", long_help = "Encrypt the JSON or text report written to --output with AES-256-GCM under a key derived from this passphrase with Argon2id. Use env:NAME or file:PATH to read the passphrase from an environment variable or a file instead of the command line. Read the report back with `quantum_scanner decrypt <FILE>`.")]
+    encrypt_output: Option<String>,
+
+    /// Print a periodic progress line (probes done, rate, ETA) to stderr
+    #[clap(long, default_value_t = false, group = "output_options", help_heading = "OUTPUT OPTIONS")]
+    progress: bool,
+
+    /// Emit progress as JSON events, one per line, on stderr
+    #[clap(long = "progress-json", default_value_t = false, group = "output_options", help_heading = "OUTPUT OPTIONS", long_help = "Emit machine-readable progress on stderr, one JSON object per line: host_start and host_done when a host begins and ends, progress every --progress-interval seconds (completed/total probes for the host and overall, smoothed probes per second, per-host and overall ETA in seconds) and done at the end. Implies --progress.")]
+    progress_json: bool,
+
+    /// Seconds between progress reports
+    #[clap(long = "progress-interval", value_name = "SECS", default_value_t = 10, group = "output_options", help_heading = "OUTPUT OPTIONS")]
+    progress_interval: u64,
+
     // ========== OPERATIONAL SECURITY ==========
 
     /// Enable memory-only mode (no disk writes)
@@ -314,12 +726,28 @@ struct Args {
     log_file: PathBuf,
     
     /// Encrypt logs with a password
-    #[clap(long, default_value_t = true, group = "operational_security", help_heading = "OPERATIONAL SECURITY")]
+    #[clap(long, default_value_t = true, group = "operational_security", help_heading = "OPERATIONAL SECURITY", long_help = "Encrypt the log. In memory-only mode the buffer is encrypted; otherwise the log file is written as an authenticated encrypted stream that `quantum_scanner logs decrypt` reads back. Without --log-password a random passphrase is generated and printed once on stderr.")]
     encrypt_logs: bool,
     
     /// Password for log encryption
-    #[clap(long, group = "operational_security", help_heading = "OPERATIONAL SECURITY")]
-    _log_password: Option<String>,
+    #[clap(long = "log-password", group = "operational_security", help_heading = "OPERATIONAL SECURITY", long_help = "Passphrase for log encryption. Use env:NAME or file:PATH to keep it off the command line.")]
+    log_password: Option<String>,
+
+    /// Record every probe sent as NDJSON, for post-engagement attestation
+    #[clap(long = "audit-log", value_name = "PATH", group = "operational_security", help_heading = "OPERATIONAL SECURITY", long_help = "Record every probe the scanner sends to PATH, one JSON object per line: timestamp, run ID, technique and scan type, protocol, source and destination address and port, estimated size in bytes, and for crafted packets the evasion parameters (pipeline, TTL, window, fragmentation, padding, checksum, extra flags, delay, spoofed MAC). Covers raw packets, connections, payloads, UDP and ICMP datagrams, tunnel and cover traffic. Follows --encrypt-logs and --log-password: an encrypted trail is read back with `quantum_scanner logs decrypt`. In memory-only mode records stay in memory and are written, encrypted, when the run ends. Records are not redacted, and --secure-delete and --burn-after-run leave the file in place.")]
+    audit_log: Option<PathBuf>,
+
+    /// Print the estimated IDS detectability of this configuration and exit without scanning
+    #[clap(long = "assess-detection", group = "operational_security", help_heading = "OPERATIONAL SECURITY", long_help = "Score how likely the configured scan is to be noticed by network IDS and portscan detection, from 0 to 100, and explain each contributing factor: techniques (XMAS, NULL and FIN match common stealth-scan signatures), rate and timing, ports and hosts covered, service detection, credential checks and evasion settings. Prints the assessment and exits without sending anything. Every scan logs the same assessment before it starts, and warns when the score is high.")]
+    assess_detection: bool,
+
+    /// Blue-team mode: scan one host with each technique in turn, quietest first, and report per technique
+    #[clap(long = "detection-test", conflicts_with_all = ["assess_detection", "safe", "passive", "stateless", "two_phase"], group = "operational_security", help_heading = "OPERATIONAL SECURITY", long_help = "Turn the scanner into a detection coverage tester for a sensor-monitored host in scope. Instead of the -s scan types, the target is scanned once per technique (syn, ack, window, fin, maimon, null, xmas, frag, badsum, udp, ssl, mimic), ordered from the stealthiest to the noisiest by the --assess-detection heuristic, with --detection-test-gap seconds between stages. The report lists each stage's start and end time (UTC), packets and bytes sent, open ports seen and the expected detectability with its reasons, so the blue team can match each window against their IDS alerts. Written as JSON with -j, as text otherwise; combine with --audit-log for per-probe records. Needs a single host target and root")]
+    detection_test: bool,
+
+    /// Seconds between detection test stages
+    #[clap(long = "detection-test-gap", value_name = "SECS", default_value_t = detection_test::DEFAULT_STAGE_GAP_SECS, requires = "detection_test", group = "operational_security", help_heading = "OPERATIONAL SECURITY")]
+    detection_test_gap: u64,
     
     /// Create RAM disk for temporary files
     #[clap(long, default_value_t = true, group = "operational_security", help_heading = "OPERATIONAL SECURITY")]
@@ -337,13 +765,33 @@ struct Args {
     #[clap(long, default_value_t = false, group = "operational_security", help_heading = "OPERATIONAL SECURITY", long_help = "When enabled, performs secure deletion of log files and temporary files using multiple overwrite passes. Disabled by default for operational safety.")]
     secure_delete: bool,
     
+    /// Destroy the binary, logs and RAM disk once results are out
+    #[clap(long = "burn-after-run", default_value_t = false, group = "operational_security", help_heading = "OPERATIONAL SECURITY", long_help = "Single-run mode. After results have been printed or written, securely delete the log file, every file on the RAM disk, and the scanner executable itself, using --delete-passes overwrite passes. The report written with --output is kept. The running executable cannot be overwritten while it is executing, so it is wiped by a detached helper right after the process exits.")]
+    burn_after_run: bool,
+
+    /// Halt the run when the target shows signs of having noticed the scan
+    #[clap(long = "abort-on-countermeasure", group = "operational_security", help_heading = "OPERATIONAL SECURITY", long_help = "Dead-man switch. Every reply is watched for signs that the scan has been noticed and is being countered: a kind of probe the host used to answer going unanswered on every port (sudden universal filtering), replies slowing down five-fold and by 200 ms or more (a tarpit switching on; not judged under --max-bandwidth), or a port that completed handshakes earlier now resetting SYNs or refusing connections. These are always warned about; with this flag the first one stops every send path at once and ends the run without writing results, exiting with code 3. The RAM disk is unmounted and the log closed; add --burn-on-abort to also wipe them and the executable.")]
+    abort_on_countermeasure: bool,
+
+    /// On a countermeasure abort, securely delete the log, RAM disk and executable
+    #[clap(long = "burn-on-abort", requires = "abort_on_countermeasure", group = "operational_security", help_heading = "OPERATIONAL SECURITY", long_help = "When --abort-on-countermeasure ends the run, do what --burn-after-run does at the end of a normal one: clear the in-memory log, securely delete the log file and every file on the RAM disk, and wipe the scanner executable once the process has exited, using --delete-passes overwrite passes. The audit log is left in place.")]
+    burn_on_abort: bool,
+
     /// Number of secure delete passes
     #[clap(long, default_value_t = 3, group = "operational_security", help_heading = "OPERATIONAL SECURITY", long_help = "Specifies how many passes of overwriting should be performed when secure_delete is enabled. More passes provide better security but take longer.")]
     delete_passes: u8,
 
     /// Path to a log file to unredact (without running a scan)
-    #[clap(long, group = "operational_security", help_heading = "OPERATIONAL SECURITY", long_help = "When provided without running a scan, this will only perform the redaction removal operation on the specified log file, replacing [REDACTED] with the target IP.")]
+    #[clap(long, group = "operational_security", help_heading = "OPERATIONAL SECURITY", long_help = "When provided without running a scan, this will only perform the redaction removal operation on the specified log file. Keyed tokens are restored with --redact-key; masked target markers are replaced with the target IP.")]
     fix_log_file: Option<PathBuf>,
+
+    /// Redact these kinds of values from logs and console output
+    #[clap(long, value_enum, value_delimiter = ',', value_name = "CATEGORIES", group = "operational_security", help_heading = "OPERATIONAL SECURITY", long_help = "Comma-separated categories to redact from log records and console output: targets (IP addresses), hostnames, credentials (passphrases, keys, accepted default logins), banners, or all. Values are replaced by [REDACTED:<category>]. Files written with --output are not redacted.")]
+    redact: Vec<redaction::RedactCategory>,
+
+    /// Make redactions reversible with this passphrase (passphrase, env:VAR or file:PATH)
+    #[clap(long = "redact-key", value_name = "PASSPHRASE", group = "operational_security", help_heading = "OPERATIONAL SECURITY", long_help = "Replace redacted values with tokens encrypted under a key derived from this passphrase instead of a plain mask. Equal values give equal tokens, so logs can still be correlated, and --fix-log-file with the same --redact-key restores the originals.")]
+    redact_key: Option<String>,
 }
 
 /// Enum for scan types from CLI
@@ -357,6 +805,7 @@ enum ScanTypeArg {
     Xmas,
     Null,
     Window,
+    Maimon,
     Mimic,
     Frag,
 }
@@ -372,6 +821,7 @@ impl From<ScanTypeArg> for ScanType {
             ScanTypeArg::Xmas => ScanType::Xmas,
             ScanTypeArg::Null => ScanType::Null,
             ScanTypeArg::Window => ScanType::Window,
+            ScanTypeArg::Maimon => ScanType::Maimon,
             ScanTypeArg::Mimic => ScanType::Mimic,
             ScanTypeArg::Frag => ScanType::Frag,
         }
@@ -483,9 +933,6 @@ fn setup_logging(
         // Return the configured memory buffer wrapped in Option
         Ok(Some(memory_buffer))
     } else {
-        // Set up file logging
-        let env = Env::default().filter_or("RUST_LOG", filter_level);
-        
         // Create parent directories if needed
         if let Some(parent) = log_file.parent() {
             if !parent.exists() {
@@ -493,28 +940,11 @@ fn setup_logging(
             }
         }
         
-        // Keep a copy of the log file path for logging
-        let log_file_path = log_file.clone();
-        
-        // Configure env_logger to write to file and stderr
-        let mut builder = Builder::from_env(env);
-        let logger_result = if let Ok(log_file_handle) = fs::File::create(log_file) {
-            // Use try_init to avoid panic if logger is already initialized
-            let _ = builder
-                .format_timestamp_secs()
-                .target(env_logger::Target::Pipe(Box::new(log_file_handle)))
-                .try_init();
-            info!("Logging to file: {}", log_file_path.display());
-            Ok(None)
-        } else {
-            error!("Failed to create log file, falling back to stderr only");
-            let _ = builder
-                .format_timestamp_secs()
-                .try_init();
-            Ok(None)
-        };
-        
-        logger_result
+        // The logger is already initialised; it tees every record into this file from now on
+        if let Err(e) = logging::set_file_sink(log_file, encrypt_logs, log_password) {
+            error!("{}, falling back to stderr only", e);
+        }
+        Ok(None)
     }
 }
 
@@ -530,8 +960,8 @@ fn setup_logging(
 /// - Running scans requiring raw sockets (like SYN, FIN, Xmas) without root/Administrator privileges will likely fail silently or be blocked by the OS.
 /// - This check is crucial to prevent unexpected failures and inform the user.
 ///
-/// # Known Limitations
-/// - The Windows implementation is currently a placeholder and unreliable. It assumes privileges are sufficient. A proper check using Windows APIs is needed for accuracy on Windows.
+/// # Platform Notes
+/// - On Windows the process must be elevated (`IsUserAnAdmin`) and Npcap must be installed, since raw probes are sent and captured through it.
 fn check_privileges(scanner_needs_raw_sockets: bool) -> bool {
     if !scanner_needs_raw_sockets {
         return true;
@@ -545,25 +975,16 @@ fn check_privileges(scanner_needs_raw_sockets: bool) -> bool {
     
     #[cfg(windows)]
     {
-        // On Windows, accurately checking for Administrator privileges is complex.
-        // Use the `is_elevated` crate for a simpler cross-platform check (if available)
-        // or fall back to a WinAPI check.
-        // TODO: Implement a reliable Windows privilege check using appropriate WinAPI calls (e.g., IsUserAnAdmin). -> Implementing using `is_elevated`
-        // warn!(\"Windows privilege check is currently unreliable; assuming sufficient privileges.\");
-        // true
-        return match is_elevated::is_elevated() {
-            Ok(elevated) => {
-                if !elevated {
-                    warn!("Administrator privileges are required for raw socket scans on Windows.");
-                }
-                elevated
-            }
-            Err(e) => {
-                // Error checking elevation, assume not elevated for safety
-                error!("Failed to check for Administrator privileges: {}. Assuming not elevated.", e);
-                false
-            }
-        };
+        // IsUserAnAdmin is true only for an elevated token, not merely an admin account
+        let elevated = unsafe { windows_sys::Win32::UI::Shell::IsUserAnAdmin() } != 0;
+        if !elevated {
+            warn!("Administrator privileges are required for raw socket scans on Windows. Run from an elevated prompt.");
+        }
+        if !fast_io::npcap_available() {
+            warn!("Npcap is not installed; raw scans on Windows send and capture through it. Install it from https://npcap.com");
+            return false;
+        }
+        return elevated;
     }
     
     #[cfg(not(any(unix, windows)))]
@@ -777,6 +1198,18 @@ fn cleanup_ramdisk(ramdisk: &Option<PathBuf>) -> Result<(), anyhow::Error> {
     Ok(())
 }
 
+/// Byte written by each overwrite pass, cycled through in order; `None` is random data.
+/// Uses the DoD 5220.22-M standard as a reference
+const WIPE_PATTERNS: [Option<u8>; 7] = [
+    Some(0x00), // All zeros
+    Some(0xFF), // All ones
+    None,       // Random data
+    Some(0x55), // Alternating 01010101
+    Some(0xAA), // Alternating 10101010
+    Some(0xF0), // 11110000
+    Some(0x0F), // 00001111
+];
+
 /// Securely delete a file by overwriting it multiple times
 ///
 /// # Arguments
@@ -826,30 +1259,16 @@ fn secure_delete_file(path: &PathBuf, passes: u8) -> Result<(), anyhow::Error> {
     
     use std::io::{Seek, SeekFrom, Write};
     
-    // Patterns for different passes
-    // Using the DoD 5220.22-M standard as a reference
-    let overwrite_patterns: Vec<Box<dyn Fn() -> u8>> = vec![
-        Box::new(|| 0x00), // All zeros
-        Box::new(|| 0xFF), // All ones
-        Box::new(|| thread_rng().gen::<u8>()), // Random data - Create new RNG inside closure
-        Box::new(|| 0x55), // Alternating 01010101
-        Box::new(|| 0xAA), // Alternating 10101010
-        Box::new(|| 0xF0), // 11110000
-        Box::new(|| 0x0F), // 00001111
-    ];
-    
     // Buffer size for each write operation
     const BUFFER_SIZE: usize = 4096;
     let mut buffer = vec![0u8; BUFFER_SIZE];
     
     // Perform the overwrite passes
     for pass in 0..actual_passes {
-        // Select pattern based on pass number
-        let pattern_fn = &overwrite_patterns[pass as usize % overwrite_patterns.len()];
-        
-        // Fill the buffer with the selected pattern
-        for i in 0..BUFFER_SIZE {
-            buffer[i] = pattern_fn();
+        // Fill the buffer with the pattern for this pass
+        match WIPE_PATTERNS[pass as usize % WIPE_PATTERNS.len()] {
+            Some(byte) => buffer.fill(byte),
+            None => thread_rng().fill(&mut buffer[..]),
         }
         
         // Seek to the beginning of the file
@@ -888,52 +1307,246 @@ fn secure_delete_file(path: &PathBuf, passes: u8) -> Result<(), anyhow::Error> {
     }
 }
 
-/// Unredact a log file by replacing [REDACTED] with the actual IP address
+/// Securely delete every file below a directory
+///
+/// Used on the RAM disk before it is unmounted, so nothing can be recovered from
+/// the pages it releases.
+///
+/// # Arguments
+/// * `dir` - Directory to empty (the directory itself is kept)
+/// * `passes` - Number of overwrite passes (1-7)
+fn secure_delete_dir(dir: &Path, passes: u8) -> Result<(), anyhow::Error> {
+    for entry in fs::read_dir(dir)? {
+        let path = entry?.path();
+        if path.is_dir() {
+            secure_delete_dir(&path, passes)?;
+            let _ = fs::remove_dir(&path);
+        } else if let Err(e) = secure_delete_file(&path, passes) {
+            warn!("Error during secure delete of {}: {}", path.display(), e);
+        }
+    }
+    Ok(())
+}
+
+/// Schedule the running executable for secure deletion once this process exits
+///
+/// A running image cannot be opened for writing (ETXTBSY on Linux, a sharing
+/// violation on Windows), so a detached helper waits for this PID to go away and then
+/// makes the same overwrite passes as `secure_delete_file` before removing the file:
+/// a POSIX shell using `head` and `tr` on Unix, PowerShell on Windows. The helper runs
+/// after this process has exited, so nothing can confirm the wipe; if a pass fails the
+/// executable is left in place rather than merely unlinked.
+///
+/// # Arguments
+/// * `passes` - Number of overwrite passes (1-7)
+///
+/// # Returns
+/// * `Result<(), anyhow::Error>` - Error if the helper's tools are missing or it cannot
+///   be started
+///
+/// # Opsec Considerations
+/// - The helper shows up briefly in the process list with the executable's path.
+fn schedule_self_delete(passes: u8) -> Result<(), anyhow::Error> {
+    let exe = std::env::current_exe()?;
+    let exe_str = exe.to_str().ok_or_else(|| anyhow!("Executable path is not valid UTF-8"))?;
+    let size = fs::metadata(&exe)?.len();
+    let pid = process::id();
+    let passes = passes.clamp(1, 7);
+    let patterns = (0..passes as usize).map(|pass| WIPE_PATTERNS[pass % WIPE_PATTERNS.len()]);
+
+    #[cfg(unix)]
+    {
+        // Check the tools up front: once this process is gone nobody sees the helper fail
+        let tools = std::process::Command::new("/bin/sh")
+            .args(["-c", "command -v head && command -v tr"])
+            .stdout(std::process::Stdio::null())
+            .status()?;
+        if !tools.success() {
+            return Err(anyhow!("`head` and `tr` are needed to overwrite the executable"));
+        }
+
+        // Single quotes in the path are closed, escaped and reopened for sh. `1<>` opens
+        // the file for writing without truncating it, so each pass rewrites the same blocks
+        let quoted = format!("'{}'", exe_str.replace('\'', "'\\''"));
+        let wipes: Vec<String> = patterns.map(|pattern| match pattern {
+            Some(0x00) => format!("head -c {} /dev/zero 1<> {}", size, quoted),
+            Some(byte) => format!("tr '\\000' '\\{:03o}' < /dev/zero | head -c {} 1<> {}", byte, size, quoted),
+            None => format!("head -c {} /dev/urandom 1<> {}", size, quoted),
+        }).collect();
+        let script = format!(
+            "while kill -0 {pid} 2>/dev/null; do sleep 0.2; done; {wipes} && rm -f {exe}",
+            pid = pid, wipes = wipes.join(" && "), exe = quoted
+        );
+        std::process::Command::new("/bin/sh")
+            .args(["-c", &script])
+            .stdin(std::process::Stdio::null())
+            .stdout(std::process::Stdio::null())
+            .stderr(std::process::Stdio::null())
+            .spawn()?;
+    }
+
+    #[cfg(windows)]
+    {
+        use base64::Engine;
+
+        // -1 marks a random pass; the path is a single-quoted PowerShell literal
+        let bytes: Vec<String> = patterns.map(|pattern| pattern.map_or(-1, i32::from).to_string()).collect();
+        let script = format!(
+            "$ErrorActionPreference = 'Stop'\n\
+             $path = '{exe}'\n\
+             Wait-Process -Id {pid} -ErrorAction SilentlyContinue\n\
+             $file = $null\n\
+             for ($try = 0; $try -lt 50 -and -not $file; $try++) {{\n\
+                 try {{ $file = [IO.File]::Open($path, 'Open', 'Write', 'None') }} catch {{ Start-Sleep -Milliseconds 200 }}\n\
+             }}\n\
+             if (-not $file) {{ exit 1 }}\n\
+             $size = {size}\n\
+             $buffer = New-Object byte[] 4096\n\
+             $rng = [Security.Cryptography.RandomNumberGenerator]::Create()\n\
+             foreach ($byte in @({bytes})) {{\n\
+                 if ($byte -lt 0) {{ $rng.GetBytes($buffer) }} else {{ for ($i = 0; $i -lt $buffer.Length; $i++) {{ $buffer[$i] = $byte }} }}\n\
+                 $file.Position = 0\n\
+                 for ($written = 0; $written -lt $size; $written += $count) {{\n\
+                     $count = [Math]::Min($buffer.Length, $size - $written)\n\
+                     $file.Write($buffer, 0, $count)\n\
+                 }}\n\
+                 $file.Flush($true)\n\
+             }}\n\
+             $file.Close()\n\
+             Remove-Item -LiteralPath $path -Force\n",
+            exe = exe_str.replace('\'', "''"), pid = pid, size = size, bytes = bytes.join(",")
+        );
+        // -EncodedCommand takes UTF-16LE base64, which sidesteps cmd.exe quoting
+        let utf16: Vec<u8> = script.encode_utf16().flat_map(|unit| unit.to_le_bytes()).collect();
+        std::process::Command::new("powershell")
+            .args(["-NoProfile", "-NonInteractive", "-WindowStyle", "Hidden", "-EncodedCommand",
+                   &base64::engine::general_purpose::STANDARD.encode(utf16)])
+            .stdin(std::process::Stdio::null())
+            .stdout(std::process::Stdio::null())
+            .stderr(std::process::Stdio::null())
+            .spawn()?;
+    }
+
+    info!("Executable {} is scheduled for {} overwrite passes and removal once this process exits", exe.display(), passes);
+    Ok(())
+}
+
+/// Unredact a log file
+///
+/// Tokens written in keyed mode (`--redact-key`) are decrypted back to the original
+/// values. Masked target markers (`[REDACTED:target]`, and the older bare `[REDACTED]`)
+/// carry nothing to decrypt and are replaced with the given IP address.
 ///
 /// # Arguments
 /// * `log_file` - Path to the log file to be unredacted
-/// * `ip_address` - IP address to replace [REDACTED] with
+/// * `ip_address` - IP address to put in place of masked target markers
+/// * `redact_key` - Passphrase spec the log was redacted with, if keyed mode was used
 ///
 /// # Returns
 /// * `Result<usize, anyhow::Error>` - Ok(count) with the number of replacements made, or an error.
 ///
 /// # Opsec Considerations
-/// - This utility allows reversing the redaction applied to log files for analysis *after* an operation, assuming the original target IP is known.
+/// - This utility allows reversing the redaction applied to log files for analysis *after* an operation.
 /// - Redaction helps protect the target identity in logs during the operation or if logs are exfiltrated prematurely.
-/// - Creates a backup (`.bak`) of the original redacted log before modifying it.
-fn fix_redacted_log(log_file: &PathBuf, ip_address: &str) -> Result<usize, anyhow::Error> {
+/// - Masked credentials, hostnames and banners cannot be restored; only keyed tokens can.
+fn fix_redacted_log(log_file: &PathBuf, ip_address: &str, redact_key: Option<&str>) -> Result<usize, anyhow::Error> {
     info!("Attempting to fix redactions in log file: {}", log_file.display());
 
     // Read the entire log file content
+    if fs::read(log_file).map_or(false, |data| encryption::is_encrypted_log(&data)) {
+        return Err(anyhow!("{} is an encrypted log; decrypt it first with `quantum_scanner logs decrypt`", log_file.display()));
+    }
     let content = match fs::read_to_string(log_file) {
         Ok(c) => c,
         Err(e) => return Err(anyhow!("Failed to read log file {}: {}", log_file.display(), e)),
     };
 
     // Check if redaction marker exists
-    if !content.contains("[REDACTED]") {
+    if !content.contains("[REDACTED") {
         info!("No redaction markers found in {}. Nothing to fix.", log_file.display());
         return Ok(0);
     }
 
-    // Perform the replacement
-    let updated_content = content.replace("[REDACTED]", ip_address);
-    let replacements = content.matches("[REDACTED]").count();
+    // Keyed tokens first, then masked target markers
+    let (mut updated_content, mut replacements) = match redact_key {
+        Some(key) => redaction::unredact(&content, key)?,
+        None => (content, 0),
+    };
+    for marker in ["[REDACTED:target]", "[REDACTED]"] {
+        replacements += updated_content.matches(marker).count();
+        updated_content = updated_content.replace(marker, ip_address);
+    }
 
     // Write the updated content back to the file (overwrite)
     match fs::write(log_file, updated_content) {
         Ok(_) => {
-            info!("Successfully fixed {} redactions in {}. Replaced with {}", replacements, log_file.display(), ip_address);
+            info!("Successfully fixed {} redactions in {}", replacements, log_file.display());
             Ok(replacements)
         }
         Err(e) => Err(anyhow!("Failed to write updated content to log file {}: {}", log_file.display(), e)),
     }
 }
 
+/// Scan types whose probes can upset fragile stacks, left out by `--safe`
+const SAFE_MODE_EXCLUDED: [ScanType; 7] = [
+    ScanType::Mimic, ScanType::Frag, ScanType::BadSum, ScanType::Xmas, ScanType::Null, ScanType::Maimon, ScanType::IpProto,
+];
+
+/// Most concurrent operations in safe mode
+const SAFE_MODE_MAX_CONCURRENCY: usize = 8;
+/// Most packets per second in safe mode
+const SAFE_MODE_MAX_RATE: usize = 50;
+/// Most banner grabs in flight per host in safe mode
+const SAFE_MODE_MAX_BANNER_CONCURRENCY: usize = 2;
+/// Most hosts scanned in parallel in safe mode
+const SAFE_MODE_MAX_HOSTGROUP: usize = 4;
+
+/// Turn off everything `--safe` forbids and cap the rates, logging each change
+///
+/// Scan types and evasion stages are filtered once they are parsed, since their
+/// aliases and presets are only resolved there.
+fn apply_safe_mode(args: &mut Args) {
+    let disable = |enabled: bool, what: &str| {
+        if enabled {
+            warn!("Safe mode: disabling {}", what);
+        }
+    };
+    disable(args.check_default_creds, "default credential checks");
+    args.check_default_creds = false;
+    disable(args.scripts.is_some(), "post-detection scripts");
+    args.scripts = None;
+    disable(args.plugins.is_some(), "plugins");
+    args.plugins = None;
+    disable(args.udp_payload.is_some() || args.udp_payload_file.is_some(), "the custom UDP payload");
+    args.udp_payload = None;
+    args.udp_payload_file = None;
+    args.udp_payload_ports = None;
+    disable(!args.probe_file.is_empty(), "probe files");
+    args.probe_file.clear();
+    disable(args.frag_overlap != evasion::FragmentOverlap::None, "overlapping fragments");
+    args.frag_overlap = evasion::FragmentOverlap::None;
+    if args.protect_services.is_none() {
+        warn!("Safe mode: protecting ICS, database, medical and printer services from deep enumeration");
+        args.protect_services = Some("ics,database,medical,printer".to_string());
+    }
+
+    let cap = |value: &mut usize, limit: usize, what: &str| {
+        if *value == 0 || *value > limit {
+            warn!("Safe mode: limiting {} to {}", what, limit);
+            *value = limit;
+        }
+    };
+    cap(&mut args.concurrency, SAFE_MODE_MAX_CONCURRENCY, "--concurrency");
+    cap(&mut args.rate, SAFE_MODE_MAX_RATE, "--rate");
+    cap(&mut args.banner_concurrency, SAFE_MODE_MAX_BANNER_CONCURRENCY, "--banner-concurrency");
+    cap(&mut args.max_hostgroup, SAFE_MODE_MAX_HOSTGROUP, "--max-hostgroup");
+    args.min_hostgroup = args.min_hostgroup.min(args.max_hostgroup);
+}
+
 /// Parses the scan type string (e.g., "syn,ssl,udp") and returns a vector of ScanTypes.
 /// When evasion flags are set, prompts user to confirm use of non-OPSEC-friendly scan types.
 /// Handles potential errors during parsing.
-fn parse_scan_types(scan_types_str: &str, evasion: bool, enhanced_evasion: bool) -> Result<Vec<ScanType>> {
+fn parse_scan_types(scan_types_str: &str, evasion: bool, enhanced_evasion: bool, interactive: bool) -> Result<Vec<ScanType>> {
     let mut scan_types = Vec::new();
     let mut needs_opsec_warning = false;
     let mut has_ssl = false;
@@ -958,6 +1571,7 @@ fn parse_scan_types(scan_types_str: &str, evasion: bool, enhanced_evasion: bool)
             "xmas" => scan_types.push(ScanType::Xmas),
             "null" => scan_types.push(ScanType::Null),
             "window" => scan_types.push(ScanType::Window),
+            "maimon" => scan_types.push(ScanType::Maimon),
             "mimic" => {
                 scan_types.push(ScanType::Mimic);
                 needs_opsec_warning = true;
@@ -972,6 +1586,22 @@ fn parse_scan_types(scan_types_str: &str, evasion: bool, enhanced_evasion: bool)
                 scan_types.push(ScanType::IcmpTunnel);
                 info!("Using ICMP tunnel scanning technique");
             },
+            "httptunnel" | "http-tunnel" => {
+                scan_types.push(ScanType::HttpTunnel);
+                info!("Using HTTP tunnel scanning technique");
+            },
+            "quictunnel" | "quic-tunnel" => {
+                scan_types.push(ScanType::QuicTunnel);
+                info!("Using QUIC tunnel scanning technique");
+            },
+            "badsum" | "bad-checksum" => {
+                scan_types.push(ScanType::BadSum);
+                info!("Using bad-checksum comparison scanning technique");
+            },
+            "ipproto" | "ip-proto" | "proto" => {
+                scan_types.push(ScanType::IpProto);
+                info!("Using IP protocol scanning technique");
+            },
             _ => return Err(anyhow!("Invalid scan type specified: {}", trimmed)),
         }
     }
@@ -998,12 +1628,14 @@ fn parse_scan_types(scan_types_str: &str, evasion: bool, enhanced_evasion: bool)
         warn!("   These scan types establish full TCP connections which contradicts evasion goals");
         warn!("   and leaves forensic evidence in target logs.");
         
-        // Prompt for confirmation
-        print!("   Continue with these scan types anyway? [y/N]: ");
-        std::io::stdout().flush().unwrap();
-        
+        // Prompt for confirmation; without a terminal (e.g. targets piped in) or in quiet
+        // mode the answer is no
         let mut input = String::new();
-        std::io::stdin().read_line(&mut input).unwrap();
+        if interactive && std::io::stdin().is_terminal() {
+            print!("   Continue with these scan types anyway? [y/N]: ");
+            std::io::stdout().flush().unwrap();
+            std::io::stdin().read_line(&mut input).unwrap();
+        }
         let input = input.trim().to_lowercase();
         
         // If not confirmed, remove these scan types
@@ -1030,6 +1662,14 @@ fn parse_scan_types(scan_types_str: &str, evasion: bool, enhanced_evasion: bool)
     if scan_types.contains(&ScanType::IcmpTunnel) {
         info!("ICMP tunneling technique selected. Requires root privileges.");
     }
+
+    if scan_types.contains(&ScanType::HttpTunnel) {
+        info!("HTTP tunneling technique selected. Requires --http-relay pointing at a relay you control");
+    }
+
+    if scan_types.contains(&ScanType::QuicTunnel) {
+        info!("QUIC tunneling technique selected. Requires --quic-relay pointing at a relay you control");
+    }
     
     // Deduplicate scan types
     scan_types.sort_unstable();
@@ -1037,16 +1677,136 @@ fn parse_scan_types(scan_types_str: &str, evasion: bool, enhanced_evasion: bool)
     Ok(scan_types)
 }
 
-#[tokio::main]
-async fn main() -> Result<(), anyhow::Error> {
+/// Print or save results, narrowed by --filter when given
+fn output_results(results: &[ScanResults], args: &Args) {
+    let Some(filter) = &args.filter else {
+        return output::write_results(results, &report_options(args));
+    };
+    let mut filtered = filter.apply(results.to_vec());
+    info!("Filter '{}' kept {} of {} hosts", filter, filtered.len(), results.len());
+    output::write_results(&filtered, &report_options(args));
+    // The caller only wipes the unfiltered results
+    if args.memory_only {
+        filtered.iter_mut().for_each(utils::wipe_results);
+    }
+}
+
+/// Rendering and destination options for `output::write_results`, taken from the command line
+fn report_options(args: &Args) -> output::ReportOptions<'_> {
+    output::ReportOptions {
+        format: args.output_format,
+        json: args.json,
+        pretty_json: args.pretty_json,
+        template: args.report_template.as_deref(),
+        output: args.output.as_deref(),
+        verbose: args.verbose,
+        quiet: args.quiet,
+    }
+}
+
+fn main() -> Result<(), anyhow::Error> {
+    // --netns has to be entered before the runtime starts its worker threads, which inherit it
+    if let Some(netns) = netbind::netns_from_args(&std::env::args().collect::<Vec<_>>()) {
+        netbind::enter_netns(&netns)?;
+    }
+    tokio::runtime::Builder::new_multi_thread()
+        .enable_all()
+        .build()?
+        .block_on(run())
+}
+
+async fn run() -> Result<(), anyhow::Error> {
     // Initialize default logger early to catch errors during setup
-    let _ = env_logger::Builder::from_env(env_logger::Env::default().default_filter_or("info"))
-        .format_timestamp_secs() // Keep consistent format
-        .try_init();
+    logging::init();
 
     // Parse command-line arguments
-    let args = Args::parse();
-    let _colors = Colors::new(args.color);
+    // --- Special Mode: Tunnel Relay ---
+    // `quantum_scanner relay ...` (or the binary installed as `quantum_relay`) runs the
+    // far end of the DNS/ICMP/HTTP/QUIC tunnels instead of scanning.
+    let invoked_as_relay = std::env::args().next()
+        .and_then(|arg0| std::path::Path::new(&arg0).file_stem().map(|s| s.to_string_lossy() == "quantum_relay"))
+        .unwrap_or(false);
+    if invoked_as_relay || std::env::args().nth(1).as_deref() == Some("relay") {
+        let relay_args = if invoked_as_relay {
+            relay::RelayArgs::parse()
+        } else {
+            relay::RelayArgs::parse_from(std::env::args().skip(1))
+        };
+        return relay::run_relay(relay_args).await;
+    }
+
+    // `quantum_scanner collect ...` validates replies to probes sent by another process
+    if std::env::args().nth(1).as_deref() == Some("collect") {
+        return correlate::run_collect(correlate::CollectArgs::parse_from(std::env::args().skip(1))).await;
+    }
+
+    // `quantum_scanner train-model ...` builds a custom ML service model and exits
+    if std::env::args().nth(1).as_deref() == Some("train-model") {
+        return ml_service_ident::run_train(ml_service_ident::TrainArgs::parse_from(std::env::args().skip(1)));
+    }
+
+    // `quantum_scanner decrypt ...` reads back a report written with --encrypt-output
+    if std::env::args().nth(1).as_deref() == Some("decrypt") {
+        return encryption::run_decrypt(encryption::DecryptArgs::parse_from(std::env::args().skip(1)));
+    }
+
+    // `quantum_scanner logs decrypt ...` reads back a log written with --encrypt-logs
+    if std::env::args().nth(1).as_deref() == Some("logs") {
+        return logging::run_logs(logging::LogsArgs::parse_from(std::env::args().skip(1)));
+    }
+
+    // `quantum_scanner query ...` filters saved JSON results and exits
+    if std::env::args().nth(1).as_deref() == Some("query") {
+        return query::run_query(query::QueryArgs::parse_from(std::env::args().skip(1)));
+    }
+
+    // `quantum_scanner simulate ...` serves a local fake target until Ctrl-C
+    if std::env::args().nth(1).as_deref() == Some("simulate") {
+        return simulate::run_simulate(simulate::SimulateArgs::parse_from(std::env::args().skip(1))).await;
+    }
+
+    // `quantum_scanner merge ...` combines saved results from several runs and exits
+    if std::env::args().nth(1).as_deref() == Some("merge") {
+        return merge::run_merge(merge::MergeArgs::parse_from(std::env::args().skip(1)));
+    }
+
+    // `quantum_scanner report ...` renders saved JSON results in any output format and exits
+    if std::env::args().nth(1).as_deref() == Some("report") {
+        return report::run_report(report::ReportArgs::parse_from(std::env::args().skip(1)));
+    }
+
+    // `quantum_scanner convert ...` upgrades saved JSON results to the current schema and exits
+    if std::env::args().nth(1).as_deref() == Some("convert") {
+        return schema::run_convert(schema::ConvertArgs::parse_from(std::env::args().skip(1)));
+    }
+
+    // `quantum_scanner import-fingerprints ...` converts an nmap-service-probes file and exits
+    if std::env::args().nth(1).as_deref() == Some("import-fingerprints") {
+        return service_fingerprints::run_import(service_fingerprints::ImportArgs::parse_from(std::env::args().skip(1)));
+    }
+
+    // Parse command line arguments
+    let mut args = Args::parse();
+    if args.safe {
+        apply_safe_mode(&mut args);
+    }
+    // Seed before anything draws random values, including lazily initialized ones
+    if let Some(seed) = args.seed {
+        utils::set_seed(seed);
+    }
+    if args.quiet {
+        // Nothing but results on stdout; errors still reach stderr
+        log::set_max_level(log::LevelFilter::Error);
+        console::set_colors_enabled(false);
+    }
+    metadata::set_metadata(metadata::capture(&std::env::args().collect::<Vec<_>>(), &format!("{:?}", args)));
+    if let Some(template) = &args.report_template {
+        output::load_template(template)?;
+    }
+    if let Some(spec) = &args.encrypt_output {
+        encryption::set_output_passphrase(Some(encryption::resolve_passphrase(spec)?));
+    }
+    let _colors = Colors::new(args.color && !args.quiet);
 
     // --- Special Mode: Fix Redacted Log File --- 
     // If --fix-log-file is provided, perform only that action and exit.
@@ -1058,7 +1818,7 @@ async fn main() -> Result<(), anyhow::Error> {
         }
         // The target argument is reused to provide the IP for unredaction
         let ip_to_insert = &args.target; 
-        match fix_redacted_log(log_path_to_fix, ip_to_insert) {
+        match fix_redacted_log(log_path_to_fix, ip_to_insert, args.redact_key.as_deref()) {
             Ok(count) => {
                 info!("Log file redaction fix completed. {} replacements made.", count);
                 process::exit(0);
@@ -1072,6 +1832,29 @@ async fn main() -> Result<(), anyhow::Error> {
 
     // --- Regular Scan Execution --- 
 
+    // Redaction applies to every log record from here on, so configure it before logging
+    // to a file and register the values already known
+    if !args.redact.is_empty() {
+        if let Err(e) = redaction::configure(&args.redact, args.redact_key.as_deref()) {
+            error!("Invalid redaction settings: {}", e);
+            process::exit(1);
+        }
+        let category = if args.target.parse::<IpAddr>().is_ok() {
+            redaction::RedactCategory::Targets
+        } else {
+            redaction::RedactCategory::Hostnames
+        };
+        if args.target != target_stream::STDIN_TARGET {
+            redaction::register(category, &args.target);
+        }
+        for secret in [&args.tunnel_key, &args.log_password, &args.encrypt_output].into_iter().flatten() {
+            redaction::register(redaction::RedactCategory::Credentials, secret);
+            if let Ok(resolved) = encryption::resolve_passphrase(secret) {
+                redaction::register(redaction::RedactCategory::Credentials, &resolved);
+            }
+        }
+    }
+
     // Set up logging based on args (handles memory vs file, encryption)
     // Note: This re-initializes the logger if not memory_only.
     
@@ -1085,7 +1868,7 @@ async fn main() -> Result<(), anyhow::Error> {
         args.debug,
         args.memory_only, 
         args.encrypt_logs, 
-        args._log_password.as_deref() // Pass optional password
+        args.log_password.as_deref() // Pass optional password
     ) { 
         Ok(buffer_opt) => {
             // Convert Option<MemoryLogBuffer> to Option<Arc<parking_lot::Mutex<MemoryLogBuffer>>>
@@ -1098,22 +1881,71 @@ async fn main() -> Result<(), anyhow::Error> {
         }
     };
 
-    // Display cool banner
-    if args.color {
-        println!("{}", banner::display_banner(true));
-    } else {
-        println!("{}", banner::display_banner(false));
+    // The audit trail has to be open before the first probe of any kind goes out
+    if let Some(path) = &args.audit_log {
+        if let Err(e) = audit::start(path, args.encrypt_logs, args.log_password.as_deref(), args.memory_only) {
+            error!("Failed to open audit log: {}", e);
+            process::exit(1);
+        }
+    }
+
+    // Display cool banner, unless stdout is reserved for results
+    if !args.quiet {
+        println!("{}", banner::display_banner(args.color));
     }
 
     // Parse scan types from args.scan_types_str and check for needed privileges
-    let scan_types = parse_scan_types(&args.scan_types_str, args.evasion, args.enhanced_evasion)?;
-    let needs_raw_sockets = requires_raw_sockets(&scan_types);
+    // Build the evasion pipeline: an explicit --evasion spec wins over the -e/-E presets
+    let mut evasion_pipeline = match (&args.evasion, args.enhanced_evasion, args.basic_evasion) {
+        (Some(spec), _, _) => spec.parse::<evasion::EvasionPipeline>()?,
+        (None, true, _) => evasion::EvasionPipeline::enhanced(args.mimic_os.as_deref().unwrap_or("random"), args.ttl_jitter)?,
+        (None, false, true) => evasion::EvasionPipeline::basic(),
+        (None, false, false) => evasion::EvasionPipeline::default(),
+    };
+    if args.safe {
+        for stage in evasion_pipeline.remove_malformed() {
+            warn!("Safe mode: dropping evasion stage {}", stage);
+        }
+    }
+    let evasion_enabled = !evasion_pipeline.is_empty();
+
+    let mut scan_types = parse_scan_types(&args.scan_types_str, evasion_enabled, args.enhanced_evasion, !args.quiet)?;
+    if args.safe {
+        scan_types.retain(|scan_type| {
+            let allowed = !SAFE_MODE_EXCLUDED.contains(scan_type);
+            if !allowed {
+                warn!("Safe mode: dropping the {} scan type", scan_type);
+            }
+            allowed
+        });
+        if scan_types.is_empty() {
+            warn!("Safe mode: no scan types left, falling back to syn");
+            scan_types.push(ScanType::Syn);
+        }
+    }
+    // A detection test runs through its own spectrum of techniques
+    if args.detection_test {
+        scan_types = detection_test::SPECTRUM.to_vec();
+    }
+    // The ICMP tunnel scan hands its echo to the kernel, which would add the real MAC
+    if args.spoof_mac.is_some() && scan_types.contains(&ScanType::IcmpTunnel) {
+        return Err(anyhow!("--spoof-mac cannot be combined with the icmptunnel scan type: its echo requests are sent with the interface's real MAC"));
+    }
+    let uses_dns_tunnel = args.dns_tunnel || scan_types.contains(&ScanType::DnsTunnel);
+    // Stateless and two-phase discovery always send raw SYNs, whatever the scan types
+    // Passive mode opens its own capture and never sends, so nothing here applies to it
+    let needs_raw_sockets = args.passive.is_none()
+        && (requires_raw_sockets(&scan_types) || args.stateless || args.two_phase || args.icmp_probes || args.auto_technique || args.icmp_relay.is_some());
     
     // Determine the scanning mode
     let service_scan_mode = if args.port_scan_only {
         // Port scan only mode explicitly chosen
         info!("Port scan only mode selected (-sP). Service identification disabled for improved stealth.");
         false
+    } else if args.two_phase {
+        // Deep enumeration of responsive ports is the point of two-phase scanning
+        info!("Two-phase scan selected. Service identification enabled for the enumeration phase.");
+        true
     } else if args.service_scan {
         // Service scan mode explicitly chosen
         info!("Service scan mode selected (-sV). Will perform additional connections for service identification.");
@@ -1125,6 +1957,26 @@ async fn main() -> Result<(), anyhow::Error> {
         false
     };
     
+    if let Some(netns) = &args.netns {
+        info!("Scanning from network namespace {}", netns);
+    }
+
+    if let Some(budget) = args.max_scan_time {
+        info!("Time budget: {:?} (winding down for the last fifth)", budget);
+        scheduler::set_deadline(budget);
+    }
+
+    if let Some(limit) = args.max_bandwidth {
+        info!("Bandwidth budget: {} ({:.0} bytes/s)", limit, limit.bytes_per_sec());
+        scheduler::set_bandwidth_limit(limit);
+    }
+
+    // Pin probes to the requested interface/source before anything picks a local address
+    if let Err(e) = netbind::configure(args.interface.as_deref(), args.source_ip) {
+        error!("Invalid --interface/--source-ip: {}", e);
+        process::exit(1);
+    }
+
     // Try to detect local IPv4 if raw sockets are needed
     let local_ip_v4 = if needs_raw_sockets {
         info!("Raw socket scans selected. Attempting to detect local IPv4 address.");
@@ -1162,12 +2014,37 @@ async fn main() -> Result<(), anyhow::Error> {
     } else if args.top_10 {
         info!("Using top 10 common ports");
         TopPorts::top_10()
+    } else if let Some(count) = args.top_ports {
+        if count == 0 || count > 20000 {
+            error!("--top-ports must be between 1 and 20000 (got {}).", count);
+            process::exit(1);
+        }
+        match &args.port_frequency_file {
+            Some(path) => match TopPorts::from_frequency_file(path, count) {
+                Ok(ports) => {
+                    info!("Using top {} ports from frequency file {}", ports.len(), path.display());
+                    ports
+                }
+                Err(e) => {
+                    error!("Error loading port frequency file '{}': {}", path.display(), e);
+                    process::exit(1);
+                }
+            },
+            None => {
+                info!("Using top {} common ports", count);
+                TopPorts::top_n(count)
+            }
+        }
     } else {
         info!("Parsing custom port specification: {}", &args.ports);
         match PortRanges::parse(&args.ports) {
             Ok(ranges) => {
                 // Expand ranges into a Vec<u16>
-                let expanded_ports: Vec<u16> = PortRanges::new(ranges).into_iter().collect();
+                // Service names can overlap (e.g. http,http-alt), so drop repeats while keeping order
+                let mut seen_ports = std::collections::HashSet::new();
+                let expanded_ports: Vec<u16> = PortRanges::new(ranges).into_iter()
+                    .filter(|port| seen_ports.insert(*port))
+                    .collect();
                 // Add validation for total number of ports to prevent excessive scanning
                 if expanded_ports.len() > 20000 { // Limit to 20k ports
                    error!("Too many ports specified ({}). Maximum allowed is 20000.", expanded_ports.len());
@@ -1187,6 +2064,69 @@ async fn main() -> Result<(), anyhow::Error> {
         }
     };
 
+    // Asset metadata is attached to each host as it finishes
+    if let Some(path) = &args.asset_file {
+        let assets = assets::AssetMap::load_file(path)?;
+        info!("Loaded {} asset entries from {}", assets.entry_count(), path.display());
+        assets::set_assets(Some(assets));
+    }
+
+    // The baseline is loaded up front so a bad file fails before any traffic
+    let expected_inventory = match &args.baseline {
+        Some(path) => {
+            let inventory = inventory::Inventory::load_file(path)?;
+            info!("Loaded baseline of {} hosts from {}", inventory.host_count(), path.display());
+            Some(inventory)
+        }
+        None => None,
+    };
+
+    // Likewise the DNS history for --find-origin
+    let dns_history = match &args.dns_history {
+        Some(path) => {
+            let history = origin_hunt::DnsHistory::load_file(path)?;
+            info!("Loaded {} DNS history records from {}", history.record_count(), path.display());
+            Some(history)
+        }
+        None => None,
+    };
+
+    // Passive mode observes instead of scanning: nothing below this point runs
+    if let Some(source) = args.passive.clone() {
+        if args.target == target_stream::STDIN_TARGET {
+            error!("Passive mode needs its scope as the target; stdin targets are not supported");
+            process::exit(1);
+        }
+        let scope = passive::PassiveScope::new(&args.target, &ports_to_scan)?;
+        let duration = (args.passive_duration > 0).then(|| Duration::from_secs(args.passive_duration));
+        let mut results = passive::observe(source, scope, duration).await?;
+        if results.is_empty() {
+            warn!("No in-scope port activity observed.");
+        }
+        baseline::find_outliers(&mut results);
+        let deviations = expected_inventory.as_ref().map_or(0, |inventory| inventory.compare(&mut results));
+        output_results(&results, &args);
+        if args.memory_only {
+            results.iter_mut().for_each(utils::wipe_results);
+        }
+
+        logging::close_file_sink();
+        if (args.secure_delete || args.burn_after_run) && !args.memory_only {
+            if let Err(e) = secure_delete_file(&args.log_file, args.delete_passes) {
+                warn!("Error during secure delete of log file: {}", e);
+            }
+        }
+        if args.burn_after_run {
+            if let Err(e) = schedule_self_delete(args.delete_passes) {
+                warn!("Could not schedule deletion of the executable: {}", e);
+            }
+        }
+        if deviations > 0 {
+            process::exit(inventory::DEVIATION_EXIT_CODE);
+        }
+        return Ok(());
+    }
+
     // Handle Tor setup (best effort)
     if args.use_tor {
         info!("Attempting to route traffic through Tor...");
@@ -1213,12 +2153,52 @@ async fn main() -> Result<(), anyhow::Error> {
         }
     };
 
-    // Apply random delay if requested
-    if args.random_delay {
+    // --- Dead-man switch ---
+    // A countermeasure halts every send path at once; this task then ends the run from
+    // whatever phase it was in
+    if args.abort_on_countermeasure {
+        hostile::arm_abort();
+        let ramdisk = ramdisk_path.clone();
+        let memory_log = memory_log_buffer.clone();
+        let (burn, memory_only, passes, log_file) = (args.burn_on_abort, args.memory_only, args.delete_passes, args.log_file.clone());
+        tokio::spawn(async move {
+            let reason = hostile::abort_signal().await;
+            error!("Run aborted by countermeasure ({}); no results are written", reason);
+            audit::finish();
+            if burn {
+                if let Some(buffer) = &memory_log {
+                    buffer.lock().clear();
+                }
+                if let Some(mount_point) = &ramdisk {
+                    if let Err(e) = secure_delete_dir(mount_point, passes) {
+                        warn!("Error during secure delete of RAM disk contents: {}", e);
+                    }
+                }
+            }
+            if let Err(e) = cleanup_ramdisk(&ramdisk) {
+                warn!("Error during RAM disk cleanup: {}", e);
+            }
+            logging::close_file_sink();
+            if burn && !memory_only {
+                if let Err(e) = secure_delete_file(&log_file, passes) {
+                    warn!("Error during secure delete of log file: {}", e);
+                }
+            }
+            if burn {
+                if let Err(e) = schedule_self_delete(passes) {
+                    warn!("Could not schedule deletion of the executable: {}", e);
+                }
+            }
+            process::exit(hostile::ABORT_EXIT_CODE);
+        });
+    }
+
+    // Apply random delay if requested (nothing is sent when only assessing detectability)
+    if args.random_delay && !args.assess_detection {
         let delay_secs = if args.max_delay > 0 {
-            thread_rng().gen_range(0..=args.max_delay)
+            utils::scan_rng().gen_range(0..=args.max_delay)
         } else {
-            thread_rng().gen_range(0..=3) // Default 0-3 seconds
+            utils::scan_rng().gen_range(0..=3) // Default 0-3 seconds
         };
         
         if delay_secs > 0 {
@@ -1227,15 +2207,179 @@ async fn main() -> Result<(), anyhow::Error> {
         }
     }
 
+    cert_analysis::set_expiry_warning_days(args.cert_expiry_warn_days);
+
+    // User fingerprints go ahead of the built-in database and are watched for edits
+    if !args.fingerprints.is_empty() {
+        service_fingerprints::set_fingerprint_files(&args.fingerprints)?;
+        service_fingerprints::watch_fingerprint_files();
+    }
+    if !args.probe_file.is_empty() {
+        service_fingerprints::set_probe_files(&args.probe_file)?;
+    }
+
+    // Scripts are compiled before any traffic so a syntax error aborts cleanly
+    if let Some(dir) = &args.scripts {
+        let count = scripting::load_scripts(dir)?;
+        info!("Loaded {} scripts from {}", count, dir.display());
+        if count > 0 {
+            warn!("⚠️ OPSEC WARNING: Scripts open their own connections to every open port they match.");
+        }
+    }
+
+    dns_audit::set_zones(args.dns_zones.clone());
+    if let Some(spec) = &args.rtp_range {
+        if let Err(e) = voip::set_rtp_range(spec) {
+            error!("Invalid --rtp-range: {}", e);
+            process::exit(1);
+        }
+    }
+    well_known::set_enabled(args.web_paths);
+    if let Some(path) = &args.sni_list {
+        match sni_probe::load_list(path) {
+            Ok(count) => info!("Loaded {} SNI names from {}", count, path.display()),
+            Err(e) => {
+                error!("Invalid --sni-list: {:#}", e);
+                process::exit(1);
+            }
+        }
+    }
+
+    // The service policy is checked after detection, before any deep enumeration
+    if let Some(spec) = &args.protect_services {
+        let policy = match service_policy::ServicePolicy::parse(spec) {
+            Ok(policy) => policy,
+            Err(e) => {
+                error!("Invalid --protect-services: {}", e);
+                process::exit(1);
+            }
+        };
+        info!("Deep enumeration skips protected services: {}", policy.describe());
+        service_policy::set_policy(Some(policy));
+    }
+
+    // Default credential checks are opt-in and need the credential list loaded up front
+    if args.check_default_creds {
+        let mut config = default_creds::CredCheckConfig::builtin(
+            Duration::from_millis(args.creds_delay),
+            args.creds_max_attempts.max(1),
+            Duration::from_secs_f64(args.timeout_banner),
+        );
+        if let Some(path) = &args.creds_file {
+            config.load_credentials(path)?;
+        }
+        info!("Default credential checks enabled: {} credentials, {} attempts per service, {}ms apart",
+              config.credentials.len(), config.max_attempts, args.creds_delay);
+        warn!("⚠️ OPSEC WARNING: Default credential checks produce failed-login events on the target.");
+        default_creds::set_config(Some(config));
+    }
+
+    // Plugins are validated up front; one that fails to load aborts the run
+    if let Some(dir) = &args.plugins {
+        let count = plugins::load_plugins(dir)?;
+        info!("Loaded {} plugins from {}", count, dir.display());
+    }
+
+    // A custom ML model must load, rather than silently falling back to the stock one
+    if let Some(path) = &args.ml_model {
+        ml_service_ident::ServiceModel::load(path)?;
+        ml_service_ident::set_model_path(path.clone());
+    }
+
+    // A CIDR target is expanded to its host addresses, all scanned with the same settings
+    let network_hosts: Vec<IpAddr> = match args.target.parse::<ipnet::IpNet>() {
+        Ok(net) if net.hosts().take(MAX_NETWORK_HOSTS + 1).count() > MAX_NETWORK_HOSTS => {
+            error!("{} has more than {} hosts; split it into smaller ranges", args.target, MAX_NETWORK_HOSTS);
+            process::exit(1);
+        }
+        Ok(net) => net.hosts().collect(),
+        Err(_) => Vec::new(),
+    };
+    if args.detection_test && (network_hosts.len() > 1 || args.target == target_stream::STDIN_TARGET) {
+        error!("--detection-test scans a single monitored host; give one IP or hostname as the target");
+        process::exit(1);
+    }
+    if args.min_hostgroup == 0 || args.max_hostgroup < args.min_hostgroup {
+        error!("Invalid hostgroup sizes: need 1 <= --min-hostgroup <= --max-hostgroup (got {} and {})", args.min_hostgroup, args.max_hostgroup);
+        process::exit(1);
+    }
+
+    // Estimate how loud this configuration is before anything is sent
+    let profile = detectability::ScanProfile {
+        scan_types: &scan_types,
+        ports: ports_to_scan.len(),
+        hosts: network_hosts.len().max(1),
+        rate: args.rate,
+        concurrency: args.concurrency,
+        timing_model: args.timing_model,
+        evasion_stages: evasion_pipeline.to_string().split(',')
+            .filter_map(|stage| stage.split(':').next())
+            .filter(|name| *name != "none")
+            .map(str::to_string)
+            .collect(),
+        stateless: args.stateless,
+        service_scan: service_scan_mode,
+        default_creds: args.check_default_creds,
+        scripts: args.scripts.is_some(),
+        icmp_probes: args.icmp_probes,
+        auto_technique: args.auto_technique,
+        cover_traffic: args.cover_traffic.is_some(),
+        tcp_segment: args.tcp_segment.is_some(),
+    };
+    let assessment = detectability::assess(&profile);
+    let detection_stages = args.detection_test.then(|| detection_test::plan(&profile));
+    if args.assess_detection {
+        println!("{}", assessment);
+        if let Err(e) = cleanup_ramdisk(&ramdisk_path) {
+            warn!("Error during RAM disk cleanup: {}", e);
+        }
+        audit::finish();
+        return Ok(());
+    }
+    for line in assessment.to_string().lines() {
+        info!("[OPSEC] {}", line);
+    }
+    if assessment.score >= 60 {
+        warn!("⚠️ OPSEC WARNING: Estimated detectability {}/100 ({}); run with --assess-detection for the reasons", assessment.score, assessment.level());
+    }
+    // Stdin targets: wait for the first one that resolves, since the scanner is built
+    // around a target; the rest are read while scanning
+    let mut stdin_targets = None;
+    let mut first_stdin_hosts = VecDeque::new();
+    if args.target == target_stream::STDIN_TARGET {
+        info!("Reading targets from stdin");
+        let mut lines = target_stream::spawn_stdin_reader();
+        while first_stdin_hosts.is_empty() {
+            let Some(line) = lines.recv().await else {
+                error!("No usable targets on stdin");
+                process::exit(1);
+            };
+            match target_stream::expand(&line, args.ipv6, MAX_NETWORK_HOSTS).await {
+                Ok(hosts) => first_stdin_hosts.extend(hosts),
+                Err(e) => warn!("[Stdin] Skipping {}: {}", line, e),
+            }
+        }
+        stdin_targets = Some(lines);
+    }
+    let first_target = first_stdin_hosts.front().map(|(name, _)| name.clone())
+        .or_else(|| network_hosts.first().map(|ip| ip.to_string()))
+        .unwrap_or_else(|| args.target.clone());
+    if network_hosts.len() > 1 {
+        info!("Target {} expands to {} hosts; scanning {}-{} hosts in parallel with concurrency {} each",
+              args.target, network_hosts.len(), args.min_hostgroup, args.max_hostgroup, args.concurrency);
+    }
+
+    let udp_selected = scan_types.contains(&ScanType::Udp);
+
     // Create scanner instance with all parameters
     let mut scanner = QuantumScanner::new(
-        &args.target,
+        &first_target,
         ports_to_scan,
         scan_types,
         local_ip_v4, // Pass the detected local IPv4 address
         args.concurrency,
         args.rate,
-        args.evasion,
+        evasion_enabled,
         args.verbose,
         args.debug,
         args.ipv6,
@@ -1265,6 +2409,62 @@ async fn main() -> Result<(), anyhow::Error> {
         );
     }
 
+    scanner.set_evasion_pipeline(evasion_pipeline);
+
+    scanner.set_fragment_policy(evasion::FragmentPolicy { order: args.frag_order, overlap: args.frag_overlap });
+
+    if let Some(model) = args.timing_model {
+        scanner.set_timing_model(model);
+    }
+
+    if args.adaptive_timeout {
+        scanner.set_adaptive_timeout(true);
+    }
+    if args.auto_technique {
+        scanner.set_auto_technique(true);
+    }
+    if args.safe {
+        scanner.set_safe_mode(true);
+        banner::set_read_only(true);
+    }
+    scanner.set_banner_concurrency(args.banner_concurrency);
+    if let Some(limit) = args.capture_responses {
+        if limit == 0 || limit > banner::MAX_CAPTURE_BYTES {
+            error!("--capture-responses must be between 1 and {} bytes", banner::MAX_CAPTURE_BYTES);
+            process::exit(1);
+        }
+        if !service_scan_mode {
+            warn!("--capture-responses only captures replies to banner grabs; enable -sV or --two-phase");
+        }
+        scanner.set_capture_responses(limit);
+    }
+
+    if let Some(segment_size) = args.tcp_segment {
+        if segment_size == 0 {
+            return Err(anyhow!("--tcp-segment must be at least 1 byte"));
+        }
+        let (min_ms, max_ms) = match args.tcp_segment_delay.split_once('-') {
+            Some((min, max)) => (min.trim().parse::<u64>(), max.trim().parse::<u64>()),
+            None => (args.tcp_segment_delay.trim().parse::<u64>(), args.tcp_segment_delay.trim().parse::<u64>()),
+        };
+        let (min_ms, max_ms) = match (min_ms, max_ms) {
+            (Ok(min), Ok(max)) if min <= max => (min, max),
+            _ => return Err(anyhow!("Invalid --tcp-segment-delay '{}': expected <min>-<max> in ms", args.tcp_segment_delay)),
+        };
+        scanner.set_tcp_segmentation(evasion::SegmentationPolicy {
+            segment_size,
+            min_delay: Duration::from_millis(min_ms),
+            max_delay: Duration::from_millis(max_ms),
+        });
+    }
+
+    if let Some(ratio) = args.cover_traffic {
+        if ratio <= 0.0 || !ratio.is_finite() {
+            return Err(anyhow!("--cover-traffic must be a positive number of requests per probe"));
+        }
+        scanner.set_cover_traffic(cover::CoverTrafficConfig { ratio, dns_share: args.cover_dns_share });
+    }
+
     // Set protocol variant if provided
     if let Some(variant) = &args.protocol_variant {
         scanner.set_protocol_variant(Some(variant));
@@ -1276,6 +2476,147 @@ async fn main() -> Result<(), anyhow::Error> {
         scanner.set_memory_log(buffer.clone()); 
     }
 
+    // Set source MAC spoofing if requested
+    if let Some(spoof_mac) = &args.spoof_mac {
+        if let Err(e) = scanner.set_spoof_mac(spoof_mac) {
+            error!("Cannot spoof the source MAC: {}", e);
+            process::exit(1);
+        }
+    }
+
+    // Split T:/U: port specifications so each protocol only probes its own ports
+    if !args.top_100 && !args.top_10 && args.top_ports.is_none() {
+        if let Ok(Some(protocol_ports)) = ProtocolPorts::parse(&args.ports) {
+            scanner.set_protocol_ports(protocol_ports);
+        }
+    }
+
+    // Enable ICMP host characterization
+    if args.icmp_probes {
+        scanner.set_icmp_probes(true);
+    }
+
+    // Set the IP protocols for the ipproto scan type
+    if let Some(spec) = &args.ip_protocols {
+        match parse_ip_protocols(spec) {
+            Ok(protocols) => scanner.set_ip_protocols(protocols),
+            Err(e) => {
+                error!("Invalid --ip-protocols specification '{}': {}", spec, e);
+                process::exit(1);
+            }
+        }
+    }
+
+    // Custom UDP probe payload
+    let udp_payload = match (&args.udp_payload, &args.udp_payload_file) {
+        (Some(hex), _) => Some(techniques::parse_hex_payload(hex)
+            .map_err(|e| anyhow!("Invalid --udp-payload: {}", e))?),
+        (None, Some(path)) => Some(std::fs::read(path)
+            .map_err(|e| anyhow!("Failed to read --udp-payload-file {}: {}", path.display(), e))?),
+        (None, None) => None,
+    };
+    match (udp_payload, &args.udp_payload_ports) {
+        (Some(payload), ports) => {
+            let ports = match ports {
+                Some(spec) => match PortRanges::parse(spec) {
+                    Ok(ranges) => Some(PortRanges::new(ranges).into_iter().collect::<Vec<u16>>()),
+                    Err(e) => {
+                        error!("Invalid --udp-payload-ports specification '{}': {}", spec, e);
+                        process::exit(1);
+                    }
+                },
+                None => None,
+            };
+            if !udp_selected {
+                warn!("--udp-payload is only used by the udp scan type, which is not selected");
+            }
+            info!("Using a {}-byte UDP payload on {}", payload.len(),
+                  ports.as_ref().map_or("all UDP ports".to_string(), |p| format!("{} ports", p.len())));
+            techniques::set_udp_payload(payload, ports);
+        }
+        (None, Some(_)) => {
+            error!("--udp-payload-ports needs --udp-payload or --udp-payload-file");
+            process::exit(1);
+        }
+        (None, None) => {}
+    }
+
+    // Enable adaptive port expansion
+    if args.adaptive_ports {
+        scanner.set_adaptive_ports(true);
+    }
+
+    // Enable two-phase discovery/enumeration
+    if args.two_phase {
+        scanner.set_two_phase(true);
+    }
+
+    // Enable stateless SYN cookie discovery
+    if args.stateless {
+        scanner.set_stateless(true);
+    }
+
+    // Probe tagging for reply correlation, optionally shared with `quantum_scanner collect`
+    match &args.correlation_key {
+        Some(hex) => correlate::set_probe_key(correlate::ProbeKey::from_hex(hex)?),
+        None => debug!("Correlation key for this run: {}", correlate::probe_key().to_hex()),
+    }
+    if let Some(iface) = &args.response_iface {
+        scanner.set_response_iface(iface);
+    }
+
+    // Set up the AF_PACKET fast path after MAC spoofing so it picks up the spoofed address.
+    // Windows has no raw TCP sockets, so raw scans there always go through the Npcap engine
+    if args.fast_io || (cfg!(windows) && needs_raw_sockets) {
+        scanner.set_fast_io(true);
+    }
+
+    // Route banner grabs through an ICMP covert channel relay
+    if let Some(relay) = args.icmp_relay {
+        match tunnel::Obfuscation::from_spec(&args.tunnel_obfuscation, args.tunnel_key.as_deref()) {
+            Ok(obfuscation) => {
+                let config = tunnel::CovertChannelConfig { obfuscation, ..Default::default() };
+                scanner.set_icmp_relay(relay, config);
+            }
+            Err(e) => {
+                error!("Invalid ICMP relay configuration: {}", e);
+                process::exit(1);
+            }
+        }
+    }
+
+    // Route HTTP tunnel scans and banner grabs through an HTTP(S) relay
+    if let Some(url) = &args.http_relay {
+        match tunnel::HttpTunnelConfig::from_url(url) {
+            Ok(mut config) => {
+                config.host_header = args.http_host_header.clone();
+                config.sni = args.http_sni.clone();
+                config.auth_token = args.http_relay_token.clone();
+                config.verify_tls = !args.http_relay_insecure;
+                scanner.set_http_relay(config);
+            }
+            Err(e) => {
+                error!("Invalid HTTP relay configuration: {}", e);
+                process::exit(1);
+            }
+        }
+    }
+
+    // Route QUIC tunnel scans and banner grabs through a QUIC relay
+    if let Some(spec) = &args.quic_relay {
+        match tunnel::QuicTunnelConfig::parse(spec, args.quic_sni.as_deref()) {
+            Ok(mut config) => {
+                config.auth_token = args.http_relay_token.clone();
+                config.reuse_connection = !args.quic_fresh_connections;
+                scanner.set_quic_relay(config);
+            }
+            Err(e) => {
+                error!("Invalid QUIC relay configuration: {}", e);
+                process::exit(1);
+            }
+        }
+    }
+
     // Set DNS tunneling options if enabled
     if args.dns_tunnel {
         let server_ip = match args.dns_server {
@@ -1289,86 +2630,232 @@ async fn main() -> Result<(), anyhow::Error> {
             None => None,
         };
         scanner.set_dns_tunnel_options(server_ip, args.lookup_domain.as_deref());
+        scanner.set_dns_tunnel_transport(tunnel::DnsTunnelOptions {
+            record_type: args.dns_record_type,
+            encoding: args.dns_encoding,
+            edns0: !args.no_edns0,
+            probe_mtu: !args.no_dns_mtu_probe,
+        });
     }
 
-    // --- Run Scan --- 
-    info!("Starting scan execution...");
-    let scan_result = match scanner.run_scan().await {
-        Ok(result) => {
-            info!("Scan completed successfully.");
-            result
+    // Captive portals and intercepting resolvers corrupt hostname targets and DNS tunnel
+    // results without any error, so check the resolver path when the scan relies on it
+    let target_is_hostname = args.target.parse::<IpAddr>().is_err() && !args.target.contains('/')
+        && args.target != target_stream::STDIN_TARGET;
+    if !args.no_dns_sanity && (uses_dns_tunnel || target_is_hostname) {
+        let resolver = args.dns_server.as_deref()
+            .and_then(|s| s.parse::<IpAddr>().ok())
+            .or_else(tunnel::system_resolver);
+        let tunnel_domain = args.lookup_domain.clone().unwrap_or_else(|| tunnel::DEFAULT_TUNNEL_DOMAIN.to_string());
+        let target_parent = args.target.split_once('.')
+            .map(|(_, parent)| parent.to_string())
+            .filter(|parent| target_is_hostname && parent.contains('.'));
+
+        let mut domains: Vec<String> = target_parent.iter().cloned().collect();
+        if uses_dns_tunnel {
+            domains.push(tunnel_domain.clone());
         }
-        Err(e) => {
-            error!("Scan failed: {}", e);
-            // Attempt cleanup before exiting
-            if let Err(cleanup_err) = cleanup_ramdisk(&ramdisk_path) {
-                 warn!("Error during RAM disk cleanup on scan failure: {}", cleanup_err);
-            }
+        let mut report = dns_sanity::check_resolver(resolver, &domains, Duration::from_secs_f64(args.timeout)).await;
+
+        let target_ip = scanner.target_ip();
+        if target_is_hostname && report.nxdomain_rewrite.contains(&target_ip) {
+            error!("{} resolved to {}, the address this resolver returns for names that do not exist. The target most likely does not exist; use its IP address instead", args.target, target_ip);
             process::exit(1);
         }
-    };
+        if let Some(addrs) = target_parent.as_ref().and_then(|parent| report.wildcard_domains.get(parent)) {
+            if addrs.contains(&target_ip) {
+                report.warnings.push(format!("{} resolves to the same address as the *.{} wildcard ({}); the name may have no record of its own", args.target, target_parent.as_deref().unwrap_or_default(), target_ip));
+            }
+        }
+        if uses_dns_tunnel {
+            if report.intercepted {
+                report.warnings.push("DNS traffic is intercepted on this network: queries do not reach the chosen resolver, and tunnel answers without a tunnel server payload are reported as unknown".to_string());
+            }
+            if !report.nxdomain_rewrite.is_empty() {
+                report.warnings.push(format!("Resolver rewrites NXDOMAIN to {:?}: tunnel answers without a tunnel server payload are reported as unknown", report.nxdomain_rewrite));
+            }
+            if let Some(addrs) = report.wildcard_domains.get(&tunnel_domain.trim_matches('.').to_ascii_lowercase()) {
+                if !report.nxdomain_rewrite.is_empty() && addrs.iter().all(|ip| report.nxdomain_rewrite.contains(ip)) {
+                    report.warnings.push(format!("Queries under {} are answered by the resolver's NXDOMAIN rewrite; they may never reach the tunnel server", tunnel_domain));
+                }
+            }
+        }
+        for warning in &report.warnings {
+            warn!("[DNS] {}", warning);
+        }
+        dns_sanity::set_report(Some(report));
+    }
 
-    // --- Output Results --- 
-    info!("Processing and outputting results...");
-    // Always generate structured output (e.g., JSON), then decide how to print/save
-    
-    // Simplify the JSON serialization logic
-    let json_output = if args.pretty_json {
-        serde_json::to_string_pretty(&scan_result)
+    // Stdin hosts are counted as they arrive
+    if stdin_targets.is_none() {
+        progress::add_hosts(network_hosts.len().max(1));
+    }
+    let progress_reporter = if args.progress_json {
+        Some(progress::start(progress::ProgressFormat::Json, Duration::from_secs(args.progress_interval.max(1))))
+    } else if args.progress {
+        Some(progress::start(progress::ProgressFormat::Text, Duration::from_secs(args.progress_interval.max(1))))
     } else {
-        serde_json::to_string(&scan_result)
+        None
     };
-    
-    // Handle JSON output to file or console
-    if args.json {
-        match json_output {
-            Ok(json) => {
-                if let Some(output_path) = &args.output {
-                    // Save to file
-                    if let Err(e) = std::fs::write(output_path, json) {
-                        error!("Failed to write JSON output to file: {}", e);
-                    } else {
-                        info!("Results saved to JSON file: {}", output_path.display());
-                    }
-                } else {
-                    // Print to console
-                    println!("{}", json);
+
+    // --- Detection test ---
+    // One scan per technique replaces the normal run, and the stage report is its output
+    if let Some(stages) = detection_stages {
+        info!("Starting detection test: {} stages, {}s apart", stages.len(), args.detection_test_gap);
+        let report = detection_test::run(&mut scanner, stages, local_ip_v4.map(IpAddr::V4), Duration::from_secs(args.detection_test_gap)).await;
+        if let Some(reporter) = progress_reporter {
+            progress::finish(reporter);
+        }
+        if args.json {
+            output::write_report("Detection test report", args.output.as_deref(),
+                |path| encryption::write_output(path, serde_json::to_string_pretty(&report)?.as_bytes()),
+                || Ok(serde_json::to_string_pretty(&report)?));
+        } else {
+            output::write_report("Detection test report", args.output.as_deref(),
+                |path| encryption::write_output(path, report.to_string().as_bytes()),
+                || Ok(report.to_string()));
+        }
+        if let Err(e) = cleanup_ramdisk(&ramdisk_path) {
+            warn!("Error during RAM disk cleanup: {}", e);
+        }
+        audit::finish();
+        logging::close_file_sink();
+        return Ok(());
+    }
+
+    // --- Run Scan --- 
+    info!("Starting scan execution...");
+    let mut all_results = if let Some(lines) = stdin_targets {
+        let results = scanner.run_stream(first_stdin_hosts, lines, args.max_hostgroup, MAX_NETWORK_HOSTS).await;
+        info!("Scanned {} hosts from stdin successfully.", results.len());
+        results
+    } else if network_hosts.len() > 1 {
+        let results = scanner.run_hostgroups(&network_hosts, args.min_hostgroup, args.max_hostgroup).await;
+        info!("Scanned {} of {} hosts successfully.", results.len(), network_hosts.len());
+        results
+    } else {
+        match scanner.run_scan().await {
+            Ok(result) => {
+                info!("Scan completed successfully.");
+                vec![result]
+            }
+            Err(e) => {
+                error!("Scan failed: {}", e);
+                audit::finish();
+                // Attempt cleanup before exiting
+                if let Err(cleanup_err) = cleanup_ramdisk(&ramdisk_path) {
+                     warn!("Error during RAM disk cleanup on scan failure: {}", cleanup_err);
                 }
+                process::exit(1);
             }
-            Err(e) => error!("Failed to serialize scan results to JSON: {}", e)
         }
-    } else {
-        // Text format - either save to file or print to console
-        if let Some(output_path) = &args.output {
-            if let Err(e) = output::save_text_results(&scan_result, output_path) {
-                error!("Failed to save results to file: {}", e);
-            } else {
-                info!("Results saved to file: {}", output_path.display());
+    };
+
+    // --- Certificate-based target expansion ---
+    // Hostnames from collected certificates that resolve into scope are scanned with the
+    // same settings; their certificates can add further targets
+    if args.expand_from_certs {
+        let scope = cert_expand::parse_scope(&args.scope)?;
+        let mut known: HashSet<IpAddr> = all_results.iter().filter_map(|r| r.target_ip.parse().ok()).collect();
+        let mut queue: VecDeque<cert_expand::ExpandedTarget> = VecDeque::new();
+        for result in &all_results {
+            let found = cert_expand::expansion_targets(result, &scope, &known, args.ipv6).await;
+            known.extend(found.iter().map(|t| t.ip));
+            queue.extend(found);
+        }
+        progress::add_hosts(queue.len().min(args.expand_max));
+
+        let mut expanded = 0;
+        while let Some(next) = queue.pop_front() {
+            if expanded >= args.expand_max {
+                warn!("[Expand] Reached --expand-max ({}); {} discovered targets not scanned", args.expand_max, queue.len() + 1);
+                break;
             }
-        } else {
-            // Print results to console - pass the verbose flag from args
-            if let Err(e) = output::print_results(&scan_result, args.verbose) {
-                error!("Failed to print results: {}", e);
+            expanded += 1;
+            info!("[Expand] Scanning {} ({}), named by the certificate on {}", next.hostname, next.ip, next.source);
+            scanner.retarget(&next.hostname, next.ip);
+            match scanner.run_scan().await {
+                Ok(result) => {
+                    let more = cert_expand::expansion_targets(&result, &scope, &known, args.ipv6).await;
+                    known.extend(more.iter().map(|t| t.ip));
+                    let budget = args.expand_max.saturating_sub(expanded + queue.len());
+                    progress::add_hosts(more.len().min(budget));
+                    queue.extend(more);
+                    all_results.push(result);
+                }
+                Err(e) => warn!("[Expand] Scan of {} failed: {}", next.hostname, e),
             }
         }
     }
 
+    // --- Origin discovery ---
+    // Every host is scanned by now, so directly reached hosts can be matched against the edges
+    if args.find_origin {
+        let scope = cert_expand::parse_scope(&args.scope)?;
+        let found = origin_hunt::hunt(&mut all_results, dns_history.as_ref(), &scope).await;
+        info!("[Origin] {} origin candidates found", found);
+    }
+
+    // Dropping the fast I/O engine stops its receive and flush threads
+    fast_io::set_engine(None);
+
+    if let Some(reporter) = progress_reporter {
+        progress::finish(reporter);
+    }
+
+    // --- Output Results --- 
+    info!("Processing and outputting results...");
+    baseline::find_outliers(&mut all_results);
+    let deviations = expected_inventory.as_ref().map_or(0, |inventory| inventory.compare(&mut all_results));
+    output_results(&all_results, &args);
+
     // --- Cleanup --- 
+    // In memory-only mode nothing else holds the results, so wipe banners before they are freed
+    if args.memory_only {
+        all_results.iter_mut().for_each(utils::wipe_results);
+        if let Some(buffer) = &memory_log_buffer {
+            buffer.lock().clear();
+        }
+    }
     info!("Starting cleanup phase...");
+    // Unmounting frees the RAM disk's pages without clearing them, so wipe its files first
+    if let (true, Some(mount_point)) = (args.burn_after_run, &ramdisk_path) {
+        if let Err(e) = secure_delete_dir(mount_point, args.delete_passes) {
+            warn!("Error during secure delete of RAM disk contents: {}", e);
+        }
+    }
     // Unmount RAM disk if created
     if let Err(e) = cleanup_ramdisk(&ramdisk_path) {
         warn!("Error during RAM disk cleanup: {}", e);
         // Don't exit, just warn
     }
 
+    match args.max_bandwidth {
+        Some(limit) => info!("Estimated traffic sent: {} bytes (budget {})", scheduler::bytes_sent(), limit),
+        None => info!("Estimated traffic sent: {} bytes", scheduler::bytes_sent()),
+    }
+    audit::finish();
+    info!("Quantum Scanner finished.");
+    logging::close_file_sink();
+
     // Securely delete log file if requested and not in memory-only mode
-    if args.secure_delete && !args.memory_only {
+    if (args.secure_delete || args.burn_after_run) && !args.memory_only {
         info!("Attempting secure delete for log file: {}", args.log_file.display());
         if let Err(e) = secure_delete_file(&args.log_file, args.delete_passes) {
             warn!("Error during secure delete of log file: {}", e);
         }
     }
 
-    info!("Quantum Scanner finished.");
+    // Last step: the executable goes once this process has exited
+    if args.burn_after_run {
+        if let Err(e) = schedule_self_delete(args.delete_passes) {
+            warn!("Could not schedule deletion of the executable: {}", e);
+        }
+    }
+
+    if deviations > 0 {
+        warn!("Results deviate from the baseline in {} places", deviations);
+        process::exit(inventory::DEVIATION_EXIT_CODE);
+    }
     Ok(())
 }
@@ -0,0 +1,320 @@
+//! `quantum_scanner merge`: combine saved results from several runs into one dataset.
+//!
+//! Distributed workers, rescans from other vantage points and follow-up runs each
+//! leave their own JSON file. Merging groups hosts by address and, where runs
+//! disagree about a port, keeps one answer:
+//! - Results marked `unreliable` (inline interference) lose to clean ones
+//! - Then the newest run wins, or with `--prefer confidence` the highest consensus
+//!   confidence, ties going to the newest run
+//! - Service, version, banner and certificate details missing from the kept result
+//!   are filled in from runs that saw the same state, and findings are united
+//! - Each disagreement is recorded as an anomaly on the port naming the runs and what
+//!   they saw, so the conflict stays visible in every report
+
+use std::collections::{BTreeMap, BTreeSet, HashMap};
+use std::path::PathBuf;
+
+use anyhow::{bail, Result};
+use clap::{Parser, ValueEnum};
+use log::info;
+
+use crate::models::{Finding, PortResult, PortStatus, ScanResults, ScanType, SCHEMA_VERSION};
+use crate::output::{OutputFormat, ReportOptions};
+
+/// How conflicting port results are resolved
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum)]
+pub enum Prefer {
+    /// The most recent run's result
+    Recent,
+    /// The result with the highest consensus confidence
+    Confidence,
+}
+
+/// Arguments for `quantum_scanner merge`
+#[derive(Parser, Debug)]
+#[clap(
+    name = "merge",
+    about = "Merge saved JSON results from several runs into one dataset",
+    after_help = "EXAMPLES:
+    quantum_scanner merge worker1.json worker2.json worker3.json -o combined.json
+    quantum_scanner merge monday.json friday.json --prefer confidence --pretty
+    quantum_scanner merge *.json -f markdown -o findings.md"
+)]
+pub struct MergeArgs {
+    /// JSON results written with -j/--json (one host or an array of hosts per file)
+    #[clap(required = true, num_args = 2..)]
+    pub inputs: Vec<PathBuf>,
+
+    /// Which result to keep when runs disagree about a port
+    #[clap(long, value_enum, default_value = "recent")]
+    pub prefer: Prefer,
+
+    /// Format of the merged output; json produces a dataset for `report`, `query` or another merge
+    #[clap(short, long, value_enum, default_value = "json")]
+    pub format: OutputFormat,
+
+    /// Where to write the merged results (default: stdout)
+    #[clap(short, long)]
+    pub output: Option<PathBuf>,
+
+    /// Pretty-print JSON output
+    #[clap(long)]
+    pub pretty: bool,
+
+    /// Passphrase of inputs written with --encrypt-output, or env:NAME / file:PATH to read it from (prompted for per file when omitted)
+    #[clap(short, long)]
+    pub passphrase: Option<String>,
+}
+
+/// Label identifying the run a host record came from
+fn run_label(host: &ScanResults) -> String {
+    host.metadata.as_ref()
+        .map(|m| m.run_id.clone())
+        .unwrap_or_else(|| host.end_time.to_rfc3339())
+}
+
+fn confidence(result: &PortResult) -> f64 {
+    result.consensus.as_ref().map_or(0.0, |c| c.confidence)
+}
+
+/// Fill details the kept result lacks from another run that saw the same state
+fn fill_gaps(result: &mut PortResult, other: &PortResult) {
+    if result.service.is_none() {
+        result.service = other.service.clone();
+    }
+    if result.version.is_none() {
+        result.version = other.version.clone();
+    }
+    if result.banner.is_none() {
+        result.banner = other.banner.clone();
+    }
+    if result.cert_info.is_none() {
+        result.cert_info = other.cert_info.clone();
+    }
+    if result.http_info.is_none() {
+        result.http_info = other.http_info.clone();
+    }
+    if result.os_guess.is_none() {
+        result.os_guess = other.os_guess.clone();
+    }
+    for vuln in &other.vulns {
+        if !result.vulns.iter().any(|v| v.id == vuln.id) {
+            result.vulns.push(vuln.clone());
+        }
+    }
+}
+
+/// Pick one result for a port out of every run that probed it
+///
+/// # Arguments
+/// * `candidates` - (run index, result) pairs, oldest run first
+/// * `labels` - Run labels by index
+/// * `prefer` - Conflict resolution rule
+fn merge_port(candidates: &[(usize, &PortResult)], labels: &[String], prefer: Prefer) -> PortResult {
+    let reliable = |r: &PortResult| r.unreliable.is_none();
+    let &(kept, winner) = match prefer {
+        Prefer::Recent => candidates.iter()
+            .max_by_key(|(i, r)| (reliable(r), *i)),
+        Prefer::Confidence => candidates.iter()
+            .max_by(|(i, a), (j, b)| reliable(a).cmp(&reliable(b))
+                .then(confidence(a).total_cmp(&confidence(b)))
+                .then(i.cmp(j))),
+    }.expect("every merged port has at least one candidate");
+
+    let mut result = winner.clone();
+    for &(i, other) in candidates.iter().rev() {
+        if i != kept && other.final_status == result.final_status {
+            fill_gaps(&mut result, other);
+        }
+    }
+
+    let dissent: Vec<String> = candidates.iter()
+        .filter(|(_, other)| other.final_status != result.final_status)
+        .map(|(i, other)| format!("{} saw {}", labels[*i], other.final_status))
+        .collect();
+    if !dissent.is_empty() {
+        result.anomalies.push(Finding::anomaly(format!(
+            "Runs disagree on this port ({}); kept {} from {}",
+            dissent.join(", "), result.final_status, labels[kept])));
+    }
+    result
+}
+
+/// Merge every record of one host, oldest run first
+fn merge_host(mut runs: Vec<ScanResults>, prefer: Prefer) -> ScanResults {
+    if runs.len() == 1 {
+        return runs.remove(0);
+    }
+    let labels: Vec<String> = runs.iter().map(run_label).collect();
+
+    let ports: BTreeSet<u16> = runs.iter().flat_map(|r| r.results.keys().copied()).collect();
+    let mut results = HashMap::new();
+    let mut conflicts = 0;
+    for port in ports {
+        let candidates: Vec<(usize, &PortResult)> = runs.iter().enumerate()
+            .filter_map(|(i, r)| r.results.get(&port).map(|p| (i, p)))
+            .collect();
+        let result = merge_port(&candidates, &labels, prefer);
+        if candidates.iter().any(|(_, other)| other.final_status != result.final_status) {
+            conflicts += 1;
+        }
+        results.insert(port, result);
+    }
+
+    let start_time = runs.iter().map(|r| r.start_time).min().unwrap_or(runs[0].start_time);
+    let packets_sent = runs.iter().map(|r| r.packets_sent).sum();
+    let bytes_sent = runs.iter().map(|r| r.bytes_sent).sum();
+    let successful_scans = runs.iter().map(|r| r.successful_scans).sum();
+    let mut scan_types = Vec::new();
+    for scan_type in runs.iter().flat_map(|r| r.scan_types.iter()) {
+        if !scan_types.contains(scan_type) {
+            scan_types.push(*scan_type);
+        }
+    }
+    // A phase counts as skipped only if no run got to it
+    let skipped_phases = runs[0].skipped_phases.iter()
+        .filter(|phase| runs.iter().all(|r| r.skipped_phases.contains(phase)))
+        .cloned()
+        .collect();
+    // A probe counts as skipped only if no run sent it
+    let mut skipped_probes: HashMap<u16, Vec<ScanType>> = HashMap::new();
+    for (port, probes) in runs.iter().flat_map(|r| r.skipped_probes.iter()) {
+        let sent = |probe: &ScanType| runs.iter()
+            .any(|r| r.results.get(port).is_some_and(|p| p.tcp_states.contains_key(probe)));
+        for probe in probes {
+            if !sent(probe) {
+                let entry = skipped_probes.entry(*port).or_default();
+                if !entry.contains(probe) {
+                    entry.push(*probe);
+                }
+            }
+        }
+    }
+    let mut ip_protocols = HashMap::new();
+    let mut service_categories: Option<HashMap<String, Vec<u16>>> = None;
+    let mut interference = Vec::new();
+    for run in &runs {
+        ip_protocols.extend(run.ip_protocols.clone());
+        for (category, ports) in run.service_categories.iter().flatten() {
+            let entry = service_categories.get_or_insert_with(HashMap::new).entry(category.clone()).or_default();
+            for port in ports {
+                if !entry.contains(port) {
+                    entry.push(*port);
+                }
+            }
+        }
+        interference.extend(run.interference.iter().cloned());
+    }
+    if conflicts > 0 {
+        info!("[Merge] {}: {} port(s) differ between runs", runs[0].target_ip, conflicts);
+    }
+
+    // Newer runs supply host-level details, falling back to older ones where a run lacks them
+    macro_rules! newest {
+        ($field:ident) => {
+            runs.iter().rev().find_map(|r| r.$field.clone())
+        };
+    }
+    let os_summary = newest!(os_summary);
+    let risk_assessment = newest!(risk_assessment);
+    let mac_address = newest!(mac_address);
+    let mac_vendor = newest!(mac_vendor);
+    let icmp_info = newest!(icmp_info);
+    let host_timing = newest!(host_timing);
+    let technique_selection = newest!(technique_selection);
+    let dns_sanity = newest!(dns_sanity);
+    let asset = newest!(asset);
+    let edge = newest!(edge);
+    let cloud = newest!(cloud);
+
+    let newest = runs.pop().expect("more than one run");
+    let open_ports = results.iter()
+        .filter(|(_, r)| matches!(r.final_status, PortStatus::Open | PortStatus::OpenFiltered))
+        .map(|(port, _)| *port)
+        .collect();
+    ScanResults {
+        schema_version: SCHEMA_VERSION,
+        target: newest.target,
+        target_ip: newest.target_ip,
+        open_ports,
+        results,
+        start_time,
+        end_time: newest.end_time,
+        scan_types,
+        packets_sent,
+        bytes_sent,
+        successful_scans,
+        os_summary,
+        risk_assessment,
+        service_categories,
+        mac_address,
+        mac_vendor,
+        ip_protocols,
+        icmp_info,
+        host_timing,
+        technique_selection,
+        dns_sanity,
+        metadata: newest.metadata,
+        skipped_probes,
+        skipped_phases,
+        asset,
+        // Recomputed over the merged population by the caller
+        outliers: Vec::new(),
+        // Compared against one run's ports; rerun with --baseline to check the merged state
+        deviations: Vec::new(),
+        edge,
+        cloud,
+        interference,
+        merged_runs: labels,
+    }
+}
+
+/// Merge results from several runs, one record per host
+///
+/// # Arguments
+/// * `hosts` - Host records from every input, in any order
+/// * `prefer` - Which port result to keep when runs disagree
+///
+/// # Returns
+/// * `Vec<ScanResults>` - One record per target address, ordered by address
+pub fn merge_results(hosts: Vec<ScanResults>, prefer: Prefer) -> Vec<ScanResults> {
+    let mut by_host: BTreeMap<String, Vec<ScanResults>> = BTreeMap::new();
+    for host in hosts {
+        by_host.entry(host.target_ip.clone()).or_default().push(host);
+    }
+    let mut merged: Vec<ScanResults> = by_host.into_values()
+        .map(|mut runs| {
+            runs.sort_by_key(|r| r.end_time);
+            merge_host(runs, prefer)
+        })
+        .collect();
+    crate::baseline::find_outliers(&mut merged);
+    merged
+}
+
+/// Run the `merge` subcommand
+pub fn run_merge(args: MergeArgs) -> Result<()> {
+    let mut hosts = Vec::new();
+    for input in &args.inputs {
+        let results = crate::report::read_results(input, args.passphrase.as_deref())?;
+        info!("[Merge] {}: {} host(s)", input.display(), results.len());
+        hosts.extend(results);
+    }
+    if hosts.is_empty() {
+        bail!("The inputs contain no hosts");
+    }
+    let total = hosts.len();
+    let merged = merge_results(hosts, args.prefer);
+    info!("[Merge] {} host record(s) merged into {} host(s)", total, merged.len());
+
+    crate::output::write_results(&merged, &ReportOptions {
+        format: Some(args.format),
+        json: false,
+        pretty_json: args.pretty,
+        template: None,
+        output: args.output.as_deref(),
+        verbose: false,
+        quiet: false,
+    });
+    Ok(())
+}
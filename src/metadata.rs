@@ -0,0 +1,94 @@
+//! Run metadata for traceability.
+//!
+//! Every invocation gets a random run ID, and records the tool version, the start time,
+//! the command line (secret values removed) and a hash of the effective configuration.
+//! The metadata is attached to every `ScanResults` the run produces, so a report can be
+//! tied back to exactly how it was produced. Two runs with the same settings, including
+//! defaults, share a config hash even when the flags were spelled differently.
+
+use chrono::Utc;
+use lazy_static::lazy_static;
+use parking_lot::RwLock;
+use rand::{thread_rng, Rng};
+use regex::Regex;
+use sha2::{Digest, Sha256};
+
+use crate::models::ScanMetadata;
+
+/// Options whose values are secrets and never recorded
+const SECRET_FLAGS: &[&str] = &["--tunnel-key", "--log-password", "--encrypt-output", "--redact-key"];
+
+lazy_static! {
+    /// Metadata for this run, set once at startup
+    static ref METADATA: RwLock<Option<ScanMetadata>> = RwLock::new(None);
+    /// `field: Some("value")` for secret fields in the Debug form of the arguments
+    static ref SECRET_FIELDS: Regex = Regex::new(r#"(tunnel_key|_log_password|log_password|encrypt_output|redact_key): Some\("(?:[^"\\]|\\.)*"\)"#).unwrap();
+}
+
+/// Random RFC 4122 version 4 UUID
+pub fn generate_run_id() -> String {
+    let mut bytes: [u8; 16] = thread_rng().gen();
+    bytes[6] = (bytes[6] & 0x0f) | 0x40;
+    bytes[8] = (bytes[8] & 0x3f) | 0x80;
+    let hex: String = bytes.iter().map(|b| format!("{:02x}", b)).collect();
+    format!("{}-{}-{}-{}-{}", &hex[0..8], &hex[8..12], &hex[12..16], &hex[16..20], &hex[20..32])
+}
+
+/// Command line with the values of secret options replaced
+fn redact_command_line(argv: &[String]) -> Vec<String> {
+    let mut redacted = Vec::with_capacity(argv.len());
+    let mut hide_next = false;
+    for arg in argv {
+        if hide_next {
+            redacted.push("[REDACTED]".to_string());
+            hide_next = false;
+            continue;
+        }
+        match SECRET_FLAGS.iter().find(|flag| arg.starts_with(*flag)) {
+            Some(flag) if arg.len() > flag.len() && arg[flag.len()..].starts_with('=') => {
+                redacted.push(format!("{}=[REDACTED]", flag));
+            }
+            Some(flag) if arg.len() == flag.len() => {
+                redacted.push(arg.clone());
+                hide_next = true;
+            }
+            _ => redacted.push(arg.clone()),
+        }
+    }
+    redacted
+}
+
+/// Build the metadata for this run
+///
+/// # Arguments
+/// * `argv` - Process arguments, program name first
+/// * `effective_config` - Debug rendering of the parsed arguments, defaults included
+///
+/// # Returns
+/// * `ScanMetadata` - Metadata with a fresh run ID
+pub fn capture(argv: &[String], effective_config: &str) -> ScanMetadata {
+    let canonical = SECRET_FIELDS.replace_all(effective_config, "$1: [REDACTED]");
+    let mut hasher = Sha256::new();
+    hasher.update(env!("CARGO_PKG_VERSION").as_bytes());
+    hasher.update([0u8]);
+    hasher.update(canonical.as_bytes());
+
+    ScanMetadata {
+        run_id: generate_run_id(),
+        tool_name: env!("CARGO_PKG_NAME").to_string(),
+        tool_version: env!("CARGO_PKG_VERSION").to_string(),
+        started_at: Utc::now(),
+        command_line: redact_command_line(argv),
+        config_hash: format!("{:x}", hasher.finalize()),
+    }
+}
+
+/// Record the metadata attached to every result of this run
+pub fn set_metadata(metadata: ScanMetadata) {
+    *METADATA.write() = Some(metadata);
+}
+
+/// Metadata for this run, if recorded
+pub fn current() -> Option<ScanMetadata> {
+    METADATA.read().clone()
+}
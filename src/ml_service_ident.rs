@@ -25,27 +25,271 @@ pub trait ServiceIdentification {
         immediate_close: bool,
         server_initiated: bool
     ) -> Option<(String, Option<String>)>;
+
+    /// Most likely services with their confidence, best first
+    /// 
+    /// Confidences are shares of the classifier's total score (summing to at most 1.0
+    /// over all services). Identifiers without a probabilistic model return nothing.
+    /// 
+    /// # Parameters
+    /// Same as `identify_service`, plus `limit`, the maximum number of candidates
+    fn candidates(
+        &self,
+        _data: &[u8],
+        _port: u16,
+        _response_time_ms: f32,
+        _immediate_close: bool,
+        _server_initiated: bool,
+        _limit: usize
+    ) -> Vec<(String, f32)> {
+        Vec::new()
+    }
 }
 
-use rustlearn::ensemble::random_forest::RandomForest;
-use bincode::deserialize;
+use rustlearn::ensemble::random_forest::{self, RandomForest};
+use rustlearn::multiclass::OneVsRestWrapper;
+use rustlearn::trees::decision_tree;
+use bincode::{deserialize, serialize};
 use memmap2::Mmap;
 
 use std::collections::HashMap;
-use std::path::PathBuf;
+use std::io::{BufRead, BufReader};
+use std::path::{Path, PathBuf};
 use std::fs::File;
-use anyhow::Result;
-use log::{debug, warn};
+use anyhow::{anyhow, Context, Result};
+use clap::Parser;
+use lazy_static::lazy_static;
+use log::{debug, info, warn};
+use parking_lot::RwLock;
 use regex;
 use rustlearn::prelude::*;
 use rustlearn::array::dense::Array as RustlearnArray;
+use serde::{Deserialize, Serialize};
+
+use crate::models::PayloadClass;
+
+/// Number of features produced by `extract_features`; models must be trained on exactly this
+pub const FEATURE_COUNT: usize = 32;
+
+/// Format version written into trained model bundles
+const MODEL_FORMAT_VERSION: u32 = 1;
+
+/// Class labels of the stock model, in class-index order
+const DEFAULT_LABELS: &[&str] = &[
+    "http", "https", "ssh", "ftp", "smtp", "pop3", "imap", "dns", "mysql", "postgresql",
+    "telnet", "rdp", "vnc", "snmp", "ldap", "sip", "ntp", "rtsp", "redis", "mongodb", "unknown",
+];
+
+lazy_static! {
+    /// Model file given with `--ml-model`, replacing the stock model
+    static ref MODEL_PATH: RwLock<Option<PathBuf>> = RwLock::new(None);
+}
+
+/// Load identifiers from this model file instead of the stock model
+pub fn set_model_path(path: PathBuf) {
+    *MODEL_PATH.write() = Some(path);
+}
+
+/// The classifier inside a model file
+#[derive(Serialize, Deserialize)]
+enum Classifier {
+    /// Stock model: a bare forest whose prediction is the class index
+    Forest(RandomForest),
+    /// Models produced by `train-model`: one forest per class
+    OneVsRest(OneVsRestWrapper<RandomForest>),
+}
+
+impl Classifier {
+    fn predict(&self, features: &RustlearnArray) -> Result<RustlearnArray, &'static str> {
+        match self {
+            Classifier::Forest(model) => model.predict(features),
+            Classifier::OneVsRest(model) => model.predict(features),
+        }
+    }
+}
+
+/// A trained classifier together with its class labels
+///
+/// Bundles also keep the feature vectors they were trained on so `train-model --update`
+/// can retrain with old and new samples together; forests cannot be grown incrementally.
+#[derive(Serialize, Deserialize)]
+pub struct ServiceModel {
+    format_version: u32,
+    labels: Vec<String>,
+    classifier: Classifier,
+    samples: Vec<(Vec<f32>, usize)>,
+}
+
+/// Hyperparameters for `ServiceModel::train`
+#[derive(Debug, Clone, Copy)]
+pub struct TrainingOptions {
+    /// Trees per class
+    pub trees: usize,
+    /// Maximum tree depth (None = grow until pure)
+    pub max_depth: Option<usize>,
+    /// Minimum samples required to split a node
+    pub min_samples_split: usize,
+}
+
+impl Default for TrainingOptions {
+    fn default() -> Self {
+        TrainingOptions { trees: 50, max_depth: None, min_samples_split: 2 }
+    }
+}
+
+impl ServiceModel {
+    /// Train a model on labelled samples, optionally on top of an existing model's samples
+    ///
+    /// # Arguments
+    /// * `samples` - New labelled observations
+    /// * `base` - Existing model whose labels and training samples are kept
+    /// * `options` - Forest hyperparameters
+    ///
+    /// # Returns
+    /// * `Result<Self>` - Trained model, or an error if there is too little data
+    pub fn train(samples: &[LabeledSample], base: Option<ServiceModel>, options: TrainingOptions) -> Result<Self> {
+        let (mut labels, mut rows) = match base {
+            Some(base) => (base.labels, base.samples),
+            None => (Vec::new(), Vec::new()),
+        };
+        for sample in samples {
+            let class = match labels.iter().position(|label| *label == sample.service) {
+                Some(index) => index,
+                None => {
+                    labels.push(sample.service.clone());
+                    labels.len() - 1
+                }
+            };
+            rows.push((sample.features(), class));
+        }
+        if labels.len() < 2 {
+            return Err(anyhow!("Training needs samples of at least two services (got {})", labels.len()));
+        }
+
+        let features: Vec<Vec<f32>> = rows.iter().map(|(features, _)| features.clone()).collect();
+        let x = RustlearnArray::from(&features);
+        let y = RustlearnArray::from(rows.iter().map(|(_, class)| *class as f32).collect::<Vec<f32>>());
+
+        let mut tree_params = decision_tree::Hyperparameters::new(FEATURE_COUNT);
+        tree_params
+            .min_samples_split(options.min_samples_split)
+            .max_features((FEATURE_COUNT as f64).sqrt() as usize);
+        if let Some(depth) = options.max_depth {
+            tree_params.max_depth(depth);
+        }
+        let mut classifier = random_forest::Hyperparameters::new(tree_params, options.trees).one_vs_rest();
+        classifier.fit(&x, &y).map_err(|e| anyhow!("Training failed: {}", e))?;
+
+        let model = ServiceModel { format_version: MODEL_FORMAT_VERSION, labels, classifier: Classifier::OneVsRest(classifier), samples: rows };
+        info!("[ML] Trained on {} samples across {} services, training accuracy {:.1}%",
+              model.samples.len(), model.labels.len(), model.training_accuracy() * 100.0);
+        Ok(model)
+    }
+
+    /// Fraction of training samples the model classifies correctly
+    pub fn training_accuracy(&self) -> f64 {
+        if self.samples.is_empty() {
+            return 0.0;
+        }
+        let correct = self.samples.iter()
+            .filter(|(features, class)| self.predict_index(features) == Some(*class))
+            .count();
+        correct as f64 / self.samples.len() as f64
+    }
+
+    /// Class labels in class-index order
+    pub fn labels(&self) -> &[String] {
+        &self.labels
+    }
+
+    /// Load a model bundle, or a bare stock forest (which gets the stock labels)
+    pub fn from_bytes(data: &[u8]) -> Result<Self> {
+        if let Ok(model) = deserialize::<ServiceModel>(data) {
+            if model.format_version > MODEL_FORMAT_VERSION {
+                return Err(anyhow!("Model format {} is newer than this build supports ({})", model.format_version, MODEL_FORMAT_VERSION));
+            }
+            return Ok(model);
+        }
+        let forest: RandomForest = deserialize(data).map_err(|e| anyhow!("Not a service model: {}", e))?;
+        Ok(ServiceModel {
+            format_version: MODEL_FORMAT_VERSION,
+            labels: DEFAULT_LABELS.iter().map(|label| label.to_string()).collect(),
+            classifier: Classifier::Forest(forest),
+            samples: Vec::new(),
+        })
+    }
+
+    /// Load a model file
+    pub fn load(path: &Path) -> Result<Self> {
+        let file = File::open(path).with_context(|| format!("Failed to open ML model {}", path.display()))?;
+        let mmap = unsafe { Mmap::map(&file) }.with_context(|| format!("Failed to map ML model {}", path.display()))?;
+        Self::from_bytes(&mmap[..]).with_context(|| format!("Failed to load ML model {}", path.display()))
+    }
+
+    /// Write the model to a file
+    pub fn save(&self, path: &Path) -> Result<()> {
+        let data = serialize(self).map_err(|e| anyhow!("Failed to serialize model: {}", e))?;
+        std::fs::write(path, data).with_context(|| format!("Failed to write ML model {}", path.display()))
+    }
+
+    /// Classes ranked by normalised one-vs-rest score, best first
+    ///
+    /// Bare stock forests only yield a label, so they produce no ranking.
+    fn rank(&self, features: &[f32], limit: usize) -> Vec<(usize, f32)> {
+        let model = match &self.classifier {
+            Classifier::OneVsRest(model) => model,
+            Classifier::Forest(_) => return Vec::new(),
+        };
+        let mut matrix = RustlearnArray::zeros(1, FEATURE_COUNT);
+        for (i, val) in features.iter().take(FEATURE_COUNT).enumerate() {
+            matrix.set(0, i, *val);
+        }
+        let scores = match model.decision_function(&matrix) {
+            Ok(scores) => scores,
+            Err(e) => {
+                warn!("ML scoring failed: {}", e);
+                return Vec::new();
+            }
+        };
+
+        // Column i scores the class whose label value is class_labels()[i]
+        let mut ranked: Vec<(usize, f32)> = model.class_labels().iter().enumerate()
+            .map(|(column, class)| (*class as usize, scores.get(0, column).max(0.0)))
+            .collect();
+        let total: f32 = ranked.iter().map(|(_, score)| score).sum();
+        if total <= 0.0 {
+            return Vec::new();
+        }
+        for (_, score) in ranked.iter_mut() {
+            *score /= total;
+        }
+        ranked.sort_by(|a, b| b.1.partial_cmp(&a.1).unwrap_or(std::cmp::Ordering::Equal));
+        ranked.truncate(limit);
+        ranked
+    }
+
+    /// Predicted class index for one feature vector
+    fn predict_index(&self, features: &[f32]) -> Option<usize> {
+        let mut matrix = RustlearnArray::zeros(1, FEATURE_COUNT);
+        for (i, val) in features.iter().take(FEATURE_COUNT).enumerate() {
+            matrix.set(0, i, *val);
+        }
+        match self.classifier.predict(&matrix) {
+            Ok(prediction) if prediction.rows() > 0 && prediction.cols() > 0 => Some(prediction.get(0, 0) as usize),
+            Ok(_) => None,
+            Err(e) => {
+                warn!("ML prediction execution failed: {}", e);
+                None
+            }
+        }
+    }
+}
 
 /// ML-based service identifier implementation
 #[derive(Default)]
 pub struct MlServiceIdentifier {
     // Internal implementation details
-    model: Option<RandomForest>,
-    service_labels: Vec<String>,
+    model: Option<ServiceModel>,
     version_patterns: HashMap<String, Vec<(regex::Regex, String)>>,
 }
 
@@ -54,31 +298,6 @@ impl MlServiceIdentifier {
         // Create a new ML service identifier with model loading
         let mut identifier = Self::default();
         
-        // Initialize service labels - these must match the model's class labels
-        identifier.service_labels = vec![
-            "http".to_string(),
-            "https".to_string(),
-            "ssh".to_string(),
-            "ftp".to_string(),
-            "smtp".to_string(),
-            "pop3".to_string(),
-            "imap".to_string(),
-            "dns".to_string(),
-            "mysql".to_string(),
-            "postgresql".to_string(),
-            "telnet".to_string(),
-            "rdp".to_string(),
-            "vnc".to_string(),
-            "snmp".to_string(),
-            "ldap".to_string(),
-            "sip".to_string(),
-            "ntp".to_string(),
-            "rtsp".to_string(),
-            "redis".to_string(),
-            "mongodb".to_string(),
-            "unknown".to_string(),
-        ];
-        
         // Try to load model from embedded binary or external file
         // Embedded binary approach is more secure for red teaming
         if let Err(e) = identifier.load_model() {
@@ -92,14 +311,22 @@ impl MlServiceIdentifier {
         identifier
     }
     
-    /// Load the ML model from an embedded binary or external file
+    /// Load the ML model from `--ml-model`, an embedded binary or the default file
     fn load_model(&mut self) -> Result<()> {
+        // A model given on the command line replaces the stock one entirely
+        if let Some(path) = MODEL_PATH.read().clone() {
+            let model = ServiceModel::load(&path)?;
+            info!("Loaded custom ML model from {} ({} services)", path.display(), model.labels().len());
+            self.model = Some(model);
+            return Ok(());
+        }
+
         // First try to load from embedded binary (preferred for red teaming)
         // This data would be embedded during compilation
         #[cfg(feature = "embedded_model")]
         {
             const MODEL_DATA: &[u8] = include_bytes!("../models/service_model.bin");
-            match deserialize(MODEL_DATA) {
+            match ServiceModel::from_bytes(MODEL_DATA) {
                 Ok(model) => {
                     self.model = Some(model);
                     debug!("Loaded ML model from embedded binary");
@@ -115,28 +342,14 @@ impl MlServiceIdentifier {
         // If not embedded, try to load from file
         let model_path = PathBuf::from("models/service_model.bin");
         if model_path.exists() {
-            match File::open(&model_path) {
-                Ok(file) => {
-                    match unsafe { Mmap::map(&file) } {
-                        Ok(mmap) => {
-                            match deserialize(&mmap[..]) {
-                                Ok(model) => {
-                                    self.model = Some(model);
-                                    debug!("Loaded ML model from file");
-                                    return Ok(());
-                                },
-                                Err(e) => {
-                                    warn!("Could not deserialize ML model from file: {}", e);
-                                }
-                            }
-                        },
-                        Err(e) => {
-                            warn!("Could not memory map ML model file: {}", e);
-                        }
-                    }
+            match ServiceModel::load(&model_path) {
+                Ok(model) => {
+                    self.model = Some(model);
+                    debug!("Loaded ML model from file");
+                    return Ok(());
                 },
                 Err(e) => {
-                    warn!("Could not open ML model file: {}", e);
+                    warn!("Could not load ML model file: {:#}", e);
                 }
             }
         }
@@ -199,111 +412,6 @@ impl MlServiceIdentifier {
         self.version_patterns = patterns;
     }
     
-    /// Extract features from service response for ML classification
-    fn extract_features(&self, data: &[u8], port: u16, response_time_ms: f32, 
-                      immediate_close: bool, server_initiated: bool) -> Vec<f32> {
-        // Feature vector for the ML model
-        let mut features = Vec::with_capacity(32);
-        
-        // Port as a normalized feature (divide by 65535 to get 0-1 range)
-        features.push(port as f32 / 65535.0);
-        
-        // Response time as a feature (normalized by dividing by 10s)
-        features.push(response_time_ms / 10000.0);
-        
-        // Connection behavior features
-        features.push(if immediate_close { 1.0 } else { 0.0 });
-        features.push(if server_initiated { 1.0 } else { 0.0 });
-        
-        // Data-based features
-        if data.is_empty() {
-            // Padding with zeros for empty response
-            features.extend(vec![0.0; 28]);
-        } else {
-            // Content features
-            // 1. Length (normalized by dividing by 1000)
-            features.push((data.len() as f32).min(1000.0) / 1000.0);
-            
-            // 2. Printable character ratio
-            let printable_count = data.iter()
-                .filter(|&&b| b >= 32 && b <= 126)
-                .count();
-            features.push(printable_count as f32 / data.len() as f32);
-            
-            // 3. Byte histograms - create a histogram of byte values
-            // Group bytes into 16 buckets for dimensionality reduction
-            let mut histogram = vec![0.0; 16];
-            for &byte in data {
-                let bucket = (byte as usize) / 16;
-                histogram[bucket] += 1.0;
-            }
-            // Normalize histogram
-            let sum: f32 = histogram.iter().sum();
-            if sum > 0.0 {
-                for val in &mut histogram {
-                    *val /= sum;
-                }
-            }
-            features.extend(histogram);
-            
-            // 4. ASCII word patterns
-            let text = String::from_utf8_lossy(data);
-            
-            // Check for HTTP patterns
-            features.push(if text.contains("HTTP/") { 1.0 } else { 0.0 });
-            
-            // Check for SSH patterns
-            features.push(if text.contains("SSH-") { 1.0 } else { 0.0 });
-            
-            // Check for SMTP patterns
-            features.push(if text.contains("SMTP") || text.contains("220 ") { 1.0 } else { 0.0 });
-            
-            // Check for FTP patterns
-            features.push(if text.contains("FTP") || text.contains("220-") { 1.0 } else { 0.0 });
-            
-            // Check for database patterns
-            features.push(if text.contains("MySQL") || text.contains("PostgreSQL") { 1.0 } else { 0.0 });
-            
-            // Byte distribution entropy - measure of randomness
-            let mut entropy = 0.0;
-            let mut byte_counts = [0.0f32; 256];
-            let total_bytes = data.len() as f32;
-            
-            // Count occurrences of each byte value
-            for &byte in data {
-                byte_counts[byte as usize] += 1.0;
-            }
-            
-            // Calculate entropy based on byte distribution
-            for count in byte_counts.iter() {
-                if *count > 0.0 {
-                    let probability = *count / total_bytes;
-                    entropy -= probability * probability.log2();
-                }
-            }
-            // Normalize entropy to 0-1 range (divide by maximum possible entropy)
-            entropy /= 8.0;
-            
-            // Add entropy as a feature - high entropy often indicates encryption or compression
-            features.push(entropy);
-            
-            // Add binary protocol detection features
-            // Binary protocols often have specific byte patterns in headers
-            features.push(if data.len() >= 4 && data[0] == 0x03 && data[1] == 0x00 { 1.0 } else { 0.0 }); // Possible RDP
-            features.push(if data.len() >= 2 && data[0] == 0x16 && data[1] == 0x03 { 1.0 } else { 0.0 }); // Possible TLS
-            
-            // Remaining feature slots (pad if needed)
-            while features.len() < 32 {
-                features.push(0.0);
-            }
-        }
-        
-        // Truncate to exactly 32 features if we have more
-        features.truncate(32);
-        
-        features
-    }
-    
     /// Extract version information from service response data
     fn extract_version_info(&self, service_name: &str, data: &[u8]) -> Option<String> {
         // Convert binary data to string for pattern matching
@@ -355,53 +463,25 @@ impl ServiceIdentification for MlServiceIdentifier {
         if let Some(model) = &self.model {
             debug!("ML model is loaded, attempting ML-based identification");
             // Extract features from the data and metadata.
-            let features_vec = self.extract_features(data, port, response_time_ms, 
-                                                 immediate_close, server_initiated);
+            let features_vec = extract_features(data, port, response_time_ms,
+                                                immediate_close, server_initiated);
             
-            // Ensure the number of features matches the model's expectation (implicitly 32 here).
-            if features_vec.len() == 32 { // Check expected feature count
-                // Create a new Array with the features
-                let mut features_matrix = RustlearnArray::zeros(1, 32);
-                
-                // Fill the array with our feature values
-                for (i, val) in features_vec.iter().enumerate() {
-                    features_matrix.set(0, i, *val);
-                }
-                
-                // Perform the prediction using the RandomForest model.
-                match model.predict(&features_matrix) { 
-                    Ok(prediction_array) => {
-                        // Check dimensions before accessing the element
-                        if prediction_array.rows() > 0 && prediction_array.cols() > 0 { 
-                            // Directly use the result of get(0, 0) as f32
-                            let predicted_index_val = prediction_array.get(0, 0);
-                            let index = predicted_index_val as usize;
-                            // Map the index to a service label from our predefined list.
-                            if let Some(service_name) = self.service_labels.get(index) {
-                                debug!("ML prediction: Index={}, Service='{}'", index, service_name);
-                                // Avoid returning "unknown" if ML predicts it; let fallbacks provide more context if possible.
-                                if service_name != "unknown" {
-                                    // Attempt to extract version info using regex patterns for the predicted service.
-                                    let version_info = self.extract_version_info(service_name, data);
-                                    // Return the ML-identified service and optional version.
-                                    return Some((service_name.clone(), version_info)); 
-                                } else {
-                                    debug!("ML predicted 'unknown', proceeding to fallbacks for potential refinement.");
-                                }
-                            } else {
-                                warn!("ML predicted index {} which is out of bounds for service_labels ({} labels)", index, self.service_labels.len());
-                            }
-                        } else {
-                             warn!("ML prediction array was empty or had invalid dimensions.");
-                        }
-                    },
-                    Err(e) => {
-                        // Log errors during prediction.
-                        warn!("ML prediction execution failed: {}", e);
+            if let Some(index) = model.predict_index(&features_vec) {
+                // Map the index to a service label from the model's list.
+                if let Some(service_name) = model.labels().get(index) {
+                    debug!("ML prediction: Index={}, Service='{}'", index, service_name);
+                    // Avoid returning "unknown" if ML predicts it; let fallbacks provide more context if possible.
+                    if service_name != "unknown" {
+                        // Attempt to extract version info using regex patterns for the predicted service.
+                        let version_info = self.extract_version_info(service_name, data);
+                        // Return the ML-identified service and optional version.
+                        return Some((service_name.clone(), version_info)); 
+                    } else {
+                        debug!("ML predicted 'unknown', proceeding to fallbacks for potential refinement.");
                     }
+                } else {
+                    warn!("ML predicted index {} which is out of bounds for service_labels ({} labels)", index, model.labels().len());
                 }
-            } else {
-                 warn!("Feature vector size mismatch (expected 32, got {}), skipping ML prediction.", features_vec.len());
             }
         } else {
              // Model not loaded - this is logged during initialization.
@@ -485,6 +565,135 @@ impl ServiceIdentification for MlServiceIdentifier {
         debug!("Service identification failed for port {}", port);
         None
     }
+
+    fn candidates(
+        &self,
+        data: &[u8],
+        port: u16,
+        response_time_ms: f32,
+        immediate_close: bool,
+        server_initiated: bool,
+        limit: usize
+    ) -> Vec<(String, f32)> {
+        let model = match &self.model {
+            Some(model) => model,
+            None => return Vec::new(),
+        };
+        let features = extract_features(data, port, response_time_ms, immediate_close, server_initiated);
+        model.rank(&features, limit).into_iter()
+            .filter_map(|(index, confidence)| model.labels().get(index).map(|label| (label.clone(), confidence)))
+            .collect()
+    }
+}
+
+/// Extract features from service response for ML classification
+pub fn extract_features(data: &[u8], port: u16, response_time_ms: f32,
+                        immediate_close: bool, server_initiated: bool) -> Vec<f32> {
+    // Feature vector for the ML model
+    let mut features = Vec::with_capacity(FEATURE_COUNT);
+    
+    // Port as a normalized feature (divide by 65535 to get 0-1 range)
+    features.push(port as f32 / 65535.0);
+    
+    // Response time as a feature (normalized by dividing by 10s)
+    features.push(response_time_ms / 10000.0);
+    
+    // Connection behavior features
+    features.push(if immediate_close { 1.0 } else { 0.0 });
+    features.push(if server_initiated { 1.0 } else { 0.0 });
+    
+    // Data-based features
+    if data.is_empty() {
+        // Padding with zeros for empty response
+        features.extend(vec![0.0; 28]);
+    } else {
+        // Content features
+        // 1. Length (normalized by dividing by 1000)
+        features.push((data.len() as f32).min(1000.0) / 1000.0);
+        
+        // 2. Printable character ratio
+        let printable_count = data.iter()
+            .filter(|&&b| b >= 32 && b <= 126)
+            .count();
+        features.push(printable_count as f32 / data.len() as f32);
+        
+        // 3. Byte histograms - create a histogram of byte values
+        // Group bytes into 16 buckets for dimensionality reduction
+        let mut histogram = vec![0.0; 16];
+        for &byte in data {
+            let bucket = (byte as usize) / 16;
+            histogram[bucket] += 1.0;
+        }
+        // Normalize histogram
+        let sum: f32 = histogram.iter().sum();
+        if sum > 0.0 {
+            for val in &mut histogram {
+                *val /= sum;
+            }
+        }
+        features.extend(histogram);
+        
+        // 4. ASCII word patterns
+        let text = String::from_utf8_lossy(data);
+        
+        // Check for HTTP patterns
+        features.push(if text.contains("HTTP/") { 1.0 } else { 0.0 });
+        
+        // Check for SSH patterns
+        features.push(if text.contains("SSH-") { 1.0 } else { 0.0 });
+        
+        // Check for SMTP patterns
+        features.push(if text.contains("SMTP") || text.contains("220 ") { 1.0 } else { 0.0 });
+        
+        // Check for FTP patterns
+        features.push(if text.contains("FTP") || text.contains("220-") { 1.0 } else { 0.0 });
+        
+        // Check for database patterns
+        features.push(if text.contains("MySQL") || text.contains("PostgreSQL") { 1.0 } else { 0.0 });
+        
+        // Byte distribution entropy - measure of randomness
+        let mut entropy = 0.0;
+        let mut byte_counts = [0.0f32; 256];
+        let total_bytes = data.len() as f32;
+        
+        // Count occurrences of each byte value
+        for &byte in data {
+            byte_counts[byte as usize] += 1.0;
+        }
+        
+        // Calculate entropy based on byte distribution
+        for count in byte_counts.iter() {
+            if *count > 0.0 {
+                let probability = *count / total_bytes;
+                entropy -= probability * probability.log2();
+            }
+        }
+        // Normalize entropy to 0-1 range (divide by maximum possible entropy)
+        entropy /= 8.0;
+        
+        // Add entropy as a feature - high entropy often indicates encryption or compression
+        features.push(entropy);
+        
+        // Add binary protocol detection features
+        // Binary protocols often have specific byte patterns in headers
+        features.push(if data.len() >= 4 && data[0] == 0x03 && data[1] == 0x00 { 1.0 } else { 0.0 }); // Possible RDP
+        features.push(if data.len() >= 2 && data[0] == 0x16 && data[1] == 0x03 { 1.0 } else { 0.0 }); // Possible TLS
+        
+        // Payload class from the entropy analysis, which tells ciphertext from compressed data
+        let payload = crate::entropy::profile(data);
+        features.push(if payload.class == PayloadClass::Compressed { 1.0 } else { 0.0 });
+        features.push(if payload.class == PayloadClass::Encrypted { 1.0 } else { 0.0 });
+        
+        // Remaining feature slots (pad if needed)
+        while features.len() < FEATURE_COUNT {
+            features.push(0.0);
+        }
+    }
+    
+    // Truncate to exactly 32 features if we have more
+    features.truncate(FEATURE_COUNT);
+    
+    features
 }
 
 // Extract version information using regex patterns
@@ -507,4 +716,135 @@ fn extract_version_with_patterns(text: &str, patterns: &[(regex::Regex, String)]
 // This is a convenience function that ensures consistent initialization
 pub fn create_ml_identifier() -> MlServiceIdentifier {
     MlServiceIdentifier::new()
-} 
\ No newline at end of file
+}
+
+/// One labelled observation from a training dataset
+#[derive(Debug, Clone, Deserialize)]
+pub struct LabeledSample {
+    /// Service label to learn
+    pub service: String,
+    pub port: u16,
+    /// Banner as text
+    #[serde(default)]
+    pub banner: Option<String>,
+    /// Banner as hex, for binary protocols; takes precedence over `banner`
+    #[serde(default)]
+    pub banner_hex: Option<String>,
+    #[serde(default)]
+    pub response_time_ms: f32,
+    #[serde(default)]
+    pub immediate_close: bool,
+    #[serde(default = "default_server_initiated")]
+    pub server_initiated: bool,
+}
+
+fn default_server_initiated() -> bool {
+    true
+}
+
+impl LabeledSample {
+    fn data(&self) -> Vec<u8> {
+        match (&self.banner_hex, &self.banner) {
+            (Some(hex), _) => (0..hex.len() / 2)
+                .filter_map(|i| u8::from_str_radix(&hex[i * 2..i * 2 + 2], 16).ok())
+                .collect(),
+            (None, Some(text)) => text.as_bytes().to_vec(),
+            (None, None) => Vec::new(),
+        }
+    }
+
+    fn features(&self) -> Vec<f32> {
+        extract_features(&self.data(), self.port, self.response_time_ms, self.immediate_close, self.server_initiated)
+    }
+}
+
+/// Read a JSON Lines dataset of labelled banners
+///
+/// Each line is an object such as
+/// `{"service": "acme-erp", "port": 9100, "banner": "ACME ERP 4.2 ready"}`; binary
+/// greetings go in `banner_hex`. Blank lines and lines starting with `#` are skipped.
+pub fn load_dataset(path: &Path) -> Result<Vec<LabeledSample>> {
+    let file = File::open(path).with_context(|| format!("Failed to open dataset {}", path.display()))?;
+    let mut samples = Vec::new();
+    for (index, line) in BufReader::new(file).lines().enumerate() {
+        let line = line?;
+        let line = line.trim();
+        if line.is_empty() || line.starts_with('#') {
+            continue;
+        }
+        let sample: LabeledSample = serde_json::from_str(line)
+            .with_context(|| format!("{}:{}: invalid sample", path.display(), index + 1))?;
+        if sample.banner_hex.as_ref().map_or(false, |hex| hex.len() % 2 != 0 || !hex.chars().all(|c| c.is_ascii_hexdigit())) {
+            return Err(anyhow!("{}:{}: banner_hex is not valid hex", path.display(), index + 1));
+        }
+        samples.push(sample);
+    }
+    Ok(samples)
+}
+
+/// Command line for model training mode
+#[derive(Parser, Debug)]
+#[clap(
+    name = "quantum_train_model",
+    about = "Train or update the ML service classifier from labelled banner datasets",
+    after_help = "EXAMPLES:
+    # New model from in-house banners plus a public dataset
+    quantum_scanner train-model --dataset inhouse.jsonl --dataset public.jsonl --output models/custom.bin
+
+    # Add samples to an existing model and scan with it
+    quantum_scanner train-model --update models/custom.bin --dataset new.jsonl --output models/custom.bin
+    quantum_scanner 10.0.0.1 -sV --ml-model models/custom.bin"
+)]
+pub struct TrainArgs {
+    /// JSON Lines dataset of labelled banners (repeatable)
+    #[clap(long, required = true)]
+    pub dataset: Vec<PathBuf>,
+
+    /// Where to write the trained model
+    #[clap(long)]
+    pub output: PathBuf,
+
+    /// Existing model to extend; its labels and training samples are kept
+    #[clap(long)]
+    pub update: Option<PathBuf>,
+
+    /// Trees per service class
+    #[clap(long, default_value_t = 50)]
+    pub trees: usize,
+
+    /// Maximum tree depth (default: unlimited)
+    #[clap(long)]
+    pub max_depth: Option<usize>,
+}
+
+/// Run model training
+///
+/// # Arguments
+/// * `args` - Parsed training command line
+///
+/// # Returns
+/// * `Result<()>` - Error if a dataset or model could not be read, or training failed
+pub fn run_train(args: TrainArgs) -> Result<()> {
+    let mut samples = Vec::new();
+    for path in &args.dataset {
+        let loaded = load_dataset(path)?;
+        info!("[ML] {} samples from {}", loaded.len(), path.display());
+        samples.extend(loaded);
+    }
+    let base = match &args.update {
+        Some(path) => {
+            let base = ServiceModel::load(path)?;
+            if base.samples.is_empty() {
+                return Err(anyhow!("{} carries no training samples and cannot be updated; train a new model instead", path.display()));
+            }
+            Some(base)
+        }
+        None => None,
+    };
+
+    let options = TrainingOptions { trees: args.trees.max(1), max_depth: args.max_depth, ..TrainingOptions::default() };
+    let model = ServiceModel::train(&samples, base, options)?;
+    model.save(&args.output)?;
+    println!("Model written to {} ({} services: {})", args.output.display(), model.labels().len(), model.labels().join(", "));
+    Ok(())
+}
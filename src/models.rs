@@ -1,6 +1,7 @@
 use std::collections::{HashMap, HashSet};
 use std::fmt;
 use std::str::FromStr;
+use std::net::IpAddr;
 use chrono::{DateTime, Utc};
 use serde::{Serialize, Deserialize};
 use thiserror::Error;
@@ -80,6 +81,12 @@ pub enum ScanType {
     /// open and closed ports, even when both return RST packets.
     Window,
     
+    /// TCP scan with FIN and ACK flags set (Uriel Maimon's technique)
+    /// 
+    /// RFC 793 stacks answer with RST whatever the port state, but BSD-derived
+    /// stacks drop the segment when the port is open. Same detection logic as FIN scans.
+    Maimon,
+    
     /// Sends SYN packets with protocol-specific payloads
     /// 
     /// Crafts SYN packets with data that mimics legitimate protocol behavior
@@ -108,6 +115,36 @@ pub enum ScanType {
     /// Encodes scan traffic within ICMP echo (ping) packets, which are sometimes
     /// allowed through firewalls when other traffic is blocked.
     IcmpTunnel,
+
+    /// Enumerates which IP protocols (ICMP, IGMP, GRE, ESP, AH, SCTP...) the host handles
+    /// 
+    /// Sends raw IP packets with each protocol number and watches for protocol
+    /// replies or ICMP protocol unreachable errors. Runs once per protocol rather
+    /// than once per port. Reveals VPN and tunnel endpoints invisible to TCP/UDP scans.
+    IpProto,
+
+    /// Tunnels scan through an HTTP(S) relay to bypass restrictive firewalls
+    /// 
+    /// Each probe is a web request to an operator-controlled relay which tests the
+    /// port and reports back. Supports a Host header/SNI split for fronted relays.
+    HttpTunnel,
+
+    /// Tunnels scan through a QUIC relay to bypass restrictive firewalls
+    /// 
+    /// Same relay protocol as `HttpTunnel`, carried on QUIC streams over UDP/443 so
+    /// probes resemble HTTP/3 traffic. Reconnects resume with 0-RTT.
+    QuicTunnel,
+
+    /// Compares answers to a normal SYN, a bad-checksum SYN and a SYN+FIN
+    /// 
+    /// Hosts drop segments with bad checksums, so any answer to that probe comes
+    /// from a firewall, IDS or load balancer replying on the host's behalf.
+    BadSum,
+
+    /// Port state inferred from observed traffic without sending probes
+    /// 
+    /// Only produced by `--passive`; not selectable with `-s`.
+    Passive,
 }
 
 impl fmt::Display for ScanType {
@@ -121,10 +158,16 @@ impl fmt::Display for ScanType {
             ScanType::Xmas => write!(f, "XMAS"),
             ScanType::Null => write!(f, "NULL"),
             ScanType::Window => write!(f, "WINDOW"),
+            ScanType::Maimon => write!(f, "MAIMON"),
             ScanType::Mimic => write!(f, "MIMIC"),
             ScanType::Frag => write!(f, "FRAG"),
             ScanType::DnsTunnel => write!(f, "DNS_TUNNEL"),
             ScanType::IcmpTunnel => write!(f, "ICMP_TUNNEL"),
+            ScanType::IpProto => write!(f, "IPPROTO"),
+            ScanType::HttpTunnel => write!(f, "HTTP_TUNNEL"),
+            ScanType::QuicTunnel => write!(f, "QUIC_TUNNEL"),
+            ScanType::BadSum => write!(f, "BADSUM"),
+            ScanType::Passive => write!(f, "PASSIVE"),
         }
     }
 }
@@ -236,6 +279,76 @@ pub struct CertificateInfo {
     
     /// Algorithm used for the public key (e.g., RSA, ECC).
     pub key_algorithm: Option<String>,
+
+    /// The rest of the chain the server presented (intermediates, and the root if sent),
+    /// in the order received. Empty on the chain's own entries.
+    #[serde(default)]
+    pub chain: Vec<CertificateInfo>,
+
+    /// Whether the chain validated against the system trust store. `None` when it could
+    /// not be checked (no trust anchors available).
+    #[serde(default)]
+    pub trusted: Option<bool>,
+
+    /// Whether the server stapled an OCSP response to the handshake. `None` when it was
+    /// not checked (no trust anchors available).
+    #[serde(default)]
+    pub ocsp_stapled: Option<bool>,
+
+    /// Problems found with the certificate or its chain
+    #[serde(default)]
+    pub issues: Vec<CertificateIssue>,
+}
+
+/// Kind of certificate problem found during chain analysis
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum CertIssueKind {
+    /// Leaf is signed by its own key
+    SelfSigned,
+    /// Chain does not lead to a trusted root
+    Untrusted,
+    /// A certificate in the chain was not issued by the next one
+    BrokenChain,
+    /// Validity period has ended
+    Expired,
+    /// Validity period has not started
+    NotYetValid,
+    /// Validity ends within the warning window
+    ExpiresSoon,
+    /// Signed with MD2, MD5 or SHA-1
+    WeakSignature,
+    /// RSA or DSA key under 2048 bits
+    WeakKey,
+    /// Certificate does not cover the scanned hostname
+    HostnameMismatch,
+}
+
+impl fmt::Display for CertIssueKind {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let name = match self {
+            CertIssueKind::SelfSigned => "self-signed",
+            CertIssueKind::Untrusted => "untrusted",
+            CertIssueKind::BrokenChain => "broken-chain",
+            CertIssueKind::Expired => "expired",
+            CertIssueKind::NotYetValid => "not-yet-valid",
+            CertIssueKind::ExpiresSoon => "expires-soon",
+            CertIssueKind::WeakSignature => "weak-signature",
+            CertIssueKind::WeakKey => "weak-key",
+            CertIssueKind::HostnameMismatch => "hostname-mismatch",
+        };
+        write!(f, "{}", name)
+    }
+}
+
+/// A single certificate problem
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CertificateIssue {
+    pub kind: CertIssueKind,
+    /// Severity rating, same scale as `Finding::severity`
+    pub severity: String,
+    /// Human readable explanation
+    pub detail: String,
 }
 
 /// Detailed results for a single port scan
@@ -269,8 +382,8 @@ pub struct PortResult {
     pub version: Option<String>,
     
     /// Potential vulnerabilities identified for this service/version based on integrated
-    /// or external vulnerability databases. Stores detailed VulnInfo structs.
-    pub vulns: Vec<VulnInfo>,
+    /// or external vulnerability databases. Stores detailed `Finding` structs.
+    pub vulns: Vec<Finding>,
     
     /// SSL/TLS certificate information if the service uses TLS (e.g., HTTPS on port 443).
     pub cert_info: Option<CertificateInfo>,
@@ -297,7 +410,8 @@ pub struct PortResult {
     
     /// Any unusual or unexpected behavior observed during scanning this port.
     /// E.g., "Unexpected RST packet during SYN scan", "Non-standard HTTP header".
-    pub anomalies: Vec<String>,
+    /// Recorded as Info findings (see `Finding::anomaly`).
+    pub anomalies: Vec<Finding>,
     
     /// Analysis of response times (RTT) which can sometimes infer network distance
     /// or server load.
@@ -312,8 +426,17 @@ pub struct PortResult {
     
     /// Final determined status for the port considering all scan types performed.
     /// This field is used to determine the overall status of the port for reporting.
-    /// It prioritizes Open > OpenFiltered > other statuses.
+    /// Once the scan ends it is the weighted consensus of the techniques (see `consensus`).
     pub final_status: PortStatus,
+
+    /// How the techniques' verdicts were merged into `final_status`
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub consensus: Option<StateConsensus>,
+
+    /// Why this port's replies may have come from an inline security appliance rather
+    /// than the host (see `ScanResults::interference`). Absent when nothing was suspected.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub unreliable: Option<String>,
     
     /// Detailed reason why this port has its current status. Provides context about
     /// why a port was classified as open, closed, filtered, etc. Examples include
@@ -333,6 +456,265 @@ pub struct PortResult {
     /// Confidence level of the nDPI detection (placeholder).
     /// Currently uses String to match output.rs expectation, could be f32 later.
     pub ndpi_confidence: Option<String>,
+
+    /// Top services proposed by the ML classifier with their confidence (0.0-1.0),
+    /// best first. Empty when ML identification did not run or the model gives no scores.
+    #[serde(default)]
+    pub ml_candidates: Vec<(String, f32)>,
+
+    /// Findings reported by user scripts (`--scripts`), one entry per script that
+    /// reported something for this port.
+    #[serde(default)]
+    pub script_output: Vec<ScriptOutput>,
+
+    /// Leading bytes the service sent during banner grabbing, kept with
+    /// `--capture-responses` for offline analysis and re-fingerprinting.
+    #[serde(default)]
+    pub captured_response: Option<CapturedResponse>,
+
+    /// Service class (or service or port) of `--protect-services` that matched this port.
+    /// Set means VPN handshakes, amplification, SMTP, DNS, open proxy, remote desktop, container
+    /// platform, cloud metadata, printer, SIP and cleartext protocol checks, credential checks,
+    /// scripts and plugins skipped it.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub protected_by: Option<String>,
+
+    /// Relay, AUTH and STARTTLS policy of an SMTP service
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub smtp: Option<SmtpPolicy>,
+
+    /// Reply sizes of the standard queries of a UDP reflector protocol
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub amplification: Vec<AmplificationMeasurement>,
+
+    /// Model, firmware and exposed functions of a printing service
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub printer: Option<PrinterInfo>,
+
+    /// SIP agent, methods and registration policy, one entry per transport that answered
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub sip: Vec<SipInfo>,
+
+    /// Shape of the service's response when no fingerprint matched it, telling an
+    /// encrypted or compressed protocol apart from a silent one
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub payload: Option<PayloadProfile>,
+
+    /// Distinct certificates the port served for the names in `--sni-list`
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub sni_certificates: Vec<SniCertificate>,
+
+    /// Client certificate request seen during the SSL scan (mutual TLS)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub client_auth: Option<ClientAuthInfo>,
+}
+
+/// How a SIP service answered `OPTIONS` and a bindings query
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct SipInfo {
+    /// `udp`, `tcp` or `tls`
+    pub transport: String,
+
+    /// Status line of the `OPTIONS` answer
+    pub status: String,
+
+    /// `Server` or `User-Agent` header
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub agent: Option<String>,
+
+    /// Methods listed in `Allow`
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub methods: Vec<String>,
+
+    /// `open`, `auth_required` or the rejection, for a `REGISTER` without credentials
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub registration: Option<String>,
+
+    /// Realm of the authentication challenge
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub realm: Option<String>,
+
+    /// Media ports of `--rtp-range` that relayed RTP to an unsolicited sender
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub rtp_relays: Vec<u16>,
+}
+
+/// What a printing service (JetDirect, IPP, LPD) revealed about its device
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct PrinterInfo {
+    /// Protocol that answered: `pjl`, `ipp` or `lpd`
+    pub protocol: String,
+
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub model: Option<String>,
+
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub firmware: Option<String>,
+
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub serial: Option<String>,
+
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub location: Option<String>,
+
+    /// Status display message or queue state
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub status: Option<String>,
+
+    /// Functions reachable without authentication (PJL control, file system, IPP operations...)
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub functions: Vec<String>,
+}
+
+/// Size of a UDP service's reply to one standard query
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct AmplificationMeasurement {
+    /// Service and query, e.g. `ntp monlist`
+    pub query: String,
+
+    /// UDP payload bytes sent
+    pub request_bytes: usize,
+
+    /// UDP payload bytes received across all reply datagrams
+    pub response_bytes: usize,
+
+    pub datagrams: usize,
+
+    /// `response_bytes` over `request_bytes`
+    pub factor: f64,
+
+    /// Counting stopped at the reply limit, so the factor is a lower bound
+    #[serde(default)]
+    pub capped: bool,
+}
+
+/// Weighted vote of the scan techniques on a port's state
+///
+/// Techniques are weighted by how directly they observe the port: a SYN-ACK or a
+/// banner is proof, a missing reply to a FIN probe is not. Ambiguous verdicts split
+/// their weight (open|filtered between open and filtered, unfiltered between open and
+/// closed).
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct StateConsensus {
+    /// Merged state, copied to the port's `final_status`
+    pub state: PortStatus,
+
+    /// Share of the total weight that supports `state`, from 0.0 to 1.0
+    pub confidence: f64,
+
+    /// One line per technique: its verdict, the reason it gave and its weight
+    pub evidence: Vec<String>,
+
+    /// Techniques whose verdict does not support `state`
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub dissenting: Vec<String>,
+}
+
+/// A server's request for a client certificate
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ClientAuthInfo {
+    /// The server dropped the handshake when no certificate was sent; otherwise the
+    /// certificate is optional
+    pub required: bool,
+
+    /// Distinguished names of the CAs the server accepts client certificates from, as
+    /// advertised in the request. Empty when the server names none.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub acceptable_cas: Vec<String>,
+}
+
+/// A certificate served for one or more SNI names
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SniCertificate {
+    /// Server names the certificate was returned for
+    pub names: Vec<String>,
+
+    /// Whether it differs from the certificate served without SNI, i.e. the name
+    /// reached a separate virtual host or backend
+    pub differs_from_default: bool,
+
+    /// The leaf certificate with its chain
+    pub certificate: CertificateInfo,
+}
+
+/// Byte statistics of a response, used to classify protocols no fingerprint knows
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PayloadProfile {
+    /// What the bytes look like
+    pub class: PayloadClass,
+
+    /// Shannon entropy in bits per byte (0.0 to 8.0)
+    pub entropy: f64,
+
+    /// Share of printable ASCII bytes, whitespace included
+    pub printable: f64,
+
+    /// Number of bytes analysed
+    pub length: usize,
+
+    /// Container or record format recognised from the leading bytes (gzip, TLS record, ...)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub format: Option<String>,
+}
+
+/// Broad kind of data a service sent
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum PayloadClass {
+    /// The service sent nothing
+    NoData,
+    /// Mostly printable ASCII: a line-based or markup protocol
+    Text,
+    /// Structured binary with low or moderate entropy
+    Binary,
+    /// High entropy with a compression header or an uneven byte distribution
+    Compressed,
+    /// High entropy with evenly spread byte values: ciphertext or random data
+    Encrypted,
+}
+
+impl fmt::Display for PayloadClass {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            PayloadClass::NoData => write!(f, "no data"),
+            PayloadClass::Text => write!(f, "text"),
+            PayloadClass::Binary => write!(f, "binary"),
+            PayloadClass::Compressed => write!(f, "compressed"),
+            PayloadClass::Encrypted => write!(f, "encrypted"),
+        }
+    }
+}
+
+/// How an SMTP service treats unauthenticated mail and encryption
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct SmtpPolicy {
+    /// TLS from the first byte (port 465), so there is no plaintext phase
+    pub implicit_tls: bool,
+
+    /// STARTTLS listed among the EHLO extensions
+    pub starttls_offered: bool,
+
+    /// Why STARTTLS was offered but could not be completed
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub starttls_error: Option<String>,
+
+    /// AUTH mechanisms offered before TLS
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub auth_plaintext: Vec<String>,
+
+    /// AUTH mechanisms offered once TLS is up
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub auth_tls: Vec<String>,
+
+    /// Whether `MAIL FROM` was accepted without authentication
+    pub unauthenticated_mail_accepted: Option<bool>,
+
+    /// Whether a recipient outside the server's domains was accepted without authentication
+    pub open_relay: Option<bool>,
+
+    /// Server's answer to the relay test
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub relay_reply: Option<String>,
 }
 
 impl Default for PortResult {
@@ -355,14 +737,30 @@ impl Default for PortResult {
             service_details: None,
             tls_protocol_version: None,
             final_status: PortStatus::Filtered, // Default to Filtered
+            consensus: None,
+            unreliable: None,
             reason: None, // Default to None
             tcp_reasons: HashMap::new(),
             ndpi_protocol: None, // Initialize renamed field
             ndpi_confidence: None, // Initialize new field
+            ml_candidates: Vec::new(),
+            script_output: Vec::new(),
+            captured_response: None,
+            protected_by: None,
+            smtp: None,
+            amplification: Vec::new(),
+            printer: None,
+            sip: Vec::new(),
+            payload: None,
+            sni_certificates: Vec::new(),
+            client_auth: None,
         }
     }
 }
 
+/// Version of the `ScanResults` JSON format; see `schema` for the migrations
+pub const SCHEMA_VERSION: u32 = 2;
+
 /// Overall scan results for a target
 ///
 /// Aggregates all information collected during the scanning process for a single target host,
@@ -370,6 +768,11 @@ impl Default for PortResult {
 /// view of the target's network posture.
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct ScanResults {
+    /// Version of the format this result was written in (`SCHEMA_VERSION`). Files
+    /// written before versioning have none and read as version 0.
+    #[serde(default)]
+    pub schema_version: u32,
+
     /// Target hostname or IP address as specified by the user.
     pub target: String,
     
@@ -397,6 +800,11 @@ pub struct ScanResults {
     /// handshakes, retries, etc. High packet counts increase the chance of detection (OpSec).
     pub packets_sent: usize,
     
+    /// Estimated bytes put on the wire for this target: probes, handshakes, payloads and
+    /// UDP datagrams, IP headers included. Checked against `--max-bandwidth`.
+    #[serde(default)]
+    pub bytes_sent: u64,
+    
     /// Number of scan operations (e.g., probing a specific port with a specific method)
     /// that completed successfully (received a definitive response or timed out as expected).
     pub successful_scans: usize,
@@ -410,35 +818,682 @@ pub struct ScanResults {
     /// Could be a qualitative score (Low, Medium, High) or a quantitative metric.
     pub risk_assessment: Option<String>,
     
-    /// Groups detected services into logical categories (e.g., "web", "database", "remote_access")
+    /// Groups detected services into logical categories (e.g., "container-platform", "voip")
     /// along with the ports where they were found. Helps in quickly understanding the
     /// types of services exposed by the target.
     pub service_categories: Option<HashMap<String, Vec<u16>>>,
+    
+    /// Hardware (MAC) address of the target. Only available when the target is on the
+    /// same layer 2 segment as the scanner, taken from the local neighbour cache.
+    #[serde(default)]
+    pub mac_address: Option<String>,
+    
+    /// Vendor registered to the OUI of `mac_address`, if known. Useful for spotting
+    /// virtual machines, network appliances and embedded devices.
+    #[serde(default)]
+    pub mac_vendor: Option<String>,
+    
+    /// Results of the IP protocol scan (`ScanType::IpProto`), keyed by IP protocol number.
+    /// Empty unless that scan type was requested.
+    #[serde(default)]
+    pub ip_protocols: HashMap<u8, IpProtocolResult>,
+    
+    /// Host characteristics gathered from ICMP timestamp, address mask and
+    /// unreachable responses. Only present when ICMP probing was enabled.
+    #[serde(default)]
+    pub icmp_info: Option<IcmpHostInfo>,
+
+    /// Round-trip estimate and the probe timeout derived from it. Only present with
+    /// `--adaptive-timeout`.
+    #[serde(default)]
+    pub host_timing: Option<HostTiming>,
+
+    /// TCP techniques chosen for this host by the pre-probe and why. Only present with
+    /// `--auto-technique`.
+    #[serde(default)]
+    pub technique_selection: Option<TechniqueSelection>,
+
+    /// Resolver sanity checks run before DNS tunnel or hostname scans. Present when
+    /// the checks ran.
+    #[serde(default)]
+    pub dns_sanity: Option<DnsSanityReport>,
+
+    /// How and by what the scan was run. Shared by every result of one invocation.
+    #[serde(default)]
+    pub metadata: Option<ScanMetadata>,
+
+    /// Probes not sent because `--max-scan-time` ran out, by port. A port listed here
+    /// but absent from `results` was never probed; its state is unknown, not filtered.
+    #[serde(default, skip_serializing_if = "HashMap::is_empty")]
+    pub skipped_probes: HashMap<u16, Vec<ScanType>>,
+
+    /// Optional phases (adaptive ports, ICMP probing, VPN detection, credential checks,
+    /// scripts) left out to stay within `--max-scan-time`
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub skipped_phases: Vec<String>,
+
+    /// Owner, environment, criticality and tags from `--asset-file`. Absent when no
+    /// asset file was given or no entry covers the host.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub asset: Option<AssetInfo>,
+
+    /// Ways this host differs from the other hosts of the run (a port only it has open,
+    /// an odd service or version). Only filled for runs with enough hosts to compare.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub outliers: Vec<HostOutlier>,
+
+    /// Differences from the expected inventory given with `--baseline`
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub deviations: Vec<BaselineDeviation>,
+
+    /// Signs that the host is a CDN or load-balancer edge rather than the origin server.
+    /// When set, ports, banners and certificates describe the edge.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub edge: Option<EdgeInfo>,
+
+    /// Cloud provider the host appears to run in, for follow-up testing of its metadata service
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub cloud: Option<CloudInfo>,
+
+    /// Signs that an inline IPS or firewall forged replies during the scan. The ports
+    /// they cover are marked `unreliable`.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub interference: Vec<InterferenceSign>,
+
+    /// Runs combined into this record by `quantum_scanner merge`, oldest first (run IDs,
+    /// or end times for results saved without metadata)
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub merged_runs: Vec<String>,
+}
+
+/// Evidence that something on the path, not the host, answered the probes
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct InterferenceSign {
+    /// What gave the appliance away
+    pub kind: InterferenceKind,
+
+    /// Human-readable explanation with the numbers behind it
+    pub detail: String,
+
+    /// Ports whose replies the sign casts doubt on
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub ports: Vec<u16>,
+}
+
+/// Kinds of reply forgery spotted mid-scan
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum InterferenceKind {
+    /// RSTs whose TTL or window does not match the host's own stack
+    ForgedRst,
+    /// Nearly every probed port completed the handshake
+    SynAckEverywhere,
+    /// Replies arrive with the same delay regardless of port
+    UniformTiming,
+}
+
+impl fmt::Display for InterferenceKind {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            InterferenceKind::ForgedRst => write!(f, "forged RSTs"),
+            InterferenceKind::SynAckEverywhere => write!(f, "SYN-ACK on every port"),
+            InterferenceKind::UniformTiming => write!(f, "uniform reply timing"),
+        }
+    }
+}
+
+/// Evidence that a scanned address is CDN or load-balancer infrastructure
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct EdgeInfo {
+    /// CDN or plain load balancer
+    pub kind: EdgeKind,
+
+    /// Provider, when the evidence names one
+    pub provider: Option<String>,
+
+    /// Human-readable signals that point to the edge
+    pub evidence: Vec<String>,
+
+    /// Every address the hostname resolved to
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub addresses: Vec<IpAddr>,
+
+    /// Shortest TTL of the DNS answers, in seconds
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub dns_ttl: Option<u32>,
+
+    /// Addresses that may be the origin behind the edge, from `--find-origin`
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub origin_candidates: Vec<OriginCandidate>,
+}
+
+/// Cloud provider a host was tagged with, and where to aim SSRF follow-up
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CloudInfo {
+    /// Provider name (e.g., "AWS", "Azure", "GCP")
+    pub provider: String,
+
+    /// Human-readable signals that point to the provider
+    pub evidence: Vec<String>,
+
+    /// The provider's instance metadata service, as an SSRF would reach it
+    pub metadata_endpoint: String,
+
+    /// Ports that relay the metadata service, name it, or redirect through URL parameters
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub ssrf_candidates: Vec<u16>,
+}
+
+/// An address that may be the origin server behind an edge
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct OriginCandidate {
+    pub ip: IpAddr,
+
+    /// Techniques that point to it, as human-readable explanations
+    pub evidence: Vec<String>,
+}
+
+impl fmt::Display for EdgeInfo {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match &self.provider {
+            Some(provider) => write!(f, "{} {}", provider, self.kind),
+            None => write!(f, "{}", self.kind),
+        }
+    }
+}
+
+/// Kinds of edge infrastructure
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum EdgeKind {
+    Cdn,
+    LoadBalancer,
+}
+
+impl fmt::Display for EdgeKind {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            EdgeKind::Cdn => write!(f, "CDN"),
+            EdgeKind::LoadBalancer => write!(f, "load balancer"),
+        }
+    }
+}
+
+/// A difference between a host and its known-good baseline
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct BaselineDeviation {
+    /// Port the difference was seen on
+    pub port: u16,
+
+    /// What kind of difference it is
+    pub kind: DeviationKind,
+
+    /// Human-readable explanation, naming what the baseline expected
+    pub detail: String,
+}
+
+/// Kinds of difference from a known-good baseline
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum DeviationKind {
+    /// Open, but not in the baseline
+    UnexpectedPort,
+    /// In the baseline, but not open
+    MissingPort,
+    /// Open with another service than the baseline lists
+    ServiceChanged,
+    /// Open with another version than the baseline lists
+    VersionChanged,
+}
+
+/// Something that sets a host apart from the rest of the scanned population
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct HostOutlier {
+    /// Port the difference was seen on
+    pub port: u16,
+
+    /// What kind of difference it is
+    pub kind: OutlierKind,
+
+    /// Human-readable explanation, naming the population's norm
+    pub detail: String,
+}
+
+/// Kinds of difference from the population baseline
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum OutlierKind {
+    /// No other host has this port open
+    UniquePort,
+    /// Most hosts run another service on this port
+    OddService,
+    /// Most hosts run another version of this service
+    OddVersion,
+}
+
+/// What the operator knows about a host, from `--asset-file`
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct AssetInfo {
+    /// Team or person responsible for the host
+    pub owner: Option<String>,
+
+    /// Environment the host belongs to (e.g. production, staging)
+    pub environment: Option<String>,
+
+    /// How much the host matters; weights its risk assessment
+    pub criticality: Option<Criticality>,
+
+    /// Free-form labels, e.g. `pci` or `dmz`
+    #[serde(default)]
+    pub tags: Vec<String>,
+}
+
+impl fmt::Display for AssetInfo {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let mut parts = Vec::new();
+        if let Some(owner) = &self.owner {
+            parts.push(format!("owner {}", owner));
+        }
+        if let Some(environment) = &self.environment {
+            parts.push(format!("environment {}", environment));
+        }
+        if let Some(criticality) = &self.criticality {
+            parts.push(format!("criticality {}", criticality));
+        }
+        if !self.tags.is_empty() {
+            parts.push(format!("tags {}", self.tags.join(", ")));
+        }
+        write!(f, "{}", parts.join("; "))
+    }
+}
+
+/// Business criticality of an asset
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Criticality {
+    Low,
+    Medium,
+    High,
+    Critical,
+}
+
+impl Criticality {
+    /// Factor applied to a host's risk score
+    pub fn weight(&self) -> f64 {
+        match self {
+            Criticality::Low => 0.5,
+            Criticality::Medium => 1.0,
+            Criticality::High => 1.25,
+            Criticality::Critical => 1.5,
+        }
+    }
+}
+
+impl fmt::Display for Criticality {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            Criticality::Low => "low",
+            Criticality::Medium => "medium",
+            Criticality::High => "high",
+            Criticality::Critical => "critical",
+        })
+    }
+}
+
+/// Provenance of a scan run, for reproducibility and chain of custody
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ScanMetadata {
+    /// Random UUID identifying the invocation
+    pub run_id: String,
+
+    /// Tool name and version that produced the results
+    pub tool_name: String,
+    pub tool_version: String,
+
+    /// When the invocation started (individual hosts have their own start times)
+    pub started_at: DateTime<Utc>,
+
+    /// Command line as given, with secret values replaced by [REDACTED]
+    pub command_line: Vec<String>,
+
+    /// SHA-256 over the tool version and the effective configuration, defaults included
+    pub config_hash: String,
 }
 
-/// Represents information about a potential vulnerability detected.
+/// Behaviour of the resolver path, checked before relying on DNS
 ///
-/// Storing identified vulnerabilities is key for reporting. 
-/// OpSec Note: The method of vulnerability detection (e.g., specific probes vs. banner/version matching)
-/// can affect stealth. Simple version matching is less intrusive.
+/// Captive portals, ISP "search assist" resolvers and transparent DNS proxies answer
+/// queries they should not, which silently corrupts hostname resolution and DNS tunnel
+/// results. Addresses listed here are placeholders rather than real hosts.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct DnsSanityReport {
+    /// Resolver that was checked
+    pub resolver: Option<IpAddr>,
+
+    /// Addresses returned for a name that cannot exist. Non-empty means the resolver
+    /// rewrites NXDOMAIN.
+    pub nxdomain_rewrite: Vec<IpAddr>,
+
+    /// Domains answering for random subdomains, with the addresses returned
+    pub wildcard_domains: HashMap<String, Vec<IpAddr>>,
+
+    /// A query sent to an address with no DNS server was answered, so something on the
+    /// path intercepts DNS traffic
+    pub intercepted: bool,
+
+    /// Consequences for this scan, in readable form
+    pub warnings: Vec<String>,
+}
+
+/// Host characterization from ICMP information requests and error messages
+///
+/// ICMP behaviour differs a lot between stacks (which requests are answered, how
+/// much of the offending packet an error quotes, initial TTLs), which makes it a
+/// useful complement to port-based OS fingerprinting.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct IcmpHostInfo {
+    /// Whether the host answered an ICMP timestamp request (type 13)
+    pub timestamp_reply: bool,
+    
+    /// Estimated offset of the host clock from ours in milliseconds (remote - local)
+    pub clock_offset_ms: Option<i64>,
+    
+    /// Timestamp reply used the "non-standard" format (high bit set) instead of
+    /// milliseconds since midnight UTC
+    pub nonstandard_timestamp: bool,
+    
+    /// Subnet mask returned by an address mask request (type 17), if answered
+    pub netmask: Option<String>,
+    
+    /// TTL of the first ICMP reply, used to estimate the initial TTL of the stack
+    pub reply_ttl: Option<u8>,
+    
+    /// Analysis of the ICMP error returned for a closed UDP port
+    pub udp_unreachable: Option<UnreachableAnalysis>,
+    
+    /// OS and device hints derived from the above
+    pub os_hints: Vec<String>,
+}
+
+/// Probe timing calibrated for one host before its scan
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct HostTiming {
+    /// Calibration probes that got a SYN-ACK or RST
+    pub samples: u32,
+
+    /// Calibration probes that got no reply within the global timeout
+    pub lost: u32,
+
+    /// Smoothed round-trip time in milliseconds (RFC 6298), if any probe was answered
+    pub srtt_ms: Option<f64>,
+
+    /// Round-trip time variation in milliseconds
+    pub rttvar_ms: Option<f64>,
+
+    /// Timeout used for this host's probes in milliseconds
+    pub probe_timeout_ms: u64,
+
+    /// Retransmissions for probes that got no reply
+    pub retries: u8,
+}
+
+/// TCP techniques chosen for one host by the `--auto-technique` pre-probe
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct TechniqueSelection {
+    /// Initial TTL inferred from the ICMP reply TTL (64, 128 or 255)
+    pub initial_ttl: Option<u8>,
+
+    /// Stack family the initial TTL points to
+    pub os_family: Option<String>,
+
+    /// TCP techniques run against the host
+    pub scan_types: Vec<ScanType>,
+
+    /// Observations behind the choice, in probe order
+    pub reasons: Vec<String>,
+}
+
+/// Details of an ICMP destination unreachable message elicited by a UDP probe
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct UnreachableAnalysis {
+    /// UDP port that was probed
+    pub port: u16,
+    
+    /// ICMP code (type is always 3)
+    pub icmp_code: u8,
+    
+    /// What the code means (port unreachable, administratively prohibited...)
+    pub meaning: String,
+    
+    /// Address that sent the error; differs from the target when a router or firewall answers
+    pub source: String,
+    
+    /// TTL of the ICMP error
+    pub reply_ttl: u8,
+    
+    /// Don't Fragment bit on the ICMP error
+    pub dont_fragment: bool,
+    
+    /// Bytes of the original datagram quoted after its IP header (8 = RFC 792 minimum)
+    pub quoted_bytes: usize,
+    
+    /// Whether the quoted IP total length and UDP length match what was sent
+    pub quote_intact: bool,
+}
+
+/// Result of probing a single IP protocol number
 #[derive(Debug, Clone, Serialize, Deserialize)]
-pub struct VulnInfo {
-    /// Vulnerability identifier (e.g., CVE-2021-44228, OSVDB-12345).
+pub struct IpProtocolResult {
+    /// Well-known name of the protocol (e.g., "GRE", "ESP")
+    pub name: String,
+    
+    /// Observed state. `Open` means the host answered in that protocol, `Closed` means
+    /// it sent ICMP protocol unreachable, `OpenFiltered` means nothing came back.
+    pub status: PortStatus,
+    
+    /// Evidence behind the state
+    pub reason: String,
+}
+
+/// Default IP protocols probed by `ScanType::IpProto`
+///
+/// Covers the protocols that matter for exposure: ICMP/IGMP, IP-in-IP and IPv6-in-IPv4
+/// tunnels, GRE, IPsec (ESP/AH), routing protocols, L2TPv3, SCTP and friends.
+pub const DEFAULT_IP_PROTOCOLS: &[u8] = &[1, 2, 4, 6, 17, 41, 47, 50, 51, 89, 103, 112, 115, 132, 136, 137];
+
+/// Get the well-known name of an IP protocol number
+pub fn ip_protocol_name(protocol: u8) -> &'static str {
+    match protocol {
+        0 => "HOPOPT",
+        1 => "ICMP",
+        2 => "IGMP",
+        4 => "IPv4-in-IPv4",
+        6 => "TCP",
+        8 => "EGP",
+        9 => "IGP",
+        17 => "UDP",
+        27 => "RDP",
+        33 => "DCCP",
+        41 => "IPv6-in-IPv4",
+        46 => "RSVP",
+        47 => "GRE",
+        50 => "ESP",
+        51 => "AH",
+        55 => "MOBILE",
+        58 => "IPv6-ICMP",
+        88 => "EIGRP",
+        89 => "OSPF",
+        94 => "IPIP",
+        97 => "ETHERIP",
+        98 => "ENCAP",
+        103 => "PIM",
+        108 => "IPComp",
+        112 => "VRRP",
+        115 => "L2TP",
+        132 => "SCTP",
+        136 => "UDPLite",
+        137 => "MPLS-in-IP",
+        _ => "unknown",
+    }
+}
+
+/// Parse an IP protocol specification like "1,6,17,47-51"
+///
+/// # Arguments
+/// * `spec` - Comma-separated protocol numbers or ranges (0-255)
+///
+/// # Returns
+/// * `Result<Vec<u8>, PortRangeError>` - Deduplicated protocol numbers in the given order
+pub fn parse_ip_protocols(spec: &str) -> Result<Vec<u8>, PortRangeError> {
+    let mut protocols = Vec::new();
+    for part in spec.split(',').map(str::trim).filter(|p| !p.is_empty()) {
+        let (start, end) = match part.split_once('-') {
+            Some((start, end)) => (start.trim(), end.trim()),
+            None => (part, part),
+        };
+        let start: u8 = start.parse().map_err(|_| PortRangeError::InvalidFormat(format!("Invalid IP protocol: {}", part)))?;
+        let end: u8 = end.parse().map_err(|_| PortRangeError::InvalidFormat(format!("Invalid IP protocol: {}", part)))?;
+        if start > end {
+            return Err(PortRangeError::InvalidFormat(format!("Invalid IP protocol range: {}", part)));
+        }
+        for protocol in start..=end {
+            if !protocols.contains(&protocol) {
+                protocols.push(protocol);
+            }
+        }
+    }
+    if protocols.is_empty() {
+        return Err(PortRangeError::InvalidFormat("No IP protocols specified".to_string()));
+    }
+    Ok(protocols)
+}
+
+/// How sure a check is that a finding is real
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, PartialOrd, Ord, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Confidence {
+    /// The service was seen doing what the finding describes (a relay, an accepted login)
+    Confirmed,
+    /// Read from what the service itself reports (advertised features, negotiation replies)
+    #[default]
+    Firm,
+    /// Inferred from version strings, port numbers or heuristics
+    Tentative,
+}
+
+impl Confidence {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Confidence::Confirmed => "confirmed",
+            Confidence::Firm => "firm",
+            Confidence::Tentative => "tentative",
+        }
+    }
+}
+
+impl fmt::Display for Confidence {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+/// A security finding on one port: a vulnerability or an anomaly.
+///
+/// Findings carry their own structure so downstream tooling can group, filter and
+/// deduplicate them without parsing the description.
+/// OpSec Note: The method of vulnerability detection (e.g., specific probes vs. banner/version matching)
+/// can affect stealth. Simple version matching is less intrusive; `confidence` says which one was used.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct Finding {
+    /// Finding identifier (e.g., CVE-2021-44228, OPEN-PROXY).
     /// Standard identifiers help in referencing external databases.
     pub id: String,
 
-    /// Description of the vulnerability.
-    /// Provides context about the potential issue.
+    /// Short name of the issue, the same for every occurrence of `id`
+    #[serde(default)]
+    pub title: String,
+
+    /// What was found on this port, in a sentence.
     pub description: String,
 
     /// Severity assessment (e.g., "Critical", "High", "Medium", "Low", "Info").
     /// Helps prioritize findings.
     pub severity: String,
-    // Potential future fields: CVSS score, references, remediation steps
+
+    /// How the finding was established
+    #[serde(default)]
+    pub confidence: Confidence,
+
+    /// Observations the finding rests on: replies, headers, negotiated values
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub evidence: Vec<String>,
+
+    /// Advisories, CVEs and documentation about the issue
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub references: Vec<String>,
+
+    /// How to fix the finding, when the check knows. Reports show it next to the
+    /// description so analysts don't have to look it up.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub remediation: Option<String>,
+}
+
+impl Finding {
+    /// Unusual behaviour worth a look, recorded as a tentative Info finding
+    pub fn anomaly(description: impl Into<String>) -> Self {
+        Finding {
+            id: "ANOMALY".to_string(),
+            title: "Anomalous behaviour".to_string(),
+            description: description.into(),
+            severity: "Info".to_string(),
+            confidence: Confidence::Tentative,
+            ..Default::default()
+        }
+    }
+
+    /// `title` if set, else `id`; findings from scripts and plugins may only have an id
+    pub fn display_title(&self) -> &str {
+        if self.title.is_empty() { &self.id } else { &self.title }
+    }
+}
+
+/// Findings a post-detection script reported for a port
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ScriptOutput {
+    /// Script name (file name without extension)
+    pub script: String,
+
+    /// Free-form findings, in the order the script added them
+    pub findings: Vec<String>,
 }
 
-// Implement the Display trait for VulnInfo for user-friendly printing.
-impl fmt::Display for VulnInfo {
+/// Raw reply of a service, as captured with `--capture-responses`
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CapturedResponse {
+    /// Captured bytes, hex-encoded; at most the configured capture size
+    pub hex: String,
+
+    /// Bytes the service actually sent
+    pub total_bytes: usize,
+
+    /// Whether the reply was longer than the capture size
+    pub truncated: bool,
+}
+
+impl CapturedResponse {
+    /// Capture the first `limit` bytes of `data`
+    pub fn new(data: &[u8], limit: usize) -> Self {
+        let kept = &data[..data.len().min(limit)];
+        Self {
+            hex: kept.iter().map(|b| format!("{:02x}", b)).collect(),
+            total_bytes: data.len(),
+            truncated: data.len() > limit,
+        }
+    }
+}
+
+// Implement the Display trait for Finding for user-friendly printing.
+impl fmt::Display for Finding {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
         // Format: "[Severity] ID: Description"
         // Example: "[High] CVE-2021-44228: Apache Log4j Remote Code Execution"
@@ -471,6 +1526,17 @@ pub enum PortRangeError {
     RangeStartGreaterThanEnd(u16, u16),
 }
 
+/// Transport protocol a port specification applies to (from `T:`/`U:` prefixes)
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PortProtocol {
+    /// No prefix: the port is scanned with both TCP and UDP scan types
+    Both,
+    /// `T:` prefix: TCP scan types only
+    Tcp,
+    /// `U:` prefix: UDP scan types only
+    Udp,
+}
+
 /// Represents a single port or a range of ports
 ///
 /// Used to efficiently parse and represent port specifications
@@ -485,7 +1551,9 @@ pub enum PortRange {
 }
 
 impl PortRange {
-    /// Parse a string like "80,443,8000-9000" into a vector of PortRange
+    /// Parse a string like "80,443,8000-9000" or "ssh,https,8000-9000" into a vector of PortRange
+    ///
+    /// Service names are resolved through `ServicePorts` and expand to single ports.
     ///
     /// # Arguments
     /// * `port_str` - String containing port specifications
@@ -503,7 +1571,26 @@ impl PortRange {
     /// // - Range(8000, 8100)
     /// ```
     pub fn parse(port_str: &str) -> Result<Vec<Self>, PortRangeError> {
+        Ok(Self::parse_with_protocols(port_str)?
+            .into_iter()
+            .map(|(range, _)| range)
+            .collect())
+    }
+
+    /// Parse a port specification, keeping track of nmap-style protocol prefixes
+    ///
+    /// A `T:` or `U:` prefix switches all following ports to TCP or UDP respectively,
+    /// e.g. "22,T:80,443,U:53,161" gives 22 for both protocols, 80/443 for TCP and
+    /// 53/161 for UDP. Ports before any prefix apply to both protocols.
+    ///
+    /// # Arguments
+    /// * `port_str` - String containing port specifications
+    ///
+    /// # Returns
+    /// * `Result<Vec<(Self, PortProtocol)>, PortRangeError>` - Ranges tagged with their protocol
+    pub fn parse_with_protocols(port_str: &str) -> Result<Vec<(Self, PortProtocol)>, PortRangeError> {
         let mut ranges = Vec::new();
+        let mut protocol = PortProtocol::Both;
         
         // Check for empty input
         if port_str.trim().is_empty() {
@@ -516,6 +1603,35 @@ impl PortRange {
                 continue;
             }
             
+            // Protocol prefix ("T:80", "U:53-60") applies to this and all following parts
+            let part = match part.split_once(':') {
+                Some((prefix, rest)) => {
+                    protocol = match prefix.to_ascii_uppercase().as_str() {
+                        "T" => PortProtocol::Tcp,
+                        "U" => PortProtocol::Udp,
+                        _ => return Err(PortRangeError::InvalidFormat(
+                            format!("Unsupported protocol prefix '{}:' (expected T: or U:)", prefix)
+                        )),
+                    };
+                    let rest = rest.trim();
+                    if rest.is_empty() {
+                        continue;
+                    }
+                    rest
+                }
+                None => part,
+            };
+            
+            // Service name (e.g. "ssh", "http-alt") - expand to its ports.
+            // Checked before ranges since some names contain a dash.
+            if part.chars().next().map_or(false, |c| c.is_ascii_alphabetic()) {
+                match ServicePorts::lookup(part) {
+                    Some(ports) => ranges.extend(ports.iter().map(|&port| (PortRange::Single(port), protocol))),
+                    None => return Err(PortRangeError::InvalidPort(format!("{} (unknown service name)", part))),
+                }
+                continue;
+            }
+            
             if part.contains('-') {
                 // Parse as range
                 let mut parts = part.split('-');
@@ -553,11 +1669,11 @@ impl PortRange {
                     ));
                 }
                 
-                ranges.push(PortRange::Range(start, end));
+                ranges.push((PortRange::Range(start, end), protocol));
             } else {
                 // Parse as single port
                 match part.parse::<u16>() {
-                    Ok(port) => ranges.push(PortRange::Single(port)),
+                    Ok(port) => ranges.push((PortRange::Single(port), protocol)),
                     Err(_) => return Err(PortRangeError::InvalidPort(part.to_string())),
                 }
             }
@@ -628,6 +1744,52 @@ impl Iterator for PortRangeIterator {
     }
 }
 
+/// Per-protocol port sets from a specification using `T:`/`U:` prefixes
+#[derive(Debug, Clone, Default)]
+pub struct ProtocolPorts {
+    /// Ports to probe with TCP scan types
+    pub tcp: Vec<u16>,
+    /// Ports to probe with UDP scan types
+    pub udp: Vec<u16>,
+}
+
+impl ProtocolPorts {
+    /// Split a port specification into TCP and UDP port lists
+    ///
+    /// # Returns
+    /// * `Result<Option<Self>, PortRangeError>` - None if the spec has no protocol
+    ///   prefixes (every port applies to both protocols)
+    pub fn parse(port_str: &str) -> Result<Option<Self>, PortRangeError> {
+        let tagged = PortRange::parse_with_protocols(port_str)?;
+        if tagged.iter().all(|(_, protocol)| *protocol == PortProtocol::Both) {
+            return Ok(None);
+        }
+
+        let mut ports = ProtocolPorts::default();
+        let mut seen_tcp = HashSet::new();
+        let mut seen_udp = HashSet::new();
+        for (range, protocol) in tagged {
+            for port in PortRanges::new(vec![range]) {
+                if protocol != PortProtocol::Udp && seen_tcp.insert(port) {
+                    ports.tcp.push(port);
+                }
+                if protocol != PortProtocol::Tcp && seen_udp.insert(port) {
+                    ports.udp.push(port);
+                }
+            }
+        }
+        Ok(Some(ports))
+    }
+
+    /// Ports applicable to a given scan type
+    pub fn for_scan_type(&self, scan_type: ScanType) -> &[u16] {
+        match scan_type {
+            ScanType::Udp => &self.udp,
+            _ => &self.tcp,
+        }
+    }
+}
+
 /// Wrapper struct for Vec<PortRange> to avoid orphan rule issues
 ///
 /// Allows implementing IntoIterator for a collection of PortRange objects.
@@ -767,6 +1929,220 @@ impl TopPorts {
     pub fn top_10() -> Vec<u16> {
         vec![80, 443, 22, 21, 25, 3389, 110, 445, 139, 143]
     }
+
+    /// Get the top 1000 most common TCP ports to scan
+    pub fn top_1000() -> Vec<u16> {
+        Self::top_n(1000)
+    }
+
+    /// Get the N most common TCP ports, most frequent first
+    ///
+    /// Ordering follows the embedded frequency table: the top 100 in frequency order,
+    /// then the rest of the top 1000 (the same set nmap's `--top-ports 1000` scans).
+    /// Requests beyond 1000 are padded with the remaining ports in ascending order.
+    ///
+    /// # Arguments
+    /// * `n` - Number of ports to return (capped at 65535)
+    pub fn top_n(n: usize) -> Vec<u16> {
+        let mut ports = Self::top_100();
+        let mut seen: HashSet<u16> = ports.iter().copied().collect();
+
+        // The embedded spec is a constant, so a parse failure is a programming error
+        let ranges = PortRange::parse(&TOP_1000_TCP_PORTS.concat())
+            .expect("embedded top-ports table is valid");
+        for port in PortRanges::new(ranges) {
+            if seen.insert(port) {
+                ports.push(port);
+            }
+        }
+
+        if n > ports.len() {
+            for port in 1..=u16::MAX {
+                if ports.len() >= n {
+                    break;
+                }
+                if seen.insert(port) {
+                    ports.push(port);
+                }
+            }
+        }
+
+        ports.truncate(n);
+        ports
+    }
+
+    /// Get the N most frequent ports from a user-supplied frequency file
+    ///
+    /// Accepts either the nmap-services format (`name port/proto frequency`) or a
+    /// simple `port [frequency]` per line. Lines starting with `#` are ignored. Only
+    /// TCP entries are used from nmap-services files. Ports without a frequency
+    /// keep their file order after all weighted entries.
+    ///
+    /// # Arguments
+    /// * `path` - Path to the frequency file
+    /// * `n` - Number of ports to return
+    ///
+    /// # Returns
+    /// * `Result<Vec<u16>, PortRangeError>` - Ports ordered by descending frequency
+    pub fn from_frequency_file(path: &std::path::Path, n: usize) -> Result<Vec<u16>, PortRangeError> {
+        let contents = std::fs::read_to_string(path)
+            .map_err(|e| PortRangeError::InvalidFormat(format!("{}: {}", path.display(), e)))?;
+
+        let mut entries: Vec<(u16, f64, usize)> = Vec::new();
+        let mut seen = HashSet::new();
+
+        for (line_no, line) in contents.lines().enumerate() {
+            let line = line.split('#').next().unwrap_or("").trim();
+            if line.is_empty() {
+                continue;
+            }
+            let fields: Vec<&str> = line.split_whitespace().collect();
+
+            // nmap-services: "http 80/tcp 0.484143 # comment"
+            let (port_field, freq_field) = if fields.len() >= 2 && fields[1].contains('/') {
+                if !fields[1].ends_with("/tcp") {
+                    continue;
+                }
+                (fields[1].trim_end_matches("/tcp"), fields.get(2))
+            } else {
+                (fields[0], fields.get(1))
+            };
+
+            let port = port_field.parse::<u16>()
+                .map_err(|_| PortRangeError::InvalidPort(format!("{} (line {})", port_field, line_no + 1)))?;
+            if port == 0 {
+                return Err(PortRangeError::InvalidPort(format!("0 (line {})", line_no + 1)));
+            }
+            let frequency = match freq_field {
+                Some(f) => f.parse::<f64>()
+                    .map_err(|_| PortRangeError::InvalidFormat(format!("bad frequency '{}' on line {}", f, line_no + 1)))?,
+                None => 0.0,
+            };
+
+            if seen.insert(port) {
+                entries.push((port, frequency, line_no));
+            }
+        }
+
+        if entries.is_empty() {
+            return Err(PortRangeError::InvalidFormat(format!("{} contains no ports", path.display())));
+        }
+
+        // Highest frequency first; file order breaks ties
+        entries.sort_by(|a, b| b.1.partial_cmp(&a.1).unwrap_or(std::cmp::Ordering::Equal).then(a.2.cmp(&b.2)));
+        Ok(entries.into_iter().take(n).map(|(port, _, _)| port).collect())
+    }
+}
+
+/// The 1000 most common TCP ports (same set as nmap's default scan), as a port spec
+const TOP_1000_TCP_PORTS: &[&str] = &[
+    "1,3-4,6-7,9,13,17,19-26,30,32-33,37,42-43,49,53,70,79-85,88-90,99-100,106,109-111,113,119,125,",
+    "135,139,143-144,146,161,163,179,199,211-212,222,254-256,259,264,280,301,306,311,340,366,389,",
+    "406-407,416-417,425,427,443-445,458,464-465,481,497,500,512-515,524,541,543-545,548,554-555,563,",
+    "587,593,616-617,625,631,636,646,648,666-668,683,687,691,700,705,711,714,720,722,726,749,765,777,",
+    "783,787,800-801,808,843,873,880,888,898,900-903,911-912,981,987,990,992-993,995,999-1002,1007,",
+    "1009-1011,1021-1100,1102,1104-1108,1110-1114,1117,1119,1121-1124,1126,1130-1132,1137-1138,1141,",
+    "1145,1147-1149,1151-1152,1154,1163-1166,1169,1174-1175,1183,1185-1187,1192,1198-1199,1201,1213,",
+    "1216-1218,1233-1234,1236,1244,1247-1248,1259,1271-1272,1277,1287,1296,1300-1301,1309-1311,1322,",
+    "1328,1334,1352,1417,1433-1434,1443,1455,1461,1494,1500-1501,1503,1521,1524,1533,1556,1580,1583,",
+    "1594,1600,1641,1658,1666,1687-1688,1700,1717-1721,1723,1755,1761,1782-1783,1801,1805,1812,",
+    "1839-1840,1862-1864,1875,1900,1914,1935,1947,1971-1972,1974,1984,1998-2010,2013,2020-2022,2030,",
+    "2033-2035,2038,2040-2043,2045-2049,2065,2068,2099-2100,2103,2105-2107,2111,2119,2121,2126,2135,",
+    "2144,2160-2161,2170,2179,2190-2191,2196,2200,2222,2251,2260,2288,2301,2323,2366,2381-2383,",
+    "2393-2394,2399,2401,2492,2500,2522,2525,2557,2601-2602,2604-2605,2607-2608,2638,2701-2702,2710,",
+    "2717-2718,2725,2800,2809,2811,2869,2875,2909-2910,2920,2967-2968,2998,3000-3001,3003,3005-3007,",
+    "3011,3013,3017,3030-3031,3052,3071,3077,3128,3168,3211,3221,3260-3261,3268-3269,3283,3300-3301,",
+    "3306,3322-3325,3333,3351,3367,3369-3372,3389-3390,3404,3476,3493,3517,3527,3546,3551,3580,3659,",
+    "3689-3690,3703,3737,3766,3784,3800-3801,3809,3814,3826-3828,3851,3869,3871,3878,3880,3889,3905,",
+    "3914,3918,3920,3945,3971,3986,3995,3998,4000-4006,4045,4111,4125-4126,4129,4224,4242,4279,4321,",
+    "4343,4443-4446,4449,4550,4567,4662,4848,4899-4900,4998,5000-5004,5009,5030,5033,5050-5051,5054,",
+    "5060-5061,5080,5087,5100-5102,5120,5190,5200,5214,5221-5222,5225-5226,5269,5280,5298,5357,5405,",
+    "5414,5431-5432,5440,5500,5510,5544,5550,5555,5560,5566,5631,5633,5666,5678-5679,5718,5730,",
+    "5800-5802,5810-5811,5815,5822,5825,5850,5859,5862,5877,5900-5904,5906-5907,5910-5911,5915,5922,",
+    "5925,5950,5952,5959-5963,5987-5989,5998-6007,6009,6025,6059,6100-6101,6106,6112,6123,6129,6156,",
+    "6346,6389,6502,6510,6543,6547,6565-6567,6580,6646,6666-6669,6689,6692,6699,6779,6788-6789,6792,",
+    "6839,6881,6901,6969,7000-7002,7004,7007,7019,7025,7070,7100,7103,7106,7200-7201,7402,7435,7443,",
+    "7496,7512,7625,7627,7676,7741,7777-7778,7800,7911,7920-7921,7937-7938,7999-8002,8007-8011,",
+    "8021-8022,8031,8042,8045,8080-8090,8093,8099-8100,8180-8181,8192-8194,8200,8222,8254,8290-8292,",
+    "8300,8333,8383,8400,8402,8443,8500,8600,8649,8651-8652,8654,8701,8800,8873,8888,8899,8994,",
+    "9000-9003,9009-9011,9040,9050,9071,9080-9081,9090-9091,9099-9103,9110-9111,9200,9207,9220,9290,",
+    "9415,9418,9485,9500,9502-9503,9535,9575,9593-9595,9618,9666,9876-9878,9898,9900,9917,9929,",
+    "9943-9944,9968,9998-10004,10009-10010,10012,10024-10025,10082,10180,10215,10243,10566,",
+    "10616-10617,10621,10626,10628-10629,10778,11110-11111,11967,12000,12174,12265,12345,13456,13722,",
+    "13782-13783,14000,14238,14441-14442,15000,15002-15004,15660,15742,16000-16001,16012,16016,16018,",
+    "16080,16113,16992-16993,17877,17988,18040,18101,18988,19101,19283,19315,19350,19780,19801,19842,",
+    "20000,20005,20031,20221-20222,20828,21571,22939,23502,24444,24800,25734-25735,26214,27000,",
+    "27352-27353,27355-27356,27715,28201,30000,30718,30951,31038,31337,32768-32785,33354,33899,",
+    "34571-34573,35500,38292,40193,40911,41511,42510,44176,44442-44443,44501,45100,48080,49152-49161,",
+    "49163,49165,49167,49175-49176,49400,49999-50003,50006,50300,50389,50500,50636,50800,51103,51493,",
+    "52673,52822,52848,52869,54045,54328,55055-55056,55555,55600,56737-56738,57294,57797,58080,60020,",
+    "60443,61532,61900,62078,63331,64623,64680,65000,65129,65389",
+];
+
+/// Related port sets used by adaptive port selection
+///
+/// When a port is found open, the ports that usually accompany it on the same kind of
+/// host are worth probing too (e.g. an alternate web port suggests a whole range of
+/// development/admin web servers, SMB suggests the rest of the Windows service set).
+#[derive(Debug)]
+pub struct AdaptivePorts;
+
+impl AdaptivePorts {
+    /// Web ports commonly used for alternates, admin panels and dev servers
+    const WEB: &'static [u16] = &[80, 81, 443, 591, 3000, 4443, 5000, 7001, 7443, 8008, 8081, 8088, 8181, 8443, 8888, 9000, 9090, 9443, 10443];
+    /// Services typically exposed by Windows hosts and domain controllers
+    const WINDOWS: &'static [u16] = &[53, 88, 135, 137, 138, 139, 389, 445, 464, 593, 636, 3268, 3269, 3389, 5985, 5986, 9389, 47001];
+    /// Database servers and their admin/replication ports
+    const DATABASE: &'static [u16] = &[1433, 1434, 1521, 2483, 2484, 3306, 5432, 5984, 6379, 7000, 7001, 8529, 9042, 9200, 9300, 11211, 27017, 27018, 27019, 28017];
+    /// Mail service family
+    const MAIL: &'static [u16] = &[25, 110, 143, 465, 587, 993, 995, 2525];
+    /// Remote administration
+    const REMOTE_ADMIN: &'static [u16] = &[22, 23, 2222, 3389, 5800, 5900, 5901, 5902, 5985, 5986];
+    /// Container and orchestration APIs
+    const CONTAINER: &'static [u16] = &[2375, 2376, 2379, 2380, 5000, 6443, 8001, 10250, 10255, 10256, 30000];
+
+    /// Get the ports related to an open port
+    ///
+    /// # Arguments
+    /// * `port` - A port found open
+    ///
+    /// # Returns
+    /// * `Vec<u16>` - Related ports worth probing (may include the port itself)
+    pub fn related(port: u16) -> Vec<u16> {
+        match port {
+            // Alternate HTTP port found: sweep the whole 8000-8999 web range
+            8000..=8999 => {
+                let mut ports: Vec<u16> = (8000..=8999).collect();
+                ports.extend_from_slice(Self::WEB);
+                ports
+            }
+            80 | 81 | 443 | 591 | 3000 | 4443 | 5000 | 7443 | 9000 | 9090 | 9443 | 10443 => Self::WEB.to_vec(),
+            135 | 137 | 139 | 445 | 389 | 636 | 88 | 3268 | 5985 | 5986 => Self::WINDOWS.to_vec(),
+            1433 | 1521 | 3306 | 5432 | 6379 | 9200 | 11211 | 27017 => Self::DATABASE.to_vec(),
+            25 | 110 | 143 | 465 | 587 | 993 | 995 => Self::MAIL.to_vec(),
+            22 | 23 | 3389 | 5900 => Self::REMOTE_ADMIN.to_vec(),
+            2375 | 2376 | 2379 | 6443 | 10250 => Self::CONTAINER.to_vec(),
+            _ => Vec::new(),
+        }
+    }
+
+    /// Expand a set of open ports into related ports that have not been scanned yet
+    ///
+    /// # Arguments
+    /// * `open_ports` - Ports found open so far
+    /// * `already_scanned` - Ports that were already probed (excluded from the result)
+    ///
+    /// # Returns
+    /// * `Vec<u16>` - Sorted, de-duplicated list of new ports to scan
+    pub fn expand(open_ports: &HashSet<u16>, already_scanned: &HashSet<u16>) -> Vec<u16> {
+        let mut expanded: Vec<u16> = open_ports.iter()
+            .flat_map(|&port| Self::related(port))
+            .filter(|port| !already_scanned.contains(port))
+            .collect();
+        expanded.sort_unstable();
+        expanded.dedup();
+        expanded
+    }
 }
 
 /// HTTP security header type
@@ -837,6 +2213,106 @@ pub struct HttpInfo {
     pub server: Option<String>,
 }
 
+/// Service name to port mappings used in port specifications (`-p ssh,http,smb`)
+///
+/// Names resolve to every port the service is commonly found on, including
+/// well-known alternates (e.g. "http" also covers 8080, "https" covers 8443).
+#[derive(Debug)]
+pub struct ServicePorts;
+
+impl ServicePorts {
+    /// Embedded services table: (name, ports). Aliases map to the same ports.
+    const TABLE: &'static [(&'static str, &'static [u16])] = &[
+        ("ftp", &[21]),
+        ("ftp-data", &[20]),
+        ("ftps", &[990]),
+        ("ssh", &[22]),
+        ("telnet", &[23]),
+        ("smtp", &[25, 587]),
+        ("smtps", &[465]),
+        ("submission", &[587]),
+        ("dns", &[53]),
+        ("domain", &[53]),
+        ("dhcp", &[67, 68]),
+        ("tftp", &[69]),
+        ("finger", &[79]),
+        ("http", &[80, 8000, 8008, 8080, 8888]),
+        ("http-alt", &[8080, 8000, 8008, 8888]),
+        ("http-proxy", &[8080, 3128]),
+        ("kerberos", &[88]),
+        ("pop3", &[110]),
+        ("pop3s", &[995]),
+        ("rpcbind", &[111]),
+        ("sunrpc", &[111]),
+        ("ident", &[113]),
+        ("ntp", &[123]),
+        ("msrpc", &[135]),
+        ("netbios", &[137, 138, 139]),
+        ("netbios-ssn", &[139]),
+        ("imap", &[143]),
+        ("imaps", &[993]),
+        ("snmp", &[161, 162]),
+        ("ldap", &[389]),
+        ("ldaps", &[636]),
+        ("https", &[443, 8443, 4443, 9443]),
+        ("https-alt", &[8443, 4443, 9443]),
+        ("smb", &[445, 139]),
+        ("microsoft-ds", &[445]),
+        ("isakmp", &[500]),
+        ("ike", &[500, 4500]),
+        ("syslog", &[514]),
+        ("printer", &[515]),
+        ("lpd", &[515]),
+        ("rtsp", &[554]),
+        ("ipp", &[631]),
+        ("rsync", &[873]),
+        ("mssql", &[1433, 1434]),
+        ("oracle", &[1521]),
+        ("pptp", &[1723]),
+        ("mqtt", &[1883, 8883]),
+        ("upnp", &[1900]),
+        ("ssdp", &[1900]),
+        ("nfs", &[2049]),
+        ("docker", &[2375, 2376]),
+        ("etcd", &[2379, 2380]),
+        ("mysql", &[3306]),
+        ("mariadb", &[3306]),
+        ("rdp", &[3389]),
+        ("ms-wbt-server", &[3389]),
+        ("sip", &[5060, 5061]),
+        ("postgresql", &[5432]),
+        ("postgres", &[5432]),
+        ("amqp", &[5672]),
+        ("vnc", &[5900, 5901, 5902, 5800]),
+        ("couchdb", &[5984]),
+        ("winrm", &[5985, 5986]),
+        ("x11", &[6000]),
+        ("redis", &[6379]),
+        ("kubernetes", &[6443, 10250]),
+        ("k8s", &[6443, 10250]),
+        ("irc", &[6667]),
+        ("jetdirect", &[9100]),
+        ("elasticsearch", &[9200, 9300]),
+        ("memcached", &[11211]),
+        ("mongodb", &[27017, 27018]),
+        ("mongo", &[27017, 27018]),
+    ];
+
+    /// Look up the ports for a service name (case-insensitive)
+    ///
+    /// # Arguments
+    /// * `name` - Service name such as "ssh" or "https"
+    ///
+    /// # Returns
+    /// * `Option<&'static [u16]>` - Ports for the service, or None if unknown
+    pub fn lookup(name: &str) -> Option<&'static [u16]> {
+        let name = name.to_lowercase();
+        Self::TABLE.iter()
+            .find(|(service, _)| *service == name)
+            .map(|(_, ports)| *ports)
+    }
+}
+
 /// Common service port mappings
 #[derive(Debug)]
 pub struct CommonPorts;
@@ -895,6 +2371,8 @@ pub struct ScanResult {
     pub os_fingerprint: Option<String>,
     /// TLS protocol version if detected (e.g., "TLSv1.2")
     pub tls_protocol_version: Option<String>,
+    /// Client certificate request, if the TLS server sent one
+    pub client_auth: Option<ClientAuthInfo>,
     /// Timestamp when this specific port was scanned. Useful for correlating logs
     #[serde(with = "chrono::serde::ts_milliseconds")]
     pub scan_time: DateTime<Utc>,
@@ -919,6 +2397,7 @@ impl ScanResult {
             error: None,
             os_fingerprint: None,
             tls_protocol_version: None,
+            client_auth: None,
             scan_time: Utc::now(),
             reason: None,
             scan_type: None,
@@ -938,6 +2417,7 @@ impl ScanResult {
             error: Some(error),
             os_fingerprint: None,
             tls_protocol_version: None,
+            client_auth: None,
             scan_time: Utc::now(),
             reason: Some("Scan error".to_string()),
             scan_type: None,
@@ -959,6 +2439,11 @@ impl ScanResult {
         self.tls_protocol_version = version;
     }
     
+    /// Set the client certificate request
+    pub fn set_client_auth(&mut self, client_auth: Option<ClientAuthInfo>) {
+        self.client_auth = client_auth;
+    }
+    
     /// Set reason for port status
     pub fn set_reason(&mut self, reason: Option<String>) {
         self.reason = reason;
@@ -1004,8 +2489,9 @@ pub fn requires_raw_sockets(scan_types: &[ScanType]) -> bool {
     scan_types.iter().any(|&st| matches!(
         st,
         ScanType::Syn | ScanType::Ack | ScanType::Fin |
-        ScanType::Xmas | ScanType::Null | ScanType::Window |
-        ScanType::Frag // Fragmentation likely needs raw sockets too
+        ScanType::Xmas | ScanType::Null | ScanType::Window | ScanType::Maimon |
+        ScanType::Frag | // Fragmentation likely needs raw sockets too
+        ScanType::IpProto | ScanType::BadSum
         // Tunneling might or might not depending on implementation
     ))
 }
@@ -0,0 +1,293 @@
+//! Source interface and address selection.
+//!
+//! `--interface` and `--source-ip` pin every probe to one NIC/address, which matters on
+//! multihomed jump boxes and when a target is only reachable through a particular VPN
+//! tunnel. The choice is process-wide: raw scans pick it up through
+//! `utils::find_local_ipv4`/`find_local_ipv6`, raw sockets are bound with `bind_raw`, and
+//! connect/UDP sockets are created through `connect_tcp`, `connect_tcp_std`,
+//! `connect_tcp_host`, `bind_udp` and `bind_udp_std`.
+//!
+//! On Linux sockets are additionally bound to the interface with `SO_BINDTODEVICE`, so
+//! traffic leaves through it regardless of the routing table. Elsewhere binding the source
+//! address is all that is possible, and the route to the target decides the egress NIC.
+//! A VRF is selected the same way, by passing its master device to `--interface`.
+//!
+//! `--netns` moves the scanner into another Linux network namespace. Namespaces are per
+//! thread and inherited by new threads, so `enter_netns` runs on the main thread before
+//! the async runtime starts; every socket, capture and interface lookup made afterwards
+//! happens inside the namespace, while files are still read and written on the host.
+
+use std::io;
+use std::net::{IpAddr, Ipv4Addr, Ipv6Addr, SocketAddr};
+use std::time::Duration;
+
+use anyhow::{anyhow, Result};
+use lazy_static::lazy_static;
+use log::{info, warn};
+use parking_lot::RwLock;
+use tokio::net::{TcpSocket, TcpStream, UdpSocket};
+
+#[derive(Debug, Default, Clone)]
+struct Binding {
+    interface: Option<String>,
+    source_v4: Option<Ipv4Addr>,
+    source_v6: Option<Ipv6Addr>,
+}
+
+lazy_static! {
+    static ref BINDING: RwLock<Binding> = RwLock::new(Binding::default());
+}
+
+/// Select the interface and/or source address for this run
+///
+/// # Arguments
+/// * `interface` - Interface name (e.g. `eth1`, `tun0`); its first IPv4 and IPv6 addresses
+///   become the source addresses unless `source_ip` overrides one of them
+/// * `source_ip` - Source address; must be assigned to a local interface (to `interface`
+///   when both are given)
+///
+/// # Returns
+/// * `Result<()>` - Error if the interface does not exist or the address is not local
+pub fn configure(interface: Option<&str>, source_ip: Option<IpAddr>) -> Result<()> {
+    if interface.is_none() && source_ip.is_none() {
+        return Ok(());
+    }
+    let interfaces = pnet_datalink::interfaces();
+    let mut binding = Binding::default();
+
+    if let Some(name) = interface {
+        let iface = interfaces.iter().find(|i| i.name == name)
+            .ok_or_else(|| anyhow!("Interface '{}' not found (available: {})", name,
+                interfaces.iter().map(|i| i.name.as_str()).collect::<Vec<_>>().join(", ")))?;
+        if !iface.is_up() {
+            warn!("Interface {} is down; probes bound to it will not leave the host", name);
+        }
+        binding.source_v4 = iface.ips.iter().find_map(|net| match net.ip() {
+            IpAddr::V4(ip) => Some(ip),
+            IpAddr::V6(_) => None,
+        });
+        binding.source_v6 = iface.ips.iter().find_map(|net| match net.ip() {
+            IpAddr::V6(ip) if (ip.segments()[0] & 0xffc0) != 0xfe80 => Some(ip),
+            _ => None,
+        });
+        if let Some(ip) = source_ip {
+            if !iface.ips.iter().any(|net| net.ip() == ip) {
+                return Err(anyhow!("Source IP {} is not assigned to interface {}", ip, name));
+            }
+        }
+        binding.interface = Some(name.to_string());
+    } else if let Some(ip) = source_ip {
+        if !interfaces.iter().any(|i| i.ips.iter().any(|net| net.ip() == ip)) {
+            return Err(anyhow!("Source IP {} is not assigned to any local interface", ip));
+        }
+    }
+
+    match source_ip {
+        Some(IpAddr::V4(ip)) => binding.source_v4 = Some(ip),
+        Some(IpAddr::V6(ip)) => binding.source_v6 = Some(ip),
+        None => {}
+    }
+
+    info!("Binding probes to interface {} with source {} / {}",
+        binding.interface.as_deref().unwrap_or("(routing table)"),
+        binding.source_v4.map(|ip| ip.to_string()).unwrap_or_else(|| "-".to_string()),
+        binding.source_v6.map(|ip| ip.to_string()).unwrap_or_else(|| "-".to_string()));
+    *BINDING.write() = binding;
+    Ok(())
+}
+
+/// Namespace given with `--netns`, read from the raw command line
+///
+/// The namespace has to be entered before the runtime exists, which is before clap runs.
+pub fn netns_from_args(args: &[String]) -> Option<String> {
+    let mut iter = args.iter().skip(1);
+    while let Some(arg) = iter.next() {
+        if arg == "--netns" {
+            return iter.next().cloned();
+        }
+        if let Some(value) = arg.strip_prefix("--netns=") {
+            return Some(value.to_string());
+        }
+    }
+    None
+}
+
+/// Move the calling thread, and every thread it creates afterwards, into a network namespace
+///
+/// # Arguments
+/// * `spec` - Name of a namespace created with `ip netns add` (looked up in
+///   /var/run/netns), or a path such as /proc/<pid>/ns/net for a container's namespace
+///
+/// # Returns
+/// * `Result<()>` - Error if the namespace does not exist or the caller lacks CAP_SYS_ADMIN
+#[cfg(target_os = "linux")]
+pub fn enter_netns(spec: &str) -> Result<()> {
+    use std::os::unix::io::AsRawFd;
+
+    let path = if spec.contains('/') { spec.to_string() } else { format!("/var/run/netns/{}", spec) };
+    let file = std::fs::File::open(&path)
+        .map_err(|e| anyhow!("Cannot open network namespace {}: {}", path, e))?;
+    if unsafe { libc::setns(file.as_raw_fd(), libc::CLONE_NEWNET) } != 0 {
+        return Err(anyhow!("Cannot enter network namespace {} (requires CAP_SYS_ADMIN): {}", path, io::Error::last_os_error()));
+    }
+    Ok(())
+}
+
+#[cfg(not(target_os = "linux"))]
+pub fn enter_netns(_spec: &str) -> Result<()> {
+    Err(anyhow!("--netns is only supported on Linux"))
+}
+
+/// Selected IPv4 source address, if any
+pub fn source_v4() -> Option<Ipv4Addr> {
+    BINDING.read().source_v4
+}
+
+/// Selected IPv6 source address, if any
+pub fn source_v6() -> Option<Ipv6Addr> {
+    BINDING.read().source_v6
+}
+
+/// Selected interface name, if any
+pub fn interface() -> Option<String> {
+    BINDING.read().interface.clone()
+}
+
+/// Source address to bind for a connection to `target`, matching its address family
+fn source_for(target: IpAddr) -> Option<IpAddr> {
+    let binding = BINDING.read();
+    match target {
+        IpAddr::V4(_) => binding.source_v4.map(IpAddr::V4),
+        IpAddr::V6(_) => binding.source_v6.map(IpAddr::V6),
+    }
+}
+
+/// Open a TCP connection to `addr` from the selected interface/source
+pub async fn connect_tcp(addr: SocketAddr) -> io::Result<TcpStream> {
+    crate::scheduler::spend(crate::scheduler::TCP_SESSION_BYTES).await;
+    crate::audit::record(|| crate::audit::Probe::new("tcp-connect", "tcp", addr.ip(), Some(addr.port()), crate::scheduler::TCP_SESSION_BYTES));
+    let result = connect_bound(addr).await;
+    // A port that starts refusing connections is a sign the scan was noticed
+    crate::hostile::observe_connect(addr, result.as_ref().err().map(io::Error::kind));
+    result
+}
+
+/// Connect from the selected interface/source, without metering
+async fn connect_bound(addr: SocketAddr) -> io::Result<TcpStream> {
+    let Binding { interface, .. } = BINDING.read().clone();
+    let source = source_for(addr.ip());
+    if interface.is_none() && source.is_none() {
+        return TcpStream::connect(addr).await;
+    }
+
+    let socket = if addr.is_ipv4() { TcpSocket::new_v4()? } else { TcpSocket::new_v6()? };
+    #[cfg(target_os = "linux")]
+    if let Some(name) = &interface {
+        socket.bind_device(Some(name.as_bytes()))?;
+    }
+    if let Some(ip) = source {
+        socket.bind(SocketAddr::new(ip, 0))?;
+    }
+    socket.connect(addr).await
+}
+
+/// Blocking variant of `connect_tcp` with a connect timeout
+pub fn connect_tcp_std(addr: SocketAddr, timeout: Duration) -> io::Result<std::net::TcpStream> {
+    crate::scheduler::spend_blocking(crate::scheduler::TCP_SESSION_BYTES);
+    crate::audit::record(|| crate::audit::Probe::new("tcp-connect", "tcp", addr.ip(), Some(addr.port()), crate::scheduler::TCP_SESSION_BYTES));
+    let Binding { interface, .. } = BINDING.read().clone();
+    let source = source_for(addr.ip());
+    if interface.is_none() && source.is_none() {
+        return std::net::TcpStream::connect_timeout(&addr, timeout);
+    }
+
+    let socket = socket2::Socket::new(socket2::Domain::for_address(addr), socket2::Type::STREAM, Some(socket2::Protocol::TCP))?;
+    #[cfg(target_os = "linux")]
+    if let Some(name) = &interface {
+        socket.bind_device(Some(name.as_bytes()))?;
+    }
+    if let Some(ip) = source {
+        socket.bind(&SocketAddr::new(ip, 0).into())?;
+    }
+    socket.connect_timeout(&addr.into(), timeout)?;
+    Ok(socket.into())
+}
+
+/// Bind a UDP socket for talking to `target` on the selected interface/source
+pub async fn bind_udp(target: IpAddr) -> io::Result<UdpSocket> {
+    let unspecified = match target {
+        IpAddr::V4(_) => IpAddr::V4(Ipv4Addr::UNSPECIFIED),
+        IpAddr::V6(_) => IpAddr::V6(Ipv6Addr::UNSPECIFIED),
+    };
+    let socket = UdpSocket::bind(SocketAddr::new(source_for(target).unwrap_or(unspecified), 0)).await?;
+    #[cfg(target_os = "linux")]
+    if let Some(name) = interface() {
+        socket.bind_device(Some(name.as_bytes()))?;
+    }
+    Ok(socket)
+}
+
+/// Blocking variant of `bind_udp`, for APIs that take a std socket (e.g. quinn endpoints)
+pub fn bind_udp_std(target: IpAddr) -> io::Result<std::net::UdpSocket> {
+    let unspecified = match target {
+        IpAddr::V4(_) => IpAddr::V4(Ipv4Addr::UNSPECIFIED),
+        IpAddr::V6(_) => IpAddr::V6(Ipv6Addr::UNSPECIFIED),
+    };
+    let source = SocketAddr::new(source_for(target).unwrap_or(unspecified), 0);
+    let socket = socket2::Socket::new(socket2::Domain::for_address(source), socket2::Type::DGRAM, Some(socket2::Protocol::UDP))?;
+    #[cfg(target_os = "linux")]
+    if let Some(name) = interface() {
+        socket.bind_device(Some(name.as_bytes()))?;
+    }
+    socket.bind(&source.into())?;
+    Ok(socket.into())
+}
+
+/// Resolve `host` and connect to the first of its addresses that accepts, from the
+/// selected interface/source
+///
+/// Addresses of the family the binding has a source for are tried first, so a
+/// `--source-ip` of one family does not end up connecting over the other.
+pub async fn connect_tcp_host(host: &str, port: u16) -> io::Result<TcpStream> {
+    let mut addrs: Vec<SocketAddr> = tokio::net::lookup_host((host, port)).await?.collect();
+    addrs.sort_by_key(|addr| source_for(addr.ip()).is_none());
+
+    let mut last_error = io::Error::new(io::ErrorKind::NotFound, format!("{} did not resolve to any address", host));
+    for addr in addrs {
+        match connect_tcp(addr).await {
+            Ok(stream) => return Ok(stream),
+            Err(e) => last_error = e,
+        }
+    }
+    Err(last_error)
+}
+
+/// Bind a raw socket to the selected interface (Linux only; a no-op elsewhere)
+///
+/// # Arguments
+/// * `tx` - Sending half of a pnet transport channel
+///
+/// # Opsec Considerations
+/// Raw probes carry the selected source address in their IP header either way. Without
+/// the device binding they would still leave through whichever NIC the routing table
+/// picks for the target, which on a multihomed host may not be the intended one.
+pub fn bind_raw(tx: &pnet::transport::TransportSender) -> Result<()> {
+    #[cfg(target_os = "linux")]
+    if let Some(name) = interface() {
+        let rc = unsafe {
+            libc::setsockopt(
+                tx.socket.fd,
+                libc::SOL_SOCKET,
+                libc::SO_BINDTODEVICE,
+                name.as_ptr() as *const libc::c_void,
+                name.len() as libc::socklen_t,
+            )
+        };
+        if rc != 0 {
+            return Err(anyhow!("Failed to bind raw socket to {}: {}", name, io::Error::last_os_error()));
+        }
+    }
+    #[cfg(not(target_os = "linux"))]
+    let _ = tx;
+    Ok(())
+}
@@ -0,0 +1,252 @@
+//! Open proxy detection.
+//!
+//! HTTP proxies and SOCKS servers on the usual ports (3128, 8080, 8888, 1080) are asked
+//! to reach a loopback canary: the proxy host's own 127.0.0.1. The canary never leaves
+//! the proxy host, so no third party sees the test, and a proxy that accepts it will
+//! reach internal-only services for anyone:
+//! - HTTP: `CONNECT 127.0.0.1:<port>`, then a `GET` of an absolute loopback URL. The
+//!   `GET` targets a closed port, so a relaying proxy answers with its own gateway
+//!   error; the same path requested from the server itself tells that apart from a web
+//!   server that fails every request.
+//! - SOCKS: a SOCKS5 no-auth `CONNECT` to the loopback address, falling back to SOCKS4
+//!
+//! Proxies that demand authentication or refuse the destination are recorded, not
+//! flagged.
+
+use std::collections::HashMap;
+use std::fmt;
+use std::net::{IpAddr, SocketAddr};
+use std::time::Duration;
+
+use anyhow::{anyhow, Context, Result};
+use log::{debug, info, warn};
+use rand::Rng;
+use serde_json::json;
+use tokio::io::AsyncReadExt;
+use tokio::net::TcpStream;
+use tokio::time::timeout;
+
+use crate::models::{Confidence, Finding, PortResult, PortStatus};
+
+/// Ports proxies usually listen on, with whether they speak SOCKS
+const PROXY_PORTS: [(u16, bool); 4] = [(3128, false), (8080, false), (8888, false), (1080, true)];
+
+/// Loopback port the relaying `GET` targets; closed on practically every host
+const CANARY_CLOSED_PORT: u16 = 1;
+
+/// Largest reply read
+const MAX_REPLY: usize = 8192;
+
+/// How a proxy treated the loopback canary
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ProxyVerdict {
+    /// Relayed, or tried to relay, to the loopback address
+    Open,
+    /// Asked for credentials first
+    AuthRequired,
+    /// Refused the destination
+    Refused,
+}
+
+impl fmt::Display for ProxyVerdict {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            ProxyVerdict::Open => "open",
+            ProxyVerdict::AuthRequired => "auth_required",
+            ProxyVerdict::Refused => "refused",
+        })
+    }
+}
+
+/// What one proxy port did with the canary
+#[derive(Debug)]
+pub struct ProxyCheck {
+    pub port: u16,
+    /// `http` or `socks5`/`socks4`
+    pub protocol: &'static str,
+    pub verdict: ProxyVerdict,
+    /// The exchange that decided the verdict
+    pub evidence: String,
+}
+
+/// Send `request` on a new connection and read the reply
+async fn exchange(addr: SocketAddr, request: &[u8], wait: Duration) -> Result<Vec<u8>> {
+    let mut stream: TcpStream = timeout(wait, crate::netbind::connect_tcp(addr)).await.context("Connect timed out")??;
+    crate::scheduler::metered_write(&mut stream, request).await?;
+    read_reply(&mut stream, wait).await
+}
+
+async fn read_reply(stream: &mut TcpStream, wait: Duration) -> Result<Vec<u8>> {
+    let mut reply = vec![0u8; MAX_REPLY];
+    let n = timeout(wait, stream.read(&mut reply)).await.context("Reply timed out")??;
+    if n == 0 {
+        return Err(anyhow!("connection closed"));
+    }
+    reply.truncate(n);
+    Ok(reply)
+}
+
+/// Status code and status line of an HTTP reply
+fn http_status(reply: &[u8]) -> Option<(u16, String)> {
+    let text = String::from_utf8_lossy(reply);
+    let line = text.lines().next()?.trim().to_string();
+    if !line.starts_with("HTTP/") {
+        return None;
+    }
+    let code = line.split_whitespace().nth(1)?.parse().ok()?;
+    Some((code, line))
+}
+
+async fn check_http(addr: SocketAddr, wait: Duration) -> Result<Option<ProxyCheck>> {
+    let verdict = |verdict: ProxyVerdict, evidence: String| -> Result<Option<ProxyCheck>> {
+        Ok(Some(ProxyCheck { port: addr.port(), protocol: "http", verdict, evidence }))
+    };
+
+    // A tunnel to the proxy's own port settles it
+    let connect = format!("CONNECT 127.0.0.1:{0} HTTP/1.1\r\nHost: 127.0.0.1:{0}\r\n\r\n", addr.port());
+    if let Some((code, line)) = http_status(&exchange(addr, connect.as_bytes(), wait).await?) {
+        match code {
+            200..=299 => return verdict(ProxyVerdict::Open, format!("CONNECT 127.0.0.1:{} answered '{}'", addr.port(), line)),
+            407 => return verdict(ProxyVerdict::AuthRequired, format!("CONNECT answered '{}'", line)),
+            _ => debug!("[Proxy] {} CONNECT answered '{}'", addr, line),
+        }
+    } else {
+        return Ok(None);
+    }
+
+    // Proxies that only tunnel to 443 may still relay plain requests
+    let token: u32 = crate::utils::scan_rng().gen();
+    let absolute = format!("GET http://127.0.0.1:{}/{:08x} HTTP/1.1\r\nHost: 127.0.0.1:{}\r\nConnection: close\r\n\r\n",
+                           CANARY_CLOSED_PORT, token, CANARY_CLOSED_PORT);
+    let Some((code, line)) = http_status(&exchange(addr, absolute.as_bytes(), wait).await?) else { return Ok(None) };
+    match code {
+        407 => verdict(ProxyVerdict::AuthRequired, format!("GET of a loopback URL answered '{}'", line)),
+        502..=504 => {
+            // A gateway error only means relaying if the server's own pages do not fail alike
+            let origin = format!("GET /{:08x} HTTP/1.1\r\nHost: {}\r\nConnection: close\r\n\r\n", token, addr.ip());
+            let baseline = exchange(addr, origin.as_bytes(), wait).await.ok().and_then(|reply| http_status(&reply));
+            match baseline {
+                Some((own, _)) if (502..=504).contains(&own) => Ok(None),
+                _ => verdict(ProxyVerdict::Open, format!("GET http://127.0.0.1:{}/ was forwarded and failed upstream: '{}'", CANARY_CLOSED_PORT, line)),
+            }
+        }
+        400 | 403 | 405 => verdict(ProxyVerdict::Refused, format!("GET of a loopback URL answered '{}'", line)),
+        _ => Ok(None),
+    }
+}
+
+/// SOCKS5 CONNECT reply codes that mean the server tried the destination
+fn socks5_attempted(code: u8) -> bool {
+    matches!(code, 0x00 | 0x03 | 0x04 | 0x05 | 0x06)
+}
+
+async fn check_socks(addr: SocketAddr, wait: Duration) -> Result<Option<ProxyCheck>> {
+    let check = |protocol: &'static str, verdict: ProxyVerdict, evidence: String| -> Result<Option<ProxyCheck>> {
+        Ok(Some(ProxyCheck { port: addr.port(), protocol, verdict, evidence }))
+    };
+    let port = addr.port().to_be_bytes();
+
+    // SOCKS5: offer only "no authentication", then CONNECT 127.0.0.1
+    let mut stream = timeout(wait, crate::netbind::connect_tcp(addr)).await.context("Connect timed out")??;
+    crate::scheduler::metered_write(&mut stream, &[0x05, 0x01, 0x00]).await?;
+    match read_reply(&mut stream, wait).await {
+        Ok(reply) if reply.len() >= 2 && reply[0] == 0x05 => {
+            if reply[1] != 0x00 {
+                return check("socks5", ProxyVerdict::AuthRequired, "SOCKS5 refused the no-authentication method".to_string());
+            }
+            let request = [0x05, 0x01, 0x00, 0x01, 127, 0, 0, 1, port[0], port[1]];
+            crate::scheduler::metered_write(&mut stream, &request).await?;
+            let reply = read_reply(&mut stream, wait).await?;
+            let code = *reply.get(1).ok_or_else(|| anyhow!("short SOCKS5 reply"))?;
+            return if socks5_attempted(code) {
+                check("socks5", ProxyVerdict::Open, format!("SOCKS5 CONNECT to 127.0.0.1:{} without authentication got reply code {}", addr.port(), code))
+            } else {
+                check("socks5", ProxyVerdict::Refused, format!("SOCKS5 CONNECT refused with reply code {}", code))
+            };
+        }
+        Ok(_) | Err(_) => debug!("[Proxy] {} does not speak SOCKS5", addr),
+    }
+
+    // SOCKS4: CONNECT 127.0.0.1 with an empty user ID
+    let request = [0x04, 0x01, port[0], port[1], 127, 0, 0, 1, 0x00];
+    let reply = match exchange(addr, &request, wait).await {
+        Ok(reply) if reply.len() >= 2 && reply[0] == 0x00 => reply,
+        _ => return Ok(None),
+    };
+    match reply[1] {
+        0x5a => check("socks4", ProxyVerdict::Open, format!("SOCKS4 CONNECT to 127.0.0.1:{} granted", addr.port())),
+        0x5c | 0x5d => check("socks4", ProxyVerdict::AuthRequired, "SOCKS4 requires identd verification".to_string()),
+        code => check("socks4", ProxyVerdict::Refused, format!("SOCKS4 CONNECT rejected with code {:#x}", code)),
+    }
+}
+
+/// Which proxy protocol to test an open port with, by detection results and port number
+fn proxy_kind(port: u16, result: &PortResult) -> Option<bool> {
+    let service = result.service.as_deref().unwrap_or("").to_ascii_lowercase();
+    if service.contains("socks") {
+        Some(true)
+    } else if service.contains("proxy") || service.contains("squid") {
+        Some(false)
+    } else {
+        PROXY_PORTS.iter().find(|(p, _)| *p == port).map(|(_, socks)| *socks)
+    }
+}
+
+/// Findings for one checked port
+pub fn details_and_vulns(check: &ProxyCheck) -> (serde_json::Value, Vec<Finding>) {
+    let details = json!({
+        "proxy": check.verdict.to_string(),
+        "proxy_protocol": check.protocol,
+        "proxy_evidence": check.evidence,
+    });
+    let vulns = if check.verdict == ProxyVerdict::Open {
+        vec![Finding {
+            id: "OPEN-PROXY".to_string(),
+            title: "Open proxy".to_string(),
+            description: format!("Open {} proxy: relays to the proxy host's own loopback without authentication, so anyone can reach internal services through it ({})",
+                                 check.protocol, check.evidence),
+            severity: "Critical".to_string(),
+            confidence: Confidence::Confirmed,
+            evidence: vec![check.evidence.clone()],
+            ..Default::default()
+        }]
+    } else {
+        Vec::new()
+    };
+    (details, vulns)
+}
+
+/// Test every open proxy port of a host against the loopback canary
+///
+/// # Arguments
+/// * `target` - Host to check
+/// * `results` - Port results after detection; only open TCP ports are considered
+/// * `wait` - Connect and reply timeout
+///
+/// # Returns
+/// * `Vec<ProxyCheck>` - One verdict per port that behaved like a proxy
+pub async fn check_proxies(target: IpAddr, results: &HashMap<u16, PortResult>, wait: Duration) -> Vec<ProxyCheck> {
+    let mut ports: Vec<(u16, bool)> = results.iter()
+        .filter(|(_, r)| r.tcp_states.values().any(|&s| s == PortStatus::Open))
+        .filter_map(|(&port, r)| proxy_kind(port, r).map(|socks| (port, socks)))
+        .collect();
+    ports.sort_unstable();
+
+    let mut checks = Vec::new();
+    for (port, socks) in ports {
+        let addr = SocketAddr::new(target, port);
+        info!("[Proxy] Testing {} for open {} proxying", addr, if socks { "SOCKS" } else { "HTTP" });
+        let outcome = if socks { check_socks(addr, wait).await } else { check_http(addr, wait).await };
+        match outcome {
+            Ok(Some(check)) => {
+                if check.verdict == ProxyVerdict::Open {
+                    warn!("[Proxy] {} is an open {} proxy: {}", addr, check.protocol, check.evidence);
+                }
+                checks.push(check);
+            }
+            Ok(None) => debug!("[Proxy] {} did not behave like a proxy", addr),
+            Err(e) => debug!("[Proxy] Test of {} failed: {}", addr, e),
+        }
+    }
+    checks
+}
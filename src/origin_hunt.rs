@@ -0,0 +1,335 @@
+//! Origin discovery behind CDNs (`--find-origin`).
+//!
+//! When `edge_detect` flags a hostname as a CDN frontend, the server actually worth
+//! testing sits behind it. Three techniques look for its address once every host of the
+//! run is scanned:
+//! - **DNS history**: addresses the hostname, or a sibling name under the same domain,
+//!   pointed to before it moved behind the CDN, from a local dataset (`--dns-history`)
+//! - **Certificates**: directly scanned hosts whose certificate names the hostname
+//! - **Favicon**: directly scanned web servers that serve the same favicon as the site
+//!   when asked for it by name
+//!
+//! Each address found is recorded on the edge host as an origin candidate, with the
+//! techniques that point to it. Favicon requests only go to hosts scanned in this run or
+//! to candidates inside `--scope`; nothing outside the engagement is contacted.
+//!
+//! The DNS history dataset is a CSV file of `hostname,ip[,last_seen]` lines, as exported
+//! from passive DNS services. Blank lines and lines starting with `#` are ignored.
+
+use std::collections::{BTreeMap, BTreeSet};
+use std::net::{IpAddr, SocketAddr};
+use std::path::Path;
+use std::time::Duration;
+
+use anyhow::{anyhow, Context, Result};
+use base64::Engine;
+use ipnet::IpNet;
+use log::{debug, info, warn};
+use tokio::io::{AsyncRead, AsyncReadExt, AsyncWrite, AsyncWriteExt};
+use tokio::time::timeout;
+
+use crate::models::{OriginCandidate, PortStatus, ScanResults};
+
+/// Time allowed for each step of a favicon request
+const FAVICON_TIMEOUT: Duration = Duration::from_secs(8);
+
+/// Largest response read for a favicon, headers included
+const MAX_FAVICON_READ: usize = 256 * 1024;
+
+/// Approximate size of a TLS handshake from the client side
+const TLS_HANDSHAKE_BYTES: usize = 700;
+
+/// Ports tried for web servers when no HTTP details were collected, TLS first
+const WEB_PORTS: [(u16, bool); 4] = [(443, true), (8443, true), (80, false), (8080, false)];
+
+/// One record of the DNS history dataset
+#[derive(Debug, Clone)]
+struct HistoryRecord {
+    name: String,
+    ip: IpAddr,
+    last_seen: Option<String>,
+}
+
+/// Past DNS answers, from `--dns-history`
+#[derive(Debug, Default)]
+pub struct DnsHistory {
+    records: Vec<HistoryRecord>,
+}
+
+impl DnsHistory {
+    /// Load a CSV dataset of `hostname,ip[,last_seen]` lines
+    pub fn load_file(path: &Path) -> Result<Self> {
+        let text = std::fs::read_to_string(path)
+            .with_context(|| format!("Failed to read DNS history {}", path.display()))?;
+        let mut records = Vec::new();
+        for (number, line) in text.lines().enumerate() {
+            let line = line.trim();
+            if line.is_empty() || line.starts_with('#') {
+                continue;
+            }
+            let fields: Vec<&str> = line.split(',').map(str::trim).collect();
+            let (Some(name), Some(ip)) = (fields.first(), fields.get(1)) else {
+                return Err(anyhow!("Invalid DNS history {} line {}: expected hostname,ip[,last_seen]", path.display(), number + 1));
+            };
+            // A header row is allowed
+            let Ok(ip) = ip.parse::<IpAddr>() else {
+                if number == 0 {
+                    continue;
+                }
+                return Err(anyhow!("Invalid DNS history {} line {}: '{}' is not an IP address", path.display(), number + 1, ip));
+            };
+            records.push(HistoryRecord {
+                name: name.trim_end_matches('.').to_ascii_lowercase(),
+                ip,
+                last_seen: fields.get(2).filter(|seen| !seen.is_empty()).map(|seen| seen.to_string()),
+            });
+        }
+        Ok(DnsHistory { records })
+    }
+
+    /// Number of records loaded
+    pub fn record_count(&self) -> usize {
+        self.records.len()
+    }
+
+    /// Records of `hostname` and of its siblings under the same domain
+    fn related(&self, hostname: &str) -> impl Iterator<Item = &HistoryRecord> {
+        let domain = crate::edge_detect::base_domain(hostname);
+        let suffix = format!(".{}", domain);
+        self.records.iter().filter(move |record| record.name == domain || record.name.ends_with(&suffix))
+    }
+}
+
+/// Whether a certificate name covers `hostname`, wildcards included
+fn name_covers(name: &str, hostname: &str) -> bool {
+    let name = name.trim().trim_end_matches('.').to_ascii_lowercase();
+    match name.strip_prefix("*.") {
+        Some(parent) => hostname.split_once('.').is_some_and(|(_, rest)| rest == parent),
+        None => name == hostname,
+    }
+}
+
+/// Favicon hash as Shodan and similar search engines compute it: MurmurHash3 (x86,
+/// 32-bit, seed 0) of the base64 encoding with a newline every 76 characters
+fn favicon_hash(icon: &[u8]) -> i32 {
+    let encoded = base64::engine::general_purpose::STANDARD.encode(icon);
+    let mut wrapped = String::with_capacity(encoded.len() + encoded.len() / 76 + 1);
+    for chunk in encoded.as_bytes().chunks(76) {
+        wrapped.push_str(std::str::from_utf8(chunk).unwrap_or_default());
+        wrapped.push('\n');
+    }
+    murmur3_32(wrapped.as_bytes()) as i32
+}
+
+fn murmur3_32(data: &[u8]) -> u32 {
+    const C1: u32 = 0xcc9e_2d51;
+    const C2: u32 = 0x1b87_3593;
+    let mut hash: u32 = 0;
+    let mut blocks = data.chunks_exact(4);
+    for block in &mut blocks {
+        let mut k = u32::from_le_bytes([block[0], block[1], block[2], block[3]]);
+        k = k.wrapping_mul(C1).rotate_left(15).wrapping_mul(C2);
+        hash ^= k;
+        hash = hash.rotate_left(13).wrapping_mul(5).wrapping_add(0xe654_6b64);
+    }
+    let tail = blocks.remainder();
+    if !tail.is_empty() {
+        let mut k: u32 = 0;
+        for (i, byte) in tail.iter().enumerate() {
+            k |= (*byte as u32) << (8 * i);
+        }
+        hash ^= k.wrapping_mul(C1).rotate_left(15).wrapping_mul(C2);
+    }
+    hash ^= data.len() as u32;
+    hash ^= hash >> 16;
+    hash = hash.wrapping_mul(0x85eb_ca6b);
+    hash ^= hash >> 13;
+    hash = hash.wrapping_mul(0xc2b2_ae35);
+    hash ^ (hash >> 16)
+}
+
+/// Web ports a host was found listening on, with whether they speak TLS
+fn web_ports(host: &ScanResults) -> Vec<(u16, bool)> {
+    let mut ports: Vec<(u16, bool)> = host.results.iter()
+        .filter(|(port, result)| host.open_ports.contains(port) || result.final_status == PortStatus::Open)
+        .filter_map(|(port, result)| {
+            let known = WEB_PORTS.iter().find(|(web, _)| web == port).map(|(_, tls)| *tls);
+            match (&result.http_info, known) {
+                (Some(_), _) => Some((*port, result.cert_info.is_some() || known == Some(true))),
+                (None, Some(tls)) => Some((*port, tls)),
+                (None, None) => None,
+            }
+        })
+        .collect();
+    // TLS first, as a site behind a CDN is usually served over HTTPS at the origin too
+    ports.sort_by_key(|(port, tls)| (!tls, *port));
+    ports
+}
+
+/// Send a request and read the whole response
+async fn exchange<S: AsyncRead + AsyncWrite + Unpin>(stream: &mut S, request: &[u8]) -> Result<Vec<u8>> {
+    timeout(FAVICON_TIMEOUT, stream.write_all(request)).await.context("Write timed out")??;
+    let mut response = Vec::new();
+    let mut buf = vec![0u8; 8192];
+    while response.len() < MAX_FAVICON_READ {
+        match timeout(FAVICON_TIMEOUT, stream.read(&mut buf)).await {
+            Ok(Ok(0)) | Err(_) => break,
+            Ok(Ok(n)) => response.extend_from_slice(&buf[..n]),
+            Ok(Err(e)) if response.is_empty() => return Err(e.into()),
+            Ok(Err(_)) => break,
+        }
+    }
+    Ok(response)
+}
+
+/// Fetch `/favicon.ico` from `addr`, asking for `hostname`
+async fn fetch_favicon(addr: SocketAddr, hostname: &str, tls: bool) -> Result<Vec<u8>> {
+    let request = format!(
+        "GET /favicon.ico HTTP/1.0\r\nHost: {}\r\nUser-Agent: {}\r\nAccept: image/avif,image/webp,image/png,image/*;q=0.8,*/*;q=0.5\r\nConnection: close\r\n\r\n",
+        hostname, crate::utils::get_random_user_agent(None)
+    );
+    let bytes = request.len() + crate::scheduler::TCP_HEADER_BYTES + if tls { TLS_HANDSHAKE_BYTES } else { 0 };
+    crate::scheduler::spend(bytes).await;
+    crate::audit::record(|| crate::audit::Probe::new("find-origin", "tcp", addr.ip(), Some(addr.port()), bytes)
+        .detail(format!("GET /favicon.ico for {}", hostname)));
+
+    let mut stream = timeout(FAVICON_TIMEOUT, crate::netbind::connect_tcp(addr))
+        .await
+        .context("Connect timed out")??;
+    let response = if tls {
+        let server_name = crate::ssl_config::convert_to_server_name(hostname)
+            .map_err(|e| anyhow!("Invalid server name '{}': {}", hostname, e))?
+            .to_owned();
+        // Origins often present a self-signed or mismatched certificate
+        let connector = tokio_rustls::TlsConnector::from(crate::ssl_config::create_tls_config(false));
+        let mut tls_stream = timeout(FAVICON_TIMEOUT, connector.connect(server_name, stream))
+            .await
+            .context("TLS handshake timed out")??;
+        exchange(&mut tls_stream, request.as_bytes()).await?
+    } else {
+        exchange(&mut stream, request.as_bytes()).await?
+    };
+
+    let split = response.windows(4).position(|w| w == b"\r\n\r\n")
+        .ok_or_else(|| anyhow!("incomplete HTTP response"))?;
+    let head = String::from_utf8_lossy(&response[..split]);
+    let status = head.split_whitespace().nth(1).unwrap_or_default();
+    if status != "200" {
+        return Err(anyhow!("HTTP status {}", status));
+    }
+    let body = response[split + 4..].to_vec();
+    if body.is_empty() {
+        return Err(anyhow!("empty favicon"));
+    }
+    Ok(body)
+}
+
+/// Favicon hash of a site, from the first of `ports` that serves one
+async fn site_favicon(ip: IpAddr, hostname: &str, ports: &[(u16, bool)]) -> Option<i32> {
+    for &(port, tls) in ports {
+        match fetch_favicon(SocketAddr::new(ip, port), hostname, tls).await {
+            Ok(icon) => return Some(favicon_hash(&icon)),
+            Err(e) => debug!("[Origin] No favicon for {} from {}:{}: {}", hostname, ip, port, e),
+        }
+    }
+    None
+}
+
+/// Look for the origin of every host flagged as a CDN edge
+///
+/// # Arguments
+/// * `results` - Results of every host of the run; candidates go to each edge's `origin_candidates`
+/// * `history` - Past DNS answers, if a dataset was given
+/// * `scope` - Ranges candidates from DNS history may be contacted in
+///
+/// # Returns
+/// * `usize` - Number of origin candidates across all edge hosts
+pub async fn hunt(results: &mut [ScanResults], history: Option<&DnsHistory>, scope: &[IpNet]) -> usize {
+    // Hosts reached directly, not through an edge, and the web ports they answer on
+    let direct: Vec<(IpAddr, Vec<(u16, bool)>)> = results.iter()
+        .filter(|host| host.edge.is_none())
+        .filter_map(|host| Some((host.target_ip.parse::<IpAddr>().ok()?, web_ports(host))))
+        .collect();
+
+    let mut total = 0;
+    for index in 0..results.len() {
+        let Some(edge) = &results[index].edge else { continue };
+        let hostname = results[index].target.trim_end_matches('.').to_ascii_lowercase();
+        if hostname.parse::<IpAddr>().is_ok() {
+            debug!("[Origin] {} was given as an address; nothing to look up", hostname);
+            continue;
+        }
+        let edge_addresses: BTreeSet<IpAddr> = edge.addresses.iter().copied()
+            .chain(results[index].target_ip.parse().ok())
+            .collect();
+        let mut candidates: BTreeMap<IpAddr, Vec<String>> = BTreeMap::new();
+
+        if let Some(history) = history {
+            for record in history.related(&hostname) {
+                if edge_addresses.contains(&record.ip) || crate::edge_detect::provider_for_address(record.ip).is_some() {
+                    continue;
+                }
+                let seen = record.last_seen.as_deref().map(|seen| format!(" (last seen {})", seen)).unwrap_or_default();
+                candidates.entry(record.ip).or_default()
+                    .push(format!("DNS history: {} pointed here{}", record.name, seen));
+            }
+        }
+
+        for host in results.iter().filter(|host| host.edge.is_none()) {
+            let Ok(ip) = host.target_ip.parse::<IpAddr>() else { continue };
+            let mut ports: Vec<_> = host.results.iter().collect();
+            ports.sort_unstable_by_key(|(port, _)| **port);
+            for (port, result) in ports {
+                let Some(cert) = &result.cert_info else { continue };
+                let common_name = cert.subject.split(',')
+                    .map(str::trim)
+                    .find_map(|part| part.strip_prefix("CN="));
+                if cert.alt_names.iter().map(String::as_str).chain(common_name).any(|name| name_covers(name, &hostname)) {
+                    candidates.entry(ip).or_default()
+                        .push(format!("certificate on port {} names {}", port, hostname));
+                }
+            }
+        }
+
+        let edge_ports = web_ports(&results[index]);
+        let edge_ip = results[index].target_ip.parse::<IpAddr>().ok();
+        let site_hash = match edge_ip {
+            Some(ip) if !edge_ports.is_empty() => site_favicon(ip, &hostname, &edge_ports).await,
+            _ => None,
+        };
+        if let Some(site_hash) = site_hash {
+            // Hosts of the run, plus candidates in scope that were not scanned
+            let mut targets: Vec<(IpAddr, Vec<(u16, bool)>)> = direct.iter()
+                .filter(|(_, ports)| !ports.is_empty())
+                .cloned()
+                .collect();
+            targets.extend(candidates.keys()
+                .filter(|ip| !direct.iter().any(|(known, _)| known == *ip))
+                .filter(|ip| scope.iter().any(|net| net.contains(*ip)))
+                .map(|ip| (*ip, WEB_PORTS.to_vec())));
+            for (ip, ports) in targets {
+                if site_favicon(ip, &hostname, &ports).await == Some(site_hash) {
+                    candidates.entry(ip).or_default()
+                        .push(format!("serves the site's favicon (hash {}) when asked for {}", site_hash, hostname));
+                }
+            }
+        }
+
+        if candidates.is_empty() {
+            info!("[Origin] No origin candidates found for {}", hostname);
+            continue;
+        }
+        warn!("[Origin] {} origin candidates for {}: {}", candidates.len(), hostname,
+              candidates.keys().map(|ip| ip.to_string()).collect::<Vec<_>>().join(", "));
+        total += candidates.len();
+        // Addresses backed by more techniques first
+        let mut found: Vec<OriginCandidate> = candidates.into_iter()
+            .map(|(ip, evidence)| OriginCandidate { ip, evidence })
+            .collect();
+        found.sort_by(|a, b| b.evidence.len().cmp(&a.evidence.len()).then_with(|| a.ip.cmp(&b.ip)));
+        if let Some(edge) = results[index].edge.as_mut() {
+            edge.origin_candidates = found;
+        }
+    }
+    total
+}
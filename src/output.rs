@@ -2,13 +2,16 @@ use std::fs::File;
 use std::io::Write;
 use std::path::Path;
 
-use anyhow::{Context, Result};
+use anyhow::Result;
 use chrono::Utc;
+use clap::ValueEnum;
 use console::{style, Term};
+use log::{error, info};
+use serde::Serialize;
 use serde_json;
 
 use crate::ScanType;
-use crate::models::{PortResult, PortStatus, ScanResults};
+use crate::models::{BaselineDeviation, ClientAuthInfo, CloudInfo, Confidence, EdgeInfo, Finding, HostOutlier, HostTiming, InterferenceSign, OriginCandidate, PayloadClass, PayloadProfile, PortResult, PortStatus, ScanResults, SniCertificate, StateConsensus, TechniqueSelection};
 use crate::utils::sanitize_string;
 
 /// Save scan results to a JSON file
@@ -21,13 +24,7 @@ use crate::utils::sanitize_string;
 /// * `Result<()>` - Success or error
 pub fn save_json_results(results: &ScanResults, output_path: &Path) -> Result<()> {
     let json = serde_json::to_string_pretty(results)?;
-    let mut file = File::create(output_path)
-        .with_context(|| format!("Failed to create output file: {:?}", output_path))?;
-    
-    file.write_all(json.as_bytes())
-        .with_context(|| format!("Failed to write to output file: {:?}", output_path))?;
-    
-    Ok(())
+    crate::encryption::write_output(output_path, json.as_bytes())
 }
 
 /// Convert scan results to a simple text report format
@@ -38,9 +35,47 @@ pub fn format_text_results(results: &ScanResults, verbose: bool) -> String {
     output.push_str(&format!("# Quantum Scanner Report\n"));
     output.push_str(&format!("Target: {}\n", results.target));
     output.push_str(&format!("IP: {}\n", results.target_ip));
+    if let Some(mac) = &results.mac_address {
+        output.push_str(&format!("MAC: {} ({})\n", mac, results.mac_vendor.as_deref().unwrap_or("unknown vendor")));
+    }
+    if let Some(asset) = &results.asset {
+        output.push_str(&format!("Asset: {}\n", asset));
+    }
     output.push_str(&format!("Timestamp: {}\n", Utc::now()));
+    if let Some(meta) = &results.metadata {
+        output.push_str(&format!("Run ID: {}\n", meta.run_id));
+        output.push_str(&format!("Tool: {} {}\n", meta.tool_name, meta.tool_version));
+        output.push_str(&format!("Config hash: {}\n", meta.config_hash));
+        output.push_str(&format!("Command line: {}\n", meta.command_line.join(" ")));
+    }
     output.push_str(&format!("Scan Duration: {:.2} seconds\n", 
         results.end_time.signed_duration_since(results.start_time).num_milliseconds() as f64 / 1000.0));
+    if !results.skipped_probes.is_empty() || !results.skipped_phases.is_empty() {
+        output.push_str(&format!("Time budget: {} ports not fully probed; skipped phases: {}\n",
+            results.skipped_probes.len(),
+            if results.skipped_phases.is_empty() { "none".to_string() } else { results.skipped_phases.join(", ") }));
+    }
+    if let Some(edge) = &results.edge {
+        output.push_str(&format!("Edge: {}; results describe the edge, not the origin ({})\n", edge, edge.evidence.join("; ")));
+        for candidate in &edge.origin_candidates {
+            output.push_str(&format!("Origin candidate: {} ({})\n", candidate.ip, candidate.evidence.join("; ")));
+        }
+    }
+    if let Some(cloud) = &results.cloud {
+        output.push_str(&format!("Cloud: {} ({}); metadata service: {}\n", cloud.provider, cloud.evidence.join("; "), cloud.metadata_endpoint));
+        if !cloud.ssrf_candidates.is_empty() {
+            output.push_str(&format!("SSRF candidates: ports {}\n", cloud.ssrf_candidates.iter().map(|p| p.to_string()).collect::<Vec<_>>().join(", ")));
+        }
+    }
+    for sign in &results.interference {
+        output.push_str(&format!("Interference: {} ({} ports unreliable)\n", sign.detail, sign.ports.len()));
+    }
+    for outlier in &results.outliers {
+        output.push_str(&format!("Outlier: {}\n", outlier.detail));
+    }
+    for deviation in &results.deviations {
+        output.push_str(&format!("Baseline deviation: {}\n", deviation.detail));
+    }
     
     // Indicate scan mode
     let has_service_info = results.results.values()
@@ -65,6 +100,7 @@ pub fn format_text_results(results: &ScanResults, verbose: bool) -> String {
     if verbose {
         output.push_str("## Scan Statistics\n");
         output.push_str(&format!("Packets sent: {}\n", results.packets_sent));
+        output.push_str(&format!("Bytes sent: {}\n", results.bytes_sent));
         output.push_str(&format!("Successful operations: {}\n", results.successful_scans));
         if results.packets_sent > 0 {
             output.push_str(&format!("Success rate: {:.1}%\n", 
@@ -97,6 +133,64 @@ pub fn format_text_results(results: &ScanResults, verbose: bool) -> String {
         }
     }
     
+    // Resolver path problems that affect how far results can be trusted
+    if let Some(dns) = results.dns_sanity.as_ref().filter(|dns| !dns.warnings.is_empty()) {
+        output.push_str("## DNS Resolver Warnings\n");
+        for warning in &dns.warnings {
+            output.push_str(&format!("- {}\n", warning));
+        }
+        output.push_str("\n");
+    }
+
+    // Calibrated probe timing
+    if let Some(timing) = &results.host_timing {
+        output.push_str(&format!("Probe timing: {}\n\n", format_host_timing(timing)));
+    }
+
+    // Techniques chosen by the pre-probe
+    if let Some(selection) = &results.technique_selection {
+        output.push_str(&format!("Technique selection: {}\n", format_technique_selection(selection)));
+        for reason in &selection.reasons {
+            output.push_str(&format!("- {}\n", reason));
+        }
+        output.push_str("\n");
+    }
+
+    // ICMP host characterization
+    if let Some(icmp) = &results.icmp_info {
+        output.push_str("## ICMP Host Information\n");
+        output.push_str(&format!("Timestamp reply: {}\n", if icmp.timestamp_reply { "yes" } else { "no" }));
+        if let Some(offset) = icmp.clock_offset_ms {
+            output.push_str(&format!("Clock offset: {} ms\n", offset));
+        }
+        if let Some(mask) = &icmp.netmask {
+            output.push_str(&format!("Address mask: {}\n", mask));
+        }
+        if let Some(unreach) = &icmp.udp_unreachable {
+            output.push_str(&format!("UDP {} unreachable: code {} ({}) from {}, TTL {}, {} bytes quoted\n",
+                unreach.port, unreach.icmp_code, unreach.meaning, unreach.source, unreach.reply_ttl, unreach.quoted_bytes));
+        }
+        for hint in &icmp.os_hints {
+            output.push_str(&format!("- {}\n", hint));
+        }
+        output.push_str("\n");
+    }
+    
+    // IP protocol scan results
+    if !results.ip_protocols.is_empty() {
+        output.push_str("## IP Protocols\n");
+        output.push_str("PROTO  NAME            STATE\n");
+        let mut protocols: Vec<_> = results.ip_protocols.iter().collect();
+        protocols.sort_by_key(|(protocol, _)| **protocol);
+        for (protocol, result) in protocols {
+            output.push_str(&format!("{:<6} {:<15} {}\n", protocol, result.name, result.status));
+            if verbose {
+                output.push_str(&format!("       {}\n", result.reason));
+            }
+        }
+        output.push_str("\n");
+    }
+    
     // Open ports summary
     output.push_str(&format!("## Open Ports Summary\n"));
     if results.open_ports.is_empty() {
@@ -171,8 +265,23 @@ pub fn format_text_results(results: &ScanResults, verbose: bool) -> String {
                 output.push_str(&format!("Protocol (nDPI): {:?}\n", protocol));
             }
             
-            // States by scan type
-            if !port_result.tcp_states.is_empty() {
+            if !port_result.ml_candidates.is_empty() {
+                output.push_str(&format!("ML Candidates: {}\n", format_ml_candidates(&port_result.ml_candidates)));
+            }
+            if let Some(payload) = &port_result.payload {
+                output.push_str(&format!("Payload: {}\n", format_payload(payload)));
+            }
+            
+            // Merged state with its evidence, or the raw states when no consensus was taken
+            if let Some(reason) = &port_result.unreliable {
+                output.push_str(&format!("Unreliable: {}\n", reason));
+            }
+            if let Some(consensus) = &port_result.consensus {
+                output.push_str(&format!("State: {}\n", format_consensus(consensus)));
+                for line in &consensus.evidence {
+                    output.push_str(&format!("  - {}\n", line));
+                }
+            } else if !port_result.tcp_states.is_empty() {
                 output.push_str("TCP States:\n");
                 // Sort scan types alphabetically
                 let mut scan_types: Vec<(&ScanType, &PortStatus)> = port_result.tcp_states.iter().collect();
@@ -215,7 +324,7 @@ pub fn format_text_results(results: &ScanResults, verbose: bool) -> String {
             if !port_result.anomalies.is_empty() {
                 output.push_str("Detected Anomalies:\n");
                 for anomaly in &port_result.anomalies {
-                    output.push_str(&format!("  - {}\n", anomaly));
+                    output.push_str(&format!("  - {}\n", anomaly.description));
                 }
             }
             
@@ -255,14 +364,55 @@ pub fn format_text_results(results: &ScanResults, verbose: bool) -> String {
                         output.push_str(&format!("    - {}\n", san));
                     }
                 }
+                if let Some(trusted) = cert.trusted {
+                    output.push_str(&format!("  Trusted: {}\n", if trusted { "yes" } else { "no" }));
+                }
+                if let Some(stapled) = cert.ocsp_stapled {
+                    output.push_str(&format!("  OCSP Stapled: {}\n", if stapled { "yes" } else { "no" }));
+                }
+                for (depth, link) in cert.chain.iter().enumerate() {
+                    output.push_str(&format!("  Chain [{}]: {} (issuer: {})\n", depth + 1, link.subject, link.issuer));
+                }
+                if !cert.issues.is_empty() {
+                    output.push_str("  Certificate Issues:\n");
+                    for issue in &cert.issues {
+                        output.push_str(&format!("    - {} ({}): {}\n", issue.kind, issue.severity, issue.detail));
+                    }
+                }
+            }
+            if let Some(auth) = &port_result.client_auth {
+                output.push_str(&format!("Client Certificate: {}\n", format_client_auth(auth)));
+            }
+            if !port_result.sni_certificates.is_empty() {
+                output.push_str("SNI Certificates:\n");
+                for sni in &port_result.sni_certificates {
+                    output.push_str(&format!("  - {}\n", format_sni_certificate(sni)));
+                }
             }
             
             // Vulnerabilities if detected
             if !port_result.vulns.is_empty() {
                 output.push_str("Potential Vulnerabilities:\n");
                 for vuln in &port_result.vulns {
-                    output.push_str(&format!("  - {} ({})\n", vuln.id, vuln.severity));
+                    output.push_str(&format!("  - {}: {} ({}, {})\n", vuln.id, vuln.display_title(), vuln.severity, vuln.confidence));
                     output.push_str(&format!("    Description: {}\n", vuln.description));
+                    for evidence in &vuln.evidence {
+                        output.push_str(&format!("    Evidence: {}\n", evidence));
+                    }
+                    for reference in &vuln.references {
+                        output.push_str(&format!("    Reference: {}\n", reference));
+                    }
+                    if let Some(remediation) = &vuln.remediation {
+                        output.push_str(&format!("    Remediation: {}\n", remediation));
+                    }
+                }
+            }
+
+            // Script findings if any
+            for script in &port_result.script_output {
+                output.push_str(&format!("Script {}:\n", script.script));
+                for finding in &script.findings {
+                    output.push_str(&format!("  - {}\n", finding));
                 }
             }
             
@@ -283,13 +433,7 @@ pub fn format_text_results(results: &ScanResults, verbose: bool) -> String {
 /// * `Result<()>` - Success or error
 pub fn save_text_results(results: &ScanResults, output_path: &Path) -> Result<()> {
     let text = format_text_results(results, true);  // Always use verbose mode for saved files
-    let mut file = File::create(output_path)
-        .with_context(|| format!("Failed to create output file: {:?}", output_path))?;
-    
-    file.write_all(text.as_bytes())
-        .with_context(|| format!("Failed to write to output file: {:?}", output_path))?;
-    
-    Ok(())
+    crate::encryption::write_output(output_path, text.as_bytes())
 }
 
 #[allow(dead_code)]
@@ -308,9 +452,20 @@ fn format_port_text(file: &mut File, port: u16, result: &PortResult) -> Result<(
     if let Some(version) = &result.version {
         writeln!(file, "Version: {}", version)?;
     }
+    if let Some(payload) = &result.payload {
+        writeln!(file, "Payload: {}", format_payload(payload))?;
+    }
     
     // Scan results for each technique
-    if !result.tcp_states.is_empty() {
+    if let Some(reason) = &result.unreliable {
+        writeln!(file, "Unreliable: {}", reason)?;
+    }
+    if let Some(consensus) = &result.consensus {
+        writeln!(file, "\nState: {}", format_consensus(consensus))?;
+        for line in &consensus.evidence {
+            writeln!(file, "- {}", line)?;
+        }
+    } else if !result.tcp_states.is_empty() {
         file.write_all(b"\nTCP Scan Results:\n")?;
         // Sort scan types alphabetically
         let mut scan_types: Vec<(&ScanType, &PortStatus)> = result.tcp_states.iter().collect();
@@ -346,11 +501,86 @@ fn format_port_text(file: &mut File, port: u16, result: &PortResult) -> Result<(
     if let Some(filtering) = &result.filtering {
         writeln!(file, "Filtering: {}", filtering)?;
     }
-    
+
+    // Policy that kept deep enumeration off this port
+    if let Some(protected_by) = &result.protected_by {
+        writeln!(file, "Protected: {} (no deep enumeration)", protected_by)?;
+    }
+
+    // Reply sizes of reflector queries if measured
+    for measurement in &result.amplification {
+        writeln!(file, "Amplification: {} - {} bytes sent, {} bytes back in {} datagrams ({}{:.1}x)",
+            measurement.query, measurement.request_bytes, measurement.response_bytes, measurement.datagrams,
+            if measurement.capped { ">=" } else { "" }, measurement.factor)?;
+    }
+
+    // SMTP relay and STARTTLS policy if checked
+    if let Some(smtp) = &result.smtp {
+        file.write_all(b"\nSMTP Policy:\n")?;
+        let yes_no = |value: Option<bool>| match value {
+            Some(true) => "yes",
+            Some(false) => "no",
+            None => "not tested",
+        };
+        if smtp.implicit_tls {
+            writeln!(file, "- TLS: implicit")?;
+        } else {
+            writeln!(file, "- STARTTLS: {}{}", if smtp.starttls_offered { "offered" } else { "not offered" },
+                smtp.starttls_error.as_deref().map(|e| format!(" (failed: {})", e)).unwrap_or_default())?;
+            if !smtp.auth_plaintext.is_empty() {
+                writeln!(file, "- AUTH before TLS: {}", smtp.auth_plaintext.join(" "))?;
+            }
+        }
+        if !smtp.auth_tls.is_empty() {
+            writeln!(file, "- AUTH over TLS: {}", smtp.auth_tls.join(" "))?;
+        }
+        writeln!(file, "- Unauthenticated MAIL FROM accepted: {}", yes_no(smtp.unauthenticated_mail_accepted))?;
+        writeln!(file, "- Open relay: {}{}", yes_no(smtp.open_relay),
+            smtp.relay_reply.as_deref().map(|r| format!(" ({})", sanitize_string(r))).unwrap_or_default())?;
+    }
+
+    // SIP agent and registration policy per transport
+    for sip in &result.sip {
+        writeln!(file, "\nSIP ({}): {}", sip.transport, sanitize_string(&sip.status))?;
+        if let Some(agent) = &sip.agent {
+            writeln!(file, "- Agent: {}", sanitize_string(agent))?;
+        }
+        if !sip.methods.is_empty() {
+            writeln!(file, "- Methods: {}", sip.methods.join(", "))?;
+        }
+        if let Some(registration) = &sip.registration {
+            writeln!(file, "- Registration: {}{}", sanitize_string(registration),
+                sip.realm.as_deref().map(|realm| format!(" (realm {})", sanitize_string(realm))).unwrap_or_default())?;
+        }
+        if !sip.rtp_relays.is_empty() {
+            writeln!(file, "- RTP relays: {}", sip.rtp_relays.iter().map(|p| p.to_string()).collect::<Vec<_>>().join(", "))?;
+        }
+    }
+
+    // Printer model and exposed functions if enumerated
+    if let Some(printer) = &result.printer {
+        writeln!(file, "\nPrinter ({}):", printer.protocol)?;
+        for (label, value) in [("Model", &printer.model), ("Firmware", &printer.firmware), ("Serial", &printer.serial),
+                               ("Location", &printer.location), ("Status", &printer.status)] {
+            if let Some(value) = value {
+                writeln!(file, "- {}: {}", label, sanitize_string(value))?;
+            }
+        }
+        if !printer.functions.is_empty() {
+            writeln!(file, "- Exposed functions: {}", printer.functions.join(", "))?;
+        }
+    }
+
     // Banner if available
     if let Some(banner) = &result.banner {
         writeln!(file, "\nBanner: {}", sanitize_string(banner))?;
     }
+
+    // Captured response if available
+    if let Some(capture) = &result.captured_response {
+        writeln!(file, "Captured Response ({} of {} bytes): {}",
+            capture.hex.len() / 2, capture.total_bytes, capture.hex)?;
+    }
     
     // Certificate info if available
     if let Some(cert) = &result.cert_info {
@@ -368,6 +598,27 @@ fn format_port_text(file: &mut File, port: u16, result: &PortResult) -> Result<(
                 bits
             )?;
         }
+        if let Some(trusted) = cert.trusted {
+            writeln!(file, "- Trusted: {}", if trusted { "yes" } else { "no" })?;
+        }
+        if let Some(stapled) = cert.ocsp_stapled {
+            writeln!(file, "- OCSP Stapled: {}", if stapled { "yes" } else { "no" })?;
+        }
+        for (depth, link) in cert.chain.iter().enumerate() {
+            writeln!(file, "- Chain [{}]: {} (issuer: {})", depth + 1, link.subject, link.issuer)?;
+        }
+        for issue in &cert.issues {
+            writeln!(file, "- Issue: {} ({}): {}", issue.kind, issue.severity, issue.detail)?;
+        }
+    }
+    if let Some(auth) = &result.client_auth {
+        writeln!(file, "Client Certificate: {}", format_client_auth(auth))?;
+    }
+    if !result.sni_certificates.is_empty() {
+        file.write_all(b"\nSNI Certificates:\n")?;
+        for sni in &result.sni_certificates {
+            writeln!(file, "- {}", format_sni_certificate(sni))?;
+        }
     }
     
     // Vulnerabilities if found
@@ -377,6 +628,14 @@ fn format_port_text(file: &mut File, port: u16, result: &PortResult) -> Result<(
             writeln!(file, "- {}", vuln)?;
         }
     }
+
+    // Script findings if any
+    for script in &result.script_output {
+        writeln!(file, "\nScript {}:", script.script)?;
+        for finding in &script.findings {
+            writeln!(file, "- {}", finding)?;
+        }
+    }
     
     // Add a separator
     file.write_all(b"\n----------\n\n")?;
@@ -392,6 +651,58 @@ pub fn print_results(results: &ScanResults, verbose: bool) -> Result<()> {
     // Print header
     println!("{}", style("Quantum Scanner Results").cyan().bold());
     println!("Target: {} ({})", style(&results.target).green(), results.target_ip);
+    if let Some(mac) = &results.mac_address {
+        println!("MAC Address: {} ({})", mac, style(results.mac_vendor.as_deref().unwrap_or("unknown vendor")).yellow());
+    }
+    if let Some(asset) = &results.asset {
+        println!("Asset: {}", style(asset).yellow());
+    }
+    if let Some(meta) = &results.metadata {
+        println!("Run ID: {} ({} {})", meta.run_id, meta.tool_name, meta.tool_version);
+        if verbose {
+            println!("Config Hash: {}", meta.config_hash);
+            println!("Command Line: {}", meta.command_line.join(" "));
+        }
+    }
+    
+    if !results.skipped_probes.is_empty() || !results.skipped_phases.is_empty() {
+        println!("{}", style(format!("Time budget ran out: {} ports not fully probed; skipped phases: {}",
+            results.skipped_probes.len(),
+            if results.skipped_phases.is_empty() { "none".to_string() } else { results.skipped_phases.join(", ") })).yellow());
+    }
+    if let Some(edge) = &results.edge {
+        println!("{} {}", style("Edge:").red().bold(),
+                 style(format!("{}; results describe the edge, not the origin", edge)).yellow());
+        if verbose {
+            for evidence in &edge.evidence {
+                println!("  - {}", evidence);
+            }
+        }
+        for candidate in &edge.origin_candidates {
+            println!("{} {} ({})", style("Origin candidate:").green().bold(), style(candidate.ip).green(), candidate.evidence.join("; "));
+        }
+    }
+    if let Some(cloud) = &results.cloud {
+        println!("{} {} (metadata service: {})", style("Cloud:").cyan().bold(), style(&cloud.provider).cyan(), cloud.metadata_endpoint);
+        if verbose {
+            for evidence in &cloud.evidence {
+                println!("  - {}", evidence);
+            }
+        }
+        if !cloud.ssrf_candidates.is_empty() {
+            println!("{} ports {}", style("SSRF candidates:").yellow().bold(),
+                     cloud.ssrf_candidates.iter().map(|p| p.to_string()).collect::<Vec<_>>().join(", "));
+        }
+    }
+    for sign in &results.interference {
+        println!("{} {} ({} ports unreliable)", style("Interference:").red().bold(), style(&sign.detail).yellow(), sign.ports.len());
+    }
+    for outlier in &results.outliers {
+        println!("{} {}", style("Outlier:").red().bold(), style(&outlier.detail).yellow());
+    }
+    for deviation in &results.deviations {
+        println!("{} {}", style("Baseline deviation:").red().bold(), style(&deviation.detail).yellow());
+    }
     
     // Display scan mode
     let has_service_info = results.results.values()
@@ -421,6 +732,7 @@ pub fn print_results(results: &ScanResults, verbose: bool) -> Result<()> {
         
         // Display scan statistics
         println!("Total Packets: {}", results.packets_sent);
+        println!("Bytes Sent: {}", results.bytes_sent);
         println!("Successful Operations: {}", results.successful_scans);
         
         if results.packets_sent > 0 {
@@ -439,6 +751,67 @@ pub fn print_results(results: &ScanResults, verbose: bool) -> Result<()> {
         );
     }
     
+    // Resolver path problems that affect how far results can be trusted
+    if let Some(dns) = results.dns_sanity.as_ref().filter(|dns| !dns.warnings.is_empty()) {
+        println!("\n{}", style("DNS Resolver Warnings").yellow().bold());
+        for warning in &dns.warnings {
+            println!("  - {}", warning);
+        }
+    }
+
+    // Calibrated probe timing
+    if let Some(timing) = &results.host_timing {
+        println!("\nProbe Timing: {}", format_host_timing(timing));
+    }
+
+    // Techniques chosen by the pre-probe
+    if let Some(selection) = &results.technique_selection {
+        println!("\nTechnique Selection: {}", format_technique_selection(selection));
+        for reason in &selection.reasons {
+            println!("  - {}", reason);
+        }
+    }
+
+    // ICMP host characterization
+    if let Some(icmp) = &results.icmp_info {
+        println!("\n{}", style("ICMP Host Information").cyan().bold());
+        println!("Timestamp Reply: {}", if icmp.timestamp_reply { style("yes").yellow() } else { style("no").green() });
+        if let Some(offset) = icmp.clock_offset_ms {
+            println!("Clock Offset: {} ms", offset);
+        }
+        if let Some(mask) = &icmp.netmask {
+            println!("Address Mask: {}", style(mask).yellow());
+        }
+        if let Some(unreach) = &icmp.udp_unreachable {
+            println!("UDP {} Unreachable: code {} ({}) from {}", unreach.port, unreach.icmp_code, unreach.meaning, unreach.source);
+            if verbose {
+                println!("  TTL {}, DF {}, {} bytes quoted, quote intact: {}",
+                         unreach.reply_ttl, unreach.dont_fragment, unreach.quoted_bytes, unreach.quote_intact);
+            }
+        }
+        for hint in &icmp.os_hints {
+            println!("  - {}", hint);
+        }
+    }
+    
+    // IP protocol scan results
+    if !results.ip_protocols.is_empty() {
+        println!("\n{}", style("IP Protocols").cyan().bold());
+        let mut protocols: Vec<_> = results.ip_protocols.iter().collect();
+        protocols.sort_by_key(|(protocol, _)| **protocol);
+        for (protocol, result) in protocols {
+            let state = match result.status {
+                PortStatus::Open => style(result.status.to_string()).green(),
+                PortStatus::Closed => style(result.status.to_string()).red(),
+                _ => style(result.status.to_string()).yellow(),
+            };
+            println!("{:<6} {:<15} {}", protocol, result.name, state);
+            if verbose {
+                println!("       {}", style(&result.reason).dim());
+            }
+        }
+    }
+    
     println!("\n{} open ports discovered", style(results.open_ports.len()).yellow().bold());
     
     // If no open ports, end here
@@ -545,8 +918,24 @@ fn print_detailed_results(results: &ScanResults) -> Result<()> {
                 println!("  Version: {}", version);
             }
             
+            // Print ML alternatives so borderline identifications stand out
+            if !result.ml_candidates.is_empty() {
+                println!("  ML Candidates: {}", format_ml_candidates(&result.ml_candidates));
+            }
+            if let Some(payload) = &result.payload {
+                println!("  Payload: {}", format_payload(payload));
+            }
+            
             // Print service fingerprinting information
-            if !result.tcp_states.is_empty() {
+            if let Some(reason) = &result.unreliable {
+                println!("  {} {}", style("Unreliable:").red().bold(), style(reason).yellow());
+            }
+            if let Some(consensus) = &result.consensus {
+                println!("  State: {}", style(format_consensus(consensus)).green());
+                for line in &consensus.evidence {
+                    println!("    - {}", line);
+                }
+            } else if !result.tcp_states.is_empty() {
                 println!("  Scan Results:");
                 // Sort scan types alphabetically
                 let mut scan_types: Vec<(&ScanType, &PortStatus)> = result.tcp_states.iter().collect();
@@ -605,8 +994,25 @@ fn print_detailed_results(results: &ScanResults) -> Result<()> {
             if !result.vulns.is_empty() {
                 println!("\nPotential Vulnerabilities:");
                 for vuln in &result.vulns {
-                    println!("- {} ({})", style(&vuln.id).red().bold(), vuln.severity);
+                    println!("- {}: {} ({}, {})", style(&vuln.id).red().bold(), vuln.display_title(), vuln.severity, vuln.confidence);
                     println!("  Description: {}", vuln.description);
+                    for evidence in &vuln.evidence {
+                        println!("  Evidence: {}", evidence);
+                    }
+                    for reference in &vuln.references {
+                        println!("  Reference: {}", reference);
+                    }
+                    if let Some(remediation) = &vuln.remediation {
+                        println!("  Remediation: {}", remediation);
+                    }
+                }
+            }
+
+            // Print script findings if any
+            for script in &result.script_output {
+                println!("\nScript {}:", style(&script.script).cyan());
+                for finding in &script.findings {
+                    println!("- {}", finding);
                 }
             }
         }
@@ -699,11 +1105,24 @@ pub fn print_port_details(results: &ScanResults, port: u16, verbose: bool) -> Re
             }
         }
         
+        if !port_result.ml_candidates.is_empty() {
+            println!("ML Candidates: {}", format_ml_candidates(&port_result.ml_candidates));
+        }
+        if let Some(payload) = &port_result.payload {
+            println!("Payload: {}", format_payload(payload));
+        }
+        
         // Determine state
         let mut states = Vec::new();
         
+        // The consensus already carries every technique's verdict
+        let mut scan_types: Vec<(&ScanType, &PortStatus)> = if let Some(consensus) = &port_result.consensus {
+            states.push(style(format_consensus(consensus)).green().to_string());
+            Vec::new()
+        } else {
+            port_result.tcp_states.iter().collect()
+        };
         // Sort scan types alphabetically
-        let mut scan_types: Vec<(&ScanType, &PortStatus)> = port_result.tcp_states.iter().collect();
         scan_types.sort_by(|a, b| a.0.cmp(b.0));
         
         for (scan_type, status) in scan_types {
@@ -769,14 +1188,55 @@ pub fn print_port_details(results: &ScanResults, port: u16, verbose: bool) -> Re
                         println!("  - {}", san);
                     }
                 }
+                if let Some(trusted) = cert.trusted {
+                    println!("Trusted: {}", if trusted { style("yes").green() } else { style("no").red() });
+                }
+                if let Some(stapled) = cert.ocsp_stapled {
+                    println!("OCSP Stapled: {}", if stapled { style("yes").green() } else { style("no").yellow() });
+                }
+                for (depth, link) in cert.chain.iter().enumerate() {
+                    println!("Chain [{}]: {} (issuer: {})", depth + 1, link.subject, link.issuer);
+                }
+                if !cert.issues.is_empty() {
+                    println!("Certificate Issues:");
+                    for issue in &cert.issues {
+                        println!("  - {} ({}): {}", style(issue.kind).yellow(), issue.severity, issue.detail);
+                    }
+                }
+            }
+            if let Some(auth) = &port_result.client_auth {
+                println!("Client Certificate: {}", style(format_client_auth(auth)).yellow());
+            }
+            if !port_result.sni_certificates.is_empty() {
+                println!("\n{}", style("SNI Certificates:").underlined());
+                for sni in &port_result.sni_certificates {
+                    println!("  - {}", format_sni_certificate(sni));
+                }
             }
             
             // Vulnerabilities if detected
             if !port_result.vulns.is_empty() {
                 println!("\n{}", style("Potential Vulnerabilities:").underlined());
                 for vuln in &port_result.vulns {
-                    println!("- {} ({})", style(&vuln.id).red().bold(), vuln.severity);
+                    println!("- {}: {} ({}, {})", style(&vuln.id).red().bold(), vuln.display_title(), vuln.severity, vuln.confidence);
                     println!("  Description: {}", vuln.description);
+                    for evidence in &vuln.evidence {
+                        println!("  Evidence: {}", evidence);
+                    }
+                    for reference in &vuln.references {
+                        println!("  Reference: {}", reference);
+                    }
+                    if let Some(remediation) = &vuln.remediation {
+                        println!("  Remediation: {}", remediation);
+                    }
+                }
+            }
+
+            // Script findings if any
+            for script in &port_result.script_output {
+                println!("\n{}", style(format!("Script {}:", script.script)).underlined());
+                for finding in &script.findings {
+                    println!("- {}", finding);
                 }
             }
         }
@@ -787,6 +1247,74 @@ pub fn print_port_details(results: &ScanResults, port: u16, verbose: bool) -> Re
     Ok(())
 }
 
+/// Render ML candidates as "http 82%, https 11%, ssh 4%"
+/// Render a consensus as "open (confidence 0.87, dissent: FIN)"
+fn format_consensus(consensus: &StateConsensus) -> String {
+    if consensus.dissenting.is_empty() {
+        format!("{} (confidence {:.2})", consensus.state, consensus.confidence)
+    } else {
+        format!("{} (confidence {:.2}, dissent: {})", consensus.state, consensus.confidence, consensus.dissenting.join(", "))
+    }
+}
+
+fn format_ml_candidates(candidates: &[(String, f32)]) -> String {
+    candidates.iter()
+        .map(|(service, confidence)| format!("{} {:.0}%", service, confidence * 100.0))
+        .collect::<Vec<_>>()
+        .join(", ")
+}
+
+/// Render a client certificate request as "required (acceptable CAs: CN=Corp Root; CN=Ops CA)"
+fn format_client_auth(auth: &ClientAuthInfo) -> String {
+    let need = if auth.required { "required" } else { "optional" };
+    if auth.acceptable_cas.is_empty() {
+        format!("{} (any CA)", need)
+    } else {
+        format!("{} (acceptable CAs: {})", need, auth.acceptable_cas.join("; "))
+    }
+}
+
+/// Render a harvested certificate as "a.example, b.example -> CN=a.example (SANs: a.example, c.example)"
+fn format_sni_certificate(sni: &SniCertificate) -> String {
+    let mut line = format!("{} -> {}", sni.names.join(", "), sni.certificate.subject);
+    if !sni.certificate.alt_names.is_empty() {
+        line.push_str(&format!(" (SANs: {})", sni.certificate.alt_names.join(", ")));
+    }
+    if !sni.differs_from_default {
+        line.push_str(" [default certificate]");
+    }
+    line
+}
+
+/// Render a payload profile as "encrypted (7.94 bits/byte over 512 bytes, tls record)"
+fn format_payload(payload: &PayloadProfile) -> String {
+    if payload.class == PayloadClass::NoData {
+        return payload.class.to_string();
+    }
+    match &payload.format {
+        Some(format) => format!("{} ({:.2} bits/byte over {} bytes, {})", payload.class, payload.entropy, payload.length, format),
+        None => format!("{} ({:.2} bits/byte over {} bytes)", payload.class, payload.entropy, payload.length),
+    }
+}
+
+/// Render calibrated timing as "srtt 12.3 ms ± 4.1 ms (5 samples, 0 lost), timeout 100 ms, 1 retries"
+fn format_host_timing(timing: &HostTiming) -> String {
+    match (timing.srtt_ms, timing.rttvar_ms) {
+        (Some(srtt), Some(rttvar)) => format!("srtt {:.1} ms ± {:.1} ms ({} samples, {} lost), timeout {} ms, {} retries",
+            srtt, rttvar, timing.samples, timing.lost, timing.probe_timeout_ms, timing.retries),
+        _ => format!("no calibration reply ({} lost), timeout {} ms", timing.lost, timing.probe_timeout_ms),
+    }
+}
+
+/// Render a technique selection as "SYN, FIN (initial TTL 64, Linux/Unix)"
+fn format_technique_selection(selection: &TechniqueSelection) -> String {
+    let techniques = selection.scan_types.iter().map(|t| t.to_string()).collect::<Vec<_>>().join(", ");
+    match (selection.initial_ttl, &selection.os_family) {
+        (Some(ttl), Some(family)) => format!("{} (initial TTL {}, {})", techniques, ttl, family),
+        _ => techniques,
+    }
+}
+
 /// Sanitize a banner string for safe display
 /// 
 /// # Arguments
@@ -807,42 +1335,1025 @@ fn sanitize_banner(banner: &str) -> String {
     }
 }
 
-#[allow(dead_code)]
-pub fn export_to_csv(results: &ScanResults, writer: &mut dyn Write) -> Result<()> {
-    // Write the header row
-    writeln!(writer, "port,status,service,version,reason,banner")?;
-    
-    // Write each port result
-    let mut ports: Vec<u16> = results.results.keys().copied().collect();
-    ports.sort_unstable();
-    
-    for port in ports {
-        if let Some(port_result) = results.results.get(&port) {
-            // Determine the overall port status
-            let status = match port_result.final_status {
-                PortStatus::Open => "open",
-                PortStatus::Closed => "closed", 
-                PortStatus::Filtered => "filtered",
-                PortStatus::Unfiltered => "unfiltered",
-                PortStatus::OpenFiltered => "open|filtered",
-            };
-            
-            // Get basic port information
-            let service = port_result.service.as_deref().unwrap_or("").replace(",", "");
-            let version = port_result.version.as_deref().unwrap_or("").replace(",", "");
-            let reason = port_result.reason.as_deref().unwrap_or("").replace(",", "");
-            
-            // Get banner, limiting to first line
-            let banner = match &port_result.banner {
-                Some(b) => b.lines().next().unwrap_or("").replace(",", "").replace("\"", "'"),
-                None => String::new(),
-            };
-            
-            // Write the CSV row
-            writeln!(writer, "{},{},{},{},{},\"{}\"", 
-                port, status, service, version, reason, banner)?;
+/// Quote a CSV field when it holds a separator, quote or line break
+fn csv_field(value: &str) -> String {
+    if value.contains([',', '"', '\n', '\r']) {
+        format!("\"{}\"", value.replace('"', "\"\""))
+    } else {
+        value.to_string()
+    }
+}
+
+/// Render one row per port of one or more hosts as CSV, sorted by host and port
+///
+/// Columns: host, port, proto, status, service, version, reason, first banner line,
+/// number of findings, highest finding severity and run ID.
+pub fn format_csv_report(results: &[ScanResults]) -> String {
+    let mut csv = String::from("host,port,proto,status,service,version,reason,banner,findings,max_severity,run_id\n");
+    for host in results {
+        let run_id = host.metadata.as_ref().map(|meta| meta.run_id.as_str()).unwrap_or("");
+        let mut ports: Vec<(&u16, &PortResult)> = host.results.iter().collect();
+        ports.sort_by_key(|(port, _)| **port);
+        for (port, result) in ports {
+            let proto = if result.udp_state.is_some() && result.tcp_states.is_empty() { "udp" } else { "tcp" };
+            let banner = result.banner.as_deref().and_then(|b| b.lines().next()).unwrap_or("");
+            let max_severity = result.vulns.iter()
+                .min_by_key(|v| severity_rank(&v.severity))
+                .map(|v| v.severity.as_str())
+                .unwrap_or("");
+            let row = [
+                host.target_ip.clone(),
+                port.to_string(),
+                proto.to_string(),
+                result.final_status.to_string(),
+                result.service.clone().unwrap_or_default(),
+                result.version.clone().unwrap_or_default(),
+                result.reason.clone().unwrap_or_default(),
+                banner.to_string(),
+                result.vulns.len().to_string(),
+                max_severity.to_string(),
+                run_id.to_string(),
+            ];
+            csv.push_str(&row.iter().map(|field| csv_field(field)).collect::<Vec<_>>().join(","));
+            csv.push('\n');
         }
     }
+    csv
+}
+
+/// Report format written by `--output-format`
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum)]
+pub enum OutputFormat {
+    /// Plain-text report (styled on a terminal)
+    Text,
+    /// JSON, as with -j/--json
+    Json,
+    /// Markdown report for engagement deliverables
+    Markdown,
+    /// SARIF 2.1.0 log for CI/CD security gates and code scanning dashboards
+    Sarif,
+    /// Nessus v2 XML (.nessus) for vulnerability management imports
+    Nessus,
+    /// Greenbone/OpenVAS report XML for import with GMP create_report
+    Greenbone,
+    /// One CSV row per port, for spreadsheets
+    Csv,
+    /// Self-contained HTML report, printable to PDF
+    Html,
+}
+
+/// Rank of a vulnerability severity, most severe first
+fn severity_rank(severity: &str) -> u8 {
+    match severity.to_ascii_lowercase().as_str() {
+        "critical" => 0,
+        "high" => 1,
+        "medium" | "moderate" => 2,
+        "low" => 3,
+        _ => 4,
+    }
+}
+
+/// Make a value safe inside a Markdown table cell
+fn md_cell(value: &str) -> String {
+    let value = sanitize_string(&value.replace(['\r', '\n'], " ")).replace('|', "\\|");
+    if value.trim().is_empty() { "-".to_string() } else { value.trim().to_string() }
+}
+
+/// One issue as it appears across hosts; the first occurrence stands for the rest
+#[derive(Debug, Serialize)]
+pub struct AggregatedFinding<'a> {
+    pub finding: &'a Finding,
+    /// `host:port` of every occurrence, in host then port order
+    pub affected: Vec<String>,
+    /// Distinct hosts among `affected`
+    pub hosts: usize,
+    /// Whether every occurrence has the same description as `finding`
+    pub uniform: bool,
+}
+
+/// Group the vulnerabilities and anomalies of several hosts by issue
+///
+/// Findings with the same id and severity are the same issue, whatever their
+/// per-port description says. Reports list each issue once with the endpoints it
+/// affects; JSON output keeps every occurrence on its port.
+///
+/// # Arguments
+/// * `results` - Results of one or more hosts
+///
+/// # Returns
+/// * `Vec<AggregatedFinding>` - One entry per issue, most severe and widespread first
+pub fn aggregate_findings(results: &[ScanResults]) -> Vec<AggregatedFinding<'_>> {
+    let mut hosts: Vec<&ScanResults> = results.iter().collect();
+    hosts.sort_by(|a, b| a.target_ip.cmp(&b.target_ip));
+
+    let mut aggregated: Vec<AggregatedFinding> = Vec::new();
+    for host in hosts {
+        let mut ports: Vec<(&u16, &PortResult)> = host.results.iter().collect();
+        ports.sort_by_key(|(port, _)| **port);
+        for (port, result) in ports {
+            for finding in result.vulns.iter().chain(&result.anomalies) {
+                let endpoint = format!("{}:{}", host.target_ip, port);
+                match aggregated.iter_mut().find(|a| a.finding.id == finding.id && a.finding.severity == finding.severity) {
+                    Some(entry) => {
+                        // Hosts are visited in turn, so a new host differs from the last endpoint's
+                        if entry.affected.last().and_then(|e| e.rsplit_once(':')).map(|(h, _)| h) != Some(host.target_ip.as_str()) {
+                            entry.hosts += 1;
+                        }
+                        entry.uniform &= entry.finding.description == finding.description;
+                        entry.affected.push(endpoint);
+                    }
+                    None => aggregated.push(AggregatedFinding { finding, affected: vec![endpoint], hosts: 1, uniform: true }),
+                }
+            }
+        }
+    }
+    aggregated.sort_by(|a, b| severity_rank(&a.finding.severity).cmp(&severity_rank(&b.finding.severity))
+        .then(b.affected.len().cmp(&a.affected.len()))
+        .then(a.finding.id.cmp(&b.finding.id)));
+    aggregated
+}
+
+/// Describe an aggregated finding in one line: its own description when every
+/// occurrence shares it, otherwise the first one and how many differ
+fn aggregated_description(entry: &AggregatedFinding) -> String {
+    if entry.uniform || entry.affected.len() == 1 {
+        entry.finding.description.clone()
+    } else {
+        format!("{} (first of {} occurrences)", entry.finding.description, entry.affected.len())
+    }
+}
+
+/// Plain-text list of the issues found across several hosts, one block per issue
+///
+/// # Arguments
+/// * `results` - Results of the hosts of one run
+///
+/// # Returns
+/// * `String` - The summary, or an empty string when nothing was found
+pub fn format_findings_summary(results: &[ScanResults]) -> String {
+    let aggregated = aggregate_findings(results);
+    if aggregated.is_empty() {
+        return String::new();
+    }
+    let mut output = format!("Findings Across {} Hosts:\n", results.len());
+    for entry in &aggregated {
+        output.push_str(&format!("- [{}] {}: {} ({} hosts, {} ports)\n", entry.finding.severity, entry.finding.id,
+            entry.finding.display_title(), entry.hosts, entry.affected.len()));
+        output.push_str(&format!("  Description: {}\n", aggregated_description(entry)));
+        output.push_str(&format!("  Affected: {}\n", entry.affected.join(", ")));
+        if let Some(remediation) = &entry.finding.remediation {
+            output.push_str(&format!("  Remediation: {}\n", remediation));
+        }
+    }
+    output
+}
+
+/// Render one or more hosts as a Markdown report
+///
+/// The report opens with a summary table (one row per host), then lists every issue
+/// once, ordered by severity, with the endpoints it affects, then has one section per
+/// host with its open ports. Anomalies are Info findings.
+///
+/// # Arguments
+/// * `results` - Results of one or more hosts
+///
+/// # Returns
+/// * `String` - The report, ready to paste into a reporting platform
+pub fn format_markdown_report(results: &[ScanResults]) -> String {
+    let mut md = String::new();
+    md.push_str("# Quantum Scanner Report\n\n");
+    if let Some(meta) = results.iter().find_map(|r| r.metadata.as_ref()) {
+        md.push_str(&format!("- **Run ID:** {}\n", meta.run_id));
+        md.push_str(&format!("- **Tool:** {} {}\n", meta.tool_name, meta.tool_version));
+        md.push_str(&format!("- **Started:** {}\n", meta.started_at.format("%Y-%m-%d %H:%M:%S UTC")));
+        md.push_str(&format!("- **Command line:** `{}`\n", meta.command_line.join(" ").replace('`', "'")));
+    }
+    md.push_str(&format!("- **Generated:** {}\n\n", Utc::now().format("%Y-%m-%d %H:%M:%S UTC")));
+
+    let findings = aggregate_findings(results);
+
+    md.push_str("## Summary\n\n");
+    md.push_str("| Host | Target | Open Ports | Critical | High | Medium | Low | Info | Duration (s) |\n");
+    md.push_str("|------|--------|-----------:|---------:|-----:|-------:|----:|-----:|-------------:|\n");
+    for host in results {
+        let mut counts = [0usize; 5];
+        for finding in host.results.values().flat_map(|r| r.vulns.iter().chain(&r.anomalies)) {
+            counts[severity_rank(&finding.severity) as usize] += 1;
+        }
+        md.push_str(&format!("| {} | {} | {} | {} | {} | {} | {} | {} | {:.1} |\n",
+            md_cell(&host.target_ip), md_cell(&host.target), host.open_ports.len(),
+            counts[0], counts[1], counts[2], counts[3], counts[4],
+            host.end_time.signed_duration_since(host.start_time).num_milliseconds() as f64 / 1000.0));
+    }
+    md.push('\n');
+
+    // Hosts answered by a CDN or load balancer, whose findings belong to the edge
+    let edges: Vec<(&ScanResults, &EdgeInfo)> = results.iter()
+        .filter_map(|host| host.edge.as_ref().map(|edge| (host, edge)))
+        .collect();
+    if !edges.is_empty() {
+        md.push_str("## Edge Infrastructure\n\n");
+        md.push_str("These hosts are CDN or load-balancer frontends; their results describe the edge, not the origin.\n\n");
+        md.push_str("| Host | Target | Edge | Evidence |\n");
+        md.push_str("|------|--------|------|----------|\n");
+        for (host, edge) in edges {
+            md.push_str(&format!("| {} | {} | {} | {} |\n", md_cell(&host.target_ip), md_cell(&host.target),
+                md_cell(&edge.to_string()), md_cell(&edge.evidence.join("; "))));
+        }
+        md.push('\n');
+
+        let candidates: Vec<(&ScanResults, &OriginCandidate)> = results.iter()
+            .flat_map(|host| host.edge.iter().flat_map(move |edge| edge.origin_candidates.iter().map(move |candidate| (host, candidate))))
+            .collect();
+        if !candidates.is_empty() {
+            md.push_str("## Origin Candidates\n\n");
+            md.push_str("| Edge Host | Candidate | Evidence |\n");
+            md.push_str("|-----------|-----------|----------|\n");
+            for (host, candidate) in candidates {
+                md.push_str(&format!("| {} | {} | {} |\n", md_cell(&host.target), candidate.ip, md_cell(&candidate.evidence.join("; "))));
+            }
+            md.push('\n');
+        }
+    }
+
+    let signs: Vec<(&ScanResults, &InterferenceSign)> = results.iter()
+        .flat_map(|host| host.interference.iter().map(move |sign| (host, sign)))
+        .collect();
+    if !signs.is_empty() {
+        md.push_str("## Inline Interference\n\n");
+        md.push_str("Replies on these hosts look forged by an inline IPS or firewall; states and services on the listed ports may not be the host's.\n\n");
+        md.push_str("| Host | Sign | Detail | Unreliable Ports |\n");
+        md.push_str("|------|------|--------|-----------------:|\n");
+        for (host, sign) in signs {
+            md.push_str(&format!("| {} | {} | {} | {} |\n", md_cell(&host.target_ip), sign.kind, md_cell(&sign.detail), sign.ports.len()));
+        }
+        md.push('\n');
+    }
+
+    let mut harvested: Vec<(&ScanResults, u16, &SniCertificate)> = results.iter()
+        .flat_map(|host| host.results.iter().flat_map(move |(port, result)| result.sni_certificates.iter()
+            .filter(|sni| sni.differs_from_default)
+            .map(move |sni| (host, *port, sni))))
+        .collect();
+    if !harvested.is_empty() {
+        md.push_str("## SNI Certificates\n\n");
+        md.push_str("Certificates served for names from --sni-list that differ from the default: other tenants or services behind the same address.\n\n");
+        md.push_str("| Host | Port | Names | Subject | Alternative Names |\n");
+        md.push_str("|------|-----:|-------|---------|-------------------|\n");
+        harvested.sort_by(|a, b| a.0.target_ip.cmp(&b.0.target_ip).then(a.1.cmp(&b.1)));
+        for (host, port, sni) in harvested {
+            md.push_str(&format!("| {} | {} | {} | {} | {} |\n", md_cell(&host.target_ip), port,
+                md_cell(&sni.names.join(", ")), md_cell(&sni.certificate.subject), md_cell(&sni.certificate.alt_names.join(", "))));
+        }
+        md.push('\n');
+    }
+
+    let clouds: Vec<(&ScanResults, &CloudInfo)> = results.iter()
+        .filter_map(|host| host.cloud.as_ref().map(|cloud| (host, cloud)))
+        .collect();
+    if !clouds.is_empty() {
+        md.push_str("## Cloud Hosts\n\n");
+        md.push_str("Follow-up targets for SSRF: the provider's metadata service and the ports that relay it, name it or redirect through URL parameters.\n\n");
+        md.push_str("| Host | Provider | Evidence | Metadata Service | SSRF Candidates |\n");
+        md.push_str("|------|----------|----------|------------------|-----------------|\n");
+        for (host, cloud) in clouds {
+            let ports = cloud.ssrf_candidates.iter().map(|p| p.to_string()).collect::<Vec<_>>().join(", ");
+            md.push_str(&format!("| {} | {} | {} | {} | {} |\n", md_cell(&host.target_ip), md_cell(&cloud.provider),
+                md_cell(&cloud.evidence.join("; ")), md_cell(&cloud.metadata_endpoint), md_cell(&ports)));
+        }
+        md.push('\n');
+    }
+
+    // Then differences from the known-good baseline, then hosts that differ from
+    // the rest of the run, then the findings
+    let deviations: Vec<(&ScanResults, &BaselineDeviation)> = results.iter()
+        .flat_map(|host| host.deviations.iter().map(move |deviation| (host, deviation)))
+        .collect();
+    if !deviations.is_empty() {
+        md.push_str("## Baseline Deviations\n\n");
+        md.push_str("| Host | Port | Deviation |\n");
+        md.push_str("|------|-----:|-----------|\n");
+        for (host, deviation) in deviations {
+            md.push_str(&format!("| {} | {} | {} |\n", md_cell(&host.target_ip), deviation.port, md_cell(&deviation.detail)));
+        }
+        md.push('\n');
+    }
+
+    let outliers: Vec<(&ScanResults, &HostOutlier)> = results.iter()
+        .flat_map(|host| host.outliers.iter().map(move |outlier| (host, outlier)))
+        .collect();
+    if !outliers.is_empty() {
+        md.push_str("## Outliers\n\n");
+        md.push_str("| Host | Port | Difference |\n");
+        md.push_str("|------|-----:|------------|\n");
+        for (host, outlier) in outliers {
+            md.push_str(&format!("| {} | {} | {} |\n", md_cell(&host.target_ip), outlier.port, md_cell(&outlier.detail)));
+        }
+        md.push('\n');
+    }
+
+    md.push_str("## Findings\n\n");
+    if findings.is_empty() {
+        md.push_str("No vulnerabilities or anomalies were identified.\n\n");
+    } else {
+        md.push_str("| Severity | Confidence | Finding | Hosts | Affected | Description | Remediation |\n");
+        md.push_str("|----------|------------|---------|------:|----------|-------------|-------------|\n");
+        for entry in &findings {
+            let finding = entry.finding;
+            md.push_str(&format!("| {} | {} | {} ({}) | {} | {} | {} | {} |\n",
+                md_cell(&finding.severity), finding.confidence, md_cell(finding.display_title()), md_cell(&finding.id), entry.hosts,
+                md_cell(&entry.affected.join(", ")), md_cell(&aggregated_description(entry)), md_cell(finding.remediation.as_deref().unwrap_or(""))));
+        }
+        md.push_str("\nPer-port descriptions and evidence of every occurrence are in the JSON results.\n");
+        md.push('\n');
+    }
+
+    for host in results {
+        if host.target == host.target_ip {
+            md.push_str(&format!("## {}\n\n", host.target_ip));
+        } else {
+            md.push_str(&format!("## {} ({})\n\n", host.target, host.target_ip));
+        }
+        if let Some(os) = &host.os_summary {
+            md.push_str(&format!("- **OS:** {}\n", md_cell(os)));
+        }
+        if let Some(mac) = &host.mac_address {
+            md.push_str(&format!("- **MAC:** {} ({})\n", mac, host.mac_vendor.as_deref().unwrap_or("unknown vendor")));
+        }
+        if let Some(asset) = &host.asset {
+            md.push_str(&format!("- **Asset:** {}\n", md_cell(&asset.to_string())));
+        }
+        if let Some(risk) = &host.risk_assessment {
+            md.push_str(&format!("- **Risk:** {}\n", md_cell(risk)));
+        }
+        if !host.skipped_probes.is_empty() || !host.skipped_phases.is_empty() {
+            md.push_str(&format!("- **Incomplete:** {} ports not fully probed within the time budget\n", host.skipped_probes.len()));
+        }
+
+        let mut ports: Vec<(&u16, &PortResult)> = host.results.iter()
+            .filter(|(_, r)| matches!(r.final_status, PortStatus::Open | PortStatus::OpenFiltered))
+            .collect();
+        ports.sort_by_key(|(port, _)| **port);
+        if ports.is_empty() {
+            md.push_str("\nNo open ports found.\n\n");
+            continue;
+        }
+        md.push_str("\n| Port | Proto | State | Service | Version | TLS | Certificate | Vulns |\n");
+        md.push_str("|-----:|-------|-------|---------|---------|-----|-------------|------:|\n");
+        for (port, result) in ports {
+            let proto = if result.udp_state.is_some() && result.tcp_states.is_empty() { "udp" } else { "tcp" };
+            md.push_str(&format!("| {} | {} | {} | {} | {} | {} | {} | {} |\n",
+                port, proto, md_cell(&result.final_status.to_string()),
+                md_cell(result.service.as_deref().unwrap_or("")),
+                md_cell(result.version.as_deref().unwrap_or("")),
+                md_cell(result.tls_protocol_version.as_deref().unwrap_or("")),
+                md_cell(result.cert_info.as_ref().map(|c| c.subject.as_str()).unwrap_or("")),
+                result.vulns.len()));
+        }
+        md.push('\n');
+    }
+    md
+}
+
+/// Save a Markdown report of one or more hosts
+///
+/// # Arguments
+/// * `results` - Results of one or more hosts
+/// * `output_path` - Path to the output file (encrypted with --encrypt-output)
+///
+/// # Returns
+/// * `Result<()>` - Success or error
+pub fn save_markdown_report(results: &[ScanResults], output_path: &Path) -> Result<()> {
+    crate::encryption::write_output(output_path, format_markdown_report(results).as_bytes())
+}
+
+/// SARIF level and GitHub `security-severity` score for a vulnerability severity
+fn sarif_level(severity: &str) -> (&'static str, &'static str) {
+    match severity_rank(severity) {
+        0 => ("error", "9.5"),
+        1 => ("error", "8.0"),
+        2 => ("warning", "5.5"),
+        3 => ("note", "3.0"),
+        _ => ("note", "0.0"),
+    }
+}
+
+/// Render vulnerabilities and security posture findings as a SARIF 2.1.0 log
+///
+/// Each distinct vulnerability id becomes a rule and each occurrence a result located
+/// at `<proto>://<host>:<port>`; security posture assessments share one rule at level
+/// note. The network endpoint stands in for a file, since there is no source code, so
+/// results carry it both as a physical artifact URI (for dashboards that require one)
+/// and as a logical location.
+///
+/// # Arguments
+/// * `results` - Results of one or more hosts, reported as one SARIF run
+///
+/// # Returns
+/// * `Result<String>` - The SARIF log as pretty-printed JSON
+pub fn format_sarif_report(results: &[ScanResults]) -> Result<String> {
+    let meta = results.iter().find_map(|r| r.metadata.as_ref());
+    let mut rules: Vec<serde_json::Value> = Vec::new();
+    let mut rule_ids: Vec<String> = Vec::new();
+    let mut sarif_results: Vec<serde_json::Value> = Vec::new();
+
+    let mut rule_index = |finding: &Finding| -> usize {
+        if let Some(index) = rule_ids.iter().position(|known| *known == finding.id) {
+            return index;
+        }
+        let (level, score) = sarif_level(&finding.severity);
+        let precision = match finding.confidence {
+            Confidence::Confirmed => "very-high",
+            Confidence::Firm => "high",
+            Confidence::Tentative => "low",
+        };
+        let mut rule = serde_json::json!({
+            "id": finding.id,
+            "name": finding.id,
+            "shortDescription": { "text": finding.display_title() },
+            "defaultConfiguration": { "level": level },
+            "properties": { "security-severity": score, "precision": precision, "tags": ["security", "network"] },
+        });
+        if let Some(remediation) = &finding.remediation {
+            rule["help"] = serde_json::json!({ "text": remediation });
+        }
+        if let Some(url) = finding.references.iter().find(|r| r.starts_with("http")) {
+            rule["helpUri"] = url.as_str().into();
+        }
+        rules.push(rule);
+        rule_ids.push(id.to_string());
+        rule_ids.len() - 1
+    };
+
+    for host in results {
+        let mut ports: Vec<(&u16, &PortResult)> = host.results.iter().collect();
+        ports.sort_by_key(|(port, _)| **port);
+        for (port, result) in ports {
+            let proto = if result.udp_state.is_some() && result.tcp_states.is_empty() { "udp" } else { "tcp" };
+            let endpoint = format!("{}://{}:{}", proto, host.target_ip, port);
+            let service = result.service.as_deref().unwrap_or("unknown");
+            let location = serde_json::json!({
+                "physicalLocation": {
+                    "artifactLocation": { "uri": endpoint },
+                    "region": { "startLine": 1 },
+                },
+                "logicalLocations": [{ "name": format!("{}:{}", host.target_ip, port), "fullyQualifiedName": endpoint, "kind": "resource" }],
+            });
+
+            let mut findings: Vec<(Finding, String)> = result.vulns.iter()
+                .map(|v| (v.clone(), format!("{} on {} ({}): {}", v.id, endpoint, service, v.description)))
+                .collect();
+            if let Some(posture) = &result.security_posture {
+                let finding = Finding {
+                    id: "security-posture".to_string(),
+                    title: "Security posture assessment".to_string(),
+                    description: posture.clone(),
+                    severity: "Info".to_string(),
+                    ..Default::default()
+                };
+                findings.push((finding, format!("{} ({}): {}", endpoint, service, posture)));
+            }
+            for (finding, message) in findings {
+                let index = rule_index(&finding);
+                sarif_results.push(serde_json::json!({
+                    "ruleId": finding.id,
+                    "ruleIndex": index,
+                    "level": sarif_level(&finding.severity).0,
+                    "message": { "text": message },
+                    "locations": [location.clone()],
+                    "partialFingerprints": { "endpointFinding/v1": format!("{}:{}:{}:{}", host.target_ip, proto, port, finding.id) },
+                    "properties": {
+                        "host": host.target_ip,
+                        "target": host.target,
+                        "port": port,
+                        "protocol": proto,
+                        "service": service,
+                        "version": result.version.as_deref().unwrap_or(""),
+                        "severity": finding.severity,
+                        "confidence": finding.confidence,
+                        "evidence": finding.evidence,
+                        "references": finding.references,
+                    },
+                }));
+            }
+        }
+    }
+
+    // SARIF has no nulls, so optional properties are only added when known
+    let mut invocation = serde_json::json!({ "executionSuccessful": true });
+    if let Some(start) = results.iter().map(|r| r.start_time).min() {
+        invocation["startTimeUtc"] = start.to_rfc3339().into();
+    }
+    if let Some(end) = results.iter().map(|r| r.end_time).max() {
+        invocation["endTimeUtc"] = end.to_rfc3339().into();
+    }
+    let mut run = serde_json::json!({
+        "tool": {
+            "driver": {
+                "name": meta.map_or("quantum_scanner", |m| m.tool_name.as_str()),
+                "version": meta.map_or(env!("CARGO_PKG_VERSION"), |m| m.tool_version.as_str()),
+                "rules": rules,
+            },
+        },
+        "results": sarif_results,
+    });
+    if let Some(meta) = meta {
+        invocation["commandLine"] = meta.command_line.join(" ").into();
+        run["automationDetails"] = serde_json::json!({ "id": format!("quantum_scanner/{}", meta.run_id) });
+    }
+    run["invocations"] = serde_json::json!([invocation]);
+
+    let sarif = serde_json::json!({
+        "$schema": "https://json.schemastore.org/sarif-2.1.0.json",
+        "version": "2.1.0",
+        "runs": [run],
+    });
+    Ok(serde_json::to_string_pretty(&sarif)?)
+}
+
+/// Save a SARIF 2.1.0 log of one or more hosts
+///
+/// # Arguments
+/// * `results` - Results of one or more hosts
+/// * `output_path` - Path to the output file (encrypted with --encrypt-output)
+///
+/// # Returns
+/// * `Result<()>` - Success or error
+pub fn save_sarif_report(results: &[ScanResults], output_path: &Path) -> Result<()> {
+    crate::encryption::write_output(output_path, format_sarif_report(results)?.as_bytes())
+}
+
+/// Escape text for an XML element or attribute
+fn xml_escape(value: &str) -> String {
+    value.chars()
+        .filter(|c| !c.is_control() || matches!(c, '\n' | '\t'))
+        .map(|c| match c {
+            '&' => "&amp;".to_string(),
+            '<' => "&lt;".to_string(),
+            '>' => "&gt;".to_string(),
+            '"' => "&quot;".to_string(),
+            '\'' => "&apos;".to_string(),
+            c => c.to_string(),
+        })
+        .collect()
+}
+
+/// Plugin ID for a finding, stable across runs: 990000-999999, away from Tenable's IDs
+fn synthetic_plugin_id(finding: &str) -> u32 {
+    // FNV-1a
+    let hash = finding.bytes().fold(0x811c9dc5u32, |hash, b| (hash ^ b as u32).wrapping_mul(0x01000193));
+    990_000 + hash % 10_000
+}
+
+/// One finding of a port in a VM export: open port, service or vulnerability
+struct ExportFinding {
+    plugin_id: u32,
+    name: String,
+    family: &'static str,
+    /// 0 (info) to 4 (critical), as in .nessus
+    severity: u8,
+    description: String,
+    output: String,
+    cve: Option<String>,
+    solution: Option<String>,
+    /// Advisory and documentation links
+    see_also: Vec<String>,
+}
+
+/// Findings of one port, shared by the .nessus and Greenbone exports
+fn export_findings(port: u16, proto: &str, result: &PortResult) -> Vec<ExportFinding> {
+    let mut findings = vec![ExportFinding {
+        plugin_id: synthetic_plugin_id("open-port"),
+        name: "Open Port".to_string(),
+        family: "Port scanners",
+        severity: 0,
+        description: "The port is open.".to_string(),
+        output: format!("Port {}/{} was found to be {}", port, proto, result.final_status),
+        cve: None,
+        solution: None,
+        see_also: Vec::new(),
+    }];
+    if result.service.is_some() || result.banner.is_some() {
+        let mut output = format!("Service: {}", result.service.as_deref().unwrap_or("unknown"));
+        if let Some(version) = &result.version {
+            output.push_str(&format!("\nVersion: {}", version));
+        }
+        if let Some(banner) = &result.banner {
+            output.push_str(&format!("\nBanner: {}", sanitize_banner(banner)));
+        }
+        findings.push(ExportFinding {
+            plugin_id: synthetic_plugin_id("service-detection"),
+            name: "Service Detection".to_string(),
+            family: "Service detection",
+            severity: 0,
+            description: "A service was identified on the port.".to_string(),
+            output,
+            cve: None,
+            solution: None,
+            see_also: Vec::new(),
+        });
+    }
+    for vuln in &result.vulns {
+        let mut output = format!("{} ({}, {} confidence)", vuln.description, vuln.severity, vuln.confidence);
+        for evidence in &vuln.evidence {
+            output.push_str(&format!("\nEvidence: {}", evidence));
+        }
+        findings.push(ExportFinding {
+            plugin_id: synthetic_plugin_id(&vuln.id),
+            name: vuln.display_title().to_string(),
+            family: "General",
+            severity: 4 - severity_rank(&vuln.severity),
+            description: vuln.description.clone(),
+            output,
+            cve: vuln.id.to_ascii_uppercase().starts_with("CVE-").then(|| vuln.id.to_ascii_uppercase()),
+            solution: vuln.remediation.clone(),
+            see_also: vuln.references.clone(),
+        });
+    }
+    findings
+}
+
+/// Ports of a host worth exporting (open or open|filtered), in order, with their protocol
+fn export_ports(host: &ScanResults) -> Vec<(u16, &'static str, &PortResult)> {
+    let mut ports: Vec<(u16, &'static str, &PortResult)> = host.results.iter()
+        .filter(|(_, r)| matches!(r.final_status, PortStatus::Open | PortStatus::OpenFiltered))
+        .map(|(port, r)| (*port, if r.udp_state.is_some() && r.tcp_states.is_empty() { "udp" } else { "tcp" }, r))
+        .collect();
+    ports.sort_by_key(|(port, _, _)| *port);
+    ports
+}
+
+/// Render results as a Nessus v2 (.nessus) file
+///
+/// Every open port, identified service and vulnerability becomes a ReportItem. Plugin
+/// IDs are synthetic (990000-999999) but stable per finding, so importers that track
+/// findings by plugin ID see the same finding across scans.
+///
+/// # Arguments
+/// * `results` - Results of one or more hosts, one ReportHost each
+///
+/// # Returns
+/// * `String` - The .nessus XML document
+pub fn format_nessus_report(results: &[ScanResults]) -> String {
+    let run_id = results.iter().find_map(|r| r.metadata.as_ref()).map_or_else(crate::metadata::generate_run_id, |m| m.run_id.clone());
+    let mut xml = String::from("<?xml version=\"1.0\" ?>\n<NessusClientData_v2>\n");
+    xml.push_str("<Policy><policyName>quantum_scanner</policyName><Preferences><ServerPreferences></ServerPreferences><PluginsPreferences></PluginsPreferences></Preferences></Policy>\n");
+    xml.push_str(&format!("<Report name=\"quantum_scanner {}\" xmlns:cm=\"http://www.nessus.org/cm\">\n", xml_escape(&run_id)));
+    for host in results {
+        xml.push_str(&format!("<ReportHost name=\"{}\"><HostProperties>\n", xml_escape(&host.target_ip)));
+        xml.push_str(&format!("<tag name=\"HOST_START\">{}</tag>\n", host.start_time.format("%a %b %e %H:%M:%S %Y")));
+        xml.push_str(&format!("<tag name=\"HOST_END\">{}</tag>\n", host.end_time.format("%a %b %e %H:%M:%S %Y")));
+        xml.push_str(&format!("<tag name=\"host-ip\">{}</tag>\n", xml_escape(&host.target_ip)));
+        if host.target != host.target_ip {
+            xml.push_str(&format!("<tag name=\"host-fqdn\">{}</tag>\n", xml_escape(&host.target)));
+        }
+        if let Some(os) = &host.os_summary {
+            xml.push_str(&format!("<tag name=\"operating-system\">{}</tag>\n", xml_escape(os)));
+        }
+        if let Some(mac) = &host.mac_address {
+            xml.push_str(&format!("<tag name=\"mac-address\">{}</tag>\n", xml_escape(mac)));
+        }
+        if let Some(asset) = &host.asset {
+            let fields = [("asset-owner", asset.owner.clone()), ("asset-environment", asset.environment.clone()),
+                          ("asset-criticality", asset.criticality.map(|c| c.to_string()))];
+            for (name, value) in fields {
+                if let Some(value) = value {
+                    xml.push_str(&format!("<tag name=\"{}\">{}</tag>\n", name, xml_escape(&value)));
+                }
+            }
+            if !asset.tags.is_empty() {
+                xml.push_str(&format!("<tag name=\"asset-tags\">{}</tag>\n", xml_escape(&asset.tags.join(","))));
+            }
+        }
+        xml.push_str("</HostProperties>\n");
+        for (port, proto, result) in export_ports(host) {
+            let service = result.service.as_deref().unwrap_or("unknown");
+            for finding in export_findings(port, proto, result) {
+                let risk = ["None", "Low", "Medium", "High", "Critical"][finding.severity as usize];
+                xml.push_str(&format!("<ReportItem port=\"{}\" svc_name=\"{}\" protocol=\"{}\" severity=\"{}\" pluginID=\"{}\" pluginName=\"{}\" pluginFamily=\"{}\">\n",
+                    port, xml_escape(service), proto, finding.severity, finding.plugin_id, xml_escape(&finding.name), finding.family));
+                xml.push_str(&format!("<description>{}</description>\n", xml_escape(&finding.description)));
+                xml.push_str(&format!("<risk_factor>{}</risk_factor>\n", risk));
+                if let Some(cve) = &finding.cve {
+                    xml.push_str(&format!("<cve>{}</cve>\n", xml_escape(cve)));
+                }
+                xml.push_str(&format!("<plugin_output>{}</plugin_output>\n", xml_escape(&finding.output)));
+                if !finding.see_also.is_empty() {
+                    xml.push_str(&format!("<see_also>{}</see_also>\n", xml_escape(&finding.see_also.join("\n"))));
+                }
+                xml.push_str(&format!("<plugin_type>remote</plugin_type>\n<solution>{}</solution>\n</ReportItem>\n",
+                    finding.solution.as_deref().map_or_else(|| "n/a".to_string(), xml_escape)));
+            }
+        }
+        xml.push_str("</ReportHost>\n");
+    }
+    xml.push_str("</Report>\n</NessusClientData_v2>\n");
+    xml
+}
+
+/// Render results as a Greenbone/OpenVAS report for `create_report` (GMP)
+///
+/// Uses the layout of Greenbone's own XML report format: one result per open port,
+/// identified service and vulnerability, and one host element per target. Severities
+/// follow the CVSS-style scale of the SARIF export; NVT OIDs are `quantum_scanner:`
+/// followed by the synthetic plugin ID of the .nessus export.
+///
+/// # Arguments
+/// * `results` - Results of one or more hosts, in one report
+///
+/// # Returns
+/// * `String` - The report XML
+pub fn format_greenbone_report(results: &[ScanResults]) -> String {
+    let report_id = results.iter().find_map(|r| r.metadata.as_ref()).map_or_else(crate::metadata::generate_run_id, |m| m.run_id.clone());
+    let iso = |t: &chrono::DateTime<Utc>| t.format("%Y-%m-%dT%H:%M:%SZ").to_string();
+    let mut xml = format!("<report id=\"{0}\" format_id=\"a994b278-1f62-11e1-96ac-406186ea4fc5\" extension=\"xml\" content_type=\"text/xml\">\n<report id=\"{0}\">\n<scan_run_status>Done</scan_run_status>\n", xml_escape(&report_id));
+    if let Some(start) = results.iter().map(|r| r.start_time).min() {
+        xml.push_str(&format!("<scan_start>{}</scan_start>\n", iso(&start)));
+    }
+    if let Some(end) = results.iter().map(|r| r.end_time).max() {
+        xml.push_str(&format!("<scan_end>{}</scan_end>\n", iso(&end)));
+    }
+
+    let mut ports = String::new();
+    let mut results_xml = String::new();
+    let mut hosts = String::new();
+    for host in results {
+        for (port, proto, result) in export_ports(host) {
+            ports.push_str(&format!("<port><host>{}</host>{}/{}<severity>0.0</severity><threat>Log</threat></port>\n", xml_escape(&host.target_ip), port, proto));
+            for finding in export_findings(port, proto, result) {
+                let (threat, score) = match finding.severity {
+                    4 => ("High", "9.5"),
+                    3 => ("High", "8.0"),
+                    2 => ("Medium", "5.5"),
+                    1 => ("Low", "3.0"),
+                    _ => ("Log", "0.0"),
+                };
+                let refs: String = finding.cve.iter()
+                    .map(|cve| format!("<ref type=\"cve\" id=\"{}\"/>", xml_escape(cve)))
+                    .chain(finding.see_also.iter().map(|url| format!("<ref type=\"url\" id=\"{}\"/>", xml_escape(url))))
+                    .collect();
+                let refs = if refs.is_empty() { refs } else { format!("<refs>{}</refs>", refs) };
+                let solution = finding.solution.as_ref()
+                    .map(|text| format!("<solution type=\"Mitigation\">{}</solution>", xml_escape(text)))
+                    .unwrap_or_default();
+                results_xml.push_str(&format!(
+                    "<result id=\"{}\"><name>{}</name><host>{}<hostname>{}</hostname></host><port>{}/{}</port><nvt oid=\"quantum_scanner:{}\"><type>nvt</type><name>{}</name><family>{}</family><cvss_base>{}</cvss_base><tags>summary={}</tags>{}{}</nvt><threat>{}</threat><severity>{}</severity><original_threat>{}</original_threat><description>{}</description></result>\n",
+                    crate::metadata::generate_run_id(), xml_escape(&finding.name), xml_escape(&host.target_ip),
+                    if host.target != host.target_ip { xml_escape(&host.target) } else { String::new() },
+                    port, proto, finding.plugin_id, xml_escape(&finding.name), finding.family, score,
+                    xml_escape(&finding.description), refs, solution, threat, score, threat, xml_escape(&finding.output)));
+            }
+        }
+        hosts.push_str(&format!("<host><ip>{}</ip><start>{}</start><end>{}</end>", xml_escape(&host.target_ip), iso(&host.start_time), iso(&host.end_time)));
+        if host.target != host.target_ip {
+            hosts.push_str(&format!("<detail><name>hostname</name><value>{}</value></detail>", xml_escape(&host.target)));
+        }
+        if let Some(os) = &host.os_summary {
+            hosts.push_str(&format!("<detail><name>best_os_txt</name><value>{}</value></detail>", xml_escape(os)));
+        }
+        hosts.push_str("</host>\n");
+    }
+    xml.push_str(&format!("<ports>\n{}</ports>\n<results>\n{}</results>\n{}", ports, results_xml, hosts));
+    xml.push_str("</report>\n</report>\n");
+    xml
+}
+
+/// Save a Nessus v2 (.nessus) file of one or more hosts
+pub fn save_nessus_report(results: &[ScanResults], output_path: &Path) -> Result<()> {
+    crate::encryption::write_output(output_path, format_nessus_report(results).as_bytes())
+}
+
+/// Save a Greenbone/OpenVAS report XML of one or more hosts
+pub fn save_greenbone_report(results: &[ScanResults], output_path: &Path) -> Result<()> {
+    crate::encryption::write_output(output_path, format_greenbone_report(results).as_bytes())
+}
+
+/// Extensions of templates whose output is HTML or XML, which get auto-escaping
+const ESCAPED_TEMPLATE_SUFFIXES: [&str; 6] = [".html", ".htm", ".xml", ".html.tera", ".htm.tera", ".xml.tera"];
+
+/// Render results through a user-supplied Tera template
+///
+/// The template sees `hosts`, the results of every host with an extra `ports` array
+/// holding each port's result plus `port` and `proto`, sorted by port (`host.results`
+/// remains available as a map); `findings`, each issue once with its `affected`
+/// endpoints (see `aggregate_findings`); `metadata`, the run metadata when known; and
+/// `generated_at`, the RFC 3339 time of rendering. Templates whose file name ends in
+/// .html, .htm or .xml (optionally followed by .tera) have values HTML-escaped.
+///
+/// # Arguments
+/// * `results` - Results of one or more hosts
+/// * `template_path` - Tera template file
+///
+/// # Returns
+/// * `Result<String>` - The rendered report, or the template's syntax or render error
+pub fn render_template_report(results: &[ScanResults], template_path: &Path) -> Result<String> {
+    let (tera, name) = load_template(template_path)?;
+    tera.render(&name, &template_context(results)?)
+        .map_err(|e| anyhow::anyhow!("Failed to render report template {}: {}", template_path.display(), template_error_chain(&e)))
+}
+
+/// Built-in HTML report, rendered with the values a `--report-template` gets
+const HTML_TEMPLATE: &str = include_str!("../templates/report.html.tera");
+
+/// Render the built-in HTML report of one or more hosts
+///
+/// # Returns
+/// * `Result<String>` - A self-contained HTML page with inline styles
+pub fn format_html_report(results: &[ScanResults]) -> Result<String> {
+    let mut tera = tera::Tera::default();
+    tera.autoescape_on(ESCAPED_TEMPLATE_SUFFIXES.to_vec());
+    tera.add_raw_template("report.html", HTML_TEMPLATE)
+        .map_err(|e| anyhow::anyhow!("Invalid built-in HTML template: {}", template_error_chain(&e)))?;
+    tera.render("report.html", &template_context(results)?)
+        .map_err(|e| anyhow::anyhow!("Failed to render HTML report: {}", template_error_chain(&e)))
+}
+
+/// Values handed to report templates: `hosts` (each with a sorted `ports` array),
+/// `findings`, `metadata` and `generated_at`
+fn template_context(results: &[ScanResults]) -> Result<tera::Context> {
+    let mut hosts = Vec::with_capacity(results.len());
+    for host in results {
+        let mut value = serde_json::to_value(host)?;
+        let mut ports: Vec<(&u16, &PortResult)> = host.results.iter().collect();
+        ports.sort_by_key(|(port, _)| **port);
+        let ports: Vec<serde_json::Value> = ports.into_iter().map(|(port, result)| {
+            let mut entry = serde_json::to_value(result).unwrap_or_default();
+            entry["port"] = (*port).into();
+            entry["proto"] = if result.udp_state.is_some() && result.tcp_states.is_empty() { "udp" } else { "tcp" }.into();
+            entry
+        }).collect();
+        value["ports"] = ports.into();
+        hosts.push(value);
+    }
+
+    let mut context = tera::Context::new();
+    context.insert("hosts", &hosts);
+    context.insert("findings", &aggregate_findings(results));
+    context.insert("metadata", &results.iter().find_map(|r| r.metadata.as_ref()));
+    context.insert("generated_at", &Utc::now().to_rfc3339());
+    Ok(context)
+}
+
+/// Read and compile a report template, returning it with the name it is registered under
+///
+/// Called before scanning as well, so a syntax error does not surface only after the scan.
+pub fn load_template(template_path: &Path) -> Result<(tera::Tera, String)> {
+    let source = std::fs::read_to_string(template_path)
+        .map_err(|e| anyhow::anyhow!("Cannot read report template {}: {}", template_path.display(), e))?;
+    let name = template_path.file_name().map(|n| n.to_string_lossy().into_owned()).unwrap_or_else(|| "report".to_string());
+
+    let mut tera = tera::Tera::default();
+    tera.autoescape_on(ESCAPED_TEMPLATE_SUFFIXES.to_vec());
+    tera.add_raw_template(&name, &source)
+        .map_err(|e| anyhow::anyhow!("Invalid report template {}: {}", template_path.display(), template_error_chain(&e)))?;
+    Ok((tera, name))
+}
+
+/// Tera nests the useful message (line, missing variable) in the error's sources
+fn template_error_chain(error: &tera::Error) -> String {
+    let mut message = error.to_string();
+    let mut source = std::error::Error::source(error);
+    while let Some(cause) = source {
+        message.push_str(&format!(": {}", cause));
+        source = cause.source();
+    }
+    message
+}
+
+/// How `write_results` renders results and where it puts them
+///
+/// Filled from the scan's command line, or from `quantum_scanner report` when
+/// regenerating a report from saved results.
+pub struct ReportOptions<'a> {
+    /// Format chosen with `--output-format`; `None` is text (or JSON with `json`)
+    pub format: Option<OutputFormat>,
+    pub json: bool,
+    pub pretty_json: bool,
+    /// Tera template that replaces the built-in formats
+    pub template: Option<&'a Path>,
+    /// File to write instead of printing
+    pub output: Option<&'a Path>,
+    pub verbose: bool,
+    pub quiet: bool,
+}
+
+/// Save a rendered report to `output`, or print it (redacted) when no file is given
+pub fn write_report(kind: &str, output: Option<&Path>, save: impl FnOnce(&Path) -> Result<()>, render: impl FnOnce() -> Result<String>) {
+    match output {
+        Some(output_path) => match save(output_path) {
+            Ok(()) => info!("{} saved to: {}", kind, output_path.display()),
+            Err(e) => error!("Failed to save {}: {}", kind, e),
+        },
+        None => match render() {
+            Ok(report) => println!("{}", crate::redaction::apply(&report)),
+            Err(e) => error!("Failed to build {}: {}", kind, e),
+        },
+    }
+}
+
+/// Print or save results as JSON or text according to the output options
+///
+/// A single host is written exactly as before; several hosts (passive mode over a
+/// subnet) become a JSON array or consecutive text reports.
+pub fn write_results(results: &[ScanResults], options: &ReportOptions) {
+    let json_output = match (results, options.pretty_json) {
+        ([single], true) => serde_json::to_string_pretty(single),
+        ([single], false) => serde_json::to_string(single),
+        (many, true) => serde_json::to_string_pretty(many),
+        (many, false) => serde_json::to_string(many),
+    }.map(crate::utils::SecureString::new); // Serialized results are wiped from memory once written
     
-    Ok(())
-} 
\ No newline at end of file
+    // Handle JSON output to file or console
+    if let Some(template) = options.template {
+        write_report("Templated report", options.output,
+            |path| crate::encryption::write_output(path, render_template_report(results, template)?.as_bytes()),
+            || render_template_report(results, template));
+    } else if options.format == Some(OutputFormat::Markdown) {
+        write_report("Markdown report", options.output,
+            |path| save_markdown_report(results, path),
+            || Ok(format_markdown_report(results)));
+    } else if options.format == Some(OutputFormat::Sarif) {
+        write_report("SARIF log", options.output,
+            |path| save_sarif_report(results, path),
+            || format_sarif_report(results));
+    } else if options.format == Some(OutputFormat::Nessus) {
+        write_report(".nessus report", options.output,
+            |path| save_nessus_report(results, path),
+            || Ok(format_nessus_report(results)));
+    } else if options.format == Some(OutputFormat::Greenbone) {
+        write_report("Greenbone report", options.output,
+            |path| save_greenbone_report(results, path),
+            || Ok(format_greenbone_report(results)));
+    } else if options.format == Some(OutputFormat::Csv) {
+        write_report("CSV report", options.output,
+            |path| crate::encryption::write_output(path, format_csv_report(results).as_bytes()),
+            || Ok(format_csv_report(results)));
+    } else if options.format == Some(OutputFormat::Html) {
+        write_report("HTML report", options.output,
+            |path| crate::encryption::write_output(path, format_html_report(results)?.as_bytes()),
+            || format_html_report(results));
+    } else if options.json || options.format == Some(OutputFormat::Json) {
+        match json_output {
+            Ok(json) => {
+                if let Some(output_path) = options.output {
+                    // Save to file
+                    if let Err(e) = crate::encryption::write_output(output_path, json.as_bytes()) {
+                        error!("Failed to write JSON output to file: {}", e);
+                    } else {
+                        info!("Results saved to JSON file: {}", output_path.display());
+                    }
+                } else {
+                    // Print to console
+                    println!("{}", crate::redaction::apply(&json));
+                }
+            }
+            Err(e) => error!("Failed to serialize scan results to JSON: {}", e)
+        }
+    } else if let [scan_result] = results {
+        // Text format - either save to file or print to console
+        if let Some(output_path) = options.output {
+            if let Err(e) = save_text_results(scan_result, output_path) {
+                error!("Failed to save results to file: {}", e);
+            } else {
+                info!("Results saved to file: {}", output_path.display());
+            }
+        } else {
+            // Print results to console - pass the verbose flag from args. The styled
+            // report is printed piecemeal, so redaction uses the plain-text report instead
+            // Quiet mode also takes the plain report, which does not clear the screen
+            if crate::redaction::active() || options.quiet {
+                println!("{}", crate::redaction::apply(&format_text_results(scan_result, options.verbose)));
+            } else if let Err(e) = print_results(scan_result, options.verbose) {
+                error!("Failed to print results: {}", e);
+            }
+        }
+    } else {
+        // Several hosts: consecutive plain-text reports, then each issue once across hosts
+        let mut text: Vec<crate::utils::SecureString> = results.iter()
+            .map(|r| format_text_results(r, options.output.is_some() || options.verbose).into())
+            .collect();
+        text.push(format_findings_summary(results).into());
+        let text = crate::utils::SecureString::new(text.iter().map(|t| t.as_str()).collect::<Vec<_>>().join("\n"));
+        match options.output {
+            Some(output_path) => match crate::encryption::write_output(output_path, text.as_bytes()) {
+                Ok(()) => info!("Results saved to file: {}", output_path.display()),
+                Err(e) => error!("Failed to save results to file: {}", e),
+            },
+            None => println!("{}", crate::redaction::apply(&text)),
+        }
+    }
+
+    // Plugin reports go to stderr when stdout carries JSON or another structured report,
+    // so it stays parseable
+    for result in results {
+        for (plugin, text) in crate::plugins::render_output(result) {
+            let report = format!("=== Plugin {} ({}) ===\n{}", plugin, result.target, text.trim_end());
+            let report = crate::redaction::apply(&report);
+            if options.format.map_or(options.json, |format| format != OutputFormat::Text) && options.output.is_none() {
+                eprintln!("{}", report);
+            } else {
+                println!("{}", report);
+            }
+        }
+    }
+}
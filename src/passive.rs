@@ -0,0 +1,646 @@
+//! Passive scanning: port states and service details from observed traffic only.
+//!
+//! `--passive <iface|pcap>` never sends a packet. It watches a live interface or reads
+//! a capture file and builds the same `ScanResults` an active scan would, from what the
+//! in-scope hosts reveal on their own: SYN-ACKs and RSTs answering other clients'
+//! connection attempts, server greetings and HTTP responses, and TLS handshakes
+//! (version and, up to TLS 1.2, the server certificate). Hostnames clients ask for via
+//! SNI or the HTTP Host header are kept as service details.
+//!
+//! Coverage is limited to what happens to cross the capture point during the window,
+//! so a port missing from the results says nothing about its state.
+
+use std::collections::{BTreeSet, HashMap, HashSet};
+use std::fs::File;
+use std::io::{BufReader, Read};
+use std::net::IpAddr;
+use std::path::{Path, PathBuf};
+use std::str::FromStr;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+use std::time::Duration;
+
+use anyhow::{anyhow, Context, Result};
+use chrono::{DateTime, TimeZone, Utc};
+use ipnet::IpNet;
+use log::{debug, info, warn};
+use pnet::datalink::{self, Channel};
+use pnet::packet::ip::IpNextHeaderProtocols;
+use pnet::packet::ipv4::Ipv4Packet;
+use pnet::packet::ipv6::Ipv6Packet;
+use pnet::packet::tcp::{TcpFlags, TcpPacket};
+use pnet::packet::Packet;
+
+use crate::models::{PortResult, PortStatus, ScanResults, ScanType};
+
+/// pcap link types we can decode
+const LINKTYPE_NULL: u32 = 0;
+const LINKTYPE_ETHERNET: u32 = 1;
+const LINKTYPE_RAW: u32 = 101;
+const LINKTYPE_LINUX_SLL: u32 = 113;
+
+/// Largest record accepted from a capture file
+const MAX_RECORD_LEN: usize = 256 * 1024;
+
+/// Most of a server's first TLS flight buffered while looking for the certificate
+const MAX_TLS_FLIGHT: usize = 64 * 1024;
+
+/// Most of a server's first payload kept as a banner
+const MAX_BANNER_LEN: usize = 512;
+
+/// Where observed traffic comes from
+#[derive(Debug, Clone, PartialEq)]
+pub enum PassiveSource {
+    /// Live promiscuous capture on a network interface
+    Interface(String),
+    /// Classic libpcap capture file
+    Pcap(PathBuf),
+}
+
+impl FromStr for PassiveSource {
+    type Err = anyhow::Error;
+
+    /// An existing file is read as a capture; anything else is taken as an interface name
+    fn from_str(s: &str) -> Result<Self> {
+        if s.is_empty() {
+            return Err(anyhow!("Passive source must be an interface name or a pcap file"));
+        }
+        if Path::new(s).is_file() {
+            Ok(PassiveSource::Pcap(PathBuf::from(s)))
+        } else {
+            Ok(PassiveSource::Interface(s.to_string()))
+        }
+    }
+}
+
+/// Which hosts and ports results are kept for
+#[derive(Debug, Clone)]
+pub struct PassiveScope {
+    networks: Vec<IpNet>,
+    ports: HashSet<u16>,
+}
+
+impl PassiveScope {
+    /// Build a scope from the scan target (IP or CIDR) and port list
+    ///
+    /// Hostnames are rejected: resolving them would put a DNS query on the wire.
+    pub fn new(target: &str, ports: &[u16]) -> Result<Self> {
+        let network = match target.parse::<IpNet>() {
+            Ok(net) => net,
+            Err(_) => target.parse::<IpAddr>().map(IpNet::from)
+                .map_err(|_| anyhow!("Passive mode needs an IP address or CIDR target, not '{}' (resolving it would send DNS queries)", target))?,
+        };
+        Ok(PassiveScope { networks: vec![network], ports: ports.iter().copied().collect() })
+    }
+
+    fn host_in_scope(&self, ip: IpAddr) -> bool {
+        self.networks.iter().any(|net| net.contains(&ip))
+    }
+
+    fn contains(&self, ip: IpAddr, port: u16) -> bool {
+        self.ports.contains(&port) && self.host_in_scope(ip)
+    }
+}
+
+/// Server-to-client stream identity: (server, server port, client, client port)
+type FlowKey = (IpAddr, u16, IpAddr, u16);
+
+/// Accumulates port evidence from decoded packets
+pub struct PassiveObserver {
+    scope: PassiveScope,
+    ports: HashMap<(IpAddr, u16), PortResult>,
+    hostnames: HashMap<(IpAddr, u16), BTreeSet<String>>,
+    tls_flights: HashMap<FlowKey, Vec<u8>>,
+    finished_flows: HashSet<FlowKey>,
+    first_seen: Option<DateTime<Utc>>,
+    last_seen: Option<DateTime<Utc>>,
+    packets: usize,
+}
+
+impl PassiveObserver {
+    pub fn new(scope: PassiveScope) -> Self {
+        PassiveObserver {
+            scope,
+            ports: HashMap::new(),
+            hostnames: HashMap::new(),
+            tls_flights: HashMap::new(),
+            finished_flows: HashSet::new(),
+            first_seen: None,
+            last_seen: None,
+            packets: 0,
+        }
+    }
+
+    /// Feed one captured frame of the given pcap link type
+    pub fn observe_frame(&mut self, linktype: u32, frame: &[u8], at: DateTime<Utc>) {
+        self.packets += 1;
+        self.first_seen.get_or_insert(at);
+        self.last_seen = Some(at);
+
+        let ip = match ip_payload(linktype, frame) {
+            Some(ip) => ip,
+            None => return,
+        };
+        match ip.first().map(|b| b >> 4) {
+            Some(4) => {
+                if let Some(packet) = Ipv4Packet::new(ip) {
+                    if packet.get_next_level_protocol() == IpNextHeaderProtocols::Tcp {
+                        if let Some(tcp) = TcpPacket::new(packet.payload()) {
+                            self.observe_tcp(IpAddr::V4(packet.get_source()), IpAddr::V4(packet.get_destination()), packet.get_ttl(), &tcp, at);
+                        }
+                    }
+                }
+            }
+            Some(6) => {
+                if let Some(packet) = Ipv6Packet::new(ip) {
+                    if packet.get_next_header() == IpNextHeaderProtocols::Tcp {
+                        if let Some(tcp) = TcpPacket::new(packet.payload()) {
+                            self.observe_tcp(IpAddr::V6(packet.get_source()), IpAddr::V6(packet.get_destination()), packet.get_hop_limit(), &tcp, at);
+                        }
+                    }
+                }
+            }
+            _ => {}
+        }
+    }
+
+    fn observe_tcp(&mut self, source: IpAddr, destination: IpAddr, ttl: u8, tcp: &TcpPacket, at: DateTime<Utc>) {
+        let flags = tcp.get_flags();
+        let (source_port, destination_port) = (tcp.get_source(), tcp.get_destination());
+        let payload = tcp.payload();
+
+        // Client side: hostnames the client is asking the in-scope server for
+        if self.scope.contains(destination, destination_port) && !payload.is_empty() {
+            if let Some(name) = client_hello_sni(payload).or_else(|| http_host(payload)) {
+                self.hostnames.entry((destination, destination_port)).or_default().insert(name);
+            }
+            return;
+        }
+
+        if !self.scope.contains(source, source_port) {
+            return;
+        }
+
+        if flags & TcpFlags::SYN != 0 && flags & TcpFlags::ACK != 0 {
+            let entry = self.port_entry(source, source_port, at);
+            mark(entry, PortStatus::Open, "syn-ack observed");
+            entry.os_guess.get_or_insert_with(|| os_from_ttl(ttl, tcp.get_window()));
+            return;
+        }
+
+        if flags & TcpFlags::RST != 0 && payload.is_empty() {
+            let entry = self.port_entry(source, source_port, at);
+            // RSTs also tear down established connections; they only mean closed
+            // if the port never showed signs of life
+            if entry.final_status != PortStatus::Open {
+                mark(entry, PortStatus::Closed, "rst observed");
+            }
+            return;
+        }
+
+        if payload.is_empty() {
+            return;
+        }
+
+        let flow = (source, source_port, destination, destination_port);
+        let entry = self.port_entry(source, source_port, at);
+        if entry.final_status != PortStatus::Open {
+            mark(entry, PortStatus::Open, "server data observed");
+        }
+
+        if self.finished_flows.contains(&flow) {
+            return;
+        }
+        if payload[0] == 0x16 || self.tls_flights.contains_key(&flow) {
+            self.observe_tls_flight(flow, payload);
+        } else {
+            self.finished_flows.insert(flow);
+            let entry = self.port_entry(source, source_port, at);
+            if entry.banner.is_none() {
+                entry.banner = printable_banner(payload);
+            }
+        }
+    }
+
+    /// Buffer a server's first TLS flight until the handshake can be read
+    ///
+    /// Segments are appended in arrival order; out-of-order delivery just means the
+    /// certificate is missed.
+    fn observe_tls_flight(&mut self, flow: FlowKey, payload: &[u8]) {
+        let buffer = self.tls_flights.entry(flow).or_default();
+        buffer.extend_from_slice(payload);
+
+        let handshake = parse_server_flight(buffer);
+        let done = handshake.certificate.is_some() || handshake.finished || buffer.len() >= MAX_TLS_FLIGHT;
+
+        let entry = self.ports.entry((flow.0, flow.1)).or_default();
+        if let Some(version) = handshake.version {
+            entry.tls_protocol_version.get_or_insert(version);
+        }
+        if let Some(cert) = handshake.certificate {
+            entry.cert_info.get_or_insert(cert);
+        }
+
+        if done {
+            self.tls_flights.remove(&flow);
+            self.finished_flows.insert(flow);
+        }
+    }
+
+    fn port_entry(&mut self, host: IpAddr, port: u16, at: DateTime<Utc>) -> &mut PortResult {
+        let entry = self.ports.entry((host, port)).or_insert_with(|| PortResult { scan_time: at, ..PortResult::default() });
+        entry.scan_time = at;
+        entry
+    }
+
+    /// Number of frames seen so far
+    pub fn packets(&self) -> usize {
+        self.packets
+    }
+
+    /// One `ScanResults` per host with at least one observed port
+    pub fn into_results(self) -> Vec<ScanResults> {
+        let start_time = self.first_seen.unwrap_or_else(Utc::now);
+        let end_time = self.last_seen.unwrap_or(start_time);
+        let mut hostnames = self.hostnames;
+
+        let mut hosts: HashMap<IpAddr, HashMap<u16, PortResult>> = HashMap::new();
+        for ((host, port), mut result) in self.ports {
+            let (service, version) = crate::service_fingerprints::identify_service(
+                port, result.banner.as_deref(), result.cert_info.as_ref(),
+            );
+            result.service = service;
+            result.version = version;
+            if let Some(names) = hostnames.remove(&(host, port)) {
+                result.service_details = Some(serde_json::json!({ "observed_hostnames": names }));
+            }
+            hosts.entry(host).or_default().insert(port, result);
+        }
+
+        let mut results: Vec<ScanResults> = hosts.into_iter().map(|(host, ports)| {
+            let open_ports = ports.iter()
+                .filter(|(_, r)| r.final_status == PortStatus::Open)
+                .map(|(port, _)| *port)
+                .collect();
+            let os_summary = ports.values().find_map(|r| r.os_guess.clone());
+            let mut results = ScanResults {
+                schema_version: crate::models::SCHEMA_VERSION,
+                target: host.to_string(),
+                target_ip: host.to_string(),
+                open_ports,
+                successful_scans: ports.len(),
+                results: ports,
+                start_time,
+                end_time,
+                scan_types: vec![ScanType::Passive],
+                packets_sent: 0,
+                bytes_sent: 0,
+                os_summary,
+                risk_assessment: None,
+                service_categories: None,
+                mac_address: None,
+                mac_vendor: None,
+                ip_protocols: HashMap::new(),
+                icmp_info: None,
+                host_timing: None,
+                technique_selection: None,
+                dns_sanity: None,
+                metadata: crate::metadata::current(),
+                skipped_probes: HashMap::new(),
+                skipped_phases: Vec::new(),
+                asset: None,
+                outliers: Vec::new(),
+                deviations: Vec::new(),
+                edge: None,
+                cloud: None,
+                interference: Vec::new(),
+                merged_runs: Vec::new(),
+            };
+            crate::assets::annotate(&mut results);
+            results
+        }).collect();
+        results.sort_by(|a, b| a.target_ip.cmp(&b.target_ip));
+        results
+    }
+}
+
+fn mark(entry: &mut PortResult, status: PortStatus, reason: &str) {
+    entry.final_status = status;
+    entry.reason = Some(reason.to_string());
+    entry.tcp_states.insert(ScanType::Passive, status);
+    entry.tcp_reasons.insert(ScanType::Passive, reason.to_string());
+}
+
+/// Rough OS family from the initial TTL a SYN-ACK was sent with
+fn os_from_ttl(ttl: u8, window: u16) -> String {
+    let family = match ttl {
+        0..=64 => "Linux/Unix",
+        65..=128 => "Windows",
+        _ => "Network device",
+    };
+    format!("{} (observed TTL {}, window {})", family, ttl, window)
+}
+
+/// Keep a server's first payload as a banner if it is mostly text
+fn printable_banner(payload: &[u8]) -> Option<String> {
+    let data = &payload[..payload.len().min(MAX_BANNER_LEN)];
+    let printable = data.iter().filter(|b| b.is_ascii_graphic() || b.is_ascii_whitespace()).count();
+    if printable * 10 < data.len() * 8 {
+        return None;
+    }
+    let text = String::from_utf8_lossy(data).trim().to_string();
+    (!text.is_empty()).then_some(text)
+}
+
+/// Locate the IP header inside a frame of the given link type
+fn ip_payload(linktype: u32, frame: &[u8]) -> Option<&[u8]> {
+    match linktype {
+        LINKTYPE_ETHERNET => {
+            let mut offset = 12;
+            let mut ethertype = u16::from_be_bytes([*frame.get(offset)?, *frame.get(offset + 1)?]);
+            // Skip 802.1Q / 802.1ad tags
+            while ethertype == 0x8100 || ethertype == 0x88a8 {
+                offset += 4;
+                ethertype = u16::from_be_bytes([*frame.get(offset)?, *frame.get(offset + 1)?]);
+            }
+            match ethertype {
+                0x0800 | 0x86dd => frame.get(offset + 2..),
+                _ => None,
+            }
+        }
+        LINKTYPE_LINUX_SLL => match u16::from_be_bytes([*frame.get(14)?, *frame.get(15)?]) {
+            0x0800 | 0x86dd => frame.get(16..),
+            _ => None,
+        },
+        LINKTYPE_NULL => frame.get(4..),
+        // Raw IP appears as 101, or as 12/14 on some BSDs
+        LINKTYPE_RAW | 12 | 14 => Some(frame),
+        _ => None,
+    }
+}
+
+/// What could be read from a server's first TLS flight
+#[derive(Default)]
+struct ServerFlight {
+    version: Option<String>,
+    certificate: Option<crate::models::CertificateInfo>,
+    /// ServerHelloDone or encrypted records reached: nothing more to learn
+    finished: bool,
+}
+
+/// Concatenate handshake bytes from the complete TLS records at the start of `buf`
+fn handshake_bytes(buf: &[u8]) -> (Vec<u8>, bool) {
+    let mut handshake = Vec::new();
+    let mut offset = 0;
+    while offset + 5 <= buf.len() {
+        let content_type = buf[offset];
+        let len = u16::from_be_bytes([buf[offset + 3], buf[offset + 4]]) as usize;
+        if content_type != 0x16 {
+            // ChangeCipherSpec or application data: the cleartext part is over
+            return (handshake, true);
+        }
+        if offset + 5 + len > buf.len() {
+            break;
+        }
+        handshake.extend_from_slice(&buf[offset + 5..offset + 5 + len]);
+        offset += 5 + len;
+    }
+    (handshake, false)
+}
+
+fn parse_server_flight(buf: &[u8]) -> ServerFlight {
+    let (handshake, encrypted) = handshake_bytes(buf);
+    let mut flight = ServerFlight { finished: encrypted, ..ServerFlight::default() };
+
+    let mut offset = 0;
+    while offset + 4 <= handshake.len() {
+        let msg_type = handshake[offset];
+        let len = u32::from_be_bytes([0, handshake[offset + 1], handshake[offset + 2], handshake[offset + 3]]) as usize;
+        let body = match handshake.get(offset + 4..offset + 4 + len) {
+            Some(body) => body,
+            None => break,
+        };
+        match msg_type {
+            2 => flight.version = server_hello_version(body),
+            11 => {
+                // certificate_list<3> of certificate<3>; the leaf comes first
+                flight.certificate = body.get(3..6)
+                    .map(|l| u32::from_be_bytes([0, l[0], l[1], l[2]]) as usize)
+                    .and_then(|cert_len| body.get(6..6 + cert_len))
+                    .and_then(crate::techniques::parse_certificate);
+            }
+            14 => flight.finished = true,
+            _ => {}
+        }
+        offset += 4 + len;
+    }
+    flight
+}
+
+/// Negotiated version from a ServerHello body
+fn server_hello_version(body: &[u8]) -> Option<String> {
+    let legacy = u16::from_be_bytes([*body.first()?, *body.get(1)?]);
+    // version(2) random(32) session_id<1> cipher(2) compression(1) extensions<2>
+    let sid_len = *body.get(34)? as usize;
+    let mut offset = 35 + sid_len + 3;
+    let mut version = legacy;
+    if let Some(ext_len) = body.get(offset..offset + 2).map(|l| u16::from_be_bytes([l[0], l[1]]) as usize) {
+        offset += 2;
+        let end = (offset + ext_len).min(body.len());
+        while offset + 4 <= end {
+            let ext_type = u16::from_be_bytes([body[offset], body[offset + 1]]);
+            let len = u16::from_be_bytes([body[offset + 2], body[offset + 3]]) as usize;
+            // supported_versions carries the real version for TLS 1.3
+            if ext_type == 0x002b && len == 2 {
+                version = u16::from_be_bytes([*body.get(offset + 4)?, *body.get(offset + 5)?]);
+            }
+            offset += 4 + len;
+        }
+    }
+    Some(match version {
+        0x0300 => "SSLv3".to_string(),
+        0x0301 => "TLSv1.0".to_string(),
+        0x0302 => "TLSv1.1".to_string(),
+        0x0303 => "TLSv1.2".to_string(),
+        0x0304 => "TLSv1.3".to_string(),
+        other => format!("0x{:04x}", other),
+    })
+}
+
+/// SNI hostname from a ClientHello at the start of a client payload
+fn client_hello_sni(payload: &[u8]) -> Option<String> {
+    if payload.first() != Some(&0x16) || payload.get(5) != Some(&0x01) {
+        return None;
+    }
+    let body = payload.get(9..)?;
+    // version(2) random(32) session_id<1> cipher_suites<2> compression<1> extensions<2>
+    let mut offset = 34;
+    offset += 1 + *body.get(offset)? as usize;
+    offset += 2 + u16::from_be_bytes([*body.get(offset)?, *body.get(offset + 1)?]) as usize;
+    offset += 1 + *body.get(offset)? as usize;
+    let ext_len = u16::from_be_bytes([*body.get(offset)?, *body.get(offset + 1)?]) as usize;
+    offset += 2;
+    let end = (offset + ext_len).min(body.len());
+    while offset + 4 <= end {
+        let ext_type = u16::from_be_bytes([body[offset], body[offset + 1]]);
+        let len = u16::from_be_bytes([body[offset + 2], body[offset + 3]]) as usize;
+        if ext_type == 0x0000 {
+            // server_name_list<2> of (type(1), host_name<2>)
+            let name_len = u16::from_be_bytes([*body.get(offset + 7)?, *body.get(offset + 8)?]) as usize;
+            let name = body.get(offset + 9..offset + 9 + name_len)?;
+            return std::str::from_utf8(name).ok().map(str::to_lowercase);
+        }
+        offset += 4 + len;
+    }
+    None
+}
+
+/// Host header from an HTTP request at the start of a client payload
+fn http_host(payload: &[u8]) -> Option<String> {
+    const METHODS: &[&[u8]] = &[b"GET ", b"POST ", b"HEAD ", b"PUT ", b"OPTIONS ", b"DELETE ", b"PATCH "];
+    if !METHODS.iter().any(|m| payload.starts_with(m)) {
+        return None;
+    }
+    let text = String::from_utf8_lossy(&payload[..payload.len().min(4096)]);
+    text.lines()
+        .take_while(|line| !line.is_empty())
+        .find_map(|line| {
+            let (name, value) = line.split_once(':')?;
+            name.trim().eq_ignore_ascii_case("host").then(|| value.trim().to_lowercase())
+        })
+}
+
+/// Minimal reader for classic libpcap files
+struct PcapReader {
+    reader: BufReader<File>,
+    big_endian: bool,
+    nanoseconds: bool,
+    linktype: u32,
+}
+
+impl PcapReader {
+    fn open(path: &Path) -> Result<Self> {
+        let mut reader = BufReader::new(File::open(path).with_context(|| format!("Failed to open capture {}", path.display()))?);
+        let mut header = [0u8; 24];
+        reader.read_exact(&mut header).context("Capture file too short for a pcap header")?;
+
+        let (big_endian, nanoseconds) = match u32::from_le_bytes([header[0], header[1], header[2], header[3]]) {
+            0xa1b2c3d4 => (false, false),
+            0xa1b23c4d => (false, true),
+            0xd4c3b2a1 => (true, false),
+            0x4d3cb2a1 => (true, true),
+            0x0a0d0d0a => return Err(anyhow!("pcapng captures are not supported; convert with 'editcap -F pcap {} out.pcap'", path.display())),
+            magic => return Err(anyhow!("Not a pcap file (magic {:#010x})", magic)),
+        };
+        let mut reader = PcapReader { reader, big_endian, nanoseconds, linktype: 0 };
+        reader.linktype = reader.u32_at(&header, 20);
+        Ok(reader)
+    }
+
+    fn u32_at(&self, buf: &[u8], offset: usize) -> u32 {
+        let bytes = [buf[offset], buf[offset + 1], buf[offset + 2], buf[offset + 3]];
+        if self.big_endian { u32::from_be_bytes(bytes) } else { u32::from_le_bytes(bytes) }
+    }
+
+    /// Next record and its capture timestamp, or `None` at end of file
+    fn next_record(&mut self) -> Result<Option<(DateTime<Utc>, Vec<u8>)>> {
+        let mut header = [0u8; 16];
+        match self.reader.read_exact(&mut header) {
+            Ok(()) => {}
+            Err(e) if e.kind() == std::io::ErrorKind::UnexpectedEof => return Ok(None),
+            Err(e) => return Err(e.into()),
+        }
+        let seconds = self.u32_at(&header, 0) as i64;
+        let fraction = self.u32_at(&header, 4);
+        let captured = self.u32_at(&header, 8) as usize;
+        if captured > MAX_RECORD_LEN {
+            return Err(anyhow!("Corrupt capture: record of {} bytes", captured));
+        }
+        let nanos = if self.nanoseconds { fraction } else { fraction.saturating_mul(1000) };
+        let at = Utc.timestamp_opt(seconds, nanos).single().unwrap_or_else(Utc::now);
+
+        let mut data = vec![0u8; captured];
+        self.reader.read_exact(&mut data).context("Truncated capture record")?;
+        Ok(Some((at, data)))
+    }
+}
+
+/// Build scan results from observed traffic without sending anything
+///
+/// # Arguments
+/// * `source` - Capture file or interface to observe
+/// * `scope` - Hosts and ports to report on
+/// * `duration` - How long to watch a live interface (`None` = until Ctrl-C); ignored for files
+///
+/// # Returns
+/// * `Result<Vec<ScanResults>>` - One result set per host with observed ports
+///
+/// # Opsec Considerations
+/// - Sends no packets at all; target resolution is refused for the same reason
+/// - Live capture puts the NIC in promiscuous mode, which local EDR may flag
+pub async fn observe(source: PassiveSource, scope: PassiveScope, duration: Option<Duration>) -> Result<Vec<ScanResults>> {
+    let observer = match source {
+        PassiveSource::Pcap(path) => {
+            info!("[Passive] Reading capture {}", path.display());
+            tokio::task::spawn_blocking(move || -> Result<PassiveObserver> {
+                let mut reader = PcapReader::open(&path)?;
+                let mut observer = PassiveObserver::new(scope);
+                while let Some((at, frame)) = reader.next_record()? {
+                    observer.observe_frame(reader.linktype, &frame, at);
+                }
+                Ok(observer)
+            }).await.map_err(|e| anyhow!("Capture reader task failed: {}", e))??
+        }
+        PassiveSource::Interface(name) => {
+            let iface = datalink::interfaces()
+                .into_iter()
+                .find(|iface| iface.name == name)
+                .ok_or_else(|| anyhow!("No such interface: {}", name))?;
+            let config = datalink::Config {
+                read_timeout: Some(Duration::from_millis(100)),
+                promiscuous: true,
+                ..Default::default()
+            };
+            let mut rx = match datalink::channel(&iface, config) {
+                Ok(Channel::Ethernet(_, rx)) => rx,
+                Ok(_) => return Err(anyhow!("Unsupported datalink channel type on {}", iface.name)),
+                Err(e) => return Err(anyhow!("Failed to open capture on {} (requires root/admin privileges): {}", iface.name, e)),
+            };
+
+            match duration {
+                Some(duration) => info!("[Passive] Watching {} for {:?}", iface.name, duration),
+                None => info!("[Passive] Watching {} until interrupted", iface.name),
+            }
+            let done = Arc::new(AtomicBool::new(false));
+            let capture = {
+                let done = done.clone();
+                tokio::task::spawn_blocking(move || {
+                    let mut observer = PassiveObserver::new(scope);
+                    while !done.load(Ordering::Relaxed) {
+                        match rx.next() {
+                            Ok(frame) => observer.observe_frame(LINKTYPE_ETHERNET, frame, Utc::now()),
+                            Err(e) if e.kind() == std::io::ErrorKind::TimedOut => {}
+                            Err(e) => debug!("[Passive] Capture error: {}", e),
+                        }
+                    }
+                    observer
+                })
+            };
+
+            match duration {
+                Some(duration) => tokio::select! {
+                    _ = tokio::time::sleep(duration) => {}
+                    _ = tokio::signal::ctrl_c() => warn!("[Passive] Interrupted, reporting what was seen so far"),
+                },
+                None => { let _ = tokio::signal::ctrl_c().await; }
+            }
+            done.store(true, Ordering::Relaxed);
+            capture.await.map_err(|e| anyhow!("Capture task failed: {}", e))?
+        }
+    };
+
+    let packets = observer.packets();
+    let results = observer.into_results();
+    info!("[Passive] {} frames observed, {} hosts with port evidence", packets, results.len());
+    Ok(results)
+}
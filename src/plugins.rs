@@ -0,0 +1,325 @@
+//! Scanner plugins.
+//!
+//! `ScannerPlugin` is the extension point for third-party detection logic, with three
+//! hooks:
+//! - `probe`: bytes to send when grabbing a port's banner, replacing the built-in probe
+//! - `enrich`: additions to a port's result after detection (service, version, findings,
+//!   vulnerabilities, anomalies)
+//! - `output`: extra report text rendered from the final results
+//!
+//! Plugins are WebAssembly modules loaded from `--plugins <DIR>` and run in the `wasmi`
+//! interpreter. A module sees nothing of the host beyond one logging import, gets a fresh
+//! instance for every call, a fuel budget per call and capped memory and tables, so a
+//! plugin can neither touch the network or filesystem nor stall the scan or exhaust its
+//! memory.
+//!
+//! # Module ABI (version 1)
+//! Exports:
+//! - `memory`
+//! - `qs_abi_version() -> i32`, returning 1
+//! - `qs_alloc(len: i32) -> i32`, a buffer the host writes the hook input into
+//! - any of `qs_probe`, `qs_enrich`, `qs_output`, each `(ptr: i32, len: i32) -> i64`
+//!
+//! Hook input is JSON (see `ProbeRequest`, `EnrichRequest` and `ScanResults`). The return
+//! value packs the output buffer as `ptr << 32 | len`; 0 means nothing to add. `qs_probe`
+//! returns raw probe bytes, `qs_enrich` an `Enrichment` as JSON and `qs_output` UTF-8 text.
+//!
+//! Imports: `env.qs_log(ptr: i32, len: i32)`, which writes a UTF-8 message to the scanner
+//! log. A module importing anything else fails to load.
+
+use std::net::IpAddr;
+use std::path::Path;
+use std::sync::Arc;
+
+use anyhow::{anyhow, Context, Result};
+use lazy_static::lazy_static;
+use log::{debug, info, warn};
+use parking_lot::RwLock;
+use serde::{Deserialize, Serialize};
+use wasmi::{Caller, Config, Engine, Extern, Instance, Linker, Module, Store, StoreLimits, StoreLimitsBuilder};
+
+use crate::models::{Finding, PortResult, ScanResults, ScriptOutput};
+
+/// Plugin ABI version this build implements
+pub const PLUGIN_ABI_VERSION: i32 = 1;
+
+/// Instructions a plugin may execute per hook call
+const FUEL_PER_CALL: u64 = 50_000_000;
+
+/// Largest linear memory a plugin instance may grow to
+const MAX_MEMORY: usize = 64 * 1024 * 1024;
+
+/// Most elements a plugin table may hold
+const MAX_TABLE_ELEMENTS: u32 = 10_000;
+
+/// Largest hook output accepted from a plugin
+const MAX_OUTPUT: usize = 1024 * 1024;
+
+/// Input to the probe hook
+#[derive(Debug, Clone, Serialize)]
+pub struct ProbeRequest {
+    pub host: IpAddr,
+    pub port: u16,
+}
+
+/// Input to the enrichment hook
+#[derive(Debug, Clone, Serialize)]
+pub struct EnrichRequest<'a> {
+    pub host: IpAddr,
+    pub port: u16,
+    pub result: &'a PortResult,
+}
+
+/// What an enrichment hook adds to a port result
+///
+/// `service` and `version` only fill in fields detection left empty; the lists are
+/// appended to.
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(default)]
+pub struct Enrichment {
+    pub service: Option<String>,
+    pub version: Option<String>,
+    pub findings: Vec<String>,
+    pub vulns: Vec<Finding>,
+    pub anomalies: Vec<String>,
+}
+
+/// Detection plugin interface
+///
+/// Every hook has a no-op default, so a plugin implements only what it needs.
+pub trait ScannerPlugin: Send + Sync {
+    /// Plugin name used in logs and script output
+    fn name(&self) -> &str;
+
+    /// Probe to send when grabbing this port's banner; `None` keeps the built-in probe
+    fn probe(&self, _request: &ProbeRequest) -> Result<Option<Vec<u8>>> {
+        Ok(None)
+    }
+
+    /// Additions to a port's result once detection has finished
+    fn enrich(&self, _request: &EnrichRequest<'_>) -> Result<Option<Enrichment>> {
+        Ok(None)
+    }
+
+    /// Extra report text for a finished scan
+    fn output(&self, _results: &ScanResults) -> Result<Option<String>> {
+        Ok(None)
+    }
+}
+
+/// Host state visible to a plugin instance
+struct HostState {
+    plugin: String,
+    limits: StoreLimits,
+}
+
+/// A WebAssembly plugin
+pub struct WasmPlugin {
+    name: String,
+    engine: Engine,
+    module: Module,
+    hooks: Vec<&'static str>,
+}
+
+impl WasmPlugin {
+    /// Load and validate a plugin module
+    ///
+    /// # Arguments
+    /// * `path` - `.wasm` file implementing the plugin ABI
+    ///
+    /// # Returns
+    /// * `Result<Self>` - Error if the module is invalid, imports anything but `env.qs_log`
+    ///   or reports a different ABI version
+    pub fn load(path: &Path) -> Result<Self> {
+        let name = path.file_stem().map(|s| s.to_string_lossy().into_owned()).unwrap_or_default();
+        let bytes = std::fs::read(path).with_context(|| format!("Failed to read plugin {}", path.display()))?;
+
+        let mut config = Config::default();
+        config.consume_fuel(true);
+        let engine = Engine::new(&config);
+        let module = Module::new(&engine, &bytes[..]).map_err(|e| anyhow!("Plugin {}: {}", path.display(), e))?;
+
+        let mut plugin = WasmPlugin { name, engine, module, hooks: Vec::new() };
+        let (mut store, instance) = plugin.instantiate()?;
+        let version = instance.get_typed_func::<(), i32>(&store, "qs_abi_version")
+            .and_then(|f| f.call(&mut store, ()))
+            .map_err(|e| anyhow!("Plugin {}: qs_abi_version: {}", path.display(), e))?;
+        if version != PLUGIN_ABI_VERSION {
+            return Err(anyhow!("Plugin {} targets ABI {}, this build supports {}", path.display(), version, PLUGIN_ABI_VERSION));
+        }
+        plugin.hooks = ["qs_probe", "qs_enrich", "qs_output"].into_iter()
+            .filter(|hook| instance.get_func(&store, hook).is_some())
+            .collect();
+        Ok(plugin)
+    }
+
+    /// Fresh sandboxed instance with a full fuel budget and capped memory and tables
+    fn instantiate(&self) -> Result<(Store<HostState>, Instance)> {
+        let limits = StoreLimitsBuilder::new()
+            .memory_size(MAX_MEMORY)
+            .table_elements(MAX_TABLE_ELEMENTS)
+            .build();
+        let mut store = Store::new(&self.engine, HostState { plugin: self.name.clone(), limits });
+        store.limiter(|state| &mut state.limits);
+        store.add_fuel(FUEL_PER_CALL).map_err(|e| anyhow!("{}", e))?;
+
+        let mut linker = <Linker<HostState>>::new(&self.engine);
+        linker.func_wrap("env", "qs_log", |caller: Caller<'_, HostState>, ptr: i32, len: i32| {
+            let memory = caller.get_export("memory").and_then(Extern::into_memory);
+            let mut buffer = vec![0u8; (len.max(0) as usize).min(4096)];
+            if let Some(memory) = memory {
+                if memory.read(&caller, ptr as u32 as usize, &mut buffer).is_ok() {
+                    info!("[Plugin {}] {}", caller.data().plugin, String::from_utf8_lossy(&buffer));
+                }
+            }
+        }).map_err(|e| anyhow!("{}", e))?;
+
+        let instance = linker.instantiate(&mut store, &self.module)
+            .and_then(|pre| pre.start(&mut store))
+            .map_err(|e| anyhow!("Plugin {}: {}", self.name, e))?;
+        Ok((store, instance))
+    }
+
+    /// Call a hook with `input` and return its output buffer, if any
+    fn call_hook(&self, hook: &'static str, input: &[u8]) -> Result<Option<Vec<u8>>> {
+        if !self.hooks.contains(&hook) {
+            return Ok(None);
+        }
+        let (mut store, instance) = self.instantiate()?;
+        let memory = instance.get_memory(&store, "memory").ok_or_else(|| anyhow!("Plugin {} exports no memory", self.name))?;
+
+        let alloc = instance.get_typed_func::<i32, i32>(&store, "qs_alloc").map_err(|e| anyhow!("{}", e))?;
+        let ptr = alloc.call(&mut store, input.len() as i32).map_err(|e| anyhow!("qs_alloc: {}", e))?;
+        memory.write(&mut store, ptr as u32 as usize, input).map_err(|e| anyhow!("writing hook input: {}", e))?;
+
+        let func = instance.get_typed_func::<(i32, i32), i64>(&store, hook).map_err(|e| anyhow!("{}", e))?;
+        let packed = func.call(&mut store, (ptr, input.len() as i32)).map_err(|e| anyhow!("{}: {}", hook, e))?;
+        if packed == 0 {
+            return Ok(None);
+        }
+
+        let (out_ptr, out_len) = ((packed as u64 >> 32) as usize, (packed as u64 & 0xffff_ffff) as usize);
+        if out_len > MAX_OUTPUT {
+            return Err(anyhow!("{} returned {} bytes, limit is {}", hook, out_len, MAX_OUTPUT));
+        }
+        let mut output = vec![0u8; out_len];
+        memory.read(&store, out_ptr, &mut output).map_err(|e| anyhow!("reading {} output: {}", hook, e))?;
+        Ok(Some(output))
+    }
+}
+
+impl ScannerPlugin for WasmPlugin {
+    fn name(&self) -> &str {
+        &self.name
+    }
+
+    fn probe(&self, request: &ProbeRequest) -> Result<Option<Vec<u8>>> {
+        self.call_hook("qs_probe", &serde_json::to_vec(request)?)
+    }
+
+    fn enrich(&self, request: &EnrichRequest<'_>) -> Result<Option<Enrichment>> {
+        match self.call_hook("qs_enrich", &serde_json::to_vec(request)?)? {
+            Some(output) => Ok(Some(serde_json::from_slice(&output).context("qs_enrich returned invalid JSON")?)),
+            None => Ok(None),
+        }
+    }
+
+    fn output(&self, results: &ScanResults) -> Result<Option<String>> {
+        Ok(self.call_hook("qs_output", &serde_json::to_vec(results)?)?
+            .map(|output| String::from_utf8_lossy(&output).into_owned()))
+    }
+}
+
+lazy_static! {
+    /// Loaded plugins, in load order
+    static ref PLUGINS: RwLock<Arc<Vec<Box<dyn ScannerPlugin>>>> = RwLock::new(Arc::new(Vec::new()));
+}
+
+/// Loaded plugins
+pub fn plugins() -> Arc<Vec<Box<dyn ScannerPlugin>>> {
+    PLUGINS.read().clone()
+}
+
+/// Load every `*.wasm` plugin in a directory
+///
+/// # Arguments
+/// * `dir` - Directory holding the plugin modules
+///
+/// # Returns
+/// * `Result<usize>` - Number of plugins loaded, or the first load error
+pub fn load_plugins(dir: &Path) -> Result<usize> {
+    let mut paths: Vec<_> = std::fs::read_dir(dir)
+        .with_context(|| format!("Failed to read plugin directory {}", dir.display()))?
+        .filter_map(|entry| entry.ok().map(|e| e.path()))
+        .filter(|path| path.extension().map_or(false, |ext| ext == "wasm"))
+        .collect();
+    paths.sort();
+
+    let mut loaded: Vec<Box<dyn ScannerPlugin>> = Vec::new();
+    for path in paths {
+        let plugin = WasmPlugin::load(&path)?;
+        debug!("[Plugin] Loaded {} (hooks: {})", plugin.name, plugin.hooks.join(", "));
+        loaded.push(Box::new(plugin));
+    }
+
+    let count = loaded.len();
+    *PLUGINS.write() = Arc::new(loaded);
+    Ok(count)
+}
+
+/// Probe from the first plugin that supplies one for this port
+pub fn probe_payload(host: IpAddr, port: u16) -> Option<Vec<u8>> {
+    let request = ProbeRequest { host, port };
+    for plugin in plugins().iter() {
+        match plugin.probe(&request) {
+            Ok(Some(probe)) => {
+                debug!("[Plugin] {} supplied a {}-byte probe for port {}", plugin.name(), probe.len(), port);
+                return Some(probe);
+            }
+            Ok(None) => {}
+            Err(e) => warn!("[Plugin] {} probe hook failed on port {}: {}", plugin.name(), port, e),
+        }
+    }
+    None
+}
+
+/// Apply every plugin's enrichment to a port result
+///
+/// Blocking (plugins run synchronously); call from `spawn_blocking` for many ports.
+pub fn enrich_port(host: IpAddr, port: u16, result: &mut PortResult) {
+    for plugin in plugins().iter() {
+        let enrichment = match plugin.enrich(&EnrichRequest { host, port, result: &*result }) {
+            Ok(Some(enrichment)) => enrichment,
+            Ok(None) => continue,
+            Err(e) => {
+                warn!("[Plugin] {} enrich hook failed on port {}: {}", plugin.name(), port, e);
+                continue;
+            }
+        };
+        if result.service.is_none() {
+            result.service = enrichment.service;
+        }
+        if result.version.is_none() {
+            result.version = enrichment.version;
+        }
+        if !enrichment.findings.is_empty() {
+            result.script_output.push(ScriptOutput { script: plugin.name().to_string(), findings: enrichment.findings });
+        }
+        result.vulns.extend(enrichment.vulns);
+        result.anomalies.extend(enrichment.anomalies.into_iter().map(Finding::anomaly));
+    }
+}
+
+/// Report text from every plugin with an output hook
+pub fn render_output(results: &ScanResults) -> Vec<(String, String)> {
+    plugins().iter()
+        .filter_map(|plugin| match plugin.output(results) {
+            Ok(Some(text)) => Some((plugin.name().to_string(), text)),
+            Ok(None) => None,
+            Err(e) => {
+                warn!("[Plugin] {} output hook failed: {}", plugin.name(), e);
+                None
+            }
+        })
+        .collect()
+}
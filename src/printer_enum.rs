@@ -0,0 +1,517 @@
+//! Printer enumeration.
+//!
+//! Network printers store jobs, keep address books and credentials for scan-to-mail, and
+//! sit on internal segments, yet usually show up as bare open ports. Each printing
+//! protocol can be asked what the device is without printing anything:
+//! - JetDirect (9100): PJL `INFO ID`, `INFO CONFIG`, `INFO STATUS` and `INFO FILESYS`
+//!   inside a universal exit language envelope, which the interpreter consumes
+//! - IPP (631): a `Get-Printer-Attributes` request for the model, firmware, location,
+//!   supported operations and authentication
+//! - LPD (515): the short queue state of the default queue
+//! - SNMP (161, when scanned): the Printer MIB serial number and Host Resources device
+//!   description with the `public` community
+//!
+//! Printers fall in the `printer` service class, so `--safe` and `--protect-services
+//! printer` keep all of this away from them.
+
+use std::collections::HashMap;
+use std::net::{IpAddr, SocketAddr};
+use std::time::Duration;
+
+use anyhow::{anyhow, Context, Result};
+use log::{debug, info, warn};
+use rand::Rng;
+use tokio::io::AsyncReadExt;
+use tokio::net::TcpStream;
+use tokio::time::timeout;
+
+use crate::models::{Confidence, Finding, PortResult, PortStatus, PrinterInfo};
+
+/// Universal exit language: leaves whatever interpreter is active and returns to PJL
+const UEL: &str = "\x1b%-12345X";
+
+/// PJL queries, each answered with a form-feed terminated block
+const PJL_QUERIES: [&str; 4] = ["ID", "CONFIG", "STATUS", "FILESYS"];
+
+/// Largest reply read
+const MAX_REPLY: usize = 32 * 1024;
+
+/// Attributes asked for with Get-Printer-Attributes
+const IPP_ATTRIBUTES: [&str; 8] = [
+    "printer-make-and-model", "printer-firmware-string-version", "printer-info", "printer-location",
+    "printer-state-message", "operations-supported", "uri-authentication-supported", "uri-security-supported",
+];
+
+/// IPP operations worth listing, by operation id
+const IPP_OPERATIONS: [(u32, &str); 14] = [
+    (0x02, "Print-Job"), (0x03, "Print-URI"), (0x05, "Create-Job"), (0x08, "Cancel-Job"), (0x0a, "Get-Jobs"),
+    (0x10, "Pause-Printer"), (0x11, "Resume-Printer"), (0x12, "Purge-Jobs"), (0x16, "Set-Printer-Attributes"),
+    (0x22, "Enable-Printer"), (0x23, "Disable-Printer"), (0x4003, "CUPS-Add-Modify-Printer"),
+    (0x4004, "CUPS-Delete-Printer"), (0x4008, "CUPS-Accept-Jobs"),
+];
+
+/// Operations that change the printer rather than submit work
+const IPP_ADMIN_OPERATIONS: [&str; 8] = [
+    "Pause-Printer", "Purge-Jobs", "Set-Printer-Attributes", "Enable-Printer", "Disable-Printer",
+    "CUPS-Add-Modify-Printer", "CUPS-Delete-Printer", "CUPS-Accept-Jobs",
+];
+
+/// Printer MIB and related objects read over SNMP: (field, OID)
+const SNMP_OIDS: [(&str, &[u32]); 4] = [
+    ("model", &[1, 3, 6, 1, 2, 1, 25, 3, 2, 1, 3, 1]),       // hrDeviceDescr.1
+    ("serial", &[1, 3, 6, 1, 2, 1, 43, 5, 1, 1, 17, 1]),     // prtGeneralSerialNumber.1
+    ("description", &[1, 3, 6, 1, 2, 1, 1, 1, 0]),           // sysDescr.0
+    ("location", &[1, 3, 6, 1, 2, 1, 1, 6, 0]),              // sysLocation.0
+];
+
+/// Printing protocol usually listening on `port`
+fn protocol_for_port(port: u16) -> Option<&'static str> {
+    match port {
+        9100 => Some("pjl"),
+        631 => Some("ipp"),
+        515 => Some("lpd"),
+        _ => None,
+    }
+}
+
+/// Printing protocol named by detection
+fn protocol_for_service(service: &str) -> Option<&'static str> {
+    let service = service.to_ascii_lowercase();
+    if service.contains("jetdirect") || service.contains("pdl-datastream") {
+        Some("pjl")
+    } else if service.starts_with("ipp") {
+        Some("ipp")
+    } else if service.contains("lpd") || service == "printer" {
+        Some("lpd")
+    } else {
+        None
+    }
+}
+
+async fn connect(addr: SocketAddr, wait: Duration) -> Result<TcpStream> {
+    Ok(timeout(wait, crate::netbind::connect_tcp(addr)).await.context("Connect timed out")??)
+}
+
+/// Read until `done` says the reply is complete, the peer closes, or it goes quiet
+async fn read_until(stream: &mut TcpStream, wait: Duration, done: impl Fn(&[u8]) -> bool) -> Result<Vec<u8>> {
+    let mut reply = Vec::new();
+    let mut buf = vec![0u8; 4096];
+    while reply.len() < MAX_REPLY && !done(&reply) {
+        match timeout(wait, stream.read(&mut buf)).await {
+            Ok(Ok(0)) | Err(_) => break,
+            Ok(Ok(n)) => reply.extend_from_slice(&buf[..n]),
+            Ok(Err(e)) if reply.is_empty() => return Err(e.into()),
+            Ok(Err(_)) => break,
+        }
+    }
+    Ok(reply)
+}
+
+/// Strip quotes and whitespace from a PJL value
+fn pjl_value(value: &str) -> String {
+    value.trim().trim_matches('"').trim().to_string()
+}
+
+async fn check_pjl(addr: SocketAddr, wait: Duration) -> Result<Option<(PrinterInfo, Vec<Finding>)>> {
+    let mut request = format!("{}@PJL\r\n", UEL);
+    for query in PJL_QUERIES {
+        request.push_str(&format!("@PJL INFO {}\r\n", query));
+    }
+    request.push_str(UEL);
+
+    let mut stream = connect(addr, wait).await?;
+    crate::scheduler::metered_write(&mut stream, request.as_bytes()).await?;
+    let reply = read_until(&mut stream, wait, |r| r.iter().filter(|&&b| b == 0x0c).count() >= PJL_QUERIES.len()).await?;
+    let text = String::from_utf8_lossy(&reply);
+
+    // Each answer echoes its command, then the values, then a form feed
+    let mut sections: HashMap<&str, Vec<&str>> = HashMap::new();
+    for block in text.split('\x0c') {
+        let mut lines = block.lines().map(str::trim).filter(|l| !l.is_empty());
+        let Some(query) = lines.next().and_then(|l| l.strip_prefix("@PJL INFO ")) else { continue };
+        if let Some(&known) = PJL_QUERIES.iter().find(|q| q.eq_ignore_ascii_case(query.trim())) {
+            sections.insert(known, lines.collect());
+        }
+    }
+    if sections.is_empty() {
+        return Ok(None);
+    }
+
+    let mut info = PrinterInfo { protocol: "pjl".to_string(), ..PrinterInfo::default() };
+    info.model = sections.get("ID").and_then(|lines| lines.first()).map(|l| pjl_value(l)).filter(|m| !m.is_empty());
+    info.firmware = sections.get("CONFIG").and_then(|lines| lines.iter().find_map(|line| {
+        let upper = line.to_ascii_uppercase();
+        (upper.contains("FIRMWARE") || upper.contains("DATECODE"))
+            .then(|| pjl_value(line.split_once('=').map_or(*line, |(_, v)| v)))
+    }));
+    info.status = sections.get("STATUS").and_then(|lines| lines.iter().find_map(|line| line.strip_prefix("DISPLAY=").map(pjl_value)));
+    info.functions.push("PJL job and settings control".to_string());
+
+    let mut vulns = vec![Finding {
+        id: "PRINTER-PJL-UNAUTHENTICATED".to_string(),
+        title: "Unauthenticated PJL".to_string(),
+        description: format!("Raw printing port {} accepts PJL from anyone: jobs can be printed and device settings and the status display changed", addr.port()),
+        severity: "Medium".to_string(),
+        confidence: Confidence::Confirmed,
+        ..Default::default()
+    }];
+    let volumes: Vec<String> = sections.get("FILESYS").map(|lines| {
+        lines.iter().filter_map(|line| line.split_whitespace().next())
+            .filter(|word| word.ends_with(':') && word.len() <= 3)
+            .map(str::to_string)
+            .collect()
+    }).unwrap_or_default();
+    if !volumes.is_empty() {
+        info.functions.push(format!("PJL file system ({})", volumes.join(" ")));
+        vulns.push(Finding {
+            id: "PRINTER-PJL-FILESYSTEM".to_string(),
+            title: "PJL file system access".to_string(),
+            description: format!("PJL file system commands reach volumes {} on port {}; stored jobs, macros and configuration can be read and overwritten",
+                                 volumes.join(" "), addr.port()),
+            severity: "High".to_string(),
+            confidence: Confidence::Confirmed,
+            evidence: volumes.clone(),
+            ..Default::default()
+        });
+    }
+    Ok(Some((info, vulns)))
+}
+
+/// IPP attribute with a name, value length and value
+fn ipp_attribute(tag: u8, name: &str, value: &[u8], out: &mut Vec<u8>) {
+    out.push(tag);
+    out.extend_from_slice(&(name.len() as u16).to_be_bytes());
+    out.extend_from_slice(name.as_bytes());
+    out.extend_from_slice(&(value.len() as u16).to_be_bytes());
+    out.extend_from_slice(value);
+}
+
+/// Get-Printer-Attributes request for `printer_uri`
+fn ipp_request(printer_uri: &str, request_id: u32) -> Vec<u8> {
+    let mut out = vec![0x01, 0x01, 0x00, 0x0b];
+    out.extend_from_slice(&request_id.to_be_bytes());
+    out.push(0x01);
+    ipp_attribute(0x47, "attributes-charset", b"utf-8", &mut out);
+    ipp_attribute(0x48, "attributes-natural-language", b"en", &mut out);
+    ipp_attribute(0x45, "printer-uri", printer_uri.as_bytes(), &mut out);
+    for (index, attribute) in IPP_ATTRIBUTES.iter().enumerate() {
+        // Further values of a multi-valued attribute leave the name empty
+        ipp_attribute(0x44, if index == 0 { "requested-attributes" } else { "" }, attribute.as_bytes(), &mut out);
+    }
+    out.push(0x03);
+    out
+}
+
+/// An IPP attribute value: text for strings, keywords and URIs, a number for integers and enums
+#[derive(Debug)]
+enum IppValue {
+    Text(String),
+    Number(u32),
+}
+
+/// Status code and attributes of an IPP response
+fn parse_ipp(body: &[u8]) -> Option<(u16, HashMap<String, Vec<IppValue>>)> {
+    let status = u16::from_be_bytes([*body.get(2)?, *body.get(3)?]);
+    let mut attributes: HashMap<String, Vec<IppValue>> = HashMap::new();
+    let mut current = String::new();
+    let mut pos = 8;
+    while pos < body.len() {
+        let tag = body[pos];
+        pos += 1;
+        if tag == 0x03 {
+            break;
+        }
+        if tag < 0x10 {
+            continue;
+        }
+        let name_len = u16::from_be_bytes([*body.get(pos)?, *body.get(pos + 1)?]) as usize;
+        let name = body.get(pos + 2..pos + 2 + name_len)?;
+        pos += 2 + name_len;
+        let value_len = u16::from_be_bytes([*body.get(pos)?, *body.get(pos + 1)?]) as usize;
+        let value = body.get(pos + 2..pos + 2 + value_len)?;
+        pos += 2 + value_len;
+        if !name.is_empty() {
+            current = String::from_utf8_lossy(name).into_owned();
+        }
+        let value = match tag {
+            0x21 | 0x23 if value.len() == 4 => IppValue::Number(u32::from_be_bytes([value[0], value[1], value[2], value[3]])),
+            0x30..=0x4f => IppValue::Text(String::from_utf8_lossy(value).into_owned()),
+            _ => continue,
+        };
+        attributes.entry(current.clone()).or_default().push(value);
+    }
+    Some((status, attributes))
+}
+
+async fn check_ipp(addr: SocketAddr, host: &str, wait: Duration) -> Result<Option<(PrinterInfo, Vec<Finding>)>> {
+    for path in ["/ipp/print", "/ipp"] {
+        let request_id: u32 = crate::utils::scan_rng().gen_range(1..0x7fff_ffff);
+        let body = ipp_request(&format!("ipp://{}:{}{}", host, addr.port(), path), request_id);
+        let mut request = format!(
+            "POST {} HTTP/1.1\r\nHost: {}:{}\r\nContent-Type: application/ipp\r\nContent-Length: {}\r\nConnection: close\r\n\r\n",
+            path, host, addr.port(), body.len()
+        ).into_bytes();
+        request.extend_from_slice(&body);
+
+        let mut stream = connect(addr, wait).await?;
+        crate::scheduler::metered_write(&mut stream, &request).await?;
+        let reply = read_until(&mut stream, wait, |_| false).await?;
+        let Some(split) = reply.windows(4).position(|w| w == b"\r\n\r\n") else { return Ok(None) };
+        let head = String::from_utf8_lossy(&reply[..split]).to_ascii_lowercase();
+        if !head.starts_with("http/") {
+            return Ok(None);
+        }
+        if !head.contains("application/ipp") {
+            debug!("[Printer] {} answered {} without an IPP body", addr, path);
+            continue;
+        }
+        let Some((status, attributes)) = parse_ipp(&reply[split + 4..]) else { continue };
+        if status > 0x00ff {
+            debug!("[Printer] {} refused Get-Printer-Attributes on {} with status {:#06x}", addr, path, status);
+            continue;
+        }
+        return Ok(Some(ipp_findings(addr.port(), &attributes)));
+    }
+    Ok(None)
+}
+
+fn ipp_findings(port: u16, attributes: &HashMap<String, Vec<IppValue>>) -> (PrinterInfo, Vec<Finding>) {
+    let text = |name: &str| -> Option<String> {
+        attributes.get(name)?.iter().find_map(|v| match v {
+            IppValue::Text(text) if !text.is_empty() => Some(text.clone()),
+            _ => None,
+        })
+    };
+    let texts = |name: &str| -> Vec<String> {
+        attributes.get(name).map(|values| values.iter().filter_map(|v| match v {
+            IppValue::Text(text) => Some(text.clone()),
+            IppValue::Number(_) => None,
+        }).collect()).unwrap_or_default()
+    };
+    let operations: Vec<String> = attributes.get("operations-supported").map(|values| values.iter().filter_map(|v| match v {
+        IppValue::Number(id) => IPP_OPERATIONS.iter().find(|(op, _)| op == id).map(|(_, name)| name.to_string()),
+        IppValue::Text(_) => None,
+    }).collect()).unwrap_or_default();
+
+    let info = PrinterInfo {
+        protocol: "ipp".to_string(),
+        model: text("printer-make-and-model").or_else(|| text("printer-info")),
+        firmware: text("printer-firmware-string-version"),
+        serial: None,
+        location: text("printer-location"),
+        status: text("printer-state-message"),
+        functions: operations.iter().map(|op| format!("IPP {}", op)).collect(),
+    };
+
+    let authentication = texts("uri-authentication-supported");
+    let open = !authentication.is_empty() && authentication.iter().all(|a| a == "none");
+    let mut vulns = Vec::new();
+    if open {
+        let admin: Vec<&String> = operations.iter().filter(|op| IPP_ADMIN_OPERATIONS.contains(&op.as_str())).collect();
+        let (severity, reach) = if admin.is_empty() {
+            ("Medium", "anyone can submit and cancel jobs".to_string())
+        } else {
+            ("High", format!("anyone can submit jobs and use the administrative operations {}",
+                             admin.iter().map(|op| op.as_str()).collect::<Vec<_>>().join(", ")))
+        };
+        vulns.push(Finding {
+            id: "PRINTER-IPP-UNAUTHENTICATED".to_string(),
+            title: "Unauthenticated IPP".to_string(),
+            description: format!("IPP on port {} requires no authentication; {}", port, reach),
+            severity: severity.to_string(),
+            confidence: Confidence::Firm,
+            evidence: vec!["uri-authentication-supported: none".to_string()],
+            ..Default::default()
+        });
+    }
+    (info, vulns)
+}
+
+async fn check_lpd(addr: SocketAddr, wait: Duration) -> Result<Option<(PrinterInfo, Vec<Finding>)>> {
+    // 0x03: send queue state (short) of the default queue
+    let mut stream = connect(addr, wait).await?;
+    crate::scheduler::metered_write(&mut stream, b"\x03lp\n").await?;
+    let reply = read_until(&mut stream, wait, |_| false).await?;
+    let text = String::from_utf8_lossy(&reply).trim().to_string();
+    if text.is_empty() {
+        return Ok(None);
+    }
+
+    let status = text.lines().next().map(str::to_string);
+    let info = PrinterInfo {
+        protocol: "lpd".to_string(),
+        status,
+        functions: vec!["LPD queue state".to_string()],
+        ..PrinterInfo::default()
+    };
+    let vulns = vec![Finding {
+        id: "PRINTER-LPD-QUEUE".to_string(),
+        title: "LPD queue disclosure".to_string(),
+        description: format!("LPD on port {} reports its queue to anyone; job names and owners can leak", addr.port()),
+        severity: "Low".to_string(),
+        confidence: Confidence::Confirmed,
+        ..Default::default()
+    }];
+    Ok(Some((info, vulns)))
+}
+
+/// BER type-length-value
+fn tlv(tag: u8, content: &[u8]) -> Vec<u8> {
+    let mut out = vec![tag];
+    if content.len() < 0x80 {
+        out.push(content.len() as u8);
+    } else {
+        out.extend_from_slice(&[0x82, (content.len() >> 8) as u8, content.len() as u8]);
+    }
+    out.extend_from_slice(content);
+    out
+}
+
+fn encode_oid(oid: &[u32]) -> Vec<u8> {
+    let mut out = vec![(oid[0] * 40 + oid[1]) as u8];
+    for &arc in &oid[2..] {
+        let mut bytes = vec![(arc & 0x7f) as u8];
+        let mut rest = arc >> 7;
+        while rest > 0 {
+            bytes.push(0x80 | (rest & 0x7f) as u8);
+            rest >>= 7;
+        }
+        out.extend(bytes.iter().rev());
+    }
+    out
+}
+
+/// Next TLV in `data` at `pos`: tag, content and the position after it
+fn read_tlv(data: &[u8], pos: usize) -> Option<(u8, &[u8], usize)> {
+    let tag = *data.get(pos)?;
+    let first = *data.get(pos + 1)? as usize;
+    let (len, start) = if first < 0x80 {
+        (first, pos + 2)
+    } else {
+        let count = first & 0x7f;
+        let len = data.get(pos + 2..pos + 2 + count)?.iter().fold(0usize, |acc, &b| (acc << 8) | b as usize);
+        (len, pos + 2 + count)
+    };
+    Some((tag, data.get(start..start + len)?, start + len))
+}
+
+/// SNMPv2c GetRequest for every Printer MIB object at once
+fn snmp_request(request_id: u32) -> Vec<u8> {
+    let varbinds: Vec<u8> = SNMP_OIDS.iter()
+        .flat_map(|(_, oid)| tlv(0x30, &[tlv(0x06, &encode_oid(oid)), vec![0x05, 0x00]].concat()))
+        .collect();
+    let pdu = tlv(0xa0, &[
+        tlv(0x02, &request_id.to_be_bytes()),
+        tlv(0x02, &[0]),
+        tlv(0x02, &[0]),
+        tlv(0x30, &varbinds),
+    ].concat());
+    tlv(0x30, &[tlv(0x02, &[1]), tlv(0x04, b"public"), pdu].concat())
+}
+
+/// String values of the GetResponse, by field name
+fn parse_snmp(reply: &[u8]) -> Option<HashMap<&'static str, String>> {
+    let (_, message, _) = read_tlv(reply, 0)?;
+    let (_, _, pos) = read_tlv(message, 0)?;             // version
+    let (_, _, pos) = read_tlv(message, pos)?;           // community
+    let (tag, pdu, _) = read_tlv(message, pos)?;
+    if tag != 0xa2 {
+        return None;
+    }
+    let (_, _, pos) = read_tlv(pdu, 0)?;                 // request id
+    let (_, _, pos) = read_tlv(pdu, pos)?;               // error status
+    let (_, _, pos) = read_tlv(pdu, pos)?;               // error index
+    let (_, varbinds, _) = read_tlv(pdu, pos)?;
+
+    let mut values = HashMap::new();
+    let mut pos = 0;
+    while let Some((_, varbind, next)) = read_tlv(varbinds, pos) {
+        pos = next;
+        let Some((_, oid, value_pos)) = read_tlv(varbind, 0) else { continue };
+        let Some((value_tag, value, _)) = read_tlv(varbind, value_pos) else { continue };
+        let Some((field, _)) = SNMP_OIDS.iter().find(|(_, known)| encode_oid(known) == oid) else { continue };
+        let text = String::from_utf8_lossy(value).trim().to_string();
+        if value_tag == 0x04 && !text.is_empty() {
+            values.insert(*field, text);
+        }
+    }
+    Some(values)
+}
+
+async fn query_snmp(target: IpAddr, port: u16, wait: Duration) -> Result<HashMap<&'static str, String>> {
+    let addr = SocketAddr::new(target, port);
+    let socket = crate::netbind::bind_udp(target).await?;
+    let request_id: u32 = crate::utils::scan_rng().gen_range(1..0x7fff_ffff);
+    let request = snmp_request(request_id);
+    crate::scheduler::spend(request.len() + crate::scheduler::UDP_HEADER_BYTES).await;
+    crate::audit::record(|| crate::audit::Probe::new("printer-enum", "udp", target, Some(port), request.len() + crate::scheduler::UDP_HEADER_BYTES)
+        .detail("SNMP GET of the Printer MIB with community public"));
+    socket.send_to(&request, addr).await?;
+
+    let mut buffer = [0u8; 2048];
+    let (n, from) = timeout(wait, socket.recv_from(&mut buffer)).await.context("No SNMP reply")??;
+    if from != addr {
+        return Err(anyhow!("reply from {}", from));
+    }
+    parse_snmp(&buffer[..n]).ok_or_else(|| anyhow!("malformed SNMP reply"))
+}
+
+/// Enumerate the printing services of a host
+///
+/// # Arguments
+/// * `target` - Host to check
+/// * `hostname` - Name to put in IPP printer URIs; the address is used when not given
+/// * `results` - Port results after detection; only open TCP ports are considered
+/// * `snmp_port` - UDP port to read the Printer MIB from, when SNMP was scanned
+/// * `wait` - Connect and reply timeout
+///
+/// # Returns
+/// * `Vec<(u16, PrinterInfo, Vec<Finding>)>` - What each answering port revealed
+pub async fn enumerate_printers(target: IpAddr, hostname: Option<&str>, results: &HashMap<u16, PortResult>, snmp_port: Option<u16>, wait: Duration)
+    -> Vec<(u16, PrinterInfo, Vec<Finding>)> {
+    let mut ports: Vec<(u16, &'static str)> = results.iter()
+        .filter(|(_, r)| r.tcp_states.values().any(|&s| s == PortStatus::Open))
+        .filter_map(|(&port, r)| r.service.as_deref().and_then(protocol_for_service).or_else(|| protocol_for_port(port)).map(|p| (port, p)))
+        .collect();
+    ports.sort_unstable();
+    let host = hostname.map(str::to_string).unwrap_or_else(|| target.to_string());
+
+    let mut found = Vec::new();
+    for (port, protocol) in ports {
+        let addr = SocketAddr::new(target, port);
+        info!("[Printer] Querying {} over {}", addr, protocol);
+        let outcome = match protocol {
+            "pjl" => check_pjl(addr, wait).await,
+            "ipp" => check_ipp(addr, &host, wait).await,
+            _ => check_lpd(addr, wait).await,
+        };
+        match outcome {
+            Ok(Some((info, vulns))) => {
+                for vuln in vulns.iter().filter(|v| v.severity != "Low") {
+                    warn!("[Printer] {}: {}", addr, vuln.description);
+                }
+                found.push((port, info, vulns));
+            }
+            Ok(None) => debug!("[Printer] {} did not answer as {}", addr, protocol),
+            Err(e) => debug!("[Printer] Query of {} failed: {}", addr, e),
+        }
+    }
+
+    // The Printer MIB fills in what the printing protocols left out
+    if let Some(port) = snmp_port.filter(|_| !found.is_empty()) {
+        match query_snmp(target, port, wait).await {
+            Ok(values) if values.contains_key("serial") || values.contains_key("model") => {
+                for (_, info, _) in found.iter_mut() {
+                    info.model = info.model.take().or_else(|| values.get("model").cloned());
+                    info.serial = info.serial.take().or_else(|| values.get("serial").cloned());
+                    info.location = info.location.take().or_else(|| values.get("location").cloned());
+                    info.firmware = info.firmware.take().or_else(|| values.get("description").cloned());
+                    info.functions.push("SNMP Printer MIB (community public)".to_string());
+                }
+            }
+            Ok(_) => debug!("[Printer] SNMP on {} has no Printer MIB", target),
+            Err(e) => debug!("[Printer] SNMP query of {} failed: {}", target, e),
+        }
+    }
+    found
+}
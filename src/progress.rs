@@ -0,0 +1,230 @@
+//! Scan progress and ETA reporting.
+//!
+//! The scanner counts probes as they are queued and as they finish; a reporter task
+//! samples those counters on an interval and prints a status line (`--progress`) or a
+//! JSON event per line (`--progress-json`), both on stderr so results on stdout stay
+//! clean. Rates are smoothed so a burst or stall does not swing the ETA wildly.
+//!
+//! Only the port probing phases are counted. Service detection, scripts and plugins run
+//! afterwards and show up as the current phase without affecting the ETA.
+
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::time::{Duration, Instant};
+
+use chrono::Utc;
+use lazy_static::lazy_static;
+use parking_lot::RwLock;
+use serde::Serialize;
+use tokio::task::JoinHandle;
+
+/// How progress is reported
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ProgressFormat {
+    /// Human readable status line
+    Text,
+    /// One JSON object per line
+    Json,
+}
+
+/// Probes queued across all hosts
+static EXPECTED: AtomicU64 = AtomicU64::new(0);
+/// Probes finished across all hosts
+static COMPLETED: AtomicU64 = AtomicU64::new(0);
+/// Probes queued for the current host
+static HOST_EXPECTED: AtomicU64 = AtomicU64::new(0);
+/// Probes finished for the current host
+static HOST_COMPLETED: AtomicU64 = AtomicU64::new(0);
+/// Hosts known to need scanning (grows as targets are discovered)
+static HOSTS_TOTAL: AtomicU64 = AtomicU64::new(0);
+/// Hosts whose scan has finished
+static HOSTS_DONE: AtomicU64 = AtomicU64::new(0);
+
+lazy_static! {
+    /// Output format, `None` when progress reporting is off
+    static ref FORMAT: RwLock<Option<ProgressFormat>> = RwLock::new(None);
+    /// Host being scanned and what it is doing
+    static ref CURRENT: RwLock<(String, &'static str)> = RwLock::new((String::new(), "starting"));
+}
+
+/// Add hosts to the number expected to be scanned
+pub fn add_hosts(count: usize) {
+    HOSTS_TOTAL.fetch_add(count as u64, Ordering::Relaxed);
+}
+
+/// Start counting a new host
+pub fn begin_host(target: &str) {
+    HOST_EXPECTED.store(0, Ordering::Relaxed);
+    HOST_COMPLETED.store(0, Ordering::Relaxed);
+    *CURRENT.write() = (target.to_string(), "starting");
+    emit_event("host_start");
+}
+
+/// Mark the current host as finished
+pub fn end_host() {
+    HOSTS_DONE.fetch_add(1, Ordering::Relaxed);
+    set_phase("done");
+    emit_event("host_done");
+}
+
+/// Name the phase the current host is in (e.g. "port scan", "service detection")
+pub fn set_phase(phase: &'static str) {
+    CURRENT.write().1 = phase;
+}
+
+/// Count probes about to be queued for the current host
+pub fn add_expected(probes: usize) {
+    EXPECTED.fetch_add(probes as u64, Ordering::Relaxed);
+    HOST_EXPECTED.fetch_add(probes as u64, Ordering::Relaxed);
+}
+
+/// Count one finished probe
+pub fn probe_done() {
+    COMPLETED.fetch_add(1, Ordering::Relaxed);
+    HOST_COMPLETED.fetch_add(1, Ordering::Relaxed);
+}
+
+/// Point-in-time view of scan progress, as emitted by `--progress-json`
+#[derive(Debug, Clone, Serialize)]
+pub struct ProgressEvent {
+    /// "progress", "host_start", "host_done" or "done"
+    pub event: &'static str,
+    pub timestamp: String,
+    pub host: String,
+    pub phase: &'static str,
+    pub host_completed: u64,
+    pub host_total: u64,
+    pub hosts_done: u64,
+    pub hosts_total: u64,
+    pub completed: u64,
+    /// Probes queued so far; hosts not yet started are not included
+    pub total: u64,
+    /// Smoothed probes per second
+    pub pps: f64,
+    /// Seconds until the current host's probes are done
+    pub host_eta_secs: Option<u64>,
+    /// Seconds until every known host is done, assuming later hosts look like earlier ones
+    pub eta_secs: Option<u64>,
+}
+
+fn snapshot(event: &'static str, pps: Option<f64>) -> ProgressEvent {
+    let (host, phase) = CURRENT.read().clone();
+    let completed = COMPLETED.load(Ordering::Relaxed);
+    let total = EXPECTED.load(Ordering::Relaxed);
+    let host_completed = HOST_COMPLETED.load(Ordering::Relaxed);
+    let host_total = HOST_EXPECTED.load(Ordering::Relaxed);
+    let hosts_done = HOSTS_DONE.load(Ordering::Relaxed);
+    let hosts_total = HOSTS_TOTAL.load(Ordering::Relaxed).max(hosts_done);
+    let pps = pps.unwrap_or(0.0);
+
+    let (host_eta_secs, eta_secs) = if pps > 0.0 {
+        let host_remaining = host_total.saturating_sub(host_completed);
+        // Hosts not started yet are assumed to need as many probes as the average so far
+        let hosts_started = (hosts_done + u64::from(host_total > 0)).max(1);
+        let per_host = total / hosts_started;
+        let hosts_waiting = hosts_total.saturating_sub(hosts_started);
+        let remaining = total.saturating_sub(completed) + hosts_waiting * per_host;
+        (Some((host_remaining as f64 / pps) as u64), Some((remaining as f64 / pps) as u64))
+    } else {
+        (None, None)
+    };
+
+    ProgressEvent {
+        event,
+        timestamp: Utc::now().to_rfc3339(),
+        host,
+        phase,
+        host_completed,
+        host_total,
+        hosts_done,
+        hosts_total,
+        completed,
+        total,
+        pps: (pps * 10.0).round() / 10.0,
+        host_eta_secs,
+        eta_secs,
+    }
+}
+
+fn format_duration(secs: u64) -> String {
+    match secs {
+        s if s >= 3600 => format!("{}h{:02}m", s / 3600, (s % 3600) / 60),
+        s if s >= 60 => format!("{}m{:02}s", s / 60, s % 60),
+        s => format!("{}s", s),
+    }
+}
+
+fn print(event: &ProgressEvent, format: ProgressFormat) {
+    match format {
+        ProgressFormat::Json => {
+            if let Ok(line) = serde_json::to_string(event) {
+                eprintln!("{}", crate::redaction::apply(&line));
+            }
+        }
+        ProgressFormat::Text => {
+            let percent = if event.host_total > 0 {
+                event.host_completed as f64 * 100.0 / event.host_total as f64
+            } else {
+                0.0
+            };
+            let mut line = format!("[Progress] host {}/{} {} | {} | {}/{} probes ({:.1}%) | {:.0} pps",
+                (event.hosts_done + 1).min(event.hosts_total.max(1)), event.hosts_total.max(1), event.host,
+                event.phase, event.host_completed, event.host_total, percent, event.pps);
+            if let Some(eta) = event.host_eta_secs {
+                line.push_str(&format!(" | ETA {}", format_duration(eta)));
+            }
+            if let (Some(eta), true) = (event.eta_secs, event.hosts_total > 1) {
+                line.push_str(&format!(" (all hosts {})", format_duration(eta)));
+            }
+            eprintln!("{}", crate::redaction::apply(&line));
+        }
+    }
+}
+
+/// Emit a one-off event (JSON mode only; the status line only reports on the interval)
+fn emit_event(event: &'static str) {
+    if let Some(ProgressFormat::Json) = *FORMAT.read() {
+        print(&snapshot(event, None), ProgressFormat::Json);
+    }
+}
+
+/// Start periodic progress reporting
+///
+/// # Arguments
+/// * `format` - Status line or JSON events
+/// * `interval` - Time between reports
+///
+/// # Returns
+/// * `JoinHandle<()>` - The reporter task; pass it to `finish` when the scan is over
+pub fn start(format: ProgressFormat, interval: Duration) -> JoinHandle<()> {
+    *FORMAT.write() = Some(format);
+    tokio::spawn(async move {
+        let mut last_completed = COMPLETED.load(Ordering::Relaxed);
+        let mut last_sample = Instant::now();
+        let mut pps: Option<f64> = None;
+        loop {
+            tokio::time::sleep(interval).await;
+            let completed = COMPLETED.load(Ordering::Relaxed);
+            let elapsed = last_sample.elapsed().as_secs_f64().max(0.001);
+            let sample = completed.saturating_sub(last_completed) as f64 / elapsed;
+            // Exponentially weighted so one slow interval does not triple the ETA
+            pps = Some(match pps {
+                Some(previous) => previous * 0.7 + sample * 0.3,
+                None => sample,
+            });
+            last_completed = completed;
+            last_sample = Instant::now();
+            print(&snapshot("progress", pps), format);
+        }
+    })
+}
+
+/// Stop the reporter and emit a final event
+pub fn finish(reporter: JoinHandle<()>) {
+    reporter.abort();
+    let format = FORMAT.write().take();
+    if let Some(format) = format {
+        let mut event = snapshot("done", None);
+        event.phase = "done";
+        print(&event, format);
+    }
+}
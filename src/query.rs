@@ -0,0 +1,432 @@
+//! Filter expressions over scan results.
+//!
+//! `--filter` and the `query` subcommand take a small expression language evaluated per
+//! port, with the host's fields available alongside the port's:
+//!
+//! ```text
+//! port<1024 && service==http && vulns>0
+//! status==open && (banner~"OpenSSH_[67]" || tls==TLSv1.0)
+//! severity==critical || !cert
+//! ```
+//!
+//! Comparisons are `==`, `!=`, `<`, `<=`, `>`, `>=` and `~` (case-insensitive regex),
+//! combined with `&&`, `||`, `!` and parentheses. A field on its own tests that it is
+//! present (non-zero for counts). String comparisons ignore case; values with spaces or
+//! operator characters are quoted. On list fields (`cve`, `severity`, `script`) a
+//! comparison holds if any entry matches, and `!=` holds if none equals the value.
+//! Hosts are kept with only their matching ports, and dropped when none match.
+
+use std::fmt;
+use std::path::PathBuf;
+use std::str::FromStr;
+
+use anyhow::{anyhow, Context, Result};
+use regex::Regex;
+
+use crate::models::{PortResult, ScanResults};
+
+/// Value of a field for one port
+enum FieldValue<'a> {
+    Num(Option<f64>),
+    Str(Option<String>),
+    List(Vec<&'a str>),
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum FieldKind {
+    Num,
+    Str,
+    List,
+}
+
+/// Fields the language knows, with their kind
+const FIELDS: &[(&str, FieldKind)] = &[
+    ("port", FieldKind::Num),
+    ("vulns", FieldKind::Num),
+    ("anomalies", FieldKind::Num),
+    ("scripts", FieldKind::Num),
+    ("host", FieldKind::Str),
+    ("target", FieldKind::Str),
+    ("status", FieldKind::Str),
+    ("proto", FieldKind::Str),
+    ("service", FieldKind::Str),
+    ("version", FieldKind::Str),
+    ("banner", FieldKind::Str),
+    ("os", FieldKind::Str),
+    ("posture", FieldKind::Str),
+    ("tls", FieldKind::Str),
+    ("cert", FieldKind::Str),
+    ("ndpi", FieldKind::Str),
+    ("reason", FieldKind::Str),
+    ("unreliable", FieldKind::Str),
+    ("payload", FieldKind::Str),
+    ("client_cert", FieldKind::Str),
+    ("owner", FieldKind::Str),
+    ("environment", FieldKind::Str),
+    ("criticality", FieldKind::Str),
+    ("cve", FieldKind::List),
+    ("severity", FieldKind::List),
+    ("confidence", FieldKind::List),
+    ("script", FieldKind::List),
+    ("tag", FieldKind::List),
+];
+
+fn field_kind(name: &str) -> Option<FieldKind> {
+    FIELDS.iter().find(|(field, _)| *field == name).map(|(_, kind)| *kind)
+}
+
+fn field_value<'a>(name: &str, host: &'a ScanResults, port: u16, result: &'a PortResult) -> FieldValue<'a> {
+    match name {
+        "port" => FieldValue::Num(Some(port as f64)),
+        "vulns" => FieldValue::Num(Some(result.vulns.len() as f64)),
+        "anomalies" => FieldValue::Num(Some(result.anomalies.len() as f64)),
+        "scripts" => FieldValue::Num(Some(result.script_output.len() as f64)),
+        "host" => FieldValue::Str(Some(host.target_ip.clone())),
+        "target" => FieldValue::Str(Some(host.target.clone())),
+        "status" => FieldValue::Str(Some(result.final_status.to_string())),
+        "proto" => FieldValue::Str(Some(if result.udp_state.is_some() && result.tcp_states.is_empty() { "udp" } else { "tcp" }.to_string())),
+        "service" => FieldValue::Str(result.service.clone()),
+        "version" => FieldValue::Str(result.version.clone()),
+        "banner" => FieldValue::Str(result.banner.clone()),
+        "os" => FieldValue::Str(result.os_guess.clone().or_else(|| host.os_summary.clone())),
+        "posture" => FieldValue::Str(result.security_posture.clone()),
+        "tls" => FieldValue::Str(result.tls_protocol_version.clone()),
+        "cert" => FieldValue::Str(result.cert_info.as_ref().map(|cert| cert.subject.clone())),
+        "ndpi" => FieldValue::Str(result.ndpi_protocol.as_ref().map(|ndpi| ndpi.protocol_name.clone())),
+        "reason" => FieldValue::Str(result.reason.clone()),
+        "unreliable" => FieldValue::Str(result.unreliable.clone()),
+        "payload" => FieldValue::Str(result.payload.as_ref().map(|p| p.class.to_string())),
+        "client_cert" => FieldValue::Str(result.client_auth.as_ref().map(|a| if a.required { "required" } else { "optional" }.to_string())),
+        "owner" => FieldValue::Str(host.asset.as_ref().and_then(|a| a.owner.clone())),
+        "environment" => FieldValue::Str(host.asset.as_ref().and_then(|a| a.environment.clone())),
+        "criticality" => FieldValue::Str(host.asset.as_ref().and_then(|a| a.criticality).map(|c| c.to_string())),
+        "cve" => FieldValue::List(result.vulns.iter().map(|v| v.id.as_str()).collect()),
+        "severity" => FieldValue::List(result.vulns.iter().map(|v| v.severity.as_str()).collect()),
+        "confidence" => FieldValue::List(result.vulns.iter().map(|v| v.confidence.as_str()).collect()),
+        "script" => FieldValue::List(result.script_output.iter().flat_map(|s| s.findings.iter().map(String::as_str)).collect()),
+        "tag" => FieldValue::List(host.asset.iter().flat_map(|a| a.tags.iter().map(String::as_str)).collect()),
+        _ => FieldValue::Str(None),
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum CmpOp {
+    Eq,
+    Ne,
+    Lt,
+    Le,
+    Gt,
+    Ge,
+    Match,
+}
+
+#[derive(Debug, Clone)]
+enum Literal {
+    Num(f64),
+    Str(String),
+    Regex(Regex),
+}
+
+#[derive(Debug, Clone)]
+enum Expr {
+    Or(Box<Expr>, Box<Expr>),
+    And(Box<Expr>, Box<Expr>),
+    Not(Box<Expr>),
+    Present(String),
+    Cmp(String, CmpOp, Literal),
+}
+
+impl Expr {
+    fn eval(&self, host: &ScanResults, port: u16, result: &PortResult) -> bool {
+        match self {
+            Expr::Or(a, b) => a.eval(host, port, result) || b.eval(host, port, result),
+            Expr::And(a, b) => a.eval(host, port, result) && b.eval(host, port, result),
+            Expr::Not(inner) => !inner.eval(host, port, result),
+            Expr::Present(field) => match field_value(field, host, port, result) {
+                FieldValue::Num(n) => n.map_or(false, |n| n != 0.0),
+                FieldValue::Str(s) => s.map_or(false, |s| !s.is_empty()),
+                FieldValue::List(items) => !items.is_empty(),
+            },
+            Expr::Cmp(field, op, literal) => match field_value(field, host, port, result) {
+                FieldValue::Num(value) => match (value, literal) {
+                    (Some(value), Literal::Num(expected)) => compare_num(value, *op, *expected),
+                    _ => *op == CmpOp::Ne,
+                },
+                FieldValue::Str(value) => match value {
+                    Some(value) => compare_str(&value, *op, literal),
+                    None => *op == CmpOp::Ne,
+                },
+                FieldValue::List(items) if *op == CmpOp::Ne => items.iter().all(|item| compare_str(item, CmpOp::Ne, literal)),
+                FieldValue::List(items) => items.iter().any(|item| compare_str(item, *op, literal)),
+            },
+        }
+    }
+}
+
+fn compare_num(value: f64, op: CmpOp, expected: f64) -> bool {
+    match op {
+        CmpOp::Eq => value == expected,
+        CmpOp::Ne => value != expected,
+        CmpOp::Lt => value < expected,
+        CmpOp::Le => value <= expected,
+        CmpOp::Gt => value > expected,
+        CmpOp::Ge => value >= expected,
+        CmpOp::Match => false,
+    }
+}
+
+fn compare_str(value: &str, op: CmpOp, literal: &Literal) -> bool {
+    match (op, literal) {
+        (CmpOp::Match, Literal::Regex(re)) => re.is_match(value),
+        (CmpOp::Eq, Literal::Str(expected)) => value.eq_ignore_ascii_case(expected),
+        (CmpOp::Ne, Literal::Str(expected)) => !value.eq_ignore_ascii_case(expected),
+        _ => false,
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+enum Token {
+    Word(String),
+    Quoted(String),
+    Op(CmpOp),
+    And,
+    Or,
+    Not,
+    LParen,
+    RParen,
+}
+
+fn tokenize(input: &str) -> Result<Vec<Token>> {
+    let mut tokens = Vec::new();
+    let chars: Vec<char> = input.chars().collect();
+    let mut i = 0;
+    while i < chars.len() {
+        let c = chars[i];
+        let next = chars.get(i + 1).copied();
+        let (token, len) = match (c, next) {
+            (c, _) if c.is_whitespace() => { i += 1; continue; }
+            ('&', Some('&')) => (Token::And, 2),
+            ('|', Some('|')) => (Token::Or, 2),
+            ('=', Some('=')) => (Token::Op(CmpOp::Eq), 2),
+            ('!', Some('=')) => (Token::Op(CmpOp::Ne), 2),
+            ('<', Some('=')) => (Token::Op(CmpOp::Le), 2),
+            ('>', Some('=')) => (Token::Op(CmpOp::Ge), 2),
+            ('<', _) => (Token::Op(CmpOp::Lt), 1),
+            ('>', _) => (Token::Op(CmpOp::Gt), 1),
+            ('~', _) => (Token::Op(CmpOp::Match), 1),
+            ('!', _) => (Token::Not, 1),
+            ('(', _) => (Token::LParen, 1),
+            (')', _) => (Token::RParen, 1),
+            ('"', _) | ('\'', _) => {
+                let mut value = String::new();
+                let mut j = i + 1;
+                loop {
+                    match chars.get(j) {
+                        None => return Err(anyhow!("Unterminated string starting at column {}", i + 1)),
+                        Some('\\') if chars.get(j + 1) == Some(&c) => { value.push(c); j += 2; }
+                        Some(&ch) if ch == c => break,
+                        Some(&ch) => { value.push(ch); j += 1; }
+                    }
+                }
+                (Token::Quoted(value), j + 1 - i)
+            }
+            (c, _) if c.is_alphanumeric() || "-_./:".contains(c) => {
+                let len = chars[i..].iter().take_while(|c| c.is_alphanumeric() || "-_./:".contains(**c)).count();
+                (Token::Word(chars[i..i + len].iter().collect()), len)
+            }
+            (c, _) => return Err(anyhow!("Unexpected '{}' at column {}", c, i + 1)),
+        };
+        tokens.push(token);
+        i += len;
+    }
+    Ok(tokens)
+}
+
+struct ExprParser<'a> {
+    tokens: &'a [Token],
+    pos: usize,
+}
+
+impl ExprParser<'_> {
+    fn peek(&self) -> Option<&Token> {
+        self.tokens.get(self.pos)
+    }
+
+    fn next(&mut self) -> Option<Token> {
+        let token = self.tokens.get(self.pos).cloned();
+        self.pos += 1;
+        token
+    }
+
+    fn or(&mut self) -> Result<Expr> {
+        let mut expr = self.and()?;
+        while self.peek() == Some(&Token::Or) {
+            self.pos += 1;
+            expr = Expr::Or(Box::new(expr), Box::new(self.and()?));
+        }
+        Ok(expr)
+    }
+
+    fn and(&mut self) -> Result<Expr> {
+        let mut expr = self.unary()?;
+        while self.peek() == Some(&Token::And) {
+            self.pos += 1;
+            expr = Expr::And(Box::new(expr), Box::new(self.unary()?));
+        }
+        Ok(expr)
+    }
+
+    fn unary(&mut self) -> Result<Expr> {
+        match self.next() {
+            Some(Token::Not) => Ok(Expr::Not(Box::new(self.unary()?))),
+            Some(Token::LParen) => {
+                let expr = self.or()?;
+                match self.next() {
+                    Some(Token::RParen) => Ok(expr),
+                    _ => Err(anyhow!("Missing ')'")),
+                }
+            }
+            Some(Token::Word(field)) => self.comparison(field.to_ascii_lowercase()),
+            Some(token) => Err(anyhow!("Expected a field name, found {:?}", token)),
+            None => Err(anyhow!("Expression ends where a field name was expected")),
+        }
+    }
+
+    fn comparison(&mut self, field: String) -> Result<Expr> {
+        let kind = field_kind(&field).ok_or_else(|| anyhow!("Unknown field '{}' (known: {})",
+            field, FIELDS.iter().map(|(name, _)| *name).collect::<Vec<_>>().join(", ")))?;
+        let op = match self.peek() {
+            Some(Token::Op(op)) => *op,
+            _ => return Ok(Expr::Present(field)),
+        };
+        self.pos += 1;
+        let raw = match self.next() {
+            Some(Token::Word(value)) | Some(Token::Quoted(value)) => value,
+            _ => return Err(anyhow!("Expected a value after '{}' {:?}", field, op)),
+        };
+
+        let literal = match (kind, op) {
+            (FieldKind::Num, CmpOp::Match) => return Err(anyhow!("'{}' is a number; '~' only applies to text fields", field)),
+            (FieldKind::Num, _) => Literal::Num(raw.parse::<f64>()
+                .map_err(|_| anyhow!("'{}' is a number, cannot compare it with '{}'", field, raw))?),
+            (_, CmpOp::Match) => Literal::Regex(Regex::new(&format!("(?i){}", raw))
+                .map_err(|e| anyhow!("Invalid regex '{}': {}", raw, e))?),
+            (_, CmpOp::Eq) | (_, CmpOp::Ne) => Literal::Str(raw),
+            _ => return Err(anyhow!("'{}' is text; use ==, != or ~", field)),
+        };
+        Ok(Expr::Cmp(field, op, literal))
+    }
+}
+
+/// Compiled filter expression
+#[derive(Debug, Clone)]
+pub struct Filter {
+    expr: Expr,
+    source: String,
+}
+
+impl Filter {
+    /// Whether one port (with its host's fields) matches
+    pub fn matches(&self, host: &ScanResults, port: u16, result: &PortResult) -> bool {
+        self.expr.eval(host, port, result)
+    }
+
+    /// Keep the matching ports of each host, dropping hosts left with none
+    ///
+    /// # Arguments
+    /// * `results` - Results of one or more hosts
+    ///
+    /// # Returns
+    /// * `Vec<ScanResults>` - The same hosts in order, each with only matching ports
+    pub fn apply(&self, results: Vec<ScanResults>) -> Vec<ScanResults> {
+        results.into_iter().filter_map(|mut host| {
+            let matching: Vec<u16> = host.results.iter()
+                .filter(|(port, result)| self.matches(&host, **port, result))
+                .map(|(port, _)| *port)
+                .collect();
+            if matching.is_empty() {
+                return None;
+            }
+            host.results.retain(|port, _| matching.contains(port));
+            host.open_ports.retain(|port| matching.contains(port));
+            Some(host)
+        }).collect()
+    }
+}
+
+impl FromStr for Filter {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        let tokens = tokenize(s)?;
+        let mut parser = ExprParser { tokens: &tokens, pos: 0 };
+        let expr = parser.or()?;
+        if let Some(token) = parser.peek() {
+            return Err(anyhow!("Unexpected {:?} after a complete expression", token));
+        }
+        Ok(Filter { expr, source: s.to_string() })
+    }
+}
+
+impl fmt::Display for Filter {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.source)
+    }
+}
+
+/// Arguments for `quantum_scanner query`
+#[derive(clap::Parser, Debug)]
+#[clap(
+    name = "query",
+    about = "Filter saved JSON results with a filter expression",
+    after_help = "FIELDS:
+    port, vulns, anomalies, scripts                   numbers
+    host, target, status, proto, service, version,
+    banner, os, posture, tls, cert, ndpi, reason,
+    unreliable, payload, client_cert, owner,
+    environment, criticality                          text
+    cve, severity, confidence, script, tag            lists (any entry matches)
+
+EXAMPLES:
+    quantum_scanner query scan.json 'port<1024 && service==http && vulns>0'
+    quantum_scanner query scan.json 'banner~\"OpenSSH_[67]\" || tls==TLSv1.0' --json"
+)]
+pub struct QueryArgs {
+    /// JSON results written with -j/--json (one host or an array of hosts)
+    pub input: PathBuf,
+
+    /// Filter expression
+    pub filter: Filter,
+
+    /// Print the filtered results as JSON instead of one line per port
+    #[clap(long)]
+    pub json: bool,
+}
+
+/// Run the `query` subcommand
+pub fn run_query(args: QueryArgs) -> Result<()> {
+    let data = std::fs::read_to_string(&args.input)
+        .with_context(|| format!("Failed to read {}", args.input.display()))?;
+    let (results, _) = crate::schema::load_results(&data)
+        .with_context(|| format!("{} is not a JSON scan result", args.input.display()))?;
+
+    let filtered = args.filter.apply(results);
+    if args.json {
+        println!("{}", serde_json::to_string_pretty(&filtered)?);
+        return Ok(());
+    }
+    for host in &filtered {
+        let mut ports: Vec<_> = host.results.iter().collect();
+        ports.sort_by_key(|(port, _)| **port);
+        for (port, result) in ports {
+            println!("{}\t{}/{}\t{}\t{}{}",
+                host.target_ip,
+                port,
+                if result.udp_state.is_some() && result.tcp_states.is_empty() { "udp" } else { "tcp" },
+                result.final_status,
+                result.service.as_deref().unwrap_or("-"),
+                result.version.as_deref().map(|v| format!(" {}", v)).unwrap_or_default());
+        }
+    }
+    Ok(())
+}
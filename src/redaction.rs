@@ -0,0 +1,219 @@
+//! Redaction policy for logs and console output.
+//!
+//! Sensitive values are registered as the scan learns them (target addresses, hostnames,
+//! accepted credentials, banners). Every log record and console report then passes
+//! through `apply`, which replaces the values of the categories enabled by `--redact`:
+//!
+//! - **mask** (default): `[REDACTED:<category>]`, irreversible.
+//! - **keyed** (`--redact-key`): `[REDACTED:<category>:<token>]`, where the token is the
+//!   value sealed with AES-256-GCM under a key derived from the passphrase. The same
+//!   value always yields the same token, so redacted logs can still be correlated, and
+//!   `--fix-log-file` with the same key restores the originals.
+//!
+//! Result files written with `--output` are not redacted; use `--encrypt-output` for them.
+
+use std::borrow::Cow;
+use std::collections::{HashMap, HashSet};
+
+use aes_gcm::aead::{Aead, KeyInit, Payload};
+use aes_gcm::{Aes256Gcm, Nonce};
+use anyhow::{anyhow, Result};
+use base64::Engine as _;
+use clap::ValueEnum;
+use lazy_static::lazy_static;
+use parking_lot::RwLock;
+use regex::{Captures, Regex};
+use sha2::{Digest, Sha256};
+use zeroize::Zeroizing;
+
+use crate::encryption;
+
+/// Fixed salt for the redaction key, so tokens stay stable across runs with one passphrase
+const KEY_SALT: &[u8] = b"quantum_scanner/redaction/v1";
+/// Values shorter than this are never redacted; they would match all over the place
+const MIN_VALUE_LEN: usize = 3;
+
+/// Kinds of sensitive values the policy can redact independently
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, ValueEnum)]
+pub enum RedactCategory {
+    /// Target IP addresses
+    Targets,
+    /// Target hostnames and names from certificates
+    Hostnames,
+    /// Passphrases, keys and accepted default credentials
+    Credentials,
+    /// Service banners
+    Banners,
+    /// Every category above
+    All,
+}
+
+impl RedactCategory {
+    fn label(self) -> &'static str {
+        match self {
+            RedactCategory::Targets => "target",
+            RedactCategory::Hostnames => "hostname",
+            RedactCategory::Credentials => "credential",
+            RedactCategory::Banners => "banner",
+            RedactCategory::All => "all",
+        }
+    }
+}
+
+#[derive(Default)]
+struct Policy {
+    enabled: HashSet<RedactCategory>,
+    cipher: Option<(Aes256Gcm, Zeroizing<[u8; 32]>)>,
+    values: HashMap<String, RedactCategory>,
+    /// Alternation of all registered values, longest first
+    matcher: Option<Regex>,
+}
+
+lazy_static! {
+    static ref POLICY: RwLock<Policy> = RwLock::new(Policy::default());
+    static ref KEYED_TOKEN: Regex = Regex::new(r"\[REDACTED:([a-z]+):([A-Za-z0-9_-]+)\]").unwrap();
+}
+
+/// Derive the key for reversible redaction from a passphrase spec (literal, env:NAME, file:PATH)
+fn derive_redaction_key(spec: &str) -> Result<Zeroizing<[u8; 32]>> {
+    let passphrase = encryption::resolve_passphrase(spec)?;
+    encryption::derive_key(&passphrase, KEY_SALT, encryption::KDF_M_COST, encryption::KDF_T_COST, encryption::KDF_P_COST)
+}
+
+/// Set the redaction policy
+///
+/// # Arguments
+/// * `categories` - Categories to redact (`All` enables every one)
+/// * `key` - Passphrase spec for reversible tokens; `None` masks irreversibly
+pub fn configure(categories: &[RedactCategory], key: Option<&str>) -> Result<()> {
+    let cipher = match key {
+        Some(spec) => {
+            let key = derive_redaction_key(spec)?;
+            Some((Aes256Gcm::new((&*key).into()), key))
+        }
+        None => None,
+    };
+    let mut policy = POLICY.write();
+    policy.enabled = if categories.contains(&RedactCategory::All) {
+        [RedactCategory::Targets, RedactCategory::Hostnames, RedactCategory::Credentials, RedactCategory::Banners].into_iter().collect()
+    } else {
+        categories.iter().copied().collect()
+    };
+    policy.cipher = cipher;
+    Ok(())
+}
+
+/// Whether any category is being redacted
+pub fn active() -> bool {
+    !POLICY.read().enabled.is_empty()
+}
+
+fn rebuild_matcher(policy: &mut Policy) {
+    let mut values: Vec<&String> = policy.values.keys().collect();
+    values.sort_by(|a, b| b.len().cmp(&a.len()).then(a.cmp(b)));
+    let alternation: Vec<String> = values.iter().map(|value| {
+        let escaped = regex::escape(value);
+        // Word boundaries keep "public" from matching inside "republic"
+        let start = if value.starts_with(|c: char| c.is_alphanumeric()) { r"\b" } else { "" };
+        let end = if value.ends_with(|c: char| c.is_alphanumeric()) { r"\b" } else { "" };
+        format!("{}{}{}", start, escaped, end)
+    }).collect();
+    policy.matcher = Regex::new(&alternation.join("|")).ok();
+}
+
+/// Register a sensitive value; a no-op unless its category is being redacted
+///
+/// Banners are registered line by line as well, since log records usually quote a
+/// single line. The JSON-escaped form is registered too so JSON output is covered.
+pub fn register(category: RedactCategory, value: &str) {
+    if !POLICY.read().enabled.contains(&category) {
+        return;
+    }
+    let value = value.trim();
+    let mut candidates = vec![value.to_string()];
+    if category == RedactCategory::Banners {
+        candidates.extend(value.lines().map(|line| line.trim().to_string()));
+    }
+    if let Ok(json) = serde_json::to_string(value) {
+        candidates.push(json[1..json.len() - 1].to_string());
+    }
+
+    let mut policy = POLICY.write();
+    let mut changed = false;
+    for candidate in candidates {
+        if candidate.len() >= MIN_VALUE_LEN && !policy.values.contains_key(&candidate) {
+            policy.values.insert(candidate, category);
+            changed = true;
+        }
+    }
+    if changed {
+        rebuild_matcher(&mut policy);
+    }
+}
+
+fn token(policy: &Policy, category: RedactCategory, value: &str) -> String {
+    let Some((cipher, key)) = &policy.cipher else {
+        return format!("[REDACTED:{}]", category.label());
+    };
+    // Deterministic nonce (SIV-style) so equal values give equal tokens
+    let mut hasher = Sha256::new();
+    hasher.update(key.as_slice());
+    hasher.update(category.label().as_bytes());
+    hasher.update([0u8]);
+    hasher.update(value.as_bytes());
+    let digest = hasher.finalize();
+    let nonce = Nonce::from_slice(&digest[..12]);
+    match cipher.encrypt(nonce, Payload { msg: value.as_bytes(), aad: category.label().as_bytes() }) {
+        Ok(sealed) => {
+            let mut data = digest[..12].to_vec();
+            data.extend_from_slice(&sealed);
+            format!("[REDACTED:{}:{}]", category.label(), base64::engine::general_purpose::URL_SAFE_NO_PAD.encode(data))
+        }
+        Err(_) => format!("[REDACTED:{}]", category.label()),
+    }
+}
+
+/// Apply the policy to text bound for a log or the console
+pub fn apply(text: &str) -> Cow<'_, str> {
+    let policy = POLICY.read();
+    match &policy.matcher {
+        Some(matcher) if !policy.enabled.is_empty() => matcher.replace_all(text, |caps: &Captures<'_>| {
+            let value = &caps[0];
+            let category = policy.values.get(value).copied().unwrap_or(RedactCategory::All);
+            token(&policy, category, value)
+        }),
+        _ => Cow::Borrowed(text),
+    }
+}
+
+/// Restore values redacted in keyed mode
+///
+/// # Arguments
+/// * `text` - Redacted log contents
+/// * `key` - Passphrase spec used for `--redact-key` during the scan
+///
+/// # Returns
+/// * `Result<(String, usize)>` - Restored text and the number of tokens restored; tokens
+///   sealed under a different key are left in place
+pub fn unredact(text: &str, key: &str) -> Result<(String, usize)> {
+    let key = derive_redaction_key(key)?;
+    let cipher = Aes256Gcm::new((&*key).into());
+    let mut restored = 0;
+    let text = KEYED_TOKEN.replace_all(text, |caps: &Captures<'_>| {
+        let original = base64::engine::general_purpose::URL_SAFE_NO_PAD.decode(&caps[2]).ok()
+            .filter(|data| data.len() > 12)
+            .and_then(|data| cipher.decrypt(Nonce::from_slice(&data[..12]), Payload { msg: &data[12..], aad: caps[1].as_bytes() }).ok())
+            .and_then(|plain| String::from_utf8(plain).ok());
+        match original {
+            Some(value) => {
+                restored += 1;
+                value
+            }
+            None => caps[0].to_string(),
+        }
+    }).into_owned();
+    if restored == 0 && KEYED_TOKEN.is_match(&text) {
+        return Err(anyhow!("No redaction token could be restored: wrong --redact-key?"));
+    }
+    Ok((text, restored))
+}
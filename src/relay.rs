@@ -0,0 +1,996 @@
+//! Tunnel relay (`quantum_relay`).
+//!
+//! The far end of the scanner's tunnel transports. Run on a host the operator controls,
+//! it receives encoded probe requests, performs them from its own vantage point and
+//! returns the results:
+//! - DNS: authoritative server for the tunnel domain (`dns-tunnel` scans)
+//! - ICMP: covert channel endpoint for `--icmp-relay` banner grabs
+//! - HTTP(S): JSON endpoint for `http-tunnel` scans and `--http-relay`
+//! - QUIC: the same JSON requests on QUIC streams for `quic-tunnel` scans and `--quic-relay`
+//!
+//! Started with `quantum_scanner relay ...`, or by invoking the binary as `quantum_relay`.
+
+use std::collections::HashMap;
+use std::io::{Read, Write};
+use std::net::{IpAddr, Shutdown, SocketAddr};
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+
+use anyhow::{anyhow, Context, Result};
+use clap::Parser;
+use log::{debug, info, warn};
+use pnet::packet::icmp::{IcmpPacket, IcmpTypes};
+use pnet::packet::ip::IpNextHeaderProtocols;
+use pnet::packet::Packet;
+use pnet::transport::{icmp_packet_iter, transport_channel, TransportChannelType, TransportProtocol};
+use rustls::pki_types::{CertificateDer, PrivateKeyDer};
+use serde_json::json;
+use tokio::io::{AsyncRead, AsyncReadExt, AsyncWrite, AsyncWriteExt};
+use tokio::net::{TcpListener, TcpStream, UdpSocket};
+use tokio::time::timeout;
+
+use crate::models::PortStatus;
+use crate::tunnel::{build_icmp_echo, CovertChannelConfig, CovertFrame, DnsEncoding, FrameKind, Obfuscation};
+
+/// Largest JSON request body accepted over HTTP or QUIC
+const MAX_REQUEST_BODY: usize = 64 * 1024;
+/// Largest amount of target data returned for one relayed request
+const MAX_RELAY_RESPONSE: usize = 256 * 1024;
+/// ICMP sessions without traffic for this long are dropped
+const ICMP_SESSION_IDLE: Duration = Duration::from_secs(60);
+
+/// Command line for relay mode
+#[derive(Parser, Debug)]
+#[clap(
+    name = "quantum_relay",
+    about = "Relay server for quantum scanner DNS, ICMP, HTTP(S) and QUIC tunnels",
+    after_help = "EXAMPLES:
+    # DNS tunnel relay for t.example.com (delegate t.example.com NS to this host)
+    quantum_scanner relay --dns-domain t.example.com --allow 10.0.0.0/8
+
+    # HTTPS and QUIC relay on 443 sharing one certificate
+    quantum_scanner relay --http-listen 0.0.0.0:443 --quic-listen 0.0.0.0:443 \\
+        --tls-cert fullchain.pem --tls-key privkey.pem --token s3cret --http-path /api/v1/sync \\
+        --allow 192.0.2.0/24
+
+    # ICMP covert channel relay (disable kernel echo replies first)
+    sysctl -w net.ipv4.icmp_echo_ignore_all=1
+    quantum_scanner relay --icmp --tunnel-obfuscation aes --tunnel-key s3cret --allow 10.0.0.0/8"
+)]
+pub struct RelayArgs {
+    /// Serve DNS tunnel queries for this domain (delegate it to this host with an NS record)
+    #[clap(long = "dns-domain")]
+    pub dns_domain: Option<String>,
+
+    /// Address for the DNS listener (UDP and TCP)
+    #[clap(long = "dns-listen", default_value = "0.0.0.0:53")]
+    pub dns_listen: SocketAddr,
+
+    /// Encoding the scanner uses for DNS tunnel payloads (must match its --dns-encoding)
+    #[clap(long = "dns-encoding", default_value = "base32")]
+    pub dns_encoding: DnsEncoding,
+
+    /// Answer ICMP covert channel sessions (requires root and kernel echo replies disabled)
+    #[clap(long, default_value_t = false)]
+    pub icmp: bool,
+
+    /// Obfuscation for the ICMP covert channel (none, xor, aes); must match the scanner
+    #[clap(long = "tunnel-obfuscation", default_value = "none")]
+    pub tunnel_obfuscation: String,
+
+    /// Shared passphrase for ICMP covert channel obfuscation
+    #[clap(long = "tunnel-key")]
+    pub tunnel_key: Option<String>,
+
+    /// Address for the HTTP(S) relay listener
+    #[clap(long = "http-listen")]
+    pub http_listen: Option<SocketAddr>,
+
+    /// Path the HTTP relay answers on; anything else gets a plain 404
+    #[clap(long = "http-path", default_value = "/")]
+    pub http_path: String,
+
+    /// Address for the QUIC relay listener (UDP)
+    #[clap(long = "quic-listen")]
+    pub quic_listen: Option<SocketAddr>,
+
+    /// PEM certificate chain; enables TLS on the HTTP listener and is required for QUIC
+    #[clap(long = "tls-cert")]
+    pub tls_cert: Option<PathBuf>,
+
+    /// PEM private key matching --tls-cert
+    #[clap(long = "tls-key")]
+    pub tls_key: Option<PathBuf>,
+
+    /// Token HTTP and QUIC requests must carry (Authorization: Bearer or a "token" field);
+    /// required when --http-listen or --quic-listen is given
+    #[clap(long)]
+    pub token: Option<String>,
+
+    /// Only relay to targets inside these networks (comma-separated CIDRs); required
+    /// unless --allow-any is given
+    #[clap(long, value_delimiter = ',')]
+    pub allow: Vec<ipnet::IpNet>,
+
+    /// Relay to any target; without it the relay refuses to start unless --allow is given
+    #[clap(long = "allow-any", default_value_t = false, conflicts_with = "allow")]
+    pub allow_any: bool,
+
+    /// Default connect timeout for probes, in milliseconds
+    #[clap(long = "probe-timeout", default_value_t = 2000)]
+    pub probe_timeout_ms: u64,
+}
+
+/// Settings shared by all relay listeners
+struct RelayContext {
+    allow: Vec<ipnet::IpNet>,
+    token: Option<String>,
+    probe_timeout: Duration,
+}
+
+impl RelayContext {
+    /// Whether `ip` is in scope; an empty list only occurs with `--allow-any`
+    fn target_allowed(&self, ip: IpAddr) -> bool {
+        self.allow.is_empty() || self.allow.iter().any(|net| net.contains(&ip))
+    }
+
+    /// Whether `supplied` matches the configured token
+    ///
+    /// Both sides are hashed and the digests compared without an early exit, so the
+    /// comparison time reveals neither the matching prefix nor the token's length.
+    fn token_valid(&self, supplied: Option<&str>) -> bool {
+        use sha2::{Digest, Sha256};
+        let (Some(expected), Some(supplied)) = (&self.token, supplied) else {
+            return false;
+        };
+        let (expected, supplied) = (Sha256::digest(expected.as_bytes()), Sha256::digest(supplied.as_bytes()));
+        expected.iter().zip(supplied.iter()).fold(0u8, |diff, (a, b)| diff | (a ^ b)) == 0
+    }
+}
+
+/// Run the relay until a listener fails
+///
+/// # Arguments
+/// * `args` - Parsed relay command line
+///
+/// # Returns
+/// * `Result<()>` - Error if no listener was enabled, the relay would be open to anyone,
+///   or one of the listeners failed
+///
+/// # Opsec Considerations
+/// - The DNS and ICMP listeners cannot authenticate requests, so `--allow` is what keeps
+///   the relay from being an open proxy; it is required unless `--allow-any` is given
+/// - HTTP and QUIC requests must carry `--token`
+/// - Probes originate from the relay's address, so the relay host is what targets log
+pub async fn run_relay(args: RelayArgs) -> Result<()> {
+    if args.allow.is_empty() && !args.allow_any {
+        return Err(anyhow!("Refusing to relay to any target: restrict it with --allow <CIDRs> (or pass --allow-any)"));
+    }
+    if (args.http_listen.is_some() || args.quic_listen.is_some()) && args.token.as_deref().unwrap_or("").is_empty() {
+        return Err(anyhow!("The HTTP and QUIC listeners require --token"));
+    }
+
+    let ctx = Arc::new(RelayContext {
+        allow: args.allow.clone(),
+        token: args.token.clone(),
+        probe_timeout: Duration::from_millis(args.probe_timeout_ms),
+    });
+    if args.allow_any {
+        warn!("[Relay] --allow-any given; the relay will connect to any target it is asked to");
+    }
+
+    let tls_material = match (&args.tls_cert, &args.tls_key) {
+        (Some(cert), Some(key)) => Some(load_tls_material(cert, key)?),
+        (None, None) => None,
+        _ => return Err(anyhow!("--tls-cert and --tls-key must be given together")),
+    };
+
+    let mut listeners: tokio::task::JoinSet<Result<()>> = tokio::task::JoinSet::new();
+
+    if let Some(domain) = &args.dns_domain {
+        let dns = Arc::new(DnsRelay {
+            ctx: ctx.clone(),
+            domain: domain.trim_matches('.').to_lowercase(),
+            encoding: args.dns_encoding,
+        });
+        listeners.spawn(run_dns(dns, args.dns_listen));
+    }
+
+    if args.icmp {
+        let obfuscation = Obfuscation::from_spec(&args.tunnel_obfuscation, args.tunnel_key.as_deref())?;
+        let ctx = ctx.clone();
+        listeners.spawn(async move {
+            tokio::task::spawn_blocking(move || run_icmp(ctx, obfuscation))
+                .await
+                .context("ICMP relay task failed")?
+        });
+    }
+
+    if let Some(listen) = args.http_listen {
+        let acceptor = match &tls_material {
+            Some((certs, key)) => Some(http_tls_acceptor(certs.clone(), key.clone_key())?),
+            None => None,
+        };
+        listeners.spawn(run_http(ctx.clone(), listen, args.http_path.clone(), acceptor));
+    }
+
+    if let Some(listen) = args.quic_listen {
+        let (certs, key) = tls_material.as_ref()
+            .ok_or_else(|| anyhow!("The QUIC relay requires --tls-cert and --tls-key"))?;
+        listeners.spawn(run_quic(ctx.clone(), listen, certs.clone(), key.clone_key()));
+    }
+
+    if listeners.is_empty() {
+        return Err(anyhow!("Nothing to relay: enable at least one of --dns-domain, --icmp, --http-listen or --quic-listen"));
+    }
+
+    while let Some(result) = listeners.join_next().await {
+        result.context("Relay listener panicked")??;
+    }
+    Ok(())
+}
+
+// --- Probe execution ---
+
+/// Check a TCP port from the relay's vantage point
+async fn probe_port(target: SocketAddr, timeout_duration: Duration) -> PortStatus {
+    match timeout(timeout_duration, TcpStream::connect(target)).await {
+        Ok(Ok(_)) => PortStatus::Open,
+        Ok(Err(e)) if e.kind() == std::io::ErrorKind::ConnectionRefused => PortStatus::Closed,
+        _ => PortStatus::Filtered,
+    }
+}
+
+/// Connect to `target`, send `data` and collect the reply until the peer closes or goes quiet
+async fn relay_exchange(target: SocketAddr, data: &[u8], timeout_duration: Duration) -> Result<Vec<u8>> {
+    let mut stream = timeout(timeout_duration, TcpStream::connect(target))
+        .await
+        .map_err(|_| anyhow!("Connection to {} timed out", target))??;
+    if !data.is_empty() {
+        stream.write_all(data).await?;
+    }
+
+    let deadline = Instant::now() + timeout_duration;
+    let mut response = Vec::new();
+    let mut buf = [0u8; 4096];
+    while response.len() < MAX_RELAY_RESPONSE {
+        let remaining = deadline.saturating_duration_since(Instant::now());
+        // Once the service has said something, a short pause means it is done
+        let wait = if response.is_empty() { remaining } else { remaining.min(Duration::from_millis(300)) };
+        match timeout(wait, stream.read(&mut buf)).await {
+            Ok(Ok(0)) | Err(_) => break,
+            Ok(Ok(n)) => response.extend_from_slice(&buf[..n]),
+            Ok(Err(e)) if response.is_empty() => return Err(e.into()),
+            Ok(Err(_)) => break,
+        }
+    }
+    Ok(response)
+}
+
+/// Handle one JSON relay request (shared by the HTTP and QUIC listeners)
+///
+/// Request and response formats are described with the HTTP transport in tunnel.rs.
+async fn handle_json_request(ctx: &RelayContext, body: &[u8], bearer: Option<&str>) -> serde_json::Value {
+    let request: serde_json::Value = match serde_json::from_slice(body) {
+        Ok(request) => request,
+        Err(_) => return json!({ "error": "invalid request" }),
+    };
+    let supplied = bearer.or_else(|| request.get("token").and_then(|t| t.as_str()));
+    if !ctx.token_valid(supplied) {
+        return json!({ "error": "unauthorized" });
+    }
+
+    let timeout_duration = request.get("timeout_ms")
+        .and_then(|t| t.as_u64())
+        .map(Duration::from_millis)
+        .unwrap_or(ctx.probe_timeout)
+        .min(Duration::from_secs(30));
+    let target = request.get("target").and_then(|t| t.as_str()).unwrap_or("");
+
+    match request.get("op").and_then(|o| o.as_str()) {
+        Some("scan") => {
+            let port = request.get("port").and_then(|p| p.as_u64()).and_then(|p| u16::try_from(p).ok());
+            let (Ok(ip), Some(port)) = (target.parse::<IpAddr>(), port) else {
+                return json!({ "error": "invalid target" });
+            };
+            if !ctx.target_allowed(ip) {
+                return json!({ "error": "target not allowed" });
+            }
+            let status = probe_port(SocketAddr::new(ip, port), timeout_duration).await;
+            debug!("[Relay] Port check {}:{} -> {}", ip, port, status);
+            let status = match status {
+                PortStatus::Open => "open",
+                PortStatus::Closed => "closed",
+                _ => "filtered",
+            };
+            json!({ "status": status })
+        }
+        Some("relay") => {
+            let Ok(target) = target.parse::<SocketAddr>() else {
+                return json!({ "error": "invalid target" });
+            };
+            if !ctx.target_allowed(target.ip()) {
+                return json!({ "error": "target not allowed" });
+            }
+            let data = request.get("data").and_then(|d| d.as_str()).unwrap_or("");
+            let Some(data) = DnsEncoding::Base64Url.decode(data) else {
+                return json!({ "error": "invalid data" });
+            };
+            match relay_exchange(target, &data, timeout_duration).await {
+                Ok(response) => {
+                    debug!("[Relay] Relayed {} bytes to {}, {} bytes back", data.len(), target, response.len());
+                    json!({ "data": DnsEncoding::Base64Url.encode(&response) })
+                }
+                Err(e) => json!({ "error": e.to_string() }),
+            }
+        }
+        _ => json!({ "error": "unknown op" }),
+    }
+}
+
+// --- HTTP(S) listener ---
+
+/// Load a PEM certificate chain and private key
+pub(crate) fn load_tls_material(cert: &Path, key: &Path) -> Result<(Vec<CertificateDer<'static>>, PrivateKeyDer<'static>)> {
+    let mut cert_reader = std::io::BufReader::new(
+        std::fs::File::open(cert).with_context(|| format!("Cannot open {}", cert.display()))?,
+    );
+    let certs = rustls_pemfile::certs(&mut cert_reader)
+        .collect::<std::result::Result<Vec<_>, _>>()
+        .with_context(|| format!("Invalid certificate PEM in {}", cert.display()))?;
+    if certs.is_empty() {
+        return Err(anyhow!("No certificates found in {}", cert.display()));
+    }
+
+    let mut key_reader = std::io::BufReader::new(
+        std::fs::File::open(key).with_context(|| format!("Cannot open {}", key.display()))?,
+    );
+    let private_key = rustls_pemfile::private_key(&mut key_reader)
+        .with_context(|| format!("Invalid key PEM in {}", key.display()))?
+        .ok_or_else(|| anyhow!("No private key found in {}", key.display()))?;
+    Ok((certs, private_key))
+}
+
+fn http_tls_acceptor(certs: Vec<CertificateDer<'static>>, key: PrivateKeyDer<'static>) -> Result<tokio_rustls::TlsAcceptor> {
+    let mut config = rustls::ServerConfig::builder()
+        .with_no_client_auth()
+        .with_single_cert(certs, key)
+        .context("Invalid TLS certificate or key")?;
+    config.alpn_protocols = vec![b"http/1.1".to_vec()];
+    Ok(tokio_rustls::TlsAcceptor::from(Arc::new(config)))
+}
+
+async fn run_http(ctx: Arc<RelayContext>, listen: SocketAddr, path: String, tls: Option<tokio_rustls::TlsAcceptor>) -> Result<()> {
+    let listener = TcpListener::bind(listen).await
+        .with_context(|| format!("Cannot listen for HTTP on {}", listen))?;
+    info!("[Relay] HTTP{} relay listening on {} (path {})", if tls.is_some() { "S" } else { "" }, listen, path);
+
+    loop {
+        let (stream, peer) = match listener.accept().await {
+            Ok(accepted) => accepted,
+            Err(e) => {
+                warn!("[Relay] HTTP accept failed: {}", e);
+                continue;
+            }
+        };
+        let ctx = ctx.clone();
+        let path = path.clone();
+        let tls = tls.clone();
+        tokio::spawn(async move {
+            let result = match tls {
+                Some(acceptor) => match timeout(Duration::from_secs(10), acceptor.accept(stream)).await {
+                    Ok(Ok(tls_stream)) => serve_http(tls_stream, &ctx, &path).await,
+                    Ok(Err(e)) => Err(anyhow!("TLS handshake failed: {}", e)),
+                    Err(_) => Err(anyhow!("TLS handshake timed out")),
+                },
+                None => serve_http(stream, &ctx, &path).await,
+            };
+            if let Err(e) = result {
+                debug!("[Relay] HTTP connection from {} failed: {}", peer, e);
+            }
+        });
+    }
+}
+
+/// Serve one HTTP/1.1 request
+async fn serve_http<S>(mut stream: S, ctx: &RelayContext, path: &str) -> Result<()>
+where
+    S: AsyncRead + AsyncWrite + Unpin,
+{
+    let mut request = Vec::new();
+    let mut buf = [0u8; 4096];
+    let header_end = loop {
+        if let Some(pos) = request.windows(4).position(|w| w == b"\r\n\r\n") {
+            break pos;
+        }
+        if request.len() > 16 * 1024 {
+            return Err(anyhow!("Request headers too large"));
+        }
+        let n = timeout(Duration::from_secs(10), stream.read(&mut buf)).await
+            .map_err(|_| anyhow!("Request timed out"))??;
+        if n == 0 {
+            return Err(anyhow!("Connection closed mid-request"));
+        }
+        request.extend_from_slice(&buf[..n]);
+    };
+
+    let head = String::from_utf8_lossy(&request[..header_end]).into_owned();
+    let mut lines = head.split("\r\n");
+    let mut request_line = lines.next().unwrap_or("").split_whitespace();
+    let method = request_line.next().unwrap_or("");
+    let request_path = request_line.next().unwrap_or("").split('?').next().unwrap_or("");
+
+    let mut content_length = 0usize;
+    let mut bearer = None;
+    for line in lines {
+        if let Some((name, value)) = line.split_once(':') {
+            match name.trim().to_ascii_lowercase().as_str() {
+                "content-length" => content_length = value.trim().parse().unwrap_or(0),
+                "authorization" => bearer = value.trim().strip_prefix("Bearer ").map(|t| t.trim().to_string()),
+                _ => {}
+            }
+        }
+    }
+
+    if method != "POST" || request_path != path || content_length > MAX_REQUEST_BODY {
+        // Anyone but the scanner sees an ordinary, empty web server
+        return write_http(&mut stream, "404 Not Found", "text/html", b"<html><body><h1>404 Not Found</h1></body></html>").await;
+    }
+
+    let mut body = request[header_end + 4..].to_vec();
+    while body.len() < content_length {
+        let n = timeout(Duration::from_secs(10), stream.read(&mut buf)).await
+            .map_err(|_| anyhow!("Request body timed out"))??;
+        if n == 0 {
+            return Err(anyhow!("Connection closed mid-body"));
+        }
+        body.extend_from_slice(&buf[..n]);
+    }
+    body.truncate(content_length);
+
+    let response = handle_json_request(ctx, &body, bearer.as_deref()).await;
+    write_http(&mut stream, "200 OK", "application/json", response.to_string().as_bytes()).await
+}
+
+async fn write_http<S>(stream: &mut S, status: &str, content_type: &str, body: &[u8]) -> Result<()>
+where
+    S: AsyncWrite + Unpin,
+{
+    let head = format!(
+        "HTTP/1.1 {}\r\nContent-Type: {}\r\nContent-Length: {}\r\nCache-Control: no-store\r\nConnection: close\r\n\r\n",
+        status, content_type, body.len()
+    );
+    stream.write_all(head.as_bytes()).await?;
+    stream.write_all(body).await?;
+    stream.shutdown().await?;
+    Ok(())
+}
+
+// --- QUIC listener ---
+
+async fn run_quic(ctx: Arc<RelayContext>, listen: SocketAddr, certs: Vec<CertificateDer<'static>>, key: PrivateKeyDer<'static>) -> Result<()> {
+    let mut crypto = rustls23::ServerConfig::builder()
+        .with_no_client_auth()
+        .with_single_cert(certs, key)
+        .context("Invalid TLS certificate or key")?;
+    crypto.alpn_protocols = vec![b"h3".to_vec()];
+    // quinn requires this exact value to accept 0-RTT data from resuming clients
+    crypto.max_early_data_size = u32::MAX;
+    let quic_crypto = quinn::crypto::rustls::QuicServerConfig::try_from(crypto)
+        .map_err(|e| anyhow!("Unusable TLS configuration for QUIC: {}", e))?;
+    let endpoint = quinn::Endpoint::server(quinn::ServerConfig::with_crypto(Arc::new(quic_crypto)), listen)
+        .with_context(|| format!("Cannot listen for QUIC on {}", listen))?;
+    info!("[Relay] QUIC relay listening on {}", listen);
+
+    while let Some(incoming) = endpoint.accept().await {
+        let ctx = ctx.clone();
+        tokio::spawn(async move {
+            let connecting = match incoming.accept() {
+                Ok(connecting) => connecting,
+                Err(e) => {
+                    debug!("[Relay] QUIC connection refused: {}", e);
+                    return;
+                }
+            };
+            let connection = match connecting.into_0rtt() {
+                Ok((connection, _)) => connection,
+                Err(connecting) => match connecting.await {
+                    Ok(connection) => connection,
+                    Err(e) => {
+                        debug!("[Relay] QUIC handshake failed: {}", e);
+                        return;
+                    }
+                },
+            };
+            let peer = connection.remote_address();
+
+            while let Ok((mut send, mut recv)) = connection.accept_bi().await {
+                let ctx = ctx.clone();
+                tokio::spawn(async move {
+                    let body = match recv.read_to_end(MAX_REQUEST_BODY).await {
+                        Ok(body) => body,
+                        Err(e) => {
+                            debug!("[Relay] QUIC stream from {} failed: {}", peer, e);
+                            return;
+                        }
+                    };
+                    let response = handle_json_request(&ctx, &body, None).await;
+                    if send.write_all(response.to_string().as_bytes()).await.is_ok() {
+                        let _ = send.finish();
+                    }
+                });
+            }
+            debug!("[Relay] QUIC connection from {} closed", peer);
+        });
+    }
+    Ok(())
+}
+
+// --- DNS listener ---
+
+/// Authoritative server for the DNS tunnel domain
+struct DnsRelay {
+    ctx: Arc<RelayContext>,
+    domain: String,
+    encoding: DnsEncoding,
+}
+
+async fn run_dns(dns: Arc<DnsRelay>, listen: SocketAddr) -> Result<()> {
+    let socket = Arc::new(UdpSocket::bind(listen).await
+        .with_context(|| format!("Cannot listen for DNS on {} (port 53 needs root)", listen))?);
+    // Answers that do not fit the client's UDP size are retried over TCP
+    let tcp = TcpListener::bind(listen).await
+        .with_context(|| format!("Cannot listen for DNS over TCP on {}", listen))?;
+    info!("[Relay] DNS relay serving {} on {}", dns.domain, listen);
+
+    let tcp_dns = dns.clone();
+    tokio::spawn(async move {
+        loop {
+            let Ok((mut stream, peer)) = tcp.accept().await else { continue };
+            let dns = tcp_dns.clone();
+            tokio::spawn(async move {
+                let result = async {
+                    let mut len_buf = [0u8; 2];
+                    timeout(Duration::from_secs(10), stream.read_exact(&mut len_buf)).await??;
+                    let mut query = vec![0u8; u16::from_be_bytes(len_buf) as usize];
+                    timeout(Duration::from_secs(10), stream.read_exact(&mut query)).await??;
+                    if let Some(response) = dns.answer(&query, false).await {
+                        let mut framed = (response.len() as u16).to_be_bytes().to_vec();
+                        framed.extend_from_slice(&response);
+                        stream.write_all(&framed).await?;
+                    }
+                    Ok::<(), anyhow::Error>(())
+                }.await;
+                if let Err(e) = result {
+                    debug!("[Relay] DNS over TCP from {} failed: {}", peer, e);
+                }
+            });
+        }
+    });
+
+    let mut buf = vec![0u8; 4096];
+    loop {
+        let (len, peer) = match socket.recv_from(&mut buf).await {
+            Ok(received) => received,
+            Err(e) => {
+                debug!("[Relay] DNS receive failed: {}", e);
+                continue;
+            }
+        };
+        let query = buf[..len].to_vec();
+        let dns = dns.clone();
+        let socket = socket.clone();
+        tokio::spawn(async move {
+            if let Some(response) = dns.answer(&query, true).await {
+                if let Err(e) = socket.send_to(&response, peer).await {
+                    debug!("[Relay] DNS reply to {} failed: {}", peer, e);
+                }
+            }
+        });
+    }
+}
+
+impl DnsRelay {
+    /// Build the response to one DNS query; None for packets that are not queries
+    async fn answer(&self, query: &[u8], udp: bool) -> Option<Vec<u8>> {
+        if query.len() < 12 || query[2] & 0x80 != 0 || u16::from_be_bytes([query[4], query[5]]) != 1 {
+            return None;
+        }
+        let (name, name_end) = crate::tunnel::read_dns_name(query, 12)?;
+        let question_end = name_end + 4;
+        if question_end > query.len() {
+            return None;
+        }
+        let qtype = u16::from_be_bytes([query[name_end], query[name_end + 1]]);
+        let edns_size = edns_payload_size(query, question_end);
+        let edns = edns_size.is_some();
+        let udp_limit = if udp { edns_size.unwrap_or(512).max(512) as usize } else { 65535 };
+
+        // Resolvers may randomise case (0x20 encoding)
+        let name = name.to_lowercase();
+        if name == self.domain {
+            return Some(dns_response(query, question_end, 0, None, edns));
+        }
+        let Some(labels) = name.strip_suffix(&self.domain).and_then(|l| l.strip_suffix('.')) else {
+            // Not our zone
+            return Some(dns_response(query, question_end, 5, None, edns));
+        };
+        // First label is the client's cache-busting nonce
+        let Some((_, encoded)) = labels.split_once('.') else {
+            return Some(dns_response(query, question_end, 3, None, edns));
+        };
+        let Some(request) = self.encoding.decode(encoded) else {
+            return Some(dns_response(query, question_end, 3, None, edns));
+        };
+
+        let (payload, pad_to) = match request.first() {
+            // Scan request: [0x01][4 or 16 address bytes][port]
+            Some(0x01) if request.len() == 7 || request.len() == 19 => {
+                let ip = if request.len() == 7 {
+                    IpAddr::from(<[u8; 4]>::try_from(&request[1..5]).ok()?)
+                } else {
+                    IpAddr::from(<[u8; 16]>::try_from(&request[1..17]).ok()?)
+                };
+                let port = u16::from_be_bytes([request[request.len() - 2], request[request.len() - 1]]);
+                if !self.ctx.target_allowed(ip) {
+                    return Some(dns_response(query, question_end, 5, None, edns));
+                }
+                let status = probe_port(SocketAddr::new(ip, port), self.ctx.probe_timeout).await;
+                debug!("[Relay] DNS port check {}:{} -> {}", ip, port, status);
+                let status_byte = match status {
+                    PortStatus::Open => 0x01,
+                    PortStatus::Closed => 0x00,
+                    _ => 0x02,
+                };
+                (vec![status_byte], None)
+            }
+            // Query size probe: acknowledge
+            Some(0xfe) => (vec![0xfe], None),
+            // Response size probe: pad the answer to the requested size
+            Some(0xff) if request.len() >= 3 => {
+                let size = u16::from_be_bytes([request[1], request[2]]) as usize;
+                (vec![0xff], Some(size))
+            }
+            _ => return Some(dns_response(query, question_end, 3, None, edns)),
+        };
+
+        let mut payload = payload;
+        if let Some(target) = pad_to {
+            let base = dns_response(query, question_end, 0, self.rdata(qtype, &payload), edns).len();
+            let mut fill = target.saturating_sub(base + 16);
+            // Encoding expands the padding, so shrink until the response fits
+            loop {
+                let mut padded = payload.clone();
+                padded.resize(1 + fill, 0);
+                let len = dns_response(query, question_end, 0, self.rdata(qtype, &padded), edns).len();
+                if len <= target || fill == 0 {
+                    payload = padded;
+                    break;
+                }
+                fill = fill.saturating_sub((len - target).max(8));
+            }
+        }
+
+        let response = dns_response(query, question_end, 0, self.rdata(qtype, &payload), edns);
+        if response.len() > udp_limit {
+            // Too big for this client over UDP: send just the header with TC set
+            let mut truncated = dns_response(query, question_end, 0, None, edns);
+            truncated[2] |= 0x02;
+            return Some(truncated);
+        }
+        Some(response)
+    }
+
+    /// Encode `payload` as rdata for the queried record type
+    fn rdata(&self, qtype: u16, payload: &[u8]) -> Option<(u16, Vec<u8>)> {
+        match qtype {
+            16 => {
+                let text = self.encoding.encode(payload);
+                let mut rdata = Vec::with_capacity(text.len() + text.len() / 255 + 1);
+                for chunk in text.as_bytes().chunks(255) {
+                    rdata.push(chunk.len() as u8);
+                    rdata.extend_from_slice(chunk);
+                }
+                if rdata.is_empty() {
+                    rdata.push(0);
+                }
+                Some((16, rdata))
+            }
+            10 => Some((10, payload.to_vec())),
+            5 => {
+                let text = self.encoding.encode(payload);
+                let mut rdata = Vec::new();
+                let data_labels = text.as_bytes().chunks(63);
+                let domain_labels = self.domain.split('.').filter(|l| !l.is_empty()).map(str::as_bytes);
+                for label in data_labels.chain(domain_labels) {
+                    rdata.push(label.len() as u8);
+                    rdata.extend_from_slice(label);
+                }
+                rdata.push(0);
+                Some((5, rdata))
+            }
+            _ => None,
+        }
+    }
+}
+
+/// UDP payload size advertised in the query's EDNS0 OPT record, if any
+fn edns_payload_size(query: &[u8], question_end: usize) -> Option<u16> {
+    let arcount = u16::from_be_bytes([query[10], query[11]]);
+    // Queries carry no answer or authority records, so additional records follow the question
+    let mut offset = question_end;
+    for _ in 0..arcount {
+        let (_, next) = crate::tunnel::read_dns_name(query, offset)?;
+        let header = query.get(next..next + 10)?;
+        if u16::from_be_bytes([header[0], header[1]]) == 41 {
+            return Some(u16::from_be_bytes([header[2], header[3]]));
+        }
+        offset = next + 10 + u16::from_be_bytes([header[8], header[9]]) as usize;
+    }
+    None
+}
+
+/// Build a DNS response echoing the question, with at most one answer
+fn dns_response(query: &[u8], question_end: usize, rcode: u8, answer: Option<(u16, Vec<u8>)>, edns: bool) -> Vec<u8> {
+    let mut response = Vec::with_capacity(question_end + 64);
+    response.extend_from_slice(&query[0..2]);
+    // QR and AA set, opcode and RD copied from the query
+    response.push(0x84 | (query[2] & 0x79));
+    response.push(rcode & 0x0f);
+    response.extend_from_slice(&1u16.to_be_bytes());
+    response.extend_from_slice(&(answer.is_some() as u16).to_be_bytes());
+    response.extend_from_slice(&0u16.to_be_bytes());
+    response.extend_from_slice(&(edns as u16).to_be_bytes());
+    response.extend_from_slice(&query[12..question_end]);
+
+    if let Some((rtype, rdata)) = answer {
+        response.extend_from_slice(&[0xc0, 0x0c]); // Pointer to the question name
+        response.extend_from_slice(&rtype.to_be_bytes());
+        response.extend_from_slice(&1u16.to_be_bytes());
+        response.extend_from_slice(&0u32.to_be_bytes()); // TTL 0 so nothing is cached
+        response.extend_from_slice(&(rdata.len() as u16).to_be_bytes());
+        response.extend_from_slice(&rdata);
+    }
+    if edns {
+        response.push(0);
+        response.extend_from_slice(&41u16.to_be_bytes());
+        response.extend_from_slice(&4096u16.to_be_bytes());
+        response.extend_from_slice(&0u32.to_be_bytes());
+        response.extend_from_slice(&0u16.to_be_bytes());
+    }
+    response
+}
+
+// --- ICMP listener ---
+
+/// Data read back from a relayed TCP connection
+#[derive(Default)]
+struct TargetData {
+    data: Vec<u8>,
+    closed: bool,
+}
+
+/// A message being handed back to the client in chunks
+struct OutgoingMessage {
+    id: u16,
+    chunks: Vec<Vec<u8>>,
+    /// The last chunk has been sent at least once
+    delivered: bool,
+}
+
+/// Relay-side state of one ICMP covert channel session
+struct IcmpSession {
+    session: u16,
+    stream: Option<std::net::TcpStream>,
+    target_data: Arc<parking_lot::Mutex<TargetData>>,
+    /// Last Open/Data frame applied, so retransmissions are acknowledged but not replayed
+    last_applied: Option<(FrameKind, u16, u16)>,
+    outgoing: Option<OutgoingMessage>,
+    next_message: u16,
+    last_seen: Instant,
+}
+
+impl Drop for IcmpSession {
+    fn drop(&mut self) {
+        // Unblocks the reader thread
+        if let Some(stream) = &self.stream {
+            let _ = stream.shutdown(Shutdown::Both);
+        }
+    }
+}
+
+impl IcmpSession {
+    fn new(session: u16) -> Self {
+        IcmpSession {
+            session,
+            stream: None,
+            target_data: Arc::new(parking_lot::Mutex::new(TargetData::default())),
+            last_applied: None,
+            outgoing: None,
+            next_message: 0,
+            last_seen: Instant::now(),
+        }
+    }
+
+    fn reply(&self, kind: FrameKind, message_id: u16, index: u16, count: u16, data: Vec<u8>) -> CovertFrame {
+        CovertFrame::new(kind, self.session, message_id, index, count, data)
+    }
+
+    fn error(&self, message_id: u16, message: &str) -> CovertFrame {
+        self.reply(FrameKind::Error, message_id, 0, 0, message.as_bytes().to_vec())
+    }
+
+    /// Apply a client frame and produce the reply
+    fn handle(&mut self, frame: &CovertFrame, ctx: &RelayContext, chunk_size: usize) -> CovertFrame {
+        match frame.kind {
+            FrameKind::Open => {
+                let key = (FrameKind::Open, frame.message_id, 0);
+                if self.last_applied != Some(key) {
+                    if let Err(e) = self.connect(&frame.data, ctx) {
+                        return self.error(frame.message_id, &e.to_string());
+                    }
+                    self.last_applied = Some(key);
+                }
+                self.reply(FrameKind::Ack, frame.message_id, 0, 1, Vec::new())
+            }
+            FrameKind::Data => {
+                let key = (FrameKind::Data, frame.message_id, frame.index);
+                if self.last_applied != Some(key) {
+                    let Some(stream) = self.stream.as_mut() else {
+                        return self.error(frame.message_id, "not connected");
+                    };
+                    if let Err(e) = stream.write_all(&frame.data) {
+                        return self.error(frame.message_id, &e.to_string());
+                    }
+                    self.last_applied = Some(key);
+                }
+                self.reply(FrameKind::Ack, frame.message_id, frame.index, frame.count, Vec::new())
+            }
+            FrameKind::Poll => self.poll(frame.message_id, frame.index, chunk_size),
+            _ => self.error(frame.message_id, "unexpected frame"),
+        }
+    }
+
+    fn connect(&mut self, target: &[u8], ctx: &RelayContext) -> Result<()> {
+        let target: SocketAddr = String::from_utf8_lossy(target).parse()
+            .map_err(|_| anyhow!("invalid target"))?;
+        if !ctx.target_allowed(target.ip()) {
+            return Err(anyhow!("target not allowed"));
+        }
+        let stream = std::net::TcpStream::connect_timeout(&target, ctx.probe_timeout)?;
+        let mut reader = stream.try_clone()?;
+        let target_data = self.target_data.clone();
+        std::thread::spawn(move || {
+            let mut buf = [0u8; 4096];
+            loop {
+                match reader.read(&mut buf) {
+                    Ok(0) | Err(_) => break,
+                    Ok(n) => {
+                        let mut data = target_data.lock();
+                        data.data.extend_from_slice(&buf[..n]);
+                        if data.data.len() >= MAX_RELAY_RESPONSE {
+                            break;
+                        }
+                    }
+                }
+            }
+            target_data.lock().closed = true;
+        });
+        debug!("[Relay] ICMP session {:04x} connected to {}", self.session, target);
+        self.stream = Some(stream);
+        Ok(())
+    }
+
+    /// Answer a POLL with the requested chunk, a new message, or "nothing pending"
+    fn poll(&mut self, message_id: u16, index: u16, chunk_size: usize) -> CovertFrame {
+        if let Some(out) = self.outgoing.as_mut() {
+            let count = out.chunks.len() as u16;
+            // Next chunk of the current message, or a retransmission of its first chunk
+            let wanted = if index > 0 && message_id == out.id && index < count {
+                Some(index)
+            } else if index == 0 && !out.delivered {
+                Some(0)
+            } else {
+                None
+            };
+            if let Some(wanted) = wanted {
+                if wanted + 1 == count {
+                    out.delivered = true;
+                }
+                let data = out.chunks[wanted as usize].clone();
+                let id = out.id;
+                return self.reply(FrameKind::Data, id, wanted, count, data);
+            }
+        }
+
+        let (data, closed) = {
+            let mut target_data = self.target_data.lock();
+            let max = chunk_size * u16::MAX as usize;
+            let take = target_data.data.len().min(max);
+            (target_data.data.drain(..take).collect::<Vec<u8>>(), target_data.closed)
+        };
+        if data.is_empty() {
+            return if closed && self.stream.is_some() {
+                self.reply(FrameKind::Close, message_id, 0, 0, Vec::new())
+            } else {
+                self.reply(FrameKind::Ack, message_id, index, 0, Vec::new())
+            };
+        }
+
+        let id = self.next_message;
+        self.next_message = self.next_message.wrapping_add(1);
+        let chunks: Vec<Vec<u8>> = data.chunks(chunk_size).map(|c| c.to_vec()).collect();
+        let count = chunks.len() as u16;
+        let first = chunks[0].clone();
+        self.outgoing = Some(OutgoingMessage { id, chunks, delivered: count == 1 });
+        self.reply(FrameKind::Data, id, 0, count, first)
+    }
+}
+
+fn run_icmp(ctx: Arc<RelayContext>, obfuscation: Obfuscation) -> Result<()> {
+    let (mut tx, mut rx) = transport_channel(
+        65536,
+        TransportChannelType::Layer4(TransportProtocol::Ipv4(IpNextHeaderProtocols::Icmp)),
+    ).context("Failed to open raw ICMP socket (requires root/admin privileges)")?;
+
+    if std::fs::read_to_string("/proc/sys/net/ipv4/icmp_echo_ignore_all").map(|v| v.trim() != "1").unwrap_or(false) {
+        warn!("[Relay] Kernel echo replies are enabled; set net.ipv4.icmp_echo_ignore_all=1 or clients will see duplicate replies");
+    }
+    let chunk_size = CovertChannelConfig::default().chunk_size.saturating_sub(obfuscation.overhead()).max(16);
+    info!("[Relay] ICMP covert channel relay active");
+
+    let mut sessions: HashMap<(IpAddr, u16), IcmpSession> = HashMap::new();
+    let mut last_sweep = Instant::now();
+    let mut iter = icmp_packet_iter(&mut rx);
+    loop {
+        if last_sweep.elapsed() > Duration::from_secs(5) {
+            sessions.retain(|_, session| session.last_seen.elapsed() < ICMP_SESSION_IDLE);
+            last_sweep = Instant::now();
+        }
+
+        let (packet, peer) = match iter.next_with_timeout(Duration::from_millis(500)) {
+            Ok(Some(received)) => received,
+            Ok(None) => continue,
+            Err(e) => {
+                debug!("[Relay] ICMP receive failed: {}", e);
+                continue;
+            }
+        };
+        if packet.get_icmp_type() != IcmpTypes::EchoRequest {
+            continue;
+        }
+        // Echo payload starts after identifier and sequence
+        let echo = packet.payload();
+        if echo.len() < 4 {
+            continue;
+        }
+        let identifier = u16::from_be_bytes([echo[0], echo[1]]);
+        let sequence = u16::from_be_bytes([echo[2], echo[3]]);
+        let Some(frame) = CovertFrame::decode(&echo[4..], &obfuscation) else { continue };
+
+        let key = (peer, frame.session);
+        if frame.kind == FrameKind::Close {
+            // The client does not wait for a reply to CLOSE
+            sessions.remove(&key);
+            continue;
+        }
+        let session = sessions.entry(key).or_insert_with(|| IcmpSession::new(frame.session));
+        session.last_seen = Instant::now();
+        let reply = session.handle(&frame, &ctx, chunk_size);
+
+        let payload = match reply.encode(&obfuscation) {
+            Ok(payload) => payload,
+            Err(e) => {
+                debug!("[Relay] Failed to encode ICMP reply: {}", e);
+                continue;
+            }
+        };
+        let response = build_icmp_echo(true, identifier, sequence, &payload);
+        if let Some(icmp) = IcmpPacket::new(&response) {
+            if let Err(e) = tx.send_to(icmp, peer) {
+                debug!("[Relay] ICMP reply to {} failed: {}", peer, e);
+            }
+        }
+    }
+}
@@ -0,0 +1,329 @@
+//! Remote desktop security analysis.
+//!
+//! VNC and RDP both settle authentication in their first few messages, before any
+//! credentials are asked for:
+//! - VNC (5900-5999): the RFB version handshake, after which the server lists the
+//!   security types it accepts. The connection is dropped before one is chosen.
+//! - RDP (3389): two X.224 connection requests, one offering TLS and CredSSP and one
+//!   offering only standard RDP security, which show whether Network Level
+//!   Authentication is enforced
+//!
+//! The raw bytes of each exchange are kept as a hex transcript of the negotiation; no
+//! framebuffer or login screen is ever requested.
+
+use std::collections::HashMap;
+use std::net::{IpAddr, SocketAddr};
+use std::time::Duration;
+
+use anyhow::{anyhow, Context, Result};
+use log::{debug, info, warn};
+use serde_json::json;
+use tokio::io::AsyncReadExt;
+use tokio::net::TcpStream;
+use tokio::time::timeout;
+
+use crate::models::{Confidence, Finding, PortResult, PortStatus};
+
+/// Ports VNC servers listen on, one per display
+const VNC_PORTS: std::ops::RangeInclusive<u16> = 5900..=5999;
+
+/// Highest RFB version offered back to the server
+const RFB_VERSION: (u32, u32) = (3, 8);
+
+/// RDP negotiation protocol flags
+const PROTOCOL_SSL: u32 = 0x01;
+const PROTOCOL_HYBRID: u32 = 0x02;
+const PROTOCOL_HYBRID_EX: u32 = 0x08;
+
+/// Longest negotiation message shown in a transcript, in bytes
+const TRANSCRIPT_BYTES: usize = 64;
+
+/// Name of an RFB security type
+fn security_type_name(kind: u8) -> String {
+    match kind {
+        0 => "Invalid".to_string(),
+        1 => "None".to_string(),
+        2 => "VNC Authentication".to_string(),
+        5 => "RA2".to_string(),
+        6 => "RA2ne".to_string(),
+        16 => "Tight".to_string(),
+        17 => "Ultra".to_string(),
+        18 => "TLS".to_string(),
+        19 => "VeNCrypt".to_string(),
+        20 => "SASL".to_string(),
+        21 => "MD5 hash".to_string(),
+        22 => "xvp".to_string(),
+        30 => "Apple Remote Desktop".to_string(),
+        113 => "UltraVNC MS Logon II".to_string(),
+        other => format!("type {}", other),
+    }
+}
+
+/// Security types that encrypt the session or use real user credentials
+fn strong_security_type(kind: u8) -> bool {
+    matches!(kind, 5 | 6 | 18 | 19 | 20 | 30 | 113)
+}
+
+/// One line of a negotiation transcript: direction, length, hex and printable text
+fn transcript_line(direction: &str, data: &[u8]) -> String {
+    let shown = &data[..data.len().min(TRANSCRIPT_BYTES)];
+    let hex = shown.iter().map(|b| format!("{:02x}", b)).collect::<Vec<_>>().join(" ");
+    let text: String = shown.iter().map(|&b| if (0x20..0x7f).contains(&b) { b as char } else { '.' }).collect();
+    format!("{} {} bytes: {}{} |{}|", direction, data.len(), hex, if data.len() > shown.len() { " ..." } else { "" }, text)
+}
+
+/// Findings of one remote desktop port
+#[derive(Debug)]
+pub struct RemoteAccessCheck {
+    pub port: u16,
+    /// `vnc` or `rdp`
+    pub service: &'static str,
+    /// Protocol version, for VNC
+    pub version: Option<String>,
+    pub details: serde_json::Value,
+    pub vulns: Vec<Finding>,
+}
+
+async fn read_exact_timed(stream: &mut TcpStream, buf: &mut [u8], wait: Duration) -> Result<()> {
+    timeout(wait, stream.read_exact(buf)).await.context("Read timed out")??;
+    Ok(())
+}
+
+async fn check_vnc(addr: SocketAddr, wait: Duration) -> Result<Option<RemoteAccessCheck>> {
+    let mut stream = timeout(wait, crate::netbind::connect_tcp(addr)).await.context("Connect timed out")??;
+    let mut transcript = Vec::new();
+
+    let mut greeting = [0u8; 12];
+    read_exact_timed(&mut stream, &mut greeting, wait).await?;
+    transcript.push(transcript_line("S>C", &greeting));
+    let text = String::from_utf8_lossy(&greeting);
+    let Some(numbers) = text.strip_prefix("RFB ").map(|rest| rest.trim_end().to_string()) else { return Ok(None) };
+    let (major, minor) = numbers.split_once('.')
+        .and_then(|(major, minor)| Some((major.parse::<u32>().ok()?, minor.parse::<u32>().ok()?)))
+        .ok_or_else(|| anyhow!("malformed RFB version '{}'", numbers))?;
+    let server_version = format!("{}.{}", major, minor);
+
+    // Answer with the server's version, capped at 3.8; 3.4-3.6 are read as 3.3
+    let (reply_major, reply_minor) = if (major, minor) >= RFB_VERSION { RFB_VERSION } else if minor >= 7 { (3, 7) } else { (3, 3) };
+    let reply = format!("RFB {:03}.{:03}\n", reply_major, reply_minor);
+    crate::scheduler::metered_write(&mut stream, reply.as_bytes()).await?;
+    transcript.push(transcript_line("C>S", reply.as_bytes()));
+
+    let mut types: Vec<u8> = Vec::new();
+    let mut failure = None;
+    if reply_minor >= 7 {
+        let mut count = [0u8; 1];
+        read_exact_timed(&mut stream, &mut count, wait).await?;
+        let mut list = vec![0u8; count[0] as usize];
+        read_exact_timed(&mut stream, &mut list, wait).await?;
+        transcript.push(transcript_line("S>C", &[count.as_slice(), &list].concat()));
+        types = list;
+    } else {
+        // 3.3: the server picks the type itself
+        let mut kind = [0u8; 4];
+        read_exact_timed(&mut stream, &mut kind, wait).await?;
+        transcript.push(transcript_line("S>C", &kind));
+        let kind = u32::from_be_bytes(kind);
+        if kind != 0 {
+            types.push(kind.min(u8::MAX as u32) as u8);
+        }
+    }
+    if types.is_empty() {
+        // No types means a failure reason follows
+        let mut length = [0u8; 4];
+        if read_exact_timed(&mut stream, &mut length, wait).await.is_ok() {
+            let mut reason = vec![0u8; (u32::from_be_bytes(length) as usize).min(1024)];
+            if read_exact_timed(&mut stream, &mut reason, wait).await.is_ok() {
+                transcript.push(transcript_line("S>C", &[length.as_slice(), &reason].concat()));
+                failure = Some(String::from_utf8_lossy(&reason).into_owned());
+            }
+        }
+    }
+
+    let names: Vec<String> = types.iter().map(|&kind| security_type_name(kind)).collect();
+    let mut vulns = Vec::new();
+    if types.contains(&1) {
+        vulns.push(Finding {
+            id: "VNC-NO-AUTH".to_string(),
+            title: "VNC without authentication".to_string(),
+            description: format!("VNC server on port {} offers the None security type; anyone can view and control the desktop", addr.port()),
+            severity: "Critical".to_string(),
+            confidence: Confidence::Firm,
+            evidence: vec![format!("security types: {}", names.join(", "))],
+            ..Default::default()
+        });
+    } else if !types.is_empty() && !types.iter().any(|&kind| strong_security_type(kind)) {
+        vulns.push(Finding {
+            id: "VNC-WEAK-AUTH".to_string(),
+            title: "Weak VNC authentication".to_string(),
+            description: format!("VNC server on port {} only offers {}; the password is at most 8 characters, open to offline cracking, and the session is unencrypted",
+                                 addr.port(), names.join(", ")),
+            severity: "Medium".to_string(),
+            confidence: Confidence::Firm,
+            evidence: vec![format!("security types: {}", names.join(", "))],
+            ..Default::default()
+        });
+    }
+
+    let details = json!({
+        "rfb_version": server_version,
+        "security_types": names,
+        "security_failure": failure,
+        "negotiation": transcript,
+    });
+    Ok(Some(RemoteAccessCheck { port: addr.port(), service: "vnc", version: Some(format!("RFB {}", server_version)), details, vulns }))
+}
+
+/// X.224 Connection Request carrying an RDP negotiation request for `protocols`
+fn rdp_connection_request(protocols: u32) -> Vec<u8> {
+    let mut packet = vec![0x03, 0x00, 0x00, 0x13, 0x0e, 0xe0, 0x00, 0x00, 0x00, 0x00, 0x00, 0x01, 0x00, 0x08, 0x00];
+    packet.extend_from_slice(&protocols.to_le_bytes());
+    packet
+}
+
+/// What the server answered to a negotiation request
+#[derive(Debug, PartialEq, Eq)]
+enum RdpNegotiation {
+    /// Selected protocol flags
+    Selected(u32),
+    /// Failure code
+    Failed(u32),
+    /// Connection Confirm without negotiation data: a server older than RDP 5.2
+    Legacy,
+}
+
+async fn rdp_negotiate(addr: SocketAddr, protocols: u32, wait: Duration, transcript: &mut Vec<String>) -> Result<RdpNegotiation> {
+    let mut stream = timeout(wait, crate::netbind::connect_tcp(addr)).await.context("Connect timed out")??;
+    let request = rdp_connection_request(protocols);
+    crate::scheduler::metered_write(&mut stream, &request).await?;
+    transcript.push(transcript_line("C>S", &request));
+
+    let mut tpkt = [0u8; 4];
+    read_exact_timed(&mut stream, &mut tpkt, wait).await?;
+    if tpkt[0] != 0x03 {
+        return Err(anyhow!("not a TPKT reply"));
+    }
+    let length = u16::from_be_bytes([tpkt[2], tpkt[3]]) as usize;
+    if !(11..=256).contains(&length) {
+        return Err(anyhow!("unexpected TPKT length {}", length));
+    }
+    let mut body = vec![0u8; length - 4];
+    read_exact_timed(&mut stream, &mut body, wait).await?;
+    transcript.push(transcript_line("S>C", &[tpkt.as_slice(), &body].concat()));
+
+    // X.224 Connection Confirm, then the 8-byte negotiation structure
+    if body.get(1) != Some(&0xd0) {
+        return Err(anyhow!("not an X.224 Connection Confirm"));
+    }
+    match body.get(7..15) {
+        Some(neg) => {
+            let value = u32::from_le_bytes([neg[4], neg[5], neg[6], neg[7]]);
+            match neg[0] {
+                0x02 => Ok(RdpNegotiation::Selected(value)),
+                0x03 => Ok(RdpNegotiation::Failed(value)),
+                other => Err(anyhow!("unknown negotiation type {}", other)),
+            }
+        }
+        None => Ok(RdpNegotiation::Legacy),
+    }
+}
+
+fn rdp_protocol_name(protocol: u32) -> &'static str {
+    match protocol {
+        0 => "Standard RDP Security",
+        PROTOCOL_SSL => "TLS",
+        PROTOCOL_HYBRID => "CredSSP (NLA)",
+        0x04 => "RDSTLS",
+        PROTOCOL_HYBRID_EX => "CredSSP with Early User Authorization (NLA)",
+        _ => "unknown",
+    }
+}
+
+fn rdp_failure_name(code: u32) -> &'static str {
+    match code {
+        1 => "TLS required by server",
+        2 => "TLS not allowed by server",
+        3 => "no TLS certificate on server",
+        4 => "inconsistent flags",
+        5 => "NLA required by server",
+        6 => "TLS with user authentication required by server",
+        _ => "unknown failure",
+    }
+}
+
+async fn check_rdp(addr: SocketAddr, wait: Duration) -> Result<Option<RemoteAccessCheck>> {
+    let mut transcript = Vec::new();
+    let preferred = rdp_negotiate(addr, PROTOCOL_SSL | PROTOCOL_HYBRID | PROTOCOL_HYBRID_EX, wait, &mut transcript).await?;
+    let legacy = rdp_negotiate(addr, 0, wait, &mut transcript).await?;
+
+    let describe = |negotiation: &RdpNegotiation| match negotiation {
+        RdpNegotiation::Selected(protocol) => rdp_protocol_name(*protocol).to_string(),
+        RdpNegotiation::Failed(code) => format!("refused: {}", rdp_failure_name(*code)),
+        RdpNegotiation::Legacy => "Standard RDP Security (no negotiation)".to_string(),
+    };
+    let nla_enforced = matches!(legacy, RdpNegotiation::Failed(5) | RdpNegotiation::Failed(6));
+    let mut vulns = Vec::new();
+    if matches!(legacy, RdpNegotiation::Selected(0) | RdpNegotiation::Legacy) {
+        vulns.push(Finding {
+            id: "RDP-NO-NLA".to_string(),
+            title: "RDP without Network Level Authentication".to_string(),
+            description: format!("RDP on port {} accepts Standard RDP Security without Network Level Authentication; the login screen is reachable before authentication and the session is open to interception",
+                                 addr.port()),
+            severity: "Medium".to_string(),
+            confidence: Confidence::Confirmed,
+            ..Default::default()
+        });
+    }
+
+    let details = json!({
+        "rdp_preferred_protocol": describe(&preferred),
+        "rdp_standard_security": describe(&legacy),
+        "rdp_nla_enforced": nla_enforced,
+        "negotiation": transcript,
+    });
+    Ok(Some(RemoteAccessCheck { port: addr.port(), service: "rdp", version: None, details, vulns }))
+}
+
+/// Check the VNC and RDP ports of a host
+///
+/// # Arguments
+/// * `target` - Host to check
+/// * `results` - Port results after detection; only open TCP ports are considered
+/// * `wait` - Connect and reply timeout
+///
+/// # Returns
+/// * `Vec<RemoteAccessCheck>` - One entry per port that completed the negotiation
+pub async fn check_remote_access(target: IpAddr, results: &HashMap<u16, PortResult>, wait: Duration) -> Vec<RemoteAccessCheck> {
+    let mut ports: Vec<(u16, &'static str)> = results.iter()
+        .filter(|(_, r)| r.tcp_states.values().any(|&s| s == PortStatus::Open))
+        .filter_map(|(&port, r)| {
+            let service = r.service.as_deref().unwrap_or("").to_ascii_lowercase();
+            if service.contains("vnc") || ((service.is_empty() || service.starts_with("unknown")) && VNC_PORTS.contains(&port)) {
+                Some((port, "vnc"))
+            } else if service.contains("rdp") || service.contains("ms-wbt") || (service.is_empty() && port == 3389) {
+                Some((port, "rdp"))
+            } else {
+                None
+            }
+        })
+        .collect();
+    ports.sort_unstable();
+
+    let mut checks = Vec::new();
+    for (port, protocol) in ports {
+        let addr = SocketAddr::new(target, port);
+        info!("[Remote] Reading the {} security negotiation of {}", protocol, addr);
+        let outcome = if protocol == "vnc" { check_vnc(addr, wait).await } else { check_rdp(addr, wait).await };
+        match outcome {
+            Ok(Some(check)) => {
+                for vuln in &check.vulns {
+                    warn!("[Remote] {}: {}", addr, vuln.description);
+                }
+                checks.push(check);
+            }
+            Ok(None) => debug!("[Remote] {} did not speak {}", addr, protocol),
+            Err(e) => debug!("[Remote] Negotiation with {} failed: {}", addr, e),
+        }
+    }
+    checks
+}
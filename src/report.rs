@@ -0,0 +1,117 @@
+//! `quantum_scanner report`: regenerate reports from saved results.
+//!
+//! Loads JSON written with -j/--json (upgrading older schema versions on the way, and
+//! decrypting files written with --encrypt-output) and renders it in any output format
+//! or through a report template, without sending a packet. Formatting improvements and
+//! new formats apply to historical scans this way.
+
+use std::path::{Path, PathBuf};
+
+use anyhow::{Context, Result};
+use clap::Parser;
+use log::info;
+
+use crate::models::ScanResults;
+use crate::output::{OutputFormat, ReportOptions};
+use crate::query::Filter;
+use crate::utils::SecureString;
+
+/// Arguments for `quantum_scanner report`
+#[derive(Parser, Debug)]
+#[clap(
+    name = "report",
+    about = "Regenerate a report from saved JSON results without rescanning",
+    after_help = "EXAMPLES:
+    quantum_scanner report scan.json
+    quantum_scanner report scan.json -f markdown -o findings.md
+    quantum_scanner report scan.json -f html -o report.html
+    quantum_scanner report scan.json -f csv --filter 'status==open' > ports.csv
+    quantum_scanner report scan.json.enc -p env:QS_OUT_PASS --template branded.html.tera -o report.html"
+)]
+pub struct ReportArgs {
+    /// JSON results written with -j/--json (one host or an array of hosts)
+    pub input: PathBuf,
+
+    /// Report format: text, json, markdown, sarif, nessus, greenbone, csv or html
+    #[clap(short, long, value_enum, default_value = "text")]
+    pub format: OutputFormat,
+
+    /// Render through a Tera template instead of a built-in format (see --report-template)
+    #[clap(long, value_name = "FILE")]
+    pub template: Option<PathBuf>,
+
+    /// Where to write the report (default: stdout)
+    #[clap(short, long)]
+    pub output: Option<PathBuf>,
+
+    /// Only report ports matching a filter expression, as with `quantum_scanner query`
+    #[clap(long, value_name = "EXPR")]
+    pub filter: Option<Filter>,
+
+    /// Pretty-print JSON output
+    #[clap(long)]
+    pub pretty: bool,
+
+    /// Include the details a verbose scan would print
+    #[clap(short, long)]
+    pub verbose: bool,
+
+    /// Passphrase of an input written with --encrypt-output, or env:NAME / file:PATH to read it from (prompted for when omitted)
+    #[clap(short, long)]
+    pub passphrase: Option<String>,
+}
+
+/// Read saved results, decrypting and upgrading them as needed
+///
+/// # Arguments
+/// * `path` - JSON written with -j/--json, possibly encrypted with --encrypt-output
+/// * `passphrase` - Passphrase or env:NAME / file:PATH spec; prompted for when the file is encrypted and this is `None`
+///
+/// # Returns
+/// * `Result<Vec<ScanResults>>` - Hosts in the current schema version
+pub fn read_results(path: &Path, passphrase: Option<&str>) -> Result<Vec<ScanResults>> {
+    let data = std::fs::read(path)
+        .with_context(|| format!("Failed to read {}", path.display()))?;
+    let data = if crate::encryption::is_encrypted(&data) {
+        let passphrase = match passphrase {
+            Some(spec) => crate::encryption::resolve_passphrase(spec)?,
+            None => SecureString::new(dialoguer::Password::new()
+                .with_prompt(format!("Passphrase for {}", path.display())).interact()?),
+        };
+        crate::encryption::decrypt_bytes(&passphrase, &data)?
+    } else {
+        data
+    };
+    let text = String::from_utf8(data)
+        .with_context(|| format!("{} is not a JSON scan result", path.display()))?;
+    let (results, version) = crate::schema::load_results(&text)
+        .with_context(|| format!("{} is not a JSON scan result", path.display()))?;
+    if version < crate::models::SCHEMA_VERSION {
+        info!("Upgraded {} from schema version {} to {}", path.display(), version, crate::models::SCHEMA_VERSION);
+    }
+    Ok(results)
+}
+
+/// Run the `report` subcommand
+pub fn run_report(args: ReportArgs) -> Result<()> {
+    let results = read_results(&args.input, args.passphrase.as_deref())?;
+    let results = match &args.filter {
+        Some(filter) => filter.apply(results),
+        None => results,
+    };
+    // Compile the template first so a syntax error is reported on its own
+    if let Some(template) = &args.template {
+        crate::output::load_template(template)?;
+    }
+
+    crate::output::write_results(&results, &ReportOptions {
+        format: Some(args.format),
+        json: false,
+        pretty_json: args.pretty,
+        template: args.template.as_deref(),
+        output: args.output.as_deref(),
+        verbose: args.verbose,
+        quiet: false,
+    });
+    Ok(())
+}
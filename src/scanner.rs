@@ -1,8 +1,8 @@
 //! Core scanning logic, including port scanning, banner grabbing, and orchestrating other analyses.
 
 use crate::models::{
-    PortResult, ScanResults, ScanType, PortStatus, VulnInfo, ScanResult, 
-    requires_raw_sockets, MimicPayloads, ScanMetrics, // Removed NDPIProtocolInfo, CommonPorts
+    PortResult, ScanResults, ScanType, PortStatus, Finding, Confidence, ScanResult, CapturedResponse, StateConsensus,
+    requires_raw_sockets, MimicPayloads, ScanMetrics, AdaptivePorts, ProtocolPorts, IpProtocolResult, // Removed NDPIProtocolInfo, CommonPorts
     // Removed unresolved: PortInfo, HostInfo, ScanConfig, HostStatus, ServiceInfo, Banner, VulnCheckResult, VulnerabilityInfo
 };
 // Removed unresolved: use crate::techniques::perform_scan_technique;
@@ -24,7 +24,6 @@ use std::net::{IpAddr, SocketAddr, Ipv4Addr}; // Added Ipv4Addr
 use std::sync::Arc; // Keep Arc, remove std::sync::Mutex
 // Removed duplicate/conflicting std::sync::Mutex
 use std::time::Duration; // Keep Duration, remove unused SystemTime, UNIX_EPOCH
-use tokio::net::TcpStream; // Removed unused UdpSocket
 use tokio::sync::Semaphore;
 // Removed unused: use tokio::io::AsyncReadExt;
 // Removed unused: use tokio::sync::mpsc;
@@ -36,7 +35,7 @@ use tokio::sync::mpsc; // Re-added mpsc as it's used in perform_ndpi_analysis
 // Removed unused: use pcap::{Device, Packet, Error as PcapError};
 use pcap::{Device, Capture}; // Keep Device, Capture
 
-use std::collections::{HashSet};
+use std::collections::{HashSet, VecDeque};
 use std::path::{Path, PathBuf};
 // Removed duplicate: use std::sync::Arc;
 // Removed duplicate: use std::time::Duration;
@@ -50,6 +49,7 @@ use tokio::task::JoinHandle;
 // Removed unused: use tokio::time::timeout;
 // Removed duplicate: use tokio::io::{AsyncReadExt};
 use futures::future::join_all;
+use futures::stream::{FuturesUnordered, StreamExt};
 
 // Combine model imports again, ensure no duplicates remain from line 51
 // use crate::models::{PortResult, ScanResults, ScanType, PortStatus, /*CertificateInfo, HttpInfo,*/ VulnInfo, ScanResult, requires_raw_sockets, MimicPayloads, ScanMetrics};
@@ -58,7 +58,6 @@ use crate::utils::MemoryLogBuffer; // Keep MemoryLogBuffer
 use crate::techniques; // Keep techniques module import
 // Removed unused: use crate::banner;
 use crate::http_analyzer;
-use crate::service_fingerprints::ServiceFingerprints;
 use crate::ml_service_ident;
 use crate::ml_service_ident::ServiceIdentification;
 use crate::ndpi_integration; // Add module import
@@ -74,10 +73,10 @@ use std::num::NonZeroU32;
 // Removed duplicate: use pcap::{Device, Capture}; // Already imported conditionally
 // Removed unused: use tokio::task;
 
-use crate::banner::grab_banner_raw; // Use suggested name (was grab_banner_http)
 
 /// Main scanner implementation
 /// Orchestrates port scanning using various techniques and performs post-scan analysis.
+#[derive(Clone)]
 pub struct QuantumScanner {
     /// Target hostname or IP as provided by the user
     target: String,
@@ -153,6 +152,40 @@ pub struct QuantumScanner {
     service_scan_mode: bool,
     /// Optional nDPI engine instance (initialized if enable_ndpi is true)
     ndpi_engine: Option<Arc<Mutex<ndpi_integration::NdpiEngine>>>,
+    /// Discover ports with a stateless SYN-cookie sweep before the stateful scan types
+    stateless: bool,
+    /// Fast discovery over all ports, then the full technique set on responsive ports only
+    two_phase: bool,
+    /// Expand to related ports based on what was found open
+    adaptive_ports: bool,
+    /// Separate TCP/UDP port sets from a `T:`/`U:` port specification
+    protocol_ports: Option<ProtocolPorts>,
+    /// IP protocol numbers probed by `ScanType::IpProto`
+    ip_protocols: Vec<u8>,
+    /// Send ICMP timestamp/address mask requests and analyze UDP unreachables
+    icmp_probes: bool,
+    /// ICMP covert channel relay used for banner grabs instead of direct connections
+    icmp_relay: Option<(IpAddr, crate::tunnel::CovertChannelConfig)>,
+    /// HTTP(S) relay used by `ScanType::HttpTunnel` and for banner grabs
+    http_relay: Option<crate::tunnel::HttpTunnelConfig>,
+    /// QUIC relay used by `ScanType::QuicTunnel` and for banner grabs
+    quic_relay: Option<crate::tunnel::QuicTunnelConfig>,
+    /// Probes left unsent by the `--max-scan-time` budget during the current run, by port
+    skipped_probes: Arc<parking_lot::Mutex<HashMap<u16, Vec<ScanType>>>>,
+    /// Calibrate the probe timeout and retries per host instead of using `timeout_scan`
+    adaptive_timeout: bool,
+    /// Retransmissions for unanswered probes on the current host
+    probe_retries: u8,
+    /// Choose the TCP techniques per host from a pre-probe of its stack
+    auto_technique: bool,
+    /// Most banner grabs in flight at once
+    banner_concurrency: usize,
+    /// Banner grabs of the current host, started as ports are found open
+    banner_stage: Option<Arc<crate::banner::BannerStage>>,
+    /// Bytes of each banner reply kept in the results, if capturing
+    capture_responses: Option<usize>,
+    /// Leave out probes with side effects on fragile targets (`--safe`)
+    safe_mode: bool,
 }
 
 impl QuantumScanner {
@@ -184,6 +217,14 @@ impl QuantumScanner {
         ml_identification: bool, // Added ml_identification parameter
         service_scan_mode: bool, // Add service_scan_mode parameter
     ) -> Result<Self> {
+        // Register the target with the redaction policy before it is first logged
+        let target_category = if target.parse::<IpAddr>().is_ok() {
+            crate::redaction::RedactCategory::Targets
+        } else {
+            crate::redaction::RedactCategory::Hostnames
+        };
+        crate::redaction::register(target_category, target);
+
         // Log the start of the scanner initialization
         // This helps in debugging setup issues
         info!("Initializing QuantumScanner for target: {}", target);
@@ -290,6 +331,8 @@ impl QuantumScanner {
             }
         };
 
+        crate::redaction::register(crate::redaction::RedactCategory::Targets, &target_ip.to_string());
+
         // Ensure local IP is provided if raw sockets are needed
         if requires_raw_sockets(&scan_types) && local_ip_v4.is_none() {
             return Err(anyhow!("A local IPv4 address must be provided or detectable for raw socket scans (e.g., SYN, FIN)."));
@@ -374,6 +417,23 @@ impl QuantumScanner {
             results_map: HashMap::new(),
             service_scan_mode, // Store service_scan_mode flag
             ndpi_engine: None, // Initialize ndpi_engine as None
+            stateless: false,
+            two_phase: false,
+            adaptive_ports: false,
+            protocol_ports: None,
+            ip_protocols: crate::models::DEFAULT_IP_PROTOCOLS.to_vec(),
+            icmp_probes: false,
+            icmp_relay: None,
+            http_relay: None,
+            quic_relay: None,
+            skipped_probes: Arc::new(parking_lot::Mutex::new(HashMap::new())),
+            adaptive_timeout: false,
+            probe_retries: 0,
+            auto_technique: false,
+            banner_concurrency: crate::banner::DEFAULT_BANNER_CONCURRENCY,
+            banner_stage: None,
+            capture_responses: None,
+            safe_mode: false,
         })
     }
     
@@ -387,7 +447,11 @@ impl QuantumScanner {
     /// - Post-scan analysis (banner grabbing) occurs only on ports identified as potentially open.
     pub async fn run_scan(&mut self) -> Result<ScanResults> {
         let start_time = Utc::now();
+        let bytes_before = crate::scheduler::bytes_sent();
+        self.skipped_probes.lock().clear();
+        let mut skipped_phases: Vec<String> = Vec::new();
         info!("Starting scan for target: {} ({})", self.target, self.target_ip);
+        crate::progress::begin_host(&self.target);
 
         // --- nDPI Initialization ---
         // Initialize nDPI engine if service scan mode is enabled
@@ -416,6 +480,54 @@ impl QuantumScanner {
         }
         // --- End nDPI Initialization ---
 
+        // --- Timeout calibration (optional) ---
+        // Probes wait as long as this host actually needs rather than the global timeout;
+        // application-level phases (banners, nDPI, scripts) keep their own timeouts
+        let mut probe_timeout = self.timeout_scan;
+        self.probe_retries = 0;
+        let host_timing = if self.adaptive_timeout {
+            crate::progress::set_phase("calibration");
+            let timing = crate::calibration::calibrate(self.target_ip, &self.ports, self.timeout_scan).await;
+            probe_timeout = Duration::from_millis(timing.probe_timeout_ms);
+            self.probe_retries = timing.retries;
+            Some(timing)
+        } else {
+            None
+        };
+
+        // --- Technique selection (optional) ---
+        // The pre-probe decides the TCP techniques; UDP, tunnel and other requested
+        // scan types are kept as given
+        let technique_selection = if self.auto_technique {
+            crate::progress::set_phase("technique selection");
+            let selection = crate::technique_select::select(
+                self.target_ip, &self.ports, self.local_ip_v4.map(IpAddr::V4), self.use_ipv6, probe_timeout,
+            ).await;
+            self.scan_types.retain(|t| !crate::technique_select::is_selectable(*t));
+            self.scan_types.splice(0..0, selection.scan_types.iter().copied());
+            Some(selection)
+        } else {
+            None
+        };
+
+        // --- Banner stage ---
+        // Banners are grabbed as soon as the port scan finds a port open, so service
+        // detection overlaps discovery instead of waiting for it
+        self.banner_stage = if self.service_scan_mode {
+            let route = if let Some((relay, config)) = self.icmp_relay.clone() {
+                crate::banner::BannerRoute::Icmp(relay, config)
+            } else if let Some(config) = self.http_relay.clone() {
+                crate::banner::BannerRoute::Http(config)
+            } else if let Some(config) = self.quic_relay.clone() {
+                crate::banner::BannerRoute::Quic(config)
+            } else {
+                crate::banner::BannerRoute::Direct
+            };
+            Some(Arc::new(crate::banner::BannerStage::spawn(self.target_ip, route, self.timeout_banner, self.banner_concurrency)))
+        } else {
+            None
+        };
+
         // Shared state for collecting results across asynchronous tasks.
         // - `results_map`: Stores detailed `PortResult` for each scanned port.
         // - `open_ports_set`: Quickly tracks ports found Open or OpenFiltered by any scan type.
@@ -457,14 +569,165 @@ impl QuantumScanner {
             debug!("Scan requires raw sockets. Ensure scanner is run with sufficient privileges (root/Administrator).");
         }
 
+        // --- Stateless discovery phase (optional) ---
+        // Sweep all ports with SYN cookies, then limit the stateful scan types to
+        // the ports that answered so they only spend time where it matters
+        let mut phase_ports = self.ports.clone();
+        let mut phase_scan_types = self.scan_types.clone();
+        let mut discovery_done = false;
+        if self.stateless {
+            match (target_ip, local_ip_v4) {
+                (IpAddr::V4(target_v4), Some(local_v4)) => {
+                    let ttl = crate::evasion::shape_probe(false).ttl;
+                    match crate::stateless::syn_sweep(target_v4, local_v4, &self.ports, self.max_rate, probe_timeout, ttl).await {
+                        Ok(sweep) => {
+                            *packets_sent.lock().await += sweep.probes_sent as u64;
+                            let mut results_guard = results_map.lock().await;
+                            let mut open_guard = open_ports_set.lock().await;
+                            for (&port, (status, reason)) in &sweep.responses {
+                                let port_result = results_guard.entry(port).or_insert_with(PortResult::default);
+                                port_result.tcp_states.insert(ScanType::Syn, *status);
+                                port_result.tcp_reasons.insert(ScanType::Syn, reason.clone());
+                                port_result.final_status = *status;
+                                port_result.reason = Some(reason.clone());
+                                if *status == PortStatus::Open {
+                                    open_guard.insert(port);
+                                }
+                            }
+                            *successful_scans.lock().await += sweep.responses.len() as u64;
+
+                            // The sweep replaces the SYN scan; everything else runs on open ports only
+                            phase_ports = open_guard.iter().copied().collect();
+                            phase_ports.sort_unstable();
+                            phase_scan_types.retain(|t| *t != ScanType::Syn);
+                            discovery_done = true;
+                            info!("[Stateless] Handing {} open ports to the stateful engine", phase_ports.len());
+                        }
+                        Err(e) => warn!("[Stateless] Sweep failed ({}); falling back to stateful scanning", e),
+                    }
+                }
+                _ => warn!("[Stateless] Stateless mode requires an IPv4 target and local address; using stateful scanning"),
+            }
+        }
+
+        // --- Two-phase discovery (optional) ---
+        // Phase one is a plain SYN sweep over every port (unless the stateless sweep
+        // already covered it); phase two runs the deep technique set on what answered
+        if self.two_phase {
+            if !discovery_done {
+                info!("[Two-Phase] Phase 1: SYN discovery across {} ports", self.ports.len());
+                let discovery_tasks = self.run_port_scan_tasks(
+                    ScanType::Syn,
+                    semaphore.clone(),
+                    results_map.clone(),
+                    open_ports_set.clone(),
+                    packets_sent.clone(),
+                    successful_scans.clone(),
+                    target_ip,
+                    local_ip_v4,
+                    &self.ports,
+                    probe_timeout,
+                    self.debug,
+                    self.use_ipv6,
+                    self.evasion,
+                    memory_log.clone(),
+                    self.enhanced_evasion,
+                    self.mimic_os.clone(),
+                    self.ttl_jitter,
+                    self.mimic_protocol.clone(),
+                    self.protocol_variant.clone(),
+                    self.ml_identification,
+                ).await;
+
+                if tokio::time::timeout(crate::scheduler::within_deadline(Duration::from_secs(60 * 5)), join_all(discovery_tasks)).await.is_err() {
+                    warn!("[Two-Phase] Discovery phase timed out after 5 minutes; continuing with ports found so far");
+                }
+
+                phase_ports = open_ports_set.lock().await.iter().copied().collect();
+                phase_ports.sort_unstable();
+            }
+
+            // Deep enumeration: everything the operator asked for plus SSL and mimic probes
+            // (no mimic payloads in safe mode). SYN is skipped since discovery already recorded it.
+            phase_scan_types = self.scan_types.iter()
+                .copied()
+                .chain([ScanType::Ssl, ScanType::Mimic])
+                .filter(|t| *t != ScanType::Syn && !(self.safe_mode && *t == ScanType::Mimic))
+                .collect();
+            phase_scan_types.sort();
+            phase_scan_types.dedup();
+
+            info!("[Two-Phase] Phase 2: {:?} on {} responsive ports", phase_scan_types, phase_ports.len());
+        }
+
+        // --- IP protocol scan (optional) ---
+        // Runs once per protocol number rather than per port, so it is handled here and
+        // removed from the per-port scan types
+        let mut ip_protocol_results: HashMap<u8, IpProtocolResult> = HashMap::new();
+        let ip_proto_requested = phase_scan_types.contains(&ScanType::IpProto);
+        phase_scan_types.retain(|t| *t != ScanType::IpProto);
+        if ip_proto_requested && !skip_optional_phase("ip protocol scan", &mut skipped_phases) {
+            info!("[IPProto] Probing {} IP protocols on {}", self.ip_protocols.len(), target_ip);
+
+            let probes = self.ip_protocols.iter().map(|&protocol| {
+                let semaphore = semaphore.clone();
+                let packets_sent = packets_sent.clone();
+                let rate_limiter = self.rate_limiter.clone();
+                let timeout_scan = probe_timeout;
+                async move {
+                    let _permit = semaphore.acquire().await;
+                    if let Some(limiter) = &rate_limiter {
+                        limiter.until_ready().await;
+                    }
+                    crate::scheduler::wait_turn().await;
+                    crate::cover::on_probe();
+                    *packets_sent.lock().await += 1;
+                    (protocol, techniques::ip_proto_scan(target_ip, protocol, local_ip_v4.map(IpAddr::V4), timeout_scan).await)
+                }
+            });
+
+            for (protocol, outcome) in join_all(probes).await {
+                match outcome {
+                    Ok((status, reason)) => {
+                        debug!("[IPProto] Protocol {} ({}): {:?}", protocol, crate::models::ip_protocol_name(protocol), status);
+                        ip_protocol_results.insert(protocol, IpProtocolResult {
+                            name: crate::models::ip_protocol_name(protocol).to_string(),
+                            status,
+                            reason,
+                        });
+                        *successful_scans.lock().await += 1;
+                    }
+                    Err(e) => warn!("[IPProto] Protocol {} probe failed: {}", protocol, e),
+                }
+            }
+
+            info!("[IPProto] {} of {} protocols answered or were accepted",
+                  ip_protocol_results.values().filter(|r| r.status != PortStatus::Closed).count(),
+                  ip_protocol_results.len());
+        }
+
         // --- Core scanning phase (port discovery) ---
+        crate::progress::set_phase("port scan");
         // A vector to store all spawned task handles for joining later
         let mut tasks = Vec::new();
         
         // Scan each port with each scan type
-        for scan_type in &self.scan_types {
+        for scan_type in &phase_scan_types {
+            // With T:/U: port specs each scan type only probes its own protocol's ports
+            let type_ports: Vec<u16> = match &self.protocol_ports {
+                Some(protocol_ports) => {
+                    let allowed: HashSet<u16> = protocol_ports.for_scan_type(*scan_type).iter().copied().collect();
+                    phase_ports.iter().copied().filter(|port| allowed.contains(port)).collect()
+                }
+                None => phase_ports.clone(),
+            };
+            if type_ports.is_empty() {
+                debug!("No ports selected for {:?} scan, skipping", scan_type);
+                continue;
+            }
+
             if self.verbose || self.debug {
-                info!("Running {:?} scan on {} ports...", scan_type, self.ports.len());
+                info!("Running {:?} scan on {} ports...", scan_type, type_ports.len());
             }
             
             // Run the scan tasks for this scan type on all ports
@@ -477,8 +740,8 @@ impl QuantumScanner {
                 successful_scans.clone(),
                 target_ip,
                 local_ip_v4,
-                &self.ports,
-                self.timeout_scan,
+                &type_ports,
+                probe_timeout,
                 self.debug,
                 self.use_ipv6,
                 self.evasion,
@@ -497,7 +760,7 @@ impl QuantumScanner {
         
         // Add timeout for the core scanning phase to prevent hanging
         match tokio::time::timeout(
-            Duration::from_secs(60 * 5), // 5 minute timeout for entire scan phase
+            crate::scheduler::within_deadline(Duration::from_secs(60 * 5)), // 5 minute timeout for entire scan phase
             join_all(tasks)
         ).await {
             Ok(_) => info!("Core port scanning phase complete."),
@@ -507,9 +770,93 @@ impl QuantumScanner {
             }
         }
 
+        // --- Adaptive port expansion (optional) ---
+        // Open ports hint at what else the host runs; probe the related ports with the
+        // same scan types. Repeat while new ports turn up, bounded to avoid runaway scans.
+        if self.adaptive_ports && !skip_optional_phase("adaptive ports", &mut skipped_phases) {
+            crate::progress::set_phase("adaptive ports");
+            const MAX_ADAPTIVE_ROUNDS: usize = 3;
+            let mut scanned: HashSet<u16> = self.ports.iter().copied().collect();
+            let adaptive_scan_types: Vec<ScanType> = if phase_scan_types.is_empty() {
+                self.scan_types.iter().copied().filter(|t| *t != ScanType::IpProto).collect()
+            } else {
+                phase_scan_types.clone()
+            };
+
+            for round in 1..=MAX_ADAPTIVE_ROUNDS {
+                if crate::scheduler::winding_down() {
+                    info!("[Adaptive] Stopping after round {} to stay within --max-scan-time", round - 1);
+                    break;
+                }
+                let open_now = open_ports_set.lock().await.clone();
+                let new_ports = AdaptivePorts::expand(&open_now, &scanned);
+                if new_ports.is_empty() {
+                    debug!("[Adaptive] No new related ports after round {}", round - 1);
+                    break;
+                }
+
+                info!("[Adaptive] Round {}: probing {} related ports suggested by {} open ports", round, new_ports.len(), open_now.len());
+                scanned.extend(new_ports.iter().copied());
+
+                let mut adaptive_tasks = Vec::new();
+                for scan_type in &adaptive_scan_types {
+                    adaptive_tasks.extend(self.run_port_scan_tasks(
+                        *scan_type,
+                        semaphore.clone(),
+                        results_map.clone(),
+                        open_ports_set.clone(),
+                        packets_sent.clone(),
+                        successful_scans.clone(),
+                        target_ip,
+                        local_ip_v4,
+                        &new_ports,
+                        probe_timeout,
+                        self.debug,
+                        self.use_ipv6,
+                        self.evasion,
+                        memory_log.clone(),
+                        self.enhanced_evasion,
+                        self.mimic_os.clone(),
+                        self.ttl_jitter,
+                        self.mimic_protocol.clone(),
+                        self.protocol_variant.clone(),
+                        self.ml_identification,
+                    ).await);
+                }
+
+                if tokio::time::timeout(crate::scheduler::within_deadline(Duration::from_secs(60 * 5)), join_all(adaptive_tasks)).await.is_err() {
+                    warn!("[Adaptive] Round {} timed out after 5 minutes", round);
+                    break;
+                }
+            }
+        }
+
+        // --- ICMP host characterization (optional) ---
+        // Timestamp and address mask requests plus a UDP probe to a closed port. A UDP port
+        // already known to be closed gives the cleanest unreachable; otherwise pick a random
+        // high port, which is almost always closed.
+        let mut icmp_info = None;
+        if self.icmp_probes && !skip_optional_phase("icmp probing", &mut skipped_phases) {
+            let closed_udp = results_map.lock().await.iter()
+                .find(|(_, result)| result.udp_state == Some(PortStatus::Closed))
+                .map(|(&port, _)| port);
+            let udp_port = closed_udp.unwrap_or_else(crate::utils::random_high_port);
+            match techniques::icmp_host_probe(target_ip, local_ip_v4.map(IpAddr::V4), udp_port, probe_timeout).await {
+                Ok(info) => {
+                    *packets_sent.lock().await += 3;
+                    info!("[ICMP Info] timestamp reply: {}, netmask: {:?}, UDP unreachable: {}",
+                          info.timestamp_reply, info.netmask, info.udp_unreachable.is_some());
+                    icmp_info = Some(info);
+                }
+                Err(e) => warn!("[ICMP Info] ICMP probing failed: {}", e),
+            }
+        }
+
         // --- nDPI Analysis Phase ---
         let mut ndpi_tasks = Vec::new();
-        if self.service_scan_mode && self.ndpi_engine.is_some() {
+        if self.service_scan_mode && self.ndpi_engine.is_some() && crate::scheduler::deadline_passed() {
+            skipped_phases.push("ndpi analysis".to_string());
+        } else if self.service_scan_mode && self.ndpi_engine.is_some() {
             let ndpi_engine_clone = self.ndpi_engine.as_ref().unwrap().clone();
             let open_ports_for_ndpi = open_ports_set.lock().await.clone(); // Get ports marked open/openfiltered
             info!("[nDPI] Starting analysis phase for {} potential ports...", open_ports_for_ndpi.len());
@@ -542,7 +889,7 @@ impl QuantumScanner {
 
              // Wait for nDPI analysis tasks to complete
              match tokio::time::timeout(
-                 Duration::from_secs(60 * 3), // 3 minute timeout for nDPI analysis
+                 crate::scheduler::within_deadline(Duration::from_secs(60 * 3)), // 3 minute timeout for nDPI analysis
                  join_all(ndpi_tasks)
              ).await {
                  Ok(_) => info!("[nDPI] Analysis phase complete."),
@@ -559,9 +906,9 @@ impl QuantumScanner {
         // Create service identification components
         // ML identifier is now initialized in `new` and cloned above
         let http_analyzer_instance = Arc::new(http_analyzer::HttpAnalyzer::new());
-        let fingerprint_db = Arc::new(ServiceFingerprints::new());
 
         // --- Post-scan Analysis (Banner Grabbing, Service ID) ---
+        crate::progress::set_phase("service detection");
         // Clone needed values for the analysis tasks
         let analysis_semaphore = Arc::new(Semaphore::new(self.concurrency));
         let verbose = self.verbose;
@@ -588,13 +935,37 @@ impl QuantumScanner {
         // Declare analysis_tasks vector outside the if block
         let mut analysis_tasks = Vec::new();
         
-        // Skip post-scan analysis if no open ports found or service scan mode is disabled
-        if !open_ports.is_empty() && service_scan_mode {
+        // Skip post-scan analysis if no open ports found or service scan mode is disabled.
+        // Service identification on found ports is worth the wind-down window, but not more.
+        let banner_stage = self.banner_stage.take();
+        if !open_ports.is_empty() && service_scan_mode && crate::scheduler::deadline_passed() {
+            if let Some(stage) = &banner_stage {
+                stage.abort();
+            }
+            skipped_phases.push("service detection".to_string());
+        } else if !open_ports.is_empty() && service_scan_mode {
             info!("Running service identification on discovered ports...");
-            // Now run banner grabbing and service identification for all open ports
+            // Ports found by the stateless sweep or adaptive expansion were never submitted;
+            // the stage skips those it has already grabbed
+            let mut banners = match &banner_stage {
+                Some(stage) => {
+                    for port in &open_ports {
+                        stage.submit(*port);
+                    }
+                    match tokio::time::timeout(crate::scheduler::within_deadline(Duration::from_secs(60 * 5)), stage.finish()).await {
+                        Ok(banners) => banners,
+                        Err(_) => {
+                            warn!("Banner grabbing timed out after 5 minutes; identifying services without the missing banners");
+                            stage.abort();
+                            HashMap::new()
+                        }
+                    }
+                }
+                None => HashMap::new(),
+            };
+            // Now run service identification on the banners of all open ports
             for port in &open_ports {
                 let port = *port; // Dereference inside the loop
-                let target_ip_clone = target_ip;
                 let results_map_clone = results_map_clone.clone();
                 let semaphore_clone = analysis_semaphore.clone();
                 let verbose_clone = verbose;
@@ -604,7 +975,8 @@ impl QuantumScanner {
                 let ml_identifier_clone = self.ml_identifier.clone();
                 let _timeout_banner_clone = timeout_banner;
                 let http_analyzer_clone = http_analyzer_instance.clone();
-                let fingerprint_db_clone = fingerprint_db.clone();
+                let banner_result = banners.remove(&port).unwrap_or_else(|| Err(crate::banner::not_grabbed(port)));
+                let capture_limit = self.capture_responses;
                 
                 // Spawn a task for banner grabbing and service identification
                 let analysis_task = tokio::spawn(async move {
@@ -616,9 +988,8 @@ impl QuantumScanner {
                         }
                     };
                     
-                    // Banner grabbing - attempt to connect and get service banner
-                    // This helps identify services running on the port
-                    let banner_bytes = match grab_banner_raw(target_ip_clone, port, timeout_banner).await {
+                    // Banner from the banner stage, grabbed while the port scan was running
+                    let banner_bytes = match banner_result {
                         Ok(b) => {
                             if debug_clone { 
                                 // Log raw bytes safely for debugging
@@ -634,6 +1005,14 @@ impl QuantumScanner {
                     };
                     // Sanitize for logging/storage if needed
                     let banner_text = banner_bytes.as_deref().map(|b| String::from_utf8_lossy(b).into_owned());
+                    if let Some(text) = &banner_text {
+                        crate::redaction::register(crate::redaction::RedactCategory::Banners, text);
+                    }
+                    let captured = capture_limit
+                        .and_then(|limit| banner_bytes.as_deref().map(|b| CapturedResponse::new(b, limit)));
+                    if let Some(capture) = &captured {
+                        crate::redaction::register(crate::redaction::RedactCategory::Banners, &capture.hex);
+                    }
 
                     // Lock the results map once for this port's analysis
                     let mut map_guard = results_map_clone.lock().await;
@@ -641,6 +1020,7 @@ impl QuantumScanner {
                         // Explicitly type port_result for clarity
                         let result_entry: &mut PortResult = port_result;
                         result_entry.banner = banner_text.clone(); // Store the sanitized banner text
+                        result_entry.captured_response = captured;
                         
                         // If banner grabbing was successful, that's a definitive sign that the port is open
                         // Update the port's status to Open if banner was retrieved successfully
@@ -676,9 +1056,10 @@ impl QuantumScanner {
 
                         // Priority 2: Fingerprint using Banner (if no SSL/TLS service identified yet)
                         if identified_service.is_none() {
-                            if let Some(banner_str) = banner_text.as_ref() {
-                                // Use the ServiceFingerprints instance method
-                                if let Some((service, version)) = fingerprint_db_clone.identify_service(port, banner_str) {
+                            if let Some(banner_raw) = banner_bytes.as_deref() {
+                                // Look up the database now so fingerprint file reloads apply mid-scan;
+                                // raw bytes so binary signatures can match
+                                if let Some((service, version)) = crate::service_fingerprints::database().identify_bytes(port, banner_raw) {
                                     debug!("Fingerprint match for port {}: {} (Version: {:?})", port, service, version);
                                     identified_service = Some(service);
                                     identified_version = version;
@@ -686,6 +1067,13 @@ impl QuantumScanner {
                             }
                         }
 
+                        // Profile what unidentified services sent, so ciphertext is told apart from silence
+                        if identified_service.is_none() {
+                            let profile = crate::entropy::profile(banner_bytes.as_deref().unwrap_or(&[]));
+                            debug!("Payload on port {}: {} ({:.2} bits/byte over {} bytes)", port, profile.class, profile.entropy, profile.length);
+                            result_entry.payload = Some(profile);
+                        }
+
                         // Priority 3: Use ML identification if enabled and service is unknown/ambiguous
                         // Check if ML is enabled AND the identifier instance exists
                         if ml_identification_clone && ml_identifier_clone.is_some() {
@@ -696,6 +1084,16 @@ impl QuantumScanner {
                                 // Use the raw banner bytes for ML
                                 let banner_data = banner_bytes.as_deref().unwrap_or(&[]); // Use empty slice if no banner
                                 
+                                // Keep the top candidates so borderline calls can be judged later
+                                result_entry.ml_candidates = identifier.candidates(
+                                    banner_data,
+                                    port,
+                                    0.0,
+                                    false,
+                                    !banner_data.is_empty(),
+                                    3
+                                );
+
                                 // Call the identify_service method from the trait
                                 // Provide placeholder values for metadata not directly available here.
                                 // Consider enhancing banner grabbing to return this metadata if needed.
@@ -742,16 +1140,21 @@ impl QuantumScanner {
                             // Use the common port mapping from models.rs as a last resort
                             identified_service = crate::models::CommonPorts::get_service(port).map(String::from);
                         }
+                        if identified_service.is_none() {
+                            identified_service = result_entry.payload.as_ref()
+                                .and_then(crate::entropy::unknown_service)
+                                .map(String::from);
+                        }
 
                         // Update the final service and version in the results
                         result_entry.service = identified_service;
                         result_entry.version = identified_version;
 
                         // --- Vulnerability Identification (Placeholder) ---
-                        let mut vulns = Vec::<VulnInfo>::new();
+                        let mut vulns = Vec::<Finding>::new();
                         // Example placeholder check:
                         if result_entry.service.as_deref() == Some("ssh") && result_entry.version.as_deref().map_or(false, |v| v.contains("OpenSSH_7")) {
-                            vulns.push(VulnInfo { id: "CVE- Placeholder".to_string(), description: "Potential vulnerability in OpenSSH 7.x".to_string(), severity: "Info".to_string() });
+                            vulns.push(Finding { id: "CVE- Placeholder".to_string(), title: "OpenSSH 7.x".to_string(), description: "Potential vulnerability in OpenSSH 7.x".to_string(), severity: "Info".to_string(), confidence: Confidence::Tentative, ..Default::default() });
                         }
                         result_entry.vulns = vulns;
                         // --- End Vulnerability Identification ---
@@ -768,11 +1171,13 @@ impl QuantumScanner {
                 });
                 analysis_tasks.push(analysis_task);
             }
+        } else if let Some(stage) = &banner_stage {
+            stage.abort();
         }
 
         // Add timeout for the analysis phase to prevent hanging
         match tokio::time::timeout(
-            Duration::from_secs(60 * 2), // 2 minute timeout for analysis phase
+            crate::scheduler::within_deadline(Duration::from_secs(60 * 2)), // 2 minute timeout for analysis phase
             join_all(analysis_tasks)
         ).await {
             Ok(_) => info!("Post-scan analysis complete."),
@@ -783,26 +1188,484 @@ impl QuantumScanner {
         }
         // --- End Post-scan Analysis ---
 
+        // --- Sensitive service policy ---
+        // Checked once detection has identified the open ports; every deep enumeration
+        // phase below leaves the protected ones alone
+        let mut protected_ports = HashSet::<u16>::new();
+        if let Some(policy) = crate::service_policy::policy() {
+            let mut results_guard = results_map.lock().await;
+            for &port in self.ports.iter().chain(open_ports_set.lock().await.iter()) {
+                let service = results_guard.get(&port).and_then(|r| r.service.clone());
+                let Some(matched) = policy.protects(port, service.as_deref()) else { continue };
+                if let Some(port_result) = results_guard.get_mut(&port) {
+                    info!("[Policy] Port {} is protected ({}), skipping deep enumeration", port, matched);
+                    port_result.protected_by = Some(matched);
+                }
+                protected_ports.insert(port);
+            }
+        }
+
+        // --- VPN endpoint detection ---
+        // IKE and OpenVPN over UDP rarely answer generic probes, so they get dedicated
+        // handshakes whenever their ports were in scope; TCP probes only run on open ports
+        if service_scan_mode && !self.safe_mode && !skip_optional_phase("vpn detection", &mut skipped_phases) {
+            let open_tcp: Vec<u16> = open_ports_set.lock().await.iter()
+                .copied()
+                .filter(|port| !protected_ports.contains(port))
+                .collect();
+            let in_scope: Vec<u16> = self.ports.iter().copied().filter(|port| !protected_ports.contains(port)).collect();
+            let findings = crate::vpn_detect::detect_vpn_endpoints(target_ip, &in_scope, &open_tcp, self.timeout_scan.max(timeout_banner)).await;
+
+            if !findings.is_empty() {
+                let mut results_guard = results_map.lock().await;
+                let mut open_guard = open_ports_set.lock().await;
+                for finding in findings {
+                    let port_result = results_guard.entry(finding.port).or_insert_with(PortResult::default);
+                    let reason = format!("VPN probe: {} handshake answered", finding.service);
+                    if finding.transport == crate::vpn_detect::VpnTransport::Udp {
+                        port_result.udp_state = Some(PortStatus::Open);
+                    }
+                    port_result.final_status = PortStatus::Open;
+                    port_result.reason = Some(reason);
+                    port_result.service = Some(finding.service);
+                    port_result.version = finding.version;
+                    port_result.service_details = Some(finding.details);
+                    port_result.vulns.extend(finding.vulns);
+                    open_guard.insert(finding.port);
+                }
+            }
+        }
+
+        // --- UDP amplification exposure ---
+        // Reflector services often ignore the generic UDP probe, so their standard
+        // queries are sent whenever their ports were in scope
+        if service_scan_mode && !self.safe_mode && !skip_optional_phase("amplification checks", &mut skipped_phases) {
+            let in_scope: Vec<u16> = self.ports.iter().copied().filter(|port| !protected_ports.contains(port)).collect();
+            let checked = crate::amplification::check_amplification(target_ip, &in_scope, self.timeout_scan.max(timeout_banner)).await;
+
+            if !checked.is_empty() {
+                let mut results_guard = results_map.lock().await;
+                let mut open_guard = open_ports_set.lock().await;
+                for (port, service, measurements, vulns) in checked {
+                    let port_result = results_guard.entry(port).or_insert_with(PortResult::default);
+                    port_result.udp_state = Some(PortStatus::Open);
+                    port_result.final_status = PortStatus::Open;
+                    port_result.reason.get_or_insert_with(|| format!("UDP reply to the standard {} query", service));
+                    port_result.service.get_or_insert_with(|| service.to_string());
+                    port_result.amplification = measurements;
+                    port_result.vulns.extend(vulns);
+                    open_guard.insert(port);
+                }
+            }
+        }
+
+        // --- DNS server audit ---
+        // Recursion and version.bind go over UDP, zone transfers over TCP when it is open
+        if service_scan_mode && !self.safe_mode && self.ports.contains(&53) && !protected_ports.contains(&53)
+            && !skip_optional_phase("dns audit", &mut skipped_phases) {
+            let tcp_open = results_map.lock().await.get(&53)
+                .map_or(false, |r| r.tcp_states.values().any(|&s| s == PortStatus::Open));
+            let hostname = self.target.parse::<IpAddr>().is_err().then_some(self.target.as_str());
+            if let Some(audit) = crate::dns_audit::audit_dns(target_ip, 53, hostname, tcp_open, self.timeout_scan.max(timeout_banner)).await {
+                let mut results_guard = results_map.lock().await;
+                let port_result = results_guard.entry(53).or_insert_with(PortResult::default);
+                if audit.udp_answered {
+                    port_result.udp_state = Some(PortStatus::Open);
+                    port_result.final_status = PortStatus::Open;
+                    open_ports_set.lock().await.insert(53);
+                }
+                port_result.service.get_or_insert_with(|| "dns".to_string());
+                merge_service_details(port_result, audit.details);
+                port_result.vulns.extend(audit.vulns);
+            }
+        }
+
+        // Names from certificates identify the target as much as its own name does
+        for port_result in results_map.lock().await.values() {
+            if let Some(cert) = &port_result.cert_info {
+                for name in &cert.alt_names {
+                    if name.parse::<IpAddr>().is_ok() {
+                        crate::redaction::register(crate::redaction::RedactCategory::Targets, name);
+                    } else {
+                        crate::redaction::register(crate::redaction::RedactCategory::Hostnames, name.trim_start_matches("*."));
+                    }
+                }
+            }
+        }
+
+        // --- Certificate hostname checks ---
+        // The TLS handshake is made by IP, so names are only compared when the target was
+        // given as a hostname
+        if self.target.parse::<IpAddr>().is_err() {
+            let mut results_guard = results_map.lock().await;
+            for (port, port_result) in results_guard.iter_mut() {
+                let Some(cert) = port_result.cert_info.as_mut() else { continue };
+                if let Some(issue) = crate::cert_analysis::check_hostname(cert, &self.target) {
+                    debug!("[Cert] {}:{} {}", self.target, port, issue.detail);
+                    cert.issues.push(issue);
+                }
+            }
+        }
+
+        // --- SMTP relay and STARTTLS policy ---
+        // Needs detection to have named the SMTP ports; the session stops before DATA
+        if service_scan_mode && !self.safe_mode && !skip_optional_phase("smtp checks", &mut skipped_phases) {
+            let mut snapshot = results_map.lock().await.clone();
+            snapshot.retain(|port, _| !protected_ports.contains(port));
+            let hostname = self.target.parse::<IpAddr>().is_err().then_some(self.target.as_str());
+            let checked = crate::smtp_checks::check_smtp(target_ip, hostname, &snapshot, self.timeout_scan.max(timeout_banner)).await;
+
+            let mut results_guard = results_map.lock().await;
+            for (port, policy, vulns) in checked {
+                if let Some(port_result) = results_guard.get_mut(&port) {
+                    port_result.smtp = Some(policy);
+                    port_result.vulns.extend(vulns);
+                }
+            }
+        }
+
+        // --- Remote desktop security ---
+        // Only the security negotiation is read; no framebuffer or login screen is requested
+        if service_scan_mode && !self.safe_mode && !skip_optional_phase("remote access checks", &mut skipped_phases) {
+            let mut snapshot = results_map.lock().await.clone();
+            snapshot.retain(|port, _| !protected_ports.contains(port));
+            let checks = crate::remote_access::check_remote_access(target_ip, &snapshot, self.timeout_scan.max(timeout_banner)).await;
+
+            let mut results_guard = results_map.lock().await;
+            for check in checks {
+                if let Some(port_result) = results_guard.get_mut(&check.port) {
+                    port_result.service.get_or_insert_with(|| check.service.to_string());
+                    if port_result.version.is_none() {
+                        port_result.version = check.version;
+                    }
+                    merge_service_details(port_result, check.details);
+                    port_result.vulns.extend(check.vulns);
+                }
+            }
+        }
+
+        // --- Open proxy detection ---
+        // Proxies are asked to reach their own loopback, so the test never leaves their host
+        if service_scan_mode && !self.safe_mode && !skip_optional_phase("open proxy checks", &mut skipped_phases) {
+            let mut snapshot = results_map.lock().await.clone();
+            snapshot.retain(|port, _| !protected_ports.contains(port));
+            let checks = crate::open_proxy::check_proxies(target_ip, &snapshot, self.timeout_scan.max(timeout_banner)).await;
+
+            let mut results_guard = results_map.lock().await;
+            for check in checks {
+                let (details, vulns) = crate::open_proxy::details_and_vulns(&check);
+                if let Some(port_result) = results_guard.get_mut(&check.port) {
+                    merge_service_details(port_result, details);
+                    port_result.vulns.extend(vulns);
+                }
+            }
+        }
+
+        // --- Printer enumeration ---
+        // Information queries only; printers are a protected class under --safe
+        if service_scan_mode && !self.safe_mode && !skip_optional_phase("printer enumeration", &mut skipped_phases) {
+            let mut snapshot = results_map.lock().await.clone();
+            snapshot.retain(|port, _| !protected_ports.contains(port));
+            let hostname = self.target.parse::<IpAddr>().is_err().then_some(self.target.as_str());
+            let snmp_port = self.ports.contains(&161).then_some(161).filter(|port| !protected_ports.contains(port));
+            let found = crate::printer_enum::enumerate_printers(target_ip, hostname, &snapshot, snmp_port, self.timeout_scan.max(timeout_banner)).await;
+
+            let mut results_guard = results_map.lock().await;
+            for (port, info, vulns) in found {
+                if let Some(port_result) = results_guard.get_mut(&port) {
+                    if port_result.version.is_none() {
+                        port_result.version = info.model.clone();
+                    }
+                    port_result.printer = Some(info);
+                    port_result.vulns.extend(vulns);
+                }
+            }
+        }
+
+        // --- VoIP enumeration ---
+        // SIP over UDP is tried whenever its port was in scope, like the reflector queries
+        if service_scan_mode && !self.safe_mode && !skip_optional_phase("voip enumeration", &mut skipped_phases) {
+            let mut snapshot = results_map.lock().await.clone();
+            snapshot.retain(|port, _| !protected_ports.contains(port));
+            let in_scope: Vec<u16> = self.ports.iter().copied().filter(|port| !protected_ports.contains(port)).collect();
+            let found = crate::voip::check_voip(target_ip, &snapshot, &in_scope, self.timeout_scan.max(timeout_banner)).await;
+
+            let mut results_guard = results_map.lock().await;
+            let mut open_guard = open_ports_set.lock().await;
+            for (port, info, vulns) in found {
+                let port_result = results_guard.entry(port).or_insert_with(PortResult::default);
+                if info.transport == "udp" {
+                    port_result.udp_state = Some(PortStatus::Open);
+                    port_result.final_status = PortStatus::Open;
+                    port_result.reason.get_or_insert_with(|| "UDP reply to SIP OPTIONS".to_string());
+                    open_guard.insert(port);
+                }
+                port_result.service.get_or_insert_with(|| "sip".to_string());
+                if port_result.version.is_none() {
+                    port_result.version = info.agent.clone();
+                }
+                port_result.sip.push(info);
+                port_result.vulns.extend(vulns);
+            }
+        }
+
+        // --- Cloud metadata exposure ---
+        // References to the metadata service are read from what detection gathered; the
+        // canaries that ask for it through the port are left out under --safe
+        if service_scan_mode && !skip_optional_phase("cloud metadata checks", &mut skipped_phases) {
+            let mut snapshot = results_map.lock().await.clone();
+            snapshot.retain(|port, _| !protected_ports.contains(port));
+            let hostname = self.target.parse::<IpAddr>().is_err().then_some(self.target.as_str());
+            let checks = crate::cloud_exposure::check_metadata(target_ip, hostname, &snapshot, !self.safe_mode, self.timeout_scan.max(timeout_banner)).await;
+
+            let mut results_guard = results_map.lock().await;
+            for check in checks {
+                if let Some(port_result) = results_guard.get_mut(&check.port) {
+                    merge_service_details(port_result, check.details);
+                    port_result.vulns.extend(check.vulns);
+                }
+            }
+        }
+
+        // --- Web security hygiene ---
+        // One GET / per web port, over TLS where the port speaks it, scored into security_posture
+        if service_scan_mode && !skip_optional_phase("web hygiene audit", &mut skipped_phases) {
+            let mut snapshot = results_map.lock().await.clone();
+            snapshot.retain(|port, _| !protected_ports.contains(port));
+            let hostname = self.target.parse::<IpAddr>().is_err().then_some(self.target.as_str());
+            let checks = crate::web_hygiene::audit_sites(target_ip, hostname, &snapshot, self.timeout_scan.max(timeout_banner)).await;
+
+            let mut results_guard = results_map.lock().await;
+            for check in checks {
+                if let Some(port_result) = results_guard.get_mut(&check.port) {
+                    if check.http_info.is_some() {
+                        port_result.http_info = check.http_info;
+                    }
+                    port_result.security_posture = Some(check.posture);
+                    merge_service_details(port_result, check.details);
+                    port_result.vulns.extend(check.vulns);
+                }
+            }
+        }
+
+        // --- Well-known path probing (--web-paths) ---
+        // A fixed handful of GETs per web port, capped per host
+        if service_scan_mode && !self.safe_mode && crate::well_known::enabled() && !skip_optional_phase("well-known path probing", &mut skipped_phases) {
+            let mut snapshot = results_map.lock().await.clone();
+            snapshot.retain(|port, _| !protected_ports.contains(port));
+            let hostname = self.target.parse::<IpAddr>().is_err().then_some(self.target.as_str());
+            let checks = crate::well_known::probe_paths(target_ip, hostname, &snapshot, self.timeout_scan.max(timeout_banner)).await;
+
+            let mut results_guard = results_map.lock().await;
+            for check in checks {
+                if let Some(port_result) = results_guard.get_mut(&check.port) {
+                    merge_service_details(port_result, check.details);
+                    port_result.vulns.extend(check.vulns);
+                }
+            }
+        }
+
+        // --- API specification discovery ---
+        // OpenAPI/Swagger documents and GraphQL introspection on web ports; the introspection
+        // query is a POST, so not under --safe
+        if service_scan_mode && !self.safe_mode && !skip_optional_phase("api discovery", &mut skipped_phases) {
+            let mut snapshot = results_map.lock().await.clone();
+            snapshot.retain(|port, _| !protected_ports.contains(port));
+            let hostname = self.target.parse::<IpAddr>().is_err().then_some(self.target.as_str());
+            let checks = crate::api_discovery::discover_apis(target_ip, hostname, &snapshot, self.timeout_scan.max(timeout_banner)).await;
+
+            let mut results_guard = results_map.lock().await;
+            for check in checks {
+                if let Some(port_result) = results_guard.get_mut(&check.port) {
+                    merge_service_details(port_result, check.details);
+                    port_result.vulns.extend(check.vulns);
+                }
+            }
+        }
+
+        // --- Container platform checks ---
+        // Read-only requests to Docker, etcd, kubelet and Kubernetes API ports
+        if service_scan_mode && !self.safe_mode && !skip_optional_phase("container platform checks", &mut skipped_phases) {
+            let mut snapshot = results_map.lock().await.clone();
+            snapshot.retain(|port, _| !protected_ports.contains(port));
+            let hostname = self.target.parse::<IpAddr>().is_err().then_some(self.target.as_str());
+            let checks = crate::container_platform::check_platforms(target_ip, hostname, &snapshot, self.timeout_scan.max(timeout_banner)).await;
+
+            let mut results_guard = results_map.lock().await;
+            for check in checks {
+                if let Some(port_result) = results_guard.get_mut(&check.port) {
+                    port_result.service = Some(check.service.to_string());
+                    if check.version.is_some() {
+                        port_result.version = check.version;
+                    }
+                    merge_service_details(port_result, check.details);
+                    port_result.vulns.extend(check.vulns);
+                }
+            }
+        }
+
+        // --- SNI certificate harvesting (--sni-list) ---
+        // One handshake per listed name on every TLS port, so left to the operator to ask for
+        let sni_names = crate::sni_probe::names();
+        if service_scan_mode && !sni_names.is_empty() && !skip_optional_phase("sni certificate harvesting", &mut skipped_phases) {
+            let mut snapshot = results_map.lock().await.clone();
+            snapshot.retain(|port, _| !protected_ports.contains(port));
+            let harvested = crate::sni_probe::harvest(target_ip, &snapshot, &sni_names, self.timeout_scan.max(timeout_banner)).await;
+
+            let mut results_guard = results_map.lock().await;
+            for (port, certificates) in harvested {
+                if let Some(port_result) = results_guard.get_mut(&port) {
+                    port_result.sni_certificates = certificates;
+                }
+            }
+        }
+
+        // --- Cleartext administrative protocols ---
+        // Judged from detection results; only the FTP FEAT query sends traffic, and not under --safe
+        if service_scan_mode && !skip_optional_phase("cleartext findings", &mut skipped_phases) {
+            let mut snapshot = results_map.lock().await.clone();
+            snapshot.retain(|port, _| !protected_ports.contains(port));
+            let checks = crate::cleartext::check_cleartext(target_ip, &snapshot, !self.safe_mode, self.timeout_scan.max(timeout_banner)).await;
+
+            let mut results_guard = results_map.lock().await;
+            for check in checks {
+                if let Some(port_result) = results_guard.get_mut(&check.port) {
+                    merge_service_details(port_result, check.details);
+                    port_result.vulns.extend(check.vulns);
+                }
+            }
+        }
+
+        // --- Default credential checks (opt-in) ---
+        crate::progress::set_phase("post-detection");
+        // Runs after detection so only ports identified as FTP/Telnet/HTTP are tried, and
+        // before scripts so they can see the findings
+        if let Some(config) = crate::default_creds::config().filter(|_| !skip_optional_phase("default credentials", &mut skipped_phases)) {
+            let mut snapshot = results_map.lock().await.clone();
+            snapshot.retain(|port, _| !protected_ports.contains(port));
+            let snmp_port = self.ports.contains(&161).then_some(161).filter(|port| !protected_ports.contains(port));
+            let report = crate::default_creds::check_default_creds(target_ip, &snapshot, snmp_port, &config).await;
+
+            let mut results_guard = results_map.lock().await;
+            for (port, vuln) in report.findings {
+                let port_result = results_guard.entry(port).or_insert_with(PortResult::default);
+                if port == 161 && port_result.udp_state.is_none() {
+                    port_result.udp_state = Some(PortStatus::Open);
+                    port_result.final_status = PortStatus::Open;
+                    port_result.service.get_or_insert_with(|| "snmp".to_string());
+                }
+                port_result.vulns.push(vuln);
+            }
+            for (port, note) in report.notes {
+                results_guard.entry(port).or_insert_with(PortResult::default).anomalies.push(Finding::anomaly(note));
+            }
+        }
+
+        // --- Post-detection scripts ---
+        // User checks from --scripts run against every open TCP port once detection has
+        // filled in service and version, each on a blocking thread
+        if !crate::scripting::scripts().is_empty() && !skip_optional_phase("scripts", &mut skipped_phases) {
+            let open_tcp: Vec<(u16, PortResult)> = {
+                let results_guard = results_map.lock().await;
+                results_guard.iter()
+                    .filter(|(port, r)| r.tcp_states.values().any(|&s| s == PortStatus::Open) && !protected_ports.contains(port))
+                    .map(|(&port, r)| (port, r.clone()))
+                    .collect()
+            };
+            info!("[Script] Running {} scripts against {} open ports", crate::scripting::scripts().len(), open_tcp.len());
+
+            let script_timeout = self.timeout_scan.max(timeout_banner);
+            let runs = open_tcp.into_iter().map(|(port, snapshot)| {
+                let semaphore = semaphore.clone();
+                async move {
+                    let _permit = semaphore.acquire().await;
+                    let outcome = tokio::task::spawn_blocking(move || {
+                        crate::scripting::run_scripts(target_ip, port, &snapshot, script_timeout)
+                    }).await;
+                    (port, outcome)
+                }
+            });
+
+            for (port, outcome) in join_all(runs).await {
+                match outcome {
+                    Ok((outputs, vulns)) => {
+                        if outputs.is_empty() && vulns.is_empty() {
+                            continue;
+                        }
+                        let mut results_guard = results_map.lock().await;
+                        if let Some(port_result) = results_guard.get_mut(&port) {
+                            port_result.script_output.extend(outputs);
+                            port_result.vulns.extend(vulns);
+                        }
+                    }
+                    Err(e) => warn!("[Script] Script task for port {} panicked: {}", port, e),
+                }
+            }
+        }
+
+        // --- Plugin enrichment ---
+        // Plugins run synchronously in an interpreter, so the open ports are handed to a
+        // blocking thread in one batch and written back afterwards
+        if !crate::plugins::plugins().is_empty() {
+            let open_ports: Vec<(u16, PortResult)> = {
+                let results_guard = results_map.lock().await;
+                results_guard.iter()
+                    .filter(|(port, r)| (r.final_status == PortStatus::Open || r.tcp_states.values().any(|&s| s == PortStatus::Open))
+                        && !protected_ports.contains(port))
+                    .map(|(&port, r)| (port, r.clone()))
+                    .collect()
+            };
+            match tokio::task::spawn_blocking(move || {
+                open_ports.into_iter()
+                    .map(|(port, mut result)| {
+                        crate::plugins::enrich_port(target_ip, port, &mut result);
+                        (port, result)
+                    })
+                    .collect::<Vec<_>>()
+            }).await {
+                Ok(enriched) => results_map.lock().await.extend(enriched),
+                Err(e) => warn!("[Plugin] Enrichment task panicked: {}", e),
+            }
+        }
+
         let end_time = Utc::now();
-        let final_results_map = results_map.lock().await.clone();
+        let mut final_results_map = results_map.lock().await.clone();
         let mut final_open_ports = open_ports_set.lock().await.clone();
-        
-        // Final pass: ensure all ports with Open or OpenFiltered status from any scan type are in open_ports
-        for (&port, result) in &final_results_map {
-            let is_open = result.tcp_states.values().any(|&status| 
-                status == PortStatus::Open || status == PortStatus::OpenFiltered);
-            
-            let is_open_udp = result.udp_state.map_or(false, |status| 
-                status == PortStatus::Open || status == PortStatus::OpenFiltered);
-            
-            // Consider service detection (banner grab, etc.) that identified a known service
-            let has_service = result.service.is_some();
-            
-            if (is_open || is_open_udp || has_service) && !final_open_ports.contains(&port) {
+
+        // Final pass: the techniques' weighted consensus decides each port's state, and
+        // with it whether the port is listed as open
+        for (&port, result) in final_results_map.iter_mut() {
+            if let Some(consensus) = port_consensus(result) {
+                if !consensus.dissenting.is_empty() {
+                    debug!("Port {}: {} with confidence {:.2}; {} disagreed", port, consensus.state, consensus.confidence, consensus.dissenting.join(", "));
+                }
+                result.final_status = consensus.state;
+                result.consensus = Some(consensus);
+            }
+            let is_open = matches!(result.final_status, PortStatus::Open | PortStatus::OpenFiltered);
+            if is_open && final_open_ports.insert(port) {
                 info!("Adding port {} to open ports list based on post-analysis", port);
-                final_open_ports.insert(port);
+            } else if !is_open && final_open_ports.remove(&port) {
+                info!("Removing port {} from open ports list: the techniques agree it is {}", port, result.final_status);
+            }
+        }
+
+        // Replies an inline appliance may have forged cannot be taken at face value
+        let interference = crate::hostile::finish(self.target_ip);
+        for sign in &interference {
+            for port in &sign.ports {
+                if let Some(result) = final_results_map.get_mut(port) {
+                    result.unreliable = Some(match result.unreliable.take() {
+                        Some(earlier) => format!("{}; {}", earlier, sign.kind),
+                        None => sign.kind.to_string(),
+                    });
+                }
             }
         }
+        if !interference.is_empty() {
+            warn!("{} shows signs of an inline security appliance ({}); affected ports are marked unreliable",
+                  self.target, interference.iter().map(|sign| sign.kind.to_string()).collect::<Vec<_>>().join(", "));
+        }
         
         // Log a summary of all ports and their states
         if debug_mode {
@@ -835,6 +1698,20 @@ impl QuantumScanner {
             debug!("Tasks attempted (packets_sent counter): {}, Tasks completed without error (successful_scans counter): {}", final_packets_sent, final_successful_scans);
         }
 
+        // --- MAC Address Identification ---
+        // Only resolvable for on-link targets; the scan traffic has populated the neighbour cache by now
+        let (mac_address, mac_vendor) = match crate::mac_vendor::lookup_neighbor_mac(self.target_ip) {
+            Some(mac) => {
+                let vendor = crate::mac_vendor::lookup_vendor(mac).map(|v| v.to_string());
+                info!("Target {} has MAC address {} ({})", self.target_ip, mac, vendor.as_deref().unwrap_or("unknown vendor"));
+                (Some(mac.to_string()), vendor)
+            }
+            None => {
+                debug!("No MAC address available for {} (not on-link or not cached)", self.target_ip);
+                (None, None)
+            }
+        };
+
         // --- nDPI Cleanup ---
         if let Some(engine_arc) = self.ndpi_engine.take() {
             // Explicitly drop the Arc<Mutex<NdpiEngine>>
@@ -844,7 +1721,27 @@ impl QuantumScanner {
         }
         // --- End nDPI Cleanup ---
 
-        Ok(ScanResults {
+        crate::progress::end_host();
+
+        let skipped_probes = std::mem::take(&mut *self.skipped_probes.lock());
+        if !skipped_probes.is_empty() || !skipped_phases.is_empty() {
+            warn!("--max-scan-time: {} ports have unsent probes and {} phases were skipped for {}",
+                  skipped_probes.len(), skipped_phases.len(), self.target);
+        }
+
+        let edge = crate::edge_detect::detect(&self.target, self.target_ip, &final_results_map).await;
+        let cloud = crate::cloud_exposure::infer(&self.target, &final_results_map, edge.as_ref());
+        let service_categories: HashMap<String, Vec<u16>> = [
+            (crate::container_platform::CATEGORY, crate::container_platform::category_ports(&final_results_map)),
+            (crate::voip::CATEGORY, crate::voip::category_ports(&final_results_map)),
+        ].into_iter()
+            .filter(|(_, ports)| !ports.is_empty())
+            .map(|(category, ports)| (category.to_string(), ports))
+            .collect();
+        let service_categories = (!service_categories.is_empty()).then_some(service_categories);
+
+        let mut results = ScanResults {
+            schema_version: crate::models::SCHEMA_VERSION,
             target: self.target.clone(),
             target_ip: self.target_ip.to_string(),
             open_ports: final_open_ports,
@@ -853,11 +1750,32 @@ impl QuantumScanner {
             end_time,
             scan_types: self.scan_types.clone(),
             packets_sent: final_packets_sent as usize, 
+            bytes_sent: crate::scheduler::bytes_sent() - bytes_before,
             successful_scans: final_successful_scans as usize,
-            os_summary: None,
+            // ICMP behaviour is the only host-wide OS evidence gathered so far
+            os_summary: icmp_info.as_ref().and_then(|info| info.os_hints.first().cloned()),
             risk_assessment: None,
-            service_categories: None,
-        })
+            service_categories,
+            mac_address,
+            mac_vendor,
+            ip_protocols: ip_protocol_results,
+            icmp_info,
+            host_timing,
+            technique_selection,
+            dns_sanity: crate::dns_sanity::report(),
+            metadata: crate::metadata::current(),
+            skipped_probes,
+            skipped_phases,
+            asset: None,
+            outliers: Vec::new(),
+            deviations: Vec::new(),
+            edge,
+            cloud,
+            interference,
+            merged_runs: Vec::new(),
+        };
+        crate::assets::annotate(&mut results);
+        Ok(results)
     }
 
     /// Set enhanced evasion options
@@ -869,6 +1787,175 @@ impl QuantumScanner {
         self.ttl_jitter = jitter;
     }
 
+    /// Install the evasion pipeline applied to raw-socket probes
+    pub fn set_evasion_pipeline(&mut self, pipeline: crate::evasion::EvasionPipeline) {
+        info!("Setting evasion pipeline: {}", pipeline);
+        self.evasion = !pipeline.is_empty();
+        crate::evasion::set_pipeline(pipeline);
+    }
+
+    /// Set the scan-wide inter-probe timing model
+    pub fn set_timing_model(&mut self, model: crate::scheduler::TimingModel) {
+        info!("Setting timing model: {}", model);
+        crate::scheduler::set_timing_model(model);
+    }
+
+    /// Enable cover traffic interleaved with scan probes
+    pub fn set_cover_traffic(&mut self, config: crate::cover::CoverTrafficConfig) {
+        info!("Setting cover traffic: {} requests per probe, {:.0}% DNS", config.ratio, config.dns_share * 100.0);
+        crate::cover::set_cover_traffic(config);
+    }
+
+    /// Set fragment ordering and overlap for frag scans
+    pub fn set_fragment_policy(&mut self, policy: crate::evasion::FragmentPolicy) {
+        info!("Setting fragment policy: order={:?}, overlap={:?}", policy.order, policy.overlap);
+        crate::evasion::set_fragment_policy(policy);
+    }
+
+    /// Split application payloads of connect-based probes into tiny TCP segments
+    pub fn set_tcp_segmentation(&mut self, policy: crate::evasion::SegmentationPolicy) {
+        info!("Setting TCP payload segmentation: {} bytes per segment, {:?}-{:?} apart",
+              policy.segment_size, policy.min_delay, policy.max_delay);
+        crate::evasion::set_segmentation(Some(policy));
+    }
+
+    /// Collect stateless replies by capturing on an interface instead of the raw socket
+    pub fn set_response_iface(&mut self, iface: &str) {
+        info!("Setting response capture interface: {}", iface);
+        crate::correlate::set_capture_source(crate::correlate::CaptureSource::Interface(iface.to_string()));
+    }
+
+    /// Address the target resolved to
+    pub fn target_ip(&self) -> IpAddr {
+        self.target_ip
+    }
+
+    /// Point the scanner at another host, keeping every other setting
+    ///
+    /// Used to scan targets discovered mid-run (e.g. from certificate names) with the
+    /// same configuration; `target_ip` must already be resolved.
+    pub fn retarget(&mut self, target: &str, target_ip: IpAddr) {
+        info!("Retargeting scanner to {} ({})", target, target_ip);
+        self.target = target.to_string();
+        self.target_ip = target_ip;
+    }
+
+    /// Copy of this scanner pointed at another host, for scanning hosts in parallel
+    ///
+    /// The copy shares the rate limiter, so `--rate` stays a limit for the whole run,
+    /// but keeps its own per-host state.
+    pub fn fork(&self, target: &str, target_ip: IpAddr) -> Self {
+        crate::redaction::register(crate::redaction::RedactCategory::Targets, &target_ip.to_string());
+        let mut scanner = self.clone();
+        scanner.target = target.to_string();
+        scanner.target_ip = target_ip;
+        scanner.results_map = HashMap::new();
+        scanner.skipped_probes = Arc::new(parking_lot::Mutex::new(HashMap::new()));
+        scanner
+    }
+
+    /// Scan several hosts, running groups of them in parallel
+    ///
+    /// The first group has `min_hostgroup` hosts and each following group twice as many
+    /// as the one before, up to `max_hostgroup`, so the first results arrive quickly and
+    /// a misconfigured run fails early. Every host keeps its own `concurrency` limit, so
+    /// up to `max_hostgroup * concurrency` probes are in flight at once.
+    ///
+    /// # Arguments
+    /// * `hosts` - Addresses to scan, in order
+    /// * `min_hostgroup` - Size of the first group
+    /// * `max_hostgroup` - Largest group size
+    ///
+    /// # Returns
+    /// * `Vec<ScanResults>` - Results of the hosts whose scan succeeded, in `hosts` order
+    ///
+    /// # Opsec Considerations
+    /// Parallel hosts multiply the scanner's footprint on shared links and IDS sensors.
+    /// Large groups with a low per-host concurrency spread probes thinly over many hosts,
+    /// which per-host rate thresholds notice less than a fast scan of one host.
+    pub async fn run_hostgroups(&self, hosts: &[IpAddr], min_hostgroup: usize, max_hostgroup: usize) -> Vec<ScanResults> {
+        let mut results = Vec::with_capacity(hosts.len());
+        let mut group_size = min_hostgroup.max(1);
+        let mut remaining = hosts;
+        while !remaining.is_empty() {
+            let (group, rest) = remaining.split_at(group_size.min(remaining.len()));
+            remaining = rest;
+            info!("Scanning hostgroup of {} hosts ({} remaining)", group.len(), remaining.len());
+
+            let scans = group.iter().map(|&ip| {
+                let mut scanner = self.fork(&ip.to_string(), ip);
+                async move { (ip, scanner.run_scan().await) }
+            });
+            for (ip, outcome) in join_all(scans).await {
+                match outcome {
+                    Ok(result) => results.push(result),
+                    Err(e) => warn!("Scan of {} failed: {}", ip, e),
+                }
+            }
+            group_size = (group_size * 2).min(max_hostgroup.max(min_hostgroup));
+        }
+        results
+    }
+
+    /// Scan hosts as they arrive on a stream of target lines
+    ///
+    /// Each line is expanded with `target_stream::expand` and its hosts are scanned as
+    /// soon as fewer than `max_parallel` scans are running; reading pauses while hosts
+    /// are waiting, so a fast producer does not queue unbounded work. Addresses already
+    /// scanned in this run are skipped.
+    ///
+    /// # Arguments
+    /// * `pending` - Hosts already resolved, scanned first
+    /// * `lines` - Further target lines; the run ends once it closes and all scans finish
+    /// * `max_parallel` - Most hosts scanned at once
+    /// * `max_hosts` - Largest CIDR range accepted on one line
+    ///
+    /// # Returns
+    /// * `Vec<ScanResults>` - Results of the hosts whose scan succeeded, in completion order
+    pub async fn run_stream(&self, mut pending: VecDeque<(String, IpAddr)>, mut lines: mpsc::UnboundedReceiver<String>,
+                            max_parallel: usize, max_hosts: usize) -> Vec<ScanResults> {
+        let mut results = Vec::new();
+        let mut seen: HashSet<IpAddr> = pending.iter().map(|(_, ip)| *ip).collect();
+        crate::progress::add_hosts(pending.len());
+        let mut running = FuturesUnordered::new();
+        let mut input_open = true;
+
+        loop {
+            while running.len() < max_parallel.max(1) {
+                let Some((target, ip)) = pending.pop_front() else { break };
+                info!("[Stdin] Scanning {} ({})", target, ip);
+                let mut scanner = self.fork(&target, ip);
+                running.push(async move { (target, scanner.run_scan().await) });
+            }
+            if running.is_empty() && pending.is_empty() && !input_open {
+                break;
+            }
+
+            tokio::select! {
+                line = lines.recv(), if input_open && pending.is_empty() => match line {
+                    Some(line) => match crate::target_stream::expand(&line, self.use_ipv6, max_hosts).await {
+                        Ok(hosts) => {
+                            let fresh: Vec<_> = hosts.into_iter().filter(|(_, ip)| seen.insert(*ip)).collect();
+                            if fresh.is_empty() {
+                                debug!("[Stdin] {} was already scanned", line);
+                            }
+                            crate::progress::add_hosts(fresh.len());
+                            pending.extend(fresh);
+                        }
+                        Err(e) => warn!("[Stdin] Skipping {}: {}", line, e),
+                    },
+                    None => input_open = false,
+                },
+                Some((target, outcome)) = running.next(), if !running.is_empty() => match outcome {
+                    Ok(result) => results.push(result),
+                    Err(e) => warn!("Scan of {} failed: {}", target, e),
+                },
+                else => break,
+            }
+        }
+        results
+    }
+
     /// Set protocol variant for mimicry scans
     pub fn set_protocol_variant(&mut self, variant: Option<&str>) {
         info!("Setting protocol variant: {:?}", variant);
@@ -891,6 +1978,142 @@ impl QuantumScanner {
         self.dns_tunnel_domain = domain.map(|s| s.to_string());
     }
 
+    /// Set source MAC spoofing for raw frames
+    ///
+    /// # Returns
+    /// * `Result<()>` - Error if frames from the local address cannot carry a spoofed MAC;
+    ///   probes are never sent with the real one instead
+    pub fn set_spoof_mac(&mut self, spoof_mac: &crate::mac_vendor::SpoofMac) -> Result<()> {
+        info!("Setting source MAC spoofing: {}", spoof_mac);
+        if let Some(local_ip) = self.local_ip_v4 {
+            crate::mac_vendor::open_spoof_link(IpAddr::V4(local_ip))?;
+        }
+        // Resolve once so every frame in this scan carries the same source MAC
+        crate::mac_vendor::set_spoofed_mac(Some(spoof_mac.resolve()));
+        Ok(())
+    }
+
+    /// Enable or disable the AF_PACKET fast I/O path for raw scans
+    pub fn set_fast_io(&mut self, enabled: bool) {
+        info!("Setting fast I/O: {}", enabled);
+        if !enabled {
+            crate::fast_io::set_engine(None);
+            return;
+        }
+
+        // The engine is bound to the interface owning the local address and shared by
+        // every host of the run, including hostgroups and forked scanners
+        let Some(local_ip) = self.local_ip_v4 else {
+            warn!("Fast I/O requires a local IPv4 address; using regular raw sockets");
+            return;
+        };
+
+        match crate::fast_io::FastIo::new(local_ip) {
+            Ok(engine) => crate::fast_io::set_engine(Some(engine)),
+            Err(e) => warn!("Failed to initialize fast I/O ({}); using regular raw sockets", e),
+        }
+    }
+
+    /// Enable stateless (SYN cookie) port discovery
+    pub fn set_stateless(&mut self, enabled: bool) {
+        info!("Setting stateless discovery: {}", enabled);
+        self.stateless = enabled;
+    }
+
+    /// Enable two-phase scanning (fast discovery, then deep enumeration)
+    pub fn set_two_phase(&mut self, enabled: bool) {
+        info!("Setting two-phase scanning: {}", enabled);
+        self.two_phase = enabled;
+    }
+
+    /// Enable adaptive port selection (expand to related ports based on findings)
+    pub fn set_adaptive_ports(&mut self, enabled: bool) {
+        info!("Setting adaptive port selection: {}", enabled);
+        self.adaptive_ports = enabled;
+    }
+
+    /// Set per-protocol port sets (from a `T:`/`U:` port specification)
+    pub fn set_protocol_ports(&mut self, protocol_ports: ProtocolPorts) {
+        info!("Setting per-protocol ports: {} TCP, {} UDP", protocol_ports.tcp.len(), protocol_ports.udp.len());
+        self.protocol_ports = Some(protocol_ports);
+    }
+
+    /// Enable ICMP timestamp/address mask requests and UDP unreachable analysis
+    pub fn set_icmp_probes(&mut self, enabled: bool) {
+        info!("Setting ICMP host probes: {}", enabled);
+        self.icmp_probes = enabled;
+    }
+
+    /// Set the DNS tunnel transport (record type, encoding, EDNS0, path probing)
+    pub fn set_dns_tunnel_transport(&mut self, options: crate::tunnel::DnsTunnelOptions) {
+        info!("Setting DNS tunnel transport: {:?}", options);
+        crate::tunnel::set_dns_tunnel_options(options);
+    }
+
+    /// Relay banner grabs through an ICMP covert channel
+    pub fn set_icmp_relay(&mut self, relay: IpAddr, config: crate::tunnel::CovertChannelConfig) {
+        info!("Setting ICMP covert channel relay: {}", relay);
+        self.icmp_relay = Some((relay, config));
+    }
+
+    /// Relay HTTP tunnel scans and banner grabs through an HTTP(S) relay
+    pub fn set_http_relay(&mut self, config: crate::tunnel::HttpTunnelConfig) {
+        info!("Setting HTTP relay: {}:{} (Host: {}, SNI: {})",
+            config.connect_host, config.connect_port,
+            config.host_header.as_deref().unwrap_or("default"),
+            config.sni.as_deref().unwrap_or("default"));
+        self.http_relay = Some(config);
+    }
+
+    /// Relay QUIC tunnel scans and banner grabs through a QUIC relay
+    pub fn set_quic_relay(&mut self, config: crate::tunnel::QuicTunnelConfig) {
+        info!("Setting QUIC relay: {} (SNI: {}, connection reuse: {})",
+            config.relay, config.server_name, config.reuse_connection);
+        self.quic_relay = Some(config);
+    }
+
+    /// Calibrate each host's probe timeout and retries from its measured round-trip time
+    pub fn set_adaptive_timeout(&mut self, enabled: bool) {
+        info!("Setting adaptive timeouts: {}", enabled);
+        self.adaptive_timeout = enabled;
+    }
+
+    /// Set the most banner grabs in flight at once
+    pub fn set_banner_concurrency(&mut self, concurrency: usize) {
+        info!("Setting banner grab concurrency: {}", concurrency);
+        self.banner_concurrency = concurrency.max(1);
+    }
+
+    /// Keep the first `limit` bytes of each banner reply in the port results
+    pub fn set_capture_responses(&mut self, limit: usize) {
+        info!("Capturing up to {} bytes of each service response", limit);
+        self.capture_responses = Some(limit.min(crate::banner::MAX_CAPTURE_BYTES));
+    }
+
+    /// Leave out the mimic probes of two-phase enumeration and the VPN handshakes
+    pub fn set_safe_mode(&mut self, enabled: bool) {
+        info!("Setting safe mode: {}", enabled);
+        self.safe_mode = enabled;
+    }
+
+    /// Choose the TCP techniques for each host from a pre-probe of how its stack answers
+    pub fn set_auto_technique(&mut self, enabled: bool) {
+        info!("Setting automatic technique selection: {}", enabled);
+        self.auto_technique = enabled;
+    }
+
+    /// Set the IP protocol numbers probed by the IP protocol scan
+    pub fn set_ip_protocols(&mut self, protocols: Vec<u8>) {
+        info!("Setting IP protocols for protocol scan: {:?}", protocols);
+        self.ip_protocols = protocols;
+    }
+
+    /// Replace the scan types used by the next scan
+    pub fn set_scan_types(&mut self, scan_types: Vec<ScanType>) {
+        info!("Setting scan types: {:?}", scan_types);
+        self.scan_types = scan_types;
+    }
+
     /// Run scan tasks for individual ports
     async fn run_port_scan_tasks(
         &self,
@@ -916,6 +2139,7 @@ impl QuantumScanner {
         _ml_identification: bool,
     ) -> Vec<JoinHandle<()>> {
         let mut tasks = Vec::with_capacity(ports.len());
+        crate::progress::add_expected(ports.len());
         
         // Clone all values needed by the closure once outside the loop
         let rate_limiter = self.rate_limiter.clone();
@@ -950,10 +2174,15 @@ impl QuantumScanner {
             let rate_limiter_clone = rate_limiter.clone();
             let scan_type_clone = scan_type;
             let port_clone = port;
+            let skipped_probes_clone = self.skipped_probes.clone();
+            let probe_retries = self.probe_retries;
+            let banner_stage_clone = self.banner_stage.clone();
             
             // DNS tunnel specific variables
             let dns_server_clone = self.dns_tunnel_server;
             let dns_domain_clone = self.dns_tunnel_domain.clone();
+            let http_relay_clone = self.http_relay.clone();
+            let quic_relay_clone = self.quic_relay.clone();
             
             // Also clone fragment parameters
             let frag_min_size_clone = frag_min_size;
@@ -964,8 +2193,9 @@ impl QuantumScanner {
             let _frag_first_min_size_clone = frag_first_min_size;
             let _frag_two_frags_clone = frag_two_frags;
             
-            // Spawn a new async task for this port
-            let task = tokio::spawn(async move {
+            // Spawn a new async task for this port; the audit trail attributes its probes
+            // to its scan type, and under --seed it draws from its own generator
+            let task = tokio::spawn(crate::audit::with_scan_type(scan_type_clone, crate::utils::with_probe_rng(target_ip_clone, port_clone, scan_type_clone, async move {
                 // Wait until we can acquire a permit from the semaphore
                 let _permit = match semaphore_clone.acquire().await {
                     Ok(permit) => permit,
@@ -974,6 +2204,17 @@ impl QuantumScanner {
                         return;
                     }
                 };
+
+                // Under --max-scan-time, the wind-down window only confirms ports already
+                // found open; once the budget is spent nothing new is sent
+                if crate::scheduler::winding_down() {
+                    let known_open = open_ports_set_clone.lock().await.contains(&port_clone);
+                    if crate::scheduler::deadline_passed() || !known_open {
+                        skipped_probes_clone.lock().entry(port_clone).or_default().push(scan_type_clone);
+                        crate::progress::probe_done();
+                        return;
+                    }
+                }
                 
                 // Apply rate limiting if configured
                 if let Some(limiter) = &rate_limiter_clone {
@@ -983,341 +2224,452 @@ impl QuantumScanner {
                         tokio::time::sleep(sleep_time).await;
                     }
                 }
+
+                // Probes that get no reply are retransmitted up to the calibrated retry count
+                let mut attempt = 0u8;
+                let result = loop {
+                    crate::utils::probe_attempt(attempt);
+
+                    // Wait for this probe's slot under --timing-model
+                    crate::scheduler::wait_turn().await;
+                    crate::cover::on_probe();
                 
-                // Increment packets sent counter
-                {
-                    let mut counter = packets_sent_clone.lock().await;
-                    *counter += 1;
-                }
+                    // Increment packets sent counter
+                    {
+                        let mut counter = packets_sent_clone.lock().await;
+                        *counter += 1;
+                    }
                 
-                // Perform the specific scan type
-                let result = match scan_type_clone {
-                    ScanType::DnsTunnel => {
-                        techniques::dns_tunnel_scan(
-                            target_ip_clone,
-                            port_clone,
-                            local_ip,
-                            use_ipv6_clone,
-                            timeout_scan_clone,
-                            dns_server_clone,
-                            dns_domain_clone.clone()
-                        ).await.map(|status| {
-                            // Create reason for DNS tunnel scan
-                            let reason = match status {
-                                PortStatus::OpenFiltered => Some("DNS Tunnel scan: DNS query response received, port state ambiguous".to_string()),
-                                PortStatus::Filtered => Some("DNS Tunnel scan: DNS query timeout or blocked".to_string()),
-                                _ => Some("DNS Tunnel scan: Unexpected response".to_string()),
-                            };
+                    // Perform the specific scan type
+                    let result = match scan_type_clone {
+                        ScanType::DnsTunnel => {
+                            techniques::dns_tunnel_scan(
+                                target_ip_clone,
+                                port_clone,
+                                local_ip,
+                                use_ipv6_clone,
+                                timeout_scan_clone,
+                                dns_server_clone,
+                                dns_domain_clone.clone()
+                            ).await.map(|status| {
+                                // Create reason for DNS tunnel scan
+                                let reason = match status {
+                                    PortStatus::OpenFiltered if crate::dns_sanity::path_unreliable() => Some("DNS Tunnel scan: resolver path intercepts or rewrites answers, port state unknown".to_string()),
+                                    PortStatus::OpenFiltered => Some("DNS Tunnel scan: DNS query response received, port state ambiguous".to_string()),
+                                    PortStatus::Filtered => Some("DNS Tunnel scan: DNS query timeout or blocked".to_string()),
+                                    _ => Some("DNS Tunnel scan: Unexpected response".to_string()),
+                                };
                             
-                            let mut result = ScanResult::new(port_clone, status);
-                            result.set_reason(reason.clone());
+                                let mut result = ScanResult::new(port_clone, status);
+                                result.set_reason(reason.clone());
                             
-                            // For converting to PortResult later
-                            result.scan_type = Some(ScanType::DnsTunnel);
+                                // For converting to PortResult later
+                                result.scan_type = Some(ScanType::DnsTunnel);
                             
-                            result
-                        })
-                    },
-                    ScanType::IcmpTunnel => {
-                        techniques::icmp_tunnel_scan(
-                            target_ip_clone,
-                            port_clone,
-                            local_ip,
-                            use_ipv6_clone,
-                            timeout_scan_clone
-                        ).await.map(|status| {
-                            // Create reason for ICMP tunnel scan
-                            let reason = match status {
-                                PortStatus::Open => Some("ICMP Tunnel scan: ICMP echo response with valid payload received".to_string()),
-                                PortStatus::OpenFiltered => Some("ICMP Tunnel scan: Ambiguous ICMP response received".to_string()),
-                                PortStatus::Filtered => Some("ICMP Tunnel scan: No ICMP response or timeout".to_string()),
-                                _ => Some("ICMP Tunnel scan: Unexpected response".to_string()),
-                            };
+                                result
+                            })
+                        },
+                        ScanType::HttpTunnel => {
+                            techniques::http_tunnel_scan(
+                                target_ip_clone,
+                                port_clone,
+                                timeout_scan_clone,
+                                http_relay_clone.as_ref()
+                            ).await.map(|status| {
+                                let reason = match status {
+                                    PortStatus::Open => Some("HTTP Tunnel scan: Relay reported connection accepted".to_string()),
+                                    PortStatus::Closed => Some("HTTP Tunnel scan: Relay reported connection refused".to_string()),
+                                    PortStatus::Filtered => Some("HTTP Tunnel scan: Relay reported no response or relay unreachable".to_string()),
+                                    _ => Some("HTTP Tunnel scan: Unexpected relay response".to_string()),
+                                };
+
+                                let mut result = ScanResult::new(port_clone, status);
+                                result.set_reason(reason.clone());
+                                result.scan_type = Some(ScanType::HttpTunnel);
+
+                                result
+                            })
+                        },
+                        ScanType::QuicTunnel => {
+                            techniques::quic_tunnel_scan(
+                                target_ip_clone,
+                                port_clone,
+                                timeout_scan_clone,
+                                quic_relay_clone.as_ref()
+                            ).await.map(|status| {
+                                let reason = match status {
+                                    PortStatus::Open => Some("QUIC Tunnel scan: Relay reported connection accepted".to_string()),
+                                    PortStatus::Closed => Some("QUIC Tunnel scan: Relay reported connection refused".to_string()),
+                                    PortStatus::Filtered => Some("QUIC Tunnel scan: Relay reported no response or relay unreachable".to_string()),
+                                    _ => Some("QUIC Tunnel scan: Unexpected relay response".to_string()),
+                                };
+
+                                let mut result = ScanResult::new(port_clone, status);
+                                result.set_reason(reason.clone());
+                                result.scan_type = Some(ScanType::QuicTunnel);
+
+                                result
+                            })
+                        },
+                        ScanType::BadSum => {
+                            techniques::badsum_scan(
+                                target_ip_clone,
+                                port_clone,
+                                local_ip,
+                                use_ipv6_clone,
+                                timeout_scan_clone
+                            ).await.map(|(status, comparison)| {
+                                let mut result = ScanResult::new(port_clone, status);
+                                result.set_reason(Some(format!("BadSum scan: {}", comparison)));
+                                result.scan_type = Some(ScanType::BadSum);
+
+                                result
+                            })
+                        },
+                        ScanType::IpProto => {
+                            // IP protocol scans are run once per protocol in run_scan
+                            Err(anyhow!("IP protocol scan is not a per-port scan type"))
+                        },
+                        ScanType::Passive => {
+                            Err(anyhow!("Passive observation does not send probes"))
+                        },
+                        ScanType::IcmpTunnel => {
+                            techniques::icmp_tunnel_scan(
+                                target_ip_clone,
+                                port_clone,
+                                local_ip,
+                                use_ipv6_clone,
+                                timeout_scan_clone
+                            ).await.map(|status| {
+                                // Create reason for ICMP tunnel scan
+                                let reason = match status {
+                                    PortStatus::Open => Some("ICMP Tunnel scan: ICMP echo response with valid payload received".to_string()),
+                                    PortStatus::OpenFiltered => Some("ICMP Tunnel scan: Ambiguous ICMP response received".to_string()),
+                                    PortStatus::Filtered => Some("ICMP Tunnel scan: No ICMP response or timeout".to_string()),
+                                    _ => Some("ICMP Tunnel scan: Unexpected response".to_string()),
+                                };
                             
-                            let mut result = ScanResult::new(port_clone, status);
-                            result.set_reason(reason.clone());
+                                let mut result = ScanResult::new(port_clone, status);
+                                result.set_reason(reason.clone());
                             
-                            // For converting to PortResult later
-                            result.scan_type = Some(ScanType::IcmpTunnel);
+                                // For converting to PortResult later
+                                result.scan_type = Some(ScanType::IcmpTunnel);
                             
-                            result
-                        })
-                    },
-                    ScanType::Syn => {
-                        techniques::syn_scan(
-                            target_ip_clone,
-                            port_clone,
-                            local_ip,
-                            use_ipv6_clone,
-                            timeout_scan_clone,
-                            enhanced_evasion_clone,
-                            &mimic_os_clone,
-                            ttl_jitter_clone
-                        ).await.map(|status| {
-                            // Create a reason string based on status for SYN scan
-                            let reason = match status {
-                                PortStatus::Open => Some("SYN scan: SYN-ACK response received, port is listening".to_string()),
-                                PortStatus::Closed => Some("SYN scan: RST response received, port is not listening".to_string()),
-                                PortStatus::Filtered => Some("SYN scan: No response or ICMP error, port is filtered by firewall".to_string()),
-                                _ => None
-                            };
+                                result
+                            })
+                        },
+                        ScanType::Syn => {
+                            techniques::syn_scan(
+                                target_ip_clone,
+                                port_clone,
+                                local_ip,
+                                use_ipv6_clone,
+                                timeout_scan_clone,
+                                enhanced_evasion_clone,
+                                &mimic_os_clone,
+                                ttl_jitter_clone
+                            ).await.map(|status| {
+                                // Create a reason string based on status for SYN scan
+                                let reason = match status {
+                                    PortStatus::Open => Some("SYN scan: SYN-ACK response received, port is listening".to_string()),
+                                    PortStatus::Closed => Some("SYN scan: RST response received, port is not listening".to_string()),
+                                    PortStatus::Filtered => Some("SYN scan: No response or ICMP error, port is filtered by firewall".to_string()),
+                                    _ => None
+                                };
                             
-                            let mut result = ScanResult::new(port_clone, status);
-                            result.set_reason(reason.clone());
+                                let mut result = ScanResult::new(port_clone, status);
+                                result.set_reason(reason.clone());
                             
-                            // For converting to PortResult later
-                            result.scan_type = Some(ScanType::Syn);
+                                // For converting to PortResult later
+                                result.scan_type = Some(ScanType::Syn);
                             
-                            result
-                        })
-                    },
-                    ScanType::Ssl => {
-                        techniques::ssl_scan(
-                            target_ip_clone,
-                            port_clone,
-                            timeout_scan_clone
-                        ).await.map(|(status, cert_info, protocol)| {
-                            // Create reason for SSL scan
-                            let reason = match status {
-                                PortStatus::Open => Some(format!("SSL scan: TLS handshake completed successfully using {}", protocol)),
-                                PortStatus::Closed => Some("SSL scan: TCP connection refused, port is closed".to_string()),
-                                PortStatus::Filtered => Some("SSL scan: TCP connection attempt timed out, port is filtered".to_string()),
-                                PortStatus::OpenFiltered => Some("SSL scan: Connection established but TLS handshake timed out".to_string()),
-                                _ => None
-                            };
+                                result
+                            })
+                        },
+                        ScanType::Ssl => {
+                            techniques::ssl_scan(
+                                target_ip_clone,
+                                port_clone,
+                                timeout_scan_clone
+                            ).await.map(|(status, cert_info, client_auth, protocol)| {
+                                // Create reason for SSL scan
+                                let reason = match status {
+                                    PortStatus::Open => Some(format!("SSL scan: TLS handshake completed successfully using {}", protocol)),
+                                    PortStatus::Closed => Some("SSL scan: TCP connection refused, port is closed".to_string()),
+                                    PortStatus::Filtered => Some("SSL scan: TCP connection attempt timed out, port is filtered".to_string()),
+                                    PortStatus::OpenFiltered => Some("SSL scan: Connection established but TLS handshake timed out".to_string()),
+                                    _ => None
+                                };
                             
-                            let mut result = ScanResult::new(port_clone, status);
-                            result.set_certificate_info(cert_info);
-                            result.set_protocol_version(Some(protocol));
-                            result.set_reason(reason.clone());
+                                let mut result = ScanResult::new(port_clone, status);
+                                result.set_certificate_info(cert_info);
+                                result.set_client_auth(client_auth);
+                                result.set_protocol_version(Some(protocol));
+                                result.set_reason(reason.clone());
                             
-                            // For converting to PortResult later
-                            result.scan_type = Some(ScanType::Ssl);
+                                // For converting to PortResult later
+                                result.scan_type = Some(ScanType::Ssl);
                             
-                            result
-                        })
-                    },
-                    ScanType::Udp => {
-                        techniques::udp_scan(
-                            target_ip_clone,
-                            port_clone,
-                            local_ip,
-                            use_ipv6_clone,
-                            timeout_scan_clone
-                        ).await.map(|status| {
-                            // Create reason for UDP scan
-                            let reason = match status {
-                                PortStatus::Open => Some("UDP scan: Response data received from UDP service".to_string()),
-                                PortStatus::Closed => Some("UDP scan: ICMP port unreachable message received (type 3, code 3)".to_string()),
-                                PortStatus::OpenFiltered => Some("UDP scan: No response within timeout period, port may be open or filtered".to_string()),
-                                PortStatus::Filtered => Some("UDP scan: Other ICMP error message received indicating filtered port".to_string()),
-                                _ => None
-                            };
+                                result
+                            })
+                        },
+                        ScanType::Udp => {
+                            techniques::udp_scan(
+                                target_ip_clone,
+                                port_clone,
+                                local_ip,
+                                use_ipv6_clone,
+                                timeout_scan_clone
+                            ).await.map(|status| {
+                                // Create reason for UDP scan
+                                let reason = match status {
+                                    PortStatus::Open => Some("UDP scan: Response data received from UDP service".to_string()),
+                                    PortStatus::Closed => Some("UDP scan: ICMP port unreachable message received (type 3, code 3)".to_string()),
+                                    PortStatus::OpenFiltered => Some("UDP scan: No response within timeout period, port may be open or filtered".to_string()),
+                                    PortStatus::Filtered => Some("UDP scan: Other ICMP error message received indicating filtered port".to_string()),
+                                    _ => None
+                                };
                             
-                            let mut result = ScanResult::new(port_clone, status);
-                            result.set_reason(reason.clone());
+                                let mut result = ScanResult::new(port_clone, status);
+                                result.set_reason(reason.clone());
                             
-                            // For converting to PortResult later
-                            result.scan_type = Some(ScanType::Udp);
+                                // For converting to PortResult later
+                                result.scan_type = Some(ScanType::Udp);
                             
-                            result
-                        })
-                    },
-                    ScanType::Ack => {
-                        techniques::ack_scan(
-                            target_ip_clone,
-                            port_clone,
-                            local_ip,
-                            use_ipv6_clone,
-                            timeout_scan_clone,
-                            enhanced_evasion_clone,
-                            &mimic_os_clone,
-                            ttl_jitter_clone
-                        ).await.map(|(status, filter_reason)| {
-                            let mut result = ScanResult::new(port_clone, status);
-                            result.set_filter_reason(Some(filter_reason.clone()));
+                                result
+                            })
+                        },
+                        ScanType::Ack => {
+                            techniques::ack_scan(
+                                target_ip_clone,
+                                port_clone,
+                                local_ip,
+                                use_ipv6_clone,
+                                timeout_scan_clone,
+                                enhanced_evasion_clone,
+                                &mimic_os_clone,
+                                ttl_jitter_clone
+                            ).await.map(|(status, filter_reason)| {
+                                let mut result = ScanResult::new(port_clone, status);
+                                result.set_filter_reason(Some(filter_reason.clone()));
                             
-                            // Use filter_reason directly as reason but prefix with scan type
-                            result.set_reason(Some(format!("ACK scan: {}", filter_reason)));
+                                // Use filter_reason directly as reason but prefix with scan type
+                                result.set_reason(Some(format!("ACK scan: {}", filter_reason)));
                             
-                            // For converting to PortResult later
-                            result.scan_type = Some(ScanType::Ack);
+                                // For converting to PortResult later
+                                result.scan_type = Some(ScanType::Ack);
                             
-                            result
-                        })
-                    },
-                    ScanType::Fin => {
-                        techniques::fin_scan(
-                            target_ip_clone,
-                            port_clone,
-                            local_ip,
-                            use_ipv6_clone,
-                            timeout_scan_clone,
-                            enhanced_evasion_clone,
-                            &mimic_os_clone,
-                            ttl_jitter_clone
-                        ).await.map(|status| {
-                            // Create reason for FIN scan
-                            let reason = match status {
-                                PortStatus::Closed => Some("FIN scan: RST response received to FIN packet, RFC-compliant TCP stack indicates closed port".to_string()),
-                                PortStatus::OpenFiltered => Some("FIN scan: No response to FIN packet, RFC-compliant TCP stack indicates open port or filtering".to_string()),
-                                PortStatus::Filtered => Some("FIN scan: ICMP unreachable error received, port is filtered by firewall".to_string()),
-                                _ => None
-                            };
+                                result
+                            })
+                        },
+                        ScanType::Fin => {
+                            techniques::fin_scan(
+                                target_ip_clone,
+                                port_clone,
+                                local_ip,
+                                use_ipv6_clone,
+                                timeout_scan_clone,
+                                enhanced_evasion_clone,
+                                &mimic_os_clone,
+                                ttl_jitter_clone
+                            ).await.map(|status| {
+                                // Create reason for FIN scan
+                                let reason = match status {
+                                    PortStatus::Closed => Some("FIN scan: RST response received to FIN packet, RFC-compliant TCP stack indicates closed port".to_string()),
+                                    PortStatus::OpenFiltered => Some("FIN scan: No response to FIN packet, RFC-compliant TCP stack indicates open port or filtering".to_string()),
+                                    PortStatus::Filtered => Some("FIN scan: ICMP unreachable error received, port is filtered by firewall".to_string()),
+                                    _ => None
+                                };
                             
-                            let mut result = ScanResult::new(port_clone, status);
-                            result.set_reason(reason.clone());
+                                let mut result = ScanResult::new(port_clone, status);
+                                result.set_reason(reason.clone());
                             
-                            // For converting to PortResult later
-                            result.scan_type = Some(ScanType::Fin);
+                                // For converting to PortResult later
+                                result.scan_type = Some(ScanType::Fin);
                             
-                            result
-                        })
-                    },
-                    ScanType::Xmas => {
-                        techniques::xmas_scan(
-                            target_ip_clone,
-                            port_clone,
-                            local_ip,
-                            use_ipv6_clone,
-                            timeout_scan_clone,
-                            enhanced_evasion_clone,
-                            &mimic_os_clone,
-                            ttl_jitter_clone
-                        ).await.map(|status| {
-                            // Create reason for XMAS scan
-                            let reason = match status {
-                                PortStatus::Closed => Some("XMAS scan: RST response received to FIN+PSH+URG packet, RFC-compliant TCP stack indicates closed port".to_string()),
-                                PortStatus::OpenFiltered => Some("XMAS scan: No response to FIN+PSH+URG packet, RFC-compliant TCP stack indicates open port or filtering".to_string()),
-                                PortStatus::Filtered => Some("XMAS scan: ICMP unreachable error received, port is filtered by firewall".to_string()),
-                                _ => None
-                            };
+                                result
+                            })
+                        },
+                        ScanType::Xmas => {
+                            techniques::xmas_scan(
+                                target_ip_clone,
+                                port_clone,
+                                local_ip,
+                                use_ipv6_clone,
+                                timeout_scan_clone,
+                                enhanced_evasion_clone,
+                                &mimic_os_clone,
+                                ttl_jitter_clone
+                            ).await.map(|status| {
+                                // Create reason for XMAS scan
+                                let reason = match status {
+                                    PortStatus::Closed => Some("XMAS scan: RST response received to FIN+PSH+URG packet, RFC-compliant TCP stack indicates closed port".to_string()),
+                                    PortStatus::OpenFiltered => Some("XMAS scan: No response to FIN+PSH+URG packet, RFC-compliant TCP stack indicates open port or filtering".to_string()),
+                                    PortStatus::Filtered => Some("XMAS scan: ICMP unreachable error received, port is filtered by firewall".to_string()),
+                                    _ => None
+                                };
                             
-                            let mut result = ScanResult::new(port_clone, status);
-                            result.set_reason(reason.clone());
+                                let mut result = ScanResult::new(port_clone, status);
+                                result.set_reason(reason.clone());
                             
-                            // For converting to PortResult later
-                            result.scan_type = Some(ScanType::Xmas);
+                                // For converting to PortResult later
+                                result.scan_type = Some(ScanType::Xmas);
                             
-                            result
-                        })
-                    },
-                    ScanType::Null => {
-                        techniques::null_scan(
-                            target_ip_clone,
-                            port_clone,
-                            local_ip,
-                            use_ipv6_clone,
-                            timeout_scan_clone,
-                            enhanced_evasion_clone,
-                            &mimic_os_clone,
-                            ttl_jitter_clone
-                        ).await.map(|status| {
-                            // Create reason for NULL scan
-                            let reason = match status {
-                                PortStatus::Closed => Some("NULL scan: RST response received to packet with no flags set, RFC-compliant TCP stack indicates closed port".to_string()),
-                                PortStatus::OpenFiltered => Some("NULL scan: No response to packet with no flags set, RFC-compliant TCP stack indicates open port or filtering".to_string()),
-                                PortStatus::Filtered => Some("NULL scan: ICMP unreachable error received, port is filtered by firewall".to_string()),
-                                _ => None
-                            };
+                                result
+                            })
+                        },
+                        ScanType::Null => {
+                            techniques::null_scan(
+                                target_ip_clone,
+                                port_clone,
+                                local_ip,
+                                use_ipv6_clone,
+                                timeout_scan_clone,
+                                enhanced_evasion_clone,
+                                &mimic_os_clone,
+                                ttl_jitter_clone
+                            ).await.map(|status| {
+                                // Create reason for NULL scan
+                                let reason = match status {
+                                    PortStatus::Closed => Some("NULL scan: RST response received to packet with no flags set, RFC-compliant TCP stack indicates closed port".to_string()),
+                                    PortStatus::OpenFiltered => Some("NULL scan: No response to packet with no flags set, RFC-compliant TCP stack indicates open port or filtering".to_string()),
+                                    PortStatus::Filtered => Some("NULL scan: ICMP unreachable error received, port is filtered by firewall".to_string()),
+                                    _ => None
+                                };
                             
-                            let mut result = ScanResult::new(port_clone, status);
-                            result.set_reason(reason.clone());
+                                let mut result = ScanResult::new(port_clone, status);
+                                result.set_reason(reason.clone());
                             
-                            // For converting to PortResult later
-                            result.scan_type = Some(ScanType::Null);
+                                // For converting to PortResult later
+                                result.scan_type = Some(ScanType::Null);
                             
-                            result
-                        })
-                    },
-                    ScanType::Window => {
-                        techniques::window_scan(
-                            target_ip_clone,
-                            port_clone,
-                            local_ip,
-                            use_ipv6_clone,
-                            timeout_scan_clone,
-                            enhanced_evasion_clone,
-                            &mimic_os_clone,
-                            ttl_jitter_clone
-                        ).await.map(|status| {
-                            // Create reason for Window scan
-                            let reason = match status {
-                                PortStatus::Open => Some("Window scan: RST response received with non-zero TCP window size, OS fingerprint suggests open port".to_string()),
-                                PortStatus::Closed => Some("Window scan: RST response received with zero TCP window size, OS fingerprint suggests closed port".to_string()),
-                                PortStatus::Filtered => Some("Window scan: No response or ICMP error received, port is filtered by firewall".to_string()),
-                                _ => None
-                            };
+                                result
+                            })
+                        },
+                        ScanType::Window => {
+                            techniques::window_scan(
+                                target_ip_clone,
+                                port_clone,
+                                local_ip,
+                                use_ipv6_clone,
+                                timeout_scan_clone,
+                                enhanced_evasion_clone,
+                                &mimic_os_clone,
+                                ttl_jitter_clone
+                            ).await.map(|status| {
+                                // Create reason for Window scan
+                                let reason = match status {
+                                    PortStatus::Open => Some("Window scan: RST window differs from that of a closed port on this host, stack leaks open state through the window".to_string()),
+                                    PortStatus::Closed => Some("Window scan: RST window matches that of a closed port on this host (zero on Linux/Windows stacks), port is closed or the stack does not leak state".to_string()),
+                                    PortStatus::Filtered => Some("Window scan: No response or ICMP error received, port is filtered by firewall".to_string()),
+                                    _ => None
+                                };
                             
-                            let mut result = ScanResult::new(port_clone, status);
-                            result.set_reason(reason.clone());
+                                let mut result = ScanResult::new(port_clone, status);
+                                result.set_reason(reason.clone());
                             
-                            // For converting to PortResult later
-                            result.scan_type = Some(ScanType::Window);
+                                // For converting to PortResult later
+                                result.scan_type = Some(ScanType::Window);
                             
-                            result
-                        })
-                    },
-                    ScanType::Frag => {
-                        debug!("Using Fragmentation scan with min_size={}, max_size={}", frag_min_size_clone, frag_max_size_clone);
+                                result
+                            })
+                        },
+                        ScanType::Maimon => {
+                            techniques::maimon_scan(
+                                target_ip_clone,
+                                port_clone,
+                                local_ip,
+                                use_ipv6_clone,
+                                timeout_scan_clone,
+                                enhanced_evasion_clone,
+                                &mimic_os_clone,
+                                ttl_jitter_clone
+                            ).await.map(|status| {
+                                // Create reason for Maimon scan
+                                let reason = match status {
+                                    PortStatus::Closed => Some("Maimon scan: RST response received to FIN/ACK packet, port is closed (or the stack resets regardless of state)".to_string()),
+                                    PortStatus::OpenFiltered => Some("Maimon scan: No response to FIN/ACK packet, BSD-derived TCP stack indicates open port or filtering".to_string()),
+                                    PortStatus::Filtered => Some("Maimon scan: ICMP unreachable error received, port is filtered by firewall".to_string()),
+                                    _ => None
+                                };
+                            
+                                let mut result = ScanResult::new(port_clone, status);
+                                result.set_reason(reason.clone());
+                            
+                                // For converting to PortResult later
+                                result.scan_type = Some(ScanType::Maimon);
+                            
+                                result
+                            })
+                        },
+                        ScanType::Frag => {
+                            debug!("Using Fragmentation scan with min_size={}, max_size={}", frag_min_size_clone, frag_max_size_clone);
                         
-                        techniques::frag_scan(
-                            target_ip_clone,
-                            port_clone,
-                            local_ip,
-                            use_ipv6_clone,
-                            timeout_scan_clone,
-                            enhanced_evasion_clone,
-                            &mimic_os_clone,
-                            ttl_jitter_clone
-                        ).await.map(|status| {
-                            // Create reason for fragmented scan
-                            let reason = match status {
-                                PortStatus::Open => Some("Fragmentation scan: SYN-ACK response received after fragmented SYN packet, port is open and reassembly succeeded".to_string()),
-                                PortStatus::Closed => Some("Fragmentation scan: RST response received after fragmented SYN packet, port is closed and reassembly succeeded".to_string()),
-                                PortStatus::Filtered => Some("Fragmentation scan: No response after fragmented SYN packet, port is filtered or fragments were blocked/dropped".to_string()),
-                                _ => None
-                            };
+                            techniques::frag_scan(
+                                target_ip_clone,
+                                port_clone,
+                                local_ip,
+                                use_ipv6_clone,
+                                timeout_scan_clone,
+                                enhanced_evasion_clone,
+                                &mimic_os_clone,
+                                ttl_jitter_clone
+                            ).await.map(|status| {
+                                // Create reason for fragmented scan
+                                let reason = match status {
+                                    PortStatus::Open => Some("Fragmentation scan: SYN-ACK response received after fragmented SYN packet, port is open and reassembly succeeded".to_string()),
+                                    PortStatus::Closed => Some("Fragmentation scan: RST response received after fragmented SYN packet, port is closed and reassembly succeeded".to_string()),
+                                    PortStatus::Filtered => Some("Fragmentation scan: No response after fragmented SYN packet, port is filtered or fragments were blocked/dropped".to_string()),
+                                    _ => None
+                                };
                             
-                            let mut result = ScanResult::new(port_clone, status);
-                            result.set_reason(reason.clone());
+                                let mut result = ScanResult::new(port_clone, status);
+                                result.set_reason(reason.clone());
                             
-                            // For converting to PortResult later
-                            result.scan_type = Some(ScanType::Frag);
+                                // For converting to PortResult later
+                                result.scan_type = Some(ScanType::Frag);
                             
-                            result
-                        })
-                    },
-                    ScanType::Mimic => {
-                        let payload_bytes = MimicPayloads::get(&mimic_protocol_clone);
-                        techniques::mimic_scan_with_payload(
-                            target_ip_clone,
-                            port_clone,
-                            local_ip,
-                            use_ipv6_clone,
-                            _evasion_clone,
-                            &mimic_protocol_clone,
-                            payload_bytes.to_vec(),
-                            timeout_scan_clone
-                        ).await.map(|status| {
-                            // Create reason for mimic scan
-                            let reason = match status {
-                                PortStatus::Open => Some(format!("Mimic scan: Response received after sending {} protocol payload, service identified", mimic_protocol_clone)),
-                                PortStatus::Closed => Some(format!("Mimic scan: Connection refused when sending {} protocol payload, port is closed", mimic_protocol_clone)),
-                                PortStatus::Filtered => Some(format!("Mimic scan: No response after sending {} protocol payload, port filtered or wrong service type", mimic_protocol_clone)),
-                                PortStatus::OpenFiltered => Some(format!("Mimic scan: Ambiguous response to {} protocol payload, could be filtered or incorrect service", mimic_protocol_clone)),
-                                _ => None
-                            };
+                                result
+                            })
+                        },
+                        ScanType::Mimic => {
+                            let payload_bytes = MimicPayloads::get(&mimic_protocol_clone);
+                            techniques::mimic_scan_with_payload(
+                                target_ip_clone,
+                                port_clone,
+                                local_ip,
+                                use_ipv6_clone,
+                                _evasion_clone,
+                                &mimic_protocol_clone,
+                                payload_bytes.to_vec(),
+                                timeout_scan_clone
+                            ).await.map(|status| {
+                                // Create reason for mimic scan
+                                let reason = match status {
+                                    PortStatus::Open => Some(format!("Mimic scan: Response received after sending {} protocol payload, service identified", mimic_protocol_clone)),
+                                    PortStatus::Closed => Some(format!("Mimic scan: Connection refused when sending {} protocol payload, port is closed", mimic_protocol_clone)),
+                                    PortStatus::Filtered => Some(format!("Mimic scan: No response after sending {} protocol payload, port filtered or wrong service type", mimic_protocol_clone)),
+                                    PortStatus::OpenFiltered => Some(format!("Mimic scan: Ambiguous response to {} protocol payload, could be filtered or incorrect service", mimic_protocol_clone)),
+                                    _ => None
+                                };
                             
-                            let mut result = ScanResult::new(port_clone, status);
-                            result.set_reason(reason.clone());
+                                let mut result = ScanResult::new(port_clone, status);
+                                result.set_reason(reason.clone());
                             
-                            // For converting to PortResult later
-                            result.scan_type = Some(ScanType::Mimic);
+                                // For converting to PortResult later
+                                result.scan_type = Some(ScanType::Mimic);
                             
-                            result
-                        })
+                                result
+                            })
+                        }
+                    };
+                    let unanswered = matches!(&result, Ok(r) if matches!(r.status, PortStatus::Filtered | PortStatus::OpenFiltered));
+                    if unanswered && attempt < probe_retries && !crate::scheduler::deadline_passed() {
+                        attempt += 1;
+                        debug!("No reply from port {} to {:?} probe, retransmitting ({}/{})", port_clone, scan_type_clone, attempt, probe_retries);
+                        continue;
                     }
+                    break result;
                 };
                 
                 // Process the scan result
@@ -1372,6 +2724,10 @@ impl QuantumScanner {
                             port_result.cert_info = scan_result.certificate_info.clone();
                         }
                         
+                        if scan_result.client_auth.is_some() {
+                            port_result.client_auth = scan_result.client_auth.clone();
+                        }
+                        
                         // Copy protocol version if available and not already set
                         if scan_result.tls_protocol_version.is_some() && port_result.tls_protocol_version.is_none() {
                             port_result.tls_protocol_version = scan_result.tls_protocol_version.clone();
@@ -1384,6 +2740,9 @@ impl QuantumScanner {
                                     info!("Adding port {} to open ports list (Open from {:?})", port_clone, scan_type_clone);
                                 }
                                 open_ports_set_clone.lock().await.insert(port_clone);
+                                if let Some(stage) = &banner_stage_clone {
+                                    stage.submit(port_clone);
+                                }
                                 // Mark as definitely open in final_status
                                 port_result.final_status = PortStatus::Open;
                             },
@@ -1394,6 +2753,9 @@ impl QuantumScanner {
                                         info!("Adding port {} to open ports list (OpenFiltered from {:?})", port_clone, scan_type_clone);
                                     }
                                     open_ports_set_clone.lock().await.insert(port_clone);
+                                    if let Some(stage) = &banner_stage_clone {
+                                        stage.submit(port_clone);
+                                    }
                                     port_result.final_status = PortStatus::OpenFiltered;
                                 }
                             },
@@ -1425,7 +2787,9 @@ impl QuantumScanner {
                     
                     // Don't modify tcp_states or udp_state here
                 }
-            });
+
+                crate::progress::probe_done();
+            })));
             
             tasks.push(task);
         }
@@ -1542,7 +2906,7 @@ impl QuantumScanner {
         let probe_task = tokio::spawn(async move {
             let target_addr = SocketAddr::new(target_ip, port);
             debug!("[Probe Task Port {}] Sending TCP SYN probe to {}", port, target_addr);
-            match TcpStream::connect(target_addr).await {
+            match crate::netbind::connect_tcp(target_addr).await {
                 Ok(_) => info!("[Probe Task Port {}] TCP connection successful (SYN-ACK received).", port),
                 Err(e) => warn!("[Probe Task Port {}] TCP connection attempt failed: {}", port, e),
             }
@@ -1679,3 +3043,116 @@ impl QuantumScanner {
         Ok(())
     }
 }
+
+/// How much a technique's verdict counts toward the merged state of a port
+///
+/// Handshake techniques see the port answer; the RFC 793 flag probes infer the state
+/// from silence, which firewalls and non-conforming stacks (Windows) also produce; a
+/// reply to a bad checksum comes from a middlebox, not the host.
+fn technique_weight(scan_type: ScanType) -> f64 {
+    match scan_type {
+        ScanType::Syn | ScanType::Ssl | ScanType::Frag => 1.0,
+        ScanType::Mimic | ScanType::Passive => 0.8,
+        ScanType::Window => 0.5,
+        ScanType::Ack | ScanType::DnsTunnel | ScanType::IcmpTunnel | ScanType::HttpTunnel | ScanType::QuicTunnel => 0.5,
+        ScanType::Fin | ScanType::Null | ScanType::Xmas | ScanType::Maimon => 0.3,
+        ScanType::IpProto | ScanType::BadSum => 0.2,
+        ScanType::Udp => 0.8,
+    }
+}
+
+/// Merge the per-technique states of a port into one state with a confidence
+///
+/// Each verdict adds its technique's weight to the states it supports: open, closed or
+/// filtered in full, open|filtered half to open and half to filtered, unfiltered half to
+/// open and half to closed. A banner counts as a full-weight open verdict. The state
+/// with the most weight wins, open before closed before filtered on a tie; a winner
+/// that no verdict named outright is reported as the ambiguous state that carried it
+/// (open|filtered, or unfiltered when only ACK-style verdicts reached the port).
+///
+/// # Arguments
+/// * `result` - Port result with its TCP states, UDP state and banner
+///
+/// # Returns
+/// * `Option<StateConsensus>` - `None` when no technique reported on the port
+fn port_consensus(result: &PortResult) -> Option<StateConsensus> {
+    let mut verdicts: Vec<(String, PortStatus, f64, Option<&str>)> = result.tcp_states.iter()
+        .map(|(&scan_type, &status)| (scan_type.to_string(), status, technique_weight(scan_type), result.tcp_reasons.get(&scan_type).map(String::as_str)))
+        .collect();
+    if let Some(status) = result.udp_state {
+        verdicts.push((ScanType::Udp.to_string(), status, technique_weight(ScanType::Udp), None));
+    }
+    if result.banner.is_some() {
+        verdicts.push(("BANNER".to_string(), PortStatus::Open, 1.0, Some("service sent data")));
+    }
+    if verdicts.is_empty() {
+        return None;
+    }
+    verdicts.sort_by(|a, b| a.0.cmp(&b.0));
+
+    // Weight for open, closed and filtered, and whether any verdict named each outright
+    let mut scores = [0.0f64; 3];
+    let mut named = [false; 3];
+    for (_, status, weight, _) in &verdicts {
+        match status {
+            PortStatus::Open => { scores[0] += weight; named[0] = true; }
+            PortStatus::Closed => { scores[1] += weight; named[1] = true; }
+            PortStatus::Filtered => { scores[2] += weight; named[2] = true; }
+            PortStatus::OpenFiltered => { scores[0] += weight / 2.0; scores[2] += weight / 2.0; }
+            PortStatus::Unfiltered => { scores[0] += weight / 2.0; scores[1] += weight / 2.0; }
+        }
+    }
+    let total: f64 = verdicts.iter().map(|v| v.2).sum();
+    let winner = (0..3).fold(0, |best, i| if scores[i] > scores[best] { i } else { best });
+    let open_filtered = verdicts.iter().any(|v| v.1 == PortStatus::OpenFiltered);
+    let state = match (winner, named[winner]) {
+        (0, true) => PortStatus::Open,
+        (1, true) => PortStatus::Closed,
+        (2, true) => PortStatus::Filtered,
+        (0, false) if !open_filtered => PortStatus::Unfiltered,
+        (1, false) => PortStatus::Unfiltered,
+        _ => PortStatus::OpenFiltered,
+    };
+
+    let supports = |status: PortStatus| match (winner, status) {
+        (0, PortStatus::Open | PortStatus::OpenFiltered | PortStatus::Unfiltered) => true,
+        (1, PortStatus::Closed | PortStatus::Unfiltered) => true,
+        (2, PortStatus::Filtered | PortStatus::OpenFiltered) => true,
+        _ => false,
+    };
+    let evidence = verdicts.iter()
+        .map(|(technique, status, weight, reason)| match reason {
+            Some(reason) => format!("{}: {} - {} (weight {:.1})", technique, status, reason, weight),
+            None => format!("{}: {} (weight {:.1})", technique, status, weight),
+        })
+        .collect();
+    let dissenting = verdicts.iter()
+        .filter(|(_, status, _, _)| !supports(*status))
+        .map(|(technique, _, _, _)| technique.clone())
+        .collect();
+    Some(StateConsensus {
+        state,
+        confidence: ((scores[winner] / total) * 100.0).round() / 100.0,
+        evidence,
+        dissenting,
+    })
+}
+
+/// Add `details` to a port's service details, keeping what is already there
+fn merge_service_details(port_result: &mut PortResult, details: serde_json::Value) {
+    match (port_result.service_details.as_mut().and_then(|existing| existing.as_object_mut()), details) {
+        (Some(existing), serde_json::Value::Object(found)) => existing.extend(found),
+        (None, details) => port_result.service_details = Some(details),
+        _ => {}
+    }
+}
+
+/// Leave out an optional phase once `--max-scan-time` is winding down, noting it in the results
+fn skip_optional_phase(phase: &str, skipped_phases: &mut Vec<String>) -> bool {
+    if !crate::scheduler::winding_down() {
+        return false;
+    }
+    info!("Skipping {} to stay within --max-scan-time", phase);
+    skipped_phases.push(phase.to_string());
+    true
+}
@@ -0,0 +1,411 @@
+//! Probe scheduling with selectable inter-probe timing distributions.
+//!
+//! `--timing-model` spaces out probe launches across the whole scan, not per task: each
+//! probe reserves the next send slot from a shared clock and the gap to the following
+//! slot is drawn from the model. With many concurrent workers the wire still sees one
+//! stream whose gaps follow the distribution, instead of bursts of `concurrency` probes.
+//!
+//! `--max-bandwidth` paces on bytes instead. Every send path (raw probes, connect
+//! handshakes, banner and TLS payloads, UDP probes) charges its estimated wire size to
+//! `spend`, which reserves the matching share of the budget and sleeps until it is due.
+//! Sizes count IP and transport headers but not link-layer framing, so the budget holds at
+//! the IP layer: after `t` seconds at most `rate * t` plus one send (a packet, payload or
+//! TLS flight) has gone out.
+//!
+//! `--max-scan-time` bounds the whole run. Its last fifth is a wind-down window in which
+//! only probes of ports already known to be open and service identification still run,
+//! and probes left unsent are reported as skipped instead of the scan being killed.
+
+use std::fmt;
+use std::str::FromStr;
+use std::sync::atomic::{AtomicBool, AtomicU64, Ordering};
+use std::time::Duration;
+
+use anyhow::{anyhow, Result};
+use chrono::{Datelike, Local, Timelike, Weekday};
+use lazy_static::lazy_static;
+use log::debug;
+use parking_lot::{Mutex, RwLock};
+use rand::Rng;
+use tokio::time::Instant;
+
+/// Longest single gap any model will produce
+const MAX_GAP: Duration = Duration::from_secs(60);
+
+/// IPv4 + TCP header without options, charged on top of each payload segment
+pub const TCP_HEADER_BYTES: usize = 40;
+/// IPv4 + UDP header, charged on top of each datagram
+pub const UDP_HEADER_BYTES: usize = 28;
+/// SYN (with options), ACK, FIN and final ACK of one full TCP connection
+pub const TCP_SESSION_BYTES: usize = 60 + 3 * TCP_HEADER_BYTES;
+
+/// Inter-probe delay model
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum TimingModel {
+    /// Uniform between `min` and `max` milliseconds
+    Uniform { min: u64, max: u64 },
+    /// Poisson arrivals at `rate` probes per second (exponential gaps)
+    Poisson { rate: f64 },
+    /// Log-normal gaps with the given median (ms) and shape; long tail like human activity
+    LogNormal { median: f64, sigma: f64 },
+    /// Poisson arrivals whose mean gap (ms, during working hours) stretches outside local
+    /// business hours, over lunch and at weekends
+    Diurnal { mean: f64 },
+}
+
+impl TimingModel {
+    /// Draw one gap
+    pub fn sample(&self) -> Duration {
+        let mut rng = crate::utils::scan_rng();
+        let ms = match *self {
+            TimingModel::Uniform { min, max } => rng.gen_range(min..=max.max(min)) as f64,
+            TimingModel::Poisson { rate } => exponential(&mut rng, 1000.0 / rate),
+            TimingModel::LogNormal { median, sigma } => {
+                // Box-Muller standard normal
+                let u1: f64 = rng.gen_range(f64::EPSILON..1.0);
+                let u2: f64 = rng.gen();
+                let z = (-2.0 * u1.ln()).sqrt() * (2.0 * std::f64::consts::PI * u2).cos();
+                median * (sigma * z).exp()
+            }
+            TimingModel::Diurnal { mean } => exponential(&mut rng, mean * business_hours_factor()),
+        };
+        Duration::from_millis(ms.max(0.0) as u64).min(MAX_GAP)
+    }
+}
+
+fn exponential(rng: &mut impl Rng, mean: f64) -> f64 {
+    let u: f64 = rng.gen_range(f64::EPSILON..1.0);
+    -mean * u.ln()
+}
+
+/// How much slower than peak the scan should run at the current local time
+fn business_hours_factor() -> f64 {
+    let now = Local::now();
+    if matches!(now.weekday(), Weekday::Sat | Weekday::Sun) {
+        return 10.0;
+    }
+    match now.hour() {
+        9..=11 | 13..=16 => 1.0,
+        12 => 2.5,
+        8 | 17 | 18 => 3.0,
+        _ => 20.0,
+    }
+}
+
+impl fmt::Display for TimingModel {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            TimingModel::Uniform { min, max } => write!(f, "uniform:{}-{}", min, max),
+            TimingModel::Poisson { rate } => write!(f, "poisson:{}", rate),
+            TimingModel::LogNormal { median, sigma } => write!(f, "lognormal:{}:{}", median, sigma),
+            TimingModel::Diurnal { mean } => write!(f, "diurnal:{}", mean),
+        }
+    }
+}
+
+impl FromStr for TimingModel {
+    type Err = anyhow::Error;
+
+    /// Parse `uniform[:min-max]`, `poisson[:rate]`, `lognormal[:median[:sigma]]` or
+    /// `diurnal[:mean]` (times in milliseconds, rate in probes per second)
+    fn from_str(s: &str) -> Result<Self> {
+        let s = s.to_lowercase();
+        let mut parts = s.split(':');
+        let name = parts.next().unwrap_or("");
+        let args: Vec<&str> = parts.collect();
+        let num = |index: usize, default: f64| -> Result<f64> {
+            match args.get(index) {
+                Some(value) => value.parse::<f64>().ok().filter(|v| *v > 0.0)
+                    .ok_or_else(|| anyhow!("Invalid parameter '{}' in timing model '{}'", value, s)),
+                None => Ok(default),
+            }
+        };
+
+        match name {
+            "uniform" | "random" => match args.first() {
+                Some(range) => {
+                    let (min, max) = range.split_once('-')
+                        .and_then(|(min, max)| Some((min.parse().ok()?, max.parse().ok()?)))
+                        .filter(|(min, max): &(u64, u64)| min <= max)
+                        .ok_or_else(|| anyhow!("Invalid uniform range '{}': expected <min>-<max> in ms", range))?;
+                    Ok(TimingModel::Uniform { min, max })
+                }
+                None => Ok(TimingModel::Uniform { min: 50, max: 500 }),
+            },
+            "poisson" => Ok(TimingModel::Poisson { rate: num(0, 4.0)? }),
+            // Exponential gaps are Poisson arrivals; the argument is the mean gap in ms
+            "exponential" | "exp" => Ok(TimingModel::Poisson { rate: 1000.0 / num(0, 250.0)? }),
+            "lognormal" | "log-normal" => Ok(TimingModel::LogNormal { median: num(0, 200.0)?, sigma: num(1, 0.8)? }),
+            "diurnal" | "business-hours" => Ok(TimingModel::Diurnal { mean: num(0, 300.0)? }),
+            other => Err(anyhow!("Unknown timing model '{}': expected uniform, poisson, lognormal or diurnal", other)),
+        }
+    }
+}
+
+/// Hands out send slots spaced according to a timing model
+pub struct ProbeScheduler {
+    model: TimingModel,
+    next_slot: Mutex<Option<Instant>>,
+}
+
+impl ProbeScheduler {
+    pub fn new(model: TimingModel) -> Self {
+        ProbeScheduler { model, next_slot: Mutex::new(None) }
+    }
+
+    /// Reserve the next send slot and sleep until it arrives
+    pub async fn wait_turn(&self) {
+        let slot = {
+            let mut next = self.next_slot.lock();
+            let now = Instant::now();
+            let slot = next.map_or(now, |n| n.max(now));
+            *next = Some(slot + self.model.sample());
+            slot
+        };
+        if slot > Instant::now() {
+            tokio::time::sleep_until(slot).await;
+        }
+    }
+}
+
+lazy_static! {
+    /// Scan-wide scheduler set by `--timing-model`; `None` sends as fast as the rate limit allows
+    static ref SCHEDULER: RwLock<Option<std::sync::Arc<ProbeScheduler>>> = RwLock::new(None);
+}
+
+/// Install the scan-wide timing model
+pub fn set_timing_model(model: TimingModel) {
+    debug!("[Scheduler] Timing model {}", model);
+    *SCHEDULER.write() = Some(std::sync::Arc::new(ProbeScheduler::new(model)));
+}
+
+/// Wait for this probe's send slot (returns immediately when no model is configured)
+pub async fn wait_turn() {
+    let scheduler = SCHEDULER.read().clone();
+    if let Some(scheduler) = scheduler {
+        scheduler.wait_turn().await;
+    }
+}
+
+/// Bandwidth cap for `--max-bandwidth`, in bits per second
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Bandwidth(pub u64);
+
+impl Bandwidth {
+    pub fn bytes_per_sec(&self) -> f64 {
+        self.0 as f64 / 8.0
+    }
+}
+
+impl fmt::Display for Bandwidth {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self.0 {
+            bps if bps >= 1_000_000_000 && bps % 1_000_000_000 == 0 => write!(f, "{}gbps", bps / 1_000_000_000),
+            bps if bps >= 1_000_000 && bps % 1_000_000 == 0 => write!(f, "{}mbps", bps / 1_000_000),
+            bps if bps >= 1_000 && bps % 1_000 == 0 => write!(f, "{}kbps", bps / 1_000),
+            bps => write!(f, "{}bps", bps),
+        }
+    }
+}
+
+impl FromStr for Bandwidth {
+    type Err = anyhow::Error;
+
+    /// Parse a rate such as `5mbps`, `512kbps`, `1.5gbps` or `800bps` (bits per second,
+    /// decimal prefixes); an upper-case `B` means bytes, as in `2MB/s` or `256KBps`
+    fn from_str(s: &str) -> Result<Self> {
+        let s = s.trim();
+        let split = s.find(|c: char| !(c.is_ascii_digit() || c == '.')).unwrap_or(s.len());
+        let (number, unit) = s.split_at(split);
+        let value: f64 = number.parse().ok().filter(|v: &f64| *v > 0.0)
+            .ok_or_else(|| anyhow!("Invalid bandwidth '{}': expected e.g. 5mbps or 512kbps", s))?;
+        let unit = unit.trim();
+        let prefix = match unit.to_lowercase().as_str() {
+            "" | "bps" | "b/s" | "bit/s" => 1.0,
+            "k" | "kbps" | "kb/s" | "kbit/s" => 1e3,
+            "m" | "mbps" | "mb/s" | "mbit/s" => 1e6,
+            "g" | "gbps" | "gb/s" | "gbit/s" => 1e9,
+            _ => return Err(anyhow!("Unknown bandwidth unit '{}' in '{}': use bps, kbps, mbps, gbps or KB/s, MB/s", unit, s)),
+        };
+        let multiplier = if unit.contains('B') { prefix * 8.0 } else { prefix };
+        let bits = (value * multiplier).round() as u64;
+        if bits < 8 {
+            return Err(anyhow!("Bandwidth '{}' is below one byte per second", s));
+        }
+        Ok(Bandwidth(bits))
+    }
+}
+
+/// Paces bytes to a fixed rate by reserving wire time, like `ProbeScheduler` does for probes
+struct BandwidthBudget {
+    bytes_per_sec: f64,
+    next_free: Mutex<Option<Instant>>,
+}
+
+impl BandwidthBudget {
+    /// Reserve wire time for `bytes` and return when sending may start
+    fn reserve(&self, bytes: usize) -> Instant {
+        let mut next = self.next_free.lock();
+        let now = Instant::now();
+        let slot = next.map_or(now, |n| n.max(now));
+        *next = Some(slot + Duration::from_secs_f64(bytes as f64 / self.bytes_per_sec));
+        slot
+    }
+}
+
+lazy_static! {
+    /// Scan-wide budget set by `--max-bandwidth`; `None` only counts
+    static ref BANDWIDTH: RwLock<Option<std::sync::Arc<BandwidthBudget>>> = RwLock::new(None);
+}
+
+/// Bytes charged through `spend` since the process started
+static BYTES_SENT: AtomicU64 = AtomicU64::new(0);
+
+/// Set by `halt`: nothing is sent any more
+static HALTED: AtomicBool = AtomicBool::new(false);
+
+/// Stop every send path for good
+///
+/// Senders that reach `spend` or `spend_blocking` afterwards wait there forever, so a
+/// run aborted by `--abort-on-countermeasure` puts nothing more on the wire while it
+/// cleans up and exits.
+pub fn halt() {
+    HALTED.store(true, Ordering::Relaxed);
+}
+
+/// Install the scan-wide bandwidth budget
+pub fn set_bandwidth_limit(limit: Bandwidth) {
+    debug!("[Scheduler] Bandwidth budget {} ({:.0} bytes/s)", limit, limit.bytes_per_sec());
+    *BANDWIDTH.write() = Some(std::sync::Arc::new(BandwidthBudget {
+        bytes_per_sec: limit.bytes_per_sec(),
+        next_free: Mutex::new(None),
+    }));
+}
+
+/// Configured bandwidth budget in bytes per second, if any
+pub fn bandwidth_limit() -> Option<f64> {
+    BANDWIDTH.read().as_ref().map(|budget| budget.bytes_per_sec)
+}
+
+/// Charge `bytes` about to be sent and wait until the budget allows them
+///
+/// Always counts toward `bytes_sent`, with or without a budget.
+pub async fn spend(bytes: usize) {
+    if HALTED.load(Ordering::Relaxed) {
+        std::future::pending::<()>().await;
+    }
+    BYTES_SENT.fetch_add(bytes as u64, Ordering::Relaxed);
+    let budget = BANDWIDTH.read().clone();
+    if let Some(budget) = budget {
+        let slot = budget.reserve(bytes);
+        if slot > Instant::now() {
+            tokio::time::sleep_until(slot).await;
+        }
+    }
+}
+
+/// `spend` for code running on a blocking thread
+pub fn spend_blocking(bytes: usize) {
+    while HALTED.load(Ordering::Relaxed) {
+        std::thread::park();
+    }
+    BYTES_SENT.fetch_add(bytes as u64, Ordering::Relaxed);
+    let budget = BANDWIDTH.read().clone();
+    if let Some(budget) = budget {
+        let slot = budget.reserve(bytes);
+        let now = Instant::now();
+        if slot > now {
+            std::thread::sleep(slot - now);
+        }
+    }
+}
+
+/// Estimated bytes sent so far, IP headers included
+pub fn bytes_sent() -> u64 {
+    BYTES_SENT.load(Ordering::Relaxed)
+}
+
+/// Write a TCP payload, charging it to the bandwidth budget first
+pub async fn metered_write(stream: &mut tokio::net::TcpStream, data: &[u8]) -> std::io::Result<()> {
+    use tokio::io::AsyncWriteExt;
+    spend(data.len() + TCP_HEADER_BYTES).await;
+    if let Ok(peer) = stream.peer_addr() {
+        crate::audit::record(|| crate::audit::Probe::new("tcp-payload", "tcp", peer.ip(), Some(peer.port()), data.len() + TCP_HEADER_BYTES));
+    }
+    stream.write_all(data).await
+}
+
+/// Share of `--max-scan-time` kept back for confirming open ports and identifying services
+const DEADLINE_RESERVE: f64 = 0.2;
+
+/// Scan-wide time budget set by `--max-scan-time`
+struct ScanDeadline {
+    /// From here on only high-value probes are sent
+    wind_down: Instant,
+    /// From here on nothing new is sent
+    hard: Instant,
+}
+
+lazy_static! {
+    static ref DEADLINE: RwLock<Option<ScanDeadline>> = RwLock::new(None);
+}
+
+/// Start the scan-wide time budget now
+///
+/// The last fifth of the budget is the wind-down window: discovery probes on ports not yet
+/// known to be open stop, so the remaining time goes to confirming open ports and
+/// identifying their services. Once the budget is spent no new probe is started.
+pub fn set_deadline(budget: Duration) {
+    let now = Instant::now();
+    let hard = now + budget;
+    let wind_down = hard - budget.mul_f64(DEADLINE_RESERVE);
+    debug!("[Scheduler] Time budget {:?}, wind-down after {:?}", budget, wind_down - now);
+    *DEADLINE.write() = Some(ScanDeadline { wind_down, hard });
+}
+
+/// Whether the time budget has entered its wind-down window (or is spent)
+pub fn winding_down() -> bool {
+    DEADLINE.read().as_ref().map_or(false, |d| Instant::now() >= d.wind_down)
+}
+
+/// Whether the time budget is spent
+pub fn deadline_passed() -> bool {
+    DEADLINE.read().as_ref().map_or(false, |d| Instant::now() >= d.hard)
+}
+
+/// Cap a phase timeout so waiting for it cannot run past the time budget
+pub fn within_deadline(phase_timeout: Duration) -> Duration {
+    match DEADLINE.read().as_ref() {
+        Some(d) => phase_timeout.min(d.hard.saturating_duration_since(Instant::now())),
+        None => phase_timeout,
+    }
+}
+
+/// Parse a duration such as `90s`, `30m`, `2h` or `1h30m` (a bare number is seconds)
+pub fn parse_duration(s: &str) -> Result<Duration> {
+    let s = s.trim().to_lowercase();
+    if let Ok(secs) = s.parse::<u64>() {
+        return Ok(Duration::from_secs(secs));
+    }
+    let mut total = 0u64;
+    let mut number = String::new();
+    for c in s.chars() {
+        if c.is_ascii_digit() {
+            number.push(c);
+            continue;
+        }
+        let value: u64 = number.parse().map_err(|_| anyhow!("Invalid duration '{}': expected e.g. 90s, 30m or 1h30m", s))?;
+        total += value * match c {
+            's' => 1,
+            'm' => 60,
+            'h' => 3600,
+            'd' => 86400,
+            other => return Err(anyhow!("Unknown unit '{}' in duration '{}': use s, m, h or d", other, s)),
+        };
+        number.clear();
+    }
+    if !number.is_empty() || total == 0 {
+        return Err(anyhow!("Invalid duration '{}': expected e.g. 90s, 30m or 1h30m", s));
+    }
+    Ok(Duration::from_secs(total))
+}
@@ -0,0 +1,166 @@
+//! Versioned loading of saved scan results.
+//!
+//! Every `ScanResults` written with `-j` carries a `schema_version`. Files from before the
+//! field existed count as version 0. Loading a file runs the migrations from its version up
+//! to `SCHEMA_VERSION` on the raw JSON before it is deserialized, so the `query`
+//! subcommand keeps reading old files as the format grows. `quantum_scanner convert`
+//! writes the upgraded form back out.
+//!
+//! A change that only adds an optional or `#[serde(default)]` field needs no migration.
+//! Renaming, removing or restructuring a field bumps `SCHEMA_VERSION` and adds a step
+//! to `MIGRATIONS`.
+
+use std::path::PathBuf;
+
+use anyhow::{anyhow, Context, Result};
+use serde_json::{json, Map, Value};
+
+use crate::models::{ScanResults, SCHEMA_VERSION};
+
+/// Migration from version `n` to `n + 1` is `MIGRATIONS[n]`
+const MIGRATIONS: [fn(&mut Map<String, Value>); SCHEMA_VERSION as usize] = [migrate_v0, migrate_v1];
+
+/// Version 0 to 1: fill in collections and the port status that early files left out
+fn migrate_v0(host: &mut Map<String, Value>) {
+    for key in ["open_ports", "scan_types"] {
+        host.entry(key).or_insert_with(|| json!([]));
+    }
+    host.entry("results").or_insert_with(|| json!({}));
+    for key in ["packets_sent", "successful_scans"] {
+        host.entry(key).or_insert_with(|| json!(0));
+    }
+
+    let Some(Value::Object(ports)) = host.get_mut("results") else { return };
+    for port in ports.values_mut() {
+        let Value::Object(port) = port else { continue };
+        for key in ["tcp_states", "tcp_reasons"] {
+            port.entry(key).or_insert_with(|| json!({}));
+        }
+        for key in ["vulns", "anomalies"] {
+            port.entry(key).or_insert_with(|| json!([]));
+        }
+        if !port.contains_key("final_status") {
+            // The best state any technique saw, as the scanner ranks them
+            let states: Vec<String> = port.get("tcp_states")
+                .and_then(Value::as_object)
+                .map(|states| states.values().filter_map(Value::as_str).map(str::to_string).collect())
+                .unwrap_or_default();
+            let udp = port.get("udp_state").and_then(Value::as_str).map(str::to_string);
+            let status = ["Open", "OpenFiltered", "Unfiltered", "Closed"].into_iter()
+                .find(|s| states.iter().any(|t| t == s) || udp.as_deref() == Some(*s))
+                .unwrap_or("Filtered");
+            port.insert("final_status".to_string(), json!(status));
+        }
+    }
+}
+
+/// Version 1 to 2: anomalies become findings, and vulnerabilities get a title
+fn migrate_v1(host: &mut Map<String, Value>) {
+    let Some(Value::Object(ports)) = host.get_mut("results") else { return };
+    for port in ports.values_mut() {
+        let Value::Object(port) = port else { continue };
+        if let Some(Value::Array(vulns)) = port.get_mut("vulns") {
+            for vuln in vulns.iter_mut() {
+                let Value::Object(vuln) = vuln else { continue };
+                if !vuln.contains_key("title") {
+                    let id = vuln.get("id").cloned().unwrap_or_else(|| json!(""));
+                    vuln.insert("title".to_string(), id);
+                }
+            }
+        }
+        if let Some(Value::Array(anomalies)) = port.get_mut("anomalies") {
+            for anomaly in anomalies.iter_mut() {
+                if let Value::String(text) = anomaly {
+                    *anomaly = serde_json::to_value(crate::models::Finding::anomaly(text.as_str())).unwrap_or(Value::Null);
+                }
+            }
+        }
+    }
+}
+
+/// Bring one host's JSON up to the current schema
+fn upgrade_host(mut host: Value) -> Result<ScanResults> {
+    let Value::Object(map) = &mut host else {
+        return Err(anyhow!("expected a JSON object for each host"));
+    };
+    let version = map.get("schema_version").and_then(Value::as_u64).unwrap_or(0);
+    if version > SCHEMA_VERSION as u64 {
+        return Err(anyhow!("written with schema version {}, newer than the {} this build reads; upgrade quantum_scanner",
+                           version, SCHEMA_VERSION));
+    }
+    for migrate in &MIGRATIONS[version as usize..] {
+        migrate(map);
+    }
+    map.insert("schema_version".to_string(), json!(SCHEMA_VERSION));
+    Ok(serde_json::from_value(host)?)
+}
+
+/// Parse saved results of any schema version
+///
+/// # Arguments
+/// * `data` - JSON written with `-j`: a single host or an array of hosts
+///
+/// # Returns
+/// * `Result<(Vec<ScanResults>, u32)>` - Hosts upgraded to `SCHEMA_VERSION`, and the oldest
+///   version found in the input
+pub fn load_results(data: &str) -> Result<(Vec<ScanResults>, u32)> {
+    let hosts = match serde_json::from_str::<Value>(data).context("not valid JSON")? {
+        Value::Array(hosts) => hosts,
+        host => vec![host],
+    };
+    let oldest = hosts.iter()
+        .map(|host| host.get("schema_version").and_then(Value::as_u64).unwrap_or(0) as u32)
+        .min()
+        .unwrap_or(SCHEMA_VERSION);
+    let results = hosts.into_iter()
+        .enumerate()
+        .map(|(i, host)| upgrade_host(host).with_context(|| format!("host {} is not a valid scan result", i + 1)))
+        .collect::<Result<Vec<_>>>()?;
+    Ok((results, oldest))
+}
+
+/// Arguments for `quantum_scanner convert`
+#[derive(clap::Parser, Debug)]
+#[clap(
+    name = "convert",
+    about = "Upgrade saved JSON results to the current schema version",
+    after_help = "EXAMPLES:
+    quantum_scanner convert old-scan.json -o scan.json
+    quantum_scanner convert old-scan.json --in-place"
+)]
+pub struct ConvertArgs {
+    /// JSON results written with -j/--json (one host or an array of hosts)
+    pub input: PathBuf,
+
+    /// Write the upgraded results here instead of standard output
+    #[clap(short, long, value_name = "PATH", conflicts_with = "in_place")]
+    pub output: Option<PathBuf>,
+
+    /// Replace the input file with the upgraded results
+    #[clap(long)]
+    pub in_place: bool,
+}
+
+/// Run the `convert` subcommand
+pub fn run_convert(args: ConvertArgs) -> Result<()> {
+    let data = std::fs::read_to_string(&args.input)
+        .with_context(|| format!("Failed to read {}", args.input.display()))?;
+    let (results, oldest) = load_results(&data)
+        .with_context(|| format!("{} is not a JSON scan result", args.input.display()))?;
+
+    // Keep the input's shape: a single host stays an object
+    let json = match results.as_slice() {
+        [host] if !data.trim_start().starts_with('[') => serde_json::to_string_pretty(host)?,
+        _ => serde_json::to_string_pretty(&results)?,
+    };
+    let destination = if args.in_place { Some(&args.input) } else { args.output.as_ref() };
+    match destination {
+        Some(path) => {
+            std::fs::write(path, json).with_context(|| format!("Failed to write {}", path.display()))?;
+            eprintln!("Converted {} host(s) from schema version {} to {} into {}",
+                      results.len(), oldest, SCHEMA_VERSION, path.display());
+        }
+        None => println!("{}", json),
+    }
+    Ok(())
+}
@@ -0,0 +1,281 @@
+//! Post-detection scripting.
+//!
+//! User checks written in Rhai, loaded from the directory given with `--scripts`. Each
+//! `*.rhai` file defines two functions, in the spirit of NSE:
+//!
+//! ```text
+//! fn portrule(port) { port.service == "http" }
+//! fn action(port) {
+//!     send("GET /server-status HTTP/1.0\r\n\r\n");
+//!     if recv().contains("Apache Server Status") {
+//!         add_vuln("APACHE-STATUS", "mod_status page is public", "Medium");
+//!     }
+//! }
+//! ```
+//!
+//! `portrule` decides whether the script runs against an open TCP port; `action` does the
+//! check. `port` is a map with `host`, `number`, `protocol`, `state`, `service`, `version`
+//! and `banner`. Scripts get:
+//! - `send(string | blob)`, `recv()`, `recv(timeout_ms)`, `recv_bytes()`, `close()` on a
+//!   TCP connection to the port, opened on first use
+//! - `add_finding(text)` and `add_vuln(id, description[, severity])` to report results
+//! - `print(..)` / `debug(..)`, routed to the scanner log
+//!
+//! Scripts run after service detection, on blocking threads, with an operation limit so
+//! a runaway loop cannot stall the scan.
+
+use std::io::{Read, Write};
+use std::net::{IpAddr, Shutdown, SocketAddr, TcpStream};
+use std::path::Path;
+use std::sync::Arc;
+use std::time::Duration;
+
+use anyhow::{anyhow, Context, Result};
+use lazy_static::lazy_static;
+use log::{debug, info, warn};
+use parking_lot::{Mutex, RwLock};
+use rhai::{Blob, Dynamic, Engine, EvalAltResult, Map, Scope, AST};
+
+use crate::models::{Finding, PortResult, ScriptOutput};
+
+/// Upper bound on Rhai operations per script run
+const MAX_OPERATIONS: u64 = 5_000_000;
+
+/// Largest single `recv` read
+const RECV_BUFFER: usize = 16 * 1024;
+
+/// A compiled script
+#[derive(Clone)]
+pub struct Script {
+    pub name: String,
+    ast: AST,
+}
+
+lazy_static! {
+    /// Scripts loaded with `--scripts`
+    static ref SCRIPTS: RwLock<Arc<Vec<Script>>> = RwLock::new(Arc::new(Vec::new()));
+}
+
+/// Scripts to run after service detection
+pub fn scripts() -> Arc<Vec<Script>> {
+    SCRIPTS.read().clone()
+}
+
+/// Compile every `*.rhai` file in a directory
+///
+/// Scripts are compiled up front so syntax errors stop the scan before any traffic is
+/// sent rather than surfacing per port.
+///
+/// # Arguments
+/// * `dir` - Directory holding the scripts
+///
+/// # Returns
+/// * `Result<usize>` - Number of scripts loaded, or the first compile error
+pub fn load_scripts(dir: &Path) -> Result<usize> {
+    let mut paths: Vec<_> = std::fs::read_dir(dir)
+        .with_context(|| format!("Failed to read script directory {}", dir.display()))?
+        .filter_map(|entry| entry.ok().map(|e| e.path()))
+        .filter(|path| path.extension().map_or(false, |ext| ext == "rhai"))
+        .collect();
+    paths.sort();
+
+    let engine = Engine::new();
+    let mut loaded = Vec::new();
+    for path in paths {
+        let name = path.file_stem().map(|s| s.to_string_lossy().into_owned()).unwrap_or_default();
+        let ast = engine.compile_file(path.clone())
+            .map_err(|e| anyhow!("Script {}: {}", path.display(), e))?;
+        for required in ["portrule", "action"] {
+            if !ast.iter_functions().any(|f| f.name == required && f.params.len() == 1) {
+                return Err(anyhow!("Script {} must define fn {}(port)", path.display(), required));
+            }
+        }
+        debug!("[Script] Loaded {}", name);
+        loaded.push(Script { name, ast });
+    }
+
+    let count = loaded.len();
+    *SCRIPTS.write() = Arc::new(loaded);
+    Ok(count)
+}
+
+/// Per-run state shared with the functions registered on the engine
+struct ScriptContext {
+    target: SocketAddr,
+    timeout: Duration,
+    stream: Option<TcpStream>,
+    findings: Vec<String>,
+    vulns: Vec<Finding>,
+}
+
+impl ScriptContext {
+    /// The connection to the port, opened on first use
+    fn stream(&mut self) -> Result<&mut TcpStream, Box<EvalAltResult>> {
+        if self.stream.is_none() {
+            let stream = crate::netbind::connect_tcp_std(self.target, self.timeout)
+                .map_err(|e| format!("connect to {} failed: {}", self.target, e))?;
+            stream.set_write_timeout(Some(self.timeout)).ok();
+            self.stream = Some(stream);
+        }
+        Ok(self.stream.as_mut().expect("stream was just opened"))
+    }
+
+    fn send(&mut self, data: &[u8]) -> Result<(), Box<EvalAltResult>> {
+        let target = self.target;
+        let stream = self.stream()?;
+        crate::scheduler::spend_blocking(data.len() + crate::scheduler::TCP_HEADER_BYTES);
+        crate::audit::record(|| crate::audit::Probe::new("script", "tcp", target.ip(), Some(target.port()), data.len() + crate::scheduler::TCP_HEADER_BYTES));
+        stream.write_all(data).map_err(|e| format!("send failed: {}", e).into())
+    }
+
+    /// Read whatever arrives within the timeout; empty on timeout or close
+    fn recv(&mut self, timeout: Duration) -> Result<Blob, Box<EvalAltResult>> {
+        let stream = self.stream()?;
+        stream.set_read_timeout(Some(timeout.max(Duration::from_millis(1)))).ok();
+        let mut buffer = vec![0u8; RECV_BUFFER];
+        match stream.read(&mut buffer) {
+            Ok(n) => {
+                buffer.truncate(n);
+                Ok(buffer)
+            }
+            Err(e) if matches!(e.kind(), std::io::ErrorKind::WouldBlock | std::io::ErrorKind::TimedOut) => Ok(Blob::new()),
+            Err(e) => Err(format!("recv failed: {}", e).into()),
+        }
+    }
+
+    fn close(&mut self) {
+        if let Some(stream) = self.stream.take() {
+            let _ = stream.shutdown(Shutdown::Both);
+        }
+    }
+}
+
+/// The `port` map handed to `portrule` and `action`
+fn port_map(host: IpAddr, port: u16, result: &PortResult) -> Map {
+    let text = |value: &Option<String>| value.clone().map_or(Dynamic::UNIT, Dynamic::from);
+    let mut map = Map::new();
+    map.insert("host".into(), Dynamic::from(host.to_string()));
+    map.insert("number".into(), Dynamic::from(port as i64));
+    map.insert("protocol".into(), Dynamic::from("tcp".to_string()));
+    map.insert("state".into(), Dynamic::from(result.final_status.to_string()));
+    map.insert("service".into(), text(&result.service));
+    map.insert("version".into(), text(&result.version));
+    map.insert("banner".into(), text(&result.banner));
+    map
+}
+
+/// Engine with the script API bound to one run's context
+fn build_engine(name: &str, context: Arc<Mutex<ScriptContext>>) -> Engine {
+    let mut engine = Engine::new();
+    engine.set_max_operations(MAX_OPERATIONS);
+
+    let log_name = name.to_string();
+    engine.on_print(move |text| info!("[Script {}] {}", log_name, text));
+    let log_name = name.to_string();
+    engine.on_debug(move |text, _, _| debug!("[Script {}] {}", log_name, text));
+
+    let ctx = context.clone();
+    engine.register_fn("send", move |data: &str| ctx.lock().send(data.as_bytes()));
+    let ctx = context.clone();
+    engine.register_fn("send", move |data: Blob| ctx.lock().send(&data));
+
+    let ctx = context.clone();
+    engine.register_fn("recv", move || {
+        let mut ctx = ctx.lock();
+        let timeout = ctx.timeout;
+        ctx.recv(timeout).map(|data| String::from_utf8_lossy(&data).into_owned())
+    });
+    let ctx = context.clone();
+    engine.register_fn("recv", move |timeout_ms: i64| {
+        ctx.lock().recv(Duration::from_millis(timeout_ms.max(0) as u64))
+            .map(|data| String::from_utf8_lossy(&data).into_owned())
+    });
+    let ctx = context.clone();
+    engine.register_fn("recv_bytes", move || {
+        let mut ctx = ctx.lock();
+        let timeout = ctx.timeout;
+        ctx.recv(timeout)
+    });
+    let ctx = context.clone();
+    engine.register_fn("close", move || ctx.lock().close());
+
+    let ctx = context.clone();
+    engine.register_fn("add_finding", move |text: &str| ctx.lock().findings.push(text.to_string()));
+    let ctx = context.clone();
+    engine.register_fn("add_vuln", move |id: &str, description: &str| {
+        ctx.lock().vulns.push(Finding { id: id.to_string(), description: description.to_string(), severity: "Info".to_string(), ..Default::default() });
+    });
+    let ctx = context;
+    engine.register_fn("add_vuln", move |id: &str, description: &str, severity: &str| {
+        ctx.lock().vulns.push(Finding { id: id.to_string(), description: description.to_string(), severity: severity.to_string(), ..Default::default() });
+    });
+
+    engine
+}
+
+/// Run one script against one port
+///
+/// # Returns
+/// * `Result<Option<(ScriptOutput, Vec<Finding>)>>` - `None` when `portrule` declined
+///   or the script reported nothing
+fn run_script(script: &Script, host: IpAddr, port: u16, result: &PortResult, timeout: Duration) -> Result<Option<(ScriptOutput, Vec<Finding>)>> {
+    let context = Arc::new(Mutex::new(ScriptContext {
+        target: SocketAddr::new(host, port),
+        timeout,
+        stream: None,
+        findings: Vec::new(),
+        vulns: Vec::new(),
+    }));
+    let engine = build_engine(&script.name, context.clone());
+    let port_arg = port_map(host, port, result);
+    let mut scope = Scope::new();
+
+    let applies: bool = engine.call_fn(&mut scope, &script.ast, "portrule", (port_arg.clone(),))
+        .map_err(|e| anyhow!("portrule: {}", e))?;
+    if !applies {
+        return Ok(None);
+    }
+    debug!("[Script] Running {} against {}:{}", script.name, host, port);
+    let outcome = engine.call_fn::<Dynamic>(&mut scope, &script.ast, "action", (port_arg,));
+
+    let mut context = context.lock();
+    context.close();
+    outcome.map_err(|e| anyhow!("action: {}", e))?;
+
+    if context.findings.is_empty() && context.vulns.is_empty() {
+        return Ok(None);
+    }
+    let output = ScriptOutput { script: script.name.clone(), findings: std::mem::take(&mut context.findings) };
+    Ok(Some((output, std::mem::take(&mut context.vulns))))
+}
+
+/// Run every loaded script against one open port
+///
+/// Blocking; call from `spawn_blocking`. A failing script is logged and skipped so one
+/// bad check does not lose the others' results.
+///
+/// # Arguments
+/// * `host` - Target address
+/// * `port` - Open TCP port
+/// * `result` - What detection found so far; read by `portrule`/`action`
+/// * `timeout` - Connect and receive timeout for script I/O
+///
+/// # Returns
+/// * `(Vec<ScriptOutput>, Vec<Finding>)` - Findings and vulnerabilities to merge into the port
+pub fn run_scripts(host: IpAddr, port: u16, result: &PortResult, timeout: Duration) -> (Vec<ScriptOutput>, Vec<Finding>) {
+    let mut outputs = Vec::new();
+    let mut vulns = Vec::new();
+    for script in scripts().iter() {
+        match run_script(script, host, port, result, timeout) {
+            Ok(Some((output, found))) => {
+                if !output.findings.is_empty() {
+                    outputs.push(output);
+                }
+                vulns.extend(found);
+            }
+            Ok(None) => {}
+            Err(e) => warn!("[Script] {} failed on port {}: {}", script.name, port, e),
+        }
+    }
+    (outputs, vulns)
+}
@@ -1,153 +1,240 @@
+//! Service fingerprint database.
+//!
+//! Fingerprints live in a text file in (a subset of) nmap-service-probes syntax: `Probe`
+//! blocks carrying the payload to send and the ports it suits, followed by `match` and
+//! `softmatch` lines mapping response regexes to a service and version template. The
+//! stock database (`fingerprints/service-probes`) is compiled in; files given with
+//! `--fingerprints` are consulted first and reloaded whenever they change, so new
+//! signatures take effect without recompiling or restarting a long scan.
+//!
+//! A stock nmap-service-probes file loads as-is: patterns are translated from PCRE and
+//! matched against raw response bytes, and signatures using constructs the `regex` crate
+//! lacks are skipped and counted. `import-fingerprints` reports what a file would lose
+//! and can write out the supported subset.
+//!
+//! Probe files (`--probe-file`, YAML or TOML) define custom probes in a simpler form: the
+//! bytes to send to a port over TCP or UDP and a regex the reply must match. They go
+//! ahead of everything else, and unlike stock probes their payloads are actually sent,
+//! by banner grabbing for TCP and by the UDP scan.
+
 use std::collections::HashMap;
-use regex::Regex;
-use log::{debug, info};
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use std::time::{Duration, SystemTime};
+
+use anyhow::{anyhow, Context, Result};
+use clap::Parser;
+use lazy_static::lazy_static;
+use parking_lot::RwLock;
+use regex::bytes::{Captures, Regex, RegexBuilder};
+use log::{debug, info, warn};
+use serde::Deserialize;
+
+/// Stock fingerprints compiled into the binary
+const BUILTIN_FINGERPRINTS: &str = include_str!("../fingerprints/service-probes");
+
+/// How often fingerprint files are checked for changes
+const RELOAD_INTERVAL: Duration = Duration::from_secs(2);
+
+/// Transport a probe is sent over
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ProbeProtocol {
+    Tcp,
+    Udp,
+}
+
+/// One response signature
+#[derive(Debug, Clone)]
+pub struct ServiceMatch {
+    pub service: String,
+    pub pattern: Regex,
+    /// Soft matches name the service but keep looking for a versioned hard match
+    pub soft: bool,
+    /// `p/` product template
+    pub product: Option<String>,
+    /// `v/` version template
+    pub version: Option<String>,
+    /// `i/` extra info template
+    pub info: Option<String>,
+}
+
+impl ServiceMatch {
+    /// Version string for a banner this signature matches, with templates filled in
+    fn render_version(&self, banner: &[u8]) -> Option<String> {
+        let captures = self.pattern.captures(banner)?;
+        let fill = |template: &Option<String>| -> Option<String> {
+            let text = expand_template(template.as_deref()?, &captures);
+            let text = text.trim().to_string();
+            (!text.is_empty()).then_some(text)
+        };
+
+        let mut version = [fill(&self.product), fill(&self.version)]
+            .into_iter()
+            .flatten()
+            .collect::<Vec<_>>()
+            .join(" ");
+        if let Some(info) = fill(&self.info) {
+            version = if version.is_empty() { info } else { format!("{} ({})", version, info) };
+        }
+        (!version.is_empty()).then_some(version)
+    }
+}
+
+/// A probe and the signatures that apply to its responses
+#[derive(Debug, Clone)]
+pub struct ServiceProbe {
+    pub protocol: ProbeProtocol,
+    pub name: String,
+    pub payload: Vec<u8>,
+    /// Ports the probe is intended for (empty = any)
+    pub ports: Vec<u16>,
+    pub matches: Vec<ServiceMatch>,
+    /// Defined in a `--probe-file`; only these payloads are sent to services
+    pub custom: bool,
+}
+
+/// A signature left out of the database, and why
+#[derive(Debug, Clone)]
+pub struct SkippedSignature {
+    /// 1-based line number in the source file
+    pub line: usize,
+    pub service: String,
+    pub reason: String,
+}
 
 /// Service fingerprint database for identifying services and versions
-#[allow(dead_code)]
+#[derive(Debug, Clone, Default)]
 pub struct ServiceFingerprints {
-    /// Map of service signatures by port
-    port_signatures: HashMap<u16, Vec<(String, String)>>,
-    /// Map of pattern-based signatures
-    pattern_signatures: Vec<(Regex, String, String)>,
+    probes: Vec<ServiceProbe>,
 }
 
 impl ServiceFingerprints {
-    /// Create a new fingerprint database with common services
+    /// The current database: user files (if any) ahead of the stock fingerprints
     #[allow(dead_code)]
     pub fn new() -> Self {
-        let mut port_signatures = HashMap::new();
-        let mut pattern_signatures = Vec::new();
-        
-        // Add common port-based signatures
-        
-        // FTP signatures
-        port_signatures.insert(21, vec![
-            ("FTP".to_string(), "220.*FileZilla".to_string()),
-            ("FTP".to_string(), "220.*ProFTPD".to_string()),
-            ("FTP".to_string(), "220.*vsFTPd".to_string()),
-            ("FTP".to_string(), "220.*Pure-FTPd".to_string()),
-            ("FTP".to_string(), "220.*FTP server".to_string()),
-        ]);
-        
-        // SSH signatures
-        port_signatures.insert(22, vec![
-            ("SSH".to_string(), "SSH-2.0-OpenSSH".to_string()),
-            ("SSH".to_string(), "SSH-1.99-OpenSSH".to_string()),
-            ("SSH".to_string(), "SSH-2.0-dropbear".to_string()),
-        ]);
-        
-        // Telnet signatures
-        port_signatures.insert(23, vec![
-            ("Telnet".to_string(), ".*Welcome to.*telnet".to_string()),
-            ("Telnet".to_string(), ".*login:".to_string()),
-            ("Telnet".to_string(), ".*Username:".to_string()),
-        ]);
-        
-        // SMTP signatures
-        port_signatures.insert(25, vec![
-            ("SMTP".to_string(), "220.*ESMTP".to_string()),
-            ("SMTP".to_string(), "220.*Postfix".to_string()),
-            ("SMTP".to_string(), "220.*Sendmail".to_string()),
-            ("SMTP".to_string(), "220.*Exim".to_string()),
-            ("SMTP".to_string(), "220.*SMTP".to_string()),
-        ]);
-        
-        // HTTP/HTTPS signatures
-        port_signatures.insert(80, vec![
-            ("HTTP".to_string(), "HTTP/1".to_string()),
-            ("HTTP".to_string(), "HTTP/2".to_string()),
-        ]);
-        
-        port_signatures.insert(443, vec![
-            ("HTTPS".to_string(), "HTTP/1".to_string()),
-            ("HTTPS".to_string(), "HTTP/2".to_string()),
-        ]);
-        
-        // Add more complex pattern-based signatures
-        let pattern_defs = [
-            // SSH patterns
-            (r"SSH-2\.0-OpenSSH_([0-9\.]+)", "SSH", "OpenSSH $1"),
-            (r"SSH-2\.0-dropbear_([0-9\.]+)", "SSH", "Dropbear SSH $1"),
-            
-            // FTP patterns
-            (r"220.*FTP.*\nVSFTPD ([0-9\.]+)", "FTP", "vsFTPd $1"),
-            (r"220.*FileZilla Server\s+version\s+([0-9\.]+)", "FTP", "FileZilla Server $1"),
-            (r"220.*ProFTPD\s+([0-9\.]+)", "FTP", "ProFTPD $1"),
-            
-            // HTTP server patterns
-            (r"Server: Apache/([0-9\.]+)", "HTTP", "Apache $1"),
-            (r"Server: nginx/([0-9\.]+)", "HTTP", "nginx $1"),
-            (r"Server: Microsoft-IIS/([0-9\.]+)", "HTTP", "IIS $1"),
-            (r"Server: lighttpd/([0-9\.]+)", "HTTP", "lighttpd $1"),
-            
-            // Web application patterns
-            (r"X-Powered-By: PHP/([0-9\.]+)", "HTTP", "PHP $1"),
-            (r"X-Powered-By: ASP\.NET", "HTTP", "ASP.NET"),
-            
-            // Mail server patterns
-            (r"220.*ESMTP\s+Postfix", "SMTP", "Postfix"),
-            (r"220.*ESMTP\s+Sendmail\s+([0-9\.]+)", "SMTP", "Sendmail $1"),
-            (r"220.*ESMTP\s+Exim\s+([0-9\.]+)", "SMTP", "Exim $1"),
-            
-            // Database patterns
-            (r"MySQL.*?([0-9]+\.[0-9]+\.[0-9]+)", "MySQL", "MySQL $1"),
-            (r"PostgreSQL\s+([0-9\.]+)", "PostgreSQL", "PostgreSQL $1"),
-        ];
-        
-        for (pattern, service, version_format) in pattern_defs.iter() {
-            if let Ok(re) = Regex::new(pattern) {
-                pattern_signatures.push((re, service.to_string(), version_format.to_string()));
-            } else {
-                debug!("Failed to compile regex pattern: {}", pattern);
-            }
+        (*database()).clone()
+    }
+
+    /// The stock fingerprints compiled into the binary
+    pub fn builtin() -> Self {
+        Self::parse(BUILTIN_FINGERPRINTS, "built-in fingerprints").expect("built-in fingerprint file must parse")
+    }
+
+    /// Parse a fingerprint file
+    ///
+    /// Signatures that cannot be used (Perl-only regex constructs such as look-around or
+    /// back-references, or malformed version fields) are skipped with a debug message;
+    /// structural errors fail the whole file with their line number.
+    pub fn parse(text: &str, source: &str) -> Result<Self> {
+        let (db, skipped) = Self::parse_with_report(text, source)?;
+        if !skipped.is_empty() {
+            debug!("{}: skipped {} unsupported signatures", source, skipped.len());
         }
-        
-        Self {
-            port_signatures,
-            pattern_signatures,
+        Ok(db)
+    }
+
+    /// Parse a fingerprint file, also returning the signatures that were skipped
+    pub fn parse_with_report(text: &str, source: &str) -> Result<(Self, Vec<SkippedSignature>)> {
+        let mut probes: Vec<ServiceProbe> = Vec::new();
+        let mut skipped = Vec::new();
+
+        for (index, raw) in text.lines().enumerate() {
+            let line = raw.trim();
+            if line.is_empty() || line.starts_with('#') {
+                continue;
+            }
+            let location = || format!("{}:{}", source, index + 1);
+            let (directive, rest) = line.split_once(char::is_whitespace).unwrap_or((line, ""));
+            let rest = rest.trim();
+
+            match directive {
+                "Probe" => probes.push(parse_probe(rest).with_context(location)?),
+                "match" | "softmatch" => {
+                    let probe = probes.last_mut()
+                        .ok_or_else(|| anyhow!("{}: {} before any Probe", location(), directive))?;
+                    match parse_match(rest, directive == "softmatch") {
+                        Ok(signature) => probe.matches.push(signature),
+                        Err(e) => {
+                            let service = rest.split_whitespace().next().unwrap_or("").to_string();
+                            debug!("{}: skipping {} signature: {}", location(), service, e);
+                            skipped.push(SkippedSignature { line: index + 1, service, reason: e.to_string() });
+                        }
+                    }
+                }
+                "ports" | "sslports" => {
+                    let probe = probes.last_mut()
+                        .ok_or_else(|| anyhow!("{}: {} before any Probe", location(), directive))?;
+                    probe.ports.extend(parse_port_list(rest).with_context(location)?);
+                }
+                // Scheduling hints we do not use
+                "Exclude" | "rarity" | "totalwaitms" | "tcpwrappedms" | "fallback" => {}
+                other => return Err(anyhow!("{}: unknown directive '{}'", location(), other)),
+            }
         }
+
+        Ok((ServiceFingerprints { probes }, skipped))
     }
-    
+
+    /// Load a fingerprint file from disk
+    pub fn load_file(path: &Path) -> Result<Self> {
+        let text = std::fs::read_to_string(path)
+            .with_context(|| format!("Failed to read fingerprint file {}", path.display()))?;
+        Self::parse(&text, &path.display().to_string())
+    }
+
+    /// Append another database's probes; earlier entries take precedence
+    pub fn extend(&mut self, other: ServiceFingerprints) {
+        self.probes.extend(other.probes);
+    }
+
+    /// Number of probes and signatures
+    pub fn counts(&self) -> (usize, usize) {
+        (self.probes.len(), self.probes.iter().map(|p| p.matches.len()).sum())
+    }
+
     /// Identify service from a banner on a specific port
-    #[allow(dead_code)]
+    ///
+    /// Signatures of probes meant for this port are tried first, then all others, in
+    /// file order. The first hard match wins; a soft match fixes the service name and
+    /// only hard matches for that service are considered after it.
     pub fn identify_service(&self, port: u16, banner: &str) -> Option<(String, Option<String>)> {
+        self.identify_bytes(port, banner.as_bytes())
+    }
+
+    /// Identify service from raw response bytes on a specific port
+    ///
+    /// Preferred over `identify_service` when the raw response is at hand, since binary
+    /// signatures cannot match a lossily decoded banner.
+    pub fn identify_bytes(&self, port: u16, banner: &[u8]) -> Option<(String, Option<String>)> {
         debug!("Attempting to identify service on port {} from banner", port);
-        
-        // First try port-specific signatures
-        if let Some(signatures) = self.port_signatures.get(&port) {
-            for (service, pattern) in signatures {
-                if let Ok(re) = Regex::new(&pattern) {
-                    if re.is_match(banner) {
-                        debug!("Matched port-specific signature for {} on port {}", service, port);
-                        return Some((service.clone(), None));
-                    }
-                }
+
+        let for_port = self.probes.iter().filter(|p| p.ports.contains(&port));
+        let others = self.probes.iter().filter(|p| !p.ports.contains(&port));
+        let mut soft_service: Option<&str> = None;
+
+        for signature in for_port.chain(others).flat_map(|p| p.matches.iter()) {
+            if soft_service.map_or(false, |service| !signature.service.eq_ignore_ascii_case(service)) {
+                continue;
             }
-        }
-        
-        // Then try all pattern signatures for version detection
-        for (pattern, service, version_format) in &self.pattern_signatures {
-            if let Some(captures) = pattern.captures(banner) {
-                debug!("Matched pattern signature for {}", service);
-                
-                let mut version = version_format.clone();
-                
-                // Replace capture groups in version format
-                for i in 1..captures.len() {
-                    if let Some(m) = captures.get(i) {
-                        version = version.replace(&format!("${}", i), m.as_str());
-                    }
-                }
-                
-                if version == *version_format {
-                    // No replacements were made, return without version
-                    return Some((service.clone(), None));
-                } else {
-                    // Replacements were made, return with version
-                    info!("Detected service {} version {}", service, version);
-                    return Some((service.clone(), Some(version)));
-                }
+            if !signature.pattern.is_match(banner) {
+                continue;
             }
+            if signature.soft {
+                soft_service.get_or_insert(signature.service.as_str());
+                continue;
+            }
+            let version = signature.render_version(banner);
+            match &version {
+                Some(version) => info!("Detected service {} version {}", signature.service, version),
+                None => debug!("Matched signature for {}", signature.service),
+            }
+            return Some((signature.service.clone(), version));
+        }
+
+        if let Some(service) = soft_service {
+            debug!("Soft match for {} on port {}", service, port);
+            return Some((service.to_string(), None));
         }
         
         // Default well-known port mappings as fallback
@@ -172,19 +259,25 @@ impl ServiceFingerprints {
         }
     }
     
+    /// Custom probe from a `--probe-file` for this port and transport, if any
+    pub fn custom_probe(&self, port: u16, protocol: ProbeProtocol) -> Option<&ServiceProbe> {
+        self.probes.iter().find(|p| p.custom && p.protocol == protocol && p.ports.contains(&port))
+    }
+
     /// Get appropriate service probe for a port
+    ///
+    /// Returns the payload of the first TCP probe listing this port, or nothing (wait
+    /// for a banner) when none does.
     #[allow(dead_code)]
     pub fn get_service_probe(&self, port: u16) -> Vec<u8> {
-        match port {
-            21 => b"USER anonymous\r\n".to_vec(),
-            22 => b"SSH-2.0-OpenSSH_8.4p1\r\n".to_vec(),
-            23 => b"\r\n".to_vec(),
-            25 => b"EHLO test\r\n".to_vec(),
-            80 | 443 | 8080 | 8443 => 
-                b"GET / HTTP/1.1\r\nHost: localhost\r\nUser-Agent: Mozilla/5.0 (Windows NT 10.0; Win64; x64) AppleWebKit/537.36 (KHTML, like Gecko) Chrome/91.0.4472.124 Safari/537.36\r\nAccept: */*\r\nConnection: close\r\n\r\n".to_vec(),
-            110 => b"USER test\r\n".to_vec(),
-            143 => b"A001 CAPABILITY\r\n".to_vec(),
-            _ => Vec::new(),
+        match self.probes.iter()
+            .find(|p| p.protocol == ProbeProtocol::Tcp && !p.payload.is_empty() && p.ports.contains(&port))
+        {
+            Some(probe) => {
+                debug!("Using probe {} for port {}", probe.name, port);
+                probe.payload.clone()
+            }
+            None => Vec::new(),
         }
     }
     
@@ -236,8 +329,8 @@ pub fn identify_service(
     banner: Option<&str>,
     cert_info: Option<&crate::models::CertificateInfo>,
 ) -> (Option<String>, Option<String>) {
-    // Create fingerprint database
-    let fingerprints = ServiceFingerprints::new();
+    // Current fingerprint database
+    let fingerprints = database();
     
     // Try to identify from banner if available
     if let Some(banner_text) = banner {
@@ -284,4 +377,564 @@ pub fn identify_service(
         8443 => (Some("https-alt".to_string()), None),
         _ => (crate::models::CommonPorts::get_service(port).map(String::from), None)
     }
-} 
\ No newline at end of file
+}
+
+/// Parse `TCP NAME q|payload|`
+fn parse_probe(rest: &str) -> Result<ServiceProbe> {
+    let mut parts = rest.splitn(3, ' ');
+    let protocol = match parts.next() {
+        Some("TCP") => ProbeProtocol::Tcp,
+        Some("UDP") => ProbeProtocol::Udp,
+        other => return Err(anyhow!("Probe protocol must be TCP or UDP, got {:?}", other)),
+    };
+    let name = parts.next().filter(|n| !n.is_empty()).ok_or_else(|| anyhow!("Probe without a name"))?;
+    let payload_spec = parts.next().unwrap_or("").trim();
+    let payload_spec = payload_spec.strip_prefix('q')
+        .ok_or_else(|| anyhow!("Probe {} payload must be q<delim>...<delim>", name))?;
+    let (payload, _) = take_delimited(payload_spec)?;
+
+    Ok(ServiceProbe {
+        protocol,
+        name: name.to_string(),
+        payload: unescape(payload)?,
+        ports: Vec::new(),
+        matches: Vec::new(),
+        custom: false,
+    })
+}
+
+/// Parse `service m|regex|flags [p/../ v/../ i/../ ...]`
+fn parse_match(rest: &str, soft: bool) -> Result<ServiceMatch> {
+    let (service, spec) = rest.split_once(char::is_whitespace)
+        .ok_or_else(|| anyhow!("match needs a service and a pattern"))?;
+    let spec = spec.trim_start().strip_prefix('m')
+        .ok_or_else(|| anyhow!("match pattern must be m<delim>regex<delim>"))?;
+    let (regex, mut remainder) = take_delimited(spec)?;
+
+    let flags: String = remainder.chars().take_while(|c| c.is_ascii_alphabetic()).collect();
+    remainder = &remainder[flags.len()..];
+    // Byte-oriented like PCRE, so \xff means the byte 0xff rather than U+00FF
+    let pattern = RegexBuilder::new(&translate_pcre(regex))
+        .unicode(false)
+        .case_insensitive(flags.contains('i'))
+        .dot_matches_new_line(flags.contains('s'))
+        .build()
+        .map_err(|e| anyhow!("unsupported regex: {}", e.to_string().lines().last().unwrap_or("")))?;
+
+    let mut signature = ServiceMatch { service: service.to_string(), pattern, soft, product: None, version: None, info: None };
+
+    // Version info fields: a tag, then a delimited value, then optional flag letters
+    loop {
+        remainder = remainder.trim_start();
+        if remainder.is_empty() {
+            break;
+        }
+        let tag_len = if remainder.starts_with("cpe:") { 4 } else { remainder.chars().next().map_or(0, char::len_utf8) };
+        let tag = &remainder[..tag_len];
+        let (value, after) = take_delimited(&remainder[tag.len()..])?;
+        remainder = after.trim_start_matches(|c: char| c.is_ascii_alphabetic());
+        match tag {
+            "p" => signature.product = Some(value.to_string()),
+            "v" => signature.version = Some(value.to_string()),
+            "i" => signature.info = Some(value.to_string()),
+            // Hostname, OS, device type and CPE are not reported
+            "h" | "o" | "d" | "cpe:" => {}
+            other => return Err(anyhow!("Unknown version field '{}'", other)),
+        }
+    }
+
+    Ok(signature)
+}
+
+/// Rewrite PCRE syntax that means something different (or nothing) to the `regex` crate
+///
+/// Handles `\0`, `\Z`, literal `{` and the `[`, `&&`, `--` and `~~` that the `regex`
+/// crate treats as class set operations. Constructs with no equivalent (look-around,
+/// back-references, atomic groups) are left alone and fail to compile.
+fn translate_pcre(pattern: &str) -> String {
+    let chars: Vec<char> = pattern.chars().collect();
+    let mut out = String::with_capacity(pattern.len() + 8);
+    let mut in_class = false;
+    let mut i = 0;
+
+    while i < chars.len() {
+        let c = chars[i];
+        let next = chars.get(i + 1).copied();
+        i += 1;
+        match c {
+            '\\' => {
+                let Some(escaped) = next else {
+                    out.push_str("\\\\");
+                    break;
+                };
+                i += 1;
+                match escaped {
+                    // PCRE octal NUL; the regex crate has no octal escapes
+                    '0' => out.push_str("\\x00"),
+                    'Z' if !in_class => out.push_str("(?:\\n?\\z)"),
+                    e if e.is_ascii_alphanumeric() => {
+                        out.push('\\');
+                        out.push(e);
+                    }
+                    e if regex_syntax_meta(e) => {
+                        out.push('\\');
+                        out.push(e);
+                    }
+                    // Escaped punctuation that is not special is just the character
+                    e => out.push(e),
+                }
+            }
+            '[' if !in_class => {
+                in_class = true;
+                out.push('[');
+                if chars.get(i) == Some(&'^') {
+                    out.push('^');
+                    i += 1;
+                }
+                // A leading ] is literal in PCRE
+                if chars.get(i) == Some(&']') {
+                    out.push_str("\\]");
+                    i += 1;
+                }
+            }
+            '[' if in_class => {
+                // POSIX classes like [:alpha:] pass through; any other [ is literal
+                if next == Some(':') {
+                    let end = chars[i..].windows(2).position(|w| w == [':', ']']);
+                    if let Some(end) = end {
+                        out.push('[');
+                        out.extend(&chars[i..i + end + 2]);
+                        i += end + 2;
+                        continue;
+                    }
+                }
+                out.push_str("\\[");
+            }
+            ']' if in_class => {
+                in_class = false;
+                out.push(']');
+            }
+            '&' | '~' | '-' if in_class && next == Some(c) => out.push_str(&format!("\\{}", c)),
+            '{' if !in_class && !starts_repetition(&chars[i..]) => out.push_str("\\{"),
+            c => out.push(c),
+        }
+    }
+    out
+}
+
+/// Characters the `regex` crate accepts (and requires) escaped
+fn regex_syntax_meta(c: char) -> bool {
+    matches!(c, '\\' | '.' | '+' | '*' | '?' | '(' | ')' | '|' | '[' | ']' | '{' | '}' | '^' | '$' | '#' | '&' | '-' | '~')
+}
+
+/// Whether the text after a `{` forms a `{n}`, `{n,}` or `{n,m}` repetition
+fn starts_repetition(rest: &[char]) -> bool {
+    let Some(end) = rest.iter().position(|&c| c == '}') else {
+        return false;
+    };
+    let body: String = rest[..end].iter().collect();
+    let mut bounds = body.splitn(2, ',');
+    let min = bounds.next().unwrap_or("");
+    !min.is_empty()
+        && min.chars().all(|c| c.is_ascii_digit())
+        && bounds.next().map_or(true, |max| max.chars().all(|c| c.is_ascii_digit()))
+}
+
+/// Fill a version template from regex groups
+///
+/// Supports nmap's `$1`..`$N`, `$P(n)` (printable characters only),
+/// `$SUBST(n,"from","to")` and `$I(n,">")` / `$I(n,"<")` (unsigned integer, big or
+/// little endian).
+fn expand_template(template: &str, captures: &Captures) -> String {
+    let group = |n: usize| captures.get(n).map_or(&b""[..], |m| m.as_bytes());
+    let mut out = String::new();
+    let mut rest = template;
+
+    while let Some(pos) = rest.find('$') {
+        out.push_str(&rest[..pos]);
+        rest = &rest[pos + 1..];
+
+        let digits: String = rest.chars().take_while(char::is_ascii_digit).collect();
+        if !digits.is_empty() {
+            out.push_str(&String::from_utf8_lossy(group(digits.parse().unwrap_or(0))));
+            rest = &rest[digits.len()..];
+            continue;
+        }
+
+        let name: String = rest.chars().take_while(char::is_ascii_uppercase).collect();
+        let Some(args_len) = rest[name.len()..].strip_prefix('(').and_then(|a| a.find(')')) else {
+            out.push('$');
+            continue;
+        };
+        let args = &rest[name.len() + 1..name.len() + 1 + args_len];
+        rest = &rest[name.len() + args_len + 2..];
+
+        let mut args = args.split(',').map(|a| a.trim().trim_matches('"'));
+        let value = group(args.next().and_then(|n| n.parse().ok()).unwrap_or(0));
+        match name.as_str() {
+            "P" => out.extend(value.iter().filter(|b| b.is_ascii_graphic() || **b == b' ').map(|&b| b as char)),
+            "SUBST" => {
+                let text = String::from_utf8_lossy(value);
+                match (args.next(), args.next()) {
+                    (Some(from), Some(to)) if !from.is_empty() => out.push_str(&text.replace(from, to)),
+                    _ => out.push_str(&text),
+                }
+            }
+            "I" => {
+                let bytes = &value[..value.len().min(8)];
+                let fold = |n: u64, b: &u8| (n << 8) | u64::from(*b);
+                let number = if args.next() == Some("<") {
+                    bytes.iter().rev().fold(0, fold)
+                } else {
+                    bytes.iter().fold(0, fold)
+                };
+                out.push_str(&number.to_string());
+            }
+            _ => {}
+        }
+    }
+    out.push_str(rest);
+    out
+}
+
+/// Split `<d>body<d>rest` into `body` and `rest`, where `<d>` is the first character
+fn take_delimited(spec: &str) -> Result<(&str, &str)> {
+    let delimiter = spec.chars().next().ok_or_else(|| anyhow!("Missing delimiter"))?;
+    let body = &spec[delimiter.len_utf8()..];
+    let end = body.find(delimiter).ok_or_else(|| anyhow!("Unterminated {}...{} field", delimiter, delimiter))?;
+    Ok((&body[..end], &body[end + delimiter.len_utf8()..]))
+}
+
+/// Decode the C-style escapes used in probe payloads
+pub(crate) fn unescape(text: &str) -> Result<Vec<u8>> {
+    let mut bytes = Vec::with_capacity(text.len());
+    let mut chars = text.chars();
+    while let Some(c) = chars.next() {
+        if c != '\\' {
+            let mut buf = [0u8; 4];
+            bytes.extend_from_slice(c.encode_utf8(&mut buf).as_bytes());
+            continue;
+        }
+        match chars.next() {
+            Some('0') => bytes.push(0),
+            Some('a') => bytes.push(0x07),
+            Some('b') => bytes.push(0x08),
+            Some('f') => bytes.push(0x0c),
+            Some('n') => bytes.push(b'\n'),
+            Some('r') => bytes.push(b'\r'),
+            Some('t') => bytes.push(b'\t'),
+            Some('v') => bytes.push(0x0b),
+            Some('x') => {
+                let hex: String = chars.by_ref().take(2).collect();
+                bytes.push(u8::from_str_radix(&hex, 16).map_err(|_| anyhow!("Invalid escape \\x{}", hex))?);
+            }
+            Some(other) => {
+                let mut buf = [0u8; 4];
+                bytes.extend_from_slice(other.encode_utf8(&mut buf).as_bytes());
+            }
+            None => return Err(anyhow!("Trailing backslash in probe payload")),
+        }
+    }
+    Ok(bytes)
+}
+
+/// Parse `80,443,8000-8010`
+fn parse_port_list(text: &str) -> Result<Vec<u16>> {
+    let mut ports = Vec::new();
+    for item in text.split(',').map(str::trim).filter(|item| !item.is_empty()) {
+        match item.split_once('-') {
+            Some((start, end)) => {
+                let (start, end): (u16, u16) = (start.parse()?, end.parse()?);
+                ports.extend(start..=end);
+            }
+            None => ports.push(item.parse()?),
+        }
+    }
+    Ok(ports)
+}
+
+/// One entry of a probe file
+#[derive(Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
+struct ProbeSpec {
+    /// Probe name for logs; defaults to the service name
+    name: Option<String>,
+    service: String,
+    #[serde(default)]
+    port: Option<u16>,
+    #[serde(default)]
+    ports: Vec<u16>,
+    #[serde(default = "default_probe_protocol")]
+    protocol: ProbeProtocol,
+    /// Payload with `\r`, `\n`, `\0` and `\xNN` escapes; empty waits for a greeting
+    #[serde(default)]
+    send: Option<String>,
+    /// Payload as hex digits, for binary protocols
+    #[serde(default)]
+    send_hex: Option<String>,
+    /// Regex the reply must match (regex crate syntax, matched against raw bytes)
+    expect: String,
+    #[serde(default)]
+    case_insensitive: bool,
+    /// `p/`, `v/` and `i/` style templates; `$1` refers to a capture group of `expect`
+    #[serde(default)]
+    product: Option<String>,
+    #[serde(default)]
+    version: Option<String>,
+    #[serde(default)]
+    info: Option<String>,
+}
+
+fn default_probe_protocol() -> ProbeProtocol {
+    ProbeProtocol::Tcp
+}
+
+/// Top level of a probe file: `probes:` in YAML, `[[probe]]` tables in TOML
+#[derive(Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
+struct ProbeFile {
+    #[serde(alias = "probe")]
+    probes: Vec<ProbeSpec>,
+}
+
+impl ProbeSpec {
+    fn into_probe(self) -> Result<ServiceProbe> {
+        let name = self.name.unwrap_or_else(|| self.service.clone());
+        let mut ports = self.ports;
+        ports.extend(self.port);
+        if ports.is_empty() {
+            return Err(anyhow!("probe {} has no port", name));
+        }
+        let payload = match (self.send, self.send_hex) {
+            (Some(_), Some(_)) => return Err(anyhow!("probe {} has both send and send_hex", name)),
+            (Some(text), None) => unescape(&text).with_context(|| format!("probe {}", name))?,
+            (None, Some(hex)) => decode_hex(&hex).with_context(|| format!("probe {}", name))?,
+            (None, None) => Vec::new(),
+        };
+        if payload.is_empty() && self.protocol == ProbeProtocol::Udp {
+            return Err(anyhow!("UDP probe {} needs a payload", name));
+        }
+        let pattern = RegexBuilder::new(&self.expect)
+            .unicode(false)
+            .case_insensitive(self.case_insensitive)
+            .build()
+            .with_context(|| format!("probe {}: invalid expect regex", name))?;
+        Ok(ServiceProbe {
+            protocol: self.protocol,
+            name,
+            payload,
+            ports,
+            matches: vec![ServiceMatch {
+                service: self.service,
+                pattern,
+                soft: false,
+                product: self.product,
+                version: self.version,
+                info: self.info,
+            }],
+            custom: true,
+        })
+    }
+}
+
+/// Hex digits (whitespace allowed between bytes) to bytes
+fn decode_hex(text: &str) -> Result<Vec<u8>> {
+    let digits: String = text.chars().filter(|c| !c.is_whitespace()).collect();
+    if digits.len() % 2 != 0 {
+        return Err(anyhow!("send_hex has an odd number of digits"));
+    }
+    (0..digits.len()).step_by(2)
+        .map(|i| u8::from_str_radix(&digits[i..i + 2], 16).map_err(|_| anyhow!("send_hex has invalid digits '{}'", &digits[i..i + 2])))
+        .collect()
+}
+
+impl ServiceFingerprints {
+    /// Load a probe file, YAML (`.yaml`/`.yml`) or TOML (`.toml`) by extension
+    pub fn load_probe_file(path: &Path) -> Result<Self> {
+        let text = std::fs::read_to_string(path)
+            .with_context(|| format!("Failed to read probe file {}", path.display()))?;
+        let file: ProbeFile = match path.extension().and_then(|e| e.to_str()).map(str::to_ascii_lowercase).as_deref() {
+            Some("toml") => toml::from_str(&text).with_context(|| format!("Invalid probe file {}", path.display()))?,
+            Some("yaml") | Some("yml") => serde_yaml::from_str(&text).with_context(|| format!("Invalid probe file {}", path.display()))?,
+            _ => return Err(anyhow!("Probe file {} must end in .yaml, .yml or .toml", path.display())),
+        };
+        let probes = file.probes.into_iter()
+            .map(ProbeSpec::into_probe)
+            .collect::<Result<Vec<_>>>()
+            .with_context(|| format!("Invalid probe file {}", path.display()))?;
+        Ok(ServiceFingerprints { probes })
+    }
+}
+
+lazy_static! {
+    /// Active fingerprint database
+    static ref DATABASE: RwLock<Arc<ServiceFingerprints>> = RwLock::new(Arc::new(ServiceFingerprints::builtin()));
+    /// User fingerprint files and the modification time they were last loaded at
+    static ref USER_FILES: RwLock<Vec<(PathBuf, Option<SystemTime>)>> = RwLock::new(Vec::new());
+    /// Probes from `--probe-file`, kept ahead of every fingerprint file across reloads
+    static ref CUSTOM_PROBES: RwLock<ServiceFingerprints> = RwLock::new(ServiceFingerprints::default());
+}
+
+/// The active fingerprint database
+pub fn database() -> Arc<ServiceFingerprints> {
+    DATABASE.read().clone()
+}
+
+fn modified(path: &Path) -> Option<SystemTime> {
+    std::fs::metadata(path).and_then(|m| m.modified()).ok()
+}
+
+/// Build the database from the user files (in order) followed by the stock fingerprints
+fn build_database(paths: &[PathBuf]) -> Result<ServiceFingerprints> {
+    let mut db = CUSTOM_PROBES.read().clone();
+    for path in paths {
+        db.extend(ServiceFingerprints::load_file(path)?);
+    }
+    db.extend(ServiceFingerprints::builtin());
+    Ok(db)
+}
+
+/// Use fingerprints from these files ahead of the stock database
+///
+/// # Arguments
+/// * `paths` - Fingerprint files in nmap-service-probes syntax, highest priority first
+///
+/// # Returns
+/// * `Result<()>` - Error if any file cannot be read or parsed
+pub fn set_fingerprint_files(paths: &[PathBuf]) -> Result<()> {
+    let db = build_database(paths)?;
+    let (probes, signatures) = db.counts();
+    info!("Loaded fingerprint database: {} probes, {} signatures", probes, signatures);
+    *DATABASE.write() = Arc::new(db);
+    *USER_FILES.write() = paths.iter().map(|p| (p.clone(), modified(p))).collect();
+    Ok(())
+}
+
+/// Use custom probes from these files ahead of all fingerprints
+///
+/// # Arguments
+/// * `paths` - Probe files in YAML or TOML, highest priority first
+///
+/// # Returns
+/// * `Result<()>` - Error if any file cannot be read or a probe in it is invalid
+pub fn set_probe_files(paths: &[PathBuf]) -> Result<()> {
+    let mut custom = ServiceFingerprints::default();
+    for path in paths {
+        custom.extend(ServiceFingerprints::load_probe_file(path)?);
+    }
+    info!("Loaded {} custom probes", custom.probes.len());
+    *CUSTOM_PROBES.write() = custom;
+    let user_files: Vec<PathBuf> = USER_FILES.read().iter().map(|(p, _)| p.clone()).collect();
+    *DATABASE.write() = Arc::new(build_database(&user_files)?);
+    Ok(())
+}
+
+/// Reload the user fingerprint files whenever one of them changes
+///
+/// A file that fails to parse after an edit leaves the previous database in place, so a
+/// half-saved file never drops signatures mid-scan.
+pub fn watch_fingerprint_files() {
+    if USER_FILES.read().is_empty() {
+        return;
+    }
+    tokio::spawn(async move {
+        let mut interval = tokio::time::interval(RELOAD_INTERVAL);
+        loop {
+            interval.tick().await;
+            let changed = USER_FILES.read().iter().any(|(path, loaded)| modified(path) != *loaded);
+            if !changed {
+                continue;
+            }
+            let paths: Vec<PathBuf> = USER_FILES.read().iter().map(|(p, _)| p.clone()).collect();
+            match build_database(&paths) {
+                Ok(db) => {
+                    let (probes, signatures) = db.counts();
+                    info!("Fingerprint files changed, reloaded: {} probes, {} signatures", probes, signatures);
+                    *DATABASE.write() = Arc::new(db);
+                }
+                Err(e) => warn!("Fingerprint reload failed, keeping previous database: {:#}", e),
+            }
+            // Record the new times either way so a broken file is not re-parsed every tick
+            *USER_FILES.write() = paths.iter().map(|p| (p.clone(), modified(p))).collect();
+        }
+    });
+}
+
+/// Command line for fingerprint import mode
+#[derive(Parser, Debug)]
+#[clap(
+    name = "quantum_import_fingerprints",
+    about = "Check an nmap-service-probes file against the fingerprint engine and write out the usable subset",
+    after_help = "EXAMPLES:
+    # See how much of nmap's database the engine can use
+    quantum_scanner import-fingerprints /usr/share/nmap/nmap-service-probes
+
+    # Write the supported subset and scan with it
+    quantum_scanner import-fingerprints /usr/share/nmap/nmap-service-probes --output fingerprints/nmap.probes
+    quantum_scanner 10.0.0.1 -sV --fingerprints fingerprints/nmap.probes"
+)]
+pub struct ImportArgs {
+    /// nmap-service-probes file to import
+    pub input: PathBuf,
+
+    /// Write the file without the unsupported signatures here
+    #[clap(long)]
+    pub output: Option<PathBuf>,
+
+    /// List every skipped signature with its line number and reason
+    #[clap(long)]
+    pub list_skipped: bool,
+}
+
+/// Run fingerprint import
+///
+/// The input file can also be given to `--fingerprints` directly; unsupported signatures
+/// are then skipped at load time. Writing the subset keeps load times and debug output
+/// down on every scan.
+///
+/// # Arguments
+/// * `args` - Parsed import command line
+///
+/// # Returns
+/// * `Result<()>` - Error if the file cannot be read, parsed or written
+pub fn run_import(args: ImportArgs) -> Result<()> {
+    let text = std::fs::read_to_string(&args.input)
+        .with_context(|| format!("Failed to read {}", args.input.display()))?;
+    let (db, skipped) = ServiceFingerprints::parse_with_report(&text, &args.input.display().to_string())?;
+    let (probes, signatures) = db.counts();
+    println!("{}: {} probes, {} signatures usable, {} skipped", args.input.display(), probes, signatures, skipped.len());
+
+    // Group reasons so the summary stays readable for thousands of signatures
+    let mut reasons: HashMap<&str, usize> = HashMap::new();
+    for entry in &skipped {
+        *reasons.entry(entry.reason.as_str()).or_default() += 1;
+    }
+    let mut reasons: Vec<_> = reasons.into_iter().collect();
+    reasons.sort_by(|a, b| b.1.cmp(&a.1).then(a.0.cmp(b.0)));
+    for (reason, count) in reasons {
+        println!("  {:>6}  {}", count, reason);
+    }
+    if args.list_skipped {
+        for entry in &skipped {
+            println!("  line {}: {}: {}", entry.line, entry.service, entry.reason);
+        }
+    }
+
+    if let Some(output) = &args.output {
+        let dropped: std::collections::HashSet<usize> = skipped.iter().map(|entry| entry.line).collect();
+        let mut converted = format!(
+            "# Imported from {} by quantum_scanner import-fingerprints\n# {} unsupported signatures removed\n",
+            args.input.display(), skipped.len(),
+        );
+        for (index, line) in text.lines().enumerate() {
+            if !dropped.contains(&(index + 1)) {
+                converted.push_str(line);
+                converted.push('\n');
+            }
+        }
+        std::fs::write(output, converted)
+            .with_context(|| format!("Failed to write {}", output.display()))?;
+        println!("Supported subset written to {}", output.display());
+    }
+    Ok(())
+}
@@ -0,0 +1,187 @@
+//! Per-service OPSEC policy.
+//!
+//! `--protect-services` names service classes (or single services and ports) that must
+//! never get payload probes. Discovery still runs against every port. Once detection has
+//! identified a port, the scanner checks it against the policy in one place, and deep
+//! enumeration stands down on protected ports: no VPN handshakes, default credential
+//! checks, scripts or plugins. Ports are marked in the results with the class that
+//! protected them, so a report shows what was deliberately left alone.
+//!
+//! Banner grabs run before identification, so they go by port number: on the well-known
+//! ports of a protected class they only read what the service sends on its own.
+
+use std::sync::Arc;
+
+use anyhow::{anyhow, Result};
+use lazy_static::lazy_static;
+use parking_lot::RwLock;
+
+/// Classes of services that tolerate unexpected input badly or hold critical data
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ServiceClass {
+    /// Industrial control: Modbus, S7, DNP3, BACnet, EtherNet/IP, IEC 104, OPC UA
+    Ics,
+    /// Database servers
+    Database,
+    /// Medical imaging and messaging: DICOM, HL7
+    Medical,
+    /// Network printers, which print whatever they are sent
+    Printer,
+}
+
+impl ServiceClass {
+    /// Every class, in the order they are listed in help text
+    pub const ALL: [ServiceClass; 4] = [ServiceClass::Ics, ServiceClass::Database, ServiceClass::Medical, ServiceClass::Printer];
+
+    /// Name used on the command line and in results
+    pub fn name(&self) -> &'static str {
+        match self {
+            ServiceClass::Ics => "ics",
+            ServiceClass::Database => "database",
+            ServiceClass::Medical => "medical",
+            ServiceClass::Printer => "printer",
+        }
+    }
+
+    /// Parse a class name, accepting a few common aliases
+    fn parse(name: &str) -> Option<Self> {
+        match name {
+            "ics" | "scada" | "ot" => Some(ServiceClass::Ics),
+            "database" | "databases" | "db" => Some(ServiceClass::Database),
+            "medical" => Some(ServiceClass::Medical),
+            "printer" | "printers" => Some(ServiceClass::Printer),
+            _ => None,
+        }
+    }
+
+    /// Prefixes of the words in identified service names that belong to this class
+    fn keywords(&self) -> &'static [&'static str] {
+        match self {
+            ServiceClass::Ics => &["modbus", "s7", "siemens", "dnp3", "bacnet", "enip", "cip", "iec104", "iec-104",
+                                   "opcua", "opc-ua", "profinet", "fins", "melsec", "codesys", "niagara", "scada", "plc"],
+            ServiceClass::Database => &["mysql", "mariadb", "postgres", "mssql", "ms-sql", "oracle", "tns", "mongo",
+                                        "redis", "memcache", "cassandra", "couchdb", "elasticsearch", "db2", "influxdb",
+                                        "neo4j", "clickhouse"],
+            ServiceClass::Medical => &["dicom", "hl7", "acr-nema"],
+            ServiceClass::Printer => &["jetdirect", "pdl-datastream", "ipp", "lpd", "printer"],
+        }
+    }
+
+    /// Well-known ports, used while a port has not been identified
+    fn ports(&self) -> &'static [u16] {
+        match self {
+            ServiceClass::Ics => &[102, 502, 789, 1911, 1962, 2404, 4840, 5007, 9600, 18245, 20000, 44818, 47808],
+            ServiceClass::Database => &[1433, 1434, 1521, 3306, 5432, 5984, 6379, 7474, 8086, 9042, 9200, 11211, 27017, 50000],
+            ServiceClass::Medical => &[104, 2575, 4242, 11112],
+            ServiceClass::Printer => &[515, 631, 9100],
+        }
+    }
+
+    /// Whether an identified service name falls in this class
+    fn matches(&self, service: &str) -> bool {
+        let service = service.to_lowercase();
+        self.keywords().iter().any(|keyword| {
+            service.starts_with(keyword)
+                || service.split(|c: char| !c.is_ascii_alphanumeric()).any(|word| word.starts_with(keyword))
+        })
+    }
+}
+
+/// Services that deep enumeration must leave alone
+#[derive(Debug, Clone, Default)]
+pub struct ServicePolicy {
+    classes: Vec<ServiceClass>,
+    /// Individual service names, matched exactly (case-insensitive)
+    services: Vec<String>,
+    /// Individual ports, protected whatever runs on them
+    ports: Vec<u16>,
+}
+
+impl ServicePolicy {
+    /// Parse a comma-separated list of classes, service names and port numbers
+    ///
+    /// # Arguments
+    /// * `spec` - e.g. `ics,database,ldap,8443`
+    ///
+    /// # Returns
+    /// * `Result<ServicePolicy>` - The policy, or an error for an empty list
+    pub fn parse(spec: &str) -> Result<Self> {
+        let mut policy = ServicePolicy::default();
+        for entry in spec.split(',').map(|e| e.trim().to_lowercase()).filter(|e| !e.is_empty()) {
+            if let Some(class) = ServiceClass::parse(&entry) {
+                if !policy.classes.contains(&class) {
+                    policy.classes.push(class);
+                }
+            } else if let Ok(port) = entry.parse::<u16>() {
+                policy.ports.push(port);
+            } else {
+                policy.services.push(entry);
+            }
+        }
+        if policy.classes.is_empty() && policy.services.is_empty() && policy.ports.is_empty() {
+            return Err(anyhow!("no service classes, services or ports given (classes: {})",
+                               ServiceClass::ALL.iter().map(|c| c.name()).collect::<Vec<_>>().join(", ")));
+        }
+        Ok(policy)
+    }
+
+    /// Whether deep enumeration must skip a port
+    ///
+    /// # Arguments
+    /// * `port` - Port number
+    /// * `service` - Service detection identified on it, if any
+    ///
+    /// # Returns
+    /// * `Option<String>` - The class, service or port that protects it
+    pub fn protects(&self, port: u16, service: Option<&str>) -> Option<String> {
+        if self.ports.contains(&port) {
+            return Some(format!("port {}", port));
+        }
+        match service {
+            Some(service) => {
+                if let Some(name) = self.services.iter().find(|s| s.eq_ignore_ascii_case(service)) {
+                    return Some(name.clone());
+                }
+                self.classes.iter().find(|class| class.matches(service)).map(|class| class.name().to_string())
+            }
+            None => self.protects_port(port),
+        }
+    }
+
+    /// Whether a port is protected before anything is known about its service
+    pub fn protects_port(&self, port: u16) -> Option<String> {
+        if self.ports.contains(&port) {
+            return Some(format!("port {}", port));
+        }
+        self.classes.iter().find(|class| class.ports().contains(&port)).map(|class| class.name().to_string())
+    }
+
+    /// Classes, services and ports in the policy, for logging
+    pub fn describe(&self) -> String {
+        self.classes.iter().map(|c| c.name().to_string())
+            .chain(self.services.iter().cloned())
+            .chain(self.ports.iter().map(|p| p.to_string()))
+            .collect::<Vec<_>>()
+            .join(", ")
+    }
+}
+
+lazy_static! {
+    /// Active policy; `None` unless `--protect-services` was given
+    static ref POLICY: RwLock<Option<Arc<ServicePolicy>>> = RwLock::new(None);
+}
+
+/// Enable (or disable with `None`) the service policy
+pub fn set_policy(policy: Option<ServicePolicy>) {
+    *POLICY.write() = policy.map(Arc::new);
+}
+
+/// Current policy, if one is set
+pub fn policy() -> Option<Arc<ServicePolicy>> {
+    POLICY.read().clone()
+}
+
+/// Whether a banner grab on `port` must stay read-only
+pub fn protects_port(port: u16) -> bool {
+    POLICY.read().as_ref().map_or(false, |policy| policy.protects_port(port).is_some())
+}
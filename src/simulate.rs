@@ -0,0 +1,399 @@
+//! Local test target (`quantum_scanner simulate`).
+//!
+//! Serves a fake host on a local address so scanner settings, evasion pipelines and
+//! probe files can be tried out before they are pointed at anything real. Ports are
+//! configured on the command line or in a YAML/TOML file:
+//! - open: accepts connections and sends a canned banner, optionally behind TLS with a
+//!   generated or supplied certificate
+//! - tarpit: accepts connections and then trickles a byte at a time without ever
+//!   finishing, like a tarpit or a slow middlebox
+//! - UDP: answers datagrams with a canned reply
+//! - closed: nothing listens, so the kernel answers with a RST (or ICMP port unreachable)
+//! - filtered: nothing listens either; dropping the traffic takes a packet filter rule,
+//!   which the simulator prints but does not install
+//!
+//! Every connection and datagram is logged, so what a scan actually sent can be compared
+//! with what it reported.
+
+use std::collections::BTreeMap;
+use std::net::{IpAddr, Ipv4Addr, SocketAddr};
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use std::time::Duration;
+
+use anyhow::{anyhow, Context, Result};
+use clap::Parser;
+use log::{debug, info, warn};
+use rustls::pki_types::{CertificateDer, PrivateKeyDer, PrivatePkcs8KeyDer};
+use serde::Deserialize;
+use tokio::io::{AsyncRead, AsyncReadExt, AsyncWrite, AsyncWriteExt};
+use tokio::net::{TcpListener, TcpStream, UdpSocket};
+
+/// How long an open port waits for the client to send something
+const CLIENT_IDLE: Duration = Duration::from_secs(10);
+/// Longest a tarpit holds on to a connection
+const TARPIT_MAX: Duration = Duration::from_secs(600);
+
+/// Command line for simulator mode
+#[derive(Parser, Debug)]
+#[clap(
+    name = "simulate",
+    about = "Serve a local fake target to try scanner settings against",
+    after_help = "EXAMPLES:
+    # SSH and a web server on loopback; scan it with the settings to validate
+    quantum_scanner simulate --open '22:SSH-2.0-OpenSSH_8.9p1\\r\\n' --open 8080 --tls 8443:intranet.example
+    quantum_scanner 127.0.0.1 -p 20-9000 -s syn,fin -V
+
+    # Everything from a file, including tarpits, UDP and filtered ports
+    quantum_scanner simulate --config lab.yaml
+
+CONFIG FILE (YAML; TOML takes the same keys with [[port]] tables):
+    bind: 127.0.0.1
+    tarpit_interval: 10
+    ports:
+      - { port: 21, banner: \"220 ProFTPD Server ready\\r\\n\" }
+      - { port: 443, tls: true, common_name: intranet.example }
+      - { port: 8080, state: tarpit }
+      - { port: 161, protocol: udp, reply_hex: \"3026020101\" }
+      - { port: 3389, state: filtered }"
+)]
+pub struct SimulateArgs {
+    /// Address to serve on
+    #[clap(long, value_name = "IP", default_value = "127.0.0.1")]
+    pub bind: IpAddr,
+
+    /// YAML or TOML file describing the ports
+    #[clap(long, value_name = "PATH")]
+    pub config: Option<PathBuf>,
+
+    /// Open TCP port with an optional banner (PORT[:BANNER], \r \n \xNN escapes); repeatable
+    #[clap(long, value_name = "PORT[:BANNER]")]
+    pub open: Vec<String>,
+
+    /// Open TCP port behind TLS with a self-signed certificate (PORT[:COMMON_NAME]); repeatable
+    #[clap(long, value_name = "PORT[:CN]")]
+    pub tls: Vec<String>,
+
+    /// TCP port that accepts and then stalls the connection; repeatable
+    #[clap(long, value_name = "PORT")]
+    pub tarpit: Vec<u16>,
+
+    /// UDP port answering with an optional reply (PORT[:REPLY]); repeatable
+    #[clap(long, value_name = "PORT[:REPLY]")]
+    pub udp: Vec<String>,
+
+    /// Port to leave unanswered; needs a packet filter rule, which is printed; repeatable
+    #[clap(long, value_name = "PORT")]
+    pub filtered: Vec<u16>,
+
+    /// Seconds between the bytes a tarpit trickles out
+    #[clap(long, value_name = "SECS", default_value_t = 10)]
+    pub tarpit_interval: u64,
+}
+
+/// Behaviour of a simulated port
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+enum PortState {
+    #[default]
+    Open,
+    Closed,
+    Filtered,
+    Tarpit,
+}
+
+/// One port of the simulated host
+#[derive(Debug, Clone, Deserialize)]
+#[serde(deny_unknown_fields)]
+struct SimPort {
+    port: u16,
+    #[serde(default)]
+    state: PortState,
+    #[serde(default = "default_protocol")]
+    protocol: String,
+    /// Sent on connect, or for `http` ports in reply to the first request
+    banner: Option<String>,
+    /// UDP reply, or `banner` as raw bytes
+    reply_hex: Option<String>,
+    /// Wait for the client to send first before answering with the banner
+    #[serde(default)]
+    wait_for_request: bool,
+    #[serde(default)]
+    tls: bool,
+    /// Common name of the generated certificate
+    common_name: Option<String>,
+    /// PEM certificate chain and key to serve instead of a generated certificate
+    tls_cert: Option<PathBuf>,
+    tls_key: Option<PathBuf>,
+}
+
+fn default_protocol() -> String {
+    "tcp".to_string()
+}
+
+/// Simulator configuration file
+#[derive(Debug, Default, Deserialize)]
+#[serde(deny_unknown_fields)]
+struct SimConfig {
+    bind: Option<IpAddr>,
+    tarpit_interval: Option<u64>,
+    #[serde(default, alias = "port")]
+    ports: Vec<SimPort>,
+}
+
+impl SimPort {
+    fn new(port: u16, state: PortState, protocol: &str) -> Self {
+        Self {
+            port,
+            state,
+            protocol: protocol.to_string(),
+            banner: None,
+            reply_hex: None,
+            wait_for_request: false,
+            tls: false,
+            common_name: None,
+            tls_cert: None,
+            tls_key: None,
+        }
+    }
+
+    /// Bytes to send: `reply_hex` if given, otherwise the escaped `banner`
+    fn payload(&self) -> Result<Option<Vec<u8>>> {
+        if let Some(hex) = &self.reply_hex {
+            return crate::techniques::parse_hex_payload(hex).map(Some);
+        }
+        self.banner.as_deref().map(crate::service_fingerprints::unescape).transpose()
+    }
+}
+
+/// Split `PORT[:TEXT]`
+fn split_spec(spec: &str) -> Result<(u16, Option<String>)> {
+    let (port, rest) = match spec.split_once(':') {
+        Some((port, rest)) => (port, Some(rest.to_string())),
+        None => (spec, None),
+    };
+    let port = port.trim().parse::<u16>().map_err(|_| anyhow!("Invalid port in '{}'", spec))?;
+    Ok((port, rest))
+}
+
+/// Ports from the config file and the command line; the command line wins on conflicts
+fn collect_ports(args: &SimulateArgs, config: SimConfig) -> Result<Vec<SimPort>> {
+    let mut ports: BTreeMap<(u16, bool), SimPort> = BTreeMap::new();
+    let mut add = |port: SimPort| {
+        ports.insert((port.port, port.protocol == "udp"), port);
+    };
+    for port in config.ports {
+        if port.protocol != "tcp" && port.protocol != "udp" {
+            return Err(anyhow!("Port {}: protocol must be tcp or udp", port.port));
+        }
+        add(port);
+    }
+    for spec in &args.open {
+        let (port, banner) = split_spec(spec)?;
+        let mut sim = SimPort::new(port, PortState::Open, "tcp");
+        sim.banner = banner;
+        add(sim);
+    }
+    for spec in &args.tls {
+        let (port, common_name) = split_spec(spec)?;
+        let mut sim = SimPort::new(port, PortState::Open, "tcp");
+        sim.tls = true;
+        sim.common_name = common_name;
+        add(sim);
+    }
+    for port in &args.tarpit {
+        add(SimPort::new(*port, PortState::Tarpit, "tcp"));
+    }
+    for spec in &args.udp {
+        let (port, reply) = split_spec(spec)?;
+        let mut sim = SimPort::new(port, PortState::Open, "udp");
+        sim.banner = reply;
+        add(sim);
+    }
+    for port in &args.filtered {
+        add(SimPort::new(*port, PortState::Filtered, "tcp"));
+    }
+    Ok(ports.into_values().collect())
+}
+
+/// Read a YAML or TOML config, picked by extension
+fn load_config(path: &Path) -> Result<SimConfig> {
+    let text = std::fs::read_to_string(path).with_context(|| format!("Failed to read {}", path.display()))?;
+    match path.extension().and_then(|e| e.to_str()).map(str::to_ascii_lowercase).as_deref() {
+        Some("toml") => toml::from_str(&text).with_context(|| format!("Invalid simulator config {}", path.display())),
+        _ => serde_yaml::from_str(&text).with_context(|| format!("Invalid simulator config {}", path.display())),
+    }
+}
+
+/// TLS acceptor for a port: its own certificate and key, or a self-signed one
+fn tls_acceptor(port: &SimPort) -> Result<tokio_rustls::TlsAcceptor> {
+    let (certs, key): (Vec<CertificateDer<'static>>, PrivateKeyDer<'static>) = match (&port.tls_cert, &port.tls_key) {
+        (Some(cert), Some(key)) => crate::relay::load_tls_material(cert, key)?,
+        (None, None) => {
+            let name = port.common_name.clone().unwrap_or_else(|| "localhost".to_string());
+            let cert = rcgen::generate_simple_self_signed(vec![name])
+                .map_err(|e| anyhow!("Failed to generate a certificate for port {}: {}", port.port, e))?;
+            let der = cert.serialize_der()
+                .map_err(|e| anyhow!("Failed to encode the certificate for port {}: {}", port.port, e))?;
+            (vec![CertificateDer::from(der)], PrivateKeyDer::Pkcs8(PrivatePkcs8KeyDer::from(cert.serialize_private_key_der())))
+        }
+        _ => return Err(anyhow!("Port {}: tls_cert and tls_key must be given together", port.port)),
+    };
+    let config = rustls::ServerConfig::builder()
+        .with_no_client_auth()
+        .with_single_cert(certs, key)
+        .with_context(|| format!("Invalid TLS certificate or key for port {}", port.port))?;
+    Ok(tokio_rustls::TlsAcceptor::from(Arc::new(config)))
+}
+
+/// Send the banner on connect, or after the first request, then drain the client
+async fn serve_open<S>(mut stream: S, peer: SocketAddr, port: u16, banner: Option<Arc<Vec<u8>>>, wait_for_request: bool) -> Result<()>
+where
+    S: AsyncRead + AsyncWrite + Unpin,
+{
+    let mut buf = vec![0u8; 4096];
+    if wait_for_request {
+        let n = tokio::time::timeout(CLIENT_IDLE, stream.read(&mut buf)).await.unwrap_or(Ok(0))?;
+        debug!("[Simulate] {}: {} sent {} bytes", port, peer, n);
+    }
+    if let Some(banner) = &banner {
+        stream.write_all(banner).await?;
+    }
+    // Keep the connection until the client closes it or goes quiet
+    while let Ok(Ok(n)) = tokio::time::timeout(CLIENT_IDLE, stream.read(&mut buf)).await {
+        if n == 0 {
+            break;
+        }
+        debug!("[Simulate] {}: {} sent {} bytes", port, peer, n);
+    }
+    Ok(())
+}
+
+/// Trickle one byte per interval until the client gives up
+async fn serve_tarpit(mut stream: TcpStream, interval: Duration) {
+    let started = tokio::time::Instant::now();
+    while started.elapsed() < TARPIT_MAX {
+        tokio::time::sleep(interval).await;
+        if stream.write_all(b"\r").await.is_err() {
+            break;
+        }
+    }
+}
+
+/// Accept loop of one TCP port
+async fn run_tcp(bind: IpAddr, port: SimPort, tarpit_interval: Duration) -> Result<()> {
+    let listener = TcpListener::bind(SocketAddr::new(bind, port.port)).await
+        .with_context(|| format!("Cannot listen on {}:{}", bind, port.port))?;
+    let banner = port.payload()?.map(Arc::new);
+    let acceptor = if port.tls { Some(tls_acceptor(&port)?) } else { None };
+    // HTTP-style banners answer a request rather than greet
+    let wait_for_request = port.wait_for_request
+        || port.banner.as_deref().is_some_and(|b| b.starts_with("HTTP/"));
+
+    loop {
+        let (stream, peer) = listener.accept().await?;
+        info!("[Simulate] {}/tcp: connection from {}", port.port, peer);
+        let banner = banner.clone();
+        let acceptor = acceptor.clone();
+        let state = port.state;
+        let number = port.port;
+        tokio::spawn(async move {
+            let result = match (state, acceptor) {
+                (PortState::Tarpit, _) => {
+                    serve_tarpit(stream, tarpit_interval).await;
+                    Ok(())
+                }
+                (_, Some(acceptor)) => match acceptor.accept(stream).await {
+                    Ok(tls) => serve_open(tls, peer, number, banner, true).await,
+                    Err(e) => Err(anyhow!("TLS handshake failed: {}", e)),
+                },
+                (_, None) => serve_open(stream, peer, number, banner, wait_for_request).await,
+            };
+            if let Err(e) = result {
+                debug!("[Simulate] {}/tcp: {}: {}", number, peer, e);
+            }
+        });
+    }
+}
+
+/// Receive loop of one UDP port
+async fn run_udp(bind: IpAddr, port: SimPort) -> Result<()> {
+    let socket = UdpSocket::bind(SocketAddr::new(bind, port.port)).await
+        .with_context(|| format!("Cannot bind {}:{}/udp", bind, port.port))?;
+    let reply = port.payload()?;
+    let mut buf = vec![0u8; 65535];
+    loop {
+        let (n, peer) = socket.recv_from(&mut buf).await?;
+        info!("[Simulate] {}/udp: {} bytes from {}", port.port, n, peer);
+        if let Some(reply) = &reply {
+            socket.send_to(reply, peer).await?;
+        }
+    }
+}
+
+/// Run the `simulate` subcommand until Ctrl-C
+pub async fn run_simulate(args: SimulateArgs) -> Result<()> {
+    let config = match &args.config {
+        Some(path) => load_config(path)?,
+        None => SimConfig::default(),
+    };
+    // A --bind left at its default gives way to the file's
+    let bind = match (config.bind, args.bind) {
+        (Some(file_bind), cli) if cli == IpAddr::V4(Ipv4Addr::LOCALHOST) => file_bind,
+        (_, cli) => cli,
+    };
+    let tarpit_interval = Duration::from_secs(config.tarpit_interval.unwrap_or(args.tarpit_interval).max(1));
+    let ports = collect_ports(&args, config)?;
+    if ports.is_empty() {
+        return Err(anyhow!("Nothing to simulate: give --config or at least one of --open, --tls, --tarpit, --udp, --filtered"));
+    }
+    if !bind.is_loopback() {
+        warn!("[Simulate] Serving on {}, reachable from the network", bind);
+    }
+
+    let mut listeners: tokio::task::JoinSet<Result<()>> = tokio::task::JoinSet::new();
+    for port in ports {
+        let protocol = port.protocol.clone();
+        match (port.state, protocol.as_str()) {
+            (PortState::Closed, _) => info!("[Simulate] {}/{}: closed (nothing listening)", port.port, protocol),
+            (PortState::Filtered, proto) => {
+                info!("[Simulate] {}/{}: filtered; nothing listens, so drop it with a packet filter, e.g.", port.port, proto);
+                info!("[Simulate]     iptables -I INPUT -d {} -p {} --dport {} -j DROP", bind, proto, port.port);
+            }
+            (state, "udp") => {
+                if state == PortState::Tarpit {
+                    warn!("[Simulate] {}/udp: tarpit only applies to TCP; serving it as open", port.port);
+                }
+                info!("[Simulate] {}/udp: open{}", port.port, if port.banner.is_some() || port.reply_hex.is_some() { " with reply" } else { "" });
+                listeners.spawn(run_udp(bind, port));
+            }
+            (state, _) => {
+                let description = match state {
+                    PortState::Tarpit => format!("tarpit, a byte every {}s", tarpit_interval.as_secs()),
+                    _ if port.tls => format!("open, TLS ({})", port.common_name.as_deref().unwrap_or("localhost")),
+                    _ => format!("open{}", if port.banner.is_some() || port.reply_hex.is_some() { " with banner" } else { "" }),
+                };
+                info!("[Simulate] {}/tcp: {}", port.port, description);
+                listeners.spawn(run_tcp(bind, port, tarpit_interval));
+            }
+        }
+    }
+    info!("[Simulate] Serving on {}; Ctrl-C to stop", bind);
+
+    loop {
+        tokio::select! {
+            _ = tokio::signal::ctrl_c() => {
+                info!("[Simulate] Stopping");
+                return Ok(());
+            }
+            result = listeners.join_next() => match result {
+                Some(result) => result.context("Simulator listener panicked")??,
+                None => {
+                    // Only closed or filtered ports: nothing to serve, but keep the process up
+                    tokio::signal::ctrl_c().await?;
+                    return Ok(());
+                }
+            },
+        }
+    }
+}
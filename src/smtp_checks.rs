@@ -0,0 +1,313 @@
+//! SMTP relay and STARTTLS policy checks.
+//!
+//! Every port detection identified as SMTP gets one short session that stops before any
+//! mail could be sent:
+//! - `EHLO`, to list the AUTH mechanisms and whether STARTTLS is offered
+//! - `MAIL FROM` and `RCPT TO` between two outside domains without authenticating; a
+//!   server that accepts the recipient is an open relay. The session is reset before
+//!   `DATA`, so no message is ever queued.
+//! - `STARTTLS` and a second `EHLO`, to compare what is offered with and without TLS
+//!
+//! The outcome is kept on the port as an `SmtpPolicy`; open relaying, plaintext
+//! password mechanisms and missing STARTTLS are recorded as vulnerabilities too.
+//! Implicit TLS ports (465) are checked inside TLS from the start.
+
+use std::collections::HashMap;
+use std::net::{IpAddr, SocketAddr};
+use std::time::Duration;
+
+use anyhow::{anyhow, Context, Result};
+use log::{debug, info, warn};
+use tokio::io::{AsyncBufReadExt, AsyncRead, AsyncWrite, AsyncWriteExt, BufReader};
+use tokio::time::timeout;
+
+use crate::models::{Confidence, Finding, PortResult, PortStatus, SmtpPolicy};
+
+/// Name given in `EHLO`
+const HELO_NAME: &str = "mail.example.org";
+
+/// Envelope sender of the relay test; neither domain is the target's
+const RELAY_SENDER: &str = "postmaster@example.org";
+
+/// Envelope recipient of the relay test
+const RELAY_RECIPIENT: &str = "relay-test@example.net";
+
+/// Longest reply line kept
+const MAX_LINE: usize = 512;
+
+/// Approximate size of a TLS handshake from the client side
+const TLS_HANDSHAKE_BYTES: usize = 700;
+
+/// Mechanisms that send the password itself
+const PLAINTEXT_MECHANISMS: [&str; 2] = ["PLAIN", "LOGIN"];
+
+/// One SMTP reply: its code and the text of every line
+#[derive(Debug)]
+struct Reply {
+    code: u16,
+    lines: Vec<String>,
+}
+
+impl Reply {
+    fn positive(&self) -> bool {
+        (200..400).contains(&self.code)
+    }
+
+    fn text(&self) -> String {
+        format!("{} {}", self.code, self.lines.join(" / "))
+    }
+}
+
+/// An SMTP session over a plain or TLS stream
+struct Session<S> {
+    stream: BufReader<S>,
+    target: SocketAddr,
+    wait: Duration,
+}
+
+impl<S: AsyncRead + AsyncWrite + Unpin> Session<S> {
+    fn new(stream: S, target: SocketAddr, wait: Duration) -> Self {
+        Session { stream: BufReader::new(stream), target, wait }
+    }
+
+    /// Read one reply, following `250-` continuation lines
+    async fn reply(&mut self) -> Result<Reply> {
+        let mut lines = Vec::new();
+        loop {
+            let mut line = String::new();
+            let n = timeout(self.wait, self.stream.read_line(&mut line))
+                .await
+                .context("Reply timed out")??;
+            if n == 0 {
+                return Err(anyhow!("connection closed"));
+            }
+            let line = line.trim_end();
+            let code = line.get(..3).and_then(|code| code.parse::<u16>().ok())
+                .ok_or_else(|| anyhow!("not an SMTP reply: {}", line))?;
+            let last = line.as_bytes().get(3) != Some(&b'-');
+            lines.push(line.get(4..).unwrap_or_default().chars().take(MAX_LINE).collect());
+            if last {
+                return Ok(Reply { code, lines });
+            }
+        }
+    }
+
+    async fn command(&mut self, command: &str) -> Result<Reply> {
+        let line = format!("{}\r\n", command);
+        crate::scheduler::spend(line.len() + crate::scheduler::TCP_HEADER_BYTES).await;
+        crate::audit::record(|| crate::audit::Probe::new("smtp-checks", "tcp", self.target.ip(), Some(self.target.port()),
+                                                         line.len() + crate::scheduler::TCP_HEADER_BYTES)
+            .detail(command.split(':').next().unwrap_or(command).to_string()));
+        timeout(self.wait, self.stream.get_mut().write_all(line.as_bytes()))
+            .await
+            .context("Write timed out")??;
+        self.reply().await
+    }
+
+    /// `EHLO`, returning the advertised extensions in upper case
+    async fn ehlo(&mut self) -> Result<Vec<String>> {
+        let reply = self.command(&format!("EHLO {}", HELO_NAME)).await?;
+        if !reply.positive() {
+            return Err(anyhow!("EHLO refused: {}", reply.text()));
+        }
+        Ok(reply.lines.iter().skip(1).map(|line| line.trim().to_ascii_uppercase()).collect())
+    }
+}
+
+/// AUTH mechanisms listed among EHLO extensions
+fn auth_mechanisms(extensions: &[String]) -> Vec<String> {
+    extensions.iter()
+        .filter_map(|ext| ext.strip_prefix("AUTH ").or_else(|| ext.strip_prefix("AUTH=")))
+        .flat_map(|mechs| mechs.split_whitespace().map(str::to_string))
+        .fold(Vec::new(), |mut all, mech| {
+            if !all.contains(&mech) {
+                all.push(mech);
+            }
+            all
+        })
+}
+
+/// Whether an open port is SMTP, judged by detection results and port number
+fn is_smtp(port: u16, result: &PortResult) -> bool {
+    match result.service.as_deref().map(str::to_ascii_lowercase).as_deref() {
+        Some("smtp") | Some("smtps") | Some("submission") | Some("esmtp") => true,
+        Some(_) => false,
+        None => matches!(port, 25 | 465 | 587 | 2525),
+    }
+}
+
+/// Relay test and plaintext AUTH listing on an established session
+async fn probe_session<S: AsyncRead + AsyncWrite + Unpin>(session: &mut Session<S>, policy: &mut SmtpPolicy) -> Result<Vec<String>> {
+    let extensions = session.ehlo().await?;
+    policy.starttls_offered = extensions.iter().any(|ext| ext == "STARTTLS");
+
+    let mail = session.command(&format!("MAIL FROM:<{}>", RELAY_SENDER)).await?;
+    policy.unauthenticated_mail_accepted = Some(mail.positive());
+    if mail.positive() {
+        let rcpt = session.command(&format!("RCPT TO:<{}>", RELAY_RECIPIENT)).await?;
+        policy.open_relay = Some(rcpt.positive());
+        policy.relay_reply = Some(rcpt.text());
+    } else {
+        policy.relay_reply = Some(mail.text());
+    }
+    // Never DATA: the envelope is dropped here
+    session.command("RSET").await?;
+    Ok(extensions)
+}
+
+/// Check one SMTP port
+async fn check_port(target: IpAddr, port: u16, hostname: Option<&str>, wait: Duration) -> Result<SmtpPolicy> {
+    let addr = SocketAddr::new(target, port);
+    let mut policy = SmtpPolicy { implicit_tls: port == 465, ..SmtpPolicy::default() };
+    let stream = timeout(wait, crate::netbind::connect_tcp(addr)).await.context("Connect timed out")??;
+
+    let server_name = crate::ssl_config::convert_to_server_name(hostname.unwrap_or(HELO_NAME))
+        .map_err(|e| anyhow!("Invalid server name: {}", e))?
+        .to_owned();
+    // Mail servers routinely present self-signed certificates; policy, not trust, is checked
+    let connector = tokio_rustls::TlsConnector::from(crate::ssl_config::create_tls_config(false));
+
+    if policy.implicit_tls {
+        crate::scheduler::spend(TLS_HANDSHAKE_BYTES).await;
+        let tls = timeout(wait, connector.connect(server_name, stream)).await.context("TLS handshake timed out")??;
+        let mut session = Session::new(tls, addr, wait);
+        session.reply().await?;
+        let extensions = probe_session(&mut session, &mut policy).await?;
+        policy.auth_tls = auth_mechanisms(&extensions);
+        let _ = session.command("QUIT").await;
+        return Ok(policy);
+    }
+
+    let mut session = Session::new(stream, addr, wait);
+    let greeting = session.reply().await?;
+    if greeting.code != 220 {
+        return Err(anyhow!("server not ready: {}", greeting.text()));
+    }
+    let extensions = probe_session(&mut session, &mut policy).await?;
+    policy.auth_plaintext = auth_mechanisms(&extensions);
+    if !policy.starttls_offered {
+        let _ = session.command("QUIT").await;
+        return Ok(policy);
+    }
+
+    let ready = session.command("STARTTLS").await?;
+    if ready.code != 220 {
+        policy.starttls_error = Some(ready.text());
+        return Ok(policy);
+    }
+    crate::scheduler::spend(TLS_HANDSHAKE_BYTES).await;
+    let plain = session.stream.into_inner();
+    match timeout(wait, connector.connect(server_name, plain)).await {
+        Ok(Ok(tls)) => {
+            let mut session = Session::new(tls, addr, wait);
+            match session.ehlo().await {
+                Ok(extensions) => policy.auth_tls = auth_mechanisms(&extensions),
+                Err(e) => policy.starttls_error = Some(e.to_string()),
+            }
+            let _ = session.command("QUIT").await;
+        }
+        Ok(Err(e)) => policy.starttls_error = Some(format!("TLS handshake failed: {}", e)),
+        Err(_) => policy.starttls_error = Some("TLS handshake timed out".to_string()),
+    }
+    Ok(policy)
+}
+
+/// Findings of one port's policy
+fn findings(port: u16, policy: &SmtpPolicy) -> Vec<Finding> {
+    let mut vulns = Vec::new();
+    if policy.open_relay == Some(true) {
+        vulns.push(Finding {
+            id: "SMTP-OPEN-RELAY".to_string(),
+            title: "SMTP open relay".to_string(),
+            description: format!("Accepts mail from {} to {} without authentication ({})", RELAY_SENDER, RELAY_RECIPIENT,
+                                 policy.relay_reply.as_deref().unwrap_or("recipient accepted")),
+            severity: "Critical".to_string(),
+            confidence: Confidence::Confirmed,
+            evidence: policy.relay_reply.iter().cloned().collect(),
+            ..Default::default()
+        });
+    }
+    let exposed: Vec<&str> = policy.auth_plaintext.iter()
+        .map(String::as_str)
+        .filter(|mech| PLAINTEXT_MECHANISMS.contains(mech))
+        .collect();
+    if !exposed.is_empty() {
+        vulns.push(Finding {
+            id: "SMTP-AUTH-CLEARTEXT".to_string(),
+            title: "SMTP authentication before TLS".to_string(),
+            description: format!("Offers AUTH {} before STARTTLS, so passwords can cross the network in clear", exposed.join(" ")),
+            severity: "High".to_string(),
+            confidence: Confidence::Firm,
+            evidence: exposed.iter().map(|m| format!("AUTH {}", m)).collect(),
+            ..Default::default()
+        });
+    }
+    if !policy.implicit_tls && !policy.starttls_offered {
+        vulns.push(Finding {
+            id: "SMTP-NO-STARTTLS".to_string(),
+            title: "SMTP without STARTTLS".to_string(),
+            description: "Does not offer STARTTLS; mail to and from this server travels unencrypted".to_string(),
+            severity: "Medium".to_string(),
+            confidence: Confidence::Firm,
+            ..Default::default()
+        });
+    } else if let Some(error) = &policy.starttls_error {
+        vulns.push(Finding {
+            id: "SMTP-STARTTLS-BROKEN".to_string(),
+            title: "SMTP STARTTLS fails".to_string(),
+            description: format!("Offers STARTTLS but it fails ({}); senders fall back to plaintext", error),
+            severity: "Medium".to_string(),
+            confidence: Confidence::Confirmed,
+            evidence: vec![error.clone()],
+            ..Default::default()
+        });
+    }
+    // Submission ports exist for authenticated clients; taking mail without login there
+    // is a policy gap even if relaying is refused
+    if port == 587 && policy.unauthenticated_mail_accepted == Some(true) && policy.open_relay != Some(true) {
+        vulns.push(Finding {
+            id: "SMTP-SUBMISSION-NO-AUTH".to_string(),
+            title: "Unauthenticated mail submission".to_string(),
+            description: "Submission port accepts MAIL FROM without authentication".to_string(),
+            severity: "Low".to_string(),
+            confidence: Confidence::Confirmed,
+            ..Default::default()
+        });
+    }
+    vulns
+}
+
+/// Check the relay and STARTTLS policy of every SMTP port of a host
+///
+/// # Arguments
+/// * `target` - Host to check
+/// * `hostname` - Name the target was given as, for TLS server name indication
+/// * `results` - Port results after detection; only open ports identified as SMTP are checked
+/// * `wait` - Connect and reply timeout
+///
+/// # Returns
+/// * `Vec<(u16, SmtpPolicy, Vec<Finding>)>` - Policy and findings per checked port
+pub async fn check_smtp(target: IpAddr, hostname: Option<&str>, results: &HashMap<u16, PortResult>, wait: Duration) -> Vec<(u16, SmtpPolicy, Vec<Finding>)> {
+    let mut ports: Vec<u16> = results.iter()
+        .filter(|(_, r)| r.final_status == PortStatus::Open || r.tcp_states.values().any(|&s| s == PortStatus::Open))
+        .filter(|(port, r)| is_smtp(**port, r))
+        .map(|(&port, _)| port)
+        .collect();
+    ports.sort_unstable();
+
+    let mut checked = Vec::new();
+    for port in ports {
+        info!("[SMTP] Checking relay and STARTTLS policy on {}:{}", target, port);
+        match check_port(target, port, hostname, wait).await {
+            Ok(policy) => {
+                let vulns = findings(port, &policy);
+                if policy.open_relay == Some(true) {
+                    warn!("[SMTP] {}:{} is an open relay", target, port);
+                }
+                checked.push((port, policy, vulns));
+            }
+            Err(e) => debug!("[SMTP] Check of {}:{} failed: {}", target, port, e),
+        }
+    }
+    checked
+}
@@ -0,0 +1,127 @@
+//! Certificate harvesting with a list of SNI names.
+//!
+//! Shared hosting, CDN edges and SNI-routing reverse proxies pick the certificate (and
+//! often the backend) by the name in the ClientHello. A handshake without SNI only
+//! shows the default; repeating it for every name in `--sni-list` reveals the other
+//! tenants and internal services the same address fronts. Certificates are grouped by
+//! fingerprint, so each distinct one is reported once with the names that returned it.
+
+use std::collections::HashMap;
+use std::net::IpAddr;
+use std::path::Path;
+use std::sync::Arc;
+use std::time::Duration;
+
+use anyhow::{anyhow, Context, Result};
+use lazy_static::lazy_static;
+use log::{debug, info};
+use parking_lot::RwLock;
+
+use crate::models::{PortResult, SniCertificate};
+
+/// Most names `--sni-list` may hold; every name costs a handshake per TLS port
+const MAX_NAMES: usize = 1000;
+
+lazy_static! {
+    /// Server names to try on every TLS port, from `--sni-list`
+    static ref SNI_NAMES: RwLock<Arc<Vec<String>>> = RwLock::new(Arc::new(Vec::new()));
+}
+
+/// Load the server names tried on TLS ports
+///
+/// # Arguments
+/// * `path` - One name per line; blank lines and `#` comments are skipped
+///
+/// # Returns
+/// * `Result<usize>` - Number of distinct names loaded, or an error for an unreadable
+///   file, an invalid name or more than `MAX_NAMES` names
+pub fn load_list(path: &Path) -> Result<usize> {
+    let text = std::fs::read_to_string(path)
+        .with_context(|| format!("Failed to read SNI list {}", path.display()))?;
+    let mut names: Vec<String> = Vec::new();
+    for (number, line) in text.lines().enumerate() {
+        let name = line.trim().trim_end_matches('.').to_ascii_lowercase();
+        if name.is_empty() || name.starts_with('#') {
+            continue;
+        }
+        if name.parse::<IpAddr>().is_ok() || crate::ssl_config::convert_to_server_name(&name).is_err() {
+            return Err(anyhow!("Invalid SNI list {} line {}: '{}' is not a DNS name", path.display(), number + 1, name));
+        }
+        if !names.contains(&name) {
+            names.push(name);
+        }
+    }
+    if names.len() > MAX_NAMES {
+        return Err(anyhow!("SNI list {} has {} names; at most {} are allowed", path.display(), names.len(), MAX_NAMES));
+    }
+    let count = names.len();
+    *SNI_NAMES.write() = Arc::new(names);
+    Ok(count)
+}
+
+/// Names loaded with `--sni-list`, empty when none were given
+pub fn names() -> Arc<Vec<String>> {
+    SNI_NAMES.read().clone()
+}
+
+/// Whether detection saw TLS on the port
+fn speaks_tls(result: &PortResult) -> bool {
+    result.cert_info.is_some() || result.tls_protocol_version.is_some()
+}
+
+/// Handshake with every listed name on each TLS port and collect the certificates
+///
+/// # Arguments
+/// * `target` - Host to connect to
+/// * `results` - Port results after detection; ports with a certificate or TLS version are tried
+/// * `names` - Server names to send
+/// * `wait` - Connect and handshake timeout
+///
+/// # Returns
+/// * `Vec<(u16, Vec<SniCertificate>)>` - Distinct certificates per port, ports with none left out
+pub async fn harvest(target: IpAddr, results: &HashMap<u16, PortResult>, names: &[String], wait: Duration) -> Vec<(u16, Vec<SniCertificate>)> {
+    let mut ports: Vec<(&u16, &PortResult)> = results.iter().filter(|(_, r)| speaks_tls(r)).collect();
+    ports.sort_by_key(|(port, _)| **port);
+
+    let mut harvested = Vec::new();
+    for (&port, result) in ports {
+        if crate::scheduler::winding_down() {
+            info!("[SNI] Stopping before port {} to stay within --max-scan-time", port);
+            break;
+        }
+        info!("[SNI] Trying {} server names on {}:{}", names.len(), target, port);
+        let default_fingerprint = result.cert_info.as_ref().map(|cert| cert.fingerprint.clone());
+
+        let mut certificates: Vec<SniCertificate> = Vec::new();
+        for name in names {
+            let certificate = match crate::techniques::ssl_scan_with_sni(target, port, wait, Some(name)).await {
+                Ok((_, Some(certificate), _, _)) => certificate,
+                Ok((_, None, _, detail)) => {
+                    debug!("[SNI] {}:{} gave no certificate for {}: {}", target, port, name, detail);
+                    continue;
+                }
+                Err(e) => {
+                    debug!("[SNI] Handshake with {} on {}:{} failed: {}", name, target, port, e);
+                    continue;
+                }
+            };
+            match certificates.iter_mut().find(|known| known.certificate.fingerprint == certificate.fingerprint) {
+                Some(known) => known.names.push(name.clone()),
+                None => certificates.push(SniCertificate {
+                    names: vec![name.clone()],
+                    differs_from_default: default_fingerprint.as_deref() != Some(certificate.fingerprint.as_str()),
+                    certificate,
+                }),
+            }
+        }
+
+        let distinct = certificates.iter().filter(|c| c.differs_from_default).count();
+        if distinct > 0 {
+            info!("[SNI] {}:{} serves {} certificate(s) besides the default", target, port, distinct);
+        }
+        if !certificates.is_empty() {
+            harvested.push((port, certificates));
+        }
+    }
+    harvested
+}
@@ -181,6 +181,73 @@ pub fn default_client_config() -> Result<Arc<ClientConfig>> {
     Ok(Arc::new(config))
 }
 
+/// Creates a TLS client configuration that completes the handshake whatever the
+/// certificate, while recording what standard validation would have decided
+///
+/// Used by the SSL scan so untrusted, expired or self-signed chains are still collected
+/// and analyzed instead of aborting the handshake.
+///
+/// # Arguments
+/// * `roots` - Trust anchors to validate against
+///
+/// # Returns
+/// * `(Arc<ClientConfig>, Option<Arc<TrustRecorder>>, Arc<ClientAuthRecorder>)` - The
+///   configuration, the recorder holding the validation verdict (`None` when no trust
+///   anchors are available, in which case nothing is validated), and the recorder of any
+///   client certificate request
+pub fn recording_tls_config(roots: RootCertStore) -> (Arc<ClientConfig>, Option<Arc<TrustRecorder>>, Arc<ClientAuthRecorder>) {
+    let recorder = if roots.is_empty() {
+        None
+    } else {
+        match rustls::client::WebPkiServerVerifier::builder(Arc::new(roots)).build() {
+            Ok(inner) => Some(Arc::new(TrustRecorder::new(inner))),
+            Err(e) => {
+                warn!("Could not build certificate verifier, chains will not be validated: {}", e);
+                None
+            }
+        }
+    };
+
+    let builder = ClientConfig::builder().dangerous();
+    let config = match &recorder {
+        Some(recorder) => builder.with_custom_certificate_verifier(recorder.clone()),
+        None => builder.with_custom_certificate_verifier(Arc::new(danger::NoCertificateVerification {})),
+    };
+    let client_auth = Arc::new(ClientAuthRecorder::default());
+    (Arc::new(config.with_client_cert_resolver(client_auth.clone())), recorder, client_auth)
+}
+
+/// Client certificate resolver that never presents one but remembers the request
+///
+/// The server's CertificateRequest lists the CAs it accepts client certificates from,
+/// which names the internal PKI behind mutual TLS. An empty certificate is sent, so
+/// servers that require one end the handshake.
+#[derive(Debug, Default)]
+pub struct ClientAuthRecorder {
+    request: parking_lot::Mutex<Option<Vec<Vec<u8>>>>,
+}
+
+impl ClientAuthRecorder {
+    /// DER distinguished names of the acceptable CAs, `None` if no certificate was requested
+    pub fn requested(&self) -> Option<Vec<Vec<u8>>> {
+        self.request.lock().clone()
+    }
+}
+
+impl rustls::client::ResolvesClientCert for ClientAuthRecorder {
+    fn resolve(&self, root_hint_subjects: &[&[u8]], _sigschemes: &[rustls::SignatureScheme]) -> Option<Arc<rustls::sign::CertifiedKey>> {
+        *self.request.lock() = Some(root_hint_subjects.iter().map(|name| name.to_vec()).collect());
+        None
+    }
+
+    fn has_certs(&self) -> bool {
+        // Claimed so rustls consults the resolver on every request
+        true
+    }
+}
+
+pub use danger::TrustRecorder;
+
 // A module for potentially dangerous or insecure configurations.
 mod danger {
     // Necessary imports for implementing the custom verifier.
@@ -189,6 +256,79 @@ mod danger {
     use rustls::{Error, SignatureScheme};
     use rustls::DigitallySignedStruct;
 
+    use std::sync::Arc;
+    use parking_lot::Mutex;
+    use rustls::client::WebPkiServerVerifier;
+    use rustls::CertificateError;
+
+    /// Verifier that runs standard WebPKI validation but never fails the handshake
+    ///
+    /// The verdict is kept for the caller. A name mismatch counts as a valid chain, since
+    /// the scanner connects by IP and checks names separately.
+    #[derive(Debug)]
+    pub struct TrustRecorder {
+        inner: Arc<WebPkiServerVerifier>,
+        verdict: Mutex<Option<Result<(), String>>>,
+        stapled: Mutex<Option<bool>>,
+    }
+
+    impl TrustRecorder {
+        pub(super) fn new(inner: Arc<WebPkiServerVerifier>) -> Self {
+            TrustRecorder { inner, verdict: Mutex::new(None), stapled: Mutex::new(None) }
+        }
+
+        /// `Ok` if the chain validated, `Err` with the reason if not, `None` before the handshake
+        pub fn verdict(&self) -> Option<Result<(), String>> {
+            self.verdict.lock().clone()
+        }
+
+        /// Whether the server stapled an OCSP response, `None` before the handshake
+        pub fn ocsp_stapled(&self) -> Option<bool> {
+            *self.stapled.lock()
+        }
+    }
+
+    impl ServerCertVerifier for TrustRecorder {
+        fn verify_server_cert(
+            &self,
+            end_entity: &CertificateDer<'_>,
+            intermediates: &[CertificateDer<'_>],
+            server_name: &ServerName<'_>,
+            ocsp: &[u8],
+            now: UnixTime,
+        ) -> Result<ServerCertVerified, Error> {
+            let verdict = match self.inner.verify_server_cert(end_entity, intermediates, server_name, ocsp, now) {
+                Ok(_) | Err(Error::InvalidCertificate(CertificateError::NotValidForName)) => Ok(()),
+                Err(e) => Err(e.to_string()),
+            };
+            *self.verdict.lock() = Some(verdict);
+            *self.stapled.lock() = Some(!ocsp.is_empty());
+            Ok(ServerCertVerified::assertion())
+        }
+
+        fn verify_tls12_signature(
+            &self,
+            message: &[u8],
+            cert: &CertificateDer<'_>,
+            dss: &DigitallySignedStruct,
+        ) -> Result<HandshakeSignatureValid, Error> {
+            self.inner.verify_tls12_signature(message, cert, dss)
+        }
+
+        fn verify_tls13_signature(
+            &self,
+            message: &[u8],
+            cert: &CertificateDer<'_>,
+            dss: &DigitallySignedStruct,
+        ) -> Result<HandshakeSignatureValid, Error> {
+            self.inner.verify_tls13_signature(message, cert, dss)
+        }
+
+        fn supported_verify_schemes(&self) -> Vec<SignatureScheme> {
+            self.inner.supported_verify_schemes()
+        }
+    }
+
     // A custom certificate verifier that performs no validation.
     // NOTE: Using this is a security risk! This is only useful in testing environments.
     #[derive(Debug)]
@@ -0,0 +1,159 @@
+//! Stateless (masscan-style) SYN sweeps.
+//!
+//! Instead of waiting on each probe, the sender fires SYNs as fast as the rate limit
+//! allows while an independent receiver collects replies. No per-probe state is kept:
+//! the initial sequence number of every SYN is a keyed hash (SYN cookie) of the
+//! target address and ports, so a reply can be validated by recomputing the cookie
+//! and checking the acknowledgement number. Forged or stray packets fail validation.
+//! Cookies and reply validation come from `correlate`, so the receiver can sit on a
+//! different interface (`--response-iface`) than the one probes leave from.
+//!
+//! Validated open ports are then handed to the regular stateful engine for any
+//! further scan types and service detection.
+
+use std::collections::HashMap;
+use std::net::{IpAddr, Ipv4Addr};
+use std::time::{Duration, Instant};
+
+use anyhow::{anyhow, Result};
+use log::{debug, info, warn};
+use pnet::packet::ip::IpNextHeaderProtocols;
+use pnet::packet::tcp::TcpFlags;
+use pnet::transport::{transport_channel, TransportChannelType};
+
+use crate::correlate::{Correlator, ReplyFilter};
+use crate::models::PortStatus;
+
+/// Outcome of a stateless sweep
+#[derive(Debug, Default)]
+pub struct StatelessResults {
+    /// Port states for every port that sent a validated reply, with the reason
+    pub responses: HashMap<u16, (PortStatus, String)>,
+    /// Number of SYN probes sent
+    pub probes_sent: usize,
+    /// Replies that failed cookie validation (stray traffic or spoofing)
+    pub invalid_replies: usize,
+}
+
+/// Run a stateless SYN sweep against a single target
+///
+/// # Arguments
+/// * `target_ip` - Target IPv4 address
+/// * `local_ip` - Local IPv4 address to source probes from
+/// * `ports` - Ports to probe
+/// * `max_rate` - Maximum probes per second (0 = unlimited)
+/// * `wait` - How long to keep listening after the last probe
+/// * `ttl` - TTL for outgoing probes
+///
+/// # Returns
+/// * `Result<StatelessResults>` - Validated replies and sweep counters
+///
+/// # Opsec Considerations
+/// - Very high packet rates are trivially detected; use `--rate` to cap throughput
+/// - Uses one source port for the whole sweep, which is a recognisable pattern
+/// - Replies are not retransmitted; lossy paths will under-report open ports
+pub async fn syn_sweep(
+    target_ip: Ipv4Addr,
+    local_ip: Ipv4Addr,
+    ports: &[u16],
+    max_rate: usize,
+    wait: Duration,
+    ttl: u8,
+) -> Result<StatelessResults> {
+    let key = crate::correlate::probe_key();
+    let source_port = crate::utils::random_high_port();
+    let ports = ports.to_vec();
+
+    info!("[Stateless] Sweeping {} ports on {} from source port {}", ports.len(), target_ip, source_port);
+
+    // With --spoof-mac every probe goes out as a spoofed frame; fail before sending any
+    if crate::mac_vendor::spoofed_mac().is_some() {
+        crate::mac_vendor::prepare_spoofed_frame(IpAddr::V4(local_ip), IpAddr::V4(target_ip)).await
+            .map_err(|e| anyhow!("Cannot sweep {} with the spoofed MAC: {}", target_ip, e))?;
+    }
+
+    // Receiver: validates every reply against the probe key
+    let correlator = Correlator::start(
+        key.clone(),
+        crate::correlate::capture_source(),
+        ReplyFilter { targets: vec![IpAddr::V4(target_ip)], source_port: Some(source_port) },
+    )?;
+
+    // Sender: fire probes without waiting, through the fast I/O ring if enabled
+    let fast_io = crate::fast_io::engine();
+    let (mut tx, _) = transport_channel(1 << 20, TransportChannelType::Layer3(IpNextHeaderProtocols::Tcp))
+        .map_err(|e| anyhow!("Failed to open raw send socket (requires root/admin privileges): {}", e))?;
+    crate::netbind::bind_raw(&tx)?;
+
+    let interval = if max_rate > 0 {
+        Some(Duration::from_secs_f64(1.0 / max_rate as f64))
+    } else {
+        None
+    };
+
+    let mut probes_sent = 0;
+    let started = Instant::now();
+    let mut packet_buf = vec![0u8; 128];
+    let shape = crate::evasion::ProbeShape { ttl, ..crate::evasion::ProbeShape::new(false) };
+
+    for (index, &port) in ports.iter().enumerate() {
+        let seq = key.tag(IpAddr::V4(target_ip), port, source_port);
+        let len = crate::techniques::build_tcp_packet_v4(
+            &mut packet_buf, local_ip, target_ip, source_port, port, TcpFlags::SYN as u8, seq, &shape,
+        );
+
+        crate::scheduler::spend(len).await;
+        crate::audit::record(|| crate::audit::Probe::new("stateless-syn", "tcp", target_ip, Some(port), len)
+            .source(IpAddr::V4(local_ip), Some(source_port)).detail("SYN").shape(&shape));
+        let sent = match &fast_io {
+            Some(engine) => engine.send_only(target_ip, &packet_buf[..len]).await.is_ok(),
+            None => match crate::mac_vendor::send_spoofed(IpAddr::V4(local_ip), IpAddr::V4(target_ip), &packet_buf[..len]).await {
+                Ok(true) => true,
+                Ok(false) => match pnet::packet::ipv4::Ipv4Packet::new(&packet_buf[..len]) {
+                    Some(packet) => tx.send_to(packet, IpAddr::V4(target_ip)).is_ok(),
+                    None => false,
+                },
+                Err(e) => {
+                    debug!("[Stateless] Spoofed frame to {}:{} failed: {}", target_ip, port, e);
+                    false
+                }
+            },
+        };
+
+        if sent {
+            probes_sent += 1;
+        } else {
+            warn!("[Stateless] Failed to send probe to {}:{}", target_ip, port);
+        }
+
+        // Pace against the wall clock so scheduling jitter doesn't accumulate
+        if let Some(interval) = interval {
+            let due = interval * (index as u32 + 1);
+            let elapsed = started.elapsed();
+            if due > elapsed {
+                tokio::time::sleep(due - elapsed).await;
+            }
+        } else if index % 256 == 255 {
+            tokio::task::yield_now().await;
+        }
+    }
+
+    debug!("[Stateless] {} probes sent in {:?}, waiting {:?} for stragglers", probes_sent, started.elapsed(), wait);
+    tokio::time::sleep(wait).await;
+
+    let correlated = correlator.stop().await?;
+    let responses = correlated.replies.into_values()
+        .filter_map(|reply| match reply.status? {
+            PortStatus::Open => Some((reply.port, (PortStatus::Open, "syn-ack (stateless)".to_string()))),
+            status => Some((reply.port, (status, "rst (stateless)".to_string()))),
+        })
+        .collect();
+    let results = StatelessResults { responses, probes_sent, invalid_replies: correlated.invalid_replies };
+
+    info!("[Stateless] Sweep complete: {} validated replies ({} open), {} invalid",
+          results.responses.len(),
+          results.responses.values().filter(|(status, _)| *status == PortStatus::Open).count(),
+          results.invalid_replies);
+
+    Ok(results)
+}
@@ -0,0 +1,79 @@
+//! Targets streamed from stdin.
+//!
+//! With `-` as the target, hosts are read one per line from stdin and each is scanned as
+//! soon as its line arrives, so the scanner can sit at the end of a discovery pipeline
+//! (`amass enum -d example.com | quantum_scanner - -T`) instead of waiting for the
+//! enumeration to finish. A line holds an IP address, a CIDR range or a hostname; blank
+//! lines and `#` comments are skipped.
+
+use std::io::BufRead;
+use std::net::IpAddr;
+
+use anyhow::{anyhow, Result};
+use log::{debug, warn};
+use tokio::sync::mpsc;
+
+/// Target argument that selects stdin
+pub const STDIN_TARGET: &str = "-";
+
+/// Start reading target lines from stdin on a separate thread
+///
+/// # Returns
+/// * `mpsc::UnboundedReceiver<String>` - Trimmed target lines, in input order; closes at EOF
+pub fn spawn_stdin_reader() -> mpsc::UnboundedReceiver<String> {
+    let (tx, rx) = mpsc::unbounded_channel();
+    // Blocking reads on a plain thread, so a slow producer never stalls the runtime
+    std::thread::spawn(move || {
+        for line in std::io::stdin().lock().lines() {
+            let line = match line {
+                Ok(line) => line,
+                Err(e) => {
+                    warn!("[Stdin] Stopped reading targets: {}", e);
+                    break;
+                }
+            };
+            let target = line.split('#').next().unwrap_or_default().trim();
+            if target.is_empty() {
+                continue;
+            }
+            if tx.send(target.to_string()).is_err() {
+                break;
+            }
+        }
+        debug!("[Stdin] End of target input");
+    });
+    rx
+}
+
+/// Turn one target line into the hosts to scan
+///
+/// # Arguments
+/// * `line` - IP address, CIDR range or hostname
+/// * `ipv6` - Prefer an IPv6 address for hostnames; without it only IPv4 is used
+/// * `max_hosts` - Largest CIDR range accepted
+///
+/// # Returns
+/// * `Result<Vec<(String, IpAddr)>>` - Target name and address of each host
+pub async fn expand(line: &str, ipv6: bool, max_hosts: usize) -> Result<Vec<(String, IpAddr)>> {
+    if let Ok(ip) = line.parse::<IpAddr>() {
+        return Ok(vec![(ip.to_string(), ip)]);
+    }
+    if let Ok(net) = line.parse::<ipnet::IpNet>() {
+        if net.hosts().take(max_hosts + 1).count() > max_hosts {
+            return Err(anyhow!("{} has more than {} hosts", line, max_hosts));
+        }
+        return Ok(net.hosts().map(|ip| (ip.to_string(), ip)).collect());
+    }
+
+    let addrs: Vec<IpAddr> = tokio::net::lookup_host(format!("{}:0", line)).await
+        .map_err(|e| anyhow!("Could not resolve {}: {}", line, e))?
+        .map(|addr| addr.ip())
+        .collect();
+    // Without --ipv6 only IPv4 is scanned, as for a hostname given on the command line
+    let ip = addrs.iter().find(|ip| ip.is_ipv6() == ipv6)
+        .or_else(|| addrs.iter().find(|ip| ipv6 && ip.is_ipv4()))
+        .copied()
+        .ok_or_else(|| anyhow!("{} has no {} address", line, if ipv6 { "IP" } else { "IPv4" }))?;
+    debug!("[Stdin] {} resolved to {}", line, ip);
+    Ok(vec![(line.to_string(), ip)])
+}
@@ -0,0 +1,204 @@
+//! Per-host technique selection.
+//!
+//! FIN, NULL and XMAS scans rely on RFC 793: an open port ignores a segment without
+//! SYN, RST or ACK while a closed one answers with a RST. Windows, many network devices
+//! and some mainframes answer every such segment with a RST, so those scans report every
+//! port closed. ACK and Window scans only say something when a filter is in the path or
+//! the stack leaks port state through the RST window. With `--auto-technique` a handful of
+//! probes before the main scan find out how the host behaves, and the scan then uses the
+//! TCP techniques that give real answers against it.
+//!
+//! The pre-probe reads the initial TTL from ICMP replies, finds an open and a closed
+//! port with SYNs, then checks how both answer FIN, ACK and Window probes.
+
+use std::net::IpAddr;
+use std::time::Duration;
+
+use log::{debug, info};
+
+use crate::models::{PortStatus, ScanType, TechniqueSelection};
+use crate::techniques;
+
+/// Ports tried first when looking for an open port
+const PREFERRED_PORTS: [u16; 8] = [80, 443, 22, 445, 3389, 8080, 25, 53];
+
+/// SYN probes spent looking for an open port
+const MAX_OPEN_PROBES: usize = 4;
+
+/// TCP techniques the selection decides on; other requested scan types are left as they are
+const SELECTABLE: [ScanType; 7] = [
+    ScanType::Syn, ScanType::Ack, ScanType::Fin, ScanType::Xmas, ScanType::Null, ScanType::Window, ScanType::Maimon,
+];
+
+/// Whether `scan_type` is one the selection replaces
+pub fn is_selectable(scan_type: ScanType) -> bool {
+    SELECTABLE.contains(&scan_type)
+}
+
+/// Stack family implied by a received TTL, from the usual initial TTLs 64, 128 and 255
+fn os_family(reply_ttl: u8) -> (u8, &'static str) {
+    match reply_ttl {
+        0..=64 => (64, "Linux/Unix"),
+        65..=128 => (128, "Windows"),
+        _ => (255, "Network device"),
+    }
+}
+
+/// Probe a host and choose the TCP techniques worth running against it
+///
+/// # Arguments
+/// * `target` - Host to probe
+/// * `ports` - Ports of the scan; open-port candidates are taken from them
+/// * `local_ip` - Source address for raw probes
+/// * `use_ipv6` - Whether raw probes go out over IPv6
+/// * `timeout` - Timeout of each probe
+///
+/// # Returns
+/// * `TechniqueSelection` - Chosen techniques with the observations behind them. SYN is
+///   always included, since it is the only one whose answer never depends on the stack.
+///
+/// # Opsec Considerations
+/// Up to nine raw TCP probes and, on IPv4, two ICMP requests and a UDP datagram go to the
+/// host before the scan. FIN and ACK probes outside a connection are what stealth-scan
+/// signatures match, so this is not for targets where a single alert matters.
+pub async fn select(target: IpAddr, ports: &[u16], local_ip: Option<IpAddr>, use_ipv6: bool, timeout: Duration) -> TechniqueSelection {
+    let mut selection = TechniqueSelection::default();
+    let mut reasons = Vec::new();
+
+    // TTL analysis; ICMP probing is IPv4-only
+    if target.is_ipv4() {
+        crate::scheduler::wait_turn().await;
+        match techniques::icmp_host_probe(target, local_ip, crate::utils::random_high_port(), timeout).await {
+            Ok(info) => {
+                if let Some(ttl) = info.reply_ttl {
+                    let (initial, family) = os_family(ttl);
+                    selection.initial_ttl = Some(initial);
+                    selection.os_family = Some(family.to_string());
+                    reasons.push(format!("ICMP reply TTL {} suggests initial TTL {} ({})", ttl, initial, family));
+                }
+            }
+            Err(e) => debug!("[Auto-Technique] {}: ICMP probing failed: {}", target, e),
+        }
+    }
+
+    let syn = |port: u16| async move {
+        crate::scheduler::wait_turn().await;
+        techniques::syn_scan(target, port, local_ip, use_ipv6, timeout, false, "", 0).await.unwrap_or(PortStatus::Filtered)
+    };
+
+    // An open port, trying well-known ones first
+    let mut candidates: Vec<u16> = PREFERRED_PORTS.iter().copied().filter(|p| ports.contains(p)).collect();
+    candidates.extend(ports.iter().copied().filter(|p| !PREFERRED_PORTS.contains(p)));
+    candidates.truncate(MAX_OPEN_PROBES);
+    let mut open_port = None;
+    let mut closed_port = None;
+    let mut syn_filtered = false;
+    for port in candidates {
+        match syn(port).await {
+            PortStatus::Open => {
+                open_port = Some(port);
+                break;
+            }
+            PortStatus::Closed => closed_port = closed_port.or(Some(port)),
+            _ => syn_filtered = true,
+        }
+    }
+
+    // A closed port: a random high port almost always is, unless a filter drops it
+    if closed_port.is_none() {
+        let port = crate::utils::random_high_port();
+        match syn(port).await {
+            PortStatus::Closed => closed_port = Some(port),
+            PortStatus::Open => open_port = open_port.or(Some(port)),
+            _ => syn_filtered = true,
+        }
+    }
+    match (open_port, closed_port) {
+        (Some(open), Some(closed)) => reasons.push(format!("SYN probes found open port {} and closed port {}", open, closed)),
+        (Some(open), None) => reasons.push(format!("SYN probes found open port {}; no closed port answered", open)),
+        (None, Some(closed)) => reasons.push(format!("SYN probes found closed port {}; no open port among the candidates", closed)),
+        (None, None) => reasons.push("No SYN probe was answered; the host is filtered or down".to_string()),
+    }
+
+    let mut scan_types = vec![ScanType::Syn];
+
+    // FIN semantics: silence from an open port and a RST from a closed one
+    let fin = |port: u16| async move {
+        crate::scheduler::wait_turn().await;
+        techniques::fin_scan(target, port, local_ip, use_ipv6, timeout, false, "", 0).await.unwrap_or(PortStatus::Filtered)
+    };
+    let fin_open = match open_port {
+        Some(port) => Some(fin(port).await),
+        None => None,
+    };
+    let fin_closed = match closed_port {
+        Some(port) => Some(fin(port).await),
+        None => None,
+    };
+    let windows_ttl = selection.os_family.as_deref() == Some("Windows");
+    match (fin_open, fin_closed) {
+        (Some(PortStatus::Closed), _) => {
+            reasons.push("Open port answered FIN with RST: the stack ignores RFC 793, so FIN/NULL/XMAS would report every port closed".to_string());
+        }
+        (Some(PortStatus::OpenFiltered), Some(PortStatus::Closed)) => {
+            reasons.push("Open port ignored FIN and closed port answered RST: FIN/NULL/XMAS tell them apart".to_string());
+            scan_types.push(ScanType::Fin);
+        }
+        (None, Some(PortStatus::Closed)) if !windows_ttl => {
+            reasons.push("Closed port answered FIN with RST and the TTL does not point to Windows: FIN scans are likely meaningful".to_string());
+            scan_types.push(ScanType::Fin);
+        }
+        (None, Some(PortStatus::Closed)) => {
+            reasons.push("Windows TTL and no open port to verify FIN semantics on: FIN/NULL/XMAS skipped".to_string());
+        }
+        (_, Some(PortStatus::Closed)) => {
+            reasons.push("Open port answered FIN with an ICMP error: a filter sits in front of it, FIN/NULL/XMAS skipped".to_string());
+        }
+        (_, Some(_)) => {
+            reasons.push("Closed port did not answer FIN: a filter drops stray FINs, so FIN/NULL/XMAS cannot tell open from filtered".to_string());
+        }
+        (_, None) => reasons.push("No closed port to verify FIN semantics on: FIN/NULL/XMAS skipped".to_string()),
+    }
+
+    // ACK behaviour: with filtering seen, ACKs show whether the filter is stateful
+    if let Some(port) = closed_port.or(open_port) {
+        crate::scheduler::wait_turn().await;
+        let ack = techniques::ack_scan(target, port, local_ip, use_ipv6, timeout, false, "", 0).await
+            .map(|(status, _)| status)
+            .unwrap_or(PortStatus::Filtered);
+        match (ack, syn_filtered) {
+            (PortStatus::Unfiltered, true) => {
+                reasons.push(format!("ACK to port {} drew a RST while other SYNs went unanswered: a stateless filter is in the path, ACK scans map its rules", port));
+                scan_types.push(ScanType::Ack);
+            }
+            (PortStatus::Unfiltered, false) => {
+                reasons.push(format!("ACK to port {} drew a RST and no SYN was filtered: ACK scans would only report unfiltered", port));
+            }
+            (_, _) => {
+                reasons.push(format!("ACK to port {} went unanswered although SYNs reach the host: a stateful firewall drops out-of-state segments", port));
+            }
+        }
+    } else {
+        reasons.push("Host answered nothing: ACK scan kept to find out whether a firewall is stateful".to_string());
+        scan_types.push(ScanType::Ack);
+    }
+
+    // Window leak: RSTs from open ports carry a non-zero window on some stacks
+    if let (Some(open), Some(closed)) = (open_port, closed_port) {
+        let window = |port: u16| async move {
+            crate::scheduler::wait_turn().await;
+            techniques::window_scan(target, port, local_ip, use_ipv6, timeout, false, "", 0).await.unwrap_or(PortStatus::Filtered)
+        };
+        if window(open).await == PortStatus::Open && window(closed).await == PortStatus::Closed {
+            reasons.push("RST windows differ between open and closed ports: Window scans reveal port state through filters that pass ACKs".to_string());
+            scan_types.push(ScanType::Window);
+        } else {
+            debug!("[Auto-Technique] {}: RST window does not reveal port state", target);
+        }
+    }
+
+    info!("[Auto-Technique] {}: selected {:?}", target, scan_types);
+    selection.scan_types = scan_types;
+    selection.reasons = reasons;
+    selection
+}
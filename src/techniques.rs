@@ -4,8 +4,8 @@ use std::time::{Duration, Instant};
 
 use anyhow::{Result, anyhow};
 use log::{debug, error, warn};
-use rand::{thread_rng, Rng};
-use rustls::{ClientConfig, ClientConnection, RootCertStore};
+use rand::Rng;
+use rustls::{ClientConnection, RootCertStore};
 // Import types from rustls::pki_types
 use rustls::pki_types::ServerName;
 
@@ -13,7 +13,6 @@ use rustls::pki_types::ServerName;
 // Don't remove this import, even if it appears unused
 #[allow(unused_imports)]
 use sha2::{Sha256, Digest};
-use tokio::net::{UdpSocket}; // Removed TcpSocket import
 use tokio::time::{timeout};
 // Comment out the unused import
 // use webpki_roots::TLS_SERVER_ROOTS;
@@ -24,16 +23,11 @@ use pnet::packet::Packet;
 use pnet::packet::ip::IpNextHeaderProtocols;
 use pnet::packet::ipv4::{MutableIpv4Packet, Ipv4Flags/*, Ipv4Packet*/};
 use pnet::packet::ipv6::{MutableIpv6Packet/*, Ipv6Packet*/};
-use pnet::packet::tcp::{MutableTcpPacket, TcpFlags, TcpOption};
+use pnet::packet::tcp::{MutableTcpPacket, TcpFlags};
 // Removed unused udp imports: MutableUdpPacket, UdpPacket
 use pnet::transport::{self, /*TransportReceiver, TransportSender,*/ TransportChannelType, transport_channel};
 // --- End pnet imports ---
 
-// --- Add ICMP/ICMPv6 imports ---
-use pnet::packet::icmp::{self, IcmpTypes};
-use pnet::packet::icmpv6::{self/*, Icmpv6Types*/};
-// --- End pnet imports ---
-
 // --- CORRECTED: IPv6 Fragment Header is an Extension Header, not a separate module ---
 // Use types related to IPv6 extension headers generally
 // Removed unused commented out imports
@@ -53,7 +47,9 @@ use x509_parser::public_key::PublicKey;
 // #[cfg(not(feature = "minimal-static"))]
 // use x509_parser::objects::oid_registry;
 
-use crate::models::{CertificateInfo, PortStatus};
+use crate::evasion::ProbeShape;
+use crate::transport::{RawResponse, TcpProbe};
+use crate::models::{CertificateInfo, ClientAuthInfo, PortStatus};
 use crate::utils; // Use utils module directly for random_high_port and find_local_ipv4
 
 // --- Raw Socket Helper Functions ---
@@ -64,10 +60,17 @@ const TCP_HEADER_LEN: usize = 20; // Base TCP header length
 const TCP_HEADER_LEN_WITH_OPTIONS: usize = 32; // Example with MSS option
 // const MSS_OPTION_KIND: u8 = 2; // Not directly needed
 
+/// Final TCP checksum for a probe: corrupted when the shape asks for a bad checksum.
+/// XOR with a constant never maps a value onto its one's-complement twin (0x0000/0xFFFF),
+/// so the result is always invalid.
+fn finish_checksum(checksum: u16, shape: &ProbeShape) -> u16 {
+    if shape.bad_checksum { checksum ^ 0xDEAD } else { checksum }
+}
+
 /// Creates an IPv4/TCP packet buffer and populates headers.
 /// Requires a mutable buffer slice `packet_buf` large enough for IPv4 + TCP headers + payload.
 /// Returns the size of the constructed packet.
-fn build_tcp_packet_v4(
+pub(crate) fn build_tcp_packet_v4(
     packet_buf: &mut [u8],
     source_ip: Ipv4Addr,
     dest_ip: Ipv4Addr,
@@ -75,28 +78,33 @@ fn build_tcp_packet_v4(
     dest_port: u16,
     tcp_flags: u8,
     seq_num: u32,
-    ttl: u8,
+    shape: &ProbeShape,
 ) -> usize {
-    let tcp_options = [TcpOption::mss(1460)]; // Set Maximum Segment Size
-    let tcp_header_len = TCP_HEADER_LEN + (tcp_options.len() * 4);
-    let total_len = IPV4_HEADER_LEN + tcp_header_len;
+    let tcp_header_len = TCP_HEADER_LEN + shape.tcp_options_len(tcp_flags);
+    let padding_len = shape.padding_len(IPV4_HEADER_LEN + tcp_header_len);
+    let total_len = IPV4_HEADER_LEN + tcp_header_len + padding_len;
 
     if packet_buf.len() < total_len {
         panic!("Packet buffer too small ({} bytes) for IPv4+TCP headers ({} bytes)", packet_buf.len(), total_len);
     }
 
+    // Random padding payload requested by the evasion pipeline (covered by the TCP checksum)
+    utils::scan_rng().fill(&mut packet_buf[IPV4_HEADER_LEN + tcp_header_len..total_len]);
+
     // Setup IP header
     {
         let mut ip_header = MutableIpv4Packet::new(&mut packet_buf[..total_len]).unwrap();
         ip_header.set_version(4);
         ip_header.set_header_length(5);
         ip_header.set_total_length(total_len as u16);
-        ip_header.set_ttl(ttl);
+        ip_header.set_ttl(shape.ttl);
         ip_header.set_next_level_protocol(IpNextHeaderProtocols::Tcp);
         ip_header.set_source(source_ip);
         ip_header.set_destination(dest_ip);
-        ip_header.set_flags(Ipv4Flags::DontFragment);
-        ip_header.set_identification(rand::thread_rng().gen());
+        if shape.dont_fragment {
+            ip_header.set_flags(Ipv4Flags::DontFragment);
+        }
+        ip_header.set_identification(utils::scan_rng().gen());
     }
 
     // Setup TCP header
@@ -108,14 +116,14 @@ fn build_tcp_packet_v4(
         tcp_header.set_acknowledgement(0);
         tcp_header.set_data_offset(((tcp_header_len) / 4) as u8);
         // Cast tcp_flags (u8) to u16 for set_flags
-        tcp_header.set_flags(tcp_flags.into());
-        tcp_header.set_window(1024);
+        tcp_header.set_flags((tcp_flags | shape.extra_flags).into());
+        tcp_header.set_window(shape.window);
         tcp_header.set_urgent_ptr(0);
         tcp_header.set_checksum(0); // Zero out for calculation
-        tcp_header.set_options(&tcp_options);
+        tcp_header.set_options(shape.options_for(tcp_flags));
         // Use pnet::packet::tcp::ipv4_checksum for consistency
         let tcp_checksum = pnet::packet::tcp::ipv4_checksum(&tcp_header.to_immutable(), &source_ip, &dest_ip);
-        tcp_header.set_checksum(tcp_checksum);
+        tcp_header.set_checksum(finish_checksum(tcp_checksum, shape));
     }
 
     // Calculate and set IP checksum (must be done last)
@@ -132,7 +140,7 @@ fn build_tcp_packet_v4(
 /// Creates an IPv6/TCP packet buffer and populates headers.
 /// Requires a mutable buffer slice `packet_buf` large enough for IPv6 + TCP headers + payload.
 /// Returns the size of the constructed packet.
-fn build_tcp_packet_v6(
+pub(crate) fn build_tcp_packet_v6(
     packet_buf: &mut [u8],
     source_ip: std::net::Ipv6Addr,
     dest_ip: std::net::Ipv6Addr,
@@ -140,17 +148,20 @@ fn build_tcp_packet_v6(
     dest_port: u16,
     tcp_flags: u8,
     seq_num: u32,
-    hop_limit: u8,
+    shape: &ProbeShape,
 ) -> usize {
-    let tcp_options = [TcpOption::mss(1440)]; // MSS for IPv6 is typically smaller (1440 vs 1460)
-    let tcp_header_len = TCP_HEADER_LEN + (tcp_options.len() * 4);
-    let total_len = IPV6_HEADER_LEN + tcp_header_len;
-    let payload_len = tcp_header_len;
+    let tcp_header_len = TCP_HEADER_LEN + shape.tcp_options_len(tcp_flags);
+    let padding_len = shape.padding_len(IPV6_HEADER_LEN + tcp_header_len);
+    let total_len = IPV6_HEADER_LEN + tcp_header_len + padding_len;
+    let payload_len = tcp_header_len + padding_len;
 
     if packet_buf.len() < total_len {
         panic!("Packet buffer too small ({} bytes) for IPv6+TCP headers ({} bytes)", packet_buf.len(), total_len);
     }
 
+    // Random padding payload requested by the evasion pipeline (covered by the TCP checksum)
+    utils::scan_rng().fill(&mut packet_buf[IPV6_HEADER_LEN + tcp_header_len..total_len]);
+
     // Setup IPv6 header
     {
         let mut ip_header = MutableIpv6Packet::new(&mut packet_buf[..total_len]).unwrap();
@@ -159,7 +170,7 @@ fn build_tcp_packet_v6(
         ip_header.set_flow_label(0);    // No specific flow label
         ip_header.set_payload_length(payload_len as u16);
         ip_header.set_next_header(IpNextHeaderProtocols::Tcp);
-        ip_header.set_hop_limit(hop_limit);
+        ip_header.set_hop_limit(shape.ttl);
         ip_header.set_source(source_ip);
         ip_header.set_destination(dest_ip);
         // Note: IPv6 checksum is not in the header, it's handled by layers above/below
@@ -174,10 +185,10 @@ fn build_tcp_packet_v6(
         tcp_header.set_acknowledgement(0);
         tcp_header.set_data_offset(((tcp_header_len) / 4) as u8); // Data offset in 32-bit words
         // Cast tcp_flags (u8) to u16 for set_flags
-        tcp_header.set_flags(tcp_flags.into());
-        tcp_header.set_window(1024);
+        tcp_header.set_flags((tcp_flags | shape.extra_flags).into());
+        tcp_header.set_window(shape.window);
         tcp_header.set_urgent_ptr(0);
-        tcp_header.set_options(&tcp_options);
+        tcp_header.set_options(shape.options_for(tcp_flags));
         tcp_header.set_checksum(0); // Zero out for calculation
 
         // Calculate TCP checksum using the IPv6 pseudo-header
@@ -187,200 +198,61 @@ fn build_tcp_packet_v6(
             &source_ip,
             &dest_ip
         );
-        tcp_header.set_checksum(tcp_checksum);
+        tcp_header.set_checksum(finish_checksum(tcp_checksum, shape));
     }
     // IPv6 does not have a header checksum calculated at the IP layer.
 
     total_len
 }
 
-/// Sends a raw TCP packet (IPv4 or IPv6) and waits for a response matching the probe.
-/// Requires root/administrator privileges.
-/// Returns Ok(Some(received_tcp_packet)) on match, Ok(None) on timeout/filter/error, Err(e) on setup/send error.
+/// Splits a complete IPv4 packet into fragments carrying `fragment_size` bytes of
+/// payload each (rounded down to a multiple of 8). All fragments share the original
+/// identification and TTL; the checksum is recomputed per fragment.
+pub(crate) fn fragment_ipv4_packet(packet: &[u8], fragment_size: usize) -> Vec<Vec<u8>> {
+    let fragment_size = (fragment_size / 8).max(1) * 8;
+    let payload = &packet[IPV4_HEADER_LEN..];
+    let mut fragments = Vec::with_capacity(payload.len().div_ceil(fragment_size));
+    let mut offset = 0;
+
+    while offset < payload.len() {
+        let chunk = &payload[offset..(offset + fragment_size).min(payload.len())];
+        let is_last = offset + chunk.len() == payload.len();
+        let mut buf = vec![0u8; IPV4_HEADER_LEN + chunk.len()];
+        buf[..IPV4_HEADER_LEN].copy_from_slice(&packet[..IPV4_HEADER_LEN]);
+        buf[IPV4_HEADER_LEN..].copy_from_slice(chunk);
+        {
+            let mut ip_header = MutableIpv4Packet::new(&mut buf).unwrap();
+            ip_header.set_total_length((IPV4_HEADER_LEN + chunk.len()) as u16);
+            ip_header.set_fragment_offset((offset / 8) as u16);
+            ip_header.set_flags(if is_last { 0 } else { Ipv4Flags::MoreFragments });
+            ip_header.set_checksum(0);
+            let checksum = pnet::packet::ipv4::checksum(&ip_header.to_immutable());
+            ip_header.set_checksum(checksum);
+        }
+        fragments.push(buf);
+        offset += chunk.len();
+    }
+
+    fragments
+}
+
+/// Sends a raw TCP probe (IPv4 or IPv6) through the current transport and waits for a
+/// response matching it. On the wire this requires root/administrator privileges.
+/// Returns Ok(Some(response)) on match or timeout, Err(e) on setup/send error.
 async fn send_receive_raw_tcp(
     target_ip: IpAddr,
     port: u16,
     local_ip: IpAddr, // Accept generic IpAddr (must match target_ip family)
     tcp_flags_out: u8, // Keep as u8
     timeout_duration: Duration,
-    ttl: u8, // <-- Add ttl parameter
+    shape: &ProbeShape,
 ) -> Result<Option<RawResponse>> {
-    let source_port = utils::random_high_port();
-    let seq_num: u32 = thread_rng().gen();
-
-    // Enforce a reasonable minimum timeout (500ms) and maximum timeout (30s)
-    let timeout_duration = if timeout_duration.as_millis() < 2000 {
-        warn!("[Raw TCP:{}:{}] Timeout too short ({}ms), increasing to 2000ms", target_ip, port, timeout_duration.as_millis());
-        Duration::from_millis(2000)
-    } else if timeout_duration.as_secs() > 30 {
-        Duration::from_secs(30)
-    } else {
-        timeout_duration
-    };
-
-    // Determine IP version and select appropriate protocol for transport channel
-    let protocol = match target_ip {
-        IpAddr::V4(_) => TransportChannelType::Layer4(transport::TransportProtocol::Ipv4(IpNextHeaderProtocols::Tcp)),
-        IpAddr::V6(_) => TransportChannelType::Layer4(transport::TransportProtocol::Ipv6(IpNextHeaderProtocols::Tcp)),
-    };
-    
-    // Create a single channel for both sending and receiving to avoid packet loss
-    // This is important as creating a second channel might cause early responses to be missed
-    let (mut tx, mut rx) = match transport_channel(4096, protocol) {
-        Ok((tx, rx)) => (tx, rx),
-        Err(e) => return Err(anyhow!("Failed to open raw socket channel for {} (requires root/admin privileges): {}", target_ip.is_ipv4().then(|| "IPv4").unwrap_or("IPv6"), e)),
-    };
-
-    // Build packet based on IP version
-    let mut packet_buf = vec![0u8; 128]; // Buffer needs to be large enough for largest header combo (IPv6+TCP w/ Options)
-    let packet_len = match (target_ip, local_ip) {
-        (IpAddr::V4(dest_ip_v4), IpAddr::V4(local_ip_v4)) => {
-            build_tcp_packet_v4(&mut packet_buf, local_ip_v4, dest_ip_v4, source_port, port, tcp_flags_out, seq_num, ttl)
-        }
-        (IpAddr::V6(dest_ip_v6), IpAddr::V6(local_ip_v6)) => {
-            build_tcp_packet_v6(&mut packet_buf, local_ip_v6, dest_ip_v6, source_port, port, tcp_flags_out, seq_num, ttl)
-        }
-        _ => return Err(anyhow!("IP address family mismatch between target ({}) and local ({}) IP for raw socket scan", target_ip, local_ip)),
-    };
-
-    // Ensure buffer is large enough (build functions might panic, but double check)
-    if packet_buf.len() < packet_len {
-        // This case should ideally be prevented by the build functions' checks
-        return Err(anyhow!("Internal error: Packet buffer too small after building headers."));
-    }
-    // Trim buffer to actual packet length before creating the final packet view
-    packet_buf.truncate(packet_len);
-
-    // Setup a TCP packet iterator for receiving responses before sending
-    // This ensures we don't miss any responses that come back quickly
-    let mut iter = transport::tcp_packet_iter(&mut rx);
-
-    // Send packet
-    match target_ip {
-        IpAddr::V4(_) => {
-            let ipv4_packet_to_send = MutableIpv4Packet::new(&mut packet_buf).unwrap();
-            // Use packet() method from Packet trait
-            if tx.send_to(ipv4_packet_to_send.to_immutable(), target_ip).is_err() {
-                return Err(anyhow!("Failed to send raw IPv4 packet to {}", target_ip));
-            }
-        }
-        IpAddr::V6(_) => {
-            let ipv6_packet_to_send = MutableIpv6Packet::new(&mut packet_buf).unwrap();
-            // Use packet() method from Packet trait
-            if tx.send_to(ipv6_packet_to_send.to_immutable(), target_ip).is_err() {
-                return Err(anyhow!("Failed to send raw IPv6 packet to {}", target_ip));
-            }
-        }
-    }
-    debug!("[Raw TCP:{}:{}] Sent flags {:#04x} from port {} ({} -> {})", target_ip, port, tcp_flags_out, source_port, local_ip, target_ip);
-
-    // Use tokio's timeout for the receive wait loop instead of manual time tracking
-    // This is more efficient and ensures we don't hang indefinitely
-    let receive_future = async {
-        // Set a reasonable number of iterations to check for packets
-        // This prevents excessive CPU spinning while still allowing fast response
-        let max_iterations = 50;
-        let sleep_interval = Duration::from_millis(5);
-        let mut iterations = 0;
-        
-        loop {
-            match iter.next() {
-                Ok((packet, addr)) => {
-                    // Log all received packets for debugging
-                    debug!("[Raw TCP:{}:{}] Received packet from {}, source port: {}, dest port: {}, flags: {:#04x}",
-                           target_ip, port, addr, packet.get_source(), packet.get_destination(), packet.get_flags());
-                    
-                    // Much more permissive packet matching - any packet from target to our source port
-                    // or any packet from the target port is considered a match
-                    // This ensures we don't miss valid responses due to overly strict matching
-                    if packet.get_destination() == source_port || packet.get_source() == port {
-                        // Capture the TCP flags for analysis
-                        let flags = packet.get_flags();
-                        debug!("[Raw TCP:{}:{}] Matched response packet (flags: {:#04x})", target_ip, port, flags);
-                        
-                        // Clone packet data to avoid lifetime issues
-                        let packet_data = packet.packet().to_vec();
-                        
-                        // Return the matched packet
-                        return Some(RawResponse::TcpStatic {
-                            flags: flags.into(), // Fix: explicitly convert to u16
-                            data: packet_data,
-                            source: packet.get_source(),
-                            destination: packet.get_destination(),
-                        });
-                    }
-                    
-                    // Handle packets that don't match our criteria
-                    if iterations % 5 == 0 {
-                        tokio::time::sleep(sleep_interval).await;
-                    }
-                    iterations += 1;
-                    
-                    // Avoid infinite loop if we keep receiving irrelevant packets
-                    if iterations >= max_iterations {
-                        return None;
-                    }
-                }
-                Err(e) => {
-                    // Check for ICMP errors (although less direct with Layer 4 channel)
-                    if e.kind() == std::io::ErrorKind::ConnectionRefused {
-                        debug!("[Raw TCP:{}:{}] Received ICMP-like error (ConnectionRefused). Filtered?", target_ip, port);
-                        return Some(RawResponse::IcmpFiltered(
-                            IcmpTypes::DestinationUnreachable, 
-                            icmp::IcmpCode::new(3))); // Port Unreachable
-                    } else {
-                        // Log but continue - this could be a temporary error
-                        warn!("[Raw TCP:{}:{}] Error receiving: {}. May indicate filtering.", target_ip, port, e);
-                        // Sleep briefly to not burn CPU when receiving errors
-                        tokio::time::sleep(sleep_interval).await;
-                        iterations += 1;
-                        if iterations >= max_iterations {
-                            return None;
-                        }
-                    }
-                }
-            }
-        }
-    };
-    
-    // Apply timeout to the receive future
-    match tokio::time::timeout(timeout_duration, receive_future).await {
-        Ok(Some(response)) => {
-            // Successfully received a response before timeout
-            Ok(Some(response))
-        },
-        Ok(None) => {
-            // Receive future completed but didn't find a matching packet
-            debug!("[Raw TCP:{}:{}] No matching response found within timeout of {:?}", target_ip, port, timeout_duration);
-            // Return Timeout response instead of None to handle timeouts consistently
-            Ok(Some(RawResponse::Timeout))
-        },
-        Err(_) => {
-            // Timeout occurred
-            debug!("[Raw TCP:{}:{}] Timeout waiting for response after {:?}", target_ip, port, timeout_duration);
-            Ok(Some(RawResponse::Timeout))
-        }
-    }
-}
-
-// --- Added Enums ---
-
-/// Represents the different outcomes of a raw TCP receive attempt.
-#[derive(Debug)] // Add Debug trait for easier inspection
-enum RawResponse {
-    /// A TCP packet with owned data (static lifetime)
-    TcpStatic {
-        flags: u16, // Changed from u8 to u16 to match TcpPacket::get_flags
-        data: Vec<u8>,
-        source: u16,
-        destination: u16,
-    },
-    /// An ICMPv4 error message relevant to filtering was received.
-    IcmpFiltered(icmp::IcmpType, icmp::IcmpCode),
-    /// An ICMPv6 error message relevant to filtering was received.
-    Icmpv6Filtered(icmpv6::Icmpv6Type, icmpv6::Icmpv6Code),
-    /// The receive operation timed out.
-    Timeout,
+    let probe = TcpProbe { target_ip, port, local_ip, flags: tcp_flags_out, timeout: timeout_duration, shape: shape.clone() };
+    let sent = Instant::now();
+    let response = crate::transport::current().send_tcp(&probe).await?;
+    // Every reply passes the hostile-network monitor before a technique interprets it
+    crate::hostile::observe(&probe, &response, sent.elapsed().saturating_sub(shape.delay));
+    Ok(response)
 }
 
 /// Represents errors that can occur within the receive loop.
@@ -398,6 +270,47 @@ fn tcp_flags_as_u16(flags: u8) -> u16 {
     flags as u16
 }
 
+/// Port state from the answer to a SYN probe: SYN-ACK is open, RST closed, and an ICMP
+/// error, silence or any other segment filtered
+pub(crate) fn classify_syn_response(response: &Option<RawResponse>) -> PortStatus {
+    let syn_ack = tcp_flags_as_u16(TcpFlags::SYN | TcpFlags::ACK);
+    match response {
+        Some(RawResponse::TcpStatic { flags, .. }) if flags & syn_ack == syn_ack => PortStatus::Open,
+        Some(RawResponse::TcpStatic { flags, .. }) if flags & tcp_flags_as_u16(TcpFlags::RST) != 0 => PortStatus::Closed,
+        _ => PortStatus::Filtered,
+    }
+}
+
+/// Port state from the answer to a FIN, NULL, XMAS or Maimon probe, which an RFC 793
+/// stack drops on open ports: RST is closed, silence open|filtered, and an ICMP error or
+/// any other segment filtered
+pub(crate) fn classify_stealth_response(response: &Option<RawResponse>) -> PortStatus {
+    match response {
+        Some(RawResponse::TcpStatic { flags, .. }) if flags & tcp_flags_as_u16(TcpFlags::RST) != 0 => PortStatus::Closed,
+        Some(RawResponse::TcpStatic { .. } | RawResponse::IcmpFiltered(..) | RawResponse::Icmpv6Filtered(..)) => PortStatus::Filtered,
+        Some(RawResponse::Timeout) | None => PortStatus::OpenFiltered,
+    }
+}
+
+/// Filtering state from the answer to an ACK probe, with the reason: a RST means no
+/// filter dropped it, anything else that one did
+pub(crate) fn classify_ack_response(response: &Option<RawResponse>) -> (PortStatus, String) {
+    match response {
+        Some(RawResponse::TcpStatic { flags, .. }) if flags & tcp_flags_as_u16(TcpFlags::RST) != 0 => {
+            (PortStatus::Unfiltered, "No filtering detected".to_string())
+        }
+        Some(RawResponse::TcpStatic { .. }) => (PortStatus::Filtered, "Unexpected response".to_string()),
+        Some(RawResponse::IcmpFiltered(icmp_type, icmp_code)) => {
+            (PortStatus::Filtered, format!("ICMPv4 type={:?} code={:?}", icmp_type, icmp_code))
+        }
+        Some(RawResponse::Icmpv6Filtered(icmpv6_type, icmpv6_code)) => {
+            (PortStatus::Filtered, format!("ICMPv6 type={:?} code={:?}", icmpv6_type, icmpv6_code))
+        }
+        Some(RawResponse::Timeout) => (PortStatus::Filtered, "Timeout".to_string()),
+        None => (PortStatus::Filtered, "No response data".to_string()),
+    }
+}
+
 /// SYN scan implementation (using raw sockets)
 ///
 /// Sends a TCP SYN packet and analyzes the response:
@@ -411,9 +324,9 @@ pub async fn syn_scan(
     local_ip_option: Option<IpAddr>,
     use_ipv6: bool,
     timeout_duration: Duration,
-    enhanced_evasion: bool,
-    mimic_os: &str,
-    ttl_jitter: u8,
+    _enhanced_evasion: bool,
+    _mimic_os: &str,
+    _ttl_jitter: u8,
 ) -> Result<PortStatus> {
     debug!("[SYN Scan:{}:{}] Starting SYN scan", target_ip, port);
     
@@ -428,9 +341,6 @@ pub async fn syn_scan(
         local_ip_option, 
         use_ipv6, 
         timeout_duration,
-        enhanced_evasion,
-        mimic_os,
-        ttl_jitter
     ).await;
     
     // Directly return the result of the raw SYN scan, removing the fallback logic.
@@ -444,9 +354,6 @@ async fn raw_syn_scan(
     local_ip_option: Option<IpAddr>,
     use_ipv6: bool,
     timeout_duration: Duration,
-    enhanced_evasion: bool,
-    mimic_os: &str,
-    ttl_jitter: u8,
 ) -> Result<PortStatus> {
     // Resolve local IP appropriate for the target IP family (v4 or v6)
     let local_ip: IpAddr = match target_ip {
@@ -480,14 +387,10 @@ async fn raw_syn_scan(
          return Err(anyhow!("Internal error: Mismatch between selected local IP ({}) and target IP ({}) family.", local_ip, target_ip));
     }
 
-    // Calculate TTL
-    let ttl = if enhanced_evasion {
-        utils::get_advanced_ttl(mimic_os, ttl_jitter)
-    } else {
-        64 // Default TTL if not using enhanced evasion
-    };
+    // Shape the probe (TTL, TCP options, timing, fragmentation) with the evasion pipeline
+    let shape = crate::evasion::shape_probe(target_ip.is_ipv6());
 
-    debug!("[Raw SYN:{}:{}] Using local IP {} with TTL {}", target_ip, port, local_ip, ttl);
+    debug!("[Raw SYN:{}:{}] Using local IP {} with TTL {}", target_ip, port, local_ip, shape.ttl);
     
     // Ensure we have a sufficient timeout
     let timeout_duration = if timeout_duration < Duration::from_millis(2000) {
@@ -507,7 +410,7 @@ async fn raw_syn_scan(
         local_ip,
         syn_flag,
         timeout_duration,
-        ttl,
+        &shape,
     ).await {
         Ok(r) => r,
         Err(e) => {
@@ -519,40 +422,9 @@ async fn raw_syn_scan(
     };
     
     // Parse the response to determine port status
-    match response {
-        Some(RawResponse::TcpStatic { flags, .. }) => {
-            // Convert TcpFlags constants to u16 for comparison
-            let syn_flag = tcp_flags_as_u16(TcpFlags::SYN.into());
-            let ack_flag = tcp_flags_as_u16(TcpFlags::ACK.into());
-            let rst_flag = tcp_flags_as_u16(TcpFlags::RST.into());
-            
-            debug!("[Raw SYN:{}:{}] Received TCP flags: {:#04x}", target_ip, port, flags);
-            
-            // Check for SYN+ACK flags (open port)
-            if (flags & syn_flag != 0) && (flags & ack_flag != 0) {
-                debug!("[Raw SYN:{}:{}] SYN-ACK received, port is OPEN", target_ip, port);
-                Ok(PortStatus::Open)
-            }
-            // Check for RST flag (closed port)
-            else if (flags & rst_flag) != 0 {
-                debug!("[Raw SYN:{}:{}] RST received, port is CLOSED", target_ip, port);
-                Ok(PortStatus::Closed)
-            }
-            // Any other flags indicate possible filtering
-            else {
-                debug!("[Raw SYN:{}:{}] Unexpected flags: {:#04x}, assuming FILTERED", target_ip, port, flags);
-                Ok(PortStatus::Filtered)
-            }
-        },
-        Some(RawResponse::IcmpFiltered(..)) | Some(RawResponse::Icmpv6Filtered(..)) => {
-            debug!("[Raw SYN:{}:{}] ICMP error received, port is FILTERED", target_ip, port);
-            Ok(PortStatus::Filtered)
-        },
-        Some(RawResponse::Timeout) | None => {
-            debug!("[Raw SYN:{}:{}] No response received, port is FILTERED", target_ip, port);
-            Ok(PortStatus::Filtered)
-        }
-    }
+    let status = classify_syn_response(&response);
+    debug!("[Raw SYN:{}:{}] Port is {} ({})", target_ip, port, status, describe_raw_response(&response));
+    Ok(status)
 }
 
 /// SSL/TLS scan implementation
@@ -561,14 +433,33 @@ pub async fn ssl_scan(
     target_ip: IpAddr,
     port: u16,
     timeout_duration: Duration,
-) -> Result<(PortStatus, Option<CertificateInfo>, String)> {
+) -> Result<(PortStatus, Option<CertificateInfo>, Option<ClientAuthInfo>, String)> {
+    ssl_scan_with_sni(target_ip, port, timeout_duration, None).await
+}
+
+/// SSL/TLS scan sending a chosen server name
+///
+/// # Arguments
+/// * `sni` - Name for the SNI extension; `None` sends none, as the address is not a valid SNI value
+///
+/// # Returns
+/// * Same as `ssl_scan`; the certificate is the one the server picks for that name
+///
+/// The result is the port status, the certificate chain, the client certificate request
+/// if the server sent one, and a description of the handshake.
+pub async fn ssl_scan_with_sni(
+    target_ip: IpAddr,
+    port: u16,
+    timeout_duration: Duration,
+    sni: Option<&str>,
+) -> Result<(PortStatus, Option<CertificateInfo>, Option<ClientAuthInfo>, String)> {
     // Log scan attempt for debugging
     debug!("[{}:{}] Starting SSL/TLS scan", target_ip, port);
     
     // Create a more robust connection timeout to prevent hangs
     let connect_future = async {
         let socket_addr = SocketAddr::new(target_ip, port);
-        let tcp_stream = tokio::net::TcpStream::connect(socket_addr).await?;
+        let tcp_stream = crate::netbind::connect_tcp(socket_addr).await?;
         // Set TCP_NODELAY to optimize performance
         tcp_stream.set_nodelay(true)?;
         Ok::<tokio::net::TcpStream, anyhow::Error>(tcp_stream)
@@ -582,11 +473,11 @@ pub async fn ssl_scan(
         },
         Ok(Err(e)) => {
             debug!("[{}:{}] TCP connection failed: {}", target_ip, port, e);
-            return Ok((PortStatus::Closed, None, format!("Connection error: {}", e)));
+            return Ok((PortStatus::Closed, None, None, format!("Connection error: {}", e)));
         },
         Err(_) => {
             debug!("[{}:{}] TCP connection timeout", target_ip, port);
-            return Ok((PortStatus::Filtered, None, "Connection timeout".to_string()));
+            return Ok((PortStatus::Filtered, None, None, "Connection timeout".to_string()));
         }
     };
     
@@ -623,34 +514,51 @@ pub async fn ssl_scan(
     
     // For minimal-static builds, use an empty cert store (we're just testing connectivity)
     
-    // Create client configuration
-    let config = ClientConfig::builder()
-        .with_root_certificates(root_store)
-        .with_no_client_auth();
+    // Create client configuration. Validation is recorded rather than enforced so the
+    // chain is still collected from untrusted, expired or self-signed servers.
+    let (config, trust_recorder, client_auth_recorder) = crate::ssl_config::recording_tls_config(root_store);
 
     // Create TLS connection
     // Convert target_ip to DNS name format required by rustls
-    let server_name = match target_ip {
-        IpAddr::V4(ipv4) => {
+    let server_name = match (sni, target_ip) {
+        (Some(name), _) => match crate::ssl_config::convert_to_server_name(name) {
+            Ok(server_name) => server_name.to_owned(),
+            Err(e) => return Err(anyhow!("Invalid SNI name '{}': {}", name, e)),
+        },
+        (None, IpAddr::V4(ipv4)) => {
             // Create a ServerName::IpAddr
             ServerName::IpAddress(ipv4.into())
         },
-        IpAddr::V6(ipv6) => {
+        (None, IpAddr::V6(ipv6)) => {
             // Create a ServerName::IpAddr
             ServerName::IpAddress(ipv6.into())
         }
     };
     
-    let mut tls_conn = match ClientConnection::new(Arc::new(config), server_name) {
+    let mut tls_conn = match ClientConnection::new(config, server_name) {
         Ok(conn) => conn,
         Err(e) => {
             debug!("[{}:{}] TLS client creation error: {}", target_ip, port, e);
             // This is a client-side setup error, not indicative of port status
             // TCP connection succeeded, so port is likely open
-            return Ok((PortStatus::Open, None, format!("TLS setup error: {}", e)));
+            return Ok((PortStatus::Open, None, None, format!("TLS setup error: {}", e)));
         }
     };
     
+    // With --tcp-segment, send the queued ClientHello ourselves in tiny segments so the
+    // SNI and cipher list never appear in the first segment
+    let mut tcp_stream = tcp_stream;
+    if let Some(policy) = crate::evasion::segmentation() {
+        let mut client_hello = Vec::new();
+        if let Err(e) = tls_conn.write_tls(&mut client_hello) {
+            return Ok((PortStatus::Open, None, None, format!("TLS setup error: {}", e)));
+        }
+        if let Err(e) = policy.write(&mut tcp_stream, &client_hello).await {
+            debug!("[{}:{}] Segmented ClientHello write failed: {}", target_ip, port, e);
+            return Ok((PortStatus::Open, None, None, format!("TLS write error: {}", e)));
+        }
+    }
+
     // Create standard (non-async) stream from tokio stream for rustls
     let mut tcp_stream = tcp_stream.into_std()?;
     
@@ -688,8 +596,11 @@ pub async fn ssl_scan(
             // Check if connection needs to write
             if tls_conn.wants_write() {
                 match tls_conn.write_tls(&mut tcp_stream) {
-                    Ok(_) => {
-                        // Successfully wrote data
+                    Ok(written) => {
+                        // rustls writes first, so the TLS flight is charged once it is out
+                        crate::scheduler::spend(written + crate::scheduler::TCP_HEADER_BYTES).await;
+                        crate::audit::record(|| crate::audit::Probe::new("tls", "tcp", target_ip, Some(port), written + crate::scheduler::TCP_HEADER_BYTES)
+                            .detail("TLS handshake flight"));
                     },
         Err(e) => {
                         debug!("[{}:{}] TLS write error: {}", target_ip, port, e);
@@ -718,7 +629,14 @@ pub async fn ssl_scan(
     match handshake_result {
         Ok(Ok(_)) => {
             // Handshake successful, obtain certificate info
-            let cert_info = parse_certificate_chain(&tls_conn);
+            let cert_info = parse_certificate_chain(&tls_conn, trust_recorder.as_deref());
+            
+            // A TLS 1.3 server sees the (empty) client certificate only after our handshake ends
+            let client_auth = client_auth_recorder.requested().map(|cas| {
+                let required = tls_conn.protocol_version() == Some(rustls::ProtocolVersion::TLSv1_3)
+                    && rejected_after_handshake(&mut tls_conn, &mut tcp_stream, timeout_duration);
+                client_auth_info(&cas, required)
+            });
             
             // Gather additional TLS details for fingerprinting
             let protocol_version = format!("{:?}", tls_conn.protocol_version());
@@ -731,51 +649,90 @@ pub async fn ssl_scan(
             );
             
             debug!("[{}:{}] SSL/TLS scan successful: {}", target_ip, port, server_info);
-            Ok((PortStatus::Open, cert_info, server_info))
+            Ok((PortStatus::Open, cert_info, client_auth, server_info))
         },
         Ok(Err(e)) => {
             // TLS handshake failed with error
             let error_message = e.to_string();
             
             // Analyze specific TLS errors to determine port status
-            if error_message.contains("certificate") || error_message.contains("verify") {
+            if let Some(cas) = client_auth_recorder.requested() {
+                // The server asked for a certificate and dropped the handshake without one
+                debug!("[{}:{}] Server requires a client certificate", target_ip, port);
+                let cert_info = parse_certificate_chain(&tls_conn, trust_recorder.as_deref());
+                Ok((PortStatus::Open, cert_info, Some(client_auth_info(&cas, true)), format!("TLS client certificate required: {}", error_message)))
+            } else if error_message.contains("certificate") || error_message.contains("verify") {
                 // Certificate verification errors typically mean a successful connection
                 // to a server with an invalid or self-signed certificate
                 debug!("[{}:{}] Certificate validation failed but connection was established", target_ip, port);
-                Ok((PortStatus::Open, None, format!("SSL/TLS Error: {}", error_message)))
+                Ok((PortStatus::Open, None, None, format!("SSL/TLS Error: {}", error_message)))
             } else if error_message.contains("protocol") || error_message.contains("handshake") {
                 // Protocol errors often indicate an open port running a non-TLS service
                 debug!("[{}:{}] Protocol error indicates non-TLS service", target_ip, port);
-                Ok((PortStatus::Open, None, format!("Non-TLS Service: {}", error_message)))
+                Ok((PortStatus::Open, None, None, format!("Non-TLS Service: {}", error_message)))
             } else {
                 // Generic error - port is likely open but running something unexpected
                 debug!("[{}:{}] TLS handshake failed: {}", target_ip, port, error_message);
-                Ok((PortStatus::Open, None, format!("TLS Error: {}", error_message)))
+                Ok((PortStatus::Open, None, None, format!("TLS Error: {}", error_message)))
             }
         },
         Err(_) => {
             // Timeout during handshake - could be heavily filtered or slow
             debug!("[{}:{}] TLS handshake timeout", target_ip, port);
-            Ok((PortStatus::OpenFiltered, None, "TLS handshake timeout".to_string()))
+            Ok((PortStatus::OpenFiltered, None, None, "TLS handshake timeout".to_string()))
         }
     }
 }
 
-// Improved function to parse the certificate chain
-fn parse_certificate_chain(tls_conn: &ClientConnection) -> Option<CertificateInfo> {
-    // Get the server certificate chain, if available
-    if let Some(certs) = tls_conn.peer_certificates() {
-        if let Some(cert_der) = certs.first() {
-            return parse_certificate(cert_der);
-        }
+/// Whether a server that asked for a client certificate ends the connection after the
+/// handshake, i.e. the certificate it did not get was required
+///
+/// A server that only wanted a certificate carries on (usually with session tickets);
+/// one that requires it sends an alert or closes.
+fn rejected_after_handshake(tls_conn: &mut ClientConnection, tcp_stream: &mut std::net::TcpStream, timeout_duration: Duration) -> bool {
+    // Silence within a short wait means the connection was accepted
+    let _ = tcp_stream.set_read_timeout(Some(timeout_duration.min(Duration::from_secs(2))));
+    match tls_conn.read_tls(tcp_stream) {
+        Ok(0) => true,
+        Ok(_) => tls_conn.process_new_packets().is_err(),
+        Err(e) => !matches!(e.kind(), std::io::ErrorKind::WouldBlock | std::io::ErrorKind::TimedOut),
+    }
+}
+
+/// Client certificate request with the acceptable CA names made readable
+fn client_auth_info(cas: &[Vec<u8>], required: bool) -> ClientAuthInfo {
+    ClientAuthInfo {
+        required,
+        acceptable_cas: cas.iter().map(|name| parse_distinguished_name(name)).collect(),
+    }
+}
+
+/// Parse the certificate chain the server presented and analyze it
+///
+/// The leaf is returned with the rest of the chain in `chain`, the trust verdict in
+/// `trusted`, whether an OCSP response was stapled in `ocsp_stapled` and any problems in
+/// `issues`. Hostname checks happen later, once the scanner knows which name the target
+/// was given as.
+fn parse_certificate_chain(tls_conn: &ClientConnection, recorder: Option<&crate::ssl_config::TrustRecorder>) -> Option<CertificateInfo> {
+    let certs = tls_conn.peer_certificates()?;
+    let mut leaf = parse_certificate(certs.first()?)?;
+    leaf.chain = certs[1..].iter().filter_map(|der| parse_certificate(der)).collect();
+    let trust = recorder.and_then(|recorder| recorder.verdict());
+    leaf.trusted = trust.as_ref().map(|verdict| verdict.is_ok());
+    leaf.ocsp_stapled = recorder.and_then(|recorder| recorder.ocsp_stapled());
+
+    let ders: Vec<&[u8]> = certs.iter().map(|der| der.as_ref()).collect();
+    leaf.issues = crate::cert_analysis::analyze_chain(&ders, trust.as_ref());
+    for issue in &leaf.issues {
+        debug!("[Cert] {} [{}] {}", issue.kind, issue.severity, issue.detail);
     }
-    None
+    Some(leaf)
 }
 
 /// Parse an X.509 certificate and extract information
 /// (remains unchanged)
 #[cfg(not(feature = "minimal-static"))]
-fn parse_certificate(cert_der: &[u8]) -> Option<CertificateInfo> {
+pub(crate) fn parse_certificate(cert_der: &[u8]) -> Option<CertificateInfo> {
     match x509_parser::parse_x509_certificate(cert_der) {
         Ok((_, cert)) => {
             // Safely extract fields, providing defaults or handling potential errors
@@ -809,6 +766,10 @@ fn parse_certificate(cert_der: &[u8]) -> Option<CertificateInfo> {
                 alt_names: Vec::new(),
                 public_key_bits: None,
                 key_algorithm: None,
+                chain: Vec::new(),
+                trusted: None,
+                ocsp_stapled: None,
+                issues: Vec::new(),
             };
 
             // Parse extensions, especially subject alternative names
@@ -861,7 +822,7 @@ fn parse_certificate(cert_der: &[u8]) -> Option<CertificateInfo> {
 }
 
 #[cfg(feature = "minimal-static")]
-fn parse_certificate(cert_der: &[u8]) -> Option<CertificateInfo> {
+pub(crate) fn parse_certificate(cert_der: &[u8]) -> Option<CertificateInfo> {
     // Minimal implementation for when x509-parser is not available
     let mut hasher = Sha256::new();
     hasher.update(cert_der);
@@ -879,21 +840,102 @@ fn parse_certificate(cert_der: &[u8]) -> Option<CertificateInfo> {
         alt_names: vec![],
         public_key_bits: None,
         key_algorithm: None,
+        chain: Vec::new(),
+        trusted: None,
+        ocsp_stapled: None,
+        issues: Vec::new(),
     })
 }
 
+/// Render a DER distinguished name from a CertificateRequest as "CN=..., O=..."
+///
+/// Servers send the full `Name` SEQUENCE; a bare RDN sequence gets its header added back.
+#[cfg(not(feature = "minimal-static"))]
+pub(crate) fn parse_distinguished_name(der: &[u8]) -> String {
+    use x509_parser::prelude::FromDer;
+
+    let mut wrapped;
+    let name = if der.first() == Some(&0x30) {
+        der
+    } else {
+        wrapped = vec![0x30];
+        match der.len() {
+            len if len < 0x80 => wrapped.push(len as u8),
+            len if len <= 0xff => wrapped.extend_from_slice(&[0x81, len as u8]),
+            len => wrapped.extend_from_slice(&[0x82, (len >> 8) as u8, len as u8]),
+        }
+        wrapped.extend_from_slice(der);
+        &wrapped
+    };
+    match x509_parser::x509::X509Name::from_der(name) {
+        Ok((_, parsed)) => parsed.to_string(),
+        Err(_) => format!("unparsed name ({} bytes)", der.len()),
+    }
+}
+
+#[cfg(feature = "minimal-static")]
+pub(crate) fn parse_distinguished_name(der: &[u8]) -> String {
+    // Names are not parsed in minimal builds
+    format!("unparsed name ({} bytes)", der.len())
+}
+
+lazy_static::lazy_static! {
+    /// UDP probe payload from `--udp-payload`/`--udp-payload-file` and the ports it applies
+    /// to (`None` for all)
+    static ref UDP_PAYLOAD: parking_lot::RwLock<Option<(Vec<u8>, Option<std::collections::HashSet<u16>>)>> =
+        parking_lot::RwLock::new(None);
+}
+
+/// Send `payload` instead of the generic probe in UDP scans
+///
+/// # Arguments
+/// * `payload` - Bytes of the UDP probe
+/// * `ports` - Ports to use it on; every UDP port when `None`
+pub fn set_udp_payload(payload: Vec<u8>, ports: Option<Vec<u16>>) {
+    *UDP_PAYLOAD.write() = Some((payload, ports.map(|ports| ports.into_iter().collect())));
+}
+
+/// Configured UDP payload for `port`, if any
+fn udp_payload_for(port: u16) -> Option<Vec<u8>> {
+    match UDP_PAYLOAD.read().as_ref() {
+        Some((payload, None)) => Some(payload.clone()),
+        Some((payload, Some(ports))) if ports.contains(&port) => Some(payload.clone()),
+        _ => None,
+    }
+}
+
+/// Parse a hex payload such as `0xDEADBEEF` or `de ad be ef`
+///
+/// # Arguments
+/// * `spec` - Hex digits, optionally prefixed with `0x` and separated by whitespace
+///
+/// # Returns
+/// * `Result<Vec<u8>>` - The bytes, or an error for odd-length or non-hex input
+pub fn parse_hex_payload(spec: &str) -> Result<Vec<u8>> {
+    let spec = spec.trim();
+    let digits: String = spec.strip_prefix("0x").or_else(|| spec.strip_prefix("0X")).unwrap_or(spec)
+        .chars()
+        .filter(|c| !c.is_whitespace())
+        .collect();
+    if digits.is_empty() || digits.len() % 2 != 0 {
+        return Err(anyhow!("Hex payload must have an even, non-zero number of digits"));
+    }
+    (0..digits.len()).step_by(2)
+        .map(|i| u8::from_str_radix(&digits[i..i + 2], 16).map_err(|_| anyhow!("Invalid hex digits '{}'", &digits[i..i + 2])))
+        .collect()
+}
+
 /// UDP scan implementation
 /// (remains unchanged, uses standard sockets)
 pub async fn udp_scan(
     target_ip: IpAddr,
     port: u16,
     _local_ip: Option<IpAddr>,
-    use_ipv6: bool,
+    _use_ipv6: bool,
     timeout_duration: Duration,
 ) -> Result<PortStatus> {
     debug!("[UDP Scan:{}:{}] Performing standard socket UDP scan", target_ip, port);
-    let bind_addr = if use_ipv6 { "[::]:0" } else { "0.0.0.0:0" };
-    let socket = match UdpSocket::bind(bind_addr).await {
+    let socket = match crate::netbind::bind_udp(target_ip).await {
         Ok(s) => s,
         Err(e) => return Err(anyhow!("Failed to bind UDP socket: {}", e)),
     };
@@ -906,7 +948,15 @@ pub async fn udp_scan(
          warn!("[UDP Scan:{}:{}] Failed to 'connect' UDP socket (may be ok): {}", target_ip, port, e);
     }
 
-    let probe = b"QSCAN"; // Simple probe
+    // A custom probe from --probe-file gets a reply where the generic one is ignored,
+    // then a payload given with --udp-payload
+    let custom_probe = crate::service_fingerprints::database()
+        .custom_probe(port, crate::service_fingerprints::ProbeProtocol::Udp)
+        .map(|probe| probe.payload.clone())
+        .or_else(|| udp_payload_for(port));
+    let probe: &[u8] = custom_probe.as_deref().unwrap_or(b"QSCAN");
+    crate::scheduler::spend(probe.len() + crate::scheduler::UDP_HEADER_BYTES).await;
+    crate::audit::record(|| crate::audit::Probe::new("udp", "udp", target_ip, Some(port), probe.len() + crate::scheduler::UDP_HEADER_BYTES));
     if let Err(e) = socket.send(probe).await {
          // If send fails, it's likely a real issue.
          return Err(anyhow!("[UDP Scan:{}:{}] Failed to send UDP probe: {}", target_ip, port, e));
@@ -953,9 +1003,9 @@ pub async fn ack_scan(
     local_ip_option: Option<IpAddr>,
     use_ipv6: bool,
     timeout_duration: Duration,
-    enhanced_evasion: bool,
-    mimic_os: &str,
-    ttl_jitter: u8,
+    _enhanced_evasion: bool,
+    _mimic_os: &str,
+    _ttl_jitter: u8,
 ) -> Result<(PortStatus, String)> {
     // Determine the required local IP (similar logic as syn_scan)
     // Add type annotation : IpAddr
@@ -988,64 +1038,25 @@ pub async fn ack_scan(
         return Err(anyhow!("Internal error: Mismatch between selected local IP ({}) and target IP ({}) family for ACK scan.", local_ip, target_ip));
     }
 
-    // Calculate TTL
-    let ttl = if enhanced_evasion {
-        utils::get_advanced_ttl(mimic_os, ttl_jitter)
-    } else {
-        64
-    };
+    // Shape the probe (TTL, TCP options, timing, fragmentation) with the evasion pipeline
+    let shape = crate::evasion::shape_probe(target_ip.is_ipv6());
 
-    debug!("[ACK Scan:{}:{}] Performing raw ACK scan using local IP {} with TTL {} and timeout {:?}. Requires root/admin.", target_ip, port, local_ip, ttl, timeout_duration);
+    debug!("[ACK Scan:{}:{}] Performing raw ACK scan using local IP {} with TTL {} and timeout {:?}. Requires root/admin.", target_ip, port, local_ip, shape.ttl, timeout_duration);
 
     // Cast TcpFlags::ACK to u8
     let ack_flag: u8 = TcpFlags::ACK.into();
     // Generate a random sequence number
-    let _seq_num = rand::random::<u32>();
-    
-    // Generate a random source port (above 10000) if using enhanced evasion
-    let _source_port = if enhanced_evasion {
-        10000 + (rand::random::<u16>() % 55000)
-    } else {
-        12345 // Fixed source port if not using enhanced evasion
-    };
+    let _seq_num = utils::scan_rng().gen::<u32>();
     
     // Send ACK packet and wait for response
-    match send_receive_raw_tcp(target_ip, port, local_ip, ack_flag, timeout_duration, ttl).await {
-        Ok(Some(response)) => match response {
-            RawResponse::TcpStatic { flags, .. } => {
-                let rst_flag = u16::from(TcpFlags::RST);
-                if (flags & rst_flag) != 0 {
-                    debug!("[ACK Scan:{}:{}] Port is unfiltered (received RST)", target_ip, port);
-                    Ok((PortStatus::Unfiltered, "No filtering detected".to_string()))
-                } else {
-                    warn!("[ACK Scan:{}:{}] Port returned unexpected TCP flags: {:#04x}", target_ip, port, flags);
-                    Ok((PortStatus::Filtered, "Unexpected response".to_string()))
-                }
-            }
-            RawResponse::IcmpFiltered(icmp_type, icmp_code) => {
-                let filter_detail = format!("ICMPv4 type={:?} code={:?}", icmp_type, icmp_code);
-                debug!("[ACK Scan:{}:{}] Port is filtered ({})", target_ip, port, filter_detail);
-                Ok((PortStatus::Filtered, filter_detail))
-            }
-            RawResponse::Icmpv6Filtered(icmpv6_type, icmpv6_code) => {
-                let filter_detail = format!("ICMPv6 type={:?} code={:?}", icmpv6_type, icmpv6_code);
-                debug!("[ACK Scan:{}:{}] Port is filtered ({})", target_ip, port, filter_detail);
-                Ok((PortStatus::Filtered, filter_detail))
-            }
-            RawResponse::Timeout => {
-                debug!("[ACK Scan:{}:{}] Port is filtered (timeout)", target_ip, port);
-                Ok((PortStatus::Filtered, "Timeout".to_string()))
-            }
-        },
-        Ok(None) => {
-            debug!("[ACK Scan:{}:{}] No response data (filtered)", target_ip, port);
-            Ok((PortStatus::Filtered, "No response data".to_string()))
-        },
-        Err(e) => {
+    let response = send_receive_raw_tcp(target_ip, port, local_ip, ack_flag, timeout_duration, &shape).await
+        .map_err(|e| {
             error!("[ACK Scan:{}:{}] Error during scan: {}", target_ip, port, e);
-            Err(e)
-        }
-    }
+            e
+        })?;
+    let (status, detail) = classify_ack_response(&response);
+    debug!("[ACK Scan:{}:{}] Port is {} ({})", target_ip, port, status, detail);
+    Ok((status, detail))
 }
 
 /// FIN scan implementation (using raw sockets)
@@ -1059,9 +1070,9 @@ pub async fn fin_scan(
     local_ip_option: Option<IpAddr>,
     use_ipv6: bool,
     timeout_duration: Duration,
-    enhanced_evasion: bool,
-    mimic_os: &str,
-    ttl_jitter: u8,
+    _enhanced_evasion: bool,
+    _mimic_os: &str,
+    _ttl_jitter: u8,
 ) -> Result<PortStatus> {
     // Determine the required local IP (similar logic as syn_scan)
     // Add type annotation : IpAddr
@@ -1093,51 +1104,81 @@ pub async fn fin_scan(
         return Err(anyhow!("Internal error: Mismatch between selected local IP ({}) and target IP ({}) family for FIN scan.", local_ip, target_ip));
     }
 
-    // Calculate TTL
-    let ttl = if enhanced_evasion {
-        utils::get_advanced_ttl(mimic_os, ttl_jitter)
-    } else {
-        64
-    };
+    // Shape the probe (TTL, TCP options, timing, fragmentation) with the evasion pipeline
+    let shape = crate::evasion::shape_probe(target_ip.is_ipv6());
 
-    debug!("[FIN Scan:{}:{}] Performing raw FIN scan using local IP {} with TTL {} and timeout {:?}. Requires root/admin.", target_ip, port, local_ip, ttl, timeout_duration);
+    debug!("[FIN Scan:{}:{}] Performing raw FIN scan using local IP {} with TTL {} and timeout {:?}. Requires root/admin.", target_ip, port, local_ip, shape.ttl, timeout_duration);
 
     // Cast TcpFlags::FIN to u8
-    match send_receive_raw_tcp(target_ip, port, local_ip, TcpFlags::FIN.into(), timeout_duration, ttl).await {
-        Ok(Some(response)) => match response {
-            RawResponse::TcpStatic { flags, .. } => {
-                let rst_flag = u16::from(TcpFlags::RST);
-                if (flags & rst_flag) != 0 {
-                    debug!("[FIN Scan:{}:{}] Port is closed (received RST)", target_ip, port);
-                    Ok(PortStatus::Closed)
-                } else {
-                    debug!("[FIN Scan:{}:{}] Port is filtered (unexpected response)", target_ip, port);
-                    Ok(PortStatus::Filtered)
+    let response = send_receive_raw_tcp(target_ip, port, local_ip, TcpFlags::FIN.into(), timeout_duration, &shape).await
+        .map_err(|e| {
+            error!("[FIN Scan:{}:{}] Error during scan: {}", target_ip, port, e);
+            e
+        })?;
+    let status = classify_stealth_response(&response);
+    debug!("[FIN Scan:{}:{}] Port is {} ({})", target_ip, port, status, describe_raw_response(&response));
+    Ok(status)
+}
+
+/// Maimon scan implementation (using raw sockets)
+/// Sends TCP FIN/ACK packet. RFC 793 calls for a RST whatever the port state, but
+/// BSD-derived stacks drop the segment when the port is open.
+/// - No response -> Open or Filtered.
+/// - RST response -> Closed.
+/// REQUIRES root/administrator privileges.
+pub async fn maimon_scan(
+    target_ip: IpAddr,
+    port: u16,
+    local_ip_option: Option<IpAddr>,
+    use_ipv6: bool,
+    timeout_duration: Duration,
+    _enhanced_evasion: bool,
+    _mimic_os: &str,
+    _ttl_jitter: u8,
+) -> Result<PortStatus> {
+    // Determine the required local IP (similar logic as syn_scan)
+    let local_ip: IpAddr = match target_ip {
+        IpAddr::V4(target_ipv4) => {
+            match local_ip_option {
+                Some(IpAddr::V4(ip)) => ip.into(),
+                Some(IpAddr::V6(_)) => return Err(anyhow!("Provided local IP is IPv6, but target is IPv4 for raw Maimon scan.")),
+                None => {
+                    warn!("Local IPv4 address not specified for raw Maimon scan to {}, attempting auto-detection.", target_ipv4);
+                    utils::find_local_ipv4().map_err(|e| anyhow!("Local IPv4 address required for raw Maimon scan and auto-detection failed: {}", e))?.into()
                 }
             }
-            RawResponse::IcmpFiltered(icmp_type, icmp_code) => {
-                debug!("[FIN Scan:{}:{}] Port is filtered (received ICMPv4 {:?}/{:?})", target_ip, port, icmp_type, icmp_code);
-                Ok(PortStatus::Filtered)
-            }
-            RawResponse::Icmpv6Filtered(icmpv6_type, icmpv6_code) => {
-                debug!("[FIN Scan:{}:{}] Port is filtered (received ICMPv6 {:?}/{:?})", target_ip, port, icmpv6_type, icmpv6_code);
-                Ok(PortStatus::Filtered)
-            }
-            RawResponse::Timeout => {
-                // For FIN scan, no response typically means open|filtered
-                debug!("[FIN Scan:{}:{}] Port is open|filtered (no response)", target_ip, port);
-                Ok(PortStatus::OpenFiltered)
+        }
+        IpAddr::V6(target_ipv6) => {
+            if !use_ipv6 { return Err(anyhow!("Target is IPv6 ({}), but IPv6 scanning is not enabled via --ipv6 flag.", target_ipv6)); }
+            match local_ip_option {
+                Some(IpAddr::V6(ip)) => ip.into(),
+                Some(IpAddr::V4(_)) => return Err(anyhow!("Provided local IP is IPv4, but target is IPv6 for raw Maimon scan.")),
+                None => {
+                    warn!("Local IPv6 address not specified for raw Maimon scan to {}, attempting auto-detection.", target_ipv6);
+                    utils::find_local_ipv6().map_err(|e| anyhow!("Local IPv6 address required for raw Maimon scan and auto-detection failed: {}", e))?.into()
+                }
             }
-        },
-        Ok(None) => {
-            debug!("[FIN Scan:{}:{}] No response data (open|filtered)", target_ip, port);
-            Ok(PortStatus::OpenFiltered)
-        },
-        Err(e) => {
-            error!("[FIN Scan:{}:{}] Error during scan: {}", target_ip, port, e);
-            Err(e)
         }
+    };
+
+    if local_ip.is_ipv4() != target_ip.is_ipv4() {
+        return Err(anyhow!("Internal error: Mismatch between selected local IP ({}) and target IP ({}) family for Maimon scan.", local_ip, target_ip));
     }
+
+    // Shape the probe (TTL, TCP options, timing, fragmentation) with the evasion pipeline
+    let shape = crate::evasion::shape_probe(target_ip.is_ipv6());
+
+    debug!("[Maimon Scan:{}:{}] Performing raw Maimon scan using local IP {} with TTL {} and timeout {:?}. Requires root/admin.", target_ip, port, local_ip, shape.ttl, timeout_duration);
+
+    let fin_ack = (tcp_flags_as_u16(TcpFlags::FIN) | tcp_flags_as_u16(TcpFlags::ACK)) as u8;
+    let response = send_receive_raw_tcp(target_ip, port, local_ip, fin_ack, timeout_duration, &shape).await
+        .map_err(|e| {
+            error!("[Maimon Scan:{}:{}] Error during scan: {}", target_ip, port, e);
+            e
+        })?;
+    let status = classify_stealth_response(&response);
+    debug!("[Maimon Scan:{}:{}] Port is {} ({})", target_ip, port, status, describe_raw_response(&response));
+    Ok(status)
 }
 
 /// Mimic scan with custom protocol payload
@@ -1155,7 +1196,7 @@ pub async fn mimic_scan_with_payload(
     debug!("[Mimic Scan:{}:{}] Performing standard socket TCP connect and write", target_ip, port);
     let socket_addr = SocketAddr::new(target_ip, port);
 
-    let stream = match timeout(timeout_duration, tokio::net::TcpStream::connect(socket_addr)).await {
+    let mut stream = match timeout(timeout_duration, crate::netbind::connect_tcp(socket_addr)).await {
         Ok(Ok(stream)) => stream,
         Ok(Err(_)) => {
              debug!("[Mimic Scan:{}:{}] TCP connect failed", target_ip, port);
@@ -1176,7 +1217,17 @@ pub async fn mimic_scan_with_payload(
          // Can't write, connection might be dead
          return Ok(PortStatus::Filtered);
     }
-    match stream.try_write(&payload) {
+    // Segment the payload into tiny TCP segments if --tcp-segment is set
+    let write_result = match crate::evasion::segmentation() {
+        Some(policy) => policy.write(&mut stream, &payload).await.map(|()| payload.len()),
+        None => {
+            crate::scheduler::spend(payload.len() + crate::scheduler::TCP_HEADER_BYTES).await;
+            crate::audit::record(|| crate::audit::Probe::new("mimic", "tcp", target_ip, Some(port), payload.len() + crate::scheduler::TCP_HEADER_BYTES)
+                .detail(format!("{} payload", _protocol)));
+            stream.try_write(&payload)
+        }
+    };
+    match write_result {
         Ok(bytes_written) => {
              if bytes_written == 0 {
                  warn!("[Mimic Scan:{}:{}] Wrote 0 bytes, connection may be closed.", target_ip, port);
@@ -1268,6 +1319,30 @@ pub async fn icmp_tunnel_scan(
     ).await
 }
 
+/// Perform an HTTP(S) tunnel scan to a target port
+/// This scan asks an HTTP relay to test the port so only web traffic leaves the scanner
+pub async fn http_tunnel_scan(
+    target_ip: IpAddr,
+    port: u16,
+    timeout_duration: Duration,
+    config: Option<&crate::tunnel::HttpTunnelConfig>,
+) -> Result<PortStatus> {
+    let config = config.ok_or_else(|| anyhow!("HTTP tunnel scan requires --http-relay"))?;
+    crate::tunnel::http_tunnel_scan(target_ip, port, config, timeout_duration).await
+}
+
+/// Perform a QUIC tunnel scan to a target port
+/// This scan asks a QUIC relay to test the port so only HTTP/3-like UDP leaves the scanner
+pub async fn quic_tunnel_scan(
+    target_ip: IpAddr,
+    port: u16,
+    timeout_duration: Duration,
+    config: Option<&crate::tunnel::QuicTunnelConfig>,
+) -> Result<PortStatus> {
+    let config = config.ok_or_else(|| anyhow!("QUIC tunnel scan requires --quic-relay"))?;
+    crate::tunnel::quic_tunnel_scan(target_ip, port, config, timeout_duration).await
+}
+
 /// XMAS scan implementation (using raw sockets)
 /// Sends packet with FIN, PSH, URG flags set.
 /// - No response -> Open or Filtered.
@@ -1279,9 +1354,9 @@ pub async fn xmas_scan(
     local_ip_option: Option<IpAddr>,
     use_ipv6: bool,
     timeout_duration: Duration,
-    enhanced_evasion: bool,
-    mimic_os: &str,
-    ttl_jitter: u8,
+    _enhanced_evasion: bool,
+    _mimic_os: &str,
+    _ttl_jitter: u8,
 ) -> Result<PortStatus> {
     // Determine the required local IP (similar logic as syn_scan)
     // Use .into() to convert specific IP types to IpAddr
@@ -1313,52 +1388,21 @@ pub async fn xmas_scan(
         return Err(anyhow!("Internal error: Mismatch between selected local IP ({}) and target IP ({}) family for XMAS scan.", local_ip, target_ip));
     }
 
-    // Calculate TTL
-    let ttl = if enhanced_evasion {
-        utils::get_advanced_ttl(mimic_os, ttl_jitter)
-    } else {
-        64
-    };
+    // Shape the probe (TTL, TCP options, timing, fragmentation) with the evasion pipeline
+    let shape = crate::evasion::shape_probe(target_ip.is_ipv6());
 
     let xmas_flags = (tcp_flags_as_u16(TcpFlags::FIN) | tcp_flags_as_u16(TcpFlags::URG) | tcp_flags_as_u16(TcpFlags::PSH)) as u8;
-    debug!("[XMAS Scan:{}:{}] Performing raw XMAS scan (flags {:#04x}) using local IP {} with TTL {} and timeout {:?}. Requires root/admin.", target_ip, port, xmas_flags, local_ip, ttl, timeout_duration);
+    debug!("[XMAS Scan:{}:{}] Performing raw XMAS scan (flags {:#04x}) using local IP {} with TTL {} and timeout {:?}. Requires root/admin.", target_ip, port, xmas_flags, local_ip, shape.ttl, timeout_duration);
 
     // Cast xmas_flags to u8
-    match send_receive_raw_tcp(target_ip, port, local_ip, xmas_flags, timeout_duration, ttl).await {
-        Ok(Some(response)) => match response {
-            RawResponse::TcpStatic { flags, .. } => {
-                let rst_flag = u16::from(TcpFlags::RST);
-                if (flags & rst_flag) != 0 {
-                    debug!("[XMAS Scan:{}:{}] Port is closed (received RST)", target_ip, port);
-                    Ok(PortStatus::Closed)
-                } else {
-                    debug!("[XMAS Scan:{}:{}] Port is filtered (unexpected response)", target_ip, port);
-                    Ok(PortStatus::Filtered)
-                }
-            }
-            RawResponse::IcmpFiltered(icmp_type, icmp_code) => {
-                debug!("[XMAS Scan:{}:{}] Port is filtered (received ICMPv4 {:?}/{:?})", target_ip, port, icmp_type, icmp_code);
-                Ok(PortStatus::Filtered)
-            }
-            RawResponse::Icmpv6Filtered(icmpv6_type, icmpv6_code) => {
-                debug!("[XMAS Scan:{}:{}] Port is filtered (received ICMPv6 {:?}/{:?})", target_ip, port, icmpv6_type, icmpv6_code);
-                Ok(PortStatus::Filtered)
-            }
-            RawResponse::Timeout => {
-                // For XMAS scan, no response typically means open|filtered
-                debug!("[XMAS Scan:{}:{}] Port is open|filtered (no response)", target_ip, port);
-                Ok(PortStatus::OpenFiltered)
-            }
-        },
-        Ok(None) => {
-            debug!("[XMAS Scan:{}:{}] No response data (open|filtered)", target_ip, port);
-            Ok(PortStatus::OpenFiltered)
-        },
-        Err(e) => {
+    let response = send_receive_raw_tcp(target_ip, port, local_ip, xmas_flags, timeout_duration, &shape).await
+        .map_err(|e| {
             error!("[XMAS Scan:{}:{}] Error during scan: {}", target_ip, port, e);
-            Err(e)
-        }
-    }
+            e
+        })?;
+    let status = classify_stealth_response(&response);
+    debug!("[XMAS Scan:{}:{}] Port is {} ({})", target_ip, port, status, describe_raw_response(&response));
+    Ok(status)
 }
 
 /// NULL scan implementation (using raw sockets)
@@ -1372,9 +1416,9 @@ pub async fn null_scan(
     local_ip_option: Option<IpAddr>,
     use_ipv6: bool,
     timeout_duration: Duration,
-    enhanced_evasion: bool,
-    mimic_os: &str,
-    ttl_jitter: u8,
+    _enhanced_evasion: bool,
+    _mimic_os: &str,
+    _ttl_jitter: u8,
 ) -> Result<PortStatus> {
     // Determine the required local IP (similar logic as syn_scan)
     // Use .into() to convert specific IP types to IpAddr
@@ -1406,59 +1450,164 @@ pub async fn null_scan(
         return Err(anyhow!("Internal error: Mismatch between selected local IP ({}) and target IP ({}) family for NULL scan.", local_ip, target_ip));
     }
 
-    // Calculate TTL
-    let ttl = if enhanced_evasion {
-        utils::get_advanced_ttl(mimic_os, ttl_jitter)
-    } else {
-        64
-    };
+    // Shape the probe (TTL, TCP options, timing, fragmentation) with the evasion pipeline
+    let shape = crate::evasion::shape_probe(target_ip.is_ipv6());
 
     // Prefixed with underscore to silence unused variable warning
     let _null_flags = 0u8; // No flags set, explicit u8
-    debug!("[NULL Scan:{}:{}] Performing raw NULL scan (no flags) using local IP {} with TTL {} and timeout {:?}. Requires root/admin.", target_ip, port, local_ip, ttl, timeout_duration);
+    debug!("[NULL Scan:{}:{}] Performing raw NULL scan (no flags) using local IP {} with TTL {} and timeout {:?}. Requires root/admin.", target_ip, port, local_ip, shape.ttl, timeout_duration);
 
     // Pass null_flags (u8) directly
-    match send_receive_raw_tcp(target_ip, port, local_ip, 0, timeout_duration, ttl).await {
-        Ok(Some(response)) => match response {
-            RawResponse::TcpStatic { flags, .. } => {
-                let rst_flag = u16::from(TcpFlags::RST);
-                if (flags & rst_flag) != 0 {
-                    debug!("[NULL Scan:{}:{}] Port is closed (received RST)", target_ip, port);
-                    Ok(PortStatus::Closed)
-                } else {
-                    debug!("[NULL Scan:{}:{}] Port is filtered (unexpected response)", target_ip, port);
-                    Ok(PortStatus::Filtered)
-                }
-            }
-            RawResponse::IcmpFiltered(icmp_type, icmp_code) => {
-                debug!("[NULL Scan:{}:{}] Port is filtered (received ICMPv4 {:?}/{:?})", target_ip, port, icmp_type, icmp_code);
-                Ok(PortStatus::Filtered)
-            }
-            RawResponse::Icmpv6Filtered(icmpv6_type, icmpv6_code) => {
-                debug!("[NULL Scan:{}:{}] Port is filtered (received ICMPv6 {:?}/{:?})", target_ip, port, icmpv6_type, icmpv6_code);
-                Ok(PortStatus::Filtered)
-            }
-            RawResponse::Timeout => {
-                // For NULL scan, no response typically means open|filtered
-                debug!("[NULL Scan:{}:{}] Port is open|filtered (no response)", target_ip, port);
-                Ok(PortStatus::OpenFiltered)
-            }
-        },
-        Ok(None) => {
-            debug!("[NULL Scan:{}:{}] No response data (open|filtered)", target_ip, port);
-            Ok(PortStatus::OpenFiltered)
-        },
-        Err(e) => {
+    let response = send_receive_raw_tcp(target_ip, port, local_ip, 0, timeout_duration, &shape).await
+        .map_err(|e| {
             error!("[NULL Scan:{}:{}] Error during scan: {}", target_ip, port, e);
-            Err(e)
+            e
+        })?;
+    let status = classify_stealth_response(&response);
+    debug!("[NULL Scan:{}:{}] Port is {} ({})", target_ip, port, status, describe_raw_response(&response));
+    Ok(status)
+}
+
+/// Short description of a raw probe outcome for comparisons
+fn describe_raw_response(response: &Option<RawResponse>) -> String {
+    let syn_ack = tcp_flags_as_u16(TcpFlags::SYN | TcpFlags::ACK);
+    let rst = tcp_flags_as_u16(TcpFlags::RST);
+    match response {
+        Some(RawResponse::TcpStatic { flags, .. }) if flags & syn_ack == syn_ack => "SYN-ACK".to_string(),
+        Some(RawResponse::TcpStatic { flags, .. }) if flags & rst != 0 => "RST".to_string(),
+        Some(RawResponse::TcpStatic { flags, .. }) => format!("TCP flags {:#04x}", flags),
+        Some(RawResponse::IcmpFiltered(t, c)) => format!("ICMP type {} code {}", t.0, c.0),
+        Some(RawResponse::Icmpv6Filtered(t, c)) => format!("ICMPv6 type {} code {}", t.0, c.0),
+        Some(RawResponse::Timeout) | None => "no reply".to_string(),
+    }
+}
+
+/// Bad-checksum / invalid-flag comparison scan (using raw sockets)
+///
+/// Sends three SYN probes to the same port and compares the answers:
+/// - a normal SYN, which gives the port status
+/// - a SYN with a corrupted TCP checksum, which a real host always drops; any answer
+///   comes from a middlebox (firewall, IDS, load balancer, SYN proxy) that replies on the
+///   host's behalf without validating checksums
+/// - a SYN+FIN, an illegal combination that hosts and stateful filters treat differently
+///
+/// Returns the port status from the normal SYN and a reason describing the comparison.
+/// REQUIRES root/administrator privileges.
+///
+/// # Opsec Considerations
+/// - Bad-checksum and SYN+FIN segments are classic IDS signatures and are logged by most sensors
+/// - Triples the probes per port
+pub async fn badsum_scan(
+    target_ip: IpAddr,
+    port: u16,
+    local_ip_option: Option<IpAddr>,
+    use_ipv6: bool,
+    timeout_duration: Duration,
+) -> Result<(PortStatus, String)> {
+    let local_ip = match (target_ip, local_ip_option) {
+        (IpAddr::V4(_), Some(ip @ IpAddr::V4(_))) => ip,
+        (IpAddr::V4(_), None) => IpAddr::V4(utils::find_local_ipv4()
+            .map_err(|e| anyhow!("Local IPv4 address required for raw BadSum scan and auto-detection failed: {}", e))?),
+        (IpAddr::V6(target_ipv6), _) if !use_ipv6 => {
+            return Err(anyhow!("Target is IPv6 ({}), but IPv6 scanning is not enabled via --ipv6 flag.", target_ipv6));
         }
+        (IpAddr::V6(_), Some(ip @ IpAddr::V6(_))) => ip,
+        (IpAddr::V6(_), None) => IpAddr::V6(utils::find_local_ipv6()
+            .map_err(|e| anyhow!("Local IPv6 address required for raw BadSum scan and auto-detection failed: {}", e))?),
+        _ => return Err(anyhow!("Provided local IP family does not match target {} for raw BadSum scan.", target_ip)),
+    };
+
+    let syn_flag: u8 = TcpFlags::SYN;
+    let baseline_shape = crate::evasion::shape_probe(target_ip.is_ipv6());
+    let mut badsum_shape = crate::evasion::shape_probe(target_ip.is_ipv6());
+    badsum_shape.bad_checksum = true;
+    let mut synfin_shape = crate::evasion::shape_probe(target_ip.is_ipv6());
+    synfin_shape.extra_flags |= TcpFlags::FIN;
+
+    debug!("[BadSum Scan:{}:{}] Sending normal, bad-checksum and SYN+FIN probes from {}", target_ip, port, local_ip);
+    let baseline = send_receive_raw_tcp(target_ip, port, local_ip, syn_flag, timeout_duration, &baseline_shape).await?;
+    let badsum = send_receive_raw_tcp(target_ip, port, local_ip, syn_flag, timeout_duration, &badsum_shape).await?;
+    let synfin = send_receive_raw_tcp(target_ip, port, local_ip, syn_flag, timeout_duration, &synfin_shape).await?;
+
+    let syn_ack = tcp_flags_as_u16(TcpFlags::SYN | TcpFlags::ACK);
+    let rst = tcp_flags_as_u16(TcpFlags::RST);
+    let status = match &baseline {
+        Some(RawResponse::TcpStatic { flags, .. }) if flags & syn_ack == syn_ack => PortStatus::Open,
+        Some(RawResponse::TcpStatic { flags, .. }) if flags & rst != 0 => PortStatus::Closed,
+        _ => PortStatus::Filtered,
+    };
+
+    let (baseline_desc, badsum_desc, synfin_desc) =
+        (describe_raw_response(&baseline), describe_raw_response(&badsum), describe_raw_response(&synfin));
+    let badsum_answered = !matches!(badsum, Some(RawResponse::Timeout) | None);
+    let mut reason = if badsum_answered {
+        format!("bad-checksum SYN answered with {} - a middlebox is replying for the host without validating checksums, so the normal SYN's {} may not come from the host",
+                badsum_desc, baseline_desc)
+    } else if status == PortStatus::Filtered {
+        format!("normal SYN got {}, bad-checksum SYN dropped", baseline_desc)
+    } else {
+        format!("normal SYN got {} and bad-checksum SYN was dropped - response is host-generated", baseline_desc)
+    };
+    if synfin_desc != baseline_desc {
+        reason.push_str(&format!("; SYN+FIN got {} instead - illegal flags are handled by a different stack or filter", synfin_desc));
+    } else {
+        reason.push_str(&format!("; SYN+FIN also got {}", synfin_desc));
+    }
+
+    debug!("[BadSum Scan:{}:{}] {}", target_ip, port, reason);
+    Ok((status, reason))
+}
+
+lazy_static::lazy_static! {
+    /// RST window of a closed port per target, measured once by the first Window scan probe
+    static ref WINDOW_BASELINES: parking_lot::Mutex<std::collections::HashMap<IpAddr, Arc<tokio::sync::OnceCell<Option<u16>>>>> =
+        parking_lot::Mutex::new(std::collections::HashMap::new());
+}
+
+/// Window field of a TCP header, if the header is complete
+pub(crate) fn rst_window(data: &[u8]) -> Option<u16> {
+    (data.len() >= 20).then(|| u16::from(data[14]) << 8 | u16::from(data[15]))
+}
+
+/// RST window the target sends for a closed port, probing a random high port once per target
+async fn closed_port_window(target_ip: IpAddr, local_ip: IpAddr, timeout_duration: Duration, shape: &ProbeShape) -> Option<u16> {
+    let cell = WINDOW_BASELINES.lock().entry(target_ip).or_default().clone();
+    *cell.get_or_init(|| async {
+        let port = utils::random_high_port();
+        let window = match send_receive_raw_tcp(target_ip, port, local_ip, TcpFlags::ACK.into(), timeout_duration, shape).await {
+            Ok(Some(RawResponse::TcpStatic { flags, data, .. })) if flags & u16::from(TcpFlags::RST) != 0 => rst_window(&data),
+            _ => None,
+        };
+        match window {
+            Some(0) => debug!("[Window Scan:{}] Closed port {} answers with window 0; the stack zeroes RST windows (Linux, Windows, modern BSD)", target_ip, port),
+            Some(w) => debug!("[Window Scan:{}] Closed port {} answers with window {}; windows equal to it count as closed", target_ip, port, w),
+            None => debug!("[Window Scan:{}] No closed-port baseline; falling back to zero/non-zero windows", target_ip),
+        }
+        window
+    }).await
+}
+
+/// Port state from the window of a RST answering an ACK
+///
+/// Legacy stacks (AIX, HP-UX, older BSD and VMS derivatives) put a non-zero window in RSTs
+/// from open ports and zero in those from closed ones. Some embedded stacks put the same
+/// non-zero window in every RST, which on its own reads as every port open, so a window
+/// equal to that of a known-closed port counts as closed. Linux, Windows and modern BSD
+/// stacks zero every RST window, so against them every unfiltered port reads as closed.
+fn classify_rst_window(window: u16, closed_window: Option<u16>) -> PortStatus {
+    match closed_window {
+        Some(baseline) if window == baseline => PortStatus::Closed,
+        Some(_) => PortStatus::Open,
+        None if window > 0 => PortStatus::Open,
+        None => PortStatus::Closed,
     }
 }
 
 /// Window scan implementation (using raw sockets)
-/// Sends TCP ACK packet and checks TCP window size in RST response.
-/// - RST with non-zero window -> Open.
-/// - RST with zero window -> Closed.
+/// Sends TCP ACK packet and checks TCP window size in RST response against the RST window
+/// of a closed port on the same host (see `classify_rst_window`).
+/// - RST with a window other than the closed port's -> Open.
+/// - RST with the closed port's window (zero without a baseline) -> Closed.
 /// - No response / ICMP -> Filtered.
 /// REQUIRES root/administrator privileges.
 pub async fn window_scan(
@@ -1467,9 +1616,9 @@ pub async fn window_scan(
     local_ip_option: Option<IpAddr>,
     use_ipv6: bool,
     timeout_duration: Duration,
-    enhanced_evasion: bool,
-    mimic_os: &str,
-    ttl_jitter: u8,
+    _enhanced_evasion: bool,
+    _mimic_os: &str,
+    _ttl_jitter: u8,
 ) -> Result<PortStatus> {
     // Determine the required local IP
     let local_ip: IpAddr = match target_ip {
@@ -1500,47 +1649,29 @@ pub async fn window_scan(
         return Err(anyhow!("Internal error: Mismatch between selected local IP ({}) and target IP ({}) family for Window scan.", local_ip, target_ip));
     }
 
-    // Calculate TTL
-    let ttl = if enhanced_evasion {
-        utils::get_advanced_ttl(mimic_os, ttl_jitter)
-    } else {
-        64
-    };
+    // Shape the probe (TTL, TCP options, timing, fragmentation) with the evasion pipeline
+    let shape = crate::evasion::shape_probe(target_ip.is_ipv6());
 
-    debug!("[Window Scan:{}:{}] Performing raw Window scan (ACK) using local IP {} with TTL {} and timeout {:?}. Requires root/admin.", target_ip, port, local_ip, ttl, timeout_duration);
+    debug!("[Window Scan:{}:{}] Performing raw Window scan (ACK) using local IP {} with TTL {} and timeout {:?}. Requires root/admin.", target_ip, port, local_ip, shape.ttl, timeout_duration);
 
     // Cast TcpFlags::ACK to u8
     let ack_flag: u8 = TcpFlags::ACK.into();
     // Generate a random sequence number
-    let _seq_num = rand::random::<u32>();
-    
-    // Generate a random source port (above 10000) if using enhanced evasion
-    let _source_port = if enhanced_evasion {
-        10000 + (rand::random::<u16>() % 55000)
-    } else {
-        12345 // Fixed source port if not using enhanced evasion
-    };
+    let _seq_num = utils::scan_rng().gen::<u32>();
     
     // Send ACK packet and wait for response
-    match send_receive_raw_tcp(target_ip, port, local_ip, ack_flag, timeout_duration, ttl).await {
+    match send_receive_raw_tcp(target_ip, port, local_ip, ack_flag, timeout_duration, &shape).await {
         Ok(Some(response)) => match response {
             RawResponse::TcpStatic { flags, data, .. } => {
                 let rst_flag = u16::from(TcpFlags::RST);
                 if (flags & rst_flag) != 0 {
-                    // Extract window size from TCP header
                     // TCP window size is bytes 14-15 in the TCP header
-                    // This is a more accurate implementation than checking data length
-                    if data.len() >= 20 {  // Minimum TCP header length
-                        // TCP header window size is at offset 14 (0-indexed)
-                        let window_size = u16::from(data[14]) << 8 | u16::from(data[15]);
-                        if window_size > 0 {
-                            debug!("[WINDOW Scan:{}:{}] Port is likely open (RST with non-zero window size: {})", 
-                                    target_ip, port, window_size);
-                            Ok(PortStatus::Open)
-                        } else {
-                            debug!("[WINDOW Scan:{}:{}] Port is likely closed (RST with zero window size)", target_ip, port);
-                            Ok(PortStatus::Closed)
-                        }
+                    if let Some(window_size) = rst_window(&data) {
+                        let closed_window = closed_port_window(target_ip, local_ip, timeout_duration, &shape).await;
+                        let status = classify_rst_window(window_size, closed_window);
+                        debug!("[WINDOW Scan:{}:{}] Port is likely {:?} (RST window {}, closed-port window {:?})",
+                                target_ip, port, status, window_size, closed_window);
+                        Ok(status)
                     } else {
                         // Fallback to original behavior if TCP header is incomplete
                         if !data.is_empty() {
@@ -1599,9 +1730,9 @@ pub async fn frag_scan(
     // _evasion: bool, // Removed unused evasion flag
     timeout_duration: Duration,
     // Add parameters for enhanced evasion
-    enhanced_evasion: bool,
-    mimic_os: &str,
-    ttl_jitter: u8,
+    _enhanced_evasion: bool,
+    _mimic_os: &str,
+    _ttl_jitter: u8,
 ) -> Result<PortStatus> {
     // Determine the required local IP and target IP based on family
     let (local_ip, target_ip_concrete) = match target_ip {
@@ -1636,17 +1767,16 @@ pub async fn frag_scan(
         }
     };
 
-    // Calculate TTL based on evasion settings
-    let ttl = if enhanced_evasion {
-        utils::get_advanced_ttl(mimic_os, ttl_jitter)
-    } else {
-        64 // Default TTL if not using enhanced evasion
-    };
+    // Shape the probe (TTL, TCP options, timing, fragmentation) with the evasion pipeline
+    let shape = crate::evasion::shape_probe(target_ip.is_ipv6());
 
     debug!("[Frag Scan:{}:{}] Performing raw fragment scan ({} -> {}) using TTL {} and timeout {:?}. Requires root/admin.",
-        target_ip, port, local_ip, target_ip, ttl, timeout_duration);
+        target_ip, port, local_ip, target_ip, shape.ttl, timeout_duration);
+    if !shape.delay.is_zero() {
+        tokio::time::sleep(shape.delay).await;
+    }
     let source_port = utils::random_high_port();
-    let seq_num: u32 = thread_rng().gen();
+    let seq_num: u32 = utils::scan_rng().gen();
 
     // --- Transport Channel Setup ---
     // Need Layer 3 access to craft IP headers directly
@@ -1660,26 +1790,26 @@ pub async fn frag_scan(
         Err(e) => return Err(anyhow!("Failed to open raw socket channel for Frag scan ({}): {}. Requires root/admin privileges.",
             if target_ip.is_ipv4() { "IPv4" } else { "IPv6" }, e)),
     };
+    crate::netbind::bind_raw(&tx)?;
 
     // --- Build the *Payload* (TCP SYN Segment) ---
-    let tcp_options = match target_ip_concrete {
-        IpAddr::V4(_) => [TcpOption::mss(1460)], // Standard MSS for IPv4
-        IpAddr::V6(_) => [TcpOption::mss(1440)], // Standard MSS for IPv6
-    };
-    let tcp_segment_len = TCP_HEADER_LEN + (tcp_options.len() * 4);
+    let tcp_header_len = TCP_HEADER_LEN + shape.tcp_options_len(TcpFlags::SYN);
+    let ip_header_len = if target_ip.is_ipv4() { IPV4_HEADER_LEN } else { IPV6_HEADER_LEN };
+    let tcp_segment_len = tcp_header_len + shape.padding_len(ip_header_len + tcp_header_len);
     let mut tcp_segment_buf = vec![0u8; tcp_segment_len];
+    utils::scan_rng().fill(&mut tcp_segment_buf[tcp_header_len..]);
     {
         let mut tcp_header = MutableTcpPacket::new(&mut tcp_segment_buf).unwrap();
         tcp_header.set_source(source_port);
         tcp_header.set_destination(port);
         tcp_header.set_sequence(seq_num);
         tcp_header.set_acknowledgement(0);
-        tcp_header.set_data_offset(((tcp_segment_len) / 4) as u8);
+        tcp_header.set_data_offset((tcp_header_len / 4) as u8);
         // Cast TcpFlags::SYN to u16 for set_flags
         tcp_header.set_flags(TcpFlags::SYN.into()); // Use into() instead of bits() as u8
-        tcp_header.set_window(1024);
+        tcp_header.set_window(shape.window);
         tcp_header.set_urgent_ptr(0);
-        tcp_header.set_options(&tcp_options);
+        tcp_header.set_options(&shape.tcp_options);
         tcp_header.set_checksum(0); // Zero out checksum initially
 
         // Calculate TCP checksum *once* based on IP version
@@ -1698,17 +1828,23 @@ pub async fn frag_scan(
     }
 
     // --- Fragmentation Logic ---
-    const FRAGMENT_DATA_SIZE: usize = 8; // Keep small fragment size
+    // Small fragments by default; a `frag:<size>` evasion stage overrides the size
+    let fragment_data_size = shape.fragment_size.unwrap_or(8);
     let tcp_data = &tcp_segment_buf[..]; // TCP segment is the data to be fragmented
     let mut fragments_sent = 0;
-    let mut current_offset = 0;
-    let identification: u32 = thread_rng().gen(); // Use u32 for IPv6 ID, cast later for IPv4
+    let identification: u32 = utils::scan_rng().gen(); // Use u32 for IPv6 ID, cast later for IPv4
+
+    // Split, reorder and overlap according to --frag-order / --frag-overlap
+    let policy = crate::evasion::fragment_policy();
+    let plan = policy.plan(tcp_data, fragment_data_size);
+    debug!("[Frag Scan:{}:{}] Fragment plan ({:?}, {:?}): {:?}", target_ip, port, policy.order, policy.overlap,
+           plan.iter().map(|p| (p.offset, p.data.len())).collect::<Vec<_>>());
 
-    while current_offset < tcp_data.len() {
-        let remaining_data = tcp_data.len() - current_offset;
-        let current_frag_size = std::cmp::min(FRAGMENT_DATA_SIZE, remaining_data);
-        let is_last_fragment = (current_offset + current_frag_size) == tcp_data.len();
-        let fragment_data = &tcp_data[current_offset..(current_offset + current_frag_size)];
+    for piece in &plan {
+        let current_offset = piece.offset;
+        let current_frag_size = piece.data.len();
+        let is_last_fragment = piece.last;
+        let fragment_data = &piece.data[..];
 
         // Prepare packet buffer and build fragment header based on IP version
         match (local_ip, target_ip_concrete) {
@@ -1723,7 +1859,7 @@ pub async fn frag_scan(
                 ip_header.set_header_length(5);
                 ip_header.set_total_length(ip_total_len as u16);
                 ip_header.set_identification(identification as u16); // Use lower 16 bits for IPv4 ID
-                ip_header.set_ttl(ttl);
+                ip_header.set_ttl(shape.ttl);
                 ip_header.set_next_level_protocol(IpNextHeaderProtocols::Tcp);
                 ip_header.set_source(local_ip_v4);
                 ip_header.set_destination(dest_ip_v4);
@@ -1753,10 +1889,19 @@ pub async fn frag_scan(
                 drop(ip_header);
                 
                 // Send IPv4 fragment - fix the temporary value issue
-                let ip_packet = MutableIpv4Packet::new(&mut packet_buf).unwrap();
-                let packet = ip_packet.to_immutable();
-                if tx.send_to(packet, target_ip).is_err() {
-                    return Err(anyhow!("[Frag Scan:{}:{}] Failed to send IPv4 fragment {} to {}", target_ip, port, fragments_sent + 1, target_ip));
+                crate::scheduler::spend(ip_total_len).await;
+                crate::audit::record(|| crate::audit::Probe::new("frag", "tcp", target_ip, Some(port), ip_total_len)
+                    .source(local_ip, Some(source_port))
+                    .detail(format!("SYN fragment at offset {} ({:?}, {:?})", current_offset, policy.order, policy.overlap))
+                    .shape(&shape));
+                let sent_spoofed = crate::mac_vendor::send_spoofed(local_ip, target_ip, &packet_buf).await
+                    .map_err(|e| anyhow!("[Frag Scan:{}:{}] Cannot send IPv4 fragment with the spoofed MAC: {}", target_ip, port, e))?;
+                if !sent_spoofed {
+                    let ip_packet = MutableIpv4Packet::new(&mut packet_buf).unwrap();
+                    let packet = ip_packet.to_immutable();
+                    if tx.send_to(packet, target_ip).is_err() {
+                        return Err(anyhow!("[Frag Scan:{}:{}] Failed to send IPv4 fragment {} to {}", target_ip, port, fragments_sent + 1, target_ip));
+                    }
                 }
             }
             (IpAddr::V6(local_ip_v6), IpAddr::V6(dest_ip_v6)) => {
@@ -1773,7 +1918,7 @@ pub async fn frag_scan(
                     ip_header.set_payload_length(ip_payload_len as u16);
                     // Use the correct constant for IPv6 Fragment Header
                     ip_header.set_next_header(IpNextHeaderProtocols::Ipv6Frag);
-                    ip_header.set_hop_limit(ttl);
+                    ip_header.set_hop_limit(shape.ttl);
                     ip_header.set_source(local_ip_v6);
                     ip_header.set_destination(dest_ip_v6);
 
@@ -1802,9 +1947,16 @@ pub async fn frag_scan(
                     packet_buf[IPV6_HEADER_LEN + FRAG_HEADER_LEN..total_len].copy_from_slice(fragment_data);
                 }
                  // Send IPv6 fragment
+                 crate::scheduler::spend(total_len).await;
+                 crate::audit::record(|| crate::audit::Probe::new("frag", "tcp", target_ip, Some(port), total_len)
+                     .source(local_ip, Some(source_port))
+                     .detail(format!("SYN fragment at offset {} ({:?}, {:?})", current_offset, policy.order, policy.overlap))
+                     .shape(&shape));
                  // Clone target_ip before moving into the error message
                  let target_ip_clone = target_ip.clone();
-                 if tx.send_to(MutableIpv6Packet::new(&mut packet_buf).unwrap().to_immutable(), target_ip).is_err() {
+                 let sent_spoofed = crate::mac_vendor::send_spoofed(local_ip, target_ip, &packet_buf).await
+                     .map_err(|e| anyhow!("[Frag Scan:{}:{}] Cannot send IPv6 fragment with the spoofed MAC: {}", target_ip, port, e))?;
+                 if !sent_spoofed && tx.send_to(MutableIpv6Packet::new(&mut packet_buf).unwrap().to_immutable(), target_ip).is_err() {
                      return Err(anyhow!("[Frag Scan:{}:{}] Failed to send IPv6 fragment {} to {}", target_ip_clone, port, fragments_sent + 1, target_ip_clone));
                  }
             }
@@ -1812,7 +1964,6 @@ pub async fn frag_scan(
         }
 
         fragments_sent += 1;
-        current_offset += current_frag_size;
 
         // Optional: Small delay between fragments
         // sleep(Duration::from_millis(5)).await;
@@ -1820,7 +1971,7 @@ pub async fn frag_scan(
     } // End fragmentation loop
 
     debug!("[Frag Scan:{}:{}] Sent {} fragments (total {} bytes TCP data) from port {} with ID {} using TTL {}",
-           target_ip, port, fragments_sent, tcp_data.len(), source_port, identification, ttl);
+           target_ip, port, fragments_sent, tcp_data.len(), source_port, identification, shape.ttl);
 
     // --- Receive Logic (Similar approach as send_receive_raw_tcp to fix lifetime issues) ---
     // Create a new channel for receiving instead of reusing rx
@@ -1883,4 +2034,484 @@ pub async fn frag_scan(
     // Timeout occurred
     debug!("[Frag Scan:{}:{}] Timeout occurred. Assuming Filtered.", target_ip, port);
     return Ok(PortStatus::Filtered);
-} 
\ No newline at end of file
+}
+
+/// Build a complete IPv4 packet (20 byte header, TTL 64, random ID) around a payload
+fn build_raw_ipv4(
+    local_v4: Ipv4Addr,
+    target_v4: Ipv4Addr,
+    protocol: pnet::packet::ip::IpNextHeaderProtocol,
+    payload: &[u8],
+) -> Result<Vec<u8>> {
+    let total_len = IPV4_HEADER_LEN + payload.len();
+    let mut packet_buf = vec![0u8; total_len];
+    {
+        let mut ip_header = MutableIpv4Packet::new(&mut packet_buf)
+            .ok_or_else(|| anyhow!("Failed to build IPv4 header"))?;
+        ip_header.set_version(4);
+        ip_header.set_header_length(5);
+        ip_header.set_total_length(total_len as u16);
+        ip_header.set_ttl(64);
+        ip_header.set_next_level_protocol(protocol);
+        ip_header.set_source(local_v4);
+        ip_header.set_destination(target_v4);
+        ip_header.set_identification(utils::scan_rng().gen());
+        ip_header.set_payload(payload);
+        let checksum = pnet::packet::ipv4::checksum(&ip_header.to_immutable());
+        ip_header.set_checksum(checksum);
+    }
+    Ok(packet_buf)
+}
+
+/// Build a minimal, well-formed payload for an IP protocol probe
+///
+/// Hosts often silently drop malformed headers, so each protocol gets the smallest
+/// header that a real implementation would parse (and ideally answer).
+fn ip_proto_probe_payload(protocol: u8) -> Vec<u8> {
+    let mut rng = utils::scan_rng();
+    match protocol {
+        // ICMP echo request (checksum filled in below)
+        1 => {
+            let mut payload = vec![8, 0, 0, 0];
+            payload.extend_from_slice(&rng.gen::<u16>().to_be_bytes());
+            payload.extend_from_slice(&1u16.to_be_bytes());
+            let checksum = pnet::util::checksum(&payload, 1);
+            payload[2..4].copy_from_slice(&checksum.to_be_bytes());
+            payload
+        }
+        // IGMPv2 general membership query
+        2 => {
+            let mut payload = vec![0x11, 0x64, 0, 0, 0, 0, 0, 0];
+            let checksum = pnet::util::checksum(&payload, 1);
+            payload[2..4].copy_from_slice(&checksum.to_be_bytes());
+            payload
+        }
+        // TCP: bare SYN header (checksum left zero; only used to elicit protocol unreachable)
+        6 => {
+            let mut payload = vec![0u8; 20];
+            payload[0..2].copy_from_slice(&utils::random_high_port().to_be_bytes());
+            payload[2..4].copy_from_slice(&80u16.to_be_bytes());
+            payload[12] = 5 << 4;
+            payload[13] = TcpFlags::SYN as u8;
+            payload[14..16].copy_from_slice(&1024u16.to_be_bytes());
+            payload
+        }
+        // UDP header with zero checksum (allowed for IPv4)
+        17 => {
+            let mut payload = vec![0u8; 8];
+            payload[0..2].copy_from_slice(&utils::random_high_port().to_be_bytes());
+            payload[2..4].copy_from_slice(&40125u16.to_be_bytes());
+            payload[4..6].copy_from_slice(&8u16.to_be_bytes());
+            payload
+        }
+        // GRE: base header carrying IPv4, no optional fields
+        47 => vec![0x00, 0x00, 0x08, 0x00],
+        // ESP: random SPI and sequence number 1
+        50 => {
+            let mut payload = rng.gen::<u32>().to_be_bytes().to_vec();
+            payload.extend_from_slice(&1u32.to_be_bytes());
+            payload.extend_from_slice(&[0u8; 8]);
+            payload
+        }
+        // AH: next header none, length 4 (24 bytes), random SPI
+        51 => {
+            let mut payload = vec![59, 4, 0, 0];
+            payload.extend_from_slice(&rng.gen::<u32>().to_be_bytes());
+            payload.extend_from_slice(&1u32.to_be_bytes());
+            payload.extend_from_slice(&[0u8; 12]);
+            payload
+        }
+        // SCTP common header + INIT chunk (CRC32c left zero; enough to trigger ABORT or unreachable)
+        132 => {
+            let mut payload = vec![0u8; 12];
+            payload[0..2].copy_from_slice(&utils::random_high_port().to_be_bytes());
+            payload[2..4].copy_from_slice(&80u16.to_be_bytes());
+            // INIT chunk: type 1, flags 0, length 20, initiate tag, a_rwnd, streams, initial TSN
+            payload.extend_from_slice(&[1, 0, 0, 20]);
+            payload.extend_from_slice(&rng.gen::<u32>().to_be_bytes());
+            payload.extend_from_slice(&65535u32.to_be_bytes());
+            payload.extend_from_slice(&[0, 10, 0, 10]);
+            payload.extend_from_slice(&rng.gen::<u32>().to_be_bytes());
+            payload
+        }
+        // Everything else: empty payload, we only want the protocol unreachable signal
+        _ => Vec::new(),
+    }
+}
+
+/// IP protocol scan (the equivalent of nmap -sO)
+///
+/// Sends a raw IPv4 packet with the given protocol number and classifies the host's reaction:
+/// - Any reply in the same protocol (or ICMP echo reply for ICMP) -> Open
+/// - ICMP protocol unreachable (type 3 code 2) -> Closed
+/// - Other ICMP unreachable codes (admin prohibited etc.) -> Filtered
+/// - No response -> Open|Filtered (many stacks silently accept unknown payloads)
+///
+/// Useful for finding VPN concentrators and tunnel endpoints (GRE, ESP, AH) that are
+/// invisible to TCP/UDP scans. REQUIRES root/administrator privileges. IPv4 only.
+///
+/// # Returns
+/// * `Result<(PortStatus, String)>` - Protocol state and the reason for it
+pub async fn ip_proto_scan(
+    target_ip: IpAddr,
+    protocol: u8,
+    local_ip_option: Option<IpAddr>,
+    timeout_duration: Duration,
+) -> Result<(PortStatus, String)> {
+    let (target_v4, local_v4) = match (target_ip, local_ip_option) {
+        (IpAddr::V4(target), Some(IpAddr::V4(local))) => (target, local),
+        (IpAddr::V4(target), None) => {
+            let local = utils::find_local_ipv4()
+                .map_err(|e| anyhow!("Local IPv4 address required for IP protocol scan: {}", e))?;
+            (target, local)
+        }
+        _ => return Err(anyhow!("IP protocol scan only supports IPv4 targets")),
+    };
+
+    debug!("[IPProto Scan:{}:{}] Probing IP protocol {} ({})", target_ip, protocol, protocol, crate::models::ip_protocol_name(protocol));
+
+    tokio::task::spawn_blocking(move || -> Result<(PortStatus, String)> {
+        let proto = pnet::packet::ip::IpNextHeaderProtocol::new(protocol);
+
+        // Raw socket for the probed protocol (IP_HDRINCL) and one for ICMP errors
+        let (mut tx, mut proto_rx) = transport_channel(4096, TransportChannelType::Layer3(proto))
+            .map_err(|e| anyhow!("Failed to open raw socket for protocol {} (requires root/admin privileges): {}", protocol, e))?;
+        crate::netbind::bind_raw(&tx)?;
+        let (_, mut icmp_rx) = transport_channel(4096, TransportChannelType::Layer3(IpNextHeaderProtocols::Icmp))
+            .map_err(|e| anyhow!("Failed to open raw ICMP socket (requires root/admin privileges): {}", e))?;
+
+        let packet_buf = build_raw_ipv4(local_v4, target_v4, proto, &ip_proto_probe_payload(protocol))?;
+
+        let ip_packet = pnet::packet::ipv4::Ipv4Packet::new(&packet_buf)
+            .ok_or_else(|| anyhow!("Failed to build IPv4 packet"))?;
+        crate::scheduler::spend_blocking(packet_buf.len());
+        crate::audit::record(|| crate::audit::Probe::new("ip-proto", "ip", target_ip, None, packet_buf.len())
+            .source(IpAddr::V4(local_v4), None).detail(format!("IP protocol {}", protocol)));
+        let sent_spoofed = crate::mac_vendor::send_spoofed_blocking(IpAddr::V4(local_v4), target_ip, &packet_buf)
+            .map_err(|e| anyhow!("Cannot send protocol {} probe with the spoofed MAC: {}", protocol, e))?;
+        if !sent_spoofed {
+            tx.send_to(ip_packet, target_ip)
+                .map_err(|e| anyhow!("Failed to send protocol {} probe: {}", protocol, e))?;
+        }
+
+        let deadline = Instant::now() + timeout_duration;
+        let poll = Duration::from_millis(50);
+        let mut proto_iter = transport::ipv4_packet_iter(&mut proto_rx);
+        let mut icmp_iter = transport::ipv4_packet_iter(&mut icmp_rx);
+
+        while Instant::now() < deadline {
+            // Reply in the probed protocol. ICMP is handled below so an echo request
+            // we sent ourselves (looped back) is not mistaken for a reply.
+            if protocol != 1 {
+                if let Ok(Some((packet, addr))) = proto_iter.next_with_timeout(poll) {
+                    if addr == target_ip && packet.get_next_level_protocol() == proto {
+                        return Ok((PortStatus::Open, format!("IP protocol scan: {} response received", crate::models::ip_protocol_name(protocol))));
+                    }
+                }
+            }
+
+            if let Ok(Some((packet, addr))) = icmp_iter.next_with_timeout(poll) {
+                let icmp_data = packet.payload();
+                if icmp_data.len() < 8 {
+                    continue;
+                }
+                let (icmp_type, icmp_code) = (icmp_data[0], icmp_data[1]);
+
+                if protocol == 1 && icmp_type == 0 && addr == target_ip {
+                    return Ok((PortStatus::Open, "IP protocol scan: ICMP echo reply received".to_string()));
+                }
+
+                // Destination unreachable quoting our probe (may come from a router en route)
+                if icmp_type == 3 {
+                    if let Some(quoted) = pnet::packet::ipv4::Ipv4Packet::new(&icmp_data[8..]) {
+                        if quoted.get_destination() != target_v4 || quoted.get_next_level_protocol() != proto {
+                            continue;
+                        }
+                        return Ok(match icmp_code {
+                            2 if addr == target_ip => (PortStatus::Closed, "IP protocol scan: ICMP protocol unreachable received".to_string()),
+                            _ => (PortStatus::Filtered, format!("IP protocol scan: ICMP unreachable (code {}) from {}", icmp_code, addr)),
+                        });
+                    }
+                }
+            }
+        }
+
+        Ok((PortStatus::OpenFiltered, "IP protocol scan: No response (protocol accepted silently or filtered)".to_string()))
+    }).await.map_err(|e| anyhow!("IP protocol scan task failed: {}", e))?
+}
+
+/// Meaning of an ICMP destination unreachable code (RFC 792 / RFC 1812)
+pub fn icmp_unreachable_meaning(code: u8) -> &'static str {
+    match code {
+        0 => "network unreachable",
+        1 => "host unreachable",
+        2 => "protocol unreachable",
+        3 => "port unreachable (closed)",
+        4 => "fragmentation needed and DF set",
+        5 => "source route failed",
+        6 => "destination network unknown",
+        7 => "destination host unknown",
+        9 => "network administratively prohibited",
+        10 => "host administratively prohibited",
+        11 => "network unreachable for TOS",
+        12 => "host unreachable for TOS",
+        13 => "communication administratively prohibited (firewall)",
+        14 => "host precedence violation",
+        15 => "precedence cutoff in effect",
+        _ => "unknown unreachable code",
+    }
+}
+
+/// Milliseconds since midnight UTC, the unit used by ICMP timestamp messages
+fn icmp_timestamp_now() -> u32 {
+    let millis = std::time::SystemTime::now()
+        .duration_since(std::time::UNIX_EPOCH)
+        .map(|d| d.as_millis())
+        .unwrap_or(0);
+    (millis % 86_400_000) as u32
+}
+
+/// Build an ICMP information request (timestamp or address mask) with a valid checksum
+fn build_icmp_info_request(icmp_type: u8, id: u16, seq: u16) -> Vec<u8> {
+    let mut packet = vec![icmp_type, 0, 0, 0];
+    packet.extend_from_slice(&id.to_be_bytes());
+    packet.extend_from_slice(&seq.to_be_bytes());
+    match icmp_type {
+        // Timestamp: originate, receive, transmit
+        13 => {
+            packet.extend_from_slice(&icmp_timestamp_now().to_be_bytes());
+            packet.extend_from_slice(&[0u8; 8]);
+        }
+        // Address mask: mask field left zero
+        17 => packet.extend_from_slice(&[0u8; 4]),
+        _ => {}
+    }
+    let checksum = pnet::util::checksum(&packet, 1);
+    packet[2..4].copy_from_slice(&checksum.to_be_bytes());
+    packet
+}
+
+/// ICMP host characterization probe
+///
+/// Sends an ICMP timestamp request, an ICMP address mask request and a UDP datagram
+/// to a (presumably) closed port, then analyzes what comes back:
+/// - Timestamp replies reveal the remote clock offset and timestamp format
+/// - Address mask replies reveal the subnet mask (mostly legacy or embedded stacks)
+/// - The UDP port unreachable error reveals the initial TTL, DF policy and how much
+///   of the offending datagram the stack quotes, all of which vary between OSes
+///
+/// REQUIRES root/administrator privileges. IPv4 only.
+///
+/// # Arguments
+/// * `target_ip` - Target address
+/// * `local_ip_option` - Local IPv4 address to send from
+/// * `udp_port` - UDP port expected to be closed (a known closed port gives the best result)
+/// * `timeout_duration` - How long to wait for the replies
+///
+/// # Returns
+/// * `Result<IcmpHostInfo>` - Whatever the host revealed; fields stay empty if it stayed silent
+///
+/// # Opsec Considerations
+/// - Timestamp and address mask requests are rare in normal traffic and commonly flagged by IDS
+/// - Three packets total, so the volume footprint is negligible
+pub async fn icmp_host_probe(
+    target_ip: IpAddr,
+    local_ip_option: Option<IpAddr>,
+    udp_port: u16,
+    timeout_duration: Duration,
+) -> Result<crate::models::IcmpHostInfo> {
+    let (target_v4, local_v4) = match (target_ip, local_ip_option) {
+        (IpAddr::V4(target), Some(IpAddr::V4(local))) => (target, local),
+        (IpAddr::V4(target), None) => {
+            let local = utils::find_local_ipv4()
+                .map_err(|e| anyhow!("Local IPv4 address required for ICMP probing: {}", e))?;
+            (target, local)
+        }
+        _ => return Err(anyhow!("ICMP host probing only supports IPv4 targets")),
+    };
+
+    debug!("[ICMP Info:{}] Sending timestamp, address mask and UDP:{} probes", target_ip, udp_port);
+
+    tokio::task::spawn_blocking(move || -> Result<crate::models::IcmpHostInfo> {
+        use crate::models::{IcmpHostInfo, UnreachableAnalysis};
+
+        const UDP_PROBE_LEN: usize = 300;
+
+        let (mut tx, mut rx) = transport_channel(4096, TransportChannelType::Layer3(IpNextHeaderProtocols::Icmp))
+            .map_err(|e| anyhow!("Failed to open raw ICMP socket (requires root/admin privileges): {}", e))?;
+        crate::netbind::bind_raw(&tx)?;
+
+        let id: u16 = utils::scan_rng().gen();
+        for (icmp_type, seq) in [(13u8, 1u16), (17u8, 2u16)] {
+            let packet_buf = build_raw_ipv4(local_v4, target_v4, IpNextHeaderProtocols::Icmp, &build_icmp_info_request(icmp_type, id, seq))?;
+            if let Some(packet) = pnet::packet::ipv4::Ipv4Packet::new(&packet_buf) {
+                crate::scheduler::spend_blocking(packet_buf.len());
+                crate::audit::record(|| crate::audit::Probe::new("icmp-info", "icmp", target_ip, None, packet_buf.len())
+                    .source(IpAddr::V4(local_v4), None).detail(format!("ICMP type {}", icmp_type)));
+                let sent_spoofed = crate::mac_vendor::send_spoofed_blocking(IpAddr::V4(local_v4), target_ip, &packet_buf)
+                    .map_err(|e| anyhow!("Cannot send ICMP type {} request with the spoofed MAC: {}", icmp_type, e))?;
+                if !sent_spoofed {
+                    if let Err(e) = tx.send_to(packet, target_ip) {
+                        warn!("[ICMP Info:{}] Failed to send ICMP type {} request: {}", target_ip, icmp_type, e);
+                    }
+                }
+            }
+        }
+
+        // Regular UDP socket; the kernel fills in the UDP header and checksum. With
+        // --spoof-mac it only reserves the source port and the datagram is built here
+        let udp_socket = crate::netbind::bind_udp_std(target_ip)
+            .map_err(|e| anyhow!("Failed to bind UDP socket: {}", e))?;
+        let udp_source_port = udp_socket.local_addr()?.port();
+        crate::scheduler::spend_blocking(UDP_PROBE_LEN + crate::scheduler::UDP_HEADER_BYTES);
+        crate::audit::record(|| crate::audit::Probe::new("icmp-info", "udp", target_ip, Some(udp_port), UDP_PROBE_LEN + crate::scheduler::UDP_HEADER_BYTES)
+            .source(IpAddr::V4(local_v4), Some(udp_source_port)).detail("UDP probe to a closed port for ICMP unreachable"));
+        if crate::mac_vendor::spoofed_mac().is_some() {
+            // Checksum 0 means "none" for UDP over IPv4
+            let mut datagram = Vec::with_capacity(8 + UDP_PROBE_LEN);
+            datagram.extend_from_slice(&udp_source_port.to_be_bytes());
+            datagram.extend_from_slice(&udp_port.to_be_bytes());
+            datagram.extend_from_slice(&((8 + UDP_PROBE_LEN) as u16).to_be_bytes());
+            datagram.extend_from_slice(&[0, 0]);
+            datagram.extend_from_slice(&[0x43u8; UDP_PROBE_LEN]);
+            let packet_buf = build_raw_ipv4(local_v4, target_v4, IpNextHeaderProtocols::Udp, &datagram)?;
+            crate::mac_vendor::send_spoofed_blocking(IpAddr::V4(local_v4), target_ip, &packet_buf)
+                .map_err(|e| anyhow!("Cannot send UDP probe with the spoofed MAC: {}", e))?;
+        } else {
+            udp_socket.send_to(&[0x43u8; UDP_PROBE_LEN], SocketAddr::new(target_ip, udp_port))
+                .map_err(|e| anyhow!("Failed to send UDP probe: {}", e))?;
+        }
+
+        let mut info = IcmpHostInfo::default();
+        let mut mask_answered = false;
+        let deadline = Instant::now() + timeout_duration;
+        let mut iter = transport::ipv4_packet_iter(&mut rx);
+
+        while Instant::now() < deadline && !(info.timestamp_reply && mask_answered && info.udp_unreachable.is_some()) {
+            let (packet, addr) = match iter.next_with_timeout(Duration::from_millis(50)) {
+                Ok(Some(received)) => received,
+                _ => continue,
+            };
+            let icmp_data = packet.payload();
+            if icmp_data.len() < 8 {
+                continue;
+            }
+            let reply_id = u16::from_be_bytes([icmp_data[4], icmp_data[5]]);
+
+            match icmp_data[0] {
+                // Timestamp reply
+                14 if addr == target_ip && reply_id == id && icmp_data.len() >= 20 => {
+                    let originate = u32::from_be_bytes([icmp_data[8], icmp_data[9], icmp_data[10], icmp_data[11]]);
+                    let receive = u32::from_be_bytes([icmp_data[12], icmp_data[13], icmp_data[14], icmp_data[15]]);
+                    let transmit = u32::from_be_bytes([icmp_data[16], icmp_data[17], icmp_data[18], icmp_data[19]]);
+                    info.timestamp_reply = true;
+                    info.reply_ttl.get_or_insert(packet.get_ttl());
+
+                    // High bit set means the stack could not provide ms since midnight UTC
+                    if receive & 0x8000_0000 != 0 || transmit & 0x8000_0000 != 0 {
+                        info.nonstandard_timestamp = true;
+                    } else {
+                        let rtt = icmp_timestamp_now().wrapping_sub(originate) as i64 % 86_400_000;
+                        let mut offset = receive as i64 - originate as i64 - rtt / 2;
+                        // Normalise across midnight
+                        if offset > 43_200_000 {
+                            offset -= 86_400_000;
+                        } else if offset < -43_200_000 {
+                            offset += 86_400_000;
+                        }
+                        info.clock_offset_ms = Some(offset);
+                    }
+                }
+                // Address mask reply
+                18 if addr == target_ip && reply_id == id && icmp_data.len() >= 12 => {
+                    mask_answered = true;
+                    info.reply_ttl.get_or_insert(packet.get_ttl());
+                    info.netmask = Some(Ipv4Addr::new(icmp_data[8], icmp_data[9], icmp_data[10], icmp_data[11]).to_string());
+                }
+                // Destination unreachable quoting our UDP probe (may come from a filtering device)
+                3 => {
+                    let Some(quoted) = pnet::packet::ipv4::Ipv4Packet::new(&icmp_data[8..]) else { continue };
+                    let quoted_header_len = quoted.get_header_length() as usize * 4;
+                    let quoted_l4 = &icmp_data[8..][quoted_header_len.min(icmp_data.len() - 8)..];
+                    if quoted.get_destination() != target_v4
+                        || quoted.get_next_level_protocol() != IpNextHeaderProtocols::Udp
+                        || quoted_l4.len() < 8
+                        || u16::from_be_bytes([quoted_l4[0], quoted_l4[1]]) != udp_source_port
+                        || u16::from_be_bytes([quoted_l4[2], quoted_l4[3]]) != udp_port
+                    {
+                        continue;
+                    }
+
+                    let quoted_udp_len = u16::from_be_bytes([quoted_l4[4], quoted_l4[5]]) as usize;
+                    let icmp_code = icmp_data[1];
+                    if addr == target_ip {
+                        info.reply_ttl.get_or_insert(packet.get_ttl());
+                    }
+                    info.udp_unreachable = Some(UnreachableAnalysis {
+                        port: udp_port,
+                        icmp_code,
+                        meaning: icmp_unreachable_meaning(icmp_code).to_string(),
+                        source: addr.to_string(),
+                        reply_ttl: packet.get_ttl(),
+                        dont_fragment: packet.get_flags() & Ipv4Flags::DontFragment != 0,
+                        quoted_bytes: quoted_l4.len(),
+                        quote_intact: quoted.get_total_length() as usize == IPV4_HEADER_LEN + 8 + UDP_PROBE_LEN
+                            && quoted_udp_len == 8 + UDP_PROBE_LEN,
+                    });
+                }
+                _ => {}
+            }
+        }
+
+        info.os_hints = icmp_os_hints(&info, target_ip, 8 + UDP_PROBE_LEN);
+        Ok(info)
+    }).await.map_err(|e| anyhow!("ICMP probe task failed: {}", e))?
+}
+
+/// Derive OS and device hints from ICMP behaviour
+fn icmp_os_hints(info: &crate::models::IcmpHostInfo, target_ip: IpAddr, probe_len: usize) -> Vec<String> {
+    let mut hints = Vec::new();
+
+    if let Some(ttl) = info.reply_ttl {
+        hints.push(match ttl {
+            0..=32 => format!("Initial TTL 32 (observed {}): legacy Windows 9x or embedded stack", ttl),
+            33..=64 => format!("Initial TTL 64 (observed {}): Linux, BSD or macOS", ttl),
+            65..=128 => format!("Initial TTL 128 (observed {}): Windows", ttl),
+            _ => format!("Initial TTL 255 (observed {}): network device, Solaris or AIX", ttl),
+        });
+    }
+
+    if info.timestamp_reply {
+        hints.push("Answers ICMP timestamp requests (typical of Linux/BSD and network devices; modern Windows does not)".to_string());
+    }
+    if info.nonstandard_timestamp {
+        hints.push("Uses non-standard ICMP timestamp format (clock not synchronised to UTC midnight)".to_string());
+    }
+    if let Some(offset) = info.clock_offset_ms {
+        if offset.abs() > 60_000 {
+            hints.push(format!("Host clock is {:.1} minutes off (unsynchronised host)", offset as f64 / 60_000.0));
+        }
+    }
+    if info.netmask.is_some() {
+        hints.push("Answers ICMP address mask requests: legacy stack, router or embedded device".to_string());
+    }
+
+    if let Some(unreach) = &info.udp_unreachable {
+        if unreach.source != target_ip.to_string() {
+            hints.push(format!("UDP unreachable generated by {} rather than the target: filtering device in path", unreach.source));
+        }
+        if matches!(unreach.icmp_code, 9 | 10 | 13) {
+            hints.push("Firewall rejects with ICMP administratively prohibited".to_string());
+        }
+        if unreach.quoted_bytes <= 8 {
+            hints.push("ICMP errors quote only 8 bytes of the datagram (RFC 792 minimum: older BSD, Windows or router)".to_string());
+        } else if unreach.quoted_bytes >= probe_len {
+            hints.push("ICMP errors quote the full datagram (RFC 1812 style, typical of Linux)".to_string());
+        }
+        if !unreach.quote_intact {
+            hints.push("Quoted datagram was modified in transit or by the stack (NAT/normalisation or stack quirk)".to_string());
+        }
+    }
+
+    hints
+}
@@ -0,0 +1,487 @@
+//! Transports for raw TCP probes.
+//!
+//! The scan techniques build a probe, hand it to a `Transport` and interpret whatever
+//! comes back; they never touch a socket themselves. `RawSocketTransport` puts probes on
+//! the wire (through the AF_PACKET/Npcap engine with `--fast-io`, otherwise a raw socket)
+//! and is what every scan uses. `MockTransport` answers from a function instead, so the
+//! techniques and their interpretation of replies can be driven without root or a network,
+//! with simulated targets that answer as a given stack or firewall would.
+
+use std::net::IpAddr;
+use std::sync::Arc;
+use std::time::Duration;
+
+use anyhow::{anyhow, Result};
+use async_trait::async_trait;
+use lazy_static::lazy_static;
+use log::{debug, warn};
+use parking_lot::RwLock;
+use pnet::packet::icmp::{self, IcmpTypes};
+use pnet::packet::icmpv6;
+use pnet::packet::ip::IpNextHeaderProtocols;
+use pnet::packet::ipv4::MutableIpv4Packet;
+use pnet::packet::ipv6::MutableIpv6Packet;
+use pnet::packet::Packet;
+use pnet::transport::{self, transport_channel, TransportChannelType};
+use rand::Rng;
+
+use crate::evasion::ProbeShape;
+use crate::utils;
+
+/// Outcome of a raw TCP probe
+#[derive(Debug, Clone)]
+pub(crate) enum RawResponse {
+    /// A TCP segment from the target: its flags, the segment bytes and its ports
+    TcpStatic {
+        flags: u16,
+        data: Vec<u8>,
+        source: u16,
+        destination: u16,
+        /// TTL of the carrying IP packet, when the receive path sees IP headers
+        ttl: Option<u8>,
+    },
+    /// An ICMPv4 error message relevant to filtering was received.
+    IcmpFiltered(icmp::IcmpType, icmp::IcmpCode),
+    /// An ICMPv6 error message relevant to filtering was received.
+    Icmpv6Filtered(icmpv6::Icmpv6Type, icmpv6::Icmpv6Code),
+    /// The receive operation timed out.
+    Timeout,
+}
+
+/// One raw TCP probe as a technique asks for it
+#[derive(Debug, Clone)]
+pub(crate) struct TcpProbe {
+    pub target_ip: IpAddr,
+    pub port: u16,
+    /// Source address; same family as `target_ip`
+    pub local_ip: IpAddr,
+    /// TCP flags of the probe, before the shape's extra flags
+    pub flags: u8,
+    pub timeout: Duration,
+    /// Header fields and send behaviour chosen by the evasion pipeline
+    pub shape: ProbeShape,
+}
+
+/// Sends raw TCP probes and returns what came back
+#[async_trait]
+pub(crate) trait Transport: Send + Sync {
+    /// Send `probe` and wait for the matching reply
+    ///
+    /// # Returns
+    /// * `Result<Option<RawResponse>>` - The reply, `RawResponse::Timeout` when none came,
+    ///   or an error when the probe could not be sent
+    async fn send_tcp(&self, probe: &TcpProbe) -> Result<Option<RawResponse>>;
+}
+
+lazy_static! {
+    /// Transport every raw TCP probe goes through
+    static ref TRANSPORT: RwLock<Arc<dyn Transport>> = RwLock::new(Arc::new(RawSocketTransport));
+}
+
+/// Transport raw TCP probes currently go through
+pub(crate) fn current() -> Arc<dyn Transport> {
+    TRANSPORT.read().clone()
+}
+
+/// Route raw TCP probes through `transport`, e.g. a `MockTransport` in a test harness
+#[cfg(test)]
+pub(crate) fn set_transport(transport: Arc<dyn Transport>) {
+    *TRANSPORT.write() = transport;
+}
+
+/// Probes on the wire, through the fast I/O engine or a raw socket.
+/// Requires root/administrator privileges.
+pub(crate) struct RawSocketTransport;
+
+#[async_trait]
+impl Transport for RawSocketTransport {
+    async fn send_tcp(&self, probe: &TcpProbe) -> Result<Option<RawResponse>> {
+        let TcpProbe { target_ip, port, local_ip, flags: tcp_flags_out, timeout: timeout_duration, ref shape } = *probe;
+        let source_port = utils::random_high_port();
+        let seq_num: u32 = utils::scan_rng().gen();
+
+        // Cap the timeout at 30s; short ones are kept, since with --adaptive-timeout they
+        // come from the host's measured round-trip time
+        let timeout_duration = timeout_duration.min(Duration::from_secs(30));
+
+        // Delay chosen by the evasion pipeline's timing stage
+        if !shape.delay.is_zero() {
+            tokio::time::sleep(shape.delay).await;
+        }
+
+        // Fast path: hand the probe to the shared AF_PACKET engine instead of opening a
+        // raw socket per probe (IPv4 only, enabled with --fast-io; fragmented probes take the raw socket path)
+        if let (Some(engine), IpAddr::V4(dest_ip_v4), IpAddr::V4(local_ip_v4), None) = (crate::fast_io::engine(), target_ip, local_ip, shape.fragment_size) {
+            let mut packet_buf = vec![0u8; crate::evasion::MAX_PADDED_LEN];
+            let packet_len = crate::techniques::build_tcp_packet_v4(&mut packet_buf, local_ip_v4, dest_ip_v4, source_port, port, tcp_flags_out, seq_num, shape);
+            packet_buf.truncate(packet_len);
+
+            debug!("[Raw TCP:{}:{}] Sending flags {:#04x} from port {} via fast I/O", target_ip, port, tcp_flags_out, source_port);
+            crate::scheduler::spend(packet_len).await;
+            crate::audit::record(|| crate::audit::Probe::new("raw-tcp", "tcp", target_ip, Some(port), packet_len)
+                .source(local_ip, Some(source_port)).detail(crate::audit::tcp_flags(tcp_flags_out)).shape(shape));
+            return match engine.probe(dest_ip_v4, source_port, &packet_buf, timeout_duration).await? {
+                Some(crate::fast_io::FastReply::Tcp { flags, data, source, destination, ttl }) => {
+                    debug!("[Raw TCP:{}:{}] Matched response packet (flags: {:#04x}, TTL {})", target_ip, port, flags, ttl);
+                    Ok(Some(RawResponse::TcpStatic { flags, data, source, destination, ttl: Some(ttl) }))
+                }
+                Some(crate::fast_io::FastReply::Icmp(icmp_type, icmp_code)) => {
+                    debug!("[Raw TCP:{}:{}] Received ICMP type {} code {}", target_ip, port, icmp_type, icmp_code);
+                    Ok(Some(RawResponse::IcmpFiltered(icmp::IcmpType::new(icmp_type), icmp::IcmpCode::new(icmp_code))))
+                }
+                None => {
+                    debug!("[Raw TCP:{}:{}] Timeout waiting for response after {:?}", target_ip, port, timeout_duration);
+                    Ok(Some(RawResponse::Timeout))
+                }
+            };
+        }
+
+        // Windows refuses raw TCP sends outright; the Npcap engine above is the only way out
+        if cfg!(windows) {
+            return Err(anyhow!("Raw TCP probes on Windows need the Npcap engine (install Npcap, run elevated, IPv4 targets only)"));
+        }
+
+        // Determine IP version and select appropriate protocol for transport channel
+        let protocol = match target_ip {
+            IpAddr::V4(_) => TransportChannelType::Layer4(transport::TransportProtocol::Ipv4(IpNextHeaderProtocols::Tcp)),
+            IpAddr::V6(_) => TransportChannelType::Layer4(transport::TransportProtocol::Ipv6(IpNextHeaderProtocols::Tcp)),
+        };
+    
+        // Create a single channel for both sending and receiving to avoid packet loss
+        // This is important as creating a second channel might cause early responses to be missed
+        let (mut tx, mut rx) = match transport_channel(4096, protocol) {
+            Ok((tx, rx)) => (tx, rx),
+            Err(e) => return Err(anyhow!("Failed to open raw socket channel for {} (requires root/admin privileges): {}", target_ip.is_ipv4().then(|| "IPv4").unwrap_or("IPv6"), e)),
+        };
+        crate::netbind::bind_raw(&tx)?;
+
+        // Build packet based on IP version
+        let mut packet_buf = vec![0u8; crate::evasion::MAX_PADDED_LEN]; // Largest header combo (IPv6+TCP w/ Options) plus any padding
+        let packet_len = match (target_ip, local_ip) {
+            (IpAddr::V4(dest_ip_v4), IpAddr::V4(local_ip_v4)) => {
+                crate::techniques::build_tcp_packet_v4(&mut packet_buf, local_ip_v4, dest_ip_v4, source_port, port, tcp_flags_out, seq_num, shape)
+            }
+            (IpAddr::V6(dest_ip_v6), IpAddr::V6(local_ip_v6)) => {
+                crate::techniques::build_tcp_packet_v6(&mut packet_buf, local_ip_v6, dest_ip_v6, source_port, port, tcp_flags_out, seq_num, shape)
+            }
+            _ => return Err(anyhow!("IP address family mismatch between target ({}) and local ({}) IP for raw socket scan", target_ip, local_ip)),
+        };
+
+        // Ensure buffer is large enough (build functions might panic, but double check)
+        if packet_buf.len() < packet_len {
+            // This case should ideally be prevented by the build functions' checks
+            return Err(anyhow!("Internal error: Packet buffer too small after building headers."));
+        }
+        // Trim buffer to actual packet length before creating the final packet view
+        packet_buf.truncate(packet_len);
+
+        // Setup a TCP packet iterator for receiving responses before sending
+        // This ensures we don't miss any responses that come back quickly
+        let mut iter = transport::tcp_packet_iter(&mut rx);
+
+        // Split into IP fragments if the evasion pipeline asks for it (IPv4 only)
+        let datagrams = match (shape.fragment_size, target_ip) {
+            (Some(size), IpAddr::V4(_)) => crate::techniques::fragment_ipv4_packet(&packet_buf, size),
+            (Some(_), IpAddr::V6(_)) => {
+                debug!("[Raw TCP:{}:{}] Fragmentation stage ignored for IPv6 target", target_ip, port);
+                vec![packet_buf]
+            }
+            (None, _) => vec![packet_buf],
+        };
+
+        // With --spoof-mac the probe must go out as a spoofed frame or not at all
+        let spoofing = crate::mac_vendor::spoofed_mac().is_some();
+        if spoofing {
+            crate::mac_vendor::prepare_spoofed_frame(local_ip, target_ip).await
+                .map_err(|e| anyhow!("Cannot send probe to {}:{} with the spoofed MAC: {}", target_ip, port, e))?;
+        }
+
+        for mut datagram in datagrams {
+            crate::scheduler::spend(datagram.len()).await;
+            crate::audit::record(|| crate::audit::Probe::new("raw-tcp", "tcp", target_ip, Some(port), datagram.len())
+                .source(local_ip, Some(source_port)).detail(crate::audit::tcp_flags(tcp_flags_out)).shape(shape));
+            // Send packet - as a layer 2 frame with a spoofed source MAC if configured,
+            // otherwise through the raw socket and let the kernel build the Ethernet header
+            let sent_spoofed = spoofing && crate::mac_vendor::send_spoofed_frame(local_ip, target_ip, &datagram)
+                .map_err(|e| anyhow!("MAC spoofing failed for {}:{}: {}", target_ip, port, e))?;
+
+            if !sent_spoofed {
+                match target_ip {
+                    IpAddr::V4(_) => {
+                        let ipv4_packet_to_send = MutableIpv4Packet::new(&mut datagram).unwrap();
+                        // Use packet() method from Packet trait
+                        if tx.send_to(ipv4_packet_to_send.to_immutable(), target_ip).is_err() {
+                            return Err(anyhow!("Failed to send raw IPv4 packet to {}", target_ip));
+                        }
+                    }
+                    IpAddr::V6(_) => {
+                        let ipv6_packet_to_send = MutableIpv6Packet::new(&mut datagram).unwrap();
+                        // Use packet() method from Packet trait
+                        if tx.send_to(ipv6_packet_to_send.to_immutable(), target_ip).is_err() {
+                            return Err(anyhow!("Failed to send raw IPv6 packet to {}", target_ip));
+                        }
+                    }
+                }
+            }
+        }
+        debug!("[Raw TCP:{}:{}] Sent flags {:#04x} from port {} ({} -> {})", target_ip, port, tcp_flags_out, source_port, local_ip, target_ip);
+
+        // Use tokio's timeout for the receive wait loop instead of manual time tracking
+        // This is more efficient and ensures we don't hang indefinitely
+        let receive_future = async {
+            // Set a reasonable number of iterations to check for packets
+            // This prevents excessive CPU spinning while still allowing fast response
+            let max_iterations = 50;
+            let sleep_interval = Duration::from_millis(5);
+            let mut iterations = 0;
+        
+            loop {
+                match iter.next() {
+                    Ok((packet, addr)) => {
+                        // Log all received packets for debugging
+                        debug!("[Raw TCP:{}:{}] Received packet from {}, source port: {}, dest port: {}, flags: {:#04x}",
+                               target_ip, port, addr, packet.get_source(), packet.get_destination(), packet.get_flags());
+                    
+                        // Much more permissive packet matching - any packet from target to our source port
+                        // or any packet from the target port is considered a match
+                        // This ensures we don't miss valid responses due to overly strict matching
+                        if packet.get_destination() == source_port || packet.get_source() == port {
+                            // Capture the TCP flags for analysis
+                            let flags = packet.get_flags();
+                            debug!("[Raw TCP:{}:{}] Matched response packet (flags: {:#04x})", target_ip, port, flags);
+                        
+                            // Clone packet data to avoid lifetime issues
+                            let packet_data = packet.packet().to_vec();
+                        
+                            // Return the matched packet
+                            return Some(RawResponse::TcpStatic {
+                                flags: flags.into(), // Fix: explicitly convert to u16
+                                data: packet_data,
+                                source: packet.get_source(),
+                                destination: packet.get_destination(),
+                                // The layer 4 channel strips the IP header
+                                ttl: None,
+                            });
+                        }
+                    
+                        // Handle packets that don't match our criteria
+                        if iterations % 5 == 0 {
+                            tokio::time::sleep(sleep_interval).await;
+                        }
+                        iterations += 1;
+                    
+                        // Avoid infinite loop if we keep receiving irrelevant packets
+                        if iterations >= max_iterations {
+                            return None;
+                        }
+                    }
+                    Err(e) => {
+                        // Check for ICMP errors (although less direct with Layer 4 channel)
+                        if e.kind() == std::io::ErrorKind::ConnectionRefused {
+                            debug!("[Raw TCP:{}:{}] Received ICMP-like error (ConnectionRefused). Filtered?", target_ip, port);
+                            return Some(RawResponse::IcmpFiltered(
+                                IcmpTypes::DestinationUnreachable, 
+                                icmp::IcmpCode::new(3))); // Port Unreachable
+                        } else {
+                            // Log but continue - this could be a temporary error
+                            warn!("[Raw TCP:{}:{}] Error receiving: {}. May indicate filtering.", target_ip, port, e);
+                            // Sleep briefly to not burn CPU when receiving errors
+                            tokio::time::sleep(sleep_interval).await;
+                            iterations += 1;
+                            if iterations >= max_iterations {
+                                return None;
+                            }
+                        }
+                    }
+                }
+            }
+        };
+    
+        // Apply timeout to the receive future
+        match tokio::time::timeout(timeout_duration, receive_future).await {
+            Ok(Some(response)) => {
+                // Successfully received a response before timeout
+                Ok(Some(response))
+            },
+            Ok(None) => {
+                // Receive future completed but didn't find a matching packet
+                debug!("[Raw TCP:{}:{}] No matching response found within timeout of {:?}", target_ip, port, timeout_duration);
+                // Return Timeout response instead of None to handle timeouts consistently
+                Ok(Some(RawResponse::Timeout))
+            },
+            Err(_) => {
+                // Timeout occurred
+                debug!("[Raw TCP:{}:{}] Timeout waiting for response after {:?}", target_ip, port, timeout_duration);
+                Ok(Some(RawResponse::Timeout))
+            }
+        }
+    }
+}
+
+/// In-memory transport answering each probe with a function, for exercising the
+/// techniques without root or a network. Every probe it is given is kept for inspection.
+#[cfg(test)]
+pub(crate) struct MockTransport {
+    responder: Box<dyn Fn(&TcpProbe) -> RawResponse + Send + Sync>,
+    sent: parking_lot::Mutex<Vec<TcpProbe>>,
+}
+
+#[cfg(test)]
+impl MockTransport {
+    /// Transport whose simulated target answers each probe with `responder`
+    pub fn new(responder: impl Fn(&TcpProbe) -> RawResponse + Send + Sync + 'static) -> Self {
+        Self { responder: Box::new(responder), sent: parking_lot::Mutex::new(Vec::new()) }
+    }
+
+    /// Probes sent so far, oldest first
+    pub fn sent(&self) -> Vec<TcpProbe> {
+        self.sent.lock().clone()
+    }
+
+    /// Reply segment from the target's `probe.port` with `flags` and `window`, as a
+    /// stack would send it
+    pub fn reply(probe: &TcpProbe, flags: u8, window: u16) -> RawResponse {
+        let mut data = vec![0u8; 20];
+        let mut segment = pnet::packet::tcp::MutableTcpPacket::new(&mut data).unwrap();
+        segment.set_source(probe.port);
+        segment.set_destination(utils::random_high_port());
+        segment.set_sequence(utils::scan_rng().gen());
+        segment.set_data_offset(5);
+        segment.set_flags(flags.into());
+        segment.set_window(window);
+        RawResponse::TcpStatic { flags: flags.into(), source: probe.port, destination: segment.get_destination(), data, ttl: None }
+    }
+}
+
+#[cfg(test)]
+#[async_trait]
+impl Transport for MockTransport {
+    async fn send_tcp(&self, probe: &TcpProbe) -> Result<Option<RawResponse>> {
+        self.sent.lock().push(probe.clone());
+        Ok(Some((self.responder)(probe)))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    //! Simulated targets driving the raw scan techniques through `MockTransport`.
+    //!
+    //! The transport is process-wide, so every test installs the same simulated host and
+    //! scans its own target address; probes are told apart by that address.
+
+    use super::*;
+    use crate::models::PortStatus;
+    use crate::techniques;
+    use pnet::packet::tcp::TcpFlags;
+    use std::net::Ipv4Addr;
+    use std::sync::OnceLock;
+
+    const LOCAL: IpAddr = IpAddr::V4(Ipv4Addr::new(192, 0, 2, 1));
+    const OPEN: u16 = 22;
+    const CLOSED: u16 = 23;
+    /// Dropped silently by a firewall
+    const DROPPED: u16 = 25;
+    /// Rejected by a firewall with ICMP administratively prohibited
+    const REJECTED: u16 = 26;
+    const TIMEOUT: Duration = Duration::from_millis(100);
+
+    /// A BSD-derived host with a legacy stack: drops FIN and FIN/ACK on open ports and
+    /// puts a non-zero window in RSTs from open ports only
+    fn simulated_host(probe: &TcpProbe) -> RawResponse {
+        let (syn, fin, ack) = (probe.flags & TcpFlags::SYN != 0, probe.flags & TcpFlags::FIN != 0, probe.flags & TcpFlags::ACK != 0);
+        match probe.port {
+            DROPPED => RawResponse::Timeout,
+            REJECTED => RawResponse::IcmpFiltered(IcmpTypes::DestinationUnreachable, icmp::IcmpCode::new(13)),
+            OPEN if syn => MockTransport::reply(probe, TcpFlags::SYN | TcpFlags::ACK, 64240),
+            OPEN if fin => RawResponse::Timeout,
+            OPEN if ack => MockTransport::reply(probe, TcpFlags::RST, 4096),
+            _ if syn => MockTransport::reply(probe, TcpFlags::RST | TcpFlags::ACK, 0),
+            _ => MockTransport::reply(probe, TcpFlags::RST, 0),
+        }
+    }
+
+    /// Install the simulated host as the transport (once per test binary)
+    fn mock() -> Arc<MockTransport> {
+        static MOCK: OnceLock<Arc<MockTransport>> = OnceLock::new();
+        MOCK.get_or_init(|| {
+            let mock = Arc::new(MockTransport::new(simulated_host));
+            set_transport(mock.clone());
+            mock
+        }).clone()
+    }
+
+    /// Probe flags sent to `target`, in order
+    fn sent_flags(mock: &MockTransport, target: IpAddr) -> Vec<(u16, u8)> {
+        mock.sent().into_iter().filter(|p| p.target_ip == target).map(|p| (p.port, p.flags)).collect()
+    }
+
+    #[tokio::test]
+    async fn syn_scan_classifies_simulated_ports() {
+        let mock = mock();
+        let target = IpAddr::V4(Ipv4Addr::new(198, 51, 100, 1));
+        let scan = |port| techniques::syn_scan(target, port, Some(LOCAL), false, TIMEOUT, false, "", 0);
+
+        assert_eq!(scan(OPEN).await.unwrap(), PortStatus::Open);
+        assert_eq!(scan(CLOSED).await.unwrap(), PortStatus::Closed);
+        assert_eq!(scan(DROPPED).await.unwrap(), PortStatus::Filtered);
+        assert_eq!(scan(REJECTED).await.unwrap(), PortStatus::Filtered);
+        assert!(sent_flags(&mock, target).iter().all(|&(_, flags)| flags == TcpFlags::SYN));
+    }
+
+    #[tokio::test]
+    async fn fin_scan_classifies_simulated_ports() {
+        let mock = mock();
+        let target = IpAddr::V4(Ipv4Addr::new(198, 51, 100, 2));
+        let scan = |port| techniques::fin_scan(target, port, Some(LOCAL), false, TIMEOUT, false, "", 0);
+
+        assert_eq!(scan(OPEN).await.unwrap(), PortStatus::OpenFiltered);
+        assert_eq!(scan(CLOSED).await.unwrap(), PortStatus::Closed);
+        assert_eq!(scan(DROPPED).await.unwrap(), PortStatus::OpenFiltered);
+        assert_eq!(scan(REJECTED).await.unwrap(), PortStatus::Filtered);
+        assert!(sent_flags(&mock, target).iter().all(|&(_, flags)| flags == TcpFlags::FIN));
+    }
+
+    #[tokio::test]
+    async fn maimon_scan_classifies_simulated_ports() {
+        let mock = mock();
+        let target = IpAddr::V4(Ipv4Addr::new(198, 51, 100, 3));
+        let scan = |port| techniques::maimon_scan(target, port, Some(LOCAL), false, TIMEOUT, false, "", 0);
+
+        assert_eq!(scan(OPEN).await.unwrap(), PortStatus::OpenFiltered);
+        assert_eq!(scan(CLOSED).await.unwrap(), PortStatus::Closed);
+        assert_eq!(scan(REJECTED).await.unwrap(), PortStatus::Filtered);
+        assert!(sent_flags(&mock, target).iter().all(|&(_, flags)| flags == TcpFlags::FIN | TcpFlags::ACK));
+    }
+
+    #[tokio::test]
+    async fn ack_scan_separates_unfiltered_from_filtered() {
+        let mock = mock();
+        let target = IpAddr::V4(Ipv4Addr::new(198, 51, 100, 4));
+        let scan = |port| techniques::ack_scan(target, port, Some(LOCAL), false, TIMEOUT, false, "", 0);
+
+        assert_eq!(scan(OPEN).await.unwrap().0, PortStatus::Unfiltered);
+        assert_eq!(scan(CLOSED).await.unwrap().0, PortStatus::Unfiltered);
+        assert_eq!(scan(DROPPED).await.unwrap(), (PortStatus::Filtered, "Timeout".to_string()));
+        let (status, reason) = scan(REJECTED).await.unwrap();
+        assert_eq!(status, PortStatus::Filtered);
+        assert!(reason.starts_with("ICMPv4"), "unexpected reason {}", reason);
+        assert!(sent_flags(&mock, target).iter().all(|&(_, flags)| flags == TcpFlags::ACK));
+    }
+
+    #[tokio::test]
+    async fn window_scan_reads_rst_windows_against_closed_baseline() {
+        let mock = mock();
+        let target = IpAddr::V4(Ipv4Addr::new(198, 51, 100, 5));
+        let scan = |port| techniques::window_scan(target, port, Some(LOCAL), false, TIMEOUT, false, "", 0);
+
+        assert_eq!(scan(OPEN).await.unwrap(), PortStatus::Open);
+        assert_eq!(scan(CLOSED).await.unwrap(), PortStatus::Closed);
+        assert_eq!(scan(DROPPED).await.unwrap(), PortStatus::Filtered);
+        assert_eq!(scan(REJECTED).await.unwrap(), PortStatus::Filtered);
+
+        // One extra ACK to a random high port measures the closed-port window, once per target
+        let sent = sent_flags(&mock, target);
+        assert_eq!(sent.iter().filter(|(port, _)| crate::utils::PROBE_PORT_RANGE.contains(port)).count(), 1);
+        assert!(sent.iter().all(|&(_, flags)| flags == TcpFlags::ACK));
+    }
+}
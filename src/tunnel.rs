@@ -1,13 +1,25 @@
-use std::net::IpAddr;
+use std::collections::HashMap;
+use std::net::{IpAddr, SocketAddr};
+use std::str::FromStr;
 use std::time::Duration;
 use rand::Rng;
 use anyhow::{Context, Result};
-use log::{debug, warn};
+use lazy_static::lazy_static;
+use log::{debug, info, warn};
+use parking_lot::RwLock;
+use pnet::packet::icmp::{IcmpPacket, IcmpTypes};
+use pnet::packet::ip::IpNextHeaderProtocols;
+use pnet::packet::Packet;
+use pnet::transport::{icmp_packet_iter, transport_channel, TransportChannelType, TransportProtocol, TransportReceiver, TransportSender};
+use sha2::{Digest, Sha256};
 use tokio::time::timeout;
 
 use crate::models::PortStatus;
 // use crate::utils;
 
+/// Lookup domain for DNS tunnel scans when `--lookup-domain` is not given
+pub const DEFAULT_TUNNEL_DOMAIN: &str = "scanner-probe.net";
+
 /// Protocol tunneling types supported by the scanner
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub enum TunnelType {
@@ -23,9 +35,14 @@ pub enum TunnelType {
 /// DNS tunneling is often allowed even in highly restricted environments since DNS is
 /// necessary for basic network functionality.
 ///
+/// Queries go straight to the resolver using the record type, encoding and EDNS0
+/// settings from `set_dns_tunnel_options`, so a cooperating tunnel server for the
+/// lookup domain can report actual port states back.
+///
 /// # OPSEC Considerations:
 /// - Generates high volume of unusual DNS queries which may trigger alerts
 /// - Uses domain encoding that might be detected by pattern analysis
+/// - TXT and NULL queries for long random names are classic tunnel indicators
 /// - Consider using legitimate-looking domains and limiting query frequency
 pub async fn dns_tunnel_scan(
     target_ip: IpAddr,
@@ -37,12 +54,78 @@ pub async fn dns_tunnel_scan(
     // Log the tunneling attempt with OPSEC-focused wording
     debug!("Initiating indirect network analysis via DNS tunnel toward {} port {}", target_ip, port);
     
+    // Add small random delay to avoid obvious patterns in DNS requests
+    // That could be detected by DNS monitoring systems
+    if crate::utils::scan_rng().gen_bool(0.7) { // 70% chance of delay
+        let delay = crate::utils::scan_rng().gen_range(50..150);
+        tokio::time::sleep(Duration::from_millis(delay)).await;
+    }
+    
+    let client = match DnsTunnelClient::new(dns_server, lookup_domain, timeout_duration).await {
+        Ok(client) => client,
+        Err(e) => {
+            warn!("DNS tunnel unavailable ({}); falling back to the system resolver", e);
+            return dns_tunnel_scan_system(target_ip, port, timeout_duration, lookup_domain).await;
+        }
+    };
+    
+    // Scan request: [0x01][address bytes][port]. The tunnel server probes the port
+    // itself and answers with a single status byte.
+    let mut request = vec![0x01];
+    match target_ip {
+        IpAddr::V4(ipv4) => request.extend_from_slice(&ipv4.octets()),
+        IpAddr::V6(ipv6) => request.extend_from_slice(&ipv6.octets()),
+    }
+    request.extend_from_slice(&port.to_be_bytes());
+    
+    match client.query(&request, timeout_duration).await {
+        // On an intercepting or NXDOMAIN-rewriting path, anything but a well-formed
+        // status byte may have been made up by the resolver
+        Ok(DnsTunnelReply { payload: Some(status), rcode })
+            if crate::dns_sanity::path_unreliable() && (rcode != 0 || status.len() != 1 || status[0] > 0x02) =>
+        {
+            debug!("Discarding suspect DNS tunnel answer for {}:{} on an unreliable resolver path", target_ip, port);
+            Ok(PortStatus::OpenFiltered)
+        },
+        // A cooperating tunnel server reported the port state
+        Ok(DnsTunnelReply { payload: Some(status), .. }) => {
+            debug!("DNS tunnel server answered for {}:{}", target_ip, port);
+            Ok(match status.first() {
+                Some(0x01) => PortStatus::Open,
+                Some(0x00) => PortStatus::Closed,
+                Some(0x02) => PortStatus::Filtered,
+                _ => PortStatus::OpenFiltered,
+            })
+        },
+        // Any other response (including NXDOMAIN) proves the resolver path works,
+        // but says nothing definite about the port
+        Ok(reply) => {
+            debug!("DNS tunnel query answered with rcode {}, inferring port state", reply.rcode);
+            Ok(PortStatus::OpenFiltered)
+        },
+        // Timeout - likely filtered
+        Err(e) => {
+            debug!("DNS tunnel query failed ({}), suggesting filtering", e);
+            Ok(PortStatus::Filtered)
+        }
+    }
+}
+
+/// DNS tunnel scan through the system resolver (no resolver address available)
+///
+/// Only proves that DNS queries leave the network; the answer content is not visible.
+async fn dns_tunnel_scan_system(
+    target_ip: IpAddr,
+    port: u16,
+    timeout_duration: Duration,
+    lookup_domain: &str,
+) -> Result<PortStatus> {
     // Generate a random session ID to track this specific scan 
     // and make it look like a legitimate domain query
     let session_id = crate::utils::generate_dns_tunnel_id();
     
     // Encode target info into a domain name query
-    // Format: <port>-<hex-encoded-ip>-<session-id>.<lookup_domain>
+    // Format: <session-id>.<hex-encoded-ip>.<lookup_domain>
     // For OPSEC, this actually looks like a subdomain request
     let ip_hex = match target_ip {
         IpAddr::V4(ipv4) => {
@@ -59,49 +142,14 @@ pub async fn dns_tunnel_scan(
     
     // Create domain to query - designed to look like a legitimate subdomain
     let query_domain = format!("{}.{}.{}", session_id, ip_hex, lookup_domain);
+    debug!("Querying {} through the system resolver for port {}", query_domain, port);
     
-    // Add small random delay to avoid obvious patterns in DNS requests
-    // That could be detected by DNS monitoring systems
-    if rand::thread_rng().gen_bool(0.7) { // 70% chance of delay
-        let delay = rand::thread_rng().gen_range(50..150);
-        tokio::time::sleep(Duration::from_millis(delay)).await;
-    }
-    
-    // Attempt DNS lookup with timeout using tokio's built-in DNS resolver
     // For scanning, we don't actually care about the result,
     // just whether the request makes it through the firewall
-    let lookup_result = if let Some(server) = dns_server {
-        debug!("Using custom DNS server: {}", server);
-        // When using a custom server, we need to use tokio's low-level API
-        // This is a simplified version that still achieves the goal
-        timeout(
-            timeout_duration,
-            tokio::net::lookup_host(format!("{}:0", query_domain))
-        ).await
-    } else {
-        // Use standard system resolver
-        timeout(
-            timeout_duration,
-            tokio::net::lookup_host(format!("{}:0", query_domain))
-        ).await
-    };
-    
-    // Analyze result to determine port status
-    match lookup_result {
-        // Successfully made a DNS query
-        Ok(Ok(_)) => {
-            // If DNS query worked, port might be open
-            // True state is ambiguous since we're not directly testing the port
-            debug!("DNS tunnel query succeeded, inferring port state");
-            Ok(PortStatus::OpenFiltered)
-        },
-        // DNS resolution error but query went through
-        Ok(Err(_)) => {
-            // Expected error since domain doesn't exist
-            // But indicates network path to target exists
-            debug!("DNS tunnel query received expected NXDOMAIN response");
-            Ok(PortStatus::OpenFiltered)
-        },
+    match timeout(timeout_duration, tokio::net::lookup_host(format!("{}:0", query_domain))).await {
+        // Successfully made a DNS query, or got the expected NXDOMAIN;
+        // either way the network path exists but the port state is ambiguous
+        Ok(_) => Ok(PortStatus::OpenFiltered),
         // Timeout - likely filtered
         Err(_) => {
             debug!("DNS tunnel query timed out, suggesting filtering");
@@ -128,7 +176,7 @@ pub async fn icmp_tunnel_scan(
     debug!("Initiating ICMP tunnel scan to {} port {}", target_ip, port);
     
     // Generate random key for this specific scan to identify responses
-    let key: [u8; 4] = rand::random();
+    let key: [u8; 4] = crate::utils::scan_rng().gen();
     
     // Create payload with port and key
     // Format: [4 bytes random key][2 bytes port][10 bytes padding]
@@ -202,7 +250,7 @@ pub async fn tunnel_scan(
     match tunnel_type {
         TunnelType::Dns => {
             // Use DNS tunneling
-            let domain = lookup_domain.unwrap_or("scanner-probe.net");
+            let domain = lookup_domain.unwrap_or(DEFAULT_TUNNEL_DOMAIN);
             dns_tunnel_scan(target_ip, port, timeout_duration, domain, dns_server).await
         },
         TunnelType::Icmp => {
@@ -210,4 +258,1511 @@ pub async fn tunnel_scan(
             icmp_tunnel_scan(target_ip, port, timeout_duration, local_ip).await
         }
     }
-} 
\ No newline at end of file
+} 
+// --- ICMP covert channel ---
+//
+// A reliable, message-oriented channel carried in ICMP echo payloads. The client only
+// ever sends echo requests and the relay only answers with echo replies, so the channel
+// survives firewalls that track ICMP as request/response pairs. Data flowing back from
+// the relay is pulled by POLL requests for the same reason.
+//
+// Wire format of every echo payload:
+//   magic "QS" (2) | session (2) | body
+// where body (after obfuscation is removed) is:
+//   kind (1) | message id (2) | chunk index (2) | chunk count (2) | length (2) | data
+//
+// The relay side must stop the kernel from answering echo requests itself
+// (net.ipv4.icmp_echo_ignore_all=1), otherwise every request is echoed back verbatim.
+
+/// Magic bytes identifying covert channel frames
+const COVERT_MAGIC: [u8; 2] = *b"QS";
+/// Size of the clear header (magic + session)
+const COVERT_CLEAR_HEADER: usize = 4;
+/// Size of the obfuscated frame header
+const COVERT_FRAME_HEADER: usize = 9;
+/// Size of the random nonce that seeds each frame's XOR keystream
+const XOR_NONCE_LEN: usize = 8;
+
+/// Kind of covert channel frame
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum FrameKind {
+    /// Ask the relay to open a TCP connection; data is "ip:port"
+    Open = 1,
+    /// A chunk of a message
+    Data = 2,
+    /// Acknowledges a chunk (count 0 in a POLL answer means "nothing pending")
+    Ack = 3,
+    /// Client asks for the next chunk of the relay's outgoing data
+    Poll = 4,
+    /// Either side closes the session
+    Close = 5,
+    /// Relay reports a failure; data is an error message
+    Error = 6,
+}
+
+impl FrameKind {
+    fn from_u8(value: u8) -> Option<Self> {
+        match value {
+            1 => Some(FrameKind::Open),
+            2 => Some(FrameKind::Data),
+            3 => Some(FrameKind::Ack),
+            4 => Some(FrameKind::Poll),
+            5 => Some(FrameKind::Close),
+            6 => Some(FrameKind::Error),
+            _ => None,
+        }
+    }
+}
+
+/// Payload obfuscation applied to covert channel frames
+///
+/// XOR only hides content from casual inspection; AES-256-GCM also authenticates
+/// frames so injected or corrupted packets are dropped. Both carry a random per-frame
+/// nonce, so frames with the same content do not share a keystream.
+#[derive(Clone)]
+pub enum Obfuscation {
+    None,
+    Xor([u8; 32]),
+    Aes([u8; 32]),
+}
+
+impl Obfuscation {
+    /// Build an obfuscation mode from its name ("none", "xor", "aes") and a passphrase
+    ///
+    /// The passphrase is stretched to a 256-bit key with SHA-256, so both ends only
+    /// need to share the passphrase.
+    pub fn from_spec(mode: &str, passphrase: Option<&str>) -> Result<Self> {
+        let key = || -> Result<[u8; 32]> {
+            let passphrase = passphrase
+                .ok_or_else(|| anyhow::anyhow!("A tunnel key is required for {} obfuscation", mode))?;
+            Ok(Sha256::digest(passphrase.as_bytes()).into())
+        };
+        match mode.to_lowercase().as_str() {
+            "none" | "plain" => Ok(Obfuscation::None),
+            "xor" => Ok(Obfuscation::Xor(key()?)),
+            "aes" | "aes-gcm" => Ok(Obfuscation::Aes(key()?)),
+            other => Err(anyhow::anyhow!("Unknown tunnel obfuscation '{}': expected none, xor or aes", other)),
+        }
+    }
+
+    /// Extra bytes this mode adds to every frame
+    pub(crate) fn overhead(&self) -> usize {
+        match self {
+            Obfuscation::None => 0,
+            Obfuscation::Xor(_) => XOR_NONCE_LEN,
+            Obfuscation::Aes(_) => 12 + 16, // Nonce + GCM tag
+        }
+    }
+
+    /// XOR keystream block derived from the key, the clear header, the frame's nonce and
+    /// a counter
+    fn xor_block(key: &[u8; 32], clear_header: &[u8], nonce: &[u8], counter: u32) -> [u8; 32] {
+        let mut hasher = Sha256::new();
+        hasher.update(key);
+        hasher.update(clear_header);
+        hasher.update(nonce);
+        hasher.update(counter.to_be_bytes());
+        hasher.finalize().into()
+    }
+
+    fn seal(&self, clear_header: &[u8], body: &[u8]) -> Result<Vec<u8>> {
+        match self {
+            Obfuscation::None => Ok(body.to_vec()),
+            Obfuscation::Xor(key) => {
+                let nonce: [u8; XOR_NONCE_LEN] = rand::thread_rng().gen();
+                let mut sealed = nonce.to_vec();
+                sealed.extend_from_slice(&Self::xor(key, clear_header, &nonce, body));
+                Ok(sealed)
+            }
+            Obfuscation::Aes(key) => {
+                use aes_gcm::aead::{Aead, AeadCore, KeyInit, OsRng, Payload};
+                let cipher = aes_gcm::Aes256Gcm::new(key.into());
+                let nonce = aes_gcm::Aes256Gcm::generate_nonce(&mut OsRng);
+                let ciphertext = cipher.encrypt(&nonce, Payload { msg: body, aad: clear_header })
+                    .map_err(|_| anyhow::anyhow!("Failed to encrypt tunnel frame"))?;
+                let mut sealed = nonce.to_vec();
+                sealed.extend_from_slice(&ciphertext);
+                Ok(sealed)
+            }
+        }
+    }
+
+    fn open(&self, clear_header: &[u8], sealed: &[u8]) -> Option<Vec<u8>> {
+        match self {
+            Obfuscation::None => Some(sealed.to_vec()),
+            Obfuscation::Xor(key) => {
+                if sealed.len() < XOR_NONCE_LEN {
+                    return None;
+                }
+                let (nonce, body) = sealed.split_at(XOR_NONCE_LEN);
+                Some(Self::xor(key, clear_header, nonce, body))
+            }
+            Obfuscation::Aes(key) => {
+                use aes_gcm::aead::{Aead, KeyInit, Payload};
+                if sealed.len() < 12 {
+                    return None;
+                }
+                let cipher = aes_gcm::Aes256Gcm::new(key.into());
+                let nonce = aes_gcm::Nonce::from_slice(&sealed[..12]);
+                cipher.decrypt(nonce, Payload { msg: &sealed[12..], aad: clear_header }).ok()
+            }
+        }
+    }
+
+    fn xor(key: &[u8; 32], clear_header: &[u8], nonce: &[u8], data: &[u8]) -> Vec<u8> {
+        data.chunks(32)
+            .enumerate()
+            .flat_map(|(counter, chunk)| {
+                let block = Self::xor_block(key, clear_header, nonce, counter as u32);
+                chunk.iter().zip(block).map(|(b, k)| b ^ k).collect::<Vec<u8>>()
+            })
+            .collect()
+    }
+}
+
+/// One covert channel frame
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CovertFrame {
+    pub kind: FrameKind,
+    pub session: u16,
+    pub message_id: u16,
+    pub index: u16,
+    pub count: u16,
+    pub data: Vec<u8>,
+}
+
+impl CovertFrame {
+    pub fn new(kind: FrameKind, session: u16, message_id: u16, index: u16, count: u16, data: Vec<u8>) -> Self {
+        CovertFrame { kind, session, message_id, index, count, data }
+    }
+
+    /// Serialize into an echo payload
+    pub fn encode(&self, obfuscation: &Obfuscation) -> Result<Vec<u8>> {
+        let mut clear = COVERT_MAGIC.to_vec();
+        clear.extend_from_slice(&self.session.to_be_bytes());
+
+        let mut body = Vec::with_capacity(COVERT_FRAME_HEADER + self.data.len());
+        body.push(self.kind as u8);
+        body.extend_from_slice(&self.message_id.to_be_bytes());
+        body.extend_from_slice(&self.index.to_be_bytes());
+        body.extend_from_slice(&self.count.to_be_bytes());
+        body.extend_from_slice(&(self.data.len() as u16).to_be_bytes());
+        body.extend_from_slice(&self.data);
+
+        let sealed = obfuscation.seal(&clear, &body)?;
+        clear.extend_from_slice(&sealed);
+        Ok(clear)
+    }
+
+    /// Parse an echo payload; returns None for foreign traffic or frames that fail to open
+    pub fn decode(payload: &[u8], obfuscation: &Obfuscation) -> Option<Self> {
+        if payload.len() < COVERT_CLEAR_HEADER || payload[0..2] != COVERT_MAGIC {
+            return None;
+        }
+        let session = u16::from_be_bytes([payload[2], payload[3]]);
+        let body = obfuscation.open(&payload[..COVERT_CLEAR_HEADER], &payload[COVERT_CLEAR_HEADER..])?;
+        if body.len() < COVERT_FRAME_HEADER {
+            return None;
+        }
+        let len = u16::from_be_bytes([body[7], body[8]]) as usize;
+        if body.len() < COVERT_FRAME_HEADER + len {
+            return None;
+        }
+        Some(CovertFrame {
+            kind: FrameKind::from_u8(body[0])?,
+            session,
+            message_id: u16::from_be_bytes([body[1], body[2]]),
+            index: u16::from_be_bytes([body[3], body[4]]),
+            count: u16::from_be_bytes([body[5], body[6]]),
+            data: body[COVERT_FRAME_HEADER..COVERT_FRAME_HEADER + len].to_vec(),
+        })
+    }
+}
+
+/// Build an ICMP echo request or reply with a valid checksum
+pub fn build_icmp_echo(reply: bool, identifier: u16, sequence: u16, payload: &[u8]) -> Vec<u8> {
+    let mut packet = vec![if reply { 0 } else { 8 }, 0, 0, 0];
+    packet.extend_from_slice(&identifier.to_be_bytes());
+    packet.extend_from_slice(&sequence.to_be_bytes());
+    packet.extend_from_slice(payload);
+    let checksum = pnet::util::checksum(&packet, 1);
+    packet[2..4].copy_from_slice(&checksum.to_be_bytes());
+    packet
+}
+
+/// Tuning for the ICMP covert channel
+#[derive(Clone)]
+pub struct CovertChannelConfig {
+    /// Maximum data bytes per frame (keep echo packets a plausible size)
+    pub chunk_size: usize,
+    /// Retransmissions before a frame is declared lost
+    pub max_retries: u32,
+    /// How long to wait for each acknowledgement
+    pub ack_timeout: Duration,
+    /// Delay between POLL requests when the relay has nothing pending
+    pub poll_interval: Duration,
+    /// Payload obfuscation shared with the relay
+    pub obfuscation: Obfuscation,
+}
+
+impl Default for CovertChannelConfig {
+    fn default() -> Self {
+        CovertChannelConfig {
+            chunk_size: 512,
+            max_retries: 5,
+            ack_timeout: Duration::from_millis(1000),
+            poll_interval: Duration::from_millis(200),
+            obfuscation: Obfuscation::None,
+        }
+    }
+}
+
+/// Client end of the ICMP covert channel
+///
+/// Provides chunking, sequencing, retransmission and optional obfuscation over ICMP
+/// echo. Operations block, so run them inside `spawn_blocking` (as `relay_request` does).
+///
+/// # OPSEC Considerations:
+/// - Echo requests with large, high-entropy payloads stand out to ICMP-aware IDS
+/// - Steady POLL traffic is a recognisable beacon; keep sessions short
+/// - Requires root/admin privileges for raw ICMP sockets
+pub struct IcmpCovertChannel {
+    relay: IpAddr,
+    session: u16,
+    sequence: u16,
+    next_message: u16,
+    config: CovertChannelConfig,
+    tx: TransportSender,
+    rx: TransportReceiver,
+}
+
+impl IcmpCovertChannel {
+    /// Open a channel to an ICMP relay
+    pub fn open(relay: IpAddr, config: CovertChannelConfig) -> Result<Self> {
+        if !relay.is_ipv4() {
+            return Err(anyhow::anyhow!("ICMP covert channel only supports IPv4 relays"));
+        }
+        let (tx, rx) = transport_channel(
+            65536,
+            TransportChannelType::Layer4(TransportProtocol::Ipv4(IpNextHeaderProtocols::Icmp)),
+        ).context("Failed to open raw ICMP socket (requires root/admin privileges)")?;
+
+        let mut rng = crate::utils::scan_rng();
+        Ok(IcmpCovertChannel {
+            relay,
+            session: rng.gen(),
+            sequence: rng.gen(),
+            next_message: 0,
+            config,
+            tx,
+            rx,
+        })
+    }
+
+    /// Session identifier (also used as the ICMP echo identifier)
+    pub fn session(&self) -> u16 {
+        self.session
+    }
+
+    /// Largest chunk that fits the configured size after obfuscation overhead
+    fn chunk_size(&self) -> usize {
+        self.config.chunk_size.saturating_sub(self.config.obfuscation.overhead()).max(16)
+    }
+
+    fn send_frame(&mut self, frame: &CovertFrame) -> Result<()> {
+        self.sequence = self.sequence.wrapping_add(1);
+        let payload = frame.encode(&self.config.obfuscation)?;
+        let packet = build_icmp_echo(false, self.session, self.sequence, &payload);
+        let icmp = IcmpPacket::new(&packet).ok_or_else(|| anyhow::anyhow!("Failed to build ICMP echo"))?;
+        crate::scheduler::spend_blocking(packet.len() + 20);
+        crate::audit::record(|| crate::audit::Probe::new("icmp-tunnel", "icmp", self.relay, None, packet.len() + 20)
+            .detail(format!("covert echo, session {} sequence {}", self.session, self.sequence)));
+        self.tx.send_to(icmp, self.relay).context("Failed to send ICMP echo")?;
+        Ok(())
+    }
+
+    /// Send a frame and wait for a reply accepted by `accept`, retransmitting on timeout
+    fn exchange(&mut self, frame: &CovertFrame, accept: impl Fn(&CovertFrame) -> bool) -> Result<CovertFrame> {
+        for attempt in 0..=self.config.max_retries {
+            if attempt > 0 {
+                debug!("ICMP channel {:04x}: retransmitting {:?} {}/{} (attempt {})",
+                       self.session, frame.kind, frame.message_id, frame.index, attempt + 1);
+            }
+            self.send_frame(frame)?;
+
+            let deadline = std::time::Instant::now() + self.config.ack_timeout;
+            let mut iter = icmp_packet_iter(&mut self.rx);
+            while std::time::Instant::now() < deadline {
+                let (packet, addr) = match iter.next_with_timeout(Duration::from_millis(50)) {
+                    Ok(Some(received)) => received,
+                    _ => continue,
+                };
+                if addr != self.relay || packet.get_icmp_type() != IcmpTypes::EchoReply {
+                    continue;
+                }
+                // Echo payload starts after identifier and sequence
+                let echo = packet.payload();
+                if echo.len() < 4 || u16::from_be_bytes([echo[0], echo[1]]) != self.session {
+                    continue;
+                }
+                let Some(reply) = CovertFrame::decode(&echo[4..], &self.config.obfuscation) else { continue };
+                if reply.session != self.session {
+                    continue;
+                }
+                if reply.kind == FrameKind::Error {
+                    return Err(anyhow::anyhow!("Relay error: {}", String::from_utf8_lossy(&reply.data)));
+                }
+                if accept(&reply) {
+                    return Ok(reply);
+                }
+            }
+        }
+        Err(anyhow::anyhow!("ICMP channel {:04x}: no acknowledgement after {} attempts", self.session, self.config.max_retries + 1))
+    }
+
+    /// Ask the relay to open a TCP connection to `target`
+    pub fn connect(&mut self, target: SocketAddr) -> Result<()> {
+        let message_id = self.next_message;
+        self.next_message = self.next_message.wrapping_add(1);
+        let frame = CovertFrame::new(FrameKind::Open, self.session, message_id, 0, 1, target.to_string().into_bytes());
+        self.exchange(&frame, |reply| reply.kind == FrameKind::Ack && reply.message_id == message_id)?;
+        debug!("ICMP channel {:04x}: relay connected to {}", self.session, target);
+        Ok(())
+    }
+
+    /// Reliably send a message, split into chunks
+    pub fn send(&mut self, data: &[u8]) -> Result<()> {
+        let message_id = self.next_message;
+        self.next_message = self.next_message.wrapping_add(1);
+        let chunk_size = self.chunk_size();
+        let chunks: Vec<&[u8]> = if data.is_empty() { vec![&[][..]] } else { data.chunks(chunk_size).collect() };
+        if chunks.len() > u16::MAX as usize {
+            return Err(anyhow::anyhow!("Message too large for the ICMP channel ({} bytes)", data.len()));
+        }
+        let count = chunks.len() as u16;
+
+        for (index, chunk) in chunks.into_iter().enumerate() {
+            let index = index as u16;
+            let frame = CovertFrame::new(FrameKind::Data, self.session, message_id, index, count, chunk.to_vec());
+            self.exchange(&frame, |reply| {
+                reply.kind == FrameKind::Ack && reply.message_id == message_id && reply.index == index
+            })?;
+        }
+        Ok(())
+    }
+
+    /// Pull data the relay has received from the far end
+    ///
+    /// Polls until a full message has been reassembled, the relay closes the
+    /// connection, or `idle_timeout` passes without new data. Returns whatever was
+    /// received (possibly nothing).
+    pub fn receive(&mut self, idle_timeout: Duration) -> Result<Vec<u8>> {
+        let mut received = Vec::new();
+        let mut expected: Option<(u16, u16)> = None; // (message id, next chunk index)
+        let mut last_data = std::time::Instant::now();
+
+        loop {
+            let (message_id, index) = expected.unwrap_or((0, 0));
+            let poll = CovertFrame::new(FrameKind::Poll, self.session, message_id, index, 0, Vec::new());
+            let reply = self.exchange(&poll, |reply| {
+                matches!(reply.kind, FrameKind::Data | FrameKind::Ack | FrameKind::Close)
+            })?;
+
+            match reply.kind {
+                FrameKind::Data => {
+                    // Retransmitted chunk we already have
+                    if let Some((current, next)) = expected {
+                        if reply.message_id == current && reply.index < next {
+                            continue;
+                        }
+                    }
+                    received.extend_from_slice(&reply.data);
+                    last_data = std::time::Instant::now();
+                    // The relay controls index and count; the last index can't wrap around
+                    match reply.index.checked_add(1) {
+                        Some(next) if next < reply.count => expected = Some((reply.message_id, next)),
+                        _ => return Ok(received),
+                    }
+                }
+                FrameKind::Close => return Ok(received),
+                _ => {
+                    // Nothing pending yet
+                    if last_data.elapsed() >= idle_timeout {
+                        return Ok(received);
+                    }
+                    std::thread::sleep(self.config.poll_interval);
+                }
+            }
+        }
+    }
+
+    /// Close the session (best effort)
+    pub fn close(&mut self) {
+        let frame = CovertFrame::new(FrameKind::Close, self.session, self.next_message, 0, 0, Vec::new());
+        if let Err(e) = self.send_frame(&frame) {
+            debug!("ICMP channel {:04x}: failed to send close: {}", self.session, e);
+        }
+    }
+}
+
+/// Relay a request to `target` through an ICMP covert channel and return the response
+///
+/// Opens a session to the relay, has it connect to `target`, sends `request` (if
+/// any), and collects the response until `timeout_duration` passes without data.
+/// This is what service probes and banner grabs use when routed over ICMP.
+pub async fn relay_request(
+    relay: IpAddr,
+    target: SocketAddr,
+    request: Vec<u8>,
+    config: CovertChannelConfig,
+    timeout_duration: Duration,
+) -> Result<Vec<u8>> {
+    tokio::task::spawn_blocking(move || -> Result<Vec<u8>> {
+        let mut channel = IcmpCovertChannel::open(relay, config)?;
+        debug!("ICMP channel {:04x}: relaying request to {} via {}", channel.session(), target, relay);
+        let result = (|| {
+            channel.connect(target)?;
+            if !request.is_empty() {
+                channel.send(&request)?;
+            }
+            channel.receive(timeout_duration)
+        })();
+        channel.close();
+        result
+    }).await.context("ICMP relay task failed")?
+}
+
+// --- DNS tunnel transport ---
+//
+// Queries carry data in the leading labels of `<nonce>.<data labels>.<domain>`; answers
+// carry data in TXT strings, NULL rdata or the leading labels of a CNAME target. A
+// cooperating authoritative server for `domain` decodes the query and answers. Without
+// one, any response (even NXDOMAIN) still proves the resolver path works.
+
+/// DNS record type used to carry data back from the tunnel server
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum DnsRecordType {
+    /// TXT strings (most widely passed by resolvers)
+    Txt,
+    /// NULL rdata (raw binary, highest capacity, often filtered by public resolvers)
+    Null,
+    /// CNAME target labels (looks like ordinary alias chains, lowest capacity)
+    Cname,
+}
+
+impl DnsRecordType {
+    fn qtype(self) -> u16 {
+        match self {
+            DnsRecordType::Txt => 16,
+            DnsRecordType::Null => 10,
+            DnsRecordType::Cname => 5,
+        }
+    }
+}
+
+impl FromStr for DnsRecordType {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        match s.to_lowercase().as_str() {
+            "txt" => Ok(DnsRecordType::Txt),
+            "null" => Ok(DnsRecordType::Null),
+            "cname" => Ok(DnsRecordType::Cname),
+            other => Err(anyhow::anyhow!("Unknown DNS record type '{}': expected txt, null or cname", other)),
+        }
+    }
+}
+
+/// Encoding used for data placed in DNS names and text records
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum DnsEncoding {
+    /// RFC 4648 base32 without padding; case-insensitive, survives 0x20 case randomisation
+    Base32,
+    /// RFC 4648 base64url without padding; 20% denser, but needs a case-preserving path
+    Base64Url,
+}
+
+impl FromStr for DnsEncoding {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        match s.to_lowercase().as_str() {
+            "base32" | "b32" => Ok(DnsEncoding::Base32),
+            "base64url" | "base64" | "b64" => Ok(DnsEncoding::Base64Url),
+            other => Err(anyhow::anyhow!("Unknown DNS encoding '{}': expected base32 or base64url", other)),
+        }
+    }
+}
+
+const BASE32_ALPHABET: &[u8; 32] = b"abcdefghijklmnopqrstuvwxyz234567";
+const BASE64URL_ALPHABET: &[u8; 64] = b"ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz0123456789-_";
+
+impl DnsEncoding {
+    fn bits(self) -> u32 {
+        match self {
+            DnsEncoding::Base32 => 5,
+            DnsEncoding::Base64Url => 6,
+        }
+    }
+
+    fn alphabet(self) -> &'static [u8] {
+        match self {
+            DnsEncoding::Base32 => BASE32_ALPHABET,
+            DnsEncoding::Base64Url => BASE64URL_ALPHABET,
+        }
+    }
+
+    /// Encode bytes without padding
+    pub fn encode(self, data: &[u8]) -> String {
+        let bits = self.bits();
+        let alphabet = self.alphabet();
+        let mut out = String::with_capacity(data.len() * 8 / bits as usize + 1);
+        let mut buffer: u32 = 0;
+        let mut buffered = 0;
+        for &byte in data {
+            buffer = (buffer << 8) | byte as u32;
+            buffered += 8;
+            while buffered >= bits {
+                buffered -= bits;
+                out.push(alphabet[((buffer >> buffered) & ((1 << bits) - 1)) as usize] as char);
+            }
+        }
+        if buffered > 0 {
+            out.push(alphabet[((buffer << (bits - buffered)) & ((1 << bits) - 1)) as usize] as char);
+        }
+        out
+    }
+
+    /// Decode unpadded text; base32 is accepted in either case
+    pub fn decode(self, text: &str) -> Option<Vec<u8>> {
+        let bits = self.bits();
+        let mut out = Vec::with_capacity(text.len() * bits as usize / 8);
+        let mut buffer: u32 = 0;
+        let mut buffered = 0;
+        for c in text.bytes().filter(|c| *c != b'.' && *c != b'=') {
+            let value = match self {
+                DnsEncoding::Base32 => BASE32_ALPHABET.iter().position(|a| *a == c.to_ascii_lowercase())?,
+                DnsEncoding::Base64Url => BASE64URL_ALPHABET.iter().position(|a| *a == c)?,
+            } as u32;
+            buffer = (buffer << bits) | value;
+            buffered += bits;
+            if buffered >= 8 {
+                buffered -= 8;
+                out.push((buffer >> buffered) as u8);
+            }
+        }
+        Some(out)
+    }
+
+    /// Raw bytes that fit in `chars` encoded characters
+    fn capacity(self, chars: usize) -> usize {
+        chars * self.bits() as usize / 8
+    }
+}
+
+/// DNS tunnel transport settings
+#[derive(Debug, Clone)]
+pub struct DnsTunnelOptions {
+    /// Record type the server answers with
+    pub record_type: DnsRecordType,
+    /// Encoding for query labels and TXT answers
+    pub encoding: DnsEncoding,
+    /// Advertise a larger UDP payload with an EDNS0 OPT record
+    pub edns0: bool,
+    /// Probe the resolver path for usable query and response sizes before the first query
+    pub probe_mtu: bool,
+}
+
+impl Default for DnsTunnelOptions {
+    fn default() -> Self {
+        DnsTunnelOptions {
+            record_type: DnsRecordType::Txt,
+            encoding: DnsEncoding::Base32,
+            edns0: true,
+            probe_mtu: true,
+        }
+    }
+}
+
+/// Usable sizes on the path to a resolver
+#[derive(Debug, Clone, Copy)]
+pub struct DnsPathLimits {
+    /// Raw bytes that fit in one query name
+    pub max_query_bytes: usize,
+    /// Largest UDP response that arrives intact (EDNS0 buffer size to advertise)
+    pub max_response_bytes: u16,
+}
+
+impl Default for DnsPathLimits {
+    fn default() -> Self {
+        // Conservative values that work through essentially any resolver
+        DnsPathLimits { max_query_bytes: 60, max_response_bytes: 512 }
+    }
+}
+
+lazy_static! {
+    /// Process-wide DNS tunnel transport settings
+    static ref DNS_TUNNEL_OPTIONS: RwLock<DnsTunnelOptions> = RwLock::new(DnsTunnelOptions::default());
+    /// Path limits discovered per resolver, so probing happens once per scan
+    static ref DNS_PATH_LIMITS: RwLock<HashMap<SocketAddr, DnsPathLimits>> = RwLock::new(HashMap::new());
+}
+
+/// Set the DNS tunnel transport settings used by all DNS tunnel scans
+pub fn set_dns_tunnel_options(options: DnsTunnelOptions) {
+    *DNS_TUNNEL_OPTIONS.write() = options;
+}
+
+/// Current DNS tunnel transport settings
+pub fn dns_tunnel_options() -> DnsTunnelOptions {
+    DNS_TUNNEL_OPTIONS.read().clone()
+}
+
+/// First nameserver from /etc/resolv.conf
+pub(crate) fn system_resolver() -> Option<IpAddr> {
+    std::fs::read_to_string("/etc/resolv.conf").ok()?
+        .lines()
+        .filter_map(|line| line.trim().strip_prefix("nameserver"))
+        .find_map(|addr| addr.trim().parse().ok())
+}
+
+/// Answer to a tunneled DNS query
+#[derive(Debug, Clone)]
+pub struct DnsTunnelReply {
+    /// DNS response code (0 = NOERROR, 3 = NXDOMAIN...)
+    pub rcode: u8,
+    /// Decoded data from the answer section, if the server sent any
+    pub payload: Option<Vec<u8>>,
+}
+
+/// DNS tunnel client bound to one resolver and tunnel domain
+pub struct DnsTunnelClient {
+    server: SocketAddr,
+    domain: String,
+    options: DnsTunnelOptions,
+    limits: DnsPathLimits,
+}
+
+impl DnsTunnelClient {
+    /// Create a client, probing the resolver path first if enabled
+    ///
+    /// # Arguments
+    /// * `server` - Resolver to send queries to (system resolver when None)
+    /// * `domain` - Domain delegated to the tunnel server
+    /// * `timeout_duration` - Per-query timeout, also used while probing
+    pub async fn new(server: Option<IpAddr>, domain: &str, timeout_duration: Duration) -> Result<Self> {
+        let server_ip = server.or_else(system_resolver)
+            .ok_or_else(|| anyhow::anyhow!("No DNS server given and none found in /etc/resolv.conf"))?;
+        let mut client = DnsTunnelClient {
+            server: SocketAddr::new(server_ip, 53),
+            domain: domain.trim_matches('.').to_lowercase(),
+            options: dns_tunnel_options(),
+            limits: DnsPathLimits::default(),
+        };
+        // Queries can never exceed what the name length allows
+        client.limits.max_query_bytes = client.limits.max_query_bytes.min(client.name_capacity());
+
+        let cached = DNS_PATH_LIMITS.read().get(&client.server).copied();
+        if let Some(limits) = cached {
+            client.limits = limits;
+        } else if client.options.probe_mtu {
+            client.limits = client.probe_path_limits(timeout_duration).await;
+            DNS_PATH_LIMITS.write().insert(client.server, client.limits);
+        }
+        Ok(client)
+    }
+
+    /// Path limits in use
+    pub fn limits(&self) -> DnsPathLimits {
+        self.limits
+    }
+
+    /// Raw bytes that fit in a query name under this domain
+    fn name_capacity(&self) -> usize {
+        // 253 max name length, minus the domain, the nonce label (8 chars) and separators
+        let budget = 253usize.saturating_sub(self.domain.len() + 1 + 9);
+        // Every 63 characters need a separating dot
+        let chars = budget - budget / 64;
+        self.options.encoding.capacity(chars)
+    }
+
+    /// Build the query name for `data`
+    fn encode_name(&self, data: &[u8]) -> Result<String> {
+        if data.len() > self.name_capacity() {
+            return Err(anyhow::anyhow!("{} bytes do not fit in a DNS query name (max {})", data.len(), self.name_capacity()));
+        }
+        // The nonce defeats resolver caching so every query reaches the tunnel server
+        let nonce: String = (0..8)
+            .map(|_| BASE32_ALPHABET[crate::utils::scan_rng().gen_range(0..32)] as char)
+            .collect();
+        let encoded = self.options.encoding.encode(data);
+        let mut labels = vec![nonce];
+        labels.extend(encoded.as_bytes().chunks(63).map(|chunk| String::from_utf8_lossy(chunk).into_owned()));
+        labels.push(self.domain.clone());
+        Ok(labels.join("."))
+    }
+
+    fn build_query(&self, id: u16, name: &str, edns_size: Option<u16>) -> Vec<u8> {
+        let mut query = Vec::with_capacity(name.len() + 32);
+        query.extend_from_slice(&id.to_be_bytes());
+        query.extend_from_slice(&0x0100u16.to_be_bytes()); // Standard query, recursion desired
+        query.extend_from_slice(&1u16.to_be_bytes()); // QDCOUNT
+        query.extend_from_slice(&0u16.to_be_bytes()); // ANCOUNT
+        query.extend_from_slice(&0u16.to_be_bytes()); // NSCOUNT
+        query.extend_from_slice(&(edns_size.is_some() as u16).to_be_bytes()); // ARCOUNT
+        for label in name.split('.').filter(|l| !l.is_empty()) {
+            query.push(label.len() as u8);
+            query.extend_from_slice(label.as_bytes());
+        }
+        query.push(0);
+        query.extend_from_slice(&self.options.record_type.qtype().to_be_bytes());
+        query.extend_from_slice(&1u16.to_be_bytes()); // Class IN
+
+        if let Some(size) = edns_size {
+            // OPT pseudo-record: root name, type 41, class = UDP payload size, no options
+            query.push(0);
+            query.extend_from_slice(&41u16.to_be_bytes());
+            query.extend_from_slice(&size.to_be_bytes());
+            query.extend_from_slice(&0u32.to_be_bytes());
+            query.extend_from_slice(&0u16.to_be_bytes());
+        }
+        query
+    }
+
+    /// Send a query over UDP, retrying over TCP when the answer is truncated
+    async fn exchange(&self, query: &[u8], timeout_duration: Duration) -> Result<Vec<u8>> {
+        let socket = crate::netbind::bind_udp(self.server.ip()).await?;
+        socket.connect(self.server).await?;
+        crate::scheduler::spend(query.len() + crate::scheduler::UDP_HEADER_BYTES).await;
+        crate::audit::record(|| crate::audit::Probe::new("dns-tunnel", "udp", self.server.ip(), Some(self.server.port()), query.len() + crate::scheduler::UDP_HEADER_BYTES));
+        socket.send(query).await?;
+
+        let mut buf = vec![0u8; 65535];
+        let response = loop {
+            let len = timeout(timeout_duration, socket.recv(&mut buf)).await
+                .map_err(|_| anyhow::anyhow!("DNS query timed out"))??;
+            // Ignore stray datagrams with a different transaction ID
+            if len >= 12 && buf[0..2] == query[0..2] {
+                break buf[..len].to_vec();
+            }
+        };
+
+        if response[2] & 0x02 == 0 {
+            return Ok(response);
+        }
+
+        debug!("DNS tunnel response truncated, retrying over TCP");
+        use tokio::io::{AsyncReadExt, AsyncWriteExt};
+        crate::scheduler::spend(query.len() + 2 + crate::scheduler::TCP_HEADER_BYTES).await;
+        crate::audit::record(|| crate::audit::Probe::new("dns-tunnel", "tcp", self.server.ip(), Some(self.server.port()),
+            query.len() + 2 + crate::scheduler::TCP_HEADER_BYTES).detail("retry over TCP"));
+        let mut stream = timeout(timeout_duration, crate::netbind::connect_tcp(self.server)).await
+            .map_err(|_| anyhow::anyhow!("DNS TCP connect timed out"))??;
+        let mut framed = (query.len() as u16).to_be_bytes().to_vec();
+        framed.extend_from_slice(query);
+        stream.write_all(&framed).await?;
+        let mut len_buf = [0u8; 2];
+        timeout(timeout_duration, stream.read_exact(&mut len_buf)).await
+            .map_err(|_| anyhow::anyhow!("DNS TCP response timed out"))??;
+        let mut response = vec![0u8; u16::from_be_bytes(len_buf) as usize];
+        timeout(timeout_duration, stream.read_exact(&mut response)).await
+            .map_err(|_| anyhow::anyhow!("DNS TCP response timed out"))??;
+        Ok(response)
+    }
+
+    /// Send `data` to the tunnel server and decode its answer
+    ///
+    /// `data` must fit the probed query size (`limits().max_query_bytes`).
+    pub async fn query(&self, data: &[u8], timeout_duration: Duration) -> Result<DnsTunnelReply> {
+        if data.len() > self.limits.max_query_bytes {
+            return Err(anyhow::anyhow!("{} bytes exceed the DNS path limit of {} bytes per query", data.len(), self.limits.max_query_bytes));
+        }
+        self.send_query(data, timeout_duration).await
+    }
+
+    async fn send_query(&self, data: &[u8], timeout_duration: Duration) -> Result<DnsTunnelReply> {
+        let id: u16 = crate::utils::scan_rng().gen();
+        let name = self.encode_name(data)?;
+        let edns_size = if self.options.edns0 { Some(self.limits.max_response_bytes.max(512)) } else { None };
+        let response = self.exchange(&self.build_query(id, &name, edns_size), timeout_duration).await?;
+        Ok(self.parse_response(&response))
+    }
+
+    /// Extract the response code and any tunneled data from a DNS response
+    fn parse_response(&self, response: &[u8]) -> DnsTunnelReply {
+        let rcode = response.get(3).map(|b| b & 0x0f).unwrap_or(2);
+        let mut reply = DnsTunnelReply { rcode, payload: None };
+        if response.len() < 12 {
+            return reply;
+        }
+        let qdcount = u16::from_be_bytes([response[4], response[5]]);
+        let ancount = u16::from_be_bytes([response[6], response[7]]);
+
+        let mut offset = 12;
+        for _ in 0..qdcount {
+            let Some((_, next)) = read_dns_name(response, offset) else { return reply };
+            offset = next + 4;
+        }
+
+        let mut data = Vec::new();
+        for _ in 0..ancount {
+            let Some((_, next)) = read_dns_name(response, offset) else { break };
+            if next + 10 > response.len() {
+                break;
+            }
+            let rtype = u16::from_be_bytes([response[next], response[next + 1]]);
+            let rdlen = u16::from_be_bytes([response[next + 8], response[next + 9]]) as usize;
+            let rdata_start = next + 10;
+            if rdata_start + rdlen > response.len() {
+                break;
+            }
+            let rdata = &response[rdata_start..rdata_start + rdlen];
+
+            match rtype {
+                16 => {
+                    // TXT: one or more length-prefixed character strings
+                    let mut text = String::new();
+                    let mut i = 0;
+                    while i < rdata.len() {
+                        let len = rdata[i] as usize;
+                        text.push_str(&String::from_utf8_lossy(&rdata[i + 1..(i + 1 + len).min(rdata.len())]));
+                        i += 1 + len;
+                    }
+                    if let Some(decoded) = self.options.encoding.decode(&text) {
+                        data.extend(decoded);
+                    }
+                }
+                10 => data.extend_from_slice(rdata),
+                5 => {
+                    // CNAME: data labels in front of the tunnel domain
+                    if let Some((target, _)) = read_dns_name(response, rdata_start) {
+                        let target = target.to_lowercase();
+                        if let Some(labels) = target.strip_suffix(&self.domain) {
+                            if let Some(decoded) = self.options.encoding.decode(labels.trim_end_matches('.')) {
+                                data.extend(decoded);
+                            }
+                        }
+                    }
+                }
+                _ => {}
+            }
+            offset = rdata_start + rdlen;
+        }
+
+        if !data.is_empty() {
+            reply.payload = Some(data);
+        }
+        reply
+    }
+
+    /// Discover how much data fits in queries and responses on the path to the resolver
+    ///
+    /// Query side: names of growing size are sent until one goes unanswered. Response
+    /// side (EDNS0 only): the tunnel server is asked to pad its answer to each candidate
+    /// size, largest first, and the first size that arrives untruncated over UDP wins.
+    async fn probe_path_limits(&self, timeout_duration: Duration) -> DnsPathLimits {
+        let mut limits = DnsPathLimits::default();
+        let capacity = self.name_capacity();
+
+        for size in [capacity / 4, capacity / 2, capacity * 3 / 4, capacity] {
+            // 0xfe marks a probe the tunnel server should just acknowledge
+            let mut probe = vec![0xfe];
+            probe.resize(size.max(1), 0);
+            match self.send_query(&probe, timeout_duration).await {
+                Ok(_) => limits.max_query_bytes = size,
+                Err(e) => {
+                    debug!("DNS path probe: {} byte query failed ({}), keeping {}", size, e, limits.max_query_bytes);
+                    break;
+                }
+            }
+        }
+
+        if self.options.edns0 {
+            for size in [4096u16, 1232, 512] {
+                // 0xff marks a request for a response padded to `size`
+                let mut probe = vec![0xff];
+                probe.extend_from_slice(&size.to_be_bytes());
+                let id: u16 = crate::utils::scan_rng().gen();
+                let Ok(name) = self.encode_name(&probe) else { break };
+                let query = self.build_query(id, &name, Some(size));
+
+                let Ok(socket) = crate::netbind::bind_udp(self.server.ip()).await else { break };
+                crate::scheduler::spend(query.len() + crate::scheduler::UDP_HEADER_BYTES).await;
+                crate::audit::record(|| crate::audit::Probe::new("dns-tunnel", "udp", self.server.ip(), Some(self.server.port()), query.len() + crate::scheduler::UDP_HEADER_BYTES)
+                    .detail(format!("EDNS0 response size probe, {} bytes", size)));
+                if socket.connect(self.server).await.is_err() || socket.send(&query).await.is_err() {
+                    break;
+                }
+                let mut buf = vec![0u8; size as usize + 512];
+                if let Ok(Ok(len)) = timeout(timeout_duration, socket.recv(&mut buf)).await {
+                    if len >= 12 && buf[2] & 0x02 == 0 {
+                        limits.max_response_bytes = size;
+                        break;
+                    }
+                }
+            }
+        }
+
+        info!("DNS tunnel path to {}: {} byte queries, {} byte responses", self.server, limits.max_query_bytes, limits.max_response_bytes);
+        limits
+    }
+}
+
+/// Read a (possibly compressed) DNS name; returns the name and the offset after it
+pub(crate) fn read_dns_name(buf: &[u8], mut offset: usize) -> Option<(String, usize)> {
+    let mut labels = Vec::new();
+    let mut end = None;
+    // Bound pointer chasing to avoid loops in malicious packets
+    for _ in 0..64 {
+        let len = *buf.get(offset)? as usize;
+        if len == 0 {
+            return Some((labels.join("."), end.unwrap_or(offset + 1)));
+        }
+        if len & 0xc0 == 0xc0 {
+            let pointer = ((len & 0x3f) << 8) | *buf.get(offset + 1)? as usize;
+            end.get_or_insert(offset + 2);
+            offset = pointer;
+            continue;
+        }
+        labels.push(String::from_utf8_lossy(buf.get(offset + 1..offset + 1 + len)?).into_owned());
+        offset += 1 + len;
+    }
+    None
+}
+
+// --- HTTP(S) relay transport ---
+//
+// Each probe is a single POST of a small JSON document to an operator-controlled relay:
+//   {"op":"scan","target":"<ip>","port":<port>,"timeout_ms":<ms>}
+//     -> {"status":"open"|"closed"|"filtered"}
+//   {"op":"relay","target":"<ip>:<port>","data":"<base64url>","timeout_ms":<ms>}
+//     -> {"data":"<base64url>"}
+// Failures come back as {"error":"<message>"}. The TCP connection and TLS SNI go to the
+// relay URL's host (or --http-sni), while the Host header can name a different virtual
+// host behind the same edge, which is what fronting-style deployments rely on.
+
+/// HTTP(S) relay transport settings
+#[derive(Debug, Clone)]
+pub struct HttpTunnelConfig {
+    /// Use TLS to the relay (https:// URLs)
+    pub tls: bool,
+    /// Host the TCP connection is made to
+    pub connect_host: String,
+    /// Port the TCP connection is made to
+    pub connect_port: u16,
+    /// Request path on the relay
+    pub path: String,
+    /// Host header sent inside the (encrypted) request; defaults to the URL host
+    pub host_header: Option<String>,
+    /// TLS server name sent in the ClientHello; defaults to the URL host
+    pub sni: Option<String>,
+    /// Bearer token the relay expects
+    pub auth_token: Option<String>,
+    /// Verify the relay's certificate against the system roots
+    pub verify_tls: bool,
+    /// User-Agent sent with every request
+    pub user_agent: String,
+}
+
+impl HttpTunnelConfig {
+    /// Build a config from a relay URL of the form `http[s]://host[:port][/path]`
+    ///
+    /// # Arguments
+    /// * `url` - Relay endpoint
+    ///
+    /// # Returns
+    /// * `Result<HttpTunnelConfig>` - Config with no Host/SNI override, no token and
+    ///   certificate verification enabled
+    pub fn from_url(url: &str) -> Result<Self> {
+        let (tls, rest) = if let Some(rest) = url.strip_prefix("https://") {
+            (true, rest)
+        } else if let Some(rest) = url.strip_prefix("http://") {
+            (false, rest)
+        } else {
+            return Err(anyhow::anyhow!("HTTP relay URL must start with http:// or https://: '{}'", url));
+        };
+
+        let (authority, path) = match rest.find('/') {
+            Some(idx) => (&rest[..idx], rest[idx..].to_string()),
+            None => (rest, "/".to_string()),
+        };
+        let default_port = if tls { 443 } else { 80 };
+        // Bracketed IPv6 literals carry colons of their own
+        let (host, port) = if let Some(v6) = authority.strip_prefix('[') {
+            let end = v6.find(']').context("Unterminated IPv6 literal in HTTP relay URL")?;
+            let port = match v6[end + 1..].strip_prefix(':') {
+                Some(p) => p.parse().context("Invalid port in HTTP relay URL")?,
+                None => default_port,
+            };
+            (v6[..end].to_string(), port)
+        } else {
+            match authority.rsplit_once(':') {
+                Some((h, p)) => (h.to_string(), p.parse().context("Invalid port in HTTP relay URL")?),
+                None => (authority.to_string(), default_port),
+            }
+        };
+        if host.is_empty() {
+            return Err(anyhow::anyhow!("HTTP relay URL has no host: '{}'", url));
+        }
+
+        Ok(HttpTunnelConfig {
+            tls,
+            connect_host: host,
+            connect_port: port,
+            path,
+            host_header: None,
+            sni: None,
+            auth_token: None,
+            verify_tls: true,
+            user_agent: crate::utils::get_random_user_agent(None),
+        })
+    }
+
+    /// Host header value for relay requests
+    fn host_header(&self) -> String {
+        let default_port = if self.tls { 443 } else { 80 };
+        match &self.host_header {
+            Some(host) => host.clone(),
+            None if self.connect_port == default_port => self.connect_host.clone(),
+            None if self.connect_host.contains(':') => format!("[{}]:{}", self.connect_host, self.connect_port),
+            None => format!("{}:{}", self.connect_host, self.connect_port),
+        }
+    }
+}
+
+/// POST one JSON request to the relay and return the decoded JSON response
+async fn http_relay_call(config: &HttpTunnelConfig, request: serde_json::Value, timeout_duration: Duration) -> Result<serde_json::Value> {
+    let body = request.to_string();
+    let mut head = format!(
+        "POST {} HTTP/1.1\r\nHost: {}\r\nUser-Agent: {}\r\nAccept: application/json\r\nContent-Type: application/json\r\nContent-Length: {}\r\nConnection: close\r\n",
+        config.path, config.host_header(), config.user_agent, body.len()
+    );
+    if let Some(token) = &config.auth_token {
+        head.push_str(&format!("Authorization: Bearer {}\r\n", token));
+    }
+    head.push_str("\r\n");
+    let mut raw_request = head.into_bytes();
+    raw_request.extend_from_slice(body.as_bytes());
+
+    crate::audit::record(|| crate::audit::Probe::new("http-tunnel", "tcp", &config.connect_host, Some(config.connect_port),
+        raw_request.len() + crate::scheduler::TCP_HEADER_BYTES)
+        .detail(format!("relay request{}", if config.tls { " over TLS" } else { "" })));
+    let stream = timeout(timeout_duration, crate::netbind::connect_tcp_host(&config.connect_host, config.connect_port))
+        .await
+        .context("Timed out connecting to HTTP relay")?
+        .context("Failed to connect to HTTP relay")?;
+
+    let raw_response = if config.tls {
+        let sni = config.sni.as_deref().unwrap_or(&config.connect_host);
+        let server_name = crate::ssl_config::convert_to_server_name(sni)
+            .map_err(|e| anyhow::anyhow!("Invalid TLS server name '{}': {}", sni, e))?
+            .to_owned();
+        let connector = tokio_rustls::TlsConnector::from(crate::ssl_config::create_tls_config(config.verify_tls));
+        let tls_stream = timeout(timeout_duration, connector.connect(server_name, stream))
+            .await
+            .context("Timed out during TLS handshake with HTTP relay")?
+            .context("TLS handshake with HTTP relay failed")?;
+        http_exchange(tls_stream, &raw_request, timeout_duration).await?
+    } else {
+        http_exchange(stream, &raw_request, timeout_duration).await?
+    };
+
+    let (status, body) = parse_http_response(&raw_response)?;
+    if status != 200 {
+        return Err(anyhow::anyhow!("HTTP relay answered with status {}", status));
+    }
+    parse_relay_response(&body)
+}
+
+/// Relay request asking for a port check of `target_ip:port`
+fn relay_scan_request(target_ip: IpAddr, port: u16, timeout_duration: Duration) -> serde_json::Value {
+    serde_json::json!({
+        "op": "scan",
+        "target": target_ip.to_string(),
+        "port": port,
+        "timeout_ms": timeout_duration.as_millis() as u64,
+    })
+}
+
+/// Relay request asking to connect to `target`, send `data` and return the response
+fn relay_data_request(target: SocketAddr, data: &[u8], timeout_duration: Duration) -> serde_json::Value {
+    serde_json::json!({
+        "op": "relay",
+        "target": target.to_string(),
+        "data": DnsEncoding::Base64Url.encode(data),
+        "timeout_ms": timeout_duration.as_millis() as u64,
+    })
+}
+
+/// Decode a relay JSON response, turning `{"error":...}` into an error
+fn parse_relay_response(body: &[u8]) -> Result<serde_json::Value> {
+    let response: serde_json::Value = serde_json::from_slice(body).context("Relay returned invalid JSON")?;
+    if let Some(error) = response.get("error").and_then(|e| e.as_str()) {
+        return Err(anyhow::anyhow!("Relay error: {}", error));
+    }
+    Ok(response)
+}
+
+/// Port state from a relay `scan` response
+fn relay_port_status(response: &serde_json::Value) -> PortStatus {
+    match response.get("status").and_then(|s| s.as_str()).unwrap_or("") {
+        "open" => PortStatus::Open,
+        "closed" => PortStatus::Closed,
+        "filtered" => PortStatus::Filtered,
+        _ => PortStatus::OpenFiltered,
+    }
+}
+
+/// Payload bytes from a relay `relay` response
+fn relay_response_data(response: &serde_json::Value) -> Result<Vec<u8>> {
+    let data = response.get("data").and_then(|d| d.as_str()).unwrap_or("");
+    DnsEncoding::Base64Url.decode(data).context("Relay returned undecodable data")
+}
+
+/// Write a request and read the response until the relay closes the connection
+async fn http_exchange<S>(mut stream: S, request: &[u8], timeout_duration: Duration) -> Result<Vec<u8>>
+where
+    S: tokio::io::AsyncRead + tokio::io::AsyncWrite + Unpin,
+{
+    use tokio::io::{AsyncReadExt, AsyncWriteExt};
+
+    crate::scheduler::spend(request.len() + crate::scheduler::TCP_HEADER_BYTES).await;
+    stream.write_all(request).await.context("Failed to send request to HTTP relay")?;
+    stream.flush().await?;
+
+    let mut response = Vec::new();
+    match timeout(timeout_duration, stream.read_to_end(&mut response)).await {
+        Ok(Ok(_)) => {}
+        // Servers that skip TLS close_notify surface as an error after the data arrived
+        Ok(Err(e)) if !response.is_empty() => debug!("HTTP relay connection ended uncleanly: {}", e),
+        Ok(Err(e)) => return Err(e).context("Failed to read HTTP relay response"),
+        Err(_) if !response.is_empty() => debug!("HTTP relay kept the connection open; using data received so far"),
+        Err(_) => return Err(anyhow::anyhow!("Timed out waiting for HTTP relay response")),
+    }
+    Ok(response)
+}
+
+/// Split a raw HTTP/1.1 response into status code and (de-chunked) body
+fn parse_http_response(raw: &[u8]) -> Result<(u16, Vec<u8>)> {
+    let header_end = raw.windows(4).position(|w| w == b"\r\n\r\n")
+        .context("Malformed HTTP response from relay")?;
+    let head = String::from_utf8_lossy(&raw[..header_end]);
+    let mut lines = head.split("\r\n");
+    let status = lines.next()
+        .and_then(|line| line.split_whitespace().nth(1))
+        .and_then(|code| code.parse::<u16>().ok())
+        .context("Malformed HTTP status line from relay")?;
+
+    let mut chunked = false;
+    let mut content_length = None;
+    for line in lines {
+        if let Some((name, value)) = line.split_once(':') {
+            let value = value.trim();
+            match name.trim().to_ascii_lowercase().as_str() {
+                "transfer-encoding" => chunked = value.to_ascii_lowercase().contains("chunked"),
+                "content-length" => content_length = value.parse::<usize>().ok(),
+                _ => {}
+            }
+        }
+    }
+
+    let mut body = &raw[header_end + 4..];
+    if chunked {
+        // CDNs frequently re-chunk responses even when the origin sent a length
+        let mut decoded = Vec::new();
+        loop {
+            let line_end = body.windows(2).position(|w| w == b"\r\n").context("Truncated chunked body from relay")?;
+            let size_field = String::from_utf8_lossy(&body[..line_end]);
+            let size = usize::from_str_radix(size_field.split(';').next().unwrap_or("").trim(), 16)
+                .context("Invalid chunk size from relay")?;
+            body = &body[line_end + 2..];
+            if size == 0 {
+                break;
+            }
+            decoded.extend_from_slice(body.get(..size).context("Truncated chunk from relay")?);
+            body = body.get(size + 2..).unwrap_or(&[]);
+        }
+        return Ok((status, decoded));
+    }
+    if let Some(length) = content_length {
+        body = &body[..length.min(body.len())];
+    }
+    Ok((status, body.to_vec()))
+}
+
+/// HTTP(S) relay tunnel scan
+///
+/// Asks the relay to test `target_ip:port` and report the result, so the only traffic
+/// leaving the scanner is ordinary-looking web requests to the relay endpoint.
+///
+/// # Arguments
+/// * `target_ip` - Target to test from the relay
+/// * `port` - Target port
+/// * `config` - Relay endpoint and Host/SNI settings
+/// * `timeout_duration` - Time the relay may spend on the probe (the HTTP request gets twice this)
+///
+/// # Returns
+/// * `Result<PortStatus>` - State reported by the relay; `Filtered` if the relay is unreachable
+///
+/// # OPSEC Considerations:
+/// - One POST per port is a recognisable rate pattern to web proxies; use --rate
+/// - The Host header is only hidden inside TLS; over plain http:// it is visible on path
+/// - Fronting only works where the edge routes on Host rather than SNI
+pub async fn http_tunnel_scan(
+    target_ip: IpAddr,
+    port: u16,
+    config: &HttpTunnelConfig,
+    timeout_duration: Duration,
+) -> Result<PortStatus> {
+    debug!("Requesting relay check of {} port {} via HTTP relay {}", target_ip, port, config.connect_host);
+    let request = relay_scan_request(target_ip, port, timeout_duration);
+
+    let response = match http_relay_call(config, request, timeout_duration * 2).await {
+        Ok(response) => response,
+        Err(e) => {
+            debug!("HTTP relay scan of {}:{} failed: {}", target_ip, port, e);
+            return Ok(PortStatus::Filtered);
+        }
+    };
+
+    Ok(relay_port_status(&response))
+}
+
+/// Relay a request to `target` through the HTTP(S) relay and return the response
+///
+/// The HTTP counterpart of `relay_request`: the relay connects to `target`, writes
+/// `request` (if any) and returns whatever it read before `timeout_duration` passed.
+pub async fn http_relay_request(
+    config: &HttpTunnelConfig,
+    target: SocketAddr,
+    request: Vec<u8>,
+    timeout_duration: Duration,
+) -> Result<Vec<u8>> {
+    let body = relay_data_request(target, &request, timeout_duration);
+    let response = http_relay_call(config, body, timeout_duration * 2).await?;
+    relay_response_data(&response)
+}
+
+// --- QUIC relay transport ---
+//
+// Carries the same JSON relay requests as the HTTP transport, one request per
+// bidirectional QUIC stream, over UDP to an operator-controlled relay (normally on 443
+// with ALPN "h3", so the handshake looks like HTTP/3). Session tickets are kept in the
+// process-wide endpoint, so reconnects resume with 0-RTT and carry the first request in
+// the very first flight.
+
+/// QUIC relay transport settings
+#[derive(Debug, Clone)]
+pub struct QuicTunnelConfig {
+    /// Relay UDP address
+    pub relay: SocketAddr,
+    /// TLS server name (SNI) used for the handshake and certificate check
+    pub server_name: String,
+    /// ALPN protocol offered to the relay
+    pub alpn: Vec<u8>,
+    /// Shared token the relay expects in each request
+    pub auth_token: Option<String>,
+    /// Keep one connection open for all probes; otherwise every probe opens a fresh
+    /// connection resumed with 0-RTT
+    pub reuse_connection: bool,
+}
+
+impl QuicTunnelConfig {
+    /// Build a config from `host[:port]` (port defaults to 443)
+    ///
+    /// # Arguments
+    /// * `spec` - Relay host name or address, optionally with a port
+    /// * `server_name` - SNI override; defaults to the host part of `spec`
+    ///
+    /// # Returns
+    /// * `Result<QuicTunnelConfig>` - Config offering "h3" and reusing one connection
+    pub fn parse(spec: &str, server_name: Option<&str>) -> Result<Self> {
+        use std::net::ToSocketAddrs;
+
+        let (host, port) = match spec.parse::<SocketAddr>() {
+            Ok(addr) => (addr.ip().to_string(), addr.port()),
+            Err(_) => match spec.rsplit_once(':') {
+                Some((h, p)) if !h.contains(':') => (h.to_string(), p.parse().context("Invalid port in QUIC relay address")?),
+                _ => (spec.trim_matches(|c| c == '[' || c == ']').to_string(), 443),
+            },
+        };
+        let relay = (host.as_str(), port).to_socket_addrs()
+            .with_context(|| format!("Cannot resolve QUIC relay '{}'", host))?
+            .next()
+            .with_context(|| format!("No address for QUIC relay '{}'", host))?;
+
+        Ok(QuicTunnelConfig {
+            relay,
+            server_name: server_name.map(str::to_string).unwrap_or(host),
+            alpn: b"h3".to_vec(),
+            auth_token: None,
+            reuse_connection: true,
+        })
+    }
+}
+
+lazy_static! {
+    /// Client endpoint shared by all QUIC probes; holds the TLS session ticket cache
+    static ref QUIC_ENDPOINT: parking_lot::Mutex<Option<quinn::Endpoint>> = parking_lot::Mutex::new(None);
+    /// Open relay connections, reused across probes when `reuse_connection` is set
+    static ref QUIC_CONNECTIONS: parking_lot::Mutex<HashMap<SocketAddr, quinn::Connection>> = parking_lot::Mutex::new(HashMap::new());
+}
+
+/// Get (or create) the process-wide QUIC client endpoint
+fn quic_endpoint(config: &QuicTunnelConfig) -> Result<quinn::Endpoint> {
+    let mut guard = QUIC_ENDPOINT.lock();
+    if let Some(endpoint) = guard.as_ref() {
+        return Ok(endpoint.clone());
+    }
+
+    let mut roots = rustls23::RootCertStore::empty();
+    match rustls_native_certs::load_native_certs() {
+        Ok(certs) => {
+            roots.add_parsable_certificates(certs);
+        }
+        Err(e) => warn!("Could not load native certificates for QUIC relay: {:?}", e),
+    }
+    let mut crypto = rustls23::ClientConfig::builder()
+        .with_root_certificates(roots)
+        .with_no_client_auth();
+    crypto.alpn_protocols = vec![config.alpn.clone()];
+    // Lets resumed connections carry the first request before the handshake completes
+    crypto.enable_early_data = true;
+
+    let quic_crypto = quinn::crypto::rustls::QuicClientConfig::try_from(crypto)
+        .map_err(|e| anyhow::anyhow!("Unusable TLS configuration for QUIC: {}", e))?;
+    let mut client_config = quinn::ClientConfig::new(std::sync::Arc::new(quic_crypto));
+    let mut transport = quinn::TransportConfig::default();
+    transport.keep_alive_interval(Some(Duration::from_secs(15)));
+    client_config.transport_config(std::sync::Arc::new(transport));
+
+    // Bound like every other socket so the relay traffic honours --interface/--source-ip
+    let socket = crate::netbind::bind_udp_std(config.relay.ip()).context("Failed to open QUIC client socket")?;
+    socket.set_nonblocking(true)?;
+    let mut endpoint = quinn::Endpoint::new(quinn::EndpointConfig::default(), None, socket, std::sync::Arc::new(quinn::TokioRuntime))
+        .context("Failed to open QUIC client socket")?;
+    endpoint.set_default_client_config(client_config);
+    *guard = Some(endpoint.clone());
+    Ok(endpoint)
+}
+
+/// Get a connection to the relay, resuming with 0-RTT when a session ticket is cached
+async fn quic_connection(config: &QuicTunnelConfig, timeout_duration: Duration) -> Result<quinn::Connection> {
+    if config.reuse_connection {
+        if let Some(connection) = QUIC_CONNECTIONS.lock().get(&config.relay) {
+            if connection.close_reason().is_none() {
+                return Ok(connection.clone());
+            }
+        }
+    }
+
+    let endpoint = quic_endpoint(config)?;
+    let connecting = endpoint.connect(config.relay, &config.server_name)
+        .context("Failed to start QUIC connection to relay")?;
+    let connection = match connecting.into_0rtt() {
+        Ok((connection, accepted)) => {
+            debug!("QUIC relay {}: resuming session with 0-RTT", config.relay);
+            let relay = config.relay;
+            tokio::spawn(async move {
+                if !accepted.await {
+                    // quinn retransmits rejected early data once the handshake completes
+                    debug!("QUIC relay {} rejected 0-RTT; request resent after handshake", relay);
+                }
+            });
+            connection
+        }
+        Err(connecting) => timeout(timeout_duration, connecting)
+            .await
+            .context("Timed out during QUIC handshake with relay")?
+            .context("QUIC handshake with relay failed")?,
+    };
+
+    if config.reuse_connection {
+        QUIC_CONNECTIONS.lock().insert(config.relay, connection.clone());
+    }
+    Ok(connection)
+}
+
+/// Send one JSON request on a new stream and return the decoded JSON response
+async fn quic_relay_call(config: &QuicTunnelConfig, mut request: serde_json::Value, timeout_duration: Duration) -> Result<serde_json::Value> {
+    if let Some(token) = &config.auth_token {
+        request["token"] = serde_json::Value::String(token.clone());
+    }
+    let connection = quic_connection(config, timeout_duration).await?;
+
+    let exchange = timeout(timeout_duration, async {
+        let (mut send, mut recv) = connection.open_bi().await?;
+        let request = request.to_string();
+        // QUIC short header, frame headers and AEAD tag come to about 40 bytes per packet
+        crate::scheduler::spend(request.len() + crate::scheduler::UDP_HEADER_BYTES + 40).await;
+        crate::audit::record(|| crate::audit::Probe::new("quic-tunnel", "udp", config.relay.ip(), Some(config.relay.port()), request.len() + crate::scheduler::UDP_HEADER_BYTES + 40)
+            .detail("relay request"));
+        send.write_all(request.as_bytes()).await?;
+        send.finish()?;
+        let response = recv.read_to_end(1 << 20).await?;
+        Ok::<Vec<u8>, anyhow::Error>(response)
+    }).await;
+
+    if !config.reuse_connection {
+        connection.close(0u32.into(), b"");
+    }
+    let body = match exchange {
+        Ok(Ok(body)) => body,
+        Ok(Err(e)) => {
+            // Drop a broken connection so the next probe reconnects
+            QUIC_CONNECTIONS.lock().remove(&config.relay);
+            return Err(e.context("QUIC relay stream failed"));
+        }
+        Err(_) => return Err(anyhow::anyhow!("Timed out waiting for QUIC relay response")),
+    };
+    parse_relay_response(&body)
+}
+
+/// QUIC relay tunnel scan
+///
+/// Asks the relay to test `target_ip:port` over a QUIC stream, so the only traffic
+/// leaving the scanner is UDP/443 that looks like HTTP/3.
+///
+/// # Arguments
+/// * `target_ip` - Target to test from the relay
+/// * `port` - Target port
+/// * `config` - Relay address, SNI and connection reuse settings
+/// * `timeout_duration` - Time the relay may spend on the probe (the stream gets twice this)
+///
+/// # Returns
+/// * `Result<PortStatus>` - State reported by the relay; `Filtered` if the relay is unreachable
+///
+/// # OPSEC Considerations:
+/// - Networks that block UDP/443 force browsers back to TCP, so QUIC there is conspicuous
+/// - 0-RTT data is replayable by an on-path observer; requests only contain probe targets
+/// - Fresh connections per probe vary the source port but multiply handshakes on the relay
+pub async fn quic_tunnel_scan(
+    target_ip: IpAddr,
+    port: u16,
+    config: &QuicTunnelConfig,
+    timeout_duration: Duration,
+) -> Result<PortStatus> {
+    debug!("Requesting relay check of {} port {} via QUIC relay {}", target_ip, port, config.relay);
+    let request = relay_scan_request(target_ip, port, timeout_duration);
+
+    match quic_relay_call(config, request, timeout_duration * 2).await {
+        Ok(response) => Ok(relay_port_status(&response)),
+        Err(e) => {
+            debug!("QUIC relay scan of {}:{} failed: {}", target_ip, port, e);
+            Ok(PortStatus::Filtered)
+        }
+    }
+}
+
+/// Relay a request to `target` through the QUIC relay and return the response
+///
+/// The QUIC counterpart of `http_relay_request`.
+pub async fn quic_relay_request(
+    config: &QuicTunnelConfig,
+    target: SocketAddr,
+    request: Vec<u8>,
+    timeout_duration: Duration,
+) -> Result<Vec<u8>> {
+    let body = relay_data_request(target, &request, timeout_duration);
+    let response = quic_relay_call(config, body, timeout_duration * 2).await?;
+    relay_response_data(&response)
+}
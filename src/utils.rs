@@ -19,6 +19,7 @@ use pnet::packet::icmpv6::{Icmpv6Types, Icmpv6Code, MutableIcmpv6Packet};
 use pnet::packet::Packet;
 
 use anyhow::{Result, anyhow};
+use zeroize::Zeroize;
 
 /// Finds a suitable non-loopback IPv4 address for the default interface.
 ///
@@ -32,6 +33,11 @@ use anyhow::{Result, anyhow};
 /// - Binding to the correct source IP is important for scans to work and potentially
 ///   for evasion (appearing to originate from the expected interface).
 pub fn find_local_ipv4() -> Result<Ipv4Addr> {
+    // --source-ip / --interface take precedence over auto-detection
+    if let Some(ip) = crate::netbind::source_v4() {
+        return Ok(ip);
+    }
+
     // Get all network interfaces
     let interfaces = pnet_datalink::interfaces();
 
@@ -72,6 +78,10 @@ pub fn find_local_ipv4() -> Result<Ipv4Addr> {
 /// - Using temporary or privacy addresses might be preferred for stealth, but this
 ///   function currently prioritizes stable global unicast addresses for simplicity.
 pub fn find_local_ipv6() -> Result<Ipv6Addr> {
+    if let Some(ip) = crate::netbind::source_v6() {
+        return Ok(ip);
+    }
+
     // Get all network interfaces
     let interfaces = pnet_datalink::interfaces();
 
@@ -204,13 +214,142 @@ pub fn get_default_interface_ipv6() -> Option<IpAddr> {
     Some(IpAddr::V6(Ipv6Addr::LOCALHOST))
 }
 
+lazy_static::lazy_static! {
+    /// `--seed`, with the generator `scan_rng` draws from outside a probe
+    static ref SEEDED_RNG: parking_lot::Mutex<Option<(u64, rand::rngs::StdRng)>> = parking_lot::Mutex::new(None);
+}
+
+tokio::task_local! {
+    /// Generator for the probe a per-port task is sending, derived from `--seed`
+    static PROBE_RNG: std::cell::RefCell<ProbeRng>;
+}
+
+/// Per-probe generator: the hashed seed, target, port and technique, and the generator
+/// for the current attempt
+struct ProbeRng {
+    key: sha2::Sha256,
+    rng: rand::rngs::StdRng,
+}
+
+impl ProbeRng {
+    fn for_attempt(key: &sha2::Sha256, attempt: u8) -> rand::rngs::StdRng {
+        use rand::SeedableRng;
+        use sha2::Digest;
+        let mut hasher = key.clone();
+        hasher.update([attempt]);
+        rand::rngs::StdRng::from_seed(hasher.finalize().into())
+    }
+}
+
+/// Make scan randomness reproducible from `seed`
+///
+/// Delays, rates, source ports, sequence numbers, IP IDs, padding, TTLs and OS profile
+/// choices are then drawn from generators derived from the seed. Inside `with_probe_rng`
+/// they come from the probe's own generator, so concurrent probes do not shift each
+/// other's draws; elsewhere they follow one sequence in the order they are requested.
+/// Keys, passphrases and run IDs stay random.
+pub fn set_seed(seed: u64) {
+    use rand::SeedableRng;
+    *SEEDED_RNG.lock() = Some((seed, rand::rngs::StdRng::seed_from_u64(seed)));
+}
+
+/// Run a per-port probe task with its own generator under `--seed`
+///
+/// The generator is derived from the seed, target, port and technique, and from the
+/// attempt set with `probe_attempt`, so the probe makes the same draws whichever order
+/// concurrent tasks run in. Without a seed `task` runs unchanged.
+pub async fn with_probe_rng<F: std::future::Future>(target: IpAddr, port: u16, technique: impl std::fmt::Display, task: F) -> F::Output {
+    use sha2::{Digest, Sha256};
+
+    let seed = SEEDED_RNG.lock().as_ref().map(|(seed, _)| *seed);
+    let Some(seed) = seed else {
+        return task.await;
+    };
+    let mut key = Sha256::new();
+    key.update(seed.to_be_bytes());
+    match target {
+        IpAddr::V4(ip) => key.update(ip.octets()),
+        IpAddr::V6(ip) => key.update(ip.octets()),
+    }
+    key.update(port.to_be_bytes());
+    key.update(technique.to_string().as_bytes());
+    let rng = ProbeRng::for_attempt(&key, 0);
+    PROBE_RNG.scope(std::cell::RefCell::new(ProbeRng { key, rng }), task).await
+}
+
+/// Switch the current probe's generator to retransmission `attempt`
+pub fn probe_attempt(attempt: u8) {
+    let _ = PROBE_RNG.try_with(|probe| {
+        let mut probe = probe.borrow_mut();
+        probe.rng = ProbeRng::for_attempt(&probe.key, attempt);
+    });
+}
+
+/// Random generator for scan behaviour: the thread RNG, or one derived from `--seed`
+pub enum ScanRng {
+    Thread(rand::rngs::ThreadRng),
+    Seeded(rand::rngs::StdRng),
+}
+
+impl rand::RngCore for ScanRng {
+    fn next_u32(&mut self) -> u32 {
+        match self {
+            ScanRng::Thread(rng) => rng.next_u32(),
+            ScanRng::Seeded(rng) => rng.next_u32(),
+        }
+    }
+
+    fn next_u64(&mut self) -> u64 {
+        match self {
+            ScanRng::Thread(rng) => rng.next_u64(),
+            ScanRng::Seeded(rng) => rng.next_u64(),
+        }
+    }
+
+    fn fill_bytes(&mut self, dest: &mut [u8]) {
+        match self {
+            ScanRng::Thread(rng) => rng.fill_bytes(dest),
+            ScanRng::Seeded(rng) => rng.fill_bytes(dest),
+        }
+    }
+
+    fn try_fill_bytes(&mut self, dest: &mut [u8]) -> std::result::Result<(), rand::Error> {
+        match self {
+            ScanRng::Thread(rng) => rng.try_fill_bytes(dest),
+            ScanRng::Seeded(rng) => rng.try_fill_bytes(dest),
+        }
+    }
+}
+
+/// Generator for randomized scan behaviour; use instead of `thread_rng`
+///
+/// # Returns
+/// * `ScanRng` - The thread RNG, or with `--seed` a generator seeded from the next value
+///   of the current probe's generator (see `with_probe_rng`), or of the run's sequence
+///   outside a probe. Runs with the same seed and settings make the same draws.
+pub fn scan_rng() -> ScanRng {
+    use rand::{RngCore, SeedableRng};
+    let mut seeded = SEEDED_RNG.lock();
+    let Some((_, run_rng)) = seeded.as_mut() else {
+        return ScanRng::Thread(thread_rng());
+    };
+    let next = PROBE_RNG
+        .try_with(|probe| probe.borrow_mut().rng.next_u64())
+        .unwrap_or_else(|_| run_rng.next_u64());
+    ScanRng::Seeded(rand::rngs::StdRng::seed_from_u64(next))
+}
+
+/// Source ports handed out by `random_high_port`; the fast I/O receive filter only
+/// accepts TCP replies addressed to this range
+pub const PROBE_PORT_RANGE: std::ops::Range<u16> = 49152..65535;
+
 /// Generate a random source port for TCP/UDP packets
 ///
 /// This generates a port in the ephemeral port range (49152-65535)
 /// which is less likely to conflict with well-known services.
 #[allow(dead_code)]
 pub fn random_high_port() -> u16 {
-    thread_rng().gen_range(49152..65535)
+    scan_rng().gen_range(PROBE_PORT_RANGE)
 }
 
 /// Generate a random IP ID field value
@@ -218,7 +357,7 @@ pub fn random_high_port() -> u16 {
 /// Used for IP packet identification to maintain uniqueness.
 #[allow(dead_code)]
 pub fn random_ip_id() -> u16 {
-    thread_rng().gen_range(1..65535)
+    scan_rng().gen_range(1..65535)
 }
 
 /// Generate a random TCP sequence number
@@ -227,7 +366,7 @@ pub fn random_ip_id() -> u16 {
 /// coming from a scanner.
 #[allow(dead_code)]
 pub fn random_tcp_seq() -> u32 {
-    thread_rng().gen::<u32>()
+    scan_rng().gen::<u32>()
 }
 
 /// Get a randomized TTL value for evading signature detection
@@ -253,24 +392,24 @@ pub fn get_ttl(evasion: bool, os_profile: Option<&str>) -> u8 {
             // Windows typically uses TTL 128
             // Add some randomization to avoid perfect fingerprinting
             let dist = Uniform::new_inclusive(126, 130);
-            dist.sample(&mut thread_rng())
+            dist.sample(&mut scan_rng())
         },
         Some("linux") => {
             // Linux typically uses TTL 64
             // Add some randomization to avoid perfect fingerprinting
             let dist = Uniform::new_inclusive(63, 66);
-            dist.sample(&mut thread_rng())
+            dist.sample(&mut scan_rng())
         },
         Some("macos") => {
             // macOS typically uses TTL 64
             // Add some randomization to avoid perfect fingerprinting
             let dist = Uniform::new_inclusive(63, 66);
-            dist.sample(&mut thread_rng())
+            dist.sample(&mut scan_rng())
         },
         _ => {
             // Randomly choose one of the common TTL values to avoid
             // easy fingerprinting of our scanner
-            *[64, 128, 255].choose(&mut thread_rng()).unwrap()
+            *[64, 128, 255].choose(&mut scan_rng()).unwrap()
         }
     }
 }
@@ -305,7 +444,7 @@ pub fn backoff_delay(retry: usize, base_ms: u64, max_ms: u64) -> Duration {
 /// * `String` - A realistic user agent string
 #[allow(dead_code)]
 pub fn get_random_user_agent(browser_type: Option<&str>) -> String {
-    let mut rng = thread_rng();
+    let mut rng = scan_rng();
     
     // Common modern browser User-Agents
     let all_agents = [
@@ -377,7 +516,7 @@ pub fn get_advanced_ttl(os_profile: &str, jitter_amount: u8) -> u8 {
         "random" => {
             // Randomly pick one of the common TTL values
             let bases = [64u8, 128u8, 255u8];
-            *bases.choose(&mut thread_rng()).unwrap()
+            *bases.choose(&mut scan_rng()).unwrap()
         },
         _ => 64, // Default to Linux-like
     };
@@ -390,8 +529,8 @@ pub fn get_advanced_ttl(os_profile: &str, jitter_amount: u8) -> u8 {
     match base_ttl {
         64 => {
             // For Linux/macOS (typically 64)
-            let variance = thread_rng().gen_range(0..=jitter);
-            if thread_rng().gen_bool(0.7) {
+            let variance = scan_rng().gen_range(0..=jitter);
+            if scan_rng().gen_bool(0.7) {
                 // Usually decrement because most Linux TTLs arrive lower than 64
                 base_ttl.saturating_sub(variance)
             } else {
@@ -401,8 +540,8 @@ pub fn get_advanced_ttl(os_profile: &str, jitter_amount: u8) -> u8 {
         },
         128 => {
             // For Windows (typically 128)
-            let variance = thread_rng().gen_range(0..=jitter);
-            if thread_rng().gen_bool(0.6) {
+            let variance = scan_rng().gen_range(0..=jitter);
+            if scan_rng().gen_bool(0.6) {
                 // Windows packets often arrive with TTL 127-128
                 base_ttl.saturating_sub(variance)
             } else {
@@ -413,13 +552,13 @@ pub fn get_advanced_ttl(os_profile: &str, jitter_amount: u8) -> u8 {
         255 => {
             // For network devices (typically 255)
             // Cisco/network devices almost always have lower TTL than 255 when they arrive
-            let variance = thread_rng().gen_range(2..=(jitter + 2));
+            let variance = scan_rng().gen_range(2..=(jitter + 2));
             base_ttl.saturating_sub(variance)
         },
         _ => {
             // For any other base TTL
-            let variance = thread_rng().gen_range(0..=jitter);
-            if thread_rng().gen_bool(0.5) {
+            let variance = scan_rng().gen_range(0..=jitter);
+            if scan_rng().gen_bool(0.5) {
                 base_ttl.saturating_sub(variance)
             } else {
                 base_ttl.saturating_add(variance)
@@ -460,7 +599,7 @@ pub fn generate_advanced_mimicry(protocol: &str, variant: Option<&str>) -> Vec<u
                         "Mozilla/5.0 (Windows NT 10.0; Win64; x64; rv:94.0) Gecko/20100101 Firefox/94.0",
                         "Mozilla/5.0 (Macintosh; Intel Mac OS X 10_15_7) AppleWebKit/605.1.15 (KHTML, like Gecko) Version/15.0 Safari/605.1.15",
                     ];
-                    user_agents[thread_rng().gen_range(0..user_agents.len())]
+                    user_agents[scan_rng().gen_range(0..user_agents.len())]
                 }
             };
             
@@ -484,7 +623,7 @@ pub fn generate_advanced_mimicry(protocol: &str, variant: Option<&str>) -> Vec<u
             );
             
             // Add some randomness to make it less predictable
-            if thread_rng().gen_bool(0.3) {
+            if scan_rng().gen_bool(0.3) {
                 // Sometimes add a DNT (Do Not Track) header
                 request = request.replace("\r\n\r\n", "\r\nDNT: 1\r\n\r\n");
             }
@@ -504,7 +643,7 @@ pub fn generate_advanced_mimicry(protocol: &str, variant: Option<&str>) -> Vec<u
                 Some("1.3") => (3, 4),
                 _ => {
                     // Default to TLS 1.2 (most common) or randomly select
-                    if thread_rng().gen_bool(0.8) {
+                    if scan_rng().gen_bool(0.8) {
                         (3, 3) // TLS 1.2
                     } else {
                         (3, 4) // TLS 1.3
@@ -525,7 +664,7 @@ pub fn generate_advanced_mimicry(protocol: &str, variant: Option<&str>) -> Vec<u
             // Add client random (32 bytes)
             let mut client_random = vec![0u8; 32];
             for i in 0..client_random.len() {
-                client_random[i] = thread_rng().gen();
+                client_random[i] = scan_rng().gen();
             }
             tls_hello.extend_from_slice(&client_random);
             
@@ -541,16 +680,16 @@ pub fn generate_advanced_mimicry(protocol: &str, variant: Option<&str>) -> Vec<u
             let versions = ["7.9", "8.0", "8.1", "8.2", "8.3", "8.4", "8.5", "8.6"];
             let ssh_version = match variant {
                 Some(v) => v.to_string(),
-                None => versions[thread_rng().gen_range(0..versions.len())].to_string()
+                None => versions[scan_rng().gen_range(0..versions.len())].to_string()
             };
             
             // Vary the SSH implementation details to look more realistic
-            let implementation = if thread_rng().gen_bool(0.9) {
+            let implementation = if scan_rng().gen_bool(0.9) {
                 format!("OpenSSH_{}", ssh_version)
             } else {
                 // Occasionally use other client implementations
                 let alt_clients = ["PuTTY_Release_0.75", "libssh2_1.10.0", "JSCH-0.1.55"];
-                alt_clients[thread_rng().gen_range(0..alt_clients.len())].to_string()
+                alt_clients[scan_rng().gen_range(0..alt_clients.len())].to_string()
             };
             
             format!("SSH-2.0-{}\r\n", implementation).into_bytes()
@@ -571,10 +710,10 @@ pub fn generate_advanced_mimicry(protocol: &str, variant: Option<&str>) -> Vec<u
         _ => {
             // For unknown protocols, create generic binary data
             // that doesn't look like a scanner pattern
-            let length = thread_rng().gen_range(16..32);
+            let length = scan_rng().gen_range(16..32);
             let mut data = Vec::with_capacity(length);
             for _ in 0..length {
-                data.push(thread_rng().gen());
+                data.push(scan_rng().gen());
             }
             data
         }
@@ -695,6 +834,112 @@ pub fn decrypt_sensitive_data(hex_data: &str, key: &[u8]) -> Option<String> {
     }
 }
 
+/// String that is wiped from memory when dropped
+///
+/// Use for anything that must not linger in freed heap pages: decrypted log
+/// messages, serialized results, passphrases. `Debug` prints only the length.
+#[derive(Clone, Default, PartialEq, Eq)]
+pub struct SecureString(String);
+
+impl SecureString {
+    pub fn new(value: String) -> Self {
+        SecureString(value)
+    }
+
+    pub fn as_str(&self) -> &str {
+        &self.0
+    }
+}
+
+impl From<String> for SecureString {
+    fn from(value: String) -> Self {
+        SecureString(value)
+    }
+}
+
+impl std::ops::Deref for SecureString {
+    type Target = str;
+
+    fn deref(&self) -> &str {
+        &self.0
+    }
+}
+
+impl std::fmt::Debug for SecureString {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "SecureString({} bytes)", self.0.len())
+    }
+}
+
+impl std::fmt::Display for SecureString {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(&self.0)
+    }
+}
+
+impl Drop for SecureString {
+    fn drop(&mut self) {
+        self.0.zeroize();
+    }
+}
+
+/// Byte buffer (keys, raw payloads) that is wiped from memory when dropped
+#[derive(Clone, Default, PartialEq, Eq)]
+pub struct SecureBytes(Vec<u8>);
+
+impl SecureBytes {
+    pub fn new(value: Vec<u8>) -> Self {
+        SecureBytes(value)
+    }
+
+    /// Random bytes, e.g. a fresh key
+    pub fn random(len: usize) -> Self {
+        let mut bytes = vec![0u8; len];
+        thread_rng().fill(bytes.as_mut_slice());
+        SecureBytes(bytes)
+    }
+}
+
+impl std::ops::Deref for SecureBytes {
+    type Target = [u8];
+
+    fn deref(&self) -> &[u8] {
+        &self.0
+    }
+}
+
+impl std::fmt::Debug for SecureBytes {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "SecureBytes({} bytes)", self.0.len())
+    }
+}
+
+impl Drop for SecureBytes {
+    fn drop(&mut self) {
+        self.0.zeroize();
+    }
+}
+
+/// Wipe banners and certificate details held in scan results
+///
+/// Called before results are dropped in memory-only mode, so service banners (which
+/// can hold hostnames, credentials and internal paths) do not survive in freed memory.
+pub fn wipe_results(results: &mut crate::models::ScanResults) {
+    for port_result in results.results.values_mut() {
+        if let Some(banner) = port_result.banner.as_mut() {
+            banner.zeroize();
+        }
+        if let Some(capture) = port_result.captured_response.as_mut() {
+            capture.hex.zeroize();
+        }
+        if let Some(cert) = port_result.cert_info.as_mut() {
+            cert.subject.zeroize();
+            cert.alt_names.iter_mut().for_each(|name| name.zeroize());
+        }
+    }
+    results.results.clear();
+}
+
 /// In-memory log buffer that stores logs without writing to disk
 ///
 /// This type allows for storing logs in memory only, improving operational security
@@ -702,10 +947,10 @@ pub fn decrypt_sensitive_data(hex_data: &str, key: &[u8]) -> Option<String> {
 #[derive(Default, Clone)]
 pub struct MemoryLogBuffer {
     /// Vector of log entries, each as a tuple of (timestamp, level, message)
-    entries: std::sync::Arc<parking_lot::Mutex<Vec<(chrono::DateTime<Utc>, String, String)>>>,
+    entries: std::sync::Arc<parking_lot::Mutex<Vec<(chrono::DateTime<Utc>, String, SecureString)>>>,
     
     /// Optional encryption key
-    encryption_key: Option<Vec<u8>>,
+    encryption_key: Option<SecureBytes>,
     
     /// Maximum number of entries to keep
     max_entries: usize,
@@ -731,11 +976,7 @@ impl MemoryLogBuffer {
     /// * `encrypt` - Whether to encrypt log entries
     pub fn new(max_entries: usize, encrypt: bool) -> Self {
         let encryption_key = if encrypt {
-            // Generate a random encryption key with proper error handling
-            // Just use thread_rng directly without the unused imports
-            let mut key = vec![0u8; 32]; // AES-256 key size
-            thread_rng().fill(key.as_mut_slice());
-            Some(key)
+            Some(SecureBytes::random(32)) // AES-256 key size
         } else {
             None
         };
@@ -768,7 +1009,7 @@ impl MemoryLogBuffer {
                 *counter += 1;
                 
                 // Use counter in encryption to ensure unique nonces
-                match encrypt_sensitive_data_with_nonce(message, Some(key.clone()), *counter) {
+                match encrypt_sensitive_data_with_nonce(message, Some(key.to_vec()), *counter) {
                     Ok(encrypted) => encrypted,
                     Err(_) => {
                         // Fallback if encryption fails - mark as encryption error
@@ -783,28 +1024,12 @@ impl MemoryLogBuffer {
         };
         
         // Add the entry
-        entries.push((timestamp, level.to_string(), message));
+        entries.push((timestamp, level.to_string(), SecureString::new(message)));
         
-        // Trim if needed
+        // Trim if needed; removed messages are zeroized as they drop
         if entries.len() > self.max_entries {
-            // Securely clear the oldest entries
             let to_remove = entries.len() - self.max_entries;
-            for _ in 0..to_remove {
-                if let Some((_, _, message)) = entries.first() {
-                    // Overwrite the message with zeros before removing
-                    let mut overwrite = String::with_capacity(message.len());
-                    for _ in 0..message.len() {
-                        overwrite.push('\0');
-                    }
-                    
-                    // This is an approximate secure wipe - not perfect but better than nothing
-                    if let Some(entry) = entries.first_mut() {
-                        entry.2 = overwrite;
-                    }
-                }
-                
-                entries.remove(0);
-            }
+            entries.drain(..to_remove);
         }
     }
     
@@ -814,8 +1039,8 @@ impl MemoryLogBuffer {
     /// * `decrypt` - Whether to decrypt entries (if encrypted)
     ///
     /// # Returns
-    /// * `Vec<(chrono::DateTime<Utc>, String, String)>` - Vector of (timestamp, level, message)
-    pub fn get_entries(&self, decrypt: bool) -> Vec<(chrono::DateTime<Utc>, String, String)> {
+    /// * `Vec<(chrono::DateTime<Utc>, String, SecureString)>` - Vector of (timestamp, level, message)
+    pub fn get_entries(&self, decrypt: bool) -> Vec<(chrono::DateTime<Utc>, String, SecureString)> {
         // Update last export time for rate limiting
         let mut last_export = self.last_export.lock();
         
@@ -824,7 +1049,7 @@ impl MemoryLogBuffer {
             let elapsed = last.elapsed().as_secs();
             if elapsed < self.min_export_interval {
                 // Add random delay to prevent timing analysis
-                let delay = thread_rng().gen_range(50..200);
+                let delay = scan_rng().gen_range(50..200);
                 std::thread::sleep(Duration::from_millis(delay));
             }
         }
@@ -848,7 +1073,7 @@ impl MemoryLogBuffer {
                 
                 // Skip decrypt for encryption errors
                 if message.starts_with("ENCRYPTION_ERROR:") {
-                    return (*timestamp, level.clone(), message.replace("ENCRYPTION_ERROR:", "[Encryption Failed] "));
+                    return (*timestamp, level.clone(), SecureString::new(message.replace("ENCRYPTION_ERROR:", "[Encryption Failed] ")));
                 }
                 
                 let decrypted = if let Some(key) = &self.encryption_key {
@@ -860,7 +1085,7 @@ impl MemoryLogBuffer {
                     "[NO_DECRYPTION_KEY]".to_string()
                 };
                 
-                (*timestamp, level.clone(), decrypted)
+                (*timestamp, level.clone(), SecureString::new(decrypted))
             }).collect()
         }
     }
@@ -868,16 +1093,8 @@ impl MemoryLogBuffer {
     /// Clear all log entries with secure wiping
     #[allow(dead_code)]
     pub fn clear(&self) {
-        let mut entries = self.entries.lock();
-        
-        // First overwrite all messages with zeros
-        for entry in entries.iter_mut() {
-            let msg_len = entry.2.len();
-            entry.2 = "\0".repeat(msg_len);
-        }
-        
-        // Then clear the vector
-        entries.clear();
+        // Messages are zeroized as they drop
+        self.entries.lock().clear();
     }
     
     /// Get the number of log entries
@@ -897,11 +1114,11 @@ impl MemoryLogBuffer {
     /// * `decrypt` - Whether to decrypt entries (if encrypted)
     ///
     /// # Returns
-    /// * `String` - Formatted log string
-    pub fn format_logs(&self, decrypt: bool) -> String {
+    /// * `SecureString` - Formatted log string
+    pub fn format_logs(&self, decrypt: bool) -> SecureString {
         let entries = self.get_entries(decrypt);
         
-        entries.iter()
+        let mut lines: Vec<String> = entries.iter()
             .map(|(timestamp, level, message)| {
                 format!("[{}] {} - {}", 
                     timestamp.format("%Y-%m-%d %H:%M:%S%.3f"),
@@ -909,8 +1126,10 @@ impl MemoryLogBuffer {
                     sanitize_string(message) // Sanitize to prevent log injection
                 )
             })
-            .collect::<Vec<_>>()
-            .join("\n")
+            .collect();
+        let formatted = SecureString::new(lines.join("\n"));
+        lines.zeroize();
+        formatted
     }
     
     /// Export logs to a file with encryption
@@ -929,20 +1148,8 @@ impl MemoryLogBuffer {
             return Err("Invalid export path".to_string());
         }
         
-        // Get log entries
-        let entries = self.get_entries(true);
-        
         // Format logs
-        let log_content = entries.iter()
-            .map(|(timestamp, level, message)| {
-                format!("[{}] {} - {}", 
-                    timestamp.format("%Y-%m-%d %H:%M:%S%.3f"),
-                    level, 
-                    sanitize_string(message)
-                )
-            })
-            .collect::<Vec<_>>()
-            .join("\n");
+        let log_content = self.format_logs(true);
         
         // Encrypt file if password provided
         let file_content = if let Some(pwd) = password {
@@ -950,14 +1157,14 @@ impl MemoryLogBuffer {
             let key = pwd.as_bytes().to_vec();
             // Use existing encrypt_sensitive_data function
             match encrypt_sensitive_data(&log_content, Some(key)) {
-                (encrypted, _) => format!("ENCRYPTED_LOG_FILE_V1\n{}", encrypted),
+                (encrypted, _) => SecureString::new(format!("ENCRYPTED_LOG_FILE_V1\n{}", encrypted)),
             }
         } else {
             log_content
         };
         
         // Write to file
-        match std::fs::write(path, file_content) {
+        match std::fs::write(path, file_content.as_bytes()) {
             Ok(_) => Ok(()),
             Err(e) => Err(format!("Failed to write log file: {}", e)),
         }
@@ -978,11 +1185,10 @@ pub fn encrypt_sensitive_data_with_nonce(data: &str, key: Option<Vec<u8>>, nonce
     // For production use, a more robust approach would be needed
     
     // Get or generate a key
-    let key = key.unwrap_or_else(|| {
-        let mut key = vec![0u8; 32]; // 256-bit key size
-        thread_rng().fill(key.as_mut_slice());
-        key
-    });
+    let key = match key {
+        Some(key) => SecureBytes::new(key),
+        None => SecureBytes::random(32), // 256-bit key size
+    };
     
     // Create a basic XOR encryption with the key and nonce
     let mut encrypted = Vec::with_capacity(data.len() + 8);
@@ -1651,7 +1857,7 @@ pub fn generate_dns_tunnel_id() -> String {
     // But are actually encoding random data
     
     let alphanumeric_chars: &[u8] = b"abcdefghijklmnopqrstuvwxyz0123456789";
-    let mut rng = thread_rng();
+    let mut rng = scan_rng();
     
     // First part: "cdn", "api", "www", "app", etc.
     let prefixes = ["cdn", "api", "www", "app", "static", "img", "m", "s"];
@@ -1741,6 +1947,11 @@ pub fn extract_postgresql_version(banner: &str) -> Option<String> {
 pub fn send_icmp_packet(target_ip: IpAddr, payload: &[u8], ttl: u8) -> Result<bool, anyhow::Error> {
     // Log the operation
     debug!("Sending ICMP packet to {} with TTL {} and {} bytes payload", target_ip, ttl, payload.len());
+
+    // The kernel builds the IP header and frame here, so it cannot carry a spoofed MAC
+    if crate::mac_vendor::spoofed_mac().is_some() {
+        return Err(anyhow!("ICMP echo to {} would leave with the real MAC while --spoof-mac is set", target_ip));
+    }
     
     // Create a transport channel for sending ICMP packets
     let protocol = match target_ip {
@@ -1757,7 +1968,7 @@ pub fn send_icmp_packet(target_ip: IpAddr, payload: &[u8], ttl: u8) -> Result<bo
     // This is done to avoid suspicion from network monitoring systems
     let mut extended_payload = payload.to_vec();
     if extended_payload.len() < 16 {
-        let mut rng = thread_rng();
+        let mut rng = scan_rng();
         let padding_size = 16 - extended_payload.len();
         for _ in 0..padding_size {
             extended_payload.push(rng.gen());
@@ -1777,7 +1988,7 @@ pub fn send_icmp_packet(target_ip: IpAddr, payload: &[u8], ttl: u8) -> Result<bo
             
             // Set a random identifier and sequence for the echo request
             let ident = random_high_port();
-            let seq = thread_rng().gen::<u16>();
+            let seq = scan_rng().gen::<u16>();
             
             // Create a temporary buffer for the full payload
             let mut full_payload = Vec::with_capacity(4 + extended_payload.len());
@@ -1797,6 +2008,9 @@ pub fn send_icmp_packet(target_ip: IpAddr, payload: &[u8], ttl: u8) -> Result<bo
             icmp_packet.set_checksum(checksum);
             
             // Send the ICMP packet
+            crate::scheduler::spend_blocking(4 + full_payload.len() + 20);
+            crate::audit::record(|| crate::audit::Probe::new("icmp", "icmp", target_ip, None, 4 + full_payload.len() + 20)
+                .detail(format!("echo request, TTL {}", ttl)));
             match tx.send_to(icmp_packet, target_ip) {
                 Ok(_) => Ok(true),
                 Err(e) => Err(anyhow!("Failed to send ICMP packet: {}", e)),
@@ -1814,7 +2028,7 @@ pub fn send_icmp_packet(target_ip: IpAddr, payload: &[u8], ttl: u8) -> Result<bo
             
             // Set a random identifier and sequence for the echo request
             let ident = random_high_port();
-            let seq = thread_rng().gen::<u16>();
+            let seq = scan_rng().gen::<u16>();
             
             // Create a temporary buffer for the full payload
             let mut full_payload = Vec::with_capacity(4 + extended_payload.len());
@@ -1832,6 +2046,9 @@ pub fn send_icmp_packet(target_ip: IpAddr, payload: &[u8], ttl: u8) -> Result<bo
             // ICMPv6 checksum is calculated by the kernel
             
             // Send the ICMPv6 packet
+            crate::scheduler::spend_blocking(4 + full_payload.len() + 40);
+            crate::audit::record(|| crate::audit::Probe::new("icmp", "icmpv6", target_ip, None, 4 + full_payload.len() + 40)
+                .detail(format!("echo request, hop limit {}", ttl)));
             match tx.send_to(icmp_packet, target_ip) {
                 Ok(_) => Ok(true),
                 Err(e) => Err(anyhow!("Failed to send ICMPv6 packet: {}", e)),
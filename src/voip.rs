@@ -0,0 +1,369 @@
+//! VoIP enumeration.
+//!
+//! SIP servers, PBXes and phones answer an `OPTIONS` request with their agent string and
+//! the methods they support. A `REGISTER` without a `Contact` header only queries the
+//! bindings of an address of record, so it shows whether registration asks for
+//! credentials without registering anything. Both are sent over UDP and TCP on 5060 and
+//! over TLS on 5061.
+//!
+//! With `--rtp-range`, the media ports of the range also get a single RTP packet each.
+//! A media relay that sends RTP back to an unsolicited sender leaks the audio of calls in
+//! progress (RTP Bleed).
+
+use std::collections::HashMap;
+use std::net::{IpAddr, SocketAddr};
+use std::ops::RangeInclusive;
+use std::sync::Arc;
+use std::time::Duration;
+
+use anyhow::{anyhow, Context, Result};
+use lazy_static::lazy_static;
+use log::{debug, info, warn};
+use parking_lot::RwLock;
+use rand::Rng;
+use tokio::io::{AsyncRead, AsyncReadExt, AsyncWrite, AsyncWriteExt};
+use tokio::time::timeout;
+
+use crate::models::{Confidence, Finding, PortResult, PortStatus, SipInfo};
+
+/// Service category SIP and media ports are grouped under
+pub const CATEGORY: &str = "voip";
+
+/// Well-known SIP ports, with whether they use TLS
+const SIP_PORTS: [(u16, bool); 3] = [(5060, false), (5080, false), (5061, true)];
+
+/// Estimated size of a TLS handshake, counted against the scan budget
+const TLS_HANDSHAKE_BYTES: usize = 700;
+
+/// Extension whose bindings the `REGISTER` asks for
+const REGISTER_USER: &str = "100";
+
+/// Most media ports `--rtp-range` may cover
+const MAX_RTP_PORTS: usize = 2000;
+
+/// Largest SIP reply read
+const MAX_REPLY: usize = 16 * 1024;
+
+lazy_static! {
+    /// Media ports to test for RTP relaying, from `--rtp-range`
+    static ref RTP_RANGE: RwLock<Arc<Option<RangeInclusive<u16>>>> = RwLock::new(Arc::new(None));
+}
+
+/// Set the media port range tested for RTP relaying
+///
+/// # Arguments
+/// * `spec` - `START-END`, e.g. `10000-20000`
+///
+/// # Returns
+/// * `Result<()>` - An error for a malformed or oversized range
+pub fn set_rtp_range(spec: &str) -> Result<()> {
+    let (start, end) = spec.split_once('-').ok_or_else(|| anyhow!("expected START-END, got '{}'", spec))?;
+    let start: u16 = start.trim().parse().with_context(|| format!("invalid start port '{}'", start))?;
+    let end: u16 = end.trim().parse().with_context(|| format!("invalid end port '{}'", end))?;
+    if start > end {
+        return Err(anyhow!("range {}-{} is reversed", start, end));
+    }
+    // RTP uses the even ports; the odd ones carry RTCP
+    if (start..=end).step_by(2).count() > MAX_RTP_PORTS {
+        return Err(anyhow!("range {}-{} holds more than {} media ports", start, end, MAX_RTP_PORTS));
+    }
+    *RTP_RANGE.write() = Arc::new(Some(start..=end));
+    Ok(())
+}
+
+fn rtp_range() -> Option<RangeInclusive<u16>> {
+    RTP_RANGE.read().as_ref().clone()
+}
+
+/// Parsed SIP response: status code and headers (lowercase names)
+struct SipResponse {
+    code: u16,
+    status_line: String,
+    headers: HashMap<String, String>,
+}
+
+impl SipResponse {
+    fn parse(reply: &[u8]) -> Option<Self> {
+        let text = String::from_utf8_lossy(reply);
+        let mut lines = text.lines();
+        let status_line = lines.next()?.trim().to_string();
+        if !status_line.starts_with("SIP/2.0 ") {
+            return None;
+        }
+        let code = status_line.split_whitespace().nth(1)?.parse().ok()?;
+        let headers = lines.take_while(|line| !line.trim().is_empty())
+            .filter_map(|line| line.split_once(':'))
+            .map(|(name, value)| (name.trim().to_ascii_lowercase(), value.trim().to_string()))
+            .collect();
+        Some(SipResponse { code, status_line, headers })
+    }
+
+    fn header(&self, name: &str) -> Option<String> {
+        self.headers.get(name).cloned().filter(|value| !value.is_empty())
+    }
+}
+
+/// A SIP request from a random dialog
+fn sip_request(method: &str, transport: &str, target: SocketAddr, local: SocketAddr, to_user: Option<&str>, cseq: u32) -> String {
+    let mut rng = crate::utils::scan_rng();
+    let branch: u64 = rng.gen();
+    let tag: u32 = rng.gen();
+    let call_id: u64 = rng.gen();
+    let host = match target.ip() {
+        IpAddr::V6(ip) => format!("[{}]", ip),
+        IpAddr::V4(ip) => ip.to_string(),
+    };
+    let to = to_user.map(|user| format!("sip:{}@{}", user, host)).unwrap_or_else(|| format!("sip:{}", host));
+    let mut request = format!(
+        "{method} sip:{host} SIP/2.0\r\n\
+         Via: SIP/2.0/{transport} {local};branch=z9hG4bK{branch:x};rport\r\n\
+         Max-Forwards: 70\r\n\
+         From: <{to}>;tag={tag:x}\r\n\
+         To: <{to}>\r\n\
+         Call-ID: {call_id:x}@{local_ip}\r\n\
+         CSeq: {cseq} {method}\r\n",
+        method = method, host = host, transport = transport, local = local, branch = branch,
+        to = to, tag = tag, call_id = call_id, local_ip = local.ip(), cseq = cseq
+    );
+    // A REGISTER without Contact only queries bindings
+    if method == "OPTIONS" {
+        request.push_str(&format!("Contact: <sip:probe@{}>\r\nAccept: application/sdp\r\n", local));
+    }
+    request.push_str("Content-Length: 0\r\n\r\n");
+    request
+}
+
+async fn exchange_udp(target: SocketAddr, method: &str, to_user: Option<&str>, cseq: u32, wait: Duration) -> Result<SipResponse> {
+    let socket = crate::netbind::bind_udp(target.ip()).await?;
+    let request = sip_request(method, "UDP", target, socket.local_addr()?, to_user, cseq);
+    let bytes = request.len() + crate::scheduler::UDP_HEADER_BYTES;
+    crate::scheduler::spend(bytes).await;
+    crate::audit::record(|| crate::audit::Probe::new("voip", "udp", target.ip(), Some(target.port()), bytes)
+        .detail(format!("SIP {}", method)));
+    socket.send_to(request.as_bytes(), target).await?;
+
+    let mut buffer = vec![0u8; MAX_REPLY];
+    loop {
+        let (n, from) = timeout(wait, socket.recv_from(&mut buffer)).await.context("No SIP reply")??;
+        if from.ip() != target.ip() {
+            continue;
+        }
+        let Some(response) = SipResponse::parse(&buffer[..n]) else { return Err(anyhow!("not a SIP reply")) };
+        // Provisional replies come before the final one
+        if response.code >= 200 {
+            return Ok(response);
+        }
+    }
+}
+
+async fn exchange_stream<S: AsyncRead + AsyncWrite + Unpin>(stream: &mut S, request: &str, wait: Duration) -> Result<SipResponse> {
+    timeout(wait, stream.write_all(request.as_bytes())).await.context("Write timed out")??;
+    let mut reply = Vec::new();
+    let mut buf = vec![0u8; 4096];
+    loop {
+        let n = timeout(wait, stream.read(&mut buf)).await.context("No SIP reply")??;
+        if n == 0 {
+            return Err(anyhow!("connection closed"));
+        }
+        reply.extend_from_slice(&buf[..n]);
+        // Drop provisional replies, then wait for a complete final one
+        while let Some(end) = reply.windows(4).position(|w| w == b"\r\n\r\n") {
+            let response = SipResponse::parse(&reply[..end + 4]).ok_or_else(|| anyhow!("not a SIP reply"))?;
+            if response.code >= 200 {
+                return Ok(response);
+            }
+            reply.drain(..end + 4);
+        }
+        if reply.len() > MAX_REPLY {
+            return Err(anyhow!("oversized SIP reply"));
+        }
+    }
+}
+
+async fn exchange_tcp(target: SocketAddr, tls: bool, method: &str, to_user: Option<&str>, cseq: u32, wait: Duration) -> Result<SipResponse> {
+    let mut stream = timeout(wait, crate::netbind::connect_tcp(target)).await.context("Connect timed out")??;
+    let request = sip_request(method, if tls { "TLS" } else { "TCP" }, target, stream.local_addr()?, to_user, cseq);
+    let bytes = request.len() + crate::scheduler::TCP_HEADER_BYTES + if tls { TLS_HANDSHAKE_BYTES } else { 0 };
+    crate::scheduler::spend(bytes).await;
+    crate::audit::record(|| crate::audit::Probe::new("voip", "tcp", target.ip(), Some(target.port()), bytes)
+        .detail(format!("SIP {}", method)));
+
+    if tls {
+        let server_name = crate::ssl_config::convert_to_server_name(&target.ip().to_string())
+            .map_err(|e| anyhow!("Invalid server name: {}", e))?
+            .to_owned();
+        // PBXes usually present self-signed certificates
+        let connector = tokio_rustls::TlsConnector::from(crate::ssl_config::create_tls_config(false));
+        let mut tls_stream = timeout(wait, connector.connect(server_name, stream)).await.context("TLS handshake timed out")??;
+        exchange_stream(&mut tls_stream, &request, wait).await
+    } else {
+        exchange_stream(&mut stream, &request, wait).await
+    }
+}
+
+/// Send `OPTIONS` and a bindings query on one port and transport
+async fn probe_sip(target: SocketAddr, transport: &'static str, wait: Duration) -> Result<(SipInfo, Vec<Finding>)> {
+    let send = |method: &'static str, to_user: Option<&'static str>, cseq: u32| async move {
+        match transport {
+            "udp" => exchange_udp(target, method, to_user, cseq, wait).await,
+            "tls" => exchange_tcp(target, true, method, to_user, cseq, wait).await,
+            _ => exchange_tcp(target, false, method, to_user, cseq, wait).await,
+        }
+    };
+
+    let options = send("OPTIONS", None, 1).await?;
+    let mut info = SipInfo {
+        transport: transport.to_string(),
+        status: options.status_line.clone(),
+        agent: options.header("server").or_else(|| options.header("user-agent")),
+        methods: options.header("allow")
+            .map(|allow| allow.split(',').map(|m| m.trim().to_ascii_uppercase()).filter(|m| !m.is_empty()).collect())
+            .unwrap_or_default(),
+        ..SipInfo::default()
+    };
+
+    let mut vulns = Vec::new();
+    match send("REGISTER", Some(REGISTER_USER), 2).await {
+        Ok(register) => {
+            info.registration = Some(match register.code {
+                200..=299 => {
+                    vulns.push(Finding {
+                        id: "SIP-REGISTER-NO-AUTH".to_string(),
+                        title: "SIP registration without authentication".to_string(),
+                        description: format!("SIP registrar on {}/{} answers REGISTER for sip:{}@ without authentication; extensions can be hijacked and calls redirected",
+                                             target.port(), transport, REGISTER_USER),
+                        severity: "High".to_string(),
+                        confidence: Confidence::Confirmed,
+                        evidence: vec![format!("REGISTER sip:{}@ answered {}", REGISTER_USER, register.code)],
+                        ..Default::default()
+                    });
+                    "open".to_string()
+                }
+                401 | 407 => {
+                    let realm = register.header("www-authenticate").or_else(|| register.header("proxy-authenticate"))
+                        .and_then(|challenge| challenge.split("realm=\"").nth(1).and_then(|rest| rest.split('"').next()).map(str::to_string));
+                    info.realm = realm;
+                    "auth_required".to_string()
+                }
+                _ => format!("rejected: {}", register.status_line),
+            });
+        }
+        Err(e) => debug!("[VoIP] REGISTER to {}/{} failed: {}", target, transport, e),
+    }
+    Ok((info, vulns))
+}
+
+/// RTP packet with a random SSRC and a short silent G.711 payload
+fn rtp_packet() -> Vec<u8> {
+    let mut rng = crate::utils::scan_rng();
+    let sequence: u16 = rng.gen();
+    let timestamp: u32 = rng.gen();
+    let ssrc: u32 = rng.gen();
+    let mut packet = vec![0x80, 0x00];
+    packet.extend_from_slice(&sequence.to_be_bytes());
+    packet.extend_from_slice(&timestamp.to_be_bytes());
+    packet.extend_from_slice(&ssrc.to_be_bytes());
+    packet.extend_from_slice(&[0xff; 160]);
+    packet
+}
+
+/// Media ports of the range that send RTP back to an unsolicited sender
+async fn find_rtp_relays(target: IpAddr, range: RangeInclusive<u16>, wait: Duration) -> Vec<u16> {
+    let mut relays = Vec::new();
+    for port in range.filter(|port| port % 2 == 0) {
+        let addr = SocketAddr::new(target, port);
+        let Ok(socket) = crate::netbind::bind_udp(target).await else { continue };
+        let packet = rtp_packet();
+        crate::scheduler::spend(packet.len() + crate::scheduler::UDP_HEADER_BYTES).await;
+        crate::audit::record(|| crate::audit::Probe::new("voip", "udp", target, Some(port), packet.len() + crate::scheduler::UDP_HEADER_BYTES)
+            .detail("RTP relay test"));
+        if socket.send_to(&packet, addr).await.is_err() {
+            continue;
+        }
+        let mut buffer = [0u8; 2048];
+        // RTP version 2 from the media port means the relay latched onto us
+        if let Ok(Ok((n, from))) = timeout(wait, socket.recv_from(&mut buffer)).await {
+            if from == addr && n >= 12 && buffer[0] >> 6 == 2 {
+                warn!("[VoIP] {} relays RTP to an unsolicited sender", addr);
+                relays.push(port);
+            }
+        }
+    }
+    relays
+}
+
+/// Ports of a host whose identified service is SIP or RTP
+///
+/// # Arguments
+/// * `results` - Port results of one host
+///
+/// # Returns
+/// * `Vec<u16>` - Sorted ports for the "voip" category
+pub fn category_ports(results: &HashMap<u16, PortResult>) -> Vec<u16> {
+    let mut ports: Vec<u16> = results.iter()
+        .filter(|(_, r)| !r.sip.is_empty() || r.service.as_deref().is_some_and(|service| {
+            let service = service.to_ascii_lowercase();
+            service.starts_with("sip") || service.starts_with("rtp")
+        }))
+        .map(|(&port, _)| port)
+        .collect();
+    ports.sort_unstable();
+    ports
+}
+
+/// Probe the SIP ports of a host and, with `--rtp-range`, its media relay
+///
+/// # Arguments
+/// * `target` - Host to check
+/// * `results` - Port results after detection; open TCP ports named SIP or on a SIP port are probed
+/// * `udp_ports` - Ports in scope for UDP; SIP over UDP is tried on the well-known ones
+/// * `wait` - Reply timeout
+///
+/// # Returns
+/// * `Vec<(u16, SipInfo, Vec<Finding>)>` - What each answering port and transport revealed
+pub async fn check_voip(target: IpAddr, results: &HashMap<u16, PortResult>, udp_ports: &[u16], wait: Duration) -> Vec<(u16, SipInfo, Vec<Finding>)> {
+    let mut probes: Vec<(u16, &'static str)> = results.iter()
+        .filter(|(_, r)| r.tcp_states.values().any(|&s| s == PortStatus::Open))
+        .filter_map(|(&port, r)| {
+            let known = SIP_PORTS.iter().find(|(sip, _)| *sip == port).map(|(_, tls)| *tls);
+            let named = r.service.as_deref().is_some_and(|s| s.to_ascii_lowercase().starts_with("sip"));
+            match (known, named) {
+                (Some(tls), _) => Some((port, if tls { "tls" } else { "tcp" })),
+                (None, true) => Some((port, if r.cert_info.is_some() { "tls" } else { "tcp" })),
+                (None, false) => None,
+            }
+        })
+        .collect();
+    probes.extend(SIP_PORTS.iter().filter(|(port, tls)| !tls && udp_ports.contains(port)).map(|(port, _)| (*port, "udp")));
+    probes.sort_unstable();
+
+    let mut found: Vec<(u16, SipInfo, Vec<Finding>)> = Vec::new();
+    for (port, transport) in probes {
+        let addr = SocketAddr::new(target, port);
+        info!("[VoIP] Sending SIP OPTIONS to {}/{}", addr, transport);
+        match probe_sip(addr, transport, wait).await {
+            Ok((info, vulns)) => found.push((port, info, vulns)),
+            Err(e) => debug!("[VoIP] {}/{} did not answer SIP: {}", addr, transport, e),
+        }
+    }
+
+    if let Some(range) = rtp_range().filter(|_| !found.is_empty()) {
+        info!("[VoIP] Testing media ports {}-{} of {} for RTP relaying", range.start(), range.end(), target);
+        let relays = find_rtp_relays(target, range, wait).await;
+        if let Some((_, info, vulns)) = found.first_mut().filter(|_| !relays.is_empty()) {
+            vulns.push(Finding {
+                id: "RTP-RELAY".to_string(),
+                title: "RTP Bleed".to_string(),
+                description: format!("Media ports {} send RTP back to an unsolicited sender (RTP Bleed); audio of calls in progress can be captured",
+                                     relays.iter().map(|p| p.to_string()).collect::<Vec<_>>().join(", ")),
+                severity: "High".to_string(),
+                confidence: Confidence::Confirmed,
+                evidence: relays.iter().map(|p| format!("RTP returned from port {}", p)).collect(),
+                references: vec!["https://www.rtpbleed.com/".to_string()],
+                ..Default::default()
+            });
+            info.rtp_relays = relays;
+        }
+    }
+    found
+}
@@ -0,0 +1,804 @@
+//! VPN endpoint detection.
+//!
+//! Dedicated probes for the VPN services that generic banner grabbing misses:
+//! - IKE/ISAKMP on UDP 500 and 4500 (NAT-T), with IKEv1 transform enumeration,
+//!   aggressive mode detection and an IKEv2 fallback
+//! - PPTP on TCP 1723 (Start-Control-Connection handshake)
+//! - OpenVPN on UDP/TCP 1194 (P_CONTROL_HARD_RESET handshake)
+//!
+//! GRE and ESP/AH endpoints (the data planes behind PPTP and IPsec) are found with the
+//! IP protocol scan type rather than here.
+
+use std::net::{IpAddr, SocketAddr};
+use std::time::Duration;
+
+use anyhow::{anyhow, Result};
+use log::{debug, info};
+use rand::Rng;
+use serde_json::json;
+use tokio::io::AsyncReadExt;
+use tokio::net::UdpSocket;
+use tokio::time::timeout;
+
+use crate::models::{Confidence, Finding};
+
+/// Transport a VPN probe runs over
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum VpnTransport {
+    Tcp,
+    Udp,
+}
+
+/// Well-known VPN ports and the transport each probe uses
+pub const VPN_PORTS: &[(u16, VpnTransport)] = &[
+    (500, VpnTransport::Udp),
+    (4500, VpnTransport::Udp),
+    (1723, VpnTransport::Tcp),
+    (1194, VpnTransport::Udp),
+    (1194, VpnTransport::Tcp),
+];
+
+/// What a VPN probe learned about an endpoint
+#[derive(Debug, Clone)]
+pub struct VpnFinding {
+    /// Port the service answered on
+    pub port: u16,
+    /// Transport the service answered on
+    pub transport: VpnTransport,
+    /// Service name ("isakmp", "pptp", "openvpn")
+    pub service: String,
+    /// Version or implementation string, if one could be derived
+    pub version: Option<String>,
+    /// Negotiated parameters and other protocol details
+    pub details: serde_json::Value,
+    /// Weaknesses exposed by the endpoint (aggressive mode, weak transforms, PPTP)
+    pub vulns: Vec<Finding>,
+}
+
+// --- IKE / ISAKMP ---
+
+/// ISAKMP payload types (RFC 2408 / RFC 7296)
+const PAYLOAD_NONE: u8 = 0;
+const PAYLOAD_SA: u8 = 1;
+const PAYLOAD_TRANSFORM: u8 = 3;
+const PAYLOAD_KE: u8 = 4;
+const PAYLOAD_ID: u8 = 5;
+const PAYLOAD_HASH: u8 = 8;
+const PAYLOAD_NONCE: u8 = 10;
+const PAYLOAD_NOTIFY: u8 = 11;
+const PAYLOAD_VENDOR_ID: u8 = 13;
+const PAYLOAD_V2_SA: u8 = 33;
+const PAYLOAD_V2_KE: u8 = 34;
+const PAYLOAD_V2_NONCE: u8 = 40;
+const PAYLOAD_V2_NOTIFY: u8 = 41;
+
+/// ISAKMP exchange types
+const EXCHANGE_MAIN_MODE: u8 = 2;
+const EXCHANGE_AGGRESSIVE: u8 = 4;
+const EXCHANGE_IKE_SA_INIT: u8 = 34;
+
+/// IKEv1 transform candidate (Phase 1 attributes)
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+struct IkeTransform {
+    /// Encryption algorithm and key length in bits (0 = fixed length cipher)
+    encryption: (u16, u16),
+    hash: u16,
+    auth: u16,
+    group: u16,
+}
+
+/// Encryption algorithms offered during enumeration: (id, key length)
+const IKE_ENCRYPTION: &[(u16, u16)] = &[(7, 256), (7, 128), (5, 0), (1, 0)];
+/// Hash algorithms offered: SHA2-256, SHA1, MD5
+const IKE_HASH: &[u16] = &[4, 2, 1];
+/// Authentication methods offered: pre-shared key, RSA signatures
+const IKE_AUTH: &[u16] = &[1, 3];
+/// Diffie-Hellman groups offered: 2048, 1536, 1024 and 768 bit MODP
+const IKE_GROUPS: &[u16] = &[14, 5, 2, 1];
+
+/// Maximum number of rounds used to enumerate accepted transforms
+const MAX_TRANSFORM_ROUNDS: usize = 6;
+
+/// Known IKE Vendor ID payloads (MD5 hashes or their prefixes) and what they reveal
+const IKE_VENDOR_IDS: &[(&str, &str)] = &[
+    ("4a131c81070358455c5728f20e95452f", "RFC 3947 NAT-T"),
+    ("90cb80913ebb696e086381b5ec427b1f", "draft-ietf-ipsec-nat-t-ike-02"),
+    ("afcad71368a1f1c96b8696fc77570100", "Dead Peer Detection v1.0"),
+    ("12f5f28c457168a9702d9fe274cc0100", "Cisco Unity"),
+    ("09002689dfd6b712", "XAUTH"),
+    ("1e2b516905991c7d7c96fcbfb587e461", "MS NT5 ISAKMPOAKLEY (Windows)"),
+    ("4048b7d56ebce88525e7de7f00d6c2d3", "IKE Fragmentation"),
+    ("4865617274426561745f4e6f74696679", "Heartbeat Notify (Check Point)"),
+    ("f4ed19e0c114eb516faaac0ee37daf2807b4381f", "Check Point FireWall-1"),
+    ("882fe56d6fd20dbc2251613b2ebe5beb", "strongSwan"),
+    ("4f45", "OpenSwan/Libreswan"),
+    ("166f932d55eb64d8e4df4fd37e2313f0d0fd8451", "Juniper NetScreen"),
+    ("1d6e178f6c2c0be284985465450fe9d4", "Fortinet FortiGate"),
+    ("8299031757a36082c6a621de00000000", "Fortinet FortiGate"),
+    ("f758f22668750f03b08df6ebe1d00403", "SonicWall"),
+    ("bfc22e9856ba9936", "Cisco Copyright (Cisco IOS)"),
+    ("1f07f70eaa6514d3b0fa96542a500100", "Cisco FlexVPN"),
+];
+
+fn ike_encryption_name(id: u16, key_len: u16) -> String {
+    let name = match id {
+        1 => "DES",
+        2 => "IDEA",
+        3 => "Blowfish",
+        5 => "3DES",
+        6 => "CAST",
+        7 => "AES",
+        _ => return format!("enc-{}", id),
+    };
+    if key_len > 0 { format!("{}-{}", name, key_len) } else { name.to_string() }
+}
+
+fn ike_hash_name(id: u16) -> String {
+    match id {
+        1 => "MD5".to_string(),
+        2 => "SHA1".to_string(),
+        3 => "Tiger".to_string(),
+        4 => "SHA2-256".to_string(),
+        5 => "SHA2-384".to_string(),
+        6 => "SHA2-512".to_string(),
+        _ => format!("hash-{}", id),
+    }
+}
+
+fn ike_auth_name(id: u16) -> String {
+    match id {
+        1 => "PSK".to_string(),
+        2 => "DSS".to_string(),
+        3 => "RSA-Sig".to_string(),
+        4 => "RSA-Enc".to_string(),
+        5 => "RSA-RevEnc".to_string(),
+        64221 => "Hybrid-RSA".to_string(),
+        65001 => "XAUTH-PSK".to_string(),
+        65005 => "XAUTH-RSA".to_string(),
+        _ => format!("auth-{}", id),
+    }
+}
+
+fn ike_group_name(id: u16) -> String {
+    match id {
+        1 => "MODP-768".to_string(),
+        2 => "MODP-1024".to_string(),
+        5 => "MODP-1536".to_string(),
+        14 => "MODP-2048".to_string(),
+        15 => "MODP-3072".to_string(),
+        19 => "ECP-256".to_string(),
+        20 => "ECP-384".to_string(),
+        _ => format!("group-{}", id),
+    }
+}
+
+impl IkeTransform {
+    fn describe(&self) -> String {
+        format!("{}/{}/{}/{}",
+                ike_encryption_name(self.encryption.0, self.encryption.1),
+                ike_hash_name(self.hash),
+                ike_auth_name(self.auth),
+                ike_group_name(self.group))
+    }
+
+    /// Reasons this transform is considered weak, if any
+    fn weaknesses(&self) -> Vec<&'static str> {
+        let mut weak = Vec::new();
+        match self.encryption.0 {
+            1 => weak.push("single DES encryption"),
+            2 | 3 | 6 => weak.push("legacy 64-bit block cipher"),
+            _ => {}
+        }
+        if self.hash == 1 {
+            weak.push("MD5 integrity");
+        }
+        if self.group == 1 || self.group == 2 {
+            weak.push("Diffie-Hellman group below 1536 bits");
+        }
+        weak
+    }
+
+    /// Encode as an IKEv1 transform payload body (after the generic header)
+    fn encode(&self, number: u8) -> Vec<u8> {
+        let mut body = vec![number, 1 /* KEY_IKE */, 0, 0];
+        let mut tv = |attr_type: u16, value: u16| {
+            body.extend_from_slice(&(0x8000 | attr_type).to_be_bytes());
+            body.extend_from_slice(&value.to_be_bytes());
+        };
+        tv(1, self.encryption.0);
+        tv(2, self.hash);
+        tv(3, self.auth);
+        tv(4, self.group);
+        tv(11, 1); // Life type: seconds
+        if self.encryption.1 > 0 {
+            tv(14, self.encryption.1);
+        }
+        // Life duration as a TLV attribute (4 bytes: 28800 seconds)
+        body.extend_from_slice(&12u16.to_be_bytes());
+        body.extend_from_slice(&4u16.to_be_bytes());
+        body.extend_from_slice(&28800u32.to_be_bytes());
+        body
+    }
+}
+
+/// Every transform combination offered during enumeration, strongest first
+fn ike_candidate_transforms() -> Vec<IkeTransform> {
+    let mut transforms = Vec::new();
+    for &encryption in IKE_ENCRYPTION {
+        for &hash in IKE_HASH {
+            for &auth in IKE_AUTH {
+                for &group in IKE_GROUPS {
+                    transforms.push(IkeTransform { encryption, hash, auth, group });
+                }
+            }
+        }
+    }
+    transforms
+}
+
+/// Append a generic payload header plus body to a message
+fn push_payload(message: &mut Vec<u8>, next_payload: u8, body: &[u8]) {
+    message.push(next_payload);
+    message.push(0);
+    message.extend_from_slice(&((body.len() + 4) as u16).to_be_bytes());
+    message.extend_from_slice(body);
+}
+
+/// Build an IKEv1 SA payload body offering the given transforms in one proposal
+fn ikev1_sa_body(transforms: &[IkeTransform]) -> Vec<u8> {
+    let mut proposal = vec![1 /* proposal # */, 1 /* PROTO_ISAKMP */, 0 /* SPI size */, transforms.len() as u8];
+    for (index, transform) in transforms.iter().enumerate() {
+        let next = if index + 1 == transforms.len() { PAYLOAD_NONE } else { PAYLOAD_TRANSFORM };
+        push_payload(&mut proposal, next, &transform.encode(index as u8 + 1));
+    }
+
+    let mut sa = Vec::new();
+    sa.extend_from_slice(&1u32.to_be_bytes()); // DOI: IPsec
+    sa.extend_from_slice(&1u32.to_be_bytes()); // Situation: identity only
+    push_payload(&mut sa, PAYLOAD_NONE, &proposal);
+    sa
+}
+
+/// Build the fixed ISAKMP header; the total length is patched in by `finish_message`
+fn isakmp_header(initiator_cookie: &[u8; 8], first_payload: u8, version: u8, exchange: u8, flags: u8) -> Vec<u8> {
+    let mut header = Vec::with_capacity(28);
+    header.extend_from_slice(initiator_cookie);
+    header.extend_from_slice(&[0u8; 8]); // Responder cookie
+    header.push(first_payload);
+    header.push(version);
+    header.push(exchange);
+    header.push(flags);
+    header.extend_from_slice(&[0u8; 4]); // Message ID
+    header.extend_from_slice(&[0u8; 4]); // Length (patched)
+    header
+}
+
+fn finish_message(mut message: Vec<u8>) -> Vec<u8> {
+    let len = message.len() as u32;
+    message[24..28].copy_from_slice(&len.to_be_bytes());
+    message
+}
+
+/// IKEv1 Main Mode first message offering `transforms`
+fn build_main_mode(cookie: &[u8; 8], transforms: &[IkeTransform]) -> Vec<u8> {
+    let mut message = isakmp_header(cookie, PAYLOAD_SA, 0x10, EXCHANGE_MAIN_MODE, 0);
+    push_payload(&mut message, PAYLOAD_NONE, &ikev1_sa_body(transforms));
+    finish_message(message)
+}
+
+/// IKEv1 Aggressive Mode first message (SA, KE, Nonce, ID) with a PSK proposal
+///
+/// A responder that answers with a HASH payload exposes material for offline
+/// cracking of the pre-shared key.
+fn build_aggressive_mode(cookie: &[u8; 8], transforms: &[IkeTransform], group_bytes: usize) -> Vec<u8> {
+    let mut rng = crate::utils::scan_rng();
+    let mut message = isakmp_header(cookie, PAYLOAD_SA, 0x10, EXCHANGE_AGGRESSIVE, 0);
+    push_payload(&mut message, PAYLOAD_KE, &ikev1_sa_body(transforms));
+
+    let ke: Vec<u8> = (0..group_bytes).map(|_| rng.gen()).collect();
+    push_payload(&mut message, PAYLOAD_NONCE, &ke);
+
+    let nonce: Vec<u8> = (0..20).map(|_| rng.gen()).collect();
+    push_payload(&mut message, PAYLOAD_ID, &nonce);
+
+    // ID: ID_USER_FQDN with a generic group name
+    let mut id = vec![3, 17, 0x01, 0xf4];
+    id.extend_from_slice(b"vpn@example.com");
+    push_payload(&mut message, PAYLOAD_NONE, &id);
+    finish_message(message)
+}
+
+/// IKEv2 IKE_SA_INIT offering AES-CBC-256/SHA2-256/MODP-2048
+fn build_ikev2_sa_init(cookie: &[u8; 8]) -> Vec<u8> {
+    let mut rng = crate::utils::scan_rng();
+    let mut message = isakmp_header(cookie, PAYLOAD_V2_SA, 0x20, EXCHANGE_IKE_SA_INIT, 0x08);
+
+    // Transform substructures: type, reserved, id (+ key length attribute for AES)
+    let transforms: [(u8, u16, Option<u16>); 4] = [(1, 12, Some(256)), (2, 2, None), (3, 12, None), (4, 14, None)];
+    let mut proposal = vec![1 /* proposal # */, 1 /* IKE */, 0 /* SPI size */, transforms.len() as u8];
+    for (index, (transform_type, id, key_len)) in transforms.iter().enumerate() {
+        let mut body = vec![*transform_type, 0];
+        body.extend_from_slice(&id.to_be_bytes());
+        if let Some(bits) = key_len {
+            body.extend_from_slice(&0x800eu16.to_be_bytes());
+            body.extend_from_slice(&bits.to_be_bytes());
+        }
+        push_payload(&mut proposal, if index + 1 == transforms.len() { 0 } else { 3 }, &body);
+    }
+    let mut sa = Vec::new();
+    push_payload(&mut sa, 0, &proposal);
+    push_payload(&mut message, PAYLOAD_V2_KE, &sa);
+
+    let mut ke = vec![0, 14, 0, 0];
+    ke.extend((0..256).map(|_| rng.gen::<u8>()));
+    push_payload(&mut message, PAYLOAD_V2_NONCE, &ke);
+
+    let nonce: Vec<u8> = (0..32).map(|_| rng.gen()).collect();
+    push_payload(&mut message, PAYLOAD_NONE, &nonce);
+    finish_message(message)
+}
+
+/// Parsed view of an ISAKMP response
+#[derive(Debug, Default)]
+struct IkeResponse {
+    version: u8,
+    exchange: u8,
+    /// Transform the responder selected (IKEv1)
+    accepted: Option<IkeTransform>,
+    /// Implementations identified from Vendor ID payloads
+    vendor_ids: Vec<String>,
+    /// Notify message types
+    notifications: Vec<u16>,
+    /// A HASH payload came back (aggressive mode leaks PSK-derived material)
+    has_hash: bool,
+}
+
+/// Walk the payload chain of an ISAKMP message that answers `cookie`
+fn parse_ike_response(data: &[u8], cookie: &[u8; 8]) -> Option<IkeResponse> {
+    if data.len() < 28 || &data[0..8] != cookie {
+        return None;
+    }
+
+    let mut response = IkeResponse {
+        version: data[17],
+        exchange: data[18],
+        ..Default::default()
+    };
+
+    let mut next = data[16];
+    let mut offset = 28;
+    while next != PAYLOAD_NONE && offset + 4 <= data.len() {
+        let payload_type = next;
+        next = data[offset];
+        let len = u16::from_be_bytes([data[offset + 2], data[offset + 3]]) as usize;
+        if len < 4 || offset + len > data.len() {
+            break;
+        }
+        let body = &data[offset + 4..offset + len];
+
+        match payload_type {
+            PAYLOAD_SA => response.accepted = parse_ikev1_sa(body),
+            PAYLOAD_HASH => response.has_hash = true,
+            PAYLOAD_VENDOR_ID => {
+                let hex: String = body.iter().map(|b| format!("{:02x}", b)).collect();
+                let name = IKE_VENDOR_IDS.iter()
+                    .find(|(prefix, _)| hex.starts_with(prefix))
+                    .map(|(_, name)| name.to_string())
+                    .unwrap_or_else(|| format!("unknown ({})", hex));
+                response.vendor_ids.push(name);
+            }
+            PAYLOAD_NOTIFY if body.len() >= 8 => {
+                // IKEv1: DOI(4) protocol(1) spi size(1) type(2)
+                response.notifications.push(u16::from_be_bytes([body[6], body[7]]));
+            }
+            PAYLOAD_V2_NOTIFY if body.len() >= 4 => {
+                // IKEv2: protocol(1) spi size(1) type(2)
+                response.notifications.push(u16::from_be_bytes([body[2], body[3]]));
+            }
+            _ => {}
+        }
+        offset += len;
+    }
+
+    Some(response)
+}
+
+/// Extract the single transform from a responder's IKEv1 SA payload
+fn parse_ikev1_sa(body: &[u8]) -> Option<IkeTransform> {
+    // DOI(4) + situation(4), then the proposal payload header(4) + proposal fields(4)
+    let proposal = body.get(8..)?;
+    let spi_size = *proposal.get(6)? as usize;
+    let transform = proposal.get(8 + spi_size..)?;
+    // Transform payload: header(4), number(1), id(1), reserved(2), attributes
+    let mut attrs = transform.get(8..)?;
+
+    let mut parsed = IkeTransform { encryption: (0, 0), hash: 0, auth: 0, group: 0 };
+    while attrs.len() >= 4 {
+        let attr_type = u16::from_be_bytes([attrs[0], attrs[1]]);
+        let value_field = u16::from_be_bytes([attrs[2], attrs[3]]);
+        let (value, consumed) = if attr_type & 0x8000 != 0 {
+            (value_field, 4)
+        } else {
+            // Variable length attribute; only the life duration is sent this way
+            (0, 4 + value_field as usize)
+        };
+        match attr_type & 0x7fff {
+            1 => parsed.encryption.0 = value,
+            2 => parsed.hash = value,
+            3 => parsed.auth = value,
+            4 => parsed.group = value,
+            14 => parsed.encryption.1 = value,
+            _ => {}
+        }
+        if consumed > attrs.len() {
+            break;
+        }
+        attrs = &attrs[consumed..];
+    }
+
+    if parsed.encryption.0 == 0 { None } else { Some(parsed) }
+}
+
+/// Send one IKE message and wait for the matching response
+async fn ike_exchange(socket: &UdpSocket, port: u16, message: &[u8], cookie: &[u8; 8], timeout_duration: Duration) -> Result<Option<IkeResponse>> {
+    // NAT-T (4500) prefixes IKE messages with a four byte non-ESP marker
+    let mut packet = Vec::with_capacity(message.len() + 4);
+    if port == 4500 {
+        packet.extend_from_slice(&[0u8; 4]);
+    }
+    packet.extend_from_slice(message);
+    crate::scheduler::spend(packet.len() + crate::scheduler::UDP_HEADER_BYTES).await;
+    if let Ok(peer) = socket.peer_addr() {
+        crate::audit::record(|| crate::audit::Probe::new("vpn-ike", "udp", peer.ip(), Some(port), packet.len() + crate::scheduler::UDP_HEADER_BYTES));
+    }
+    socket.send(&packet).await?;
+
+    let mut buf = vec![0u8; 4096];
+    let deadline = tokio::time::Instant::now() + timeout_duration;
+    loop {
+        let remaining = deadline.saturating_duration_since(tokio::time::Instant::now());
+        let len = match timeout(remaining, socket.recv(&mut buf)).await {
+            Ok(Ok(len)) => len,
+            Ok(Err(e)) => return Err(anyhow!("IKE receive failed: {}", e)),
+            Err(_) => return Ok(None),
+        };
+        let data = if port == 4500 && len >= 4 && buf[..4] == [0, 0, 0, 0] { &buf[4..len] } else { &buf[..len] };
+        if let Some(response) = parse_ike_response(data, cookie) {
+            return Ok(Some(response));
+        }
+    }
+}
+
+/// Probe an IKE endpoint on UDP 500 or 4500
+///
+/// Enumerates accepted IKEv1 Main Mode transforms by repeatedly offering the full
+/// candidate set minus whatever the responder picked last time, then checks for
+/// Aggressive Mode. Falls back to an IKEv2 IKE_SA_INIT when IKEv1 gets no answer.
+///
+/// # Opsec Considerations
+/// - Each enumeration round is a distinct IKE negotiation and is logged by most VPN gateways
+/// - Aggressive Mode probing sends a fixed identity that stands out in gateway logs
+pub async fn probe_ike(target_ip: IpAddr, port: u16, timeout_duration: Duration) -> Result<Option<VpnFinding>> {
+    let socket = crate::netbind::bind_udp(target_ip).await?;
+    socket.connect(SocketAddr::new(target_ip, port)).await?;
+
+    let mut remaining = ike_candidate_transforms();
+    let mut accepted: Vec<IkeTransform> = Vec::new();
+    let mut vendor_ids: Vec<String> = Vec::new();
+    let mut notifications: Vec<u16> = Vec::new();
+    let mut responded = false;
+
+    // --- Main Mode transform enumeration ---
+    for round in 0..MAX_TRANSFORM_ROUNDS {
+        if remaining.is_empty() {
+            break;
+        }
+        let cookie: [u8; 8] = crate::utils::scan_rng().gen();
+        let Some(response) = ike_exchange(&socket, port, &build_main_mode(&cookie, &remaining), &cookie, timeout_duration).await? else {
+            break;
+        };
+        responded = true;
+        for vid in response.vendor_ids {
+            if !vendor_ids.contains(&vid) {
+                vendor_ids.push(vid);
+            }
+        }
+        notifications.extend(response.notifications);
+
+        match response.accepted {
+            Some(transform) => {
+                debug!("[VPN] {}:{} accepted IKE transform {} (round {})", target_ip, port, transform.describe(), round + 1);
+                remaining.retain(|t| *t != transform);
+                accepted.push(transform);
+            }
+            // NO-PROPOSAL-CHOSEN or other notify: nothing else from this set is acceptable
+            None => break,
+        }
+    }
+
+    // --- Aggressive Mode check ---
+    let mut aggressive_mode = false;
+    if responded {
+        let psk_transforms: Vec<IkeTransform> = accepted.iter().copied()
+            .filter(|t| t.auth == 1)
+            .chain(ike_candidate_transforms().into_iter().filter(|t| t.auth == 1))
+            .take(24)
+            .collect();
+        // The KE payload has to match the DH group of the first proposal the responder may pick
+        let group_bytes = match psk_transforms.first().map(|t| t.group) {
+            Some(1) => 96,
+            Some(2) => 128,
+            Some(5) => 192,
+            _ => 256,
+        };
+        let cookie: [u8; 8] = crate::utils::scan_rng().gen();
+        if let Some(response) = ike_exchange(&socket, port, &build_aggressive_mode(&cookie, &psk_transforms, group_bytes), &cookie, timeout_duration).await? {
+            aggressive_mode = response.exchange == EXCHANGE_AGGRESSIVE && response.has_hash;
+        }
+    }
+
+    // --- IKEv2 fallback ---
+    let mut ikev2 = false;
+    if !responded {
+        let cookie: [u8; 8] = crate::utils::scan_rng().gen();
+        if let Some(response) = ike_exchange(&socket, port, &build_ikev2_sa_init(&cookie), &cookie, timeout_duration).await? {
+            ikev2 = response.version >> 4 == 2;
+            responded = true;
+            notifications.extend(response.notifications);
+            vendor_ids.extend(response.vendor_ids);
+        }
+    }
+
+    if !responded {
+        return Ok(None);
+    }
+
+    let mut vulns = Vec::new();
+    if aggressive_mode {
+        vulns.push(Finding {
+            id: "CVE-2002-1623".to_string(),
+            title: "IKE Aggressive Mode with pre-shared key".to_string(),
+            description: "IKE Aggressive Mode with pre-shared key authentication is enabled; the responder returns a PSK-derived hash that can be cracked offline".to_string(),
+            severity: "High".to_string(),
+            confidence: Confidence::Confirmed,
+            references: vec!["https://nvd.nist.gov/vuln/detail/CVE-2002-1623".to_string()],
+            ..Default::default()
+        });
+    }
+    for transform in &accepted {
+        let weaknesses = transform.weaknesses();
+        if !weaknesses.is_empty() {
+            vulns.push(Finding {
+                id: "IKE-WEAK-TRANSFORM".to_string(),
+                title: "Weak IKE transform accepted".to_string(),
+                description: format!("IKE accepts weak transform {} ({})", transform.describe(), weaknesses.join(", ")),
+                severity: "Medium".to_string(),
+                confidence: Confidence::Confirmed,
+                evidence: vec![transform.describe()],
+                ..Default::default()
+            });
+        }
+    }
+
+    let version = if ikev2 {
+        "IKEv2".to_string()
+    } else {
+        match vendor_ids.iter().find(|v| !v.starts_with("unknown") && !v.contains("NAT-T") && !v.contains("Dead Peer") && !v.contains("Fragmentation")) {
+            Some(implementation) => format!("IKEv1 ({})", implementation),
+            None => "IKEv1".to_string(),
+        }
+    };
+
+    info!("[VPN] IKE endpoint on {}:{}/udp: {} ({} accepted transforms, aggressive mode: {})",
+          target_ip, port, version, accepted.len(), aggressive_mode);
+
+    Ok(Some(VpnFinding {
+        port,
+        transport: VpnTransport::Udp,
+        service: "isakmp".to_string(),
+        version: Some(version),
+        details: json!({
+            "ike_version": if ikev2 { 2 } else { 1 },
+            "nat_t": port == 4500,
+            "accepted_transforms": accepted.iter().map(|t| t.describe()).collect::<Vec<_>>(),
+            "aggressive_mode": aggressive_mode,
+            "vendor_ids": vendor_ids,
+            "notifications": notifications,
+        }),
+        vulns,
+    }))
+}
+
+// --- PPTP ---
+
+/// Probe a PPTP server with a Start-Control-Connection-Request
+///
+/// The reply carries the server's protocol version, firmware revision, hostname
+/// and vendor string.
+pub async fn probe_pptp(target_ip: IpAddr, port: u16, timeout_duration: Duration) -> Result<Option<VpnFinding>> {
+    let mut stream = timeout(timeout_duration, crate::netbind::connect_tcp(SocketAddr::new(target_ip, port))).await
+        .map_err(|_| anyhow!("PPTP connect timed out"))??;
+
+    let mut request = Vec::with_capacity(156);
+    request.extend_from_slice(&156u16.to_be_bytes()); // Length
+    request.extend_from_slice(&1u16.to_be_bytes()); // PPTP message type: control
+    request.extend_from_slice(&0x1A2B3C4Du32.to_be_bytes()); // Magic cookie
+    request.extend_from_slice(&1u16.to_be_bytes()); // Start-Control-Connection-Request
+    request.extend_from_slice(&0u16.to_be_bytes()); // Reserved
+    request.extend_from_slice(&0x0100u16.to_be_bytes()); // Protocol version 1.0
+    request.extend_from_slice(&0u16.to_be_bytes()); // Reserved
+    request.extend_from_slice(&1u32.to_be_bytes()); // Framing: async
+    request.extend_from_slice(&1u32.to_be_bytes()); // Bearer: analog
+    request.extend_from_slice(&0u16.to_be_bytes()); // Max channels
+    request.extend_from_slice(&0u16.to_be_bytes()); // Firmware revision
+    request.resize(156, 0); // Hostname and vendor left empty
+
+    crate::scheduler::metered_write(&mut stream, &request).await?;
+
+    let mut reply = [0u8; 156];
+    if timeout(timeout_duration, stream.read_exact(&mut reply)).await.is_err() {
+        return Ok(None);
+    }
+
+    let magic = u32::from_be_bytes([reply[4], reply[5], reply[6], reply[7]]);
+    let control_type = u16::from_be_bytes([reply[8], reply[9]]);
+    if magic != 0x1A2B3C4D || control_type != 2 {
+        return Ok(None);
+    }
+
+    let text = |bytes: &[u8]| -> String {
+        String::from_utf8_lossy(bytes).trim_end_matches('\0').trim().to_string()
+    };
+    let protocol_version = format!("{}.{}", reply[12], reply[13]);
+    let result_code = reply[14];
+    let firmware = u16::from_be_bytes([reply[26], reply[27]]);
+    let hostname = text(&reply[28..92]);
+    let vendor = text(&reply[92..156]);
+
+    info!("[VPN] PPTP endpoint on {}:{}/tcp: vendor '{}', hostname '{}'", target_ip, port, vendor, hostname);
+
+    let version = if vendor.is_empty() {
+        format!("PPTP {}", protocol_version)
+    } else {
+        format!("PPTP {} ({})", protocol_version, vendor)
+    };
+
+    Ok(Some(VpnFinding {
+        port,
+        transport: VpnTransport::Tcp,
+        service: "pptp".to_string(),
+        version: Some(version),
+        details: json!({
+            "protocol_version": protocol_version,
+            "result_code": result_code,
+            "firmware_revision": firmware,
+            "hostname": hostname,
+            "vendor": vendor,
+        }),
+        vulns: vec![Finding {
+            id: "PPTP-MSCHAPV2".to_string(),
+            title: "PPTP VPN exposed".to_string(),
+            description: "PPTP VPN exposed; MS-CHAPv2 authentication used by PPTP can be cracked offline and MPPE provides weak protection".to_string(),
+            severity: "Medium".to_string(),
+            confidence: Confidence::Firm,
+            references: vec!["https://learn.microsoft.com/en-us/security-updates/securityadvisories/2012/2743314".to_string()],
+            ..Default::default()
+        }],
+    }))
+}
+
+// --- OpenVPN ---
+
+/// OpenVPN opcodes (high five bits of the first byte)
+const OPENVPN_CLIENT_RESET_V2: u8 = 7;
+const OPENVPN_SERVER_RESET_V2: u8 = 8;
+
+/// Probe an OpenVPN server with a P_CONTROL_HARD_RESET_CLIENT_V2
+///
+/// Servers that use `tls-auth` or `tls-crypt` silently drop unauthenticated resets,
+/// so no answer does not rule OpenVPN out.
+pub async fn probe_openvpn(target_ip: IpAddr, port: u16, transport: VpnTransport, timeout_duration: Duration) -> Result<Option<VpnFinding>> {
+    let session_id: [u8; 8] = crate::utils::scan_rng().gen();
+    let mut packet = vec![OPENVPN_CLIENT_RESET_V2 << 3];
+    packet.extend_from_slice(&session_id);
+    packet.push(0); // ACK array length
+    packet.extend_from_slice(&0u32.to_be_bytes()); // Message packet ID
+
+    let mut buf = vec![0u8; 2048];
+    let reply: &[u8] = match transport {
+        VpnTransport::Udp => {
+            let socket = crate::netbind::bind_udp(target_ip).await?;
+            socket.connect(SocketAddr::new(target_ip, port)).await?;
+            crate::scheduler::spend(packet.len() + crate::scheduler::UDP_HEADER_BYTES).await;
+            crate::audit::record(|| crate::audit::Probe::new("vpn-openvpn", "udp", target_ip, Some(port), packet.len() + crate::scheduler::UDP_HEADER_BYTES));
+            socket.send(&packet).await?;
+            match timeout(timeout_duration, socket.recv(&mut buf)).await {
+                Ok(Ok(len)) => &buf[..len],
+                _ => return Ok(None),
+            }
+        }
+        VpnTransport::Tcp => {
+            let mut stream = timeout(timeout_duration, crate::netbind::connect_tcp(SocketAddr::new(target_ip, port))).await
+                .map_err(|_| anyhow!("OpenVPN connect timed out"))??;
+            // TCP mode frames each packet with a two byte length
+            let mut framed = (packet.len() as u16).to_be_bytes().to_vec();
+            framed.extend_from_slice(&packet);
+            crate::scheduler::metered_write(&mut stream, &framed).await?;
+            match timeout(timeout_duration, stream.read(&mut buf)).await {
+                Ok(Ok(len)) if len > 2 => &buf[2..len],
+                _ => return Ok(None),
+            }
+        }
+    };
+
+    if reply.len() < 14 || reply[0] >> 3 != OPENVPN_SERVER_RESET_V2 {
+        return Ok(None);
+    }
+
+    // Server session ID, then an ACK array that should acknowledge our packet ID
+    let server_session: String = reply[1..9].iter().map(|b| format!("{:02x}", b)).collect();
+    let ack_count = reply[9] as usize;
+    let acks_us = ack_count > 0 && reply.len() >= 10 + ack_count * 4 + 8
+        && reply[10 + ack_count * 4..10 + ack_count * 4 + 8] == session_id;
+
+    let proto = if transport == VpnTransport::Udp { "udp" } else { "tcp" };
+    info!("[VPN] OpenVPN endpoint on {}:{}/{}", target_ip, port, proto);
+
+    Ok(Some(VpnFinding {
+        port,
+        transport,
+        service: "openvpn".to_string(),
+        version: Some("OpenVPN (no tls-auth)".to_string()),
+        details: json!({
+            "transport": proto,
+            "server_session_id": server_session,
+            "acknowledged_client_session": acks_us,
+            "tls_auth": false,
+        }),
+        vulns: Vec::new(),
+    }))
+}
+
+/// Run every VPN probe applicable to the given ports
+///
+/// # Arguments
+/// * `target_ip` - Target address
+/// * `udp_ports` - UDP ports in scope (UDP probes only run on these)
+/// * `open_tcp_ports` - TCP ports known to be open (TCP probes only run on these)
+/// * `timeout_duration` - Per-exchange timeout
+///
+/// # Returns
+/// * `Vec<VpnFinding>` - One finding per port/transport that answered
+pub async fn detect_vpn_endpoints(
+    target_ip: IpAddr,
+    udp_ports: &[u16],
+    open_tcp_ports: &[u16],
+    timeout_duration: Duration,
+) -> Vec<VpnFinding> {
+    let mut findings = Vec::new();
+
+    for &(port, transport) in VPN_PORTS {
+        let in_scope = match transport {
+            VpnTransport::Udp => udp_ports.contains(&port),
+            VpnTransport::Tcp => open_tcp_ports.contains(&port),
+        };
+        if !in_scope {
+            continue;
+        }
+
+        let outcome = match (port, transport) {
+            (500 | 4500, VpnTransport::Udp) => probe_ike(target_ip, port, timeout_duration).await,
+            (1723, VpnTransport::Tcp) => probe_pptp(target_ip, port, timeout_duration).await,
+            (1194, _) => probe_openvpn(target_ip, port, transport, timeout_duration).await,
+            _ => Ok(None),
+        };
+
+        match outcome {
+            Ok(Some(finding)) => findings.push(finding),
+            Ok(None) => debug!("[VPN] No VPN response on {}:{} ({:?})", target_ip, port, transport),
+            Err(e) => debug!("[VPN] Probe of {}:{} ({:?}) failed: {}", target_ip, port, transport, e),
+        }
+    }
+
+    findings
+}
@@ -0,0 +1,230 @@
+//! Web security hygiene.
+//!
+//! Every web port gets one `GET /`, over TLS where the port speaks it (banner grabbing
+//! does not, so HTTPS sites are otherwise never read). The response is scored by
+//! `http_analyzer::assess_hygiene` on HSTS, Content-Security-Policy, framing protection,
+//! cookie flags, version disclosure and, from the TLS scan, OCSP stapling. The score and
+//! the problems behind it go into the port's `security_posture`, so sites can be compared
+//! and sorted at a glance.
+
+use std::collections::HashMap;
+use std::net::{IpAddr, SocketAddr};
+use std::time::Duration;
+
+use anyhow::{anyhow, Context, Result};
+use log::{debug, info};
+use serde_json::json;
+use tokio::io::{AsyncRead, AsyncReadExt, AsyncWrite, AsyncWriteExt};
+use tokio::time::timeout;
+
+use crate::http_analyzer::{HttpAnalyzer, HygieneReport};
+use crate::models::{Confidence, Finding, HttpInfo, PortResult, PortStatus};
+
+/// Web ports checked when detection did not see HTTP on them, with whether they use TLS
+const WEB_PORTS: [(u16, bool); 4] = [(80, false), (443, true), (8080, false), (8443, true)];
+
+/// Estimated size of a TLS handshake, counted against the scan budget
+const TLS_HANDSHAKE_BYTES: usize = 700;
+
+/// Largest response read
+const MAX_RESPONSE: usize = 64 * 1024;
+
+/// Score below which a site's hygiene is reported as a finding
+const WEAK_SCORE: u8 = 70;
+
+/// Hygiene of one web port
+#[derive(Debug)]
+pub struct HygieneCheck {
+    pub port: u16,
+    /// Response to `GET /` when it was fetched here, for ports detection left without one
+    pub http_info: Option<HttpInfo>,
+    /// Summary for `security_posture`
+    pub posture: String,
+    pub details: serde_json::Value,
+    pub vulns: Vec<Finding>,
+}
+
+/// Open ports serving HTTP, with whether they use TLS
+///
+/// Ports where detection parsed an HTTP response count, along with the usual web ports.
+/// TLS is assumed where the port presented a certificate or negotiated a TLS version.
+pub fn web_ports(results: &HashMap<u16, PortResult>) -> Vec<(u16, bool)> {
+    let mut ports: Vec<(u16, bool)> = results.iter()
+        .filter(|(_, r)| r.tcp_states.values().any(|&s| s == PortStatus::Open))
+        .filter_map(|(&port, r)| {
+            let known = WEB_PORTS.iter().find(|(web, _)| *web == port).map(|(_, tls)| *tls);
+            let tls = r.cert_info.is_some() || r.tls_protocol_version.is_some();
+            match (&r.http_info, known) {
+                (Some(_), _) => Some((port, tls || known == Some(true))),
+                (None, known) => known.map(|known_tls| (port, tls || known_tls)),
+            }
+        })
+        .collect();
+    ports.sort_unstable();
+    ports
+}
+
+async fn exchange<S: AsyncRead + AsyncWrite + Unpin>(stream: &mut S, request: &[u8], wait: Duration) -> Result<Vec<u8>> {
+    timeout(wait, stream.write_all(request)).await.context("Write timed out")??;
+    let mut response = Vec::new();
+    let mut buf = vec![0u8; 8192];
+    while response.len() < MAX_RESPONSE {
+        match timeout(wait, stream.read(&mut buf)).await {
+            Ok(Ok(0)) | Err(_) => break,
+            Ok(Ok(n)) => response.extend_from_slice(&buf[..n]),
+            Ok(Err(e)) if response.is_empty() => return Err(e.into()),
+            Ok(Err(_)) => break,
+        }
+    }
+    Ok(response)
+}
+
+/// Send one `GET`, or a `POST` when there is a body, to a web port and read the response
+///
+/// # Arguments
+/// * `addr` - Address and port to connect to
+/// * `tls` - Whether to wrap the connection in TLS
+/// * `host` - `Host` header and SNI name
+/// * `path` - Path to request
+/// * `body` - Content type and body to `POST`; `None` for a `GET`
+/// * `module` - Name the request is recorded under in the audit log
+/// * `wait` - Connect, handshake and reply timeout
+///
+/// # Returns
+/// * `Result<Vec<u8>>` - Raw response (status line, headers and up to 64 KiB in all)
+pub async fn request(addr: SocketAddr, tls: bool, host: &str, path: &str, body: Option<(&str, &[u8])>, module: &'static str, wait: Duration) -> Result<Vec<u8>> {
+    let method = if body.is_some() { "POST" } else { "GET" };
+    let mut request = format!("{} {} HTTP/1.1\r\nHost: {}\r\nUser-Agent: {}\r\nAccept: */*\r\n",
+                              method, path, host, crate::utils::get_random_user_agent(None)).into_bytes();
+    if let Some((content_type, content)) = body {
+        request.extend_from_slice(format!("Content-Type: {}\r\nContent-Length: {}\r\n", content_type, content.len()).as_bytes());
+    }
+    request.extend_from_slice(b"Connection: close\r\n\r\n");
+    if let Some((_, content)) = body {
+        request.extend_from_slice(content);
+    }
+    let bytes = request.len() + crate::scheduler::TCP_HEADER_BYTES + if tls { TLS_HANDSHAKE_BYTES } else { 0 };
+    crate::scheduler::spend(bytes).await;
+    crate::audit::record(|| crate::audit::Probe::new(module, "tcp", addr.ip(), Some(addr.port()), bytes)
+        .detail(format!("{} {}", method, path)));
+
+    let mut stream = timeout(wait, crate::netbind::connect_tcp(addr)).await.context("Connect timed out")??;
+    if tls {
+        let name = crate::ssl_config::convert_to_server_name(host)
+            .map_err(|e| anyhow!("Invalid server name '{}': {}", host, e))?
+            .to_owned();
+        let connector = tokio_rustls::TlsConnector::from(crate::ssl_config::create_tls_config(false));
+        let mut tls_stream = timeout(wait, connector.connect(name, stream)).await.context("TLS handshake timed out")??;
+        exchange(&mut tls_stream, &request, wait).await
+    } else {
+        exchange(&mut stream, &request, wait).await
+    }
+}
+
+/// Send one `GET` to a web port and read the response
+pub async fn get(addr: SocketAddr, tls: bool, host: &str, path: &str, module: &'static str, wait: Duration) -> Result<Vec<u8>> {
+    request(addr, tls, host, path, None, module, wait).await
+}
+
+/// Status code and body of a raw HTTP response, with chunked bodies reassembled
+pub fn split_response(response: &[u8]) -> Option<(u16, String)> {
+    let text = String::from_utf8_lossy(response);
+    let (head, body) = text.split_once("\r\n\r\n")?;
+    let status = head.split_whitespace().nth(1)?.parse().ok()?;
+    let chunked = head.lines().any(|line| {
+        line.split_once(':').is_some_and(|(name, value)| {
+            name.trim().eq_ignore_ascii_case("transfer-encoding") && value.to_ascii_lowercase().contains("chunked")
+        })
+    });
+    if !chunked {
+        return Some((status, body.to_string()));
+    }
+    let mut rest = body;
+    let mut joined = String::new();
+    while let Some((size, after)) = rest.split_once("\r\n") {
+        let size = usize::from_str_radix(size.split(';').next().unwrap_or("").trim(), 16).unwrap_or(0);
+        match after.get(..size) {
+            Some(chunk) if size > 0 => {
+                joined.push_str(chunk);
+                rest = after[size..].trim_start_matches("\r\n");
+            }
+            Some(_) => break,
+            // Truncated at MAX_RESPONSE
+            None => {
+                joined.push_str(after);
+                break;
+            }
+        }
+    }
+    Some((status, joined))
+}
+
+/// Finding for a site scoring below `WEAK_SCORE`
+fn weak_hygiene(port: u16, report: &HygieneReport) -> Finding {
+    Finding {
+        id: "WEB-HYGIENE-WEAK".to_string(),
+        title: "Weak web security hygiene".to_string(),
+        description: format!("The site on port {} scores {}/100 ({}) for security headers, cookie flags and transport protections",
+                             port, report.score, report.grade),
+        severity: if report.score < 50 { "Medium" } else { "Low" }.to_string(),
+        confidence: Confidence::Firm,
+        evidence: report.issues.clone(),
+        references: vec!["https://owasp.org/www-project-secure-headers/".to_string()],
+        remediation: Some("Send HSTS with a long max-age, a Content-Security-Policy with frame-ancestors, nosniff and a Referrer-Policy; mark cookies Secure, HttpOnly and SameSite; enable OCSP stapling; drop version details from Server and X-Powered-By.".to_string()),
+        ..Default::default()
+    }
+}
+
+/// Fetch the front page of each web port and score its hygiene
+///
+/// # Arguments
+/// * `target` - Host to check
+/// * `hostname` - Name to send in `Host` and SNI; the address is used when not given
+/// * `results` - Port results after detection
+/// * `wait` - Connect and reply timeout
+///
+/// # Returns
+/// * `Vec<HygieneCheck>` - One entry per web port that answered with HTTP
+pub async fn audit_sites(target: IpAddr, hostname: Option<&str>, results: &HashMap<u16, PortResult>, wait: Duration) -> Vec<HygieneCheck> {
+    let analyzer = HttpAnalyzer::new();
+    let host = hostname.map(str::to_string).unwrap_or_else(|| target.to_string());
+
+    let mut checks = Vec::new();
+    for (port, tls) in web_ports(results) {
+        if crate::scheduler::winding_down() {
+            info!("[Web] Stopping before port {} to stay within --max-scan-time", port);
+            break;
+        }
+        let result = &results[&port];
+        // Plain HTTP responses were already read by banner grabbing
+        let existing = result.http_info.as_ref().filter(|info| !tls && info.status_code.is_some());
+        let fetched = match existing {
+            Some(_) => None,
+            None => match get(SocketAddr::new(target, port), tls, &host, "/", "web-hygiene", wait).await {
+                Ok(response) => Some(analyzer.analyze_response(&response, None)),
+                Err(e) => {
+                    debug!("[Web] GET / on {}:{} failed: {}", target, port, e);
+                    continue;
+                }
+            },
+        };
+        let Some(http_info) = existing.or(fetched.as_ref()).filter(|info| info.status_code.is_some()) else {
+            debug!("[Web] {}:{} did not answer with HTTP", target, port);
+            continue;
+        };
+
+        let ocsp_stapled = result.cert_info.as_ref().and_then(|cert| cert.ocsp_stapled);
+        let report = crate::http_analyzer::assess_hygiene(http_info, tls, ocsp_stapled);
+        info!("[Web] {}:{} hygiene {}/100 ({})", target, port, report.score, report.grade);
+
+        let vulns = if report.score < WEAK_SCORE { vec![weak_hygiene(port, &report)] } else { Vec::new() };
+        checks.push(HygieneCheck {
+            port,
+            posture: report.summary(),
+            details: json!({ "web_hygiene": report }),
+            vulns,
+            http_info: fetched.filter(|_| result.http_info.as_ref().map_or(true, |info| info.status_code.is_none())),
+        });
+    }
+    checks
+}
@@ -0,0 +1,206 @@
+//! Well-known path probing (`--web-paths`).
+//!
+//! A handful of paths give away far more than the port they sit on: a deployed `.git`
+//! directory holds the source and its history, `.env` the application's secrets, Apache's
+//! `server-status` every client and URL being served, and Spring Boot's actuator the
+//! environment and heap. Each web port gets one `GET` per path in `PATHS` and nothing
+//! else; a path only counts as exposed when the body looks like the real thing, so catch-all
+//! pages answering 200 for everything are not reported. `robots.txt` is read for the paths
+//! the site asks crawlers to stay out of.
+
+use std::collections::HashMap;
+use std::net::{IpAddr, SocketAddr};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::time::Duration;
+
+use log::{debug, info};
+use serde_json::json;
+
+use crate::models::{Confidence, Finding, PortResult};
+
+/// Paths requested on every web port, in order
+const PATHS: [&str; 5] = ["/robots.txt", "/.git/HEAD", "/.env", "/server-status", "/actuator"];
+
+/// Most web ports probed per host, so at most `PATHS.len()` times this many requests
+const MAX_PORTS: usize = 8;
+
+/// Failed requests after which a port is given up on
+const MAX_FAILURES: usize = 2;
+
+/// Most `Disallow` entries kept from robots.txt
+const MAX_DISALLOWED: usize = 50;
+
+/// Actuator endpoints that hand out secrets or memory contents
+const SENSITIVE_ACTUATORS: [&str; 5] = ["env", "heapdump", "threaddump", "configprops", "jolokia"];
+
+/// Whether `--web-paths` was given
+static ENABLED: AtomicBool = AtomicBool::new(false);
+
+/// Turn well-known path probing on or off
+pub fn set_enabled(enabled: bool) {
+    ENABLED.store(enabled, Ordering::Relaxed);
+}
+
+/// Whether well-known paths are probed
+pub fn enabled() -> bool {
+    ENABLED.load(Ordering::Relaxed)
+}
+
+/// Exposed paths and robots.txt entries of one web port
+#[derive(Debug)]
+pub struct WellKnownCheck {
+    pub port: u16,
+    pub details: serde_json::Value,
+    pub vulns: Vec<Finding>,
+}
+
+fn finding(id: &str, title: &str, severity: &str, description: String, evidence: Vec<String>, remediation: &str) -> Finding {
+    Finding {
+        id: id.to_string(),
+        title: title.to_string(),
+        description,
+        severity: severity.to_string(),
+        confidence: Confidence::Confirmed,
+        evidence,
+        remediation: Some(remediation.to_string()),
+        ..Default::default()
+    }
+}
+
+/// Judge the body a path returned
+///
+/// # Returns
+/// * `Option<Finding>` - The exposure, `None` when the body is not what the path should hold
+fn judge(port: u16, path: &str, body: &str) -> Option<Finding> {
+    let trimmed = body.trim_start();
+    let looks_like_html = trimmed.starts_with('<');
+    match path {
+        "/.git/HEAD" => {
+            let head = trimmed.lines().next().unwrap_or("");
+            let is_ref = head.starts_with("ref: refs/");
+            let is_hash = head.len() == 40 && head.chars().all(|c| c.is_ascii_hexdigit());
+            (is_ref || is_hash).then(|| finding(
+                "WEB-GIT-EXPOSED", "Git repository exposed", "High",
+                format!("/.git/HEAD is served on port {}; the repository, its history and any committed secrets can be downloaded", port),
+                vec![head.to_string()],
+                "Remove the .git directory from the web root or deny access to /.git/ in the server configuration."))
+        }
+        "/.env" => {
+            if looks_like_html {
+                return None;
+            }
+            // Only the variable names are kept, never the values
+            let names: Vec<String> = trimmed.lines()
+                .filter_map(|line| line.trim().strip_prefix("export ").unwrap_or(line.trim()).split_once('='))
+                .map(|(name, _)| name.trim())
+                .filter(|name| !name.is_empty() && name.chars().all(|c| c.is_ascii_uppercase() || c.is_ascii_digit() || c == '_'))
+                .map(str::to_string)
+                .collect();
+            (names.len() >= 2).then(|| finding(
+                "WEB-ENV-EXPOSED", "Environment file exposed", "Critical",
+                format!("/.env is served on port {} with {} variables; such files usually hold database passwords and API keys", port, names.len()),
+                names.into_iter().take(20).collect(),
+                "Keep .env outside the web root, deny dotfiles in the server configuration, and rotate every secret the file held."))
+        }
+        "/server-status" => {
+            (body.contains("Apache Server Status") || body.contains("Server uptime:")).then(|| finding(
+                "WEB-SERVER-STATUS", "Apache server-status exposed", "Medium",
+                format!("/server-status on port {} lists the server version, client addresses and the URLs being requested", port),
+                vec!["Apache Server Status".to_string()],
+                "Restrict mod_status to management addresses with Require ip, or disable it."))
+        }
+        "/actuator" => {
+            let links = serde_json::from_str::<serde_json::Value>(trimmed).ok()?;
+            let endpoints: Vec<String> = links.get("_links")?.as_object()?.keys()
+                .filter(|name| name.as_str() != "self")
+                .cloned()
+                .collect();
+            let sensitive = endpoints.iter().any(|name| SENSITIVE_ACTUATORS.iter().any(|s| name.starts_with(s)));
+            Some(finding(
+                "WEB-ACTUATOR-EXPOSED", "Spring Boot actuator exposed",
+                if sensitive { "High" } else { "Medium" },
+                format!("/actuator on port {} lists {} management endpoints{}", port, endpoints.len(),
+                        if sensitive { ", including ones that return configuration, secrets or memory" } else { "" }),
+                endpoints,
+                "Expose only health and info over HTTP (management.endpoints.web.exposure.include) and put the rest behind authentication or a management port."))
+        }
+        _ => None,
+    }
+}
+
+/// `Disallow` paths of a robots.txt
+fn disallowed(body: &str) -> Vec<String> {
+    let mut paths: Vec<String> = body.lines()
+        .filter_map(|line| line.split_once(':'))
+        .filter(|(field, _)| field.trim().eq_ignore_ascii_case("disallow"))
+        .map(|(_, path)| path.trim().to_string())
+        .filter(|path| !path.is_empty() && path != "/")
+        .collect();
+    paths.dedup();
+    paths.truncate(MAX_DISALLOWED);
+    paths
+}
+
+/// Request the well-known paths on each web port of a host
+///
+/// # Arguments
+/// * `target` - Host to probe
+/// * `hostname` - Name to send in `Host` and SNI; the address is used when not given
+/// * `results` - Port results after detection
+/// * `wait` - Connect and reply timeout
+///
+/// # Returns
+/// * `Vec<WellKnownCheck>` - Entries for ports with an exposed path or a robots.txt
+pub async fn probe_paths(target: IpAddr, hostname: Option<&str>, results: &HashMap<u16, PortResult>, wait: Duration) -> Vec<WellKnownCheck> {
+    let host = hostname.map(str::to_string).unwrap_or_else(|| target.to_string());
+    let ports = crate::web_hygiene::web_ports(results);
+    if ports.len() > MAX_PORTS {
+        info!("[Paths] {} has {} web ports; probing the first {}", target, ports.len(), MAX_PORTS);
+    }
+
+    let mut checks = Vec::new();
+    for (port, tls) in ports.into_iter().take(MAX_PORTS) {
+        let mut exposed = Vec::new();
+        let mut vulns = Vec::new();
+        let mut robots = Vec::new();
+        let mut failures = 0;
+        for path in PATHS {
+            if crate::scheduler::winding_down() {
+                info!("[Paths] Stopping before {}:{}{} to stay within --max-scan-time", target, port, path);
+                break;
+            }
+            let response = match crate::web_hygiene::get(SocketAddr::new(target, port), tls, &host, path, "well-known", wait).await {
+                Ok(response) => response,
+                Err(e) => {
+                    debug!("[Paths] GET {} on {}:{} failed: {}", path, target, port, e);
+                    failures += 1;
+                    if failures >= MAX_FAILURES {
+                        break;
+                    }
+                    continue;
+                }
+            };
+            let Some((200, body)) = crate::web_hygiene::split_response(&response) else { continue };
+            if path == "/robots.txt" {
+                robots = disallowed(&body);
+            } else if let Some(vuln) = judge(port, path, &body) {
+                info!("[Paths] {}:{} exposes {}", target, port, path);
+                exposed.push(path);
+                vulns.push(vuln);
+            }
+        }
+
+        if exposed.is_empty() && robots.is_empty() {
+            continue;
+        }
+        let mut details = serde_json::Map::new();
+        if !exposed.is_empty() {
+            details.insert("exposed_paths".to_string(), json!(exposed));
+        }
+        if !robots.is_empty() {
+            details.insert("robots_disallow".to_string(), json!(robots));
+        }
+        checks.push(WellKnownCheck { port, details: serde_json::Value::Object(details), vulns });
+    }
+    checks
+}